@@ -0,0 +1,114 @@
+#[macro_use]
+extern crate criterion;
+
+use group::ff::Field;
+use halo2_proofs::arithmetic::{CpuEngine, Engine};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    commitment::ParamsProver,
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::ProverIPA,
+    },
+    EvaluationDomain,
+};
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 12;
+const COLUMNS: usize = 200;
+
+/// A circuit with many unconstrained advice columns, so that converting its
+/// witness into coefficient form is a large part of proving.
+#[derive(Clone, Copy)]
+struct WideAdvice;
+
+impl Circuit<Fp> for WideAdvice {
+    type Config = Vec<Column<Advice>>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (0..COLUMNS).map(|_| meta.advice_column()).collect()
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    for (i, column) in config.iter().enumerate() {
+                        let value = Fp::from((row * COLUMNS + i) as u64);
+                        region.assign_advice(|| "value", *column, row, || Value::known(value))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    // The conversion alone, one column after another or all together
+    let domain = EvaluationDomain::<Fp>::new(1, K);
+    let polys: Vec<_> = (0..COLUMNS)
+        .map(|_| {
+            let mut poly = domain.empty_lagrange();
+            for value in poly.iter_mut() {
+                *value = Fp::random(OsRng);
+            }
+            poly
+        })
+        .collect();
+    let mut group = c.benchmark_group("advice-ifft");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("sequential", COLUMNS), |b| {
+        b.iter(|| {
+            polys
+                .iter()
+                .map(|poly| domain.lagrange_to_coeff(poly.clone()))
+                .collect::<Vec<_>>()
+        });
+    });
+    group.bench_function(BenchmarkId::new("batched", COLUMNS), |b| {
+        let engine: &dyn Engine<EqAffine> = &CpuEngine;
+        b.iter(|| domain.batch_lagrange_to_coeff_with_engine(engine, polys.clone()));
+    });
+
+    // A whole proof
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &WideAdvice).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &WideAdvice).expect("keygen_pk should not fail");
+    group.bench_function(BenchmarkId::new("create_proof", COLUMNS), |b| {
+        b.iter(|| {
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+                &params,
+                &pk,
+                &[WideAdvice],
+                &[&[]],
+                OsRng,
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            transcript.finalize()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
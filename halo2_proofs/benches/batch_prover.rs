@@ -0,0 +1,131 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    commitment::ParamsProver,
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::ProverIPA,
+    },
+    Rotation,
+};
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 8;
+const ADVICE: usize = 8;
+const GATES: usize = 2048;
+const PROOFS: usize = 8;
+
+#[derive(Clone)]
+struct ManyGatesConfig {
+    q: Selector,
+    advice: Vec<Column<Advice>>,
+}
+
+/// A small circuit with many gates, so that configuring it is a sizeable part
+/// of each proof.
+#[derive(Clone, Copy)]
+struct ManyGates;
+
+impl Circuit<Fp> for ManyGates {
+    type Config = ManyGatesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let q = meta.selector();
+        let advice: Vec<_> = (0..ADVICE).map(|_| meta.advice_column()).collect();
+        for i in 0..GATES {
+            meta.create_gate("synthetic", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(advice[i % ADVICE], Rotation::cur());
+                let b = meta.query_advice(advice[(i * 7 + 1) % ADVICE], Rotation::cur());
+                vec![q * (a - b)]
+            });
+        }
+        ManyGatesConfig { q, advice }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..16 {
+                    config.q.enable(&mut region, row)?;
+                    for column in &config.advice {
+                        region.assign_advice(|| "a", *column, row, || Value::known(Fp::from(1)))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &ManyGates).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ManyGates).expect("keygen_pk should not fail");
+
+    let mut group = c.benchmark_group("batch-prover");
+    group.sample_size(10);
+
+    group.bench_function(BenchmarkId::new("create_proof", GATES), |b| {
+        b.iter(|| {
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+                &params,
+                &pk,
+                &[ManyGates],
+                &[&[]],
+                OsRng,
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            transcript.finalize()
+        });
+    });
+
+    // The first proof configures the circuit; the measured ones reuse it.
+    let mut prover = BatchProver::<IPACommitmentScheme<_>, _>::new(&params, &pk);
+    group.bench_function(BenchmarkId::new("BatchProver::prove", GATES), |b| {
+        b.iter(|| {
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            prover
+                .prove::<ProverIPA<_>, _, _, _>(&mut transcript, &[&[]], &[ManyGates], OsRng)
+                .expect("proof generation should not fail");
+            transcript.finalize()
+        });
+    });
+
+    let circuits = [ManyGates; PROOFS];
+    let instances: [&[&[Fp]]; PROOFS] = [&[]; PROOFS];
+    group.bench_function(BenchmarkId::new("BatchProver::prove_many", PROOFS), |b| {
+        b.iter(|| {
+            prover
+                .prove_many::<ProverIPA<_>, _, _, _>(&circuits, &instances, |_| {
+                    (OsRng, Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]))
+                })
+                .expect("proof generation should not fail")
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
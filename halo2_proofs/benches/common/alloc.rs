@@ -0,0 +1,66 @@
+//! A global allocator that tracks the heap bytes in use and counts large
+//! allocations, shared by the benchmarks and tests that measure memory.
+//!
+//! Include it with `#[path]` and install it with
+//! `#[global_allocator] static ALLOC: PeakAlloc = PeakAlloc;`. Each binary
+//! only uses part of it.
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tracks the current and peak number of heap bytes in use, and counts the
+/// allocations of at least a threshold number of bytes.
+pub struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static THRESHOLD: AtomicUsize = AtomicUsize::new(usize::MAX);
+static LARGE_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+            if layout.size() >= THRESHOLD.load(Ordering::Relaxed) {
+                LARGE_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+/// The heap bytes in use while running a closure; see [`measure_peak`].
+pub struct HeapUsage {
+    /// The bytes held before the closure started.
+    pub baseline: usize,
+    /// The most bytes held at once while it ran, on top of the baseline.
+    pub peak: usize,
+}
+
+/// Runs `f`, returning its result and how much heap it used at most.
+pub fn measure_peak<T>(f: impl FnOnce() -> T) -> (T, HeapUsage) {
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let result = f();
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+    (result, HeapUsage { baseline, peak })
+}
+
+/// Runs `f`, returning its result and the number of allocations of at least
+/// `threshold` bytes it made.
+pub fn count_large_allocations<T>(threshold: usize, f: impl FnOnce() -> T) -> (T, usize) {
+    THRESHOLD.store(threshold, Ordering::Relaxed);
+    let before = LARGE_ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    let allocations = LARGE_ALLOCATIONS.load(Ordering::Relaxed) - before;
+    THRESHOLD.store(usize::MAX, Ordering::Relaxed);
+    (result, allocations)
+}
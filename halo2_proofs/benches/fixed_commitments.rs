@@ -0,0 +1,106 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::commitment::{Blind, Params};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::{EvaluationDomain, Rotation};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 14;
+const FIXED_COLUMNS: usize = 100;
+
+#[derive(Clone)]
+struct ManyFixedConfig {
+    fixed: Vec<Column<Fixed>>,
+    a: Column<Advice>,
+}
+
+/// A circuit whose single advice column is constrained by a gate per fixed
+/// column, with every row of every fixed column assigned, so that keygen is
+/// dominated by the commitments to the fixed columns.
+#[derive(Clone, Copy)]
+struct ManyFixedCircuit;
+
+impl Circuit<Fr> for ManyFixedCircuit {
+    type Config = ManyFixedConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let fixed: Vec<_> = (0..FIXED_COLUMNS).map(|_| meta.fixed_column()).collect();
+        let a = meta.advice_column();
+        for column in fixed.iter() {
+            meta.create_gate("scaled", |meta| {
+                let f = meta.query_fixed(*column, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![f.clone() * (a - f)]
+            });
+        }
+        ManyFixedConfig { fixed, a }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    for (i, column) in config.fixed.iter().enumerate() {
+                        let value = Value::known(Fr::from((row * FIXED_COLUMNS + i) as u64));
+                        region.assign_fixed(|| "f", *column, row, || value)?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+
+    let mut group = c.benchmark_group("fixed-commitments");
+    group.sample_size(10);
+    // keygen_vk commits to the fixed columns in parallel.
+    group.bench_function(BenchmarkId::new("keygen_vk", FIXED_COLUMNS), |b| {
+        b.iter(|| keygen_vk(&params, &ManyFixedCircuit).expect("keygen_vk should not fail"));
+    });
+    // The same commitments one after another, as keygen_vk used to compute
+    // them; the difference with the above is the speedup, less the time
+    // keygen_vk spends synthesizing the circuit.
+    let domain = EvaluationDomain::<Fr>::new(1, K);
+    let columns: Vec<_> = (0..FIXED_COLUMNS)
+        .map(|i| {
+            let mut column = domain.empty_lagrange();
+            for (row, value) in column.iter_mut().enumerate() {
+                *value = Fr::from((row * FIXED_COLUMNS + i) as u64);
+            }
+            column
+        })
+        .collect();
+    group.bench_function(BenchmarkId::new("sequential", FIXED_COLUMNS), |b| {
+        b.iter(|| {
+            columns
+                .iter()
+                .map(|column| params.commit_lagrange(column, Blind::default()))
+                .collect::<Vec<_>>()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,161 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    commitment::ParamsProver,
+    kzg::{
+        commitment::{KZGCommitmentScheme, ParamsKZG},
+        multiopen::{ProverGWC, VerifierGWC},
+        strategy::{AccumulatorStrategy, SingleStrategy},
+    },
+    Rotation, VerificationStrategy,
+};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 6;
+const FIXED: usize = 16;
+const PROOFS: usize = 100;
+
+#[derive(Clone)]
+struct ManyFixedConfig {
+    q: Selector,
+    fixed: Vec<Column<Fixed>>,
+    a: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Adds the values of many fixed columns to a witness, so that the verifying
+/// key has many commitments for the proofs of a batch to share.
+#[derive(Clone, Copy)]
+struct ManyFixed(Value<Fr>);
+
+impl Circuit<Fr> for ManyFixed {
+    type Config = ManyFixedConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self(Value::unknown())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let fixed: Vec<_> = (0..FIXED).map(|_| meta.fixed_column()).collect();
+        let a = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(a);
+        meta.enable_equality(instance);
+        meta.create_gate("sum", |meta| {
+            let sum = fixed
+                .iter()
+                .map(|column| meta.query_fixed(*column, Rotation::cur()))
+                .fold(meta.query_advice(a, Rotation::cur()), |sum, f| sum + f);
+            vec![meta.query_selector(q) * (sum - meta.query_advice(a, Rotation::next()))]
+        });
+        ManyFixedConfig {
+            q,
+            fixed,
+            a,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let sum = layouter.assign_region(
+            || "sum",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                for column in &config.fixed {
+                    region.assign_fixed(|| "f", *column, 0, || Value::known(Fr::from(1)))?;
+                }
+                region.assign_advice(|| "a", config.a, 0, || self.0)?;
+                region.assign_advice(
+                    || "sum",
+                    config.a,
+                    1,
+                    || self.0.map(|a| a + Fr::from(FIXED as u64)),
+                )
+            },
+        )?;
+        layouter.constrain_instance(sum.cell(), config.instance, 0)
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &ManyFixed(Value::unknown())).expect("keygen_vk should not fail");
+    let pk =
+        keygen_pk(&params, vk, &ManyFixed(Value::unknown())).expect("keygen_pk should not fail");
+
+    let proofs: Vec<_> = (0..PROOFS as u64)
+        .map(|a| {
+            let instance = [Fr::from(a + FIXED as u64)];
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof::<KZGCommitmentScheme<_>, ProverGWC<_>, _, _, _, _>(
+                &params,
+                &pk,
+                &[ManyFixed(Value::known(Fr::from(a)))],
+                &[&[&instance[..]]],
+                OsRng,
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            (instance, transcript.finalize())
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("gwc-batch");
+    group.sample_size(10);
+
+    group.bench_function(BenchmarkId::new("SingleStrategy", PROOFS), |b| {
+        b.iter(|| {
+            for (instance, proof) in &proofs {
+                let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+                verify_proof::<_, VerifierGWC<_>, _, _, _>(
+                    params.verifier_params(),
+                    pk.get_vk(),
+                    SingleStrategy::new(&params),
+                    &[&[&instance[..]]],
+                    &mut transcript,
+                )
+                .expect("proof should verify");
+            }
+        });
+    });
+
+    group.bench_function(BenchmarkId::new("AccumulatorStrategy", PROOFS), |b| {
+        b.iter(|| {
+            let mut strategy = AccumulatorStrategy::new(&params);
+            for (instance, proof) in &proofs {
+                let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+                strategy = verify_proof::<_, VerifierGWC<_>, _, _, _>(
+                    params.verifier_params(),
+                    pk.get_vk(),
+                    strategy,
+                    &[&[&instance[..]]],
+                    &mut transcript,
+                )
+                .expect("proof should be read");
+            }
+            let valid = VerificationStrategy::<_, VerifierGWC<_>>::finalize(strategy);
+            assert!(valid);
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::ProverIPA,
+    },
+    Rotation,
+};
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 12;
+const FIXED_COLUMNS: usize = 32;
+
+#[derive(Clone)]
+struct ManyFixedConfig {
+    fixed: Vec<Column<Fixed>>,
+    a: Column<Advice>,
+}
+
+/// A circuit whose single advice column is constrained by a gate per fixed
+/// column, with every fixed column in the permutation.
+#[derive(Clone, Copy)]
+struct ManyFixedCircuit;
+
+impl Circuit<Fp> for ManyFixedCircuit {
+    type Config = ManyFixedConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let fixed: Vec<_> = (0..FIXED_COLUMNS).map(|_| meta.fixed_column()).collect();
+        let a = meta.advice_column();
+        meta.enable_equality(a);
+        for column in fixed.iter() {
+            meta.enable_equality(*column);
+            meta.create_gate("scaled", |meta| {
+                let f = meta.query_fixed(*column, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![f.clone() * (a - f)]
+            });
+        }
+        ManyFixedConfig { fixed, a }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        // Each fixed column is either zero or equal to `a` on a row.
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    let value = Value::known(Fp::from(row as u64));
+                    region.assign_advice(|| "a", config.a, row, || value)?;
+                    let column = config.fixed[row % FIXED_COLUMNS];
+                    region.assign_fixed(|| "f", column, row, || value)?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove(params: &ParamsIPA<EqAffine>, pk: &ProvingKey<EqAffine>) {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        params,
+        pk,
+        &[ManyFixedCircuit],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &ManyFixedCircuit).expect("keygen_vk should not fail");
+    let mut pk = keygen_pk(&params, vk, &ManyFixedCircuit).expect("keygen_pk should not fail");
+
+    let mut group = c.benchmark_group("key-cosets");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("cached", FIXED_COLUMNS), |b| {
+        b.iter(|| prove(&params, &pk));
+    });
+    pk.clear_cosets();
+    group.bench_function(BenchmarkId::new("cleared", FIXED_COLUMNS), |b| {
+        b.iter(|| prove(&params, &pk));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{commitment::ParamsProver, ipa::commitment::ParamsIPA, Rotation};
+use halo2curves::pasta::{EqAffine, Fp};
+
+use criterion::{BenchmarkId, Criterion};
+
+#[path = "common/alloc.rs"]
+mod alloc;
+use alloc::{measure_peak, PeakAlloc};
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+const K: u32 = 8;
+const ADVICE: usize = 32;
+const GATES: usize = 4096;
+
+/// A circuit that does nothing but configure many gates, so that keygen is
+/// dominated by the size of the constraint system.
+#[derive(Clone, Copy)]
+struct LargeCs;
+
+impl Circuit<Fp> for LargeCs {
+    type Config = ();
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let s = meta.fixed_column();
+        let advice: Vec<_> = (0..ADVICE).map(|_| meta.advice_column()).collect();
+        for i in 0..GATES {
+            meta.create_gate("synthetic", |meta| {
+                let s = meta.query_fixed(s, Rotation::cur());
+                let a = meta.query_advice(advice[i % ADVICE], Rotation::cur());
+                let b = meta.query_advice(advice[(i * 7 + 1) % ADVICE], Rotation::cur());
+                let c = meta.query_advice(advice[(i * 13 + 2) % ADVICE], Rotation::next());
+                vec![s * (a * b - c * Expression::Constant(Fp::from(i as u64)))]
+            });
+        }
+    }
+
+    fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &LargeCs).expect("keygen_vk should not fail");
+
+    // Report how much memory keygen_pk needs on top of the verifying key.
+    let (pk, usage) = measure_peak(|| {
+        keygen_pk(&params, vk.clone(), &LargeCs).expect("keygen_pk should not fail")
+    });
+    drop(pk);
+    println!(
+        "keygen_pk with {} gates: peak heap {} KiB above the {} KiB held before",
+        GATES,
+        usage.peak / 1024,
+        usage.baseline / 1024
+    );
+
+    let mut group = c.benchmark_group("keygen-large-cs");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::from_parameter(GATES), |b| {
+        b.iter(|| keygen_pk(&params, vk.clone(), &LargeCs).expect("keygen_pk should not fail"));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
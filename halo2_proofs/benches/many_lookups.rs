@@ -0,0 +1,145 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    commitment::ParamsProver,
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::{ProverIPA, VerifierIPA},
+        strategy::SingleStrategy,
+    },
+    Rotation,
+};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 10;
+const LOOKUPS: usize = 32;
+const TABLE_SIZE: usize = 256;
+
+#[derive(Clone)]
+struct ManyLookupsConfig {
+    advice: Vec<Column<Advice>>,
+    table: TableColumn,
+}
+
+/// A circuit with one lookup per advice column, all into the same table,
+/// proven with LogUp if `LOGUP` is set.
+#[derive(Clone, Copy)]
+struct ManyLookups<const LOGUP: bool>;
+
+impl<const LOGUP: bool> Circuit<Fp> for ManyLookups<LOGUP> {
+    type Config = ManyLookupsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        if LOGUP {
+            meta.set_lookup_backend(LookupBackend::LogUp);
+        }
+        let advice: Vec<_> = (0..LOOKUPS).map(|_| meta.advice_column()).collect();
+        let table = meta.lookup_table_column();
+        for column in &advice {
+            meta.lookup("range", |meta| {
+                vec![(meta.query_advice(*column, Rotation::cur()), table)]
+            });
+        }
+        ManyLookupsConfig { advice, table }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "table",
+            |mut table| {
+                for i in 0..TABLE_SIZE {
+                    table.assign_cell(
+                        || "t",
+                        config.table,
+                        i,
+                        || Value::known(Fp::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    for (i, column) in config.advice.iter().enumerate() {
+                        let value = Fp::from(((row * 7 + i) % TABLE_SIZE) as u64);
+                        region.assign_advice(|| "a", *column, row, || Value::known(value))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove<const LOGUP: bool>(params: &ParamsIPA<EqAffine>, pk: &ProvingKey<EqAffine>) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        params,
+        pk,
+        &[ManyLookups::<LOGUP>],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+fn bench_backend<const LOGUP: bool>(
+    c: &mut Criterion,
+    params: &ParamsIPA<EqAffine>,
+    backend: &str,
+) {
+    let vk = keygen_vk(params, &ManyLookups::<LOGUP>).expect("keygen_vk should not fail");
+    let pk = keygen_pk(params, vk, &ManyLookups::<LOGUP>).expect("keygen_pk should not fail");
+
+    let proof = prove::<LOGUP>(params, &pk);
+    println!("{} proof: {} bytes", backend, proof.len());
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        params,
+        pk.get_vk(),
+        SingleStrategy::new(params),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    let mut group = c.benchmark_group("prover-many-lookups");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new(backend, LOOKUPS), |b| {
+        b.iter(|| prove::<LOGUP>(params, &pk));
+    });
+    group.finish();
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    bench_backend::<false>(c, &params, "permutation");
+    bench_backend::<true>(c, &params, "logup");
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
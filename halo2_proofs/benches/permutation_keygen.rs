@@ -0,0 +1,101 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{commitment::ParamsProver, ipa::commitment::ParamsIPA};
+use halo2curves::pasta::{EqAffine, Fp};
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 14;
+const COLUMNS: usize = 32;
+
+/// A circuit made of nothing but copy constraints between many
+/// equality-enabled advice columns, so that keygen is dominated by the
+/// permutation argument.
+#[derive(Clone, Copy)]
+struct ManyCopies;
+
+impl Circuit<Fp> for ManyCopies {
+    type Config = Vec<Column<Advice>>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (0..COLUMNS)
+            .map(|_| {
+                let column = meta.advice_column();
+                meta.enable_equality(column);
+                column
+            })
+            .collect()
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "copies",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    let value = Value::known(Fp::from(row as u64));
+                    let mut previous = region.assign_advice(|| "a", config[0], row, || value)?;
+                    for column in config.iter().skip(1) {
+                        let cell = region.assign_advice(|| "a", *column, row, || value)?;
+                        region.constrain_equal(previous.cell(), cell.cell())?;
+                        previous = cell;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &ManyCopies).expect("keygen_vk should not fail");
+
+    let mut group = c.benchmark_group("permutation-keygen");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("keygen_vk", COLUMNS), |b| {
+        b.iter(|| keygen_vk(&params, &ManyCopies).expect("keygen_vk should not fail"));
+    });
+    group.bench_function(BenchmarkId::new("keygen_pk", COLUMNS), |b| {
+        b.iter(|| keygen_pk(&params, vk.clone(), &ManyCopies).expect("keygen_pk should not fail"));
+    });
+    // The same on a single thread, as the permutation polynomials and their
+    // commitments used to be built; the difference with the above is the
+    // speedup, less that of the parts of keygen that were already parallel.
+    #[cfg(feature = "multicore")]
+    {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        group.bench_function(BenchmarkId::new("keygen_vk_single_thread", COLUMNS), |b| {
+            b.iter(|| {
+                pool.install(|| keygen_vk(&params, &ManyCopies).expect("keygen_vk should not fail"))
+            });
+        });
+        group.bench_function(BenchmarkId::new("keygen_pk_single_thread", COLUMNS), |b| {
+            b.iter(|| {
+                pool.install(|| {
+                    keygen_pk(&params, vk.clone(), &ManyCopies).expect("keygen_pk should not fail")
+                })
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
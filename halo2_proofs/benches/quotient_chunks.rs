@@ -0,0 +1,174 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::ProverIPA,
+    },
+    Rotation,
+};
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+const K: u32 = 12;
+const COLUMNS: usize = 8;
+const TABLE_SIZE: usize = 256;
+
+#[derive(Clone)]
+struct ReferenceConfig {
+    q: Column<Fixed>,
+    advice: Vec<Column<Advice>>,
+    table: TableColumn,
+}
+
+/// A circuit with a degree-3 gate over each pair of adjacent advice columns,
+/// every column in the permutation and looked up in a range table. If
+/// `SKEWED`, a gate summing the products of all pairs of cells around the
+/// current row costs more to evaluate than all the other gates together.
+#[derive(Clone, Copy)]
+struct ReferenceCircuit<const SKEWED: bool>;
+
+impl<const SKEWED: bool> Circuit<Fp> for ReferenceCircuit<SKEWED> {
+    type Config = ReferenceConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let q = meta.fixed_column();
+        let advice: Vec<_> = (0..COLUMNS).map(|_| meta.advice_column()).collect();
+        let table = meta.lookup_table_column();
+        for pair in advice.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            meta.enable_equality(a);
+            meta.create_gate("product", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let next = meta.query_advice(a, Rotation::next());
+                let a_prev = meta.query_advice(pair[0], Rotation::prev());
+                vec![q * (a * b - next - a_prev)]
+            });
+            meta.lookup("range", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+        }
+        if SKEWED {
+            meta.create_gate("skewed", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let mut cells = vec![];
+                for column in advice.iter() {
+                    for rotation in [Rotation::prev(), Rotation::cur(), Rotation::next()] {
+                        cells.push(meta.query_advice(*column, rotation));
+                    }
+                }
+                let products = cells
+                    .iter()
+                    .flat_map(|a| cells.iter().map(move |b| a.clone() * b.clone()));
+                vec![q * products.reduce(|sum, product| sum + product).unwrap()]
+            });
+        }
+        ReferenceConfig { q, advice, table }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "table",
+            |mut table| {
+                for i in 0..TABLE_SIZE {
+                    table.assign_cell(
+                        || "t",
+                        config.table,
+                        i,
+                        || Value::known(Fp::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        // `q` is left unassigned, so the gates hold whatever the values; they
+        // cost as much to evaluate in the quotient either way.
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    for (i, column) in config.advice.iter().enumerate() {
+                        let value = Fp::from(((row + i) % TABLE_SIZE) as u64);
+                        region.assign_advice(|| "a", *column, row, || Value::known(value))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove<const SKEWED: bool>(
+    params: &ParamsIPA<EqAffine>,
+    pk: &ProvingKey<EqAffine>,
+    chunk_size: ChunkSize,
+) {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_options::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        params,
+        pk,
+        &[ReferenceCircuit::<SKEWED>],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+        &ProverOptions::new()
+            .gate_chunk_size(chunk_size)
+            .argument_chunk_size(chunk_size),
+    )
+    .expect("proof generation should not fail");
+}
+
+fn keygen<const SKEWED: bool>(params: &ParamsIPA<EqAffine>) -> ProvingKey<EqAffine> {
+    let circuit = ReferenceCircuit::<SKEWED>;
+    let vk = keygen_vk(params, &circuit).expect("keygen_vk should not fail");
+    keygen_pk(params, vk, &circuit).expect("keygen_pk should not fail")
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let pk = keygen::<false>(&params);
+    let skewed_pk = keygen::<true>(&params);
+
+    let mut group = c.benchmark_group("quotient-chunks");
+    group.sample_size(10);
+    let mut sweep = vec![
+        ("per-thread".to_string(), ChunkSize::PerThread),
+        ("auto".to_string(), ChunkSize::Auto),
+    ];
+    for rows in [64, 256, 1024, 4096, 16384] {
+        sweep.push((format!("rows-{}", rows), ChunkSize::Rows(rows)));
+    }
+    for (name, chunk_size) in sweep {
+        group.bench_function(BenchmarkId::new(name.clone(), K), |b| {
+            b.iter(|| prove::<false>(&params, &pk, chunk_size));
+        });
+        // The rows of the costly gate are split into more tasks than those of
+        // the others.
+        group.bench_function(BenchmarkId::new(format!("skewed-{}", name), K), |b| {
+            b.iter(|| prove::<true>(&params, &skewed_pk, chunk_size));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,160 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    commitment::ParamsProver,
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::{ProverIPA, VerifierIPA},
+        strategy::SingleStrategy,
+    },
+    Rotation,
+};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+#[path = "common/alloc.rs"]
+mod alloc;
+use alloc::{measure_peak, PeakAlloc};
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+const K: u32 = 10;
+const COLUMNS: usize = 16;
+
+#[derive(Clone)]
+struct TwoPhaseConfig {
+    q: Selector,
+    first: Vec<Column<Advice>>,
+    second: Vec<Column<Advice>>,
+    challenge: Challenge,
+}
+
+/// A circuit with as many advice columns in its second phase as in its first,
+/// each second-phase column holding its first-phase counterpart times a
+/// challenge.
+#[derive(Clone, Copy)]
+struct TwoPhase;
+
+impl Circuit<Fp> for TwoPhase {
+    type Config = TwoPhaseConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let q = meta.selector();
+        let first: Vec<_> = (0..COLUMNS)
+            .map(|_| meta.advice_column_in(FirstPhase))
+            .collect();
+        let second: Vec<_> = (0..COLUMNS)
+            .map(|_| meta.advice_column_in(SecondPhase))
+            .collect();
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        meta.create_gate("second = first * challenge", |meta| {
+            let q = meta.query_selector(q);
+            let challenge = meta.query_challenge(challenge);
+            first
+                .iter()
+                .zip(second.iter())
+                .map(|(first, second)| {
+                    let first = meta.query_advice(*first, Rotation::cur());
+                    let second = meta.query_advice(*second, Rotation::cur());
+                    q.clone() * (second - first * challenge.clone())
+                })
+                .collect::<Vec<_>>()
+        });
+        TwoPhaseConfig {
+            q,
+            first,
+            second,
+            challenge,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let challenge = layouter.get_challenge(config.challenge);
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    config.q.enable(&mut region, row)?;
+                    for (i, (first, second)) in
+                        config.first.iter().zip(config.second.iter()).enumerate()
+                    {
+                        let value = Value::known(Fp::from((row * COLUMNS + i) as u64));
+                        region.assign_advice(|| "first", *first, row, || value)?;
+                        region.assign_advice(|| "second", *second, row, || value * challenge)?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove(params: &ParamsIPA<EqAffine>, pk: &ProvingKey<EqAffine>) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        params,
+        pk,
+        &[TwoPhase],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &TwoPhase).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &TwoPhase).expect("keygen_pk should not fail");
+
+    // Report how much memory proving needs on top of the proving key, and
+    // check that the proof is still valid.
+    let (proof, usage) = measure_peak(|| prove(&params, &pk));
+    println!(
+        "create_proof with {} columns per phase: peak heap {} KiB above the {} KiB held before",
+        COLUMNS,
+        usage.peak / 1024,
+        usage.baseline / 1024
+    );
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        &params,
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    let mut group = c.benchmark_group("prover-two-phase");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::from_parameter(COLUMNS), |b| {
+        b.iter(|| prove(&params, &pk));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+#[macro_use]
+extern crate criterion;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::*;
+use halo2_proofs::poly::{
+    commitment::ParamsProver,
+    ipa::{
+        commitment::{IPACommitmentScheme, ParamsIPA},
+        multiopen::ProverIPA,
+    },
+};
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+use criterion::{BenchmarkId, Criterion};
+
+#[path = "common/alloc.rs"]
+mod alloc;
+use alloc::{measure_peak, PeakAlloc};
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+const K: u32 = 14;
+const COLUMNS: usize = 64;
+
+/// A circuit whose many advice columns are filled with rational values, so
+/// that converting its witness out of the `Assigned` form dominates memory.
+#[derive(Clone, Copy)]
+struct WideWitness;
+
+impl Circuit<Fp> for WideWitness {
+    type Config = Vec<Column<Advice>>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (0..COLUMNS).map(|_| meta.advice_column()).collect()
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..(1 << K) - 10 {
+                    for (i, column) in config.iter().enumerate() {
+                        let value = Assigned::from((Fp::from(row as u64), Fp::from(i as u64 + 1)));
+                        region.assign_advice(|| "value", *column, row, || Value::known(value))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove(params: &ParamsIPA<EqAffine>, pk: &ProvingKey<EqAffine>) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        params,
+        pk,
+        &[WideWitness],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &WideWitness).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &WideWitness).expect("keygen_pk should not fail");
+
+    // Report how much memory proving needs on top of the proving key.
+    let (_, usage) = measure_peak(|| prove(&params, &pk));
+    println!(
+        "create_proof with {} rational columns at k = {}: peak heap {} KiB above the {} KiB held before",
+        COLUMNS,
+        K,
+        usage.peak / 1024,
+        usage.baseline / 1024
+    );
+
+    let mut group = c.benchmark_group("prover-wide-witness");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::from_parameter(COLUMNS), |b| {
+        b.iter(|| prove(&params, &pk));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
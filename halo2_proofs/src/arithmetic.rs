@@ -1,12 +1,14 @@
 //! This module provides common utilities, traits and structures for group,
 //! field and polynomial arithmetic.
 
-use super::multicore;
+use super::multicore::{self, IntoParallelRefMutIterator, ParallelIterator};
 pub use ff::Field;
 use group::{
     ff::{BatchInvert, PrimeField},
     Curve, Group, GroupOpsOwned, ScalarMulOwned,
 };
+#[cfg(feature = "multicore")]
+use std::sync::Arc;
 
 pub use halo2curves::{CurveAffine, CurveExt};
 
@@ -184,6 +186,34 @@ pub fn best_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Cu
 ///
 /// This will use multithreading if beneficial.
 pub fn best_fft<Scalar: Field, G: FftGroup<Scalar>>(a: &mut [G], omega: Scalar, log_n: u32) {
+    let log_threads = log2_floor(multicore::current_num_threads());
+    fft(a, omega, log_n, log_n > log_threads)
+}
+
+/// Performs several FFTs of the same size in place, as [`best_fft`] does.
+///
+/// When there are at least as many of them as threads, each FFT runs on a
+/// single thread and the FFTs run in parallel. Otherwise they run one after
+/// another, each of them using multithreading if beneficial.
+pub fn best_batch_fft<Scalar: Field, G: FftGroup<Scalar>>(
+    values: &mut [&mut [G]],
+    omega: Scalar,
+    log_n: u32,
+) {
+    if values.len() >= multicore::current_num_threads() {
+        values
+            .par_iter_mut()
+            .for_each(|a| fft(a, omega, log_n, false));
+    } else {
+        for a in values.iter_mut() {
+            best_fft(a, omega, log_n);
+        }
+    }
+}
+
+/// Performs an FFT in place, splitting the butterflies across threads if
+/// `parallel`.
+fn fft<Scalar: Field, G: FftGroup<Scalar>>(a: &mut [G], omega: Scalar, log_n: u32, parallel: bool) {
     fn bitreverse(mut n: usize, l: usize) -> usize {
         let mut r = 0;
         for _ in 0..l {
@@ -193,8 +223,6 @@ pub fn best_fft<Scalar: Field, G: FftGroup<Scalar>>(a: &mut [G], omega: Scalar,
         r
     }
 
-    let threads = multicore::current_num_threads();
-    let log_threads = log2_floor(threads);
     let n = a.len();
     assert_eq!(n, 1 << log_n);
 
@@ -214,7 +242,7 @@ pub fn best_fft<Scalar: Field, G: FftGroup<Scalar>>(a: &mut [G], omega: Scalar,
         })
         .collect();
 
-    if log_n <= log_threads {
+    if !parallel {
         let mut chunk = 2_usize;
         let mut twiddle_chunk = n / 2;
         for _ in 0..log_n {
@@ -288,6 +316,108 @@ pub fn recursive_butterfly_arithmetic<Scalar: Field, G: FftGroup<Scalar>>(
     }
 }
 
+/// A backend for the multiexponentiations and FFTs performed by the prover and
+/// during key generation, for example to dispatch them to an accelerator.
+///
+/// [`CpuEngine`] is the default, using [`best_multiexp`], [`best_fft`] and
+/// [`best_batch_fft`].
+pub trait Engine<C: CurveAffine>: Send + Sync {
+    /// Performs a multiexponentiation as [`best_multiexp`] does.
+    fn best_multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> C::Curve;
+
+    /// Performs an FFT in place as [`best_fft`] does.
+    fn fft(&self, values: &mut [C::Scalar], omega: C::Scalar, log_n: u32);
+
+    /// Performs several independent multiexponentiations.
+    fn batch_multiexp(&self, msms: &[(&[C::Scalar], &[C])]) -> Vec<C::Curve> {
+        msms.iter()
+            .map(|(coeffs, bases)| self.best_multiexp(coeffs, bases))
+            .collect()
+    }
+
+    /// Performs several independent FFTs of the same size in place.
+    fn batch_fft(&self, values: &mut [&mut [C::Scalar]], omega: C::Scalar, log_n: u32) {
+        for values in values.iter_mut() {
+            self.fft(values, omega, log_n);
+        }
+    }
+}
+
+impl<C: CurveAffine, E: Engine<C> + ?Sized> Engine<C> for &E {
+    fn best_multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+        (**self).best_multiexp(coeffs, bases)
+    }
+
+    fn fft(&self, values: &mut [C::Scalar], omega: C::Scalar, log_n: u32) {
+        (**self).fft(values, omega, log_n)
+    }
+
+    fn batch_multiexp(&self, msms: &[(&[C::Scalar], &[C])]) -> Vec<C::Curve> {
+        (**self).batch_multiexp(msms)
+    }
+
+    fn batch_fft(&self, values: &mut [&mut [C::Scalar]], omega: C::Scalar, log_n: u32) {
+        (**self).batch_fft(values, omega, log_n)
+    }
+}
+
+/// The default [`Engine`], running on the CPU.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuEngine;
+
+impl<C: CurveAffine> Engine<C> for CpuEngine {
+    fn best_multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+        best_multiexp(coeffs, bases)
+    }
+
+    fn fft(&self, values: &mut [C::Scalar], omega: C::Scalar, log_n: u32) {
+        best_fft(values, omega, log_n)
+    }
+
+    fn batch_fft(&self, values: &mut [&mut [C::Scalar]], omega: C::Scalar, log_n: u32) {
+        best_batch_fft(values, omega, log_n)
+    }
+}
+
+/// An [`Engine`] that runs the multiexponentiations and FFTs of another one
+/// in a dedicated thread pool, rather than in the pool of the calling thread,
+/// so that they don't compete with other parallel work of the caller.
+#[cfg(feature = "multicore")]
+#[derive(Clone, Debug)]
+pub struct PoolEngine<E> {
+    pool: Arc<multicore::ThreadPool>,
+    inner: E,
+}
+
+#[cfg(feature = "multicore")]
+impl<E> PoolEngine<E> {
+    /// Runs the operations of `inner` in `pool`.
+    pub fn new(pool: Arc<multicore::ThreadPool>, inner: E) -> Self {
+        PoolEngine { pool, inner }
+    }
+}
+
+#[cfg(feature = "multicore")]
+impl<C: CurveAffine, E: Engine<C>> Engine<C> for PoolEngine<E> {
+    fn best_multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+        self.pool
+            .install(|| self.inner.best_multiexp(coeffs, bases))
+    }
+
+    fn fft(&self, values: &mut [C::Scalar], omega: C::Scalar, log_n: u32) {
+        self.pool.install(|| self.inner.fft(values, omega, log_n))
+    }
+
+    fn batch_multiexp(&self, msms: &[(&[C::Scalar], &[C])]) -> Vec<C::Curve> {
+        self.pool.install(|| self.inner.batch_multiexp(msms))
+    }
+
+    fn batch_fft(&self, values: &mut [&mut [C::Scalar]], omega: C::Scalar, log_n: u32) {
+        self.pool
+            .install(|| self.inner.batch_fft(values, omega, log_n))
+    }
+}
+
 /// Convert coefficient bases group elements to lagrange basis by inverse FFT.
 pub fn g_to_lagrange<C: CurveAffine>(g_projective: Vec<C::Curve>, k: u32) -> Vec<C> {
     let n_inv = C::Scalar::TWO_INV.pow_vartime([k as u64, 0, 0, 0]);
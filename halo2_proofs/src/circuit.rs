@@ -5,7 +5,8 @@ use std::{fmt, marker::PhantomData};
 use ff::Field;
 
 use crate::plonk::{
-    Advice, Any, Assigned, Challenge, Column, Error, Fixed, Instance, Selector, TableColumn,
+    Advice, AdviceColumn, Any, Assigned, Challenge, Column, Error, Fixed, Instance, Selector,
+    TableColumn, TypedPhase,
 };
 
 mod value;
@@ -16,8 +17,10 @@ pub use floor_planner::single_pass::SimpleFloorPlanner;
 
 pub mod layouter;
 mod table_layouter;
+mod table_set;
 
 pub use table_layouter::{SimpleTableLayouter, TableLayouter};
+pub use table_set::{LogicalTable, TableSet};
 
 /// A chip implements a set of instructions that can be used by gadgets.
 ///
@@ -253,6 +256,56 @@ impl<'r, F: Field> Region<'r, F> {
         })
     }
 
+    /// Assign an advice column value (witness), as [`Self::assign_advice`]
+    /// does, to a column whose phase is part of its type.
+    ///
+    /// Returns [`Error::ChallengeFromLaterPhase`] if the value was computed
+    /// from a challenge squeezed in the phase of the column or after it,
+    /// which the prover cannot know yet when it commits to the column; see
+    /// [`Value::challenge_phase`].
+    pub fn assign_advice_in_phase<V, VR, A, AR, P>(
+        &mut self,
+        annotation: A,
+        column: AdviceColumn<P>,
+        offset: usize,
+        mut to: V,
+    ) -> Result<AssignedCell<VR, F>, Error>
+    where
+        V: FnMut() -> Value<VR>,
+        for<'vr> Assigned<F>: From<&'vr VR>,
+        A: Fn() -> AR,
+        AR: Into<String>,
+        P: TypedPhase,
+    {
+        let mut value = Value::unknown();
+        let cell =
+            self.region
+                .assign_advice(&|| annotation().into(), column.into(), offset, &mut || {
+                    let v = to();
+                    let value_f = v.to_field();
+                    value = v;
+                    value_f
+                });
+        // The value is unknown in the phase of the column, which the backend
+        // may have already failed on.
+        if let Some(challenge_phase) = value.challenge_phase() {
+            if challenge_phase >= P::PHASE {
+                return Err(Error::ChallengeFromLaterPhase {
+                    region: None,
+                    column: column.into(),
+                    row: None,
+                    challenge_phase,
+                });
+            }
+        }
+
+        Ok(AssignedCell {
+            value,
+            cell: cell?,
+            _marker: PhantomData,
+        })
+    }
+
     /// Assigns a constant value to the column `advice` at `offset` within this region.
     ///
     /// The constant value will be assigned to a cell within one of the fixed columns
@@ -461,6 +514,36 @@ pub trait Layouter<F: Field> {
         N: Fn() -> NR,
         NR: Into<String>;
 
+    /// Assigns the logical tables of a [`TableSet`] as one table region.
+    ///
+    /// `rows` is called once for each table of the set, in the order they
+    /// were added, and returns its rows, each with a value for every column
+    /// of the table. The tag column and the padding row are assigned by the
+    /// layouter.
+    ///
+    /// ```ignore
+    /// layouter.assign_table_set(|| "tables", &config.set, |table| {
+    ///     match table.tag() {
+    ///         RANGE => (0..256).map(|i| vec![F::from(i)]).collect(),
+    ///         _ => xor_rows(),
+    ///     }
+    /// })?;
+    /// ```
+    fn assign_table_set<A, N, NR>(
+        &mut self,
+        name: N,
+        set: &TableSet,
+        mut rows: A,
+    ) -> Result<(), Error>
+    where
+        A: FnMut(&LogicalTable) -> Vec<Vec<F>>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        let rows: Vec<_> = set.tables().iter().map(&mut rows).collect();
+        self.assign_table(name, |mut table| set.assign(&mut table, &rows))
+    }
+
     /// Constrains a [`Cell`] to equal an instance column's row value at an
     /// absolute position.
     fn constrain_instance(
@@ -473,6 +556,7 @@ pub trait Layouter<F: Field> {
     /// Queries the value of the given challenge.
     ///
     /// Returns `Value::unknown()` if the current synthesis phase is before the challenge can be queried.
+    /// The value is marked as depending on the challenge, see [`Value::depending_on`].
     fn get_challenge(&self, challenge: Challenge) -> Value<F>;
 
     /// Gets the "root" of this assignment, bypassing the namespacing.
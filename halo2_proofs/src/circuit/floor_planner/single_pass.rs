@@ -157,7 +157,8 @@ impl<'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> Layouter<F>
     {
         // Maintenance hazard: there is near-duplicate code in `v1::AssignmentPass::assign_table`.
         // Assign table cells.
-        self.cs.enter_region(name);
+        let table_name: String = name().into();
+        self.cs.enter_region(|| table_name.clone());
         let mut table = SimpleTableLayouter::new(self.cs, &self.table_columns);
         {
             let table: &mut dyn TableLayouter<F> = &mut table;
@@ -168,7 +169,7 @@ impl<'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> Layouter<F>
 
         // Check that all table columns have the same length `first_unused`,
         // and all cells up to that length are assigned.
-        let first_unused = compute_table_lengths(&default_and_assigned)?;
+        let first_unused = compute_table_lengths(&table_name, &default_and_assigned)?;
 
         // Record these columns so that we can prevent them from being used again.
         for column in default_and_assigned.keys() {
@@ -201,7 +202,7 @@ impl<'a, F: Field, CS: Assignment<F> + 'a + SyncDeps> Layouter<F>
     }
 
     fn get_challenge(&self, challenge: Challenge) -> Value<F> {
-        self.cs.get_challenge(challenge)
+        self.cs.get_challenge(challenge).depending_on(challenge)
     }
 
     fn get_root(&mut self) -> &mut Self::Root {
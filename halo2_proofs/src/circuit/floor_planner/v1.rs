@@ -200,10 +200,11 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + SyncDeps> Layouter<F> for V1Pass<'p,
     }
 
     fn get_challenge(&self, challenge: Challenge) -> Value<F> {
-        match &self.0 {
+        let value = match &self.0 {
             Pass::Measurement(_) => Value::unknown(),
             Pass::Assignment(pass) => pass.plan.cs.get_challenge(challenge),
-        }
+        };
+        value.depending_on(challenge)
     }
 
     fn get_root(&mut self) -> &mut Self::Root {
@@ -302,7 +303,8 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + SyncDeps> AssignmentPass<'p, 'a, F, C
         // Maintenance hazard: there is near-duplicate code in `SingleChipLayouter::assign_table`.
 
         // Assign table cells.
-        self.plan.cs.enter_region(name);
+        let table_name: String = name().into();
+        self.plan.cs.enter_region(|| table_name.clone());
         let mut table = SimpleTableLayouter::new(self.plan.cs, &self.plan.table_columns);
         let result = {
             let table: &mut dyn TableLayouter<F> = &mut table;
@@ -313,7 +315,7 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + SyncDeps> AssignmentPass<'p, 'a, F, C
 
         // Check that all table columns have the same length `first_unused`,
         // and all cells up to that length are assigned.
-        let first_unused = compute_table_lengths(&default_and_assigned)?;
+        let first_unused = compute_table_lengths(&table_name, &default_and_assigned)?;
 
         // Record these columns so that we can prevent them from being used again.
         for column in default_and_assigned.keys() {
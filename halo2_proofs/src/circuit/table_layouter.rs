@@ -117,49 +117,57 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> TableLayouter<F>
     }
 }
 
+/// Checks that every column of the table named `table` has been assigned
+/// without gaps, and that all columns have the same length, which is returned.
 pub(crate) fn compute_table_lengths<F: Debug>(
+    table: &str,
     default_and_assigned: &HashMap<TableColumn, (DefaultTableValue<F>, Vec<bool>)>,
 ) -> Result<usize, Error> {
-    let column_lengths: Result<Vec<_>, Error> = default_and_assigned
+    let mut lengths: Vec<_> = default_and_assigned
         .iter()
-        .map(|(col, (default_value, assigned))| {
-            if default_value.is_none() || assigned.is_empty() {
-                return Err(Error::TableError(TableError::ColumnNotAssigned(*col)));
-            }
-            if assigned.iter().all(|b| *b) {
-                // All values in the column have been assigned
-                Ok((col, assigned.len()))
-            } else {
-                Err(Error::TableError(TableError::ColumnNotAssigned(*col)))
-            }
-        })
+        .map(|(column, (_, assigned))| (*column, assigned))
         .collect();
-    let column_lengths = column_lengths?;
-    column_lengths
+    lengths.sort_by_key(|(column, _)| *column);
+
+    for (column, assigned) in lengths.iter() {
+        let missing_rows: Vec<_> = assigned
+            .iter()
+            .enumerate()
+            .filter(|(_, assigned)| !**assigned)
+            .map(|(row, _)| row)
+            .collect();
+        if !missing_rows.is_empty() || assigned.is_empty() {
+            return Err(Error::TableError(TableError::ColumnNotAssigned {
+                table: table.to_string(),
+                column: *column,
+                missing_rows,
+            }));
+        }
+    }
+
+    let lengths: Vec<_> = lengths
         .into_iter()
-        .try_fold((None, 0), |acc, (col, col_len)| {
-            if acc.1 == 0 || acc.1 == col_len {
-                Ok((Some(*col), col_len))
-            } else {
-                let mut cols = [(*col, col_len), (acc.0.unwrap(), acc.1)];
-                cols.sort();
-                Err(Error::TableError(TableError::UnevenColumnLengths(
-                    cols[0], cols[1],
-                )))
-            }
-        })
-        .map(|col_len| col_len.1)
+        .map(|(column, assigned)| (column, assigned.len()))
+        .collect();
+    match lengths.first() {
+        None => Ok(0),
+        Some((_, len)) if lengths.iter().all(|(_, l)| l == len) => Ok(*len),
+        Some(_) => Err(Error::TableError(TableError::UnevenColumnLengths {
+            table: table.to_string(),
+            lengths,
+        })),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use halo2curves::pasta::Fp;
+    use halo2curves::pasta::{EqAffine, Fp};
 
     use crate::{
         circuit::{Layouter, SimpleFloorPlanner},
         dev::MockProver,
-        plonk::{Circuit, ConstraintSystem},
-        poly::Rotation,
+        plonk::{keygen_vk, Circuit, ConstraintSystem},
+        poly::{commitment::ParamsProver, ipa::commitment::ParamsIPA, Rotation},
     };
 
     use super::*;
@@ -219,7 +227,7 @@ mod tests {
         let prover = MockProver::run(K, &FaultyCircuit, vec![]);
         assert_eq!(
             format!("{}", prover.unwrap_err()),
-            "TableColumn { inner: Column { index: 0, column_type: Fixed } } not fully assigned. Help: assign a value at offset 0."
+            "TableColumn { inner: Column { index: 0, column_type: Fixed } } in table 'duplicate assignment' not fully assigned: rows [0] are missing. Help: assign a value at offset 0."
         );
     }
 
@@ -407,7 +415,93 @@ mod tests {
         let prover = MockProver::run(K, &FaultyCircuit, vec![]);
         assert_eq!(
             format!("{}", prover.unwrap_err()),
-            "TableColumn { inner: Column { index: 0, column_type: Fixed } } has length 2 while TableColumn { inner: Column { index: 1, column_type: Fixed } } has length 1"
+            "Table 'table with uneven columns' has columns of uneven lengths: TableColumn { inner: Column { index: 1, column_type: Fixed } } has length 1, shorter than the longest column length 2"
         );
+
+        // Keygen goes through the same check before filling the table columns.
+        let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+        assert!(matches!(
+            keygen_vk(&params, &FaultyCircuit),
+            Err(Error::TableError(TableError::UnevenColumnLengths { ref table, ref lengths }))
+                if table == "table with uneven columns"
+                    && lengths.iter().map(|(column, len)| (column.inner().index(), *len)).collect::<Vec<_>>() == vec![(0, 2), (1, 1)]
+        ));
+    }
+
+    #[test]
+    fn table_missing_rows() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct FaultyCircuitConfig {
+            table: (TableColumn, TableColumn),
+        }
+
+        struct FaultyCircuit;
+
+        impl Circuit<Fp> for FaultyCircuit {
+            type Config = FaultyCircuitConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let table = (meta.lookup_table_column(), meta.lookup_table_column());
+                meta.lookup("", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![(a.clone(), table.0), (a, table.1)]
+                });
+
+                Self::Config { table }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "table with gaps",
+                    |mut table| {
+                        for offset in 0..4 {
+                            table.assign_cell(
+                                || "",
+                                config.table.0,
+                                offset,
+                                || Value::known(Fp::zero()),
+                            )?;
+                        }
+                        for offset in [0, 1, 3] {
+                            table.assign_cell(
+                                || "",
+                                config.table.1,
+                                offset,
+                                || Value::known(Fp::zero()),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &FaultyCircuit, vec![]);
+        assert_eq!(
+            format!("{}", prover.unwrap_err()),
+            "TableColumn { inner: Column { index: 1, column_type: Fixed } } in table 'table with gaps' not fully assigned: rows [2] are missing."
+        );
+
+        let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+        assert!(matches!(
+            keygen_vk(&params, &FaultyCircuit),
+            Err(Error::TableError(TableError::ColumnNotAssigned { ref table, ref missing_rows, .. }))
+                if table == "table with gaps" && missing_rows == &[2]
+        ));
     }
 }
@@ -0,0 +1,197 @@
+//! Several lookup tables packed into the same table columns.
+
+use ff::Field;
+
+use super::{Table, Value};
+use crate::plonk::{ConstraintSystem, Error, TableColumn, TableError};
+
+/// A set of logical lookup tables sharing the same [`TableColumn`]s, told
+/// apart by the value of a tag column.
+///
+/// Each logical table is registered with [`TableSet::add_table`], under a
+/// nonzero tag and with the columns of the set it uses.
+/// [`Layouter::assign_table_set`](super::Layouter::assign_table_set) assigns
+/// the rows of every table one after the other, with the tag of their table
+/// in the tag column and zero in the columns their table does not use. The
+/// first row of the set is a padding row of zeros, with tag 0, which is also
+/// the value the columns are filled with up to the usable rows: a lookup
+/// created with [`ConstraintSystem::lookup_in_set`] looks up this row when it
+/// is disabled.
+#[derive(Clone, Debug)]
+pub struct TableSet {
+    name: String,
+    tag: TableColumn,
+    columns: Vec<TableColumn>,
+    tables: Vec<LogicalTable>,
+}
+
+/// A logical table of a [`TableSet`].
+#[derive(Clone, Debug)]
+pub struct LogicalTable {
+    name: String,
+    tag: u64,
+    tag_column: TableColumn,
+    columns: Vec<TableColumn>,
+}
+
+impl TableSet {
+    /// Creates a set of `num_columns` table columns, plus its tag column.
+    pub fn new<F: Field, S: Into<String>>(
+        meta: &mut ConstraintSystem<F>,
+        name: S,
+        num_columns: usize,
+    ) -> Self {
+        let name = name.into();
+        let tag = meta.lookup_table_column();
+        meta.annotate_lookup_column(tag, || format!("{} tag", name));
+        let columns = (0..num_columns)
+            .map(|i| {
+                let column = meta.lookup_table_column();
+                meta.annotate_lookup_column(column, || format!("{}[{}]", name, i));
+                column
+            })
+            .collect();
+
+        TableSet {
+            name,
+            tag,
+            columns,
+            tables: vec![],
+        }
+    }
+
+    /// Registers a logical table named `name`, with the given `tag` and the
+    /// given columns of the set, in the order its rows list their values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` is 0, which is reserved for the padding row, or is the
+    /// tag of another table of the set, or if one of `columns` is not a
+    /// column of the set or is given twice.
+    pub fn add_table<S: Into<String>>(
+        &mut self,
+        name: S,
+        tag: u64,
+        columns: &[TableColumn],
+    ) -> LogicalTable {
+        let name = name.into();
+        assert!(tag != 0, "tag 0 of table set {} is reserved", self.name);
+        assert!(
+            self.tables.iter().all(|table| table.tag != tag),
+            "tag {} of table set {} is already used",
+            tag,
+            self.name
+        );
+        for (i, column) in columns.iter().enumerate() {
+            assert!(
+                self.columns.contains(column),
+                "{:?} is not a column of table set {}",
+                column,
+                self.name
+            );
+            assert!(
+                !columns[..i].contains(column),
+                "{:?} is given twice for table {}",
+                column,
+                name
+            );
+        }
+
+        let table = LogicalTable {
+            name,
+            tag,
+            tag_column: self.tag,
+            columns: columns.to_vec(),
+        };
+        self.tables.push(table.clone());
+        table
+    }
+
+    /// Returns the name of the set.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the tag column of the set.
+    pub fn tag_column(&self) -> TableColumn {
+        self.tag
+    }
+
+    /// Returns the columns of the set, other than the tag column.
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// Returns the logical tables of the set, in the order they were added.
+    pub fn tables(&self) -> &[LogicalTable] {
+        &self.tables
+    }
+
+    /// Assigns the padding row, then the `rows` of each table of the set.
+    pub(crate) fn assign<F: Field>(
+        &self,
+        table: &mut Table<'_, F>,
+        rows: &[Vec<Vec<F>>],
+    ) -> Result<(), Error> {
+        let zero = || Value::known(F::ZERO);
+        table.assign_cell(|| "padding", self.tag, 0, zero)?;
+        for column in &self.columns {
+            table.assign_cell(|| "padding", *column, 0, zero)?;
+        }
+
+        let mut offset = 1;
+        for (logical, rows) in self.tables.iter().zip(rows) {
+            for (i, row) in rows.iter().enumerate() {
+                if row.len() != logical.columns.len() {
+                    return Err(Error::TableError(TableError::RowWidth {
+                        table: logical.name.clone(),
+                        row: i,
+                        width: row.len(),
+                        columns: logical.columns.len(),
+                    }));
+                }
+
+                let tag = Value::known(F::from(logical.tag));
+                table.assign_cell(|| format!("{} tag", logical.name), self.tag, offset, || tag)?;
+                for column in &self.columns {
+                    let value = logical
+                        .columns
+                        .iter()
+                        .position(|c| c == column)
+                        .map_or(F::ZERO, |position| row[position]);
+                    table.assign_cell(
+                        || logical.name.clone(),
+                        *column,
+                        offset,
+                        || Value::known(value),
+                    )?;
+                }
+                offset += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LogicalTable {
+    /// Returns the name of the table.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the tag of the table.
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Returns the tag column of the set the table belongs to.
+    pub fn tag_column(&self) -> TableColumn {
+        self.tag_column
+    }
+
+    /// Returns the columns of the table, in the order its rows list their
+    /// values.
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+}
@@ -1,9 +1,10 @@
 use std::borrow::Borrow;
+use std::fmt;
 use std::ops::{Add, Mul, Neg, Sub};
 
 use group::ff::Field;
 
-use crate::plonk::{Assigned, Error};
+use crate::plonk::{Assigned, Challenge, Error};
 
 /// A value that might exist within a circuit.
 ///
@@ -12,9 +13,20 @@ use crate::plonk::{Assigned, Error};
 ///   helps to ensure that unwitnessed values correctly propagate.
 /// - It provides pass-through implementations of common traits such as `Add` and `Mul`,
 ///   for improved usability.
-#[derive(Clone, Copy, Debug)]
+///
+/// It also remembers the latest phase after which a challenge it was computed
+/// from is squeezed, so that assigning it to an advice column of that phase or
+/// an earlier one can be caught; see [`Value::challenge_phase`].
+#[derive(Clone, Copy)]
 pub struct Value<V> {
     inner: Option<V>,
+    challenge_phase: Option<u8>,
+}
+
+impl<V: fmt::Debug> fmt::Debug for Value<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Value").field("inner", &self.inner).finish()
+    }
 }
 
 impl<V> Default for Value<V> {
@@ -26,7 +38,10 @@ impl<V> Default for Value<V> {
 impl<V> Value<V> {
     /// Constructs an unwitnessed value.
     pub const fn unknown() -> Self {
-        Self { inner: None }
+        Self {
+            inner: None,
+            challenge_phase: None,
+        }
     }
 
     /// Constructs a known value.
@@ -39,7 +54,10 @@ impl<V> Value<V> {
     /// let v = Value::known(37);
     /// ```
     pub const fn known(value: V) -> Self {
-        Self { inner: Some(value) }
+        Self {
+            inner: Some(value),
+            challenge_phase: None,
+        }
     }
 
     /// Obtains the inner value for assigning into the circuit.
@@ -53,6 +71,7 @@ impl<V> Value<V> {
     pub fn as_ref(&self) -> Value<&V> {
         Value {
             inner: self.inner.as_ref(),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -60,6 +79,28 @@ impl<V> Value<V> {
     pub fn as_mut(&mut self) -> Value<&mut V> {
         Value {
             inner: self.inner.as_mut(),
+            challenge_phase: self.challenge_phase,
+        }
+    }
+
+    /// Returns the latest phase after which a challenge this value was
+    /// computed from is squeezed, if it was computed from any.
+    ///
+    /// The value is only known to the prover after that phase, so it cannot
+    /// be assigned to an advice column of that phase or an earlier one: the
+    /// prover commits to those columns before the challenge exists.
+    pub fn challenge_phase(&self) -> Option<u8> {
+        self.challenge_phase
+    }
+
+    /// Marks the value as computed from `challenge`. Layouters mark the
+    /// values they return from
+    /// [`Layouter::get_challenge`](crate::circuit::Layouter::get_challenge),
+    /// and values computed from them keep the mark.
+    pub fn depending_on(self, challenge: Challenge) -> Self {
+        Value {
+            inner: self.inner,
+            challenge_phase: self.challenge_phase.max(Some(challenge.phase())),
         }
     }
 
@@ -97,15 +138,20 @@ impl<V> Value<V> {
     pub fn map<W, F: FnOnce(V) -> W>(self, f: F) -> Value<W> {
         Value {
             inner: self.inner.map(f),
+            challenge_phase: self.challenge_phase,
         }
     }
 
     /// Returns [`Value::unknown()`] if the value is [`Value::unknown()`], otherwise calls
     /// `f` with the wrapped value and returns the result.
     pub fn and_then<W, F: FnOnce(V) -> Value<W>>(self, f: F) -> Value<W> {
-        match self.inner {
+        let value = match self.inner {
             Some(v) => f(v),
             None => Value::unknown(),
+        };
+        Value {
+            inner: value.inner,
+            challenge_phase: self.challenge_phase.max(value.challenge_phase),
         }
     }
 
@@ -116,6 +162,7 @@ impl<V> Value<V> {
     pub fn zip<W>(self, other: Value<W>) -> Value<(V, W)> {
         Value {
             inner: self.inner.zip(other.inner),
+            challenge_phase: self.challenge_phase.max(other.challenge_phase),
         }
     }
 }
@@ -127,10 +174,18 @@ impl<V, W> Value<(V, W)> {
     /// `(Value::known(a), Value::known(b))`. Otherwise,
     /// `(Value::unknown(), Value::unknown())` is returned.
     pub fn unzip(self) -> (Value<V>, Value<W>) {
-        match self.inner {
-            Some((a, b)) => (Value::known(a), Value::known(b)),
-            None => (Value::unknown(), Value::unknown()),
-        }
+        let challenge_phase = self.challenge_phase;
+        let (a, b) = self.inner.unzip();
+        (
+            Value {
+                inner: a,
+                challenge_phase,
+            },
+            Value {
+                inner: b,
+                challenge_phase,
+            },
+        )
     }
 }
 
@@ -143,6 +198,7 @@ impl<V> Value<&V> {
     {
         Value {
             inner: self.inner.copied(),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -154,6 +210,7 @@ impl<V> Value<&V> {
     {
         Value {
             inner: self.inner.cloned(),
+            challenge_phase: self.challenge_phase,
         }
     }
 }
@@ -167,6 +224,7 @@ impl<V> Value<&mut V> {
     {
         Value {
             inner: self.inner.copied(),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -178,6 +236,7 @@ impl<V> Value<&mut V> {
     {
         Value {
             inner: self.inner.cloned(),
+            challenge_phase: self.challenge_phase,
         }
     }
 }
@@ -187,10 +246,13 @@ impl<V: Copy, const LEN: usize> Value<[V; LEN]> {
     ///
     /// [`Value::unknown()`] will be mapped to `[Value::unknown(); LEN]`.
     pub fn transpose_array(self) -> [Value<V>; LEN] {
-        let mut ret = [Value::unknown(); LEN];
+        let mut ret = [Value {
+            inner: None,
+            challenge_phase: self.challenge_phase,
+        }; LEN];
         if let Some(arr) = self.inner {
             for (entry, value) in ret.iter_mut().zip(arr) {
-                *entry = Value::known(value);
+                entry.inner = Some(value);
             }
         }
         ret
@@ -210,13 +272,18 @@ where
     ///
     /// Panics if `self` is `Value::known(values)` and `values.len() != length`.
     pub fn transpose_vec(self, length: usize) -> Vec<Value<V>> {
+        let challenge_phase = self.challenge_phase;
+        let value = move |inner| Value {
+            inner,
+            challenge_phase,
+        };
         match self.inner {
             Some(values) => {
                 let values = values.into_iter();
                 assert_eq!(values.len(), length);
-                values.map(Value::known).collect()
+                values.map(|v| value(Some(v))).collect()
             }
-            None => (0..length).map(|_| Value::unknown()).collect(),
+            None => (0..length).map(|_| value(None)).collect(),
         }
     }
 }
@@ -231,8 +298,22 @@ impl<A, V: FromIterator<A>> FromIterator<Value<A>> for Value<V> {
     /// [`Value::unknown()`] occur, a container of type `V` containing the values of each
     /// [`Value`] is returned.
     fn from_iter<I: IntoIterator<Item = Value<A>>>(iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        let mut challenge_phase = None;
+        let inner = iter
+            .by_ref()
+            .map(|v| {
+                challenge_phase = challenge_phase.max(v.challenge_phase);
+                v.inner
+            })
+            .collect();
+        // The remaining values are still marked, if any is unknown.
+        for v in iter {
+            challenge_phase = challenge_phase.max(v.challenge_phase);
+        }
         Self {
-            inner: iter.into_iter().map(|v| v.inner).collect(),
+            inner,
+            challenge_phase,
         }
     }
 }
@@ -247,6 +328,7 @@ impl<V: Neg> Neg for Value<V> {
     fn neg(self) -> Self::Output {
         Value {
             inner: self.inner.map(|v| -v),
+            challenge_phase: self.challenge_phase,
         }
     }
 }
@@ -264,6 +346,7 @@ where
     fn add(self, rhs: Self) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a + b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -281,6 +364,7 @@ where
                 .as_ref()
                 .zip(rhs.inner.as_ref())
                 .map(|(a, b)| a + b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -294,6 +378,7 @@ where
     fn add(self, rhs: Value<&V>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a + b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -307,6 +392,7 @@ where
     fn add(self, rhs: Value<V>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a + b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -346,6 +432,7 @@ where
     fn sub(self, rhs: Self) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a - b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -363,6 +450,7 @@ where
                 .as_ref()
                 .zip(rhs.inner.as_ref())
                 .map(|(a, b)| a - b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -376,6 +464,7 @@ where
     fn sub(self, rhs: Value<&V>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a - b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -389,6 +478,7 @@ where
     fn sub(self, rhs: Value<V>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a - b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -428,6 +518,7 @@ where
     fn mul(self, rhs: Self) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a * b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -445,6 +536,7 @@ where
                 .as_ref()
                 .zip(rhs.inner.as_ref())
                 .map(|(a, b)| a * b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -458,6 +550,7 @@ where
     fn mul(self, rhs: Value<&V>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a * b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -471,6 +564,7 @@ where
     fn mul(self, rhs: Value<V>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a * b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -505,6 +599,7 @@ impl<F: Field> From<Value<F>> for Value<Assigned<F>> {
     fn from(value: Value<F>) -> Self {
         Self {
             inner: value.inner.map(Assigned::from),
+            challenge_phase: value.challenge_phase,
         }
     }
 }
@@ -515,6 +610,7 @@ impl<F: Field> Add<Value<F>> for Value<Assigned<F>> {
     fn add(self, rhs: Value<F>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a + b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -533,6 +629,7 @@ impl<F: Field> Add<Value<F>> for Value<&Assigned<F>> {
     fn add(self, rhs: Value<F>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a + b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -551,6 +648,7 @@ impl<F: Field> Sub<Value<F>> for Value<Assigned<F>> {
     fn sub(self, rhs: Value<F>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a - b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -569,6 +667,7 @@ impl<F: Field> Sub<Value<F>> for Value<&Assigned<F>> {
     fn sub(self, rhs: Value<F>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a - b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -587,6 +686,7 @@ impl<F: Field> Mul<Value<F>> for Value<Assigned<F>> {
     fn mul(self, rhs: Value<F>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a * b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -605,6 +705,7 @@ impl<F: Field> Mul<Value<F>> for Value<&Assigned<F>> {
     fn mul(self, rhs: Value<F>) -> Self::Output {
         Value {
             inner: self.inner.zip(rhs.inner).map(|(a, b)| a * b),
+            challenge_phase: self.challenge_phase.max(rhs.challenge_phase),
         }
     }
 }
@@ -625,6 +726,7 @@ impl<V> Value<V> {
     {
         Value {
             inner: self.inner.as_ref().map(|v| v.into()),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -635,6 +737,7 @@ impl<V> Value<V> {
     {
         Value {
             inner: self.inner.map(|v| v.into()),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -657,6 +760,7 @@ impl<V> Value<V> {
     {
         Value {
             inner: self.inner.as_ref().map(|v| v.borrow().double()),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -667,6 +771,7 @@ impl<V> Value<V> {
     {
         Value {
             inner: self.inner.as_ref().map(|v| v.borrow().square()),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -677,6 +782,7 @@ impl<V> Value<V> {
     {
         Value {
             inner: self.inner.as_ref().map(|v| v.borrow().cube()),
+            challenge_phase: self.challenge_phase,
         }
     }
 
@@ -687,6 +793,7 @@ impl<V> Value<V> {
     {
         Value {
             inner: self.inner.as_ref().map(|v| v.borrow().invert()),
+            challenge_phase: self.challenge_phase,
         }
     }
 }
@@ -698,6 +805,7 @@ impl<F: Field> Value<Assigned<F>> {
     pub fn evaluate(self) -> Value<F> {
         Value {
             inner: self.inner.map(|v| v.evaluate()),
+            challenge_phase: self.challenge_phase,
         }
     }
 }
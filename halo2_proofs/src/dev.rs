@@ -16,7 +16,7 @@ use crate::{
         permutation,
         sealed::{self, SealedPhase},
         Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
-        Expression, FirstPhase, Fixed, FloorPlanner, Instance, Phase, Selector,
+        Expression, FirstPhase, Fixed, FloorPlanner, Gate, Instance, Phase, Selector,
     },
 };
 
@@ -36,6 +36,11 @@ pub use failure::{FailureLocation, VerifyFailure};
 pub mod cost;
 pub use cost::CircuitCost;
 
+pub mod cost_model;
+
+mod dummy;
+pub use dummy::{dummy_proof, dummy_proof_failing_at};
+
 mod gates;
 pub use gates::CircuitGates;
 
@@ -335,6 +340,21 @@ impl<F: Field> MockProver<F> {
     fn in_phase<P: Phase>(&self, phase: P) -> bool {
         self.current_phase == phase.to_sealed()
     }
+
+    /// Returns the value of a cell of a column of the permutation argument,
+    /// which copy constraints compare.
+    fn copied_value(&self, column: Column<Any>, row: usize) -> CellValue<F> {
+        match column.column_type() {
+            Any::Advice(_) => self.advice[column.index()][row],
+            // Fixed cells that are never assigned are zero in the keys, as
+            // instance cells past the given instances are.
+            Any::Fixed => match self.fixed[column.index()][row] {
+                CellValue::Unassigned => CellValue::Assigned(F::ZERO),
+                value => value,
+            },
+            Any::Instance => CellValue::Assigned(self.instance[column.index()][row].value()),
+        }
+    }
 }
 
 impl<F: Field> Assignment<F> for MockProver<F> {
@@ -352,7 +372,9 @@ impl<F: Field> Assignment<F> for MockProver<F> {
             name: name().into(),
             columns: HashSet::default(),
             rows: None,
-            annotations: HashMap::default(),
+            // Columns are annotated with the names they were allocated with,
+            // unless the region annotates them.
+            annotations: self.cs.general_column_annotations.clone(),
             enabled_selectors: HashMap::default(),
             cells: HashMap::default(),
         });
@@ -467,7 +489,25 @@ impl<F: Field> Assignment<F> for MockProver<F> {
             }
         }
 
-        match to().into_field().evaluate().assign() {
+        // A value computed from a challenge squeezed in the phase of the
+        // column or after it cannot be known when the column is committed to.
+        let phase = self.cs.advice_column_phase[column.index()];
+        let to = to().into_field();
+        if let Some(challenge_phase) = to.challenge_phase() {
+            if challenge_phase >= phase.0 {
+                return Err(Error::ChallengeFromLaterPhase {
+                    region: self
+                        .current_region
+                        .as_ref()
+                        .map(|region| region.name.clone()),
+                    column: column.into(),
+                    row: Some(row),
+                    challenge_phase,
+                });
+            }
+        }
+
+        match to.evaluate().assign() {
             Ok(to) => {
                 let value = self
                     .advice
@@ -478,7 +518,7 @@ impl<F: Field> Assignment<F> for MockProver<F> {
             }
             Err(err) => {
                 // Propagate `assign` error if the column is in current phase.
-                if self.in_phase(column.column_type().phase) {
+                if phase == self.current_phase {
                     return Err(err);
                 }
             }
@@ -512,6 +552,10 @@ impl<F: Field> Assignment<F> for MockProver<F> {
             self.k,
         );
 
+        if self.cs.fixed_column_values(column).is_some() {
+            return Err(Error::PreassignedFixedColumn { column, row });
+        }
+
         if let Some(region) = self.current_region.as_mut() {
             region.update_extent(column.into(), row);
             region
@@ -580,7 +624,7 @@ impl<F: Field> Assignment<F> for MockProver<F> {
     }
 
     fn get_challenge(&self, challenge: Challenge) -> circuit::Value<F> {
-        if self.current_phase <= challenge.phase {
+        if self.current_phase.0 <= self.cs.challenge_phase(challenge) {
             return circuit::Value::unknown();
         }
 
@@ -616,6 +660,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
         #[cfg(not(feature = "circuit-params"))]
         let config = ConcreteCircuit::configure(&mut cs);
         let cs = cs;
+        cs.check_column_pins()?;
 
         assert!(
             n >= cs.minimum_rows(),
@@ -631,7 +676,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
             .into_iter()
             .map(|instance| {
                 assert!(
-                    instance.len() <= n - (cs.blinding_factors() + 1),
+                    instance.len() <= cs.usable_rows(k).end,
                     "instance.len={}, n={}, cs.blinding_factors={}",
                     instance.len(),
                     n,
@@ -648,11 +693,23 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
             .collect::<Vec<_>>();
 
         // Fixed columns contain no blinding factors.
-        let fixed = vec![vec![CellValue::Unassigned; n]; cs.num_fixed_columns];
+        let mut fixed = vec![vec![CellValue::Unassigned; n]; cs.num_fixed_columns];
+        for (column, values) in &cs.preassigned_fixed {
+            assert!(
+                values.len() <= cs.usable_rows(k).end,
+                "column={:?}, values.len={}, n={}, cs.blinding_factors={}",
+                column,
+                values.len(),
+                n,
+                cs.blinding_factors()
+            );
+            for (cell, value) in fixed[column.index()].iter_mut().zip(values) {
+                *cell = CellValue::Assigned(*value);
+            }
+        }
         let selectors = vec![vec![false; n]; cs.num_selectors];
         // Advice columns contain blinding factors.
-        let blinding_factors = cs.blinding_factors();
-        let usable_rows = n - (blinding_factors + 1);
+        let usable_rows = cs.usable_rows(k).end;
         let advice = vec![
             {
                 let mut column = vec![CellValue::Unassigned; n];
@@ -668,7 +725,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
         let constants = cs.constants.clone();
 
         // Use hash chain to derive deterministic challenges for testing
-        let challenges = {
+        let mut challenges: Vec<_> = {
             let mut hash: [u8; 64] = blake2b(b"Halo2-MockProver").as_bytes().try_into().unwrap();
             iter::repeat_with(|| {
                 hash = blake2b(&hash).as_bytes().try_into().unwrap();
@@ -677,6 +734,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
             .take(cs.num_challenges)
             .collect()
         };
+        cs.compute_challenge_powers(&mut challenges);
 
         let mut prover = MockProver {
             k,
@@ -744,6 +802,41 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
         &self,
         gate_row_ids: I,
         lookup_input_row_ids: I,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        self.verify_gates_at_rows(&self.cs.gates, gate_row_ids, lookup_input_row_ids)
+    }
+
+    /// Returns `Ok(())` if this `MockProver` is satisfied once the gates named
+    /// in `gates` are disabled, or a list of errors indicating the reasons
+    /// that the circuit is not satisfied.
+    ///
+    /// The gates are disabled as with [`ConstraintSystem::disable_gate`], and
+    /// the other gates keep their indices in the failures, so comparing the
+    /// result with that of [`Self::verify`] tells which failures the disabled
+    /// gates are responsible for.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no gate is named after one of `gates`.
+    pub fn verify_without_gates(&self, gates: &[&str]) -> Result<(), Vec<VerifyFailure>> {
+        let mut cs = self.cs.clone();
+        for gate in gates {
+            cs.disable_gate(gate);
+        }
+        self.verify_gates_at_rows(
+            &cs.gates,
+            self.usable_rows.clone(),
+            self.usable_rows.clone(),
+        )
+    }
+
+    /// [`Self::verify_at_rows`], checking `gates` in place of the gates of the
+    /// constraint system.
+    fn verify_gates_at_rows<I: Clone + Iterator<Item = usize>>(
+        &self,
+        gates: &[Gate<F>],
+        gate_row_ids: I,
+        lookup_input_row_ids: I,
     ) -> Result<(), Vec<VerifyFailure>> {
         let n = self.n as i32;
 
@@ -764,8 +857,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
         let selector_errors = self.regions.iter().enumerate().flat_map(|(r_i, r)| {
             r.enabled_selectors.iter().flat_map(move |(selector, at)| {
                 // Find the gates enabled by this selector
-                self.cs
-                    .gates
+                gates
                     .iter()
                     // Assume that if a queried selector is enabled, the user wants to use the
                     // corresponding gate in some way.
@@ -829,74 +921,67 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
         });
 
         // Check that all gates are satisfied for all rows.
-        let gate_errors =
-            self.cs
-                .gates
-                .iter()
-                .enumerate()
-                .flat_map(|(gate_index, gate)| {
-                    let blinding_rows =
-                        (self.n as usize - (self.cs.blinding_factors() + 1))..(self.n as usize);
-                    (gate_row_ids.clone().chain(blinding_rows)).flat_map(move |row| {
-                        let row = row as i32 + n;
-                        gate.polynomials().iter().enumerate().filter_map(
-                            move |(poly_index, poly)| match poly.evaluate_lazy(
-                                &|scalar| Value::Real(scalar),
-                                &|_| panic!("virtual selectors are removed during optimization"),
-                                &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
-                                &util::load(n, row, &self.cs.advice_queries, &self.advice),
-                                &util::load_instance(
-                                    n,
-                                    row,
-                                    &self.cs.instance_queries,
-                                    &self.instance,
+        let gate_errors = gates.iter().enumerate().flat_map(|(gate_index, gate)| {
+            let blinding_rows =
+                (self.n as usize - (self.cs.blinding_factors() + 1))..(self.n as usize);
+            (gate_row_ids.clone().chain(blinding_rows)).flat_map(move |row| {
+                let row = row as i32 + n;
+                gate.polynomials()
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(poly_index, poly)| {
+                        match poly.evaluate_lazy(
+                            &|scalar| Value::Real(scalar),
+                            &|_| panic!("virtual selectors are removed during optimization"),
+                            &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
+                            &util::load(n, row, &self.cs.advice_queries, &self.advice),
+                            &util::load_instance(n, row, &self.cs.instance_queries, &self.instance),
+                            &|challenge| Value::Real(self.challenges[challenge.index()]),
+                            &|a| -a,
+                            &|a, b| a + b,
+                            &|a, b| a * b,
+                            &|a, scalar| a * scalar,
+                            &Value::Real(F::ZERO),
+                        ) {
+                            Value::Real(x) if x.is_zero_vartime() => None,
+                            Value::Real(_) => Some(VerifyFailure::ConstraintNotSatisfied {
+                                constraint: (
+                                    (gate_index, gate.name()).into(),
+                                    poly_index,
+                                    gate.constraint_name(poly_index),
+                                )
+                                    .into(),
+                                location: FailureLocation::find_expressions(
+                                    &self.cs,
+                                    &self.regions,
+                                    (row - n) as usize,
+                                    Some(poly).into_iter(),
                                 ),
-                                &|challenge| Value::Real(self.challenges[challenge.index()]),
-                                &|a| -a,
-                                &|a, b| a + b,
-                                &|a, b| a * b,
-                                &|a, scalar| a * scalar,
-                                &Value::Real(F::ZERO),
-                            ) {
-                                Value::Real(x) if x.is_zero_vartime() => None,
-                                Value::Real(_) => Some(VerifyFailure::ConstraintNotSatisfied {
-                                    constraint: (
-                                        (gate_index, gate.name()).into(),
-                                        poly_index,
-                                        gate.constraint_name(poly_index),
-                                    )
-                                        .into(),
-                                    location: FailureLocation::find_expressions(
-                                        &self.cs,
-                                        &self.regions,
-                                        (row - n) as usize,
-                                        Some(poly).into_iter(),
-                                    ),
-                                    cell_values: util::cell_values(
-                                        gate,
-                                        poly,
-                                        &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
-                                        &util::load(n, row, &self.cs.advice_queries, &self.advice),
-                                        &util::load_instance(
-                                            n,
-                                            row,
-                                            &self.cs.instance_queries,
-                                            &self.instance,
-                                        ),
+                                cell_values: util::cell_values(
+                                    gate,
+                                    poly,
+                                    &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
+                                    &util::load(n, row, &self.cs.advice_queries, &self.advice),
+                                    &util::load_instance(
+                                        n,
+                                        row,
+                                        &self.cs.instance_queries,
+                                        &self.instance,
                                     ),
-                                }),
-                                Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
-                                    constraint: (
-                                        (gate_index, gate.name()).into(),
-                                        poly_index,
-                                        gate.constraint_name(poly_index),
-                                    )
-                                        .into(),
-                                }),
-                            },
-                        )
+                                ),
+                            }),
+                            Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
+                                constraint: (
+                                    (gate_index, gate.name()).into(),
+                                    poly_index,
+                                    gate.constraint_name(poly_index),
+                                )
+                                    .into(),
+                            }),
+                        }
                     })
-                });
+            })
+        });
 
         let load = |expression: &Expression<F>, row| {
             expression.evaluate_lazy(
@@ -1105,14 +1190,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
                     .permutation
                     .get_columns()
                     .get(column)
-                    .map(|c: &Column<Any>| match c.column_type() {
-                        Any::Advice(_) => self.advice[c.index()][row],
-                        Any::Fixed => self.fixed[c.index()][row],
-                        Any::Instance => {
-                            let cell: &InstanceValue<F> = &self.instance[c.index()][row];
-                            CellValue::Assigned(cell.value())
-                        }
-                    })
+                    .map(|c: &Column<Any>| self.copied_value(*c, row))
                     .unwrap()
             };
 
@@ -1554,14 +1632,7 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
                     .permutation
                     .get_columns()
                     .get(column)
-                    .map(|c: &Column<Any>| match c.column_type() {
-                        Any::Advice(_) => self.advice[c.index()][row],
-                        Any::Fixed => self.fixed[c.index()][row],
-                        Any::Instance => {
-                            let cell: &InstanceValue<F> = &self.instance[c.index()][row];
-                            CellValue::Assigned(cell.value())
-                        }
-                    })
+                    .map(|c: &Column<Any>| self.copied_value(*c, row))
                     .unwrap()
             };
 
@@ -1693,6 +1764,11 @@ impl<F: FromUniformBytes<64> + Ord> MockProver<F> {
     pub fn permutation(&self) -> &Assembly {
         &self.permutation
     }
+
+    /// Returns the values the challenges of the circuit were given.
+    pub fn challenges(&self) -> &[F] {
+        &self.challenges
+    }
 }
 
 #[cfg(test)]
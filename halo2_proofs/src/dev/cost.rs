@@ -15,7 +15,7 @@ use crate::{
     circuit::{layouter::RegionColumn, Value},
     plonk::{
         Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
-        Fixed, FloorPlanner, Instance, Selector,
+        Fixed, FloorPlanner, Instance, LookupBackend, QueryRotationReport, Selector,
     },
     poly::Rotation,
 };
@@ -36,6 +36,8 @@ pub struct CircuitCost<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> {
     fixed_queries: usize,
     /// Number of lookup arguments.
     lookups: usize,
+    /// The argument proving the lookups.
+    lookup_backend: LookupBackend,
     /// Number of columns in the global permutation.
     permutation_cols: usize,
     /// Number of distinct sets of points in the multiopening argument.
@@ -50,6 +52,8 @@ pub struct CircuitCost<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> {
     num_advice_columns: usize,
     num_instance_columns: usize,
     num_total_columns: usize,
+    /// The rotations each column is queried at, and what queries them.
+    query_rotations: QueryRotationReport,
 
     _marker: PhantomData<(G, ConcreteCircuit)>,
 }
@@ -285,7 +289,14 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
         .unwrap();
         let (cs, _) = cs.compress_selectors(layout.selectors);
 
-        assert!((1 << k) >= cs.minimum_rows());
+        let query_rotations = cs.query_rotation_report();
+        assert!(
+            (1 << k) >= cs.minimum_rows(),
+            "k = {} is too small: {}\n{}",
+            k,
+            query_rotations.minimum_rows,
+            query_rotations,
+        );
 
         // Figure out how many point sets we have due to queried cells.
         let mut column_queries: HashMap<Column<Any>, HashSet<i32>> = HashMap::new();
@@ -315,9 +326,17 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
         }
 
         // Include lookup polynomials in point sets:
-        point_sets.insert(vec![0, 1]); // product_poly
-        point_sets.insert(vec![-1, 0]); // permuted_input_poly
-        point_sets.insert(vec![0]); // permuted_table_poly
+        match cs.lookup_backend() {
+            LookupBackend::Permutation => {
+                point_sets.insert(vec![0, 1]); // product_poly
+                point_sets.insert(vec![-1, 0]); // permuted_input_poly
+                point_sets.insert(vec![0]); // permuted_table_poly
+            }
+            LookupBackend::LogUp => {
+                point_sets.insert(vec![0, 1]); // running_sum_poly
+                point_sets.insert(vec![0]); // multiplicity_poly
+            }
+        }
 
         // Include permutation polynomials in point sets.
         point_sets.insert(vec![0, 1]); // permutation_product_poly
@@ -336,6 +355,7 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
             advice_queries: cs.advice_queries.len(),
             fixed_queries: cs.fixed_queries.len(),
             lookups: cs.lookups.len(),
+            lookup_backend: cs.lookup_backend(),
             permutation_cols,
             point_sets: point_sets.len(),
             max_rows: layout.total_rows,
@@ -347,10 +367,18 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
             num_total_columns: cs.num_instance_columns
                 + cs.num_advice_columns
                 + cs.num_fixed_columns,
+            query_rotations,
             _marker: PhantomData,
         }
     }
 
+    /// Returns the rotations each column of the circuit is queried at, and
+    /// the gates, lookups and shuffles responsible, which explain the rows
+    /// the circuit needs besides its blinding factors.
+    pub fn query_rotations(&self) -> &QueryRotationReport {
+        &self.query_rotations
+    }
+
     fn permutation_chunks(&self) -> usize {
         let chunk_size = self.max_deg - 2;
         (self.permutation_cols + chunk_size - 1) / chunk_size
@@ -369,9 +397,14 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
             advice: ProofContribution::new(self.advice_columns, self.advice_queries),
 
             // Lookup arguments:
-            // - 3 commitments per lookup argument per instance
-            // - 5 evals per lookup argument per instance
-            lookups: ProofContribution::new(3 * self.lookups, 5 * self.lookups),
+            // - 3 commitments per lookup argument per instance, or 2 with LogUp
+            // - 5 evals per lookup argument per instance, or 3 with LogUp
+            lookups: match self.lookup_backend {
+                LookupBackend::Permutation => {
+                    ProofContribution::new(3 * self.lookups, 5 * self.lookups)
+                }
+                LookupBackend::LogUp => ProofContribution::new(2 * self.lookups, 3 * self.lookups),
+            },
 
             // Global permutation argument:
             // - chunks commitments per instance
@@ -481,15 +514,22 @@ pub struct MarginalProofSize<G: PrimeGroup> {
     _marker: PhantomData<G>,
 }
 
-impl<G: PrimeGroup> From<MarginalProofSize<G>> for usize {
-    fn from(proof: MarginalProofSize<G>) -> Self {
-        let point = G::Repr::default().as_ref().len();
+impl<G: PrimeGroup> MarginalProofSize<G> {
+    /// Returns the size in bytes when each point takes up `point_len`
+    /// bytes; see [`ProofSize::len_with_point_len`].
+    pub fn len_with_point_len(&self, point_len: usize) -> usize {
         let scalar = <G::Scalar as PrimeField>::Repr::default().as_ref().len();
 
-        proof.instance.len(point, scalar)
-            + proof.advice.len(point, scalar)
-            + proof.lookups.len(point, scalar)
-            + proof.equality.len(point, scalar)
+        self.instance.len(point_len, scalar)
+            + self.advice.len(point_len, scalar)
+            + self.lookups.len(point_len, scalar)
+            + self.equality.len(point_len, scalar)
+    }
+}
+
+impl<G: PrimeGroup> From<MarginalProofSize<G>> for usize {
+    fn from(proof: MarginalProofSize<G>) -> Self {
+        proof.len_with_point_len(G::Repr::default().as_ref().len())
     }
 }
 
@@ -507,19 +547,30 @@ pub struct ProofSize<G: PrimeGroup> {
     _marker: PhantomData<G>,
 }
 
-impl<G: PrimeGroup> From<ProofSize<G>> for usize {
-    fn from(proof: ProofSize<G>) -> Self {
-        let point = G::Repr::default().as_ref().len();
+impl<G: PrimeGroup> ProofSize<G> {
+    /// Returns the size in bytes when each point takes up `point_len` bytes,
+    /// as given by [`PointEncoding::len`] for the encoding of the transcript
+    /// the proof is written with. Converting the size into a `usize` assumes
+    /// the compressed encoding of `G`.
+    ///
+    /// [`PointEncoding::len`]: crate::transcript::PointEncoding::len
+    pub fn len_with_point_len(&self, point_len: usize) -> usize {
         let scalar = <G::Scalar as PrimeField>::Repr::default().as_ref().len();
 
-        proof.instance.len(point, scalar)
-            + proof.advice.len(point, scalar)
-            + proof.fixed.len(point, scalar)
-            + proof.lookups.len(point, scalar)
-            + proof.equality.len(point, scalar)
-            + proof.vanishing.len(point, scalar)
-            + proof.multiopen.len(point, scalar)
-            + proof.polycomm.len(point, scalar)
+        self.instance.len(point_len, scalar)
+            + self.advice.len(point_len, scalar)
+            + self.fixed.len(point_len, scalar)
+            + self.lookups.len(point_len, scalar)
+            + self.equality.len(point_len, scalar)
+            + self.vanishing.len(point_len, scalar)
+            + self.multiopen.len(point_len, scalar)
+            + self.polycomm.len(point_len, scalar)
+    }
+}
+
+impl<G: PrimeGroup> From<ProofSize<G>> for usize {
+    fn from(proof: ProofSize<G>) -> Self {
+        proof.len_with_point_len(G::Repr::default().as_ref().len())
     }
 }
 
@@ -558,4 +609,56 @@ mod tests {
         }
         CircuitCost::<Eq, MyCircuit>::measure(K, &MyCircuit).proof_size(1);
     }
+
+    #[test]
+    fn proof_size_with_uncompressed_points() {
+        use crate::transcript::PointEncoding;
+        use halo2curves::pasta::EqAffine;
+
+        const K: u32 = 4;
+
+        struct MyCircuit;
+        impl Circuit<Fp> for MyCircuit {
+            type Config = Column<Advice>;
+            type FloorPlanner = SimpleFloorPlanner;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                meta.create_gate("a^2 = a", |meta| {
+                    let a = meta.query_advice(a, Rotation::cur());
+                    vec![a.clone() * a.clone() - a]
+                });
+                a
+            }
+
+            fn synthesize(
+                &self,
+                _config: Self::Config,
+                _layouter: impl crate::circuit::Layouter<Fp>,
+            ) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        let cost = CircuitCost::<Eq, MyCircuit>::measure(K, &MyCircuit);
+        let compressed = PointEncoding::Compressed.len::<EqAffine>();
+        let uncompressed = PointEncoding::Uncompressed.len::<EqAffine>();
+        assert_eq!((compressed, uncompressed), (32, 64));
+
+        // One advice commitment, the random polynomial, two quotient pieces
+        // and the multiopen argument's commitments.
+        let size = cost.proof_size(1);
+        let commitments = 1 + 3 + 1 + (1 + 2 * K as usize);
+        assert_eq!(
+            size.len_with_point_len(uncompressed),
+            usize::from(cost.proof_size(1)) + commitments * (uncompressed - compressed)
+        );
+        assert_eq!(size.len_with_point_len(compressed), usize::from(size));
+    }
 }
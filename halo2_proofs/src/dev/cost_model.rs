@@ -0,0 +1,250 @@
+//! Estimates the cost of verifying proofs with the KZG commitment scheme.
+
+use std::fmt::Debug;
+use std::io;
+use std::marker::PhantomData;
+
+use blake2b_simd::blake2b;
+use ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
+use halo2curves::pairing::MultiMillerLoop;
+use halo2curves::CurveAffine;
+
+use crate::helpers::SerdeCurveAffine;
+use crate::plonk::{verify_proof, VerifyingKey};
+use crate::poly::commitment::Verifier;
+use crate::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use crate::poly::kzg::msm::DualMSM;
+use crate::poly::kzg::strategy::{DeferredStrategy, GuardKZG};
+use crate::transcript::{Challenge255, EncodedChallenge, Transcript, TranscriptRead};
+
+/// What verifying proofs costs, counted by [`verifier_cost`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifierCost {
+    /// The number of proofs verified together.
+    pub proofs: usize,
+    /// The number of points read from the proof.
+    pub proof_points: usize,
+    /// The number of scalars read from the proof.
+    pub proof_scalars: usize,
+    /// The number of instance values, which the verifier absorbs into the
+    /// transcript.
+    pub instance_values: usize,
+    /// The number of challenges squeezed from the transcript.
+    pub challenges: usize,
+    /// The number of 32-byte words hashed by the Keccak256 transcript with
+    /// the byte layout of EVM verifiers, in which each point is two words
+    /// and each challenge also hashes the previous one.
+    pub hashed_words: usize,
+    /// The number of terms of the MSMs of the final pairing check, counting
+    /// the terms of the first point of the parameters as one.
+    pub msm_terms: usize,
+    /// The number of pairings of the final check.
+    pub pairings: usize,
+    /// The number of bytes of a scalar.
+    pub scalar_len: usize,
+}
+
+impl VerifierCost {
+    /// Returns the number of bytes of the proof when each point takes up
+    /// `point_len` bytes, as given by
+    /// [`PointEncoding::len`](crate::transcript::PointEncoding::len).
+    pub fn proof_len(&self, point_len: usize) -> usize {
+        self.proof_points * point_len + self.proof_scalars * self.scalar_len
+    }
+
+    /// Returns the number of bytes of the proof and of the instance values
+    /// together, as a verifier contract receives them.
+    pub fn calldata_len(&self, point_len: usize) -> usize {
+        self.proof_len(point_len) + self.instance_values * self.scalar_len
+    }
+}
+
+/// Counts what the verifier `V` does to verify `num_proofs` proofs under
+/// `vk` whose instance columns hold `instance_lens` values each.
+///
+/// The verifier is run on a transcript that reads placeholder points and
+/// scalars and counts them instead of hashing, so the counts follow the
+/// verifier as it changes. Its final pairing check is deferred with a
+/// [`DeferredStrategy`], whose MSMs give [`VerifierCost::msm_terms`].
+///
+/// # Panics
+///
+/// Panics if `instance_lens` does not have one length per instance column.
+pub fn verifier_cost<'params, E, V>(
+    params: &'params ParamsKZG<E>,
+    vk: &VerifyingKey<E::G1Affine>,
+    num_proofs: usize,
+    instance_lens: &[usize],
+) -> VerifierCost
+where
+    E: MultiMillerLoop + Debug,
+    E::Scalar: PrimeField + WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    V: Verifier<
+        'params,
+        KZGCommitmentScheme<E>,
+        MSMAccumulator = DualMSM<'params, E>,
+        Guard = GuardKZG<'params, E>,
+    >,
+{
+    assert_eq!(
+        instance_lens.len(),
+        vk.cs().num_instance_columns(),
+        "instance columns"
+    );
+    let mut transcript = CountingTranscript::<E::G1Affine> {
+        // Any point other than the first of the parameters, which the MSMs
+        // count apart
+        point: params.g[1],
+        counter: 0,
+        proof_points: 0,
+        proof_scalars: 0,
+        challenges: 0,
+        hashed_words: 0,
+        pending_words: 0,
+        _marker: PhantomData,
+    };
+    let instance: Vec<Vec<E::Scalar>> = instance_lens
+        .iter()
+        .map(|len| (0..*len).map(|_| transcript.placeholder()).collect())
+        .collect();
+    let instance: Vec<&[E::Scalar]> = instance.iter().map(|column| &column[..]).collect();
+    let instances = vec![&instance[..]; num_proofs];
+    let msm = verify_proof::<_, V, _, _, _>(
+        params,
+        vk,
+        DeferredStrategy::new(params),
+        &instances,
+        &mut transcript,
+    )
+    .expect("the placeholder proof can be read");
+
+    let instance_values = num_proofs * instance_lens.iter().sum::<usize>();
+    VerifierCost {
+        proofs: num_proofs,
+        proof_points: transcript.proof_points,
+        proof_scalars: transcript.proof_scalars,
+        instance_values,
+        challenges: transcript.challenges,
+        hashed_words: transcript.hashed_words,
+        msm_terms: msm.left().len() + msm.right().len() + 1,
+        pairings: 2,
+        scalar_len: <E::Scalar as PrimeField>::Repr::default().as_ref().len(),
+    }
+}
+
+/// A transcript reading the same placeholder point and pseudo-random
+/// scalars, and counting what it reads, absorbs and squeezes.
+struct CountingTranscript<C: CurveAffine> {
+    point: C,
+    counter: u64,
+    proof_points: usize,
+    proof_scalars: usize,
+    challenges: usize,
+    hashed_words: usize,
+    pending_words: usize,
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> CountingTranscript<C>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn next_bytes(&mut self) -> [u8; 64] {
+        self.counter += 1;
+        *blake2b(&self.counter.to_le_bytes()).as_array()
+    }
+
+    fn placeholder(&mut self) -> C::Scalar {
+        C::Scalar::from_uniform_bytes(&self.next_bytes())
+    }
+}
+
+impl<C: CurveAffine> Transcript<C, Challenge255<C>> for CountingTranscript<C>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        self.challenges += 1;
+        self.hashed_words += 1 + self.pending_words;
+        self.pending_words = 0;
+        Challenge255::new(&self.next_bytes())
+    }
+
+    fn common_point(&mut self, _: C) -> io::Result<()> {
+        self.pending_words += 2;
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, _: C::Scalar) -> io::Result<()> {
+        self.pending_words += 1;
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine> TranscriptRead<C, Challenge255<C>> for CountingTranscript<C>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        self.proof_points += 1;
+        self.common_point(self.point)?;
+        Ok(self.point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        self.proof_scalars += 1;
+        let scalar = self.placeholder();
+        self.common_scalar(scalar)?;
+        Ok(scalar)
+    }
+}
+
+/// A rough estimate of the gas an EVM verifier spends on a [`VerifierCost`],
+/// from the prices of the precompiles and of calldata after EIP-1108 and
+/// EIP-2028.
+///
+/// It assumes the verifier multiplies and adds each MSM term with the
+/// precompiles, that every calldata byte is nonzero and that points are
+/// uncompressed, and leaves out the field arithmetic, memory and the base
+/// cost of the transaction. Actual verifiers differ from it by a fair margin
+/// either way; it is meant to compare circuits, not to budget transactions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvmGasEstimate {
+    /// The gas of the calldata.
+    pub calldata: u64,
+    /// The gas of the `ecMul` and `ecAdd` precompiles.
+    pub msm: u64,
+    /// The gas of the `ecPairing` precompile.
+    pub pairing: u64,
+    /// The gas of the Keccak256 hashes of the transcript.
+    pub keccak: u64,
+}
+
+impl EvmGasEstimate {
+    const CALLDATA_BYTE: u64 = 16;
+    const EC_MUL: u64 = 6_000;
+    const EC_ADD: u64 = 150;
+    const PAIRING_BASE: u64 = 45_000;
+    const PAIRING_PER_PAIR: u64 = 34_000;
+    const KECCAK_BASE: u64 = 30;
+    const KECCAK_PER_WORD: u64 = 6;
+
+    /// Estimates the gas of verifying with the given cost.
+    pub fn new(cost: &VerifierCost) -> Self {
+        let msm_terms = cost.msm_terms as u64;
+        EvmGasEstimate {
+            calldata: Self::CALLDATA_BYTE * cost.calldata_len(2 * cost.scalar_len) as u64,
+            msm: Self::EC_MUL * msm_terms + Self::EC_ADD * msm_terms.saturating_sub(2),
+            pairing: Self::PAIRING_BASE + Self::PAIRING_PER_PAIR * cost.pairings as u64,
+            keccak: Self::KECCAK_BASE * cost.challenges as u64
+                + Self::KECCAK_PER_WORD * cost.hashed_words as u64,
+        }
+    }
+
+    /// Returns the sum of the estimates.
+    pub fn total(&self) -> u64 {
+        self.calldata + self.msm + self.pairing + self.keccak
+    }
+}
@@ -0,0 +1,327 @@
+//! Proof-shaped byte strings for testing verifiers without proving.
+
+use std::io;
+use std::marker::PhantomData;
+
+use ff::{Field, FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+use halo2curves::CurveAffine;
+use rand_core::RngCore;
+
+use crate::plonk::{verify_proof, ProofComponentKind, ProofLayout, VerifyingKey};
+use crate::poly::commitment::{CommitmentScheme, Verifier};
+use crate::poly::VerificationStrategy;
+use crate::transcript::{Challenge255, Transcript, TranscriptRead};
+
+/// Returns a proof for `num_instances` circuits under `vk` that the verifier
+/// `V` reads in full, but that fails its final check.
+///
+/// Every point of the proof is a random point of the curve and every scalar
+/// a random field element, encoded as the Blake2b and Keccak256 transcripts
+/// of this crate read them, with compressed points. The proof is laid out by
+/// running `V` with a `Strategy` over a transcript that makes up what it
+/// reads, so its size follows the verifying key and the commitment scheme
+/// exactly as a real proof does. It verifies against any instance values
+/// the verifying key accepts up to the final check of the commitment scheme,
+/// and no further.
+pub fn dummy_proof<'params, Scheme, V, Strategy, R>(
+    vk: &VerifyingKey<Scheme::Curve>,
+    params: &'params Scheme::ParamsVerifier,
+    num_instances: usize,
+    rng: R,
+) -> Vec<u8>
+where
+    Scheme: CommitmentScheme,
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+    R: RngCore,
+{
+    write_dummy_proof::<Scheme, V, Strategy, R>(vk, params, num_instances, None, rng)
+}
+
+/// Same as [`dummy_proof`], except that the first point or scalar of the
+/// first `component` of the proof, as laid out by [`ProofLayout::parse`], is
+/// replaced with bytes that do not encode one. The proof keeps its size, and
+/// the verifier fails to read it at that component.
+///
+/// # Panics
+///
+/// Panics if the proof has no such component, e.g. when asked for
+/// [`ProofComponentKind::LookupProduct`] under a verifying key without
+/// lookups.
+pub fn dummy_proof_failing_at<'params, Scheme, V, Strategy, R>(
+    vk: &VerifyingKey<Scheme::Curve>,
+    params: &'params Scheme::ParamsVerifier,
+    num_instances: usize,
+    component: ProofComponentKind,
+    rng: R,
+) -> Vec<u8>
+where
+    Scheme: CommitmentScheme,
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+    R: RngCore,
+{
+    write_dummy_proof::<Scheme, V, Strategy, R>(vk, params, num_instances, Some(component), rng)
+}
+
+fn write_dummy_proof<'params, Scheme, V, Strategy, R>(
+    vk: &VerifyingKey<Scheme::Curve>,
+    params: &'params Scheme::ParamsVerifier,
+    num_instances: usize,
+    fail_at: Option<ProofComponentKind>,
+    rng: R,
+) -> Vec<u8>
+where
+    Scheme: CommitmentScheme,
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+    R: RngCore,
+{
+    let mut transcript = DummyTranscript::<Scheme::Curve, R> {
+        proof: vec![],
+        layout: ProofLayout::parse::<Scheme, V>(vk, num_instances),
+        position: 0,
+        fail_at,
+        rng,
+        _marker: PhantomData,
+    };
+
+    // The proof does not depend on the instance values, so the verifier is
+    // given empty instance columns.
+    let instance = vec![&[][..]; vk.cs().num_instance_columns()];
+    let instances = vec![&instance[..]; num_instances];
+    // The made up proof fails the final check, if the strategy makes it.
+    let _ = verify_proof::<Scheme, V, _, _, _>(
+        params,
+        vk,
+        Strategy::new(params),
+        &instances,
+        &mut transcript,
+    );
+
+    assert!(
+        transcript.fail_at.is_none(),
+        "the proof has no {:?} component",
+        fail_at.unwrap()
+    );
+    transcript.proof
+}
+
+/// A transcript reading random points and scalars, and writing them to
+/// `proof`. The first one of the `fail_at` component is written as bytes
+/// that do not encode one.
+struct DummyTranscript<C: CurveAffine, R: RngCore> {
+    proof: Vec<u8>,
+    layout: ProofLayout,
+    position: usize,
+    fail_at: Option<ProofComponentKind>,
+    rng: R,
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine, R: RngCore> DummyTranscript<C, R> {
+    /// Writes `bytes` for the next point or scalar of the proof, or bytes
+    /// that do not encode one if it is the first of the `fail_at` component.
+    fn write(&mut self, mut bytes: impl AsMut<[u8]>) {
+        let (kind, _, _) = self.layout.locate(self.position);
+        self.position += 1;
+        if self.fail_at == Some(kind) {
+            // Not a canonical encoding, for the scalars and the compressed
+            // points of the curves this crate supports
+            bytes.as_mut().fill(0xff);
+            self.fail_at = None;
+        }
+        self.proof.extend_from_slice(bytes.as_mut());
+    }
+}
+
+impl<C: CurveAffine, R: RngCore> Transcript<C, Challenge255<C>> for DummyTranscript<C, R>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        let mut bytes = [0u8; 64];
+        self.rng.fill_bytes(&mut bytes);
+        Challenge255::new(&bytes)
+    }
+
+    fn common_point(&mut self, _: C) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, _: C::Scalar) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine, R: RngCore> TranscriptRead<C, Challenge255<C>> for DummyTranscript<C, R>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let point = (C::generator() * C::Scalar::random(&mut self.rng)).to_affine();
+        self.write(point.to_bytes());
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = C::Scalar::random(&mut self.rng);
+        self.write(scalar.to_repr());
+        Ok(scalar)
+    }
+}
+
+#[test]
+fn test_dummy_proof() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{
+            keygen_vk, verify_proof_detailed, Advice, Circuit, Column, ConstraintSystem, Error,
+            Instance, VerificationFailure,
+        },
+        poly::{
+            commitment::ParamsProver,
+            ipa::{
+                commitment::{IPACommitmentScheme, ParamsIPA},
+                multiopen::VerifierIPA,
+                strategy::SingleStrategy as IPASingleStrategy,
+            },
+            kzg::{
+                commitment::{KZGCommitmentScheme, ParamsKZG},
+                multiopen::VerifierGWC,
+                strategy::SingleStrategy as KZGSingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{Blake2bRead, TranscriptReadBuffer},
+    };
+    use halo2curves::{bn256::Bn256, pasta::EqAffine};
+    use rand_core::OsRng;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    /// Copies an advice cell into the instance, and constrains the next one
+    /// to be its square.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: PrimeField> Circuit<F> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let q = meta.selector();
+            let a = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(a);
+            meta.enable_equality(instance);
+            meta.create_gate("square", |meta| {
+                let q = meta.query_selector(q);
+                let cur = meta.query_advice(a, Rotation::cur());
+                let next = meta.query_advice(a, Rotation::next());
+                vec![q * (cur.clone() * cur - next)]
+            });
+            MyConfig { a, instance }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let cell = layouter.assign_region(
+                || "a",
+                |mut region| region.assign_advice(|| "a", config.a, 0, || Value::known(F::ONE)),
+            )?;
+            layouter.constrain_instance(cell.cell(), config.instance, 0)
+        }
+    }
+
+    macro_rules! check {
+        ($params:expr, $scheme:ty, $field:ty, $verifier:ident, $strategy:ident) => {{
+            let params = $params;
+            let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+            let instance = [<$field>::ONE];
+            let instances: &[&[&[$field]]] = &[&[&instance], &[&instance]];
+            let verify = |proof: &[u8]| {
+                let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+                verify_proof_detailed::<$scheme, $verifier<_>, _, _, _>(
+                    params.verifier_params(),
+                    &vk,
+                    $strategy::new(&params),
+                    instances,
+                    &mut transcript,
+                )
+            };
+
+            // The proof is read in full, and only fails the final check.
+            let proof = dummy_proof::<$scheme, $verifier<_>, $strategy<_>, _>(
+                &vk,
+                params.verifier_params(),
+                2,
+                OsRng,
+            );
+            let layout = ProofLayout::parse::<$scheme, $verifier<_>>(&vk, 2);
+            assert!(proof.len() > layout.end());
+            assert!(matches!(verify(&proof), Err(VerificationFailure::Opening)));
+
+            for component in layout
+                .components()
+                .iter()
+                .map(|component| component.kind)
+                .chain([ProofComponentKind::Multiopen])
+            {
+                let failing = dummy_proof_failing_at::<$scheme, $verifier<_>, $strategy<_>, _>(
+                    &vk,
+                    params.verifier_params(),
+                    2,
+                    component,
+                    OsRng,
+                );
+                assert_eq!(failing.len(), proof.len());
+                match verify(&failing) {
+                    Err(VerificationFailure::Read {
+                        component: read,
+                        index: 0,
+                        position: 0,
+                        ..
+                    }) => assert_eq!(read, component),
+                    _ => panic!("{:?} should fail to be read", component),
+                }
+            }
+        }};
+    }
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, OsRng);
+    check!(
+        &params,
+        KZGCommitmentScheme<_>,
+        halo2curves::bn256::Fr,
+        VerifierGWC,
+        KZGSingleStrategy
+    );
+
+    // The IPA verifier also reads the evaluations of the instance columns.
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(4);
+    check!(
+        &params,
+        IPACommitmentScheme<_>,
+        halo2curves::pasta::Fp,
+        VerifierIPA,
+        IPASingleStrategy
+    );
+}
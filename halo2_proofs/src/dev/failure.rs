@@ -591,7 +591,10 @@ fn render_lookup<F: Field>(
         }
     }
 
-    eprintln!("error: lookup input does not exist in table");
+    match lookup.table.as_deref() {
+        Some(table) => eprintln!("error: lookup input does not exist in table '{}'", table),
+        None => eprintln!("error: lookup input does not exist in table"),
+    }
     eprint!("  (");
     for i in 0..lookup.input_expressions.len() {
         eprint!("{}L{}", if i == 0 { "" } else { ", " }, i);
@@ -837,6 +840,19 @@ fn render_shuffle<F: Field>(
 }
 
 impl VerifyFailure {
+    /// Returns the name of the logical table of a
+    /// [`TableSet`](crate::circuit::TableSet) that a [`Self::Lookup`] failure
+    /// of `prover` is into, if the lookup was created with
+    /// [`ConstraintSystem::lookup_in_set`](crate::plonk::ConstraintSystem::lookup_in_set).
+    pub fn lookup_table<'a, F: Field>(&self, prover: &'a MockProver<F>) -> Option<&'a str> {
+        match self {
+            Self::Lookup { lookup_index, .. } => {
+                prover.cs.lookups.get(*lookup_index)?.table.as_deref()
+            }
+            _ => None,
+        }
+    }
+
     /// Emits this failure in pretty-printed format to stderr.
     pub(super) fn emit<F: Field>(&self, prover: &MockProver<F>) {
         match self {
@@ -7,9 +7,79 @@ use ff::PrimeField;
 
 use crate::{
     dev::util,
-    plonk::{sealed::SealedPhase, Circuit, ConstraintSystem, FirstPhase},
+    plonk::{
+        sealed::SealedPhase, Advice, AdviceQuery, Challenge, Circuit, Column, ConstraintSystem,
+        Expression, ExpressionFold, ExpressionVisitor, FirstPhase, Fixed, FixedQuery, Instance,
+        InstanceQuery, Selector,
+    },
 };
 
+/// Counts the operations of the expressions it visits.
+#[derive(Default)]
+struct OperationCounter {
+    negations: usize,
+    additions: usize,
+    multiplications: usize,
+}
+
+impl<F: PrimeField> ExpressionVisitor<F> for OperationCounter {
+    fn visit_negated(&mut self, a: &Expression<F>) {
+        self.negations += 1;
+        a.visit(self);
+    }
+
+    fn visit_sum(&mut self, a: &Expression<F>, b: &Expression<F>) {
+        self.additions += 1;
+        a.visit(self);
+        b.visit(self);
+    }
+
+    fn visit_product(&mut self, a: &Expression<F>, b: &Expression<F>) {
+        self.multiplications += 1;
+        a.visit(self);
+        b.visit(self);
+    }
+
+    fn visit_scaled(&mut self, a: &Expression<F>, _factor: F) {
+        self.multiplications += 1;
+        a.visit(self);
+    }
+}
+
+/// Collects the names of the selectors, queries and challenges of the
+/// expressions it visits, with the naming functions of
+/// [`CircuitGates::collect`].
+struct QueryCollector<'a> {
+    selector: &'a dyn Fn(Selector) -> String,
+    fixed: &'a dyn Fn(FixedQuery) -> String,
+    advice: &'a dyn Fn(AdviceQuery) -> String,
+    instance: &'a dyn Fn(InstanceQuery) -> String,
+    challenge: &'a dyn Fn(Challenge) -> String,
+    queries: BTreeSet<String>,
+}
+
+impl<F: PrimeField> ExpressionVisitor<F> for QueryCollector<'_> {
+    fn visit_selector(&mut self, selector: Selector) {
+        self.queries.insert((self.selector)(selector));
+    }
+
+    fn visit_fixed(&mut self, query: FixedQuery) {
+        self.queries.insert((self.fixed)(query));
+    }
+
+    fn visit_advice(&mut self, query: AdviceQuery) {
+        self.queries.insert((self.advice)(query));
+    }
+
+    fn visit_instance(&mut self, query: InstanceQuery) {
+        self.queries.insert((self.instance)(query));
+    }
+
+    fn visit_challenge(&mut self, challenge: Challenge) {
+        self.queries.insert((self.challenge)(challenge));
+    }
+}
+
 #[derive(Debug)]
 struct Constraint {
     name: String,
@@ -23,6 +93,13 @@ struct Gate {
     constraints: Vec<Constraint>,
 }
 
+#[derive(Debug)]
+struct Lookup {
+    name: String,
+    /// The input expressions, each with the table expression it is looked up in.
+    inputs: Vec<(String, String)>,
+}
+
 /// A struct for collecting and displaying the gates within a circuit.
 ///
 /// # Examples
@@ -98,6 +175,7 @@ struct Gate {
 #[derive(Debug)]
 pub struct CircuitGates {
     gates: Vec<Gate>,
+    lookups: Vec<Lookup>,
     total_negations: usize,
     total_additions: usize,
     total_multiplications: usize,
@@ -115,138 +193,135 @@ impl CircuitGates {
         #[cfg(not(feature = "circuit-params"))]
         let _ = C::configure(&mut cs);
 
+        // Columns and selectors are written with the names they were allocated
+        // with, if any.
+        let selector = |selector: Selector| {
+            cs.selector_name(selector)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("S{}", selector.0))
+        };
+        let fixed = |query: FixedQuery| {
+            let column = cs
+                .column_name(Column::new(query.column_index, Fixed))
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("F{}", query.column_index));
+            format!("{}@{}", column, query.rotation.0)
+        };
+        let advice = |query: AdviceQuery| {
+            let column = cs
+                .column_name(Column::new(
+                    query.column_index,
+                    Advice { phase: query.phase },
+                ))
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("A{}", query.column_index));
+            if query.phase == FirstPhase.to_sealed() {
+                format!("{}@{}", column, query.rotation.0)
+            } else {
+                format!("{}({})@{}", column, query.phase(), query.rotation.0)
+            }
+        };
+        let instance = |query: InstanceQuery| {
+            let column = cs
+                .column_name(Column::new(query.column_index, Instance))
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("I{}", query.column_index));
+            format!("{}@{}", column, query.rotation.0)
+        };
+        let challenge =
+            |challenge: Challenge| format!("C{}({})", challenge.index(), challenge.phase());
+
+        let format_expression = |expression: &Expression<F>| {
+            expression.fold(&ExpressionFold {
+                constant: &util::format_value::<F>,
+                selector: &selector,
+                fixed: &fixed,
+                advice: &advice,
+                instance: &instance,
+                challenge: &challenge,
+                negated: &|a| {
+                    if a.contains(' ') {
+                        format!("-({})", a)
+                    } else {
+                        format!("-{}", a)
+                    }
+                },
+                sum: &|a, b| {
+                    if let Some(b) = b.strip_prefix('-') {
+                        format!("{} - {}", a, b)
+                    } else {
+                        format!("{} + {}", a, b)
+                    }
+                },
+                product: &|a, b| match (a.contains(' '), b.contains(' ')) {
+                    (false, false) => format!("{} * {}", a, b),
+                    (false, true) => format!("{} * ({})", a, b),
+                    (true, false) => format!("({}) * {}", a, b),
+                    (true, true) => format!("({}) * ({})", a, b),
+                },
+                scaled: &|a, s| {
+                    if a.contains(' ') {
+                        format!("({}) * {}", a, util::format_value(s))
+                    } else {
+                        format!("{} * {}", a, util::format_value(s))
+                    }
+                },
+            })
+        };
+        let collect_queries = |expression: &Expression<F>| {
+            let mut collector = QueryCollector {
+                selector: &selector,
+                fixed: &fixed,
+                advice: &advice,
+                instance: &instance,
+                challenge: &challenge,
+                queries: BTreeSet::new(),
+            };
+            expression.visit(&mut collector);
+            collector.queries
+        };
+
         let gates = cs
-            .gates
-            .iter()
+            .gates()
             .map(|gate| Gate {
                 name: gate.name().to_string(),
                 constraints: gate
-                    .polynomials()
-                    .iter()
-                    .enumerate()
-                    .map(|(i, constraint)| Constraint {
-                        name: gate.constraint_name(i).to_string(),
-                        expression: constraint.evaluate(
-                            &util::format_value,
-                            &|selector| format!("S{}", selector.0),
-                            &|query| format!("F{}@{}", query.column_index, query.rotation.0),
-                            &|query| {
-                                if query.phase == FirstPhase.to_sealed() {
-                                    format!("A{}@{}", query.column_index, query.rotation.0)
-                                } else {
-                                    format!(
-                                        "A{}({})@{}",
-                                        query.column_index,
-                                        query.phase(),
-                                        query.rotation.0
-                                    )
-                                }
-                            },
-                            &|query| format!("I{}@{}", query.column_index, query.rotation.0),
-                            &|challenge| format!("C{}({})", challenge.index(), challenge.phase()),
-                            &|a| {
-                                if a.contains(' ') {
-                                    format!("-({})", a)
-                                } else {
-                                    format!("-{}", a)
-                                }
-                            },
-                            &|a, b| {
-                                if let Some(b) = b.strip_prefix('-') {
-                                    format!("{} - {}", a, b)
-                                } else {
-                                    format!("{} + {}", a, b)
-                                }
-                            },
-                            &|a, b| match (a.contains(' '), b.contains(' ')) {
-                                (false, false) => format!("{} * {}", a, b),
-                                (false, true) => format!("{} * ({})", a, b),
-                                (true, false) => format!("({}) * {}", a, b),
-                                (true, true) => format!("({}) * ({})", a, b),
-                            },
-                            &|a, s| {
-                                if a.contains(' ') {
-                                    format!("({}) * {}", a, util::format_value(s))
-                                } else {
-                                    format!("{} * {}", a, util::format_value(s))
-                                }
-                            },
-                        ),
-                        queries: constraint.evaluate(
-                            &|_| BTreeSet::default(),
-                            &|selector| vec![format!("S{}", selector.0)].into_iter().collect(),
-                            &|query| {
-                                vec![format!("F{}@{}", query.column_index, query.rotation.0)]
-                                    .into_iter()
-                                    .collect()
-                            },
-                            &|query| {
-                                let query = if query.phase == FirstPhase.to_sealed() {
-                                    format!("A{}@{}", query.column_index, query.rotation.0)
-                                } else {
-                                    format!(
-                                        "A{}({})@{}",
-                                        query.column_index,
-                                        query.phase(),
-                                        query.rotation.0
-                                    )
-                                };
-                                vec![query].into_iter().collect()
-                            },
-                            &|query| {
-                                vec![format!("I{}@{}", query.column_index, query.rotation.0)]
-                                    .into_iter()
-                                    .collect()
-                            },
-                            &|challenge| {
-                                vec![format!("C{}({})", challenge.index(), challenge.phase())]
-                                    .into_iter()
-                                    .collect()
-                            },
-                            &|a| a,
-                            &|mut a, mut b| {
-                                a.append(&mut b);
-                                a
-                            },
-                            &|mut a, mut b| {
-                                a.append(&mut b);
-                                a
-                            },
-                            &|a, _| a,
-                        ),
+                    .constraints()
+                    .map(|(name, constraint)| Constraint {
+                        name: name.to_string(),
+                        expression: format_expression(constraint),
+                        queries: collect_queries(constraint),
                     })
                     .collect(),
             })
             .collect();
 
-        let (total_negations, total_additions, total_multiplications) = cs
-            .gates
-            .iter()
-            .flat_map(|gate| {
-                gate.polynomials().iter().map(|poly| {
-                    poly.evaluate(
-                        &|_| (0, 0, 0),
-                        &|_| (0, 0, 0),
-                        &|_| (0, 0, 0),
-                        &|_| (0, 0, 0),
-                        &|_| (0, 0, 0),
-                        &|_| (0, 0, 0),
-                        &|(a_n, a_a, a_m)| (a_n + 1, a_a, a_m),
-                        &|(a_n, a_a, a_m), (b_n, b_a, b_m)| (a_n + b_n, a_a + b_a + 1, a_m + b_m),
-                        &|(a_n, a_a, a_m), (b_n, b_a, b_m)| (a_n + b_n, a_a + b_a, a_m + b_m + 1),
-                        &|(a_n, a_a, a_m), _| (a_n, a_a, a_m + 1),
-                    )
-                })
+        let mut counter = OperationCounter::default();
+        for gate in cs.gates() {
+            for poly in gate.polynomials() {
+                poly.visit(&mut counter);
+            }
+        }
+
+        let lookups = cs
+            .lookups()
+            .map(|lookup| Lookup {
+                name: lookup.name().to_string(),
+                inputs: lookup
+                    .input_expressions()
+                    .iter()
+                    .map(format_expression)
+                    .zip(lookup.table_expressions().iter().map(format_expression))
+                    .collect(),
             })
-            .fold((0, 0, 0), |(acc_n, acc_a, acc_m), (n, a, m)| {
-                (acc_n + n, acc_a + a, acc_m + m)
-            });
+            .collect();
 
         CircuitGates {
             gates,
-            total_negations,
-            total_additions,
-            total_multiplications,
+            lookups,
+            total_negations: counter.negations,
+            total_additions: counter.additions,
+            total_multiplications: counter.multiplications,
         }
     }
 
@@ -298,6 +373,12 @@ impl fmt::Display for CircuitGates {
                 }
             }
         }
+        for lookup in &self.lookups {
+            writeln!(f, "lookup '{}':", lookup.name)?;
+            for (input, table) in &lookup.inputs {
+                writeln!(f, "- {} -> {}", input, table)?;
+            }
+        }
         writeln!(f, "Total gates: {}", self.gates.len())?;
         writeln!(
             f,
@@ -307,6 +388,9 @@ impl fmt::Display for CircuitGates {
                 .map(|gate| gate.constraints.len())
                 .sum::<usize>()
         )?;
+        if !self.lookups.is_empty() {
+            writeln!(f, "Total lookups: {}", self.lookups.len())?;
+        }
         writeln!(f, "Total negations: {}", self.total_negations)?;
         writeln!(f, "Total additions: {}", self.total_additions)?;
         writeln!(f, "Total multiplications: {}", self.total_multiplications)
@@ -40,6 +40,7 @@ pub struct CircuitLayout {
     hide_labels: bool,
     mark_equality_cells: bool,
     show_equality_constraints: bool,
+    mark_query_rotations: bool,
     view_width: Option<Range<usize>>,
     view_height: Option<Range<usize>>,
 }
@@ -69,6 +70,17 @@ impl CircuitLayout {
         self
     }
 
+    /// Marks, in orange, the rows of each column that its largest and
+    /// smallest rotations reach the unusable rows from, as reported by
+    /// [`ConstraintSystem::query_rotation_report`]. With labels, the query
+    /// costing the most unusable rows is named.
+    ///
+    /// The default is to not mark these rows.
+    pub fn mark_query_rotations(mut self, show: bool) -> Self {
+        self.mark_query_rotations = show;
+        self
+    }
+
     /// Sets the view width for this layout, as a number of columns.
     pub fn view_width(mut self, width: Range<usize>) -> Self {
         self.view_width = Some(width);
@@ -171,7 +183,7 @@ impl CircuitLayout {
         }
 
         // Mark the unusable rows of the circuit.
-        let usable_rows = n - (cs.blinding_factors() + 1);
+        let usable_rows = cs.usable_rows(k).end;
         if view_bottom > usable_rows {
             root.draw(&Rectangle::new(
                 [(0, usable_rows), (total_columns, view_bottom)],
@@ -287,6 +299,35 @@ impl CircuitLayout {
             }
         }
 
+        // Mark the rows whose queries reach the unusable rows.
+        let query_rotations = if self.mark_query_rotations {
+            let report = cs.query_rotation_report();
+            for rotations in &report.columns {
+                let column = column_index(&cs, RegionColumn::Column(rotations.column));
+                let max_rotation = rotations.max_rotation.max(0) as usize;
+                let min_rotation = rotations.min_rotation.min(0).unsigned_abs() as usize;
+                for rows in [
+                    usable_rows.saturating_sub(max_rotation)..usable_rows,
+                    0..min_rotation.min(usable_rows),
+                ] {
+                    if !rows.is_empty() {
+                        root.draw(&Rectangle::new(
+                            [(column, rows.start), (column + 1, rows.end)],
+                            ShapeStyle::from(&RGBColor(255, 165, 0).mix(0.5)).filled(),
+                        ))?;
+                    }
+                }
+            }
+            report.costliest().map(|rotations| {
+                (
+                    column_index(&cs, RegionColumn::Column(rotations.column)),
+                    rotations.to_string(),
+                )
+            })
+        } else {
+            None
+        };
+
         // Add a line showing the total used rows.
         root.draw(&PathElement::new(
             [(0, layout.total_rows), (total_columns, layout.total_rows)],
@@ -317,6 +358,13 @@ impl CircuitLayout {
                         ("sans-serif", 15.0).into_font(),
                     )),
             )?;
+            if let Some((column, description)) = query_rotations {
+                let label = description.lines().next().unwrap_or_default().to_string();
+                root.draw(
+                    &(EmptyElement::at((column, usable_rows))
+                        + Text::new(label, (10, 30), ("sans-serif", 15.0).into_font())),
+                )?;
+            }
         }
         Ok(())
     }
@@ -126,6 +126,34 @@ pub(crate) fn read_polynomial_vec<R: io::Read, F: SerdePrimeField, B>(
         .collect::<io::Result<Vec<_>>>()
 }
 
+/// Reads a polynomial written with `Polynomial::write` and discards it, one
+/// value at a time.
+pub(crate) fn skip_polynomial<R: io::Read, F: SerdePrimeField>(
+    reader: &mut R,
+    format: SerdeFormat,
+) -> io::Result<()> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    for _ in 0..u32::from_be_bytes(len) {
+        F::read(reader, format)?;
+    }
+    Ok(())
+}
+
+/// Reads a vector of polynomials written with [`write_polynomial_slice`] and
+/// discards it, one value at a time.
+pub(crate) fn skip_polynomial_vec<R: io::Read, F: SerdePrimeField>(
+    reader: &mut R,
+    format: SerdeFormat,
+) -> io::Result<()> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    for _ in 0..u32::from_be_bytes(len) {
+        skip_polynomial::<R, F>(reader, format)?;
+    }
+    Ok(())
+}
+
 /// Writes a slice of polynomials to buffer
 pub(crate) fn write_polynomial_slice<W: io::Write, F: SerdePrimeField, B>(
     slice: &[Polynomial<F, B>],
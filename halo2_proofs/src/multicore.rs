@@ -19,6 +19,9 @@ pub use maybe_rayon::{
     slice::ParallelSliceMut,
 };
 
+#[cfg(feature = "multicore")]
+pub use rayon::ThreadPool;
+
 #[cfg(not(feature = "multicore"))]
 pub fn current_num_threads() -> usize {
     1
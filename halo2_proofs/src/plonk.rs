@@ -6,12 +6,12 @@
 //! [plonk]: https://eprint.iacr.org/2019/953
 
 use blake2b_simd::Params as Blake2bParams;
-use group::ff::{Field, FromUniformBytes, PrimeField};
+use group::ff::{Field, FromUniformBytes};
 
-use crate::arithmetic::CurveAffine;
+use crate::arithmetic::{parallelize, CpuEngine, CurveAffine, Engine};
 use crate::helpers::{
-    polynomial_slice_byte_length, read_polynomial_vec, write_polynomial_slice, SerdeCurveAffine,
-    SerdePrimeField,
+    polynomial_slice_byte_length, read_polynomial_vec, skip_polynomial, skip_polynomial_vec,
+    write_polynomial_slice, SerdeCurveAffine, SerdePrimeField,
 };
 use crate::poly::{
     Coeff, EvaluationDomain, ExtendedLagrangeCoeff, LagrangeCoeff, PinnedEvaluationDomain,
@@ -22,40 +22,71 @@ use crate::SerdeFormat;
 
 mod assigned;
 mod circuit;
+mod constraint_report;
 mod error;
 mod evaluation;
+mod export;
 mod keygen;
+mod layout;
+mod listener;
 mod lookup;
+mod oneshot;
 pub mod permutation;
 mod shuffle;
 mod vanishing;
 
 mod prover;
+mod reflection;
+mod rotation_report;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
 mod verifier;
 
 pub use assigned::*;
 pub use circuit::*;
+pub use constraint_report::*;
 pub use error::*;
+pub use export::*;
 pub use keygen::*;
+pub use layout::*;
+pub use listener::*;
+pub use oneshot::*;
 pub use prover::*;
+pub use reflection::*;
+pub use rotation_report::*;
 pub use verifier::*;
 
 use evaluation::Evaluator;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
 use std::io;
+#[cfg(feature = "disk-offload")]
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
 
 /// This is a verifying key which allows for the verification of proofs for a
 /// particular circuit.
 #[derive(Clone, Debug)]
 pub struct VerifyingKey<C: CurveAffine> {
-    domain: EvaluationDomain<C::Scalar>,
+    // The domain and the constraint system are shared by the keys generated
+    // together by `keygen_pk_batch`.
+    domain: Arc<EvaluationDomain<C::Scalar>>,
     fixed_commitments: Vec<C>,
     permutation: permutation::VerifyingKey<C>,
-    cs: ConstraintSystem<C::Scalar>,
+    cs: Arc<ConstraintSystem<C::Scalar>>,
     /// Cached maximum degree of `cs` (which doesn't change after construction).
     cs_degree: usize,
     /// The representative of this `VerifyingKey` in transcripts.
     transcript_repr: C::Scalar,
     selectors: Vec<Vec<bool>>,
+    /// Whether the selectors were compressed, or converted into fixed columns
+    /// one by one; see [`keygen_vk_custom`].
+    compress_selectors: bool,
+    /// Optional caller-provided bytes, e.g. an encoding of the circuit
+    /// parameters the key was generated for.
+    metadata: Option<Vec<u8>>,
+    /// Whether `metadata` is part of `transcript_repr`.
+    metadata_bound: bool,
 }
 
 impl<C: SerdeCurveAffine> VerifyingKey<C>
@@ -71,8 +102,12 @@ where
     /// - Otherwise: Writes an uncompressed curve element with coordinates in Montgomery form
     /// Writes a field element into raw bytes in its internal Montgomery representation,
     /// WITHOUT performing the expensive Montgomery reduction.
+    ///
+    /// The key is preceded by [`Self::FORMAT_VERSION`].
     pub fn write<W: io::Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        writer.write_all(&[Self::FORMAT_VERSION])?;
         writer.write_all(&self.domain.k().to_be_bytes())?;
+        writer.write_all(&[self.compress_selectors as u8])?;
         writer.write_all(&(self.fixed_commitments.len() as u32).to_be_bytes())?;
         for commitment in &self.fixed_commitments {
             commitment.write(writer, format)?;
@@ -86,6 +121,16 @@ where
                 writer.write_all(&[crate::helpers::pack(bits)])?;
             }
         }
+
+        // write self.metadata, tagged with whether it is bound to the transcript
+        match &self.metadata {
+            None => writer.write_all(&[0])?,
+            Some(metadata) => {
+                writer.write_all(&[1 + self.metadata_bound as u8])?;
+                writer.write_all(&(metadata.len() as u32).to_be_bytes())?;
+                writer.write_all(metadata)?;
+            }
+        }
         Ok(())
     }
 
@@ -99,19 +144,58 @@ where
     /// Checks that field elements are less than modulus, and then checks that the point is on the curve.
     /// - `RawBytesUnchecked`: Reads an uncompressed curve element with coordinates in Montgomery form;
     /// does not perform any checks
+    ///
+    /// Keys written before the layout was versioned are also read.
     pub fn read<R: io::Read, ConcreteCircuit: Circuit<C::Scalar>>(
         reader: &mut R,
         format: SerdeFormat,
         #[cfg(feature = "circuit-params")] params: ConcreteCircuit::Params,
     ) -> io::Result<Self> {
+        // Unversioned keys start with `k` as a big-endian `u32`, whose first
+        // byte is always zero. They have compressed selectors and no metadata.
         let mut k = [0u8; 4];
-        reader.read_exact(&mut k)?;
+        reader.read_exact(&mut k[..1])?;
+        let unversioned = match k[0] {
+            0 => true,
+            version if version == Self::FORMAT_VERSION => false,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unsupported verifying key format version",
+                ))
+            }
+        };
+        if unversioned {
+            reader.read_exact(&mut k[1..])?;
+        } else {
+            reader.read_exact(&mut k)?;
+        }
         let k = u32::from_be_bytes(k);
-        let (domain, cs, _) = keygen::create_domain::<C, ConcreteCircuit>(
+        let compress_selectors = if unversioned {
+            true
+        } else {
+            let mut compress_selectors = [0u8; 1];
+            reader.read_exact(&mut compress_selectors)?;
+            match compress_selectors[0] {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unexpected selector compression flag",
+                    ))
+                }
+            }
+        };
+        let (domain, mut cs, _) = keygen::create_domain::<C, ConcreteCircuit>(
             k,
             #[cfg(feature = "circuit-params")]
             params,
         );
+        // Unversioned keys were built before queries were reordered.
+        if unversioned {
+            cs.set_pinned_version(PinnedVersion::V0);
+        }
         let mut num_fixed_columns = [0u8; 4];
         reader.read_exact(&mut num_fixed_columns)?;
         let num_fixed_columns = u32::from_be_bytes(num_fixed_columns);
@@ -134,15 +218,50 @@ where
                 Ok(selector)
             })
             .collect::<io::Result<_>>()?;
-        let (cs, _) = cs.compress_selectors(selectors.clone());
+        let (cs, _) = if compress_selectors {
+            cs.compress_selectors(selectors.clone())
+        } else {
+            cs.selectors_to_fixed(selectors.clone())
+        };
 
-        Ok(Self::from_parts(
+        let vk = Self::from_parts(
             domain,
             fixed_commitments,
             permutation,
             cs,
             selectors,
-        ))
+            compress_selectors,
+        );
+        if unversioned {
+            return Ok(vk);
+        }
+
+        // read metadata
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        match tag[0] {
+            0 => Ok(vk),
+            tag @ (1 | 2) => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len)?;
+                let len = u64::from(u32::from_be_bytes(len));
+                // Read the metadata as it comes rather than allocating the
+                // length it claims, which a corrupted key could make huge.
+                let mut metadata = vec![];
+                io::Read::read_to_end(&mut io::Read::take(&mut *reader, len), &mut metadata)?;
+                if metadata.len() as u64 != len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "truncated verifying key metadata",
+                    ));
+                }
+                Ok(vk.with_metadata(metadata, tag == 2))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected verifying key metadata tag",
+            )),
+        }
     }
 
     /// Writes a verifying key to a vector of bytes using [`Self::write`].
@@ -168,8 +287,13 @@ where
 }
 
 impl<C: CurveAffine> VerifyingKey<C> {
+    /// The version of the layout written by [`Self::write`]. Version 1 adds
+    /// the selector compression flag and the metadata to the unversioned
+    /// layout.
+    pub const FORMAT_VERSION: u8 = 1;
+
     fn bytes_length(&self) -> usize {
-        8 + (self.fixed_commitments.len() * C::default().to_bytes().as_ref().len())
+        10 + (self.fixed_commitments.len() * C::default().to_bytes().as_ref().len())
             + self.permutation.bytes_length()
             + self.selectors.len()
                 * (self
@@ -177,6 +301,12 @@ impl<C: CurveAffine> VerifyingKey<C> {
                     .get(0)
                     .map(|selector| (selector.len() + 7) / 8)
                     .unwrap_or(0))
+            + 1
+            + self
+                .metadata
+                .as_ref()
+                .map(|metadata| 4 + metadata.len())
+                .unwrap_or(0)
     }
 
     fn from_parts(
@@ -185,6 +315,30 @@ impl<C: CurveAffine> VerifyingKey<C> {
         permutation: permutation::VerifyingKey<C>,
         cs: ConstraintSystem<C::Scalar>,
         selectors: Vec<Vec<bool>>,
+        compress_selectors: bool,
+    ) -> Self
+    where
+        C::ScalarExt: FromUniformBytes<64>,
+    {
+        Self::from_shared_parts(
+            Arc::new(domain),
+            fixed_commitments,
+            permutation,
+            Arc::new(cs),
+            selectors,
+            compress_selectors,
+        )
+    }
+
+    /// Same as [`Self::from_parts`], with a domain and constraint system that
+    /// may be shared with other keys.
+    fn from_shared_parts(
+        domain: Arc<EvaluationDomain<C::Scalar>>,
+        fixed_commitments: Vec<C>,
+        permutation: permutation::VerifyingKey<C>,
+        cs: Arc<ConstraintSystem<C::Scalar>>,
+        selectors: Vec<Vec<bool>>,
+        compress_selectors: bool,
     ) -> Self
     where
         C::ScalarExt: FromUniformBytes<64>,
@@ -201,22 +355,67 @@ impl<C: CurveAffine> VerifyingKey<C> {
             // Temporary, this is not pinned.
             transcript_repr: C::Scalar::ZERO,
             selectors,
+            compress_selectors,
+            metadata: None,
+            metadata_bound: false,
         };
+        vk.transcript_repr = vk.compute_transcript_repr();
 
+        vk
+    }
+
+    fn compute_transcript_repr(&self) -> C::Scalar
+    where
+        C::ScalarExt: FromUniformBytes<64>,
+    {
         let mut hasher = Blake2bParams::new()
             .hash_length(64)
             .personal(b"Halo2-Verify-Key")
             .to_state();
 
-        let s = format!("{:?}", vk.pinned());
+        let s = format!("{:?}", self.pinned());
 
         hasher.update(&(s.len() as u64).to_le_bytes());
         hasher.update(s.as_bytes());
 
+        if self.metadata_bound {
+            if let Some(metadata) = &self.metadata {
+                hasher.update(&(metadata.len() as u64).to_le_bytes());
+                hasher.update(metadata);
+            }
+        }
+
         // Hash in final Blake2bState
-        vk.transcript_repr = C::Scalar::from_uniform_bytes(hasher.finalize().as_array());
+        C::Scalar::from_uniform_bytes(hasher.finalize().as_array())
+    }
 
-        vk
+    /// Attaches `metadata` to this verifying key, replacing any previous
+    /// metadata. This is typically an encoding of the `Circuit::Params` the
+    /// key was generated for, so that keys for differently parameterized
+    /// circuits can be told apart.
+    ///
+    /// The metadata is written by [`Self::write`] and restored by
+    /// [`Self::read`]. It only changes [`Self::transcript_repr`], and thus
+    /// the proofs this key accepts, if `bind_to_transcript` is set.
+    pub fn with_metadata(mut self, metadata: Vec<u8>, bind_to_transcript: bool) -> Self
+    where
+        C::ScalarExt: FromUniformBytes<64>,
+    {
+        self.metadata = Some(metadata);
+        self.metadata_bound = bind_to_transcript;
+        self.transcript_repr = self.compute_transcript_repr();
+        self
+    }
+
+    /// Returns the metadata attached with [`Self::with_metadata`], if any.
+    pub fn metadata(&self) -> Option<&[u8]> {
+        self.metadata.as_deref()
+    }
+
+    /// Returns whether the attached metadata is part of
+    /// [`Self::transcript_repr`].
+    pub fn metadata_bound_to_transcript(&self) -> bool {
+        self.metadata.is_some() && self.metadata_bound
     }
 
     /// Hashes a verification key into a transcript.
@@ -229,10 +428,53 @@ impl<C: CurveAffine> VerifyingKey<C> {
         Ok(())
     }
 
+    /// Returns a 32-byte fingerprint of this verification key.
+    ///
+    /// The fingerprint is a BLAKE2b hash, under a versioned personalization, of
+    /// the pinned representation of the key: the domain, the fixed and
+    /// permutation commitments, and the pinned constraint system. If metadata
+    /// is attached with [`Self::with_metadata`], it is hashed after the pinned
+    /// representation, together with whether it is bound to the transcript,
+    /// so keys for differently parameterized circuits have different
+    /// fingerprints; keys without metadata are not affected. Unlike a hash of
+    /// [`Self::to_bytes`], it does not depend on the [`SerdeFormat`] the key
+    /// was stored with. Any change to what is pinned comes with a new
+    /// personalization, so fingerprints only ever change together with the
+    /// version.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Blake2bParams::new()
+            .hash_length(32)
+            .personal(b"Halo2-VK-Fprt-v4")
+            .to_state();
+
+        let s = format!("{:?}", self.pinned());
+
+        hasher.update(&(s.len() as u64).to_le_bytes());
+        hasher.update(s.as_bytes());
+
+        if let Some(metadata) = &self.metadata {
+            hasher.update(&[self.metadata_bound as u8]);
+            hasher.update(&(metadata.len() as u64).to_le_bytes());
+            hasher.update(metadata);
+        }
+
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(hasher.finalize().as_bytes());
+        fingerprint
+    }
+
+    /// Returns the [content hash](ConstraintSystem::content_hash) of the
+    /// constraint system of this key. Its selectors are converted into fixed
+    /// columns, so it differs from the hash of the constraint system the circuit configures.
+    pub fn content_hash(&self) -> [u8; 32] {
+        self.cs.content_hash()
+    }
+
     /// Obtains a pinned representation of this verification key that contains
     /// the minimal information necessary to reconstruct the verification key.
     pub fn pinned(&self) -> PinnedVerificationKey<'_, C> {
         PinnedVerificationKey {
+            version: self.cs.pinned_version,
             base_modulus: C::Base::MODULUS,
             scalar_modulus: C::Scalar::MODULUS,
             domain: self.domain.pinned(),
@@ -247,11 +489,90 @@ impl<C: CurveAffine> VerifyingKey<C> {
         &self.fixed_commitments
     }
 
+    /// Returns whether the selectors of the circuit were compressed, or
+    /// converted into fixed columns one by one; see [`keygen_vk_custom`].
+    pub fn compress_selectors(&self) -> bool {
+        self.compress_selectors
+    }
+
+    /// Returns how the selectors of the circuit were compressed into fixed
+    /// columns, for tools that need to interpret the contents of those columns.
+    pub fn selector_compression_map(&self) -> SelectorCompressionMap<C::Scalar> {
+        let combinations: Vec<usize> = self
+            .cs
+            .selector_combinations
+            .iter()
+            .map(|(index, _)| *index)
+            .collect();
+        let selectors = self
+            .cs
+            .selector_map
+            .iter()
+            .zip(self.cs.selector_replacements.iter())
+            .zip(self.selectors.iter())
+            .enumerate()
+            .map(
+                |(selector, ((column, expression), activations))| CompressedSelector {
+                    selector,
+                    column: *column,
+                    expression: expression.clone(),
+                    activations: activations
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(row, enabled)| enabled.then_some(row))
+                        .collect(),
+                    multiplier: self
+                        .cs
+                        .selector_values
+                        .get(selector)
+                        .copied()
+                        .flatten()
+                        .map(|(_, value)| value),
+                    shared_with: self
+                        .cs
+                        .selector_map
+                        .iter()
+                        .enumerate()
+                        .filter(|(other, other_column)| {
+                            *other != selector
+                                && *other_column == column
+                                && !combinations.contains(other)
+                        })
+                        .map(|(other, _)| other)
+                        .collect(),
+                },
+            )
+            .collect();
+
+        SelectorCompressionMap { selectors }
+    }
+
     /// Returns `VerifyingKey` of permutation
     pub fn permutation(&self) -> &permutation::VerifyingKey<C> {
         &self.permutation
     }
 
+    /// Returns the commitment to the permutation of each column of the
+    /// permutation argument, along with the column, in the order of
+    /// [`ConstraintSystem::permutation_columns`].
+    pub fn permutation_commitments(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (Column<circuit::Any>, &C)> {
+        self.cs
+            .permutation
+            .columns
+            .iter()
+            .copied()
+            .zip(self.permutation.commitments().iter())
+    }
+
+    /// Returns the number of columns checked by each grand product of the
+    /// permutation argument; see
+    /// [`ConstraintSystem::permutation_chunk_size`].
+    pub fn permutation_chunk_size(&self) -> usize {
+        self.cs_degree - 2
+    }
+
     /// Returns `ConstraintSystem`
     pub fn cs(&self) -> &ConstraintSystem<C::Scalar> {
         &self.cs
@@ -263,11 +584,61 @@ impl<C: CurveAffine> VerifyingKey<C> {
     }
 }
 
+/// Describes where a single selector ended up after selector compression.
+#[derive(Clone, Debug)]
+pub struct CompressedSelector<F: Field> {
+    /// The index of the selector, as returned by [`Selector::index`].
+    pub selector: usize,
+    /// The fixed column the selector was combined into.
+    pub column: Column<Fixed>,
+    /// The expression over `column` that replaced the selector in gates,
+    /// lookups and shuffles.
+    pub expression: Expression<F>,
+    /// The rows at which the selector is enabled.
+    pub activations: Vec<usize>,
+    /// The value assigned to `column` on the rows at which the selector is
+    /// enabled, which `expression` tells apart from the values of the other
+    /// selectors sharing the column. `None` for a selector standing for a
+    /// combination of others, whose column is not assigned a value of its own.
+    pub multiplier: Option<F>,
+    /// The indices of the other selectors compressed into `column`, which is
+    /// the selector's own if this is empty, as for the selectors allocated
+    /// with [`ConstraintSystem::uncompressed_selector`]. The selectors
+    /// standing for a combination of others, which are replaced with an
+    /// expression over the columns of those, are left out.
+    pub shared_with: Vec<usize>,
+}
+
+/// The mapping from the selectors of a circuit to the fixed columns they were
+/// compressed into, as returned by [`VerifyingKey::selector_compression_map`].
+#[derive(Clone, Debug)]
+pub struct SelectorCompressionMap<F: Field> {
+    selectors: Vec<CompressedSelector<F>>,
+}
+
+impl<F: Field> SelectorCompressionMap<F> {
+    /// Returns how `selector` was compressed.
+    pub fn get(&self, selector: &Selector) -> Option<&CompressedSelector<F>> {
+        self.selectors.get(selector.index())
+    }
+
+    /// Iterates over all selectors, in the order they were allocated.
+    pub fn iter(&self) -> impl Iterator<Item = &CompressedSelector<F>> {
+        self.selectors.iter()
+    }
+}
+
 /// Minimal representation of a verification key that can be used to identify
 /// its active contents.
+///
+/// Its `Debug` output is what [`VerifyingKey::transcript_repr`] and
+/// [`VerifyingKey::fingerprint`] hash. Keys of circuits that opt into a
+/// [`PinnedVersion`] other than [`PinnedVersion::V0`] start it with the
+/// version, which is bumped whenever what is pinned changes; see
+/// [`ConstraintSystem::set_pinned_version`].
 #[allow(dead_code)]
-#[derive(Debug)]
 pub struct PinnedVerificationKey<'a, C: CurveAffine> {
+    version: PinnedVersion,
     base_modulus: &'static str,
     scalar_modulus: &'static str,
     domain: PinnedEvaluationDomain<'a, C::Scalar>,
@@ -275,19 +646,69 @@ pub struct PinnedVerificationKey<'a, C: CurveAffine> {
     fixed_commitments: &'a Vec<C>,
     permutation: &'a permutation::VerifyingKey<C>,
 }
+
+impl<'a, C: CurveAffine> std::fmt::Debug for PinnedVerificationKey<'a, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug_struct = f.debug_struct("PinnedVerificationKey");
+        // Only show the version if it isn't the legacy one, so that existing
+        // keys keep their pinned representation.
+        match self.version {
+            PinnedVersion::V0 => {}
+            PinnedVersion::V1 => {
+                debug_struct.field("version", &1u32);
+            }
+        }
+        debug_struct
+            .field("base_modulus", &self.base_modulus)
+            .field("scalar_modulus", &self.scalar_modulus)
+            .field("domain", &self.domain)
+            .field("cs", &self.cs)
+            .field("fixed_commitments", self.fixed_commitments)
+            .field("permutation", self.permutation)
+            .finish()
+    }
+}
+
+/// Helper polynomials of live proving keys, keyed by scalar field, domain
+/// sizes and number of blinding factors. Only weak references are kept, so the
+/// polynomials are freed along with the last key using them, and the entries
+/// of freed polynomials are removed whenever an entry is added.
+static LAGRANGE_HELPERS: Mutex<Vec<(HelpersKey, [Weak<dyn Any + Send + Sync>; 3])>> =
+    Mutex::new(Vec::new());
+
+/// The scalar field, `k`, extended `k` and number of blinding factors of an
+/// entry of [`LAGRANGE_HELPERS`].
+type HelpersKey = (TypeId, u32, u32, usize);
+
 /// This is a proving key which allows for the creation of proofs for a
 /// particular circuit.
 #[derive(Clone, Debug)]
 pub struct ProvingKey<C: CurveAffine> {
     vk: VerifyingKey<C>,
-    l0: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
-    l_last: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
-    l_active_row: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
     fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
     fixed_polys: Vec<Polynomial<C::Scalar, Coeff>>,
-    fixed_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
     permutation: permutation::ProvingKey<C>,
-    ev: Evaluator<C>,
+    // The cosets only depend on the fields above, so they are not serialized
+    // and can be dropped with `clear_cosets`.
+    cosets: Option<KeyCosets<C::Scalar>>,
+    // The evaluator only depends on the constraint system, so keys built
+    // together by `keygen_pk_batch` share it. It is built by the first proof
+    // that needs it, as the interpreted evaluation strategy does not.
+    ev: Arc<Mutex<Option<Arc<Evaluator<C>>>>>,
+}
+
+/// The polynomials of a [`ProvingKey`] that the quotient polynomial is
+/// computed from, evaluated over the extended domain.
+#[derive(Clone, Debug)]
+pub(crate) struct KeyCosets<F> {
+    // The helper polynomials only depend on the domain and the number of
+    // blinding factors, so they are shared with any other live key with the
+    // same parameters.
+    pub(crate) l0: Arc<Polynomial<F, ExtendedLagrangeCoeff>>,
+    pub(crate) l_last: Arc<Polynomial<F, ExtendedLagrangeCoeff>>,
+    pub(crate) l_active_row: Arc<Polynomial<F, ExtendedLagrangeCoeff>>,
+    pub(crate) fixed: Vec<Polynomial<F, ExtendedLagrangeCoeff>>,
+    pub(crate) permutation: Vec<Polynomial<F, ExtendedLagrangeCoeff>>,
 }
 
 impl<C: CurveAffine> ProvingKey<C>
@@ -299,19 +720,402 @@ where
         &self.vk
     }
 
+    /// The version of the layout written by [`Self::write`]. Version 1 leaves
+    /// out `l0`, `l_last`, `l_active_row` and the cosets of the fixed columns,
+    /// which are computed again when the key is read.
+    pub const FORMAT_VERSION: u8 = 1;
+
     /// Gets the total number of bytes in the serialization of `self`
     fn bytes_length(&self) -> usize {
-        let scalar_len = C::Scalar::default().to_repr().as_ref().len();
-        self.vk.bytes_length()
-            + 12
-            + scalar_len * (self.l0.len() + self.l_last.len() + self.l_active_row.len())
+        1 + self.vk.bytes_length()
             + polynomial_slice_byte_length(&self.fixed_values)
             + polynomial_slice_byte_length(&self.fixed_polys)
-            + polynomial_slice_byte_length(&self.fixed_cosets)
             + self.permutation.bytes_length()
     }
 }
 
+impl<C: CurveAffine> ProvingKey<C> {
+    /// Constructs a `ProvingKey` from its verifying key, the Lagrange-basis
+    /// values of the fixed columns (including the columns produced by selector
+    /// compression) and the proving key of the permutation argument.
+    ///
+    /// All remaining parts of the proving key are derived from these, so
+    /// callers that cache them can skip synthesizing the circuit again. The
+    /// parts are not checked against the commitments in `vk`.
+    pub fn from_parts(
+        vk: VerifyingKey<C>,
+        fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        permutation: permutation::ProvingKey<C>,
+    ) -> Self {
+        Self::from_parts_with_progress(vk, fixed_values, permutation, &CpuEngine, |_, _| {})
+    }
+
+    /// Same as [`Self::from_parts`], performing the FFTs of the fixed columns
+    /// with `engine` and calling `report` with the stage being worked on and
+    /// the fraction of that stage completed.
+    pub(crate) fn from_parts_with_progress(
+        vk: VerifyingKey<C>,
+        fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        permutation: permutation::ProvingKey<C>,
+        engine: &dyn Engine<C>,
+        mut report: impl FnMut(KeygenStage, f32),
+    ) -> Self {
+        report(KeygenStage::LagrangeToCoeff, 0.0);
+        let fixed_polys = Self::fixed_polys(engine, &vk.domain, &fixed_values);
+        let helpers = Self::lagrange_helpers(&vk.domain, vk.cs.blinding_factors());
+        let cosets = Self::extend_to_cosets(
+            engine,
+            &vk.domain,
+            helpers,
+            &fixed_polys,
+            &permutation,
+            |_| None,
+            |_| None,
+        );
+        report(KeygenStage::LagrangeToCoeff, 1.0);
+
+        ProvingKey {
+            vk,
+            fixed_values,
+            fixed_polys,
+            permutation,
+            cosets: Some(cosets),
+            ev: Arc::default(),
+        }
+    }
+
+    /// Same as [`Self::from_parts`], but sharing the helper polynomials and the
+    /// evaluator of `other`, whose verifying key must have the same domain and
+    /// constraint system as `vk`.
+    pub(crate) fn from_parts_sharing(
+        vk: VerifyingKey<C>,
+        fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        permutation: permutation::ProvingKey<C>,
+        other: &Self,
+    ) -> Self {
+        let fixed_polys = Self::fixed_polys(&CpuEngine, &vk.domain, &fixed_values);
+        let cosets = Self::extend_to_cosets(
+            &CpuEngine,
+            &vk.domain,
+            other.lagrange_helpers_of(),
+            &fixed_polys,
+            &permutation,
+            |_| None,
+            |_| None,
+        );
+
+        ProvingKey {
+            vk,
+            fixed_values,
+            fixed_polys,
+            permutation,
+            cosets: Some(cosets),
+            ev: other.ev.clone(),
+        }
+    }
+
+    /// Returns the evaluator of the quotient polynomial, building it if no
+    /// proof with this key or the keys sharing it has needed it yet.
+    pub(crate) fn evaluator(&self) -> Arc<Evaluator<C>> {
+        self.ev
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(Evaluator::new(&self.vk.cs)))
+            .clone()
+    }
+
+    /// Returns the helper polynomials of this key, computing them again if
+    /// its cosets were cleared.
+    fn lagrange_helpers_of(&self) -> [Arc<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>; 3] {
+        match &self.cosets {
+            Some(cosets) => [
+                cosets.l0.clone(),
+                cosets.l_last.clone(),
+                cosets.l_active_row.clone(),
+            ],
+            None => Self::lagrange_helpers(&self.vk.domain, self.vk.cs.blinding_factors()),
+        }
+    }
+
+    /// Returns `l_0`, `l_last` and `l_active_row` for `domain` and
+    /// `blinding_factors`, reusing the ones of a live proving key if there is
+    /// one with the same parameters.
+    fn lagrange_helpers(
+        domain: &EvaluationDomain<C::Scalar>,
+        blinding_factors: usize,
+    ) -> [Arc<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>; 3] {
+        fn upgrade<F: 'static + Send + Sync>(
+            helper: &Weak<dyn Any + Send + Sync>,
+        ) -> Option<Arc<Polynomial<F, ExtendedLagrangeCoeff>>> {
+            helper.upgrade()?.downcast().ok()
+        }
+
+        let key = (
+            TypeId::of::<C::Scalar>(),
+            domain.k(),
+            domain.extended_k(),
+            blinding_factors,
+        );
+        let cached = LAGRANGE_HELPERS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, [l0, l_last, l_active_row])| {
+                Some([upgrade(l0)?, upgrade(l_last)?, upgrade(l_active_row)?])
+            });
+        if let Some(helpers) = cached {
+            return helpers;
+        }
+
+        // The lock is not held while computing, so that keygen for keys with
+        // other parameters can proceed in the meantime.
+        let helpers = Self::compute_lagrange_helpers(domain, blinding_factors);
+        let weak: [Weak<dyn Any + Send + Sync>; 3] = [
+            Arc::downgrade(&helpers[0]),
+            Arc::downgrade(&helpers[1]),
+            Arc::downgrade(&helpers[2]),
+        ];
+        let mut cache = LAGRANGE_HELPERS.lock().unwrap_or_else(|e| e.into_inner());
+        cache.retain(|(k, cached)| *k != key && cached.iter().all(|w| w.strong_count() > 0));
+        cache.push((key, weak));
+        helpers
+    }
+
+    fn compute_lagrange_helpers(
+        domain: &EvaluationDomain<C::Scalar>,
+        blinding_factors: usize,
+    ) -> [Arc<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>; 3] {
+        let n = 1usize << domain.k();
+
+        // Compute l_0(X)
+        // TODO: this can be done more efficiently
+        let mut l0 = domain.empty_lagrange();
+        l0[0] = C::Scalar::ONE;
+        let l0 = domain.lagrange_to_coeff(l0);
+        let l0 = domain.coeff_to_extended(l0);
+
+        // Compute l_blind(X) which evaluates to 1 for each blinding factor row
+        // and 0 otherwise over the domain.
+        let mut l_blind = domain.empty_lagrange();
+        for evaluation in l_blind[..].iter_mut().rev().take(blinding_factors) {
+            *evaluation = C::Scalar::ONE;
+        }
+        let l_blind = domain.lagrange_to_coeff(l_blind);
+        let l_blind = domain.coeff_to_extended(l_blind);
+
+        // Compute l_last(X) which evaluates to 1 on the first inactive row (just
+        // before the blinding factors) and 0 otherwise over the domain
+        let mut l_last = domain.empty_lagrange();
+        l_last[n - blinding_factors - 1] = C::Scalar::ONE;
+        let l_last = domain.lagrange_to_coeff(l_last);
+        let l_last = domain.coeff_to_extended(l_last);
+
+        // Compute l_active_row(X)
+        let one = C::Scalar::ONE;
+        let mut l_active_row = domain.empty_extended();
+        parallelize(&mut l_active_row, |values, start| {
+            for (i, value) in values.iter_mut().enumerate() {
+                let idx = i + start;
+                *value = one - (l_last[idx] + l_blind[idx]);
+            }
+        });
+
+        [Arc::new(l0), Arc::new(l_last), Arc::new(l_active_row)]
+    }
+
+    /// Same as [`Self::from_parts_sharing`], additionally reusing the
+    /// coefficient forms and cosets of `other` for the fixed and permutation
+    /// columns whose values are unchanged.
+    pub(crate) fn from_parts_reusing(
+        vk: VerifyingKey<C>,
+        fixed_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        permutation: permutation::ProvingKey<C>,
+        other: &Self,
+    ) -> Self {
+        let domain = &vk.domain;
+        let fixed_unchanged = |i: usize| {
+            other
+                .fixed_values
+                .get(i)
+                .map_or(false, |old| old[..] == fixed_values[i][..])
+        };
+        // Only the changed columns are converted, all together
+        let mut changed_polys = domain
+            .batch_lagrange_to_coeff_with_engine(
+                &CpuEngine,
+                (0..fixed_values.len())
+                    .filter(|&i| !fixed_unchanged(i))
+                    .map(|i| fixed_values[i].clone())
+                    .collect(),
+            )
+            .into_iter();
+        let fixed_polys: Vec<_> = (0..fixed_values.len())
+            .map(|i| {
+                if fixed_unchanged(i) {
+                    other.fixed_polys[i].clone()
+                } else {
+                    changed_polys.next().unwrap()
+                }
+            })
+            .collect();
+        let other_cosets = other.cosets.as_ref();
+        let cosets = Self::extend_to_cosets(
+            &CpuEngine,
+            domain,
+            other.lagrange_helpers_of(),
+            &fixed_polys,
+            &permutation,
+            |i| {
+                other_cosets
+                    .filter(|_| fixed_unchanged(i))
+                    .map(|cosets| cosets.fixed[i].clone())
+            },
+            |i| {
+                other_cosets
+                    .filter(|_| permutation.unchanged_from(&other.permutation, i))
+                    .map(|cosets| cosets.permutation[i].clone())
+            },
+        );
+
+        ProvingKey {
+            vk,
+            fixed_values,
+            fixed_polys,
+            permutation,
+            cosets: Some(cosets),
+            ev: other.ev.clone(),
+        }
+    }
+
+    /// Converts the fixed columns into coefficient form.
+    fn fixed_polys(
+        engine: &dyn Engine<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        fixed_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+    ) -> Vec<Polynomial<C::Scalar, Coeff>> {
+        domain.batch_lagrange_to_coeff_with_engine(engine, fixed_values.to_vec())
+    }
+
+    /// Evaluates the fixed and permutation columns over the extended domain,
+    /// except for those that `reuse_fixed` and `reuse_permutation` return a
+    /// coset for.
+    fn extend_to_cosets(
+        engine: &dyn Engine<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        [l0, l_last, l_active_row]: [Arc<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>; 3],
+        fixed_polys: &[Polynomial<C::Scalar, Coeff>],
+        permutation: &permutation::ProvingKey<C>,
+        reuse_fixed: impl Fn(usize) -> Option<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
+        reuse_permutation: impl Fn(usize) -> Option<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
+    ) -> KeyCosets<C::Scalar> {
+        type Coset<F> = Polynomial<F, ExtendedLagrangeCoeff>;
+        let extend = |polys: &[Polynomial<C::Scalar, Coeff>],
+                      reuse: &dyn Fn(usize) -> Option<Coset<C::Scalar>>| {
+            polys
+                .iter()
+                .enumerate()
+                .map(|(i, poly)| {
+                    reuse(i).unwrap_or_else(|| {
+                        domain.coeff_to_extended_with_engine(engine, poly.clone())
+                    })
+                })
+                .collect()
+        };
+
+        KeyCosets {
+            l0,
+            l_last,
+            l_active_row,
+            fixed: extend(fixed_polys, &reuse_fixed),
+            permutation: extend(permutation.polys(), &reuse_permutation),
+        }
+    }
+
+    /// Returns the cosets the quotient polynomial is computed from: those of
+    /// this key, or, if they were cleared, newly computed ones with `engine`.
+    pub(crate) fn cosets_with_engine(
+        &self,
+        engine: &dyn Engine<C>,
+    ) -> Cow<'_, KeyCosets<C::Scalar>> {
+        match &self.cosets {
+            Some(cosets) => Cow::Borrowed(cosets),
+            None => Cow::Owned(self.recompute_cosets(engine)),
+        }
+    }
+
+    fn recompute_cosets(&self, engine: &dyn Engine<C>) -> KeyCosets<C::Scalar> {
+        Self::extend_to_cosets(
+            engine,
+            &self.vk.domain,
+            self.lagrange_helpers_of(),
+            &self.fixed_polys,
+            &self.permutation,
+            |_| None,
+            |_| None,
+        )
+    }
+
+    /// Drops the fixed and permutation columns of this key evaluated over the
+    /// extended domain, along with the helper polynomials, to free memory.
+    /// Each proof created with the key then evaluates them again, which takes
+    /// as many FFTs as there are fixed and permutation columns; see
+    /// [`Self::compute_cosets`].
+    pub fn clear_cosets(&mut self) {
+        self.cosets = None;
+    }
+
+    /// Evaluates the fixed and permutation columns of this key over the
+    /// extended domain and keeps them for subsequent proofs, as keygen does,
+    /// unless they are already kept.
+    pub fn compute_cosets(&mut self) {
+        if self.cosets.is_none() {
+            self.cosets = Some(self.recompute_cosets(&CpuEngine));
+        }
+    }
+
+    /// Returns whether this key keeps its extended-domain cosets.
+    pub fn has_cosets(&self) -> bool {
+        self.cosets.is_some()
+    }
+
+    /// Returns the Lagrange-basis values of the fixed columns.
+    pub fn fixed_values(&self) -> &[Polynomial<C::Scalar, LagrangeCoeff>] {
+        &self.fixed_values
+    }
+
+    /// Returns the proving key of the permutation argument.
+    pub fn permutation(&self) -> &permutation::ProvingKey<C> {
+        &self.permutation
+    }
+
+    /// Moves the fixed and permutation columns of this key, in every basis
+    /// including the cosets it keeps,
+    /// into files created in `dir` and mapped into memory, so that the
+    /// operating system can page them out between the stages of a proof that
+    /// read them. The files are removed when the key is dropped.
+    ///
+    /// Proving with the key is slower but gives the same proofs. See
+    /// [`ProverOptions::disk_offload`] to also offload the witness.
+    ///
+    /// Only available with the `disk-offload` feature.
+    #[cfg(feature = "disk-offload")]
+    pub fn offload_to_disk(&mut self, dir: &Path) -> io::Result<()> {
+        for poly in &mut self.fixed_values {
+            poly.offload(dir)?;
+        }
+        for poly in &mut self.fixed_polys {
+            poly.offload(dir)?;
+        }
+        if let Some(cosets) = &mut self.cosets {
+            for poly in cosets.fixed.iter_mut().chain(cosets.permutation.iter_mut()) {
+                poly.offload(dir)?;
+            }
+        }
+        self.permutation.offload(dir)
+    }
+}
+
 impl<C: SerdeCurveAffine> ProvingKey<C>
 where
     C::Scalar: SerdePrimeField + FromUniformBytes<64>,
@@ -326,14 +1130,14 @@ where
     /// Writes a field element into raw bytes in its internal Montgomery representation,
     /// WITHOUT performing the expensive Montgomery reduction.
     /// Does so by first writing the verifying key and then serializing the rest of the data (in the form of field polynomials)
+    ///
+    /// The key is preceded by [`Self::FORMAT_VERSION`]. The cosets kept by the
+    /// key are not written, as [`Self::read`] computes them again.
     pub fn write<W: io::Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        writer.write_all(&[Self::FORMAT_VERSION])?;
         self.vk.write(writer, format)?;
-        self.l0.write(writer, format)?;
-        self.l_last.write(writer, format)?;
-        self.l_active_row.write(writer, format)?;
         write_polynomial_slice(&self.fixed_values, writer, format)?;
         write_polynomial_slice(&self.fixed_polys, writer, format)?;
-        write_polynomial_slice(&self.fixed_cosets, writer, format)?;
         self.permutation.write(writer, format)?;
         Ok(())
     }
@@ -349,36 +1153,70 @@ where
     /// Checks that field elements are less than modulus, and then checks that the point is on the curve.
     /// - `RawBytesUnchecked`: Reads an uncompressed curve element with coordinates in Montgomery form;
     /// does not perform any checks
+    ///
+    /// Keys written before the layout was versioned are also read. The helper
+    /// polynomials and cosets they store are skipped and recomputed.
     pub fn read<R: io::Read, ConcreteCircuit: Circuit<C::Scalar>>(
         reader: &mut R,
         format: SerdeFormat,
         #[cfg(feature = "circuit-params")] params: ConcreteCircuit::Params,
     ) -> io::Result<Self> {
-        let vk = VerifyingKey::<C>::read::<R, ConcreteCircuit>(
-            reader,
-            format,
-            #[cfg(feature = "circuit-params")]
-            params,
-        )?;
-        let l0 = Polynomial::read(reader, format)?;
-        let l_last = Polynomial::read(reader, format)?;
-        let l_active_row = Polynomial::read(reader, format)?;
+        // Unversioned keys start with an unversioned verifying key, whose
+        // first byte is always zero.
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        let (vk, legacy) = match version[0] {
+            0 => {
+                let vk = VerifyingKey::<C>::read::<_, ConcreteCircuit>(
+                    &mut io::Read::chain(&version[..], &mut *reader),
+                    format,
+                    #[cfg(feature = "circuit-params")]
+                    params,
+                )?;
+                (vk, true)
+            }
+            version if version == Self::FORMAT_VERSION => {
+                let vk = VerifyingKey::<C>::read::<R, ConcreteCircuit>(
+                    reader,
+                    format,
+                    #[cfg(feature = "circuit-params")]
+                    params,
+                )?;
+                (vk, false)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unsupported proving key format version",
+                ))
+            }
+        };
+        if legacy {
+            // l0, l_last and l_active_row
+            for _ in 0..3 {
+                skip_polynomial::<R, C::Scalar>(reader, format)?;
+            }
+        }
         let fixed_values = read_polynomial_vec(reader, format)?;
         let fixed_polys = read_polynomial_vec(reader, format)?;
-        let fixed_cosets = read_polynomial_vec(reader, format)?;
-        let permutation = permutation::ProvingKey::read(reader, format)?;
-        let ev = Evaluator::new(vk.cs());
-        Ok(Self {
+        let permutation = if legacy {
+            // The cosets of the fixed columns, then the permutation argument
+            // with its own cosets.
+            skip_polynomial_vec::<R, C::Scalar>(reader, format)?;
+            permutation::ProvingKey::read_legacy(reader, format)?
+        } else {
+            permutation::ProvingKey::read(reader, format)?
+        };
+        let mut pk = Self {
             vk,
-            l0,
-            l_last,
-            l_active_row,
             fixed_values,
             fixed_polys,
-            fixed_cosets,
             permutation,
-            ev,
-        })
+            cosets: None,
+            ev: Arc::default(),
+        };
+        pk.compute_cosets();
+        Ok(pk)
     }
 
     /// Writes a proving key to a vector of bytes using [`Self::write`].
@@ -429,3 +1267,628 @@ type ChallengeY<F> = ChallengeScalar<F, Y>;
 #[derive(Clone, Copy, Debug)]
 struct X;
 type ChallengeX<F> = ChallengeScalar<F, X>;
+
+#[test]
+fn test_vk_fingerprint() {
+    use halo2curves::bn256::G1Affine;
+    use test_fixtures::FixedCircuit;
+
+    let params = test_fixtures::params(3);
+    let vk = keygen_vk(&params, &FixedCircuit(1)).expect("keygen_vk should not fail");
+    let fingerprint = vk.fingerprint();
+
+    // The fingerprint survives serialization in every format.
+    for format in [
+        SerdeFormat::Processed,
+        SerdeFormat::RawBytes,
+        SerdeFormat::RawBytesUnchecked,
+    ] {
+        let read = VerifyingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+            &vk.to_bytes(format),
+            format,
+            #[cfg(feature = "circuit-params")]
+            (),
+        )
+        .expect("reading vk should not fail");
+        assert_eq!(read.fingerprint(), fingerprint);
+    }
+
+    // It is deterministic for fixed parameters and sensitive to the circuit.
+    let again = keygen_vk(&params, &FixedCircuit(1)).expect("keygen_vk should not fail");
+    assert_eq!(again.fingerprint(), fingerprint);
+    let other = keygen_vk(&params, &FixedCircuit(2)).expect("keygen_vk should not fail");
+    assert_ne!(other.fingerprint(), fingerprint);
+}
+
+#[test]
+fn test_vk_fingerprint_vector() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        poly::{ipa::commitment::ParamsIPA, Rotation},
+    };
+    use halo2curves::pasta::{EqAffine, Fp};
+
+    /// A circuit without fixed columns or a permutation, so that its pinned
+    /// verification key does not depend on the parameters.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            meta.create_gate("boolean", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![a.clone() * a.clone() - a]
+            });
+        }
+
+        fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    let params = ParamsIPA::<EqAffine>::new(5);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+
+    let query = "Advice { query_index: 0, column_index: 0, rotation: Rotation(0) }";
+    assert_eq!(
+        format!("{:?}", vk.pinned()),
+        format!(
+            "PinnedVerificationKey {{ \
+                base_modulus: \"0x40000000000000000000000000000000224698fc0994a8dd8c46eb2100000001\", \
+                scalar_modulus: \"0x40000000000000000000000000000000224698fc094cf91b992d30ed00000001\", \
+                domain: PinnedEvaluationDomain {{ \
+                    k: 5, \
+                    extended_k: 6, \
+                    omega: 0x0cc3380dc616f2e1daf29ad1560833ed3baea3393eceb7bc8fa36376929b78cc \
+                }}, \
+                cs: PinnedConstraintSystem {{ \
+                    num_fixed_columns: 0, \
+                    num_advice_columns: 1, \
+                    num_instance_columns: 0, \
+                    num_selectors: 0, \
+                    gates: [Sum(Product({query}, {query}), Negated({query}))], \
+                    advice_queries: [(Column {{ index: 0, column_type: Advice }}, Rotation(0))], \
+                    instance_queries: [], \
+                    fixed_queries: [], \
+                    permutation: Argument {{ columns: [] }}, \
+                    lookups: [], \
+                    constants: [], \
+                    minimum_degree: None \
+                }}, \
+                fixed_commitments: [], \
+                permutation: VerifyingKey {{ commitments: [] }} \
+            }}"
+        )
+    );
+
+    // Changing how fingerprints are computed has to come with a new
+    // personalization and new vectors.
+    let hex = |fingerprint: [u8; 32]| -> String {
+        fingerprint
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    };
+    assert_eq!(
+        hex(vk.fingerprint()),
+        "54ba311f126b2ad0a6d8d5c07c756608c45b6a72f4205e3fc79425e309a88918"
+    );
+    assert_eq!(
+        hex(vk.with_metadata(b"rows=8".to_vec(), true).fingerprint()),
+        "712634b843decda4d46630692ba0b72190b4a5d2a7d4bdc2a2cd25ff4fdff219"
+    );
+}
+
+#[test]
+fn test_selector_compression_map() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        poly::Rotation,
+    };
+
+    #[derive(Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        s_zero: Selector,
+        s_one: Selector,
+    }
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = meta.advice_column();
+            let s_zero = meta.selector();
+            let s_one = meta.selector();
+            meta.create_gate("a is zero", |meta| {
+                let s = meta.query_selector(s_zero);
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![s * a]
+            });
+            meta.create_gate("a is one", |meta| {
+                let s = meta.query_selector(s_one);
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![s * (a - Expression::Constant(F::ONE))]
+            });
+            MyConfig { a, s_zero, s_one }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    config.s_zero.enable(&mut region, 0)?;
+                    region.assign_advice(|| "zero", config.a, 0, || Value::known(F::ZERO))?;
+                    config.s_one.enable(&mut region, 1)?;
+                    region.assign_advice(|| "one", config.a, 1, || Value::known(F::ONE))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let map = vk.selector_compression_map();
+
+    // The two selectors are never enabled on the same row, so they share a
+    // single fixed column, appended after the circuit's own fixed columns.
+    let selectors: Vec<_> = map.iter().collect();
+    assert_eq!(selectors.len(), 2);
+    assert_eq!(selectors[0].column, selectors[1].column);
+    assert_eq!(selectors[0].column.index(), 0);
+    assert_eq!(selectors[0].activations, vec![0]);
+    assert_eq!(selectors[1].activations, vec![1]);
+    assert_ne!(selectors[0].expression, selectors[1].expression);
+    // They are told apart by the values 1 and 2 of that column.
+    let multipliers: Vec<_> = selectors
+        .iter()
+        .map(|selector| selector.multiplier.unwrap())
+        .collect();
+    assert!(multipliers.contains(&halo2curves::bn256::Fr::ONE));
+    assert!(multipliers.contains(&halo2curves::bn256::Fr::from(2)));
+}
+
+#[test]
+fn test_vk_metadata() {
+    use halo2curves::bn256::G1Affine;
+    use test_fixtures::FixedCircuit;
+
+    let read = |vk: &VerifyingKey<G1Affine>, format| {
+        VerifyingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+            &vk.to_bytes(format),
+            format,
+            #[cfg(feature = "circuit-params")]
+            (),
+        )
+        .expect("reading vk should not fail")
+    };
+
+    let params = test_fixtures::params(3);
+    let vk = keygen_vk(&params, &FixedCircuit(1)).expect("keygen_vk should not fail");
+    assert_eq!(vk.metadata(), None);
+    assert_eq!(read(&vk, SerdeFormat::RawBytes).metadata(), None);
+
+    // Keys written before the layout was versioned, without the version, the
+    // selector compression flag and the metadata tag, are still read.
+    let bytes = vk.to_bytes(SerdeFormat::RawBytes);
+    assert_eq!(bytes[0], VerifyingKey::<G1Affine>::FORMAT_VERSION);
+    let unversioned = [&bytes[1..5], &bytes[6..bytes.len() - 1]].concat();
+    let restored = VerifyingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+        &unversioned,
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .expect("reading an unversioned vk should not fail");
+    assert_eq!(restored.to_bytes(SerdeFormat::RawBytes), bytes);
+
+    // Unbound metadata is serialized but leaves the transcript alone.
+    let tagged = vk.clone().with_metadata(b"rows=8".to_vec(), false);
+    assert_eq!(tagged.metadata(), Some(&b"rows=8"[..]));
+    assert!(!tagged.metadata_bound_to_transcript());
+    assert_eq!(tagged.transcript_repr(), vk.transcript_repr());
+    assert_ne!(tagged.fingerprint(), vk.fingerprint());
+    for format in [
+        SerdeFormat::Processed,
+        SerdeFormat::RawBytes,
+        SerdeFormat::RawBytesUnchecked,
+    ] {
+        let bytes = tagged.to_bytes(format);
+        assert_eq!(bytes.len(), tagged.bytes_length());
+        let restored = read(&tagged, format);
+        assert_eq!(restored.metadata(), Some(&b"rows=8"[..]));
+        assert_eq!(restored.transcript_repr(), vk.transcript_repr());
+    }
+
+    // Bound metadata changes the transcript representative, and survives a
+    // round trip through the proving key.
+    let bound = vk.with_metadata(b"rows=8".to_vec(), true);
+    assert!(bound.metadata_bound_to_transcript());
+    assert_ne!(bound.transcript_repr(), tagged.transcript_repr());
+    assert_ne!(bound.fingerprint(), tagged.fingerprint());
+    let restored = read(&bound, SerdeFormat::RawBytes);
+    assert!(restored.metadata_bound_to_transcript());
+    assert_eq!(restored.transcript_repr(), bound.transcript_repr());
+
+    // A corrupted metadata length is reported rather than allocated.
+    let mut bytes = bound.to_bytes(SerdeFormat::RawBytes);
+    let len = bytes.len() - b"rows=8".len();
+    bytes[len - 4..len].copy_from_slice(&u32::MAX.to_be_bytes());
+    let err = VerifyingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+        &bytes,
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let pk =
+        keygen_pk(&params, bound.clone(), &FixedCircuit(1)).expect("keygen_pk should not fail");
+    let pk = ProvingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+        &pk.to_bytes(SerdeFormat::RawBytes),
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .expect("reading pk should not fail");
+    assert_eq!(pk.get_vk().metadata(), Some(&b"rows=8"[..]));
+    assert_eq!(pk.get_vk().transcript_repr(), bound.transcript_repr());
+}
+
+#[test]
+fn test_shuffle_three_columns() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    const K: u32 = 5;
+    const ROWS: [[u64; 3]; 4] = [[1, 2, 3], [4, 5, 6], [7, 8, 9], [1, 2, 3]];
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        input: [Column<Advice>; 3],
+        shuffled: [Column<Advice>; 3],
+    }
+
+    /// Shuffles the rows of a relation of three columns, from the input
+    /// columns into the shuffled ones unless `SWAP` is set.
+    #[derive(Clone, Copy)]
+    struct MyCircuit<const SWAP: bool> {
+        order: [usize; 4],
+    }
+
+    impl<const SWAP: bool> Circuit<Fr> for MyCircuit<SWAP> {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let input = [(); 3].map(|_| meta.advice_column());
+            let shuffled = [(); 3].map(|_| meta.advice_column());
+            meta.shuffle("rows", |meta| {
+                input
+                    .iter()
+                    .zip(shuffled.iter())
+                    .map(|(input, shuffled)| {
+                        let input = meta.query_advice(*input, Rotation::cur());
+                        let shuffled = meta.query_advice(*shuffled, Rotation::cur());
+                        if SWAP {
+                            (shuffled, input)
+                        } else {
+                            (input, shuffled)
+                        }
+                    })
+                    .collect()
+            });
+            MyConfig { input, shuffled }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "rows",
+                |mut region| {
+                    for (row, &from) in self.order.iter().enumerate() {
+                        for column in 0..3 {
+                            region.assign_advice(
+                                || "input",
+                                config.input[column],
+                                row,
+                                || Value::known(Fr::from(ROWS[row][column])),
+                            )?;
+                            region.assign_advice(
+                                || "shuffled",
+                                config.shuffled[column],
+                                row,
+                                || Value::known(Fr::from(ROWS[from][column])),
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let circuit = MyCircuit::<false> {
+        order: [2, 3, 0, 1],
+    };
+    MockProver::run(K, &circuit, vec![])
+        .unwrap()
+        .assert_satisfied();
+
+    // Mixing the columns of different rows is not a shuffle of the rows.
+    let mut mixed = circuit;
+    mixed.order = [2, 3, 0, 0];
+    assert!(MockProver::run(K, &mixed, vec![])
+        .unwrap()
+        .verify()
+        .is_err());
+
+    let params = test_fixtures::params(K);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[]],
+        test_fixtures::rng(),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    // The verifying key commits to the shuffle.
+    let swapped = keygen_vk(
+        &params,
+        &MyCircuit::<true> {
+            order: circuit.order,
+        },
+    )
+    .expect("keygen_vk should not fail");
+    assert_ne!(swapped.fingerprint(), pk.get_vk().fingerprint());
+}
+
+#[test]
+fn test_evaluate_constraints() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::{CellValue, MockProver},
+        poly::Rotation,
+    };
+    use halo2curves::bn256::Fr;
+
+    const K: u32 = 5;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Selector,
+        table: TableColumn,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    /// Multiplies small values by two, with the first one public.
+    #[derive(Clone, Copy, Default)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q = meta.complex_selector();
+            let table = meta.lookup_table_column();
+            let [a, b, c] = [(); 3].map(|_| meta.advice_column());
+            let instance = meta.instance_column();
+            meta.enable_equality(a);
+            meta.enable_equality(instance);
+
+            meta.create_gate("mul", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                Constraints::with_selector(q, [("a * b = c", a * b - c)])
+            });
+            meta.lookup("range", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![(q * a, table)]
+            });
+
+            MyConfig {
+                q,
+                table,
+                a,
+                b,
+                c,
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "range",
+                |mut table| {
+                    for value in 0..8 {
+                        table.assign_cell(
+                            || "range",
+                            config.table,
+                            value,
+                            || Value::known(Fr::from(value as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            let first = layouter.assign_region(
+                || "mul",
+                |mut region| {
+                    let mut first = None;
+                    for row in 0..4 {
+                        config.q.enable(&mut region, row)?;
+                        let a = Fr::from(row as u64 + 1);
+                        let cell =
+                            region.assign_advice(|| "a", config.a, row, || Value::known(a))?;
+                        first.get_or_insert(cell);
+                        region.assign_advice(
+                            || "b",
+                            config.b,
+                            row,
+                            || Value::known(Fr::from(2)),
+                        )?;
+                        region.assign_advice(|| "c", config.c, row, || Value::known(a.double()))?;
+                    }
+                    Ok(first.unwrap())
+                },
+            )?;
+            layouter.constrain_instance(first.cell(), config.instance, 0)
+        }
+    }
+
+    let params = test_fixtures::params(K);
+    let vk = keygen_vk(&params, &MyCircuit).unwrap();
+    let pk = keygen_pk(&params, vk, &MyCircuit).unwrap();
+
+    let instance = vec![Fr::ONE];
+    let prover = MockProver::run(K, &MyCircuit, vec![instance.clone()]).unwrap();
+    let advice: Vec<Vec<Fr>> = (0..3)
+        .map(|index| {
+            prover
+                .advice_values(Column::new(index, Advice::default()))
+                .iter()
+                .map(|value| match value {
+                    CellValue::Assigned(value) => *value,
+                    _ => Fr::ZERO,
+                })
+                .collect()
+        })
+        .collect();
+    let evaluate = |advice: &[Vec<Fr>], instance: &[Fr]| {
+        let advice: Vec<_> = advice.iter().map(|column| &column[..]).collect();
+        pk.evaluate_constraints(&advice, &[instance], prover.challenges())
+    };
+
+    let report = evaluate(&advice, &instance);
+    assert!(report.is_satisfied(), "{:?}", report.failures);
+    assert!(report.gate_values.iter().all(|value| *value == Fr::ZERO));
+
+    // A wrong product
+    let mut wrong = advice.clone();
+    wrong[2][2] += Fr::ONE;
+    let report = evaluate(&wrong, &instance);
+    assert_ne!(report.gate_values[2], Fr::ZERO);
+    assert_eq!(
+        report.failures,
+        vec![ConstraintFailure::Gate {
+            gate: 0,
+            gate_name: "mul".to_string(),
+            constraint: 0,
+            constraint_name: "a * b = c".to_string(),
+            row: 2,
+        }]
+    );
+
+    // A correct product of a value out of the table
+    let mut wrong = advice.clone();
+    wrong[0][1] = Fr::from(9);
+    wrong[2][1] = Fr::from(18);
+    let report = evaluate(&wrong, &instance);
+    assert!(report.gate_values.iter().all(|value| *value == Fr::ZERO));
+    assert_eq!(
+        report.first_failures(),
+        [ConstraintFailure::Lookup {
+            lookup: 0,
+            name: "range".to_string(),
+            row: 1,
+        }]
+    );
+
+    // A public input that differs from the cell copied to it
+    let report = evaluate(&advice, &[Fr::from(5)]);
+    assert!(!report.failures.is_empty());
+    for failure in report.failures {
+        assert!(matches!(
+            failure,
+            ConstraintFailure::Permutation {
+                row: 0,
+                other_row: 0,
+                ..
+            }
+        ));
+    }
+}
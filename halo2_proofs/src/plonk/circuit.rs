@@ -1,23 +1,38 @@
-use super::{lookup, permutation, shuffle, Assigned, Error};
+use super::{
+    lookup, permutation, shuffle, Assigned, Error, GateView, LookupView, PermutationView,
+    ShuffleView,
+};
 use crate::circuit::layouter::SyncDeps;
 use crate::dev::metadata;
 use crate::{
-    circuit::{Layouter, Region, Value},
-    poly::Rotation,
+    circuit::{Layouter, LogicalTable, Region, Value},
+    poly::{commitment::Blind, Rotation},
 };
 use core::cmp::max;
 use core::ops::{Add, Mul};
 use ff::Field;
+use rand_core::RngCore;
 use sealed::SealedPhase;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::iter::{Product, Sum};
 use std::{
     convert::TryFrom,
-    ops::{Neg, Sub},
+    ops::{Neg, Range, Sub},
 };
 
+mod compose;
 mod compress_selectors;
+pub mod expr;
+#[cfg(feature = "circuit-params")]
+mod fixed_assignments;
+mod serialization;
+mod visitor;
+
+pub use compose::{ColumnRemap, ComposedCircuit, RemapConfig};
+#[cfg(feature = "circuit-params")]
+pub use fixed_assignments::FixedAssignments;
+pub use visitor::{ExpressionFold, ExpressionVisitor};
 
 /// A column type
 pub trait ColumnType:
@@ -35,7 +50,6 @@ pub struct Column<C: ColumnType> {
 }
 
 impl<C: ColumnType> Column<C> {
-    #[cfg(test)]
     pub(crate) fn new(index: usize, column_type: C) -> Self {
         Column { index, column_type }
     }
@@ -97,7 +111,7 @@ impl<C: ColumnType> PartialOrd for Column<C> {
 pub(crate) mod sealed {
     /// Phase of advice column
     #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-    pub struct Phase(pub(super) u8);
+    pub struct Phase(pub(crate) u8);
 
     impl Phase {
         pub fn prev(&self) -> Option<Phase> {
@@ -152,6 +166,94 @@ impl SealedPhase for super::ThirdPhase {
     }
 }
 
+/// The phase with the given index, for circuits with more phases than
+/// [`FirstPhase`], [`SecondPhase`] and [`ThirdPhase`], which are the same as
+/// `NthPhase(0)`, `NthPhase(1)` and `NthPhase(2)`.
+///
+/// Phases are used in order: advice columns can only be allocated in a phase
+/// once the previous one has some, and challenges can only be requested
+/// after a phase that has some.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NthPhase(pub u8);
+
+impl SealedPhase for NthPhase {
+    fn to_sealed(self) -> sealed::Phase {
+        sealed::Phase(self.0)
+    }
+}
+
+/// A phase known from its type: [`FirstPhase`], [`SecondPhase`] or
+/// [`ThirdPhase`]; see [`AdviceColumn`].
+pub trait TypedPhase: Phase {
+    /// The index of the phase.
+    const PHASE: u8;
+}
+
+impl TypedPhase for FirstPhase {
+    const PHASE: u8 = 0;
+}
+
+impl TypedPhase for SecondPhase {
+    const PHASE: u8 = 1;
+}
+
+impl TypedPhase for ThirdPhase {
+    const PHASE: u8 = 2;
+}
+
+/// An advice column whose phase is part of its type, allocated with
+/// [`ConstraintSystem::phased_advice_column`].
+///
+/// Assigning it with [`Region::assign_advice_in_phase`] checks that the
+/// value was not computed from a challenge squeezed after the phase of the
+/// column, which the prover cannot know yet when it commits to the column.
+/// It converts into a [`Column<Advice>`] for everything else.
+#[derive(Debug)]
+pub struct AdviceColumn<P: TypedPhase> {
+    column: Column<Advice>,
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<P: TypedPhase> AdviceColumn<P> {
+    /// Returns the untyped column.
+    pub fn column(&self) -> Column<Advice> {
+        self.column
+    }
+
+    /// Index of this column.
+    pub fn index(&self) -> usize {
+        self.column.index
+    }
+}
+
+impl<P: TypedPhase> Clone for AdviceColumn<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: TypedPhase> Copy for AdviceColumn<P> {}
+
+impl<P: TypedPhase> PartialEq for AdviceColumn<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.column == other.column
+    }
+}
+
+impl<P: TypedPhase> Eq for AdviceColumn<P> {}
+
+impl<P: TypedPhase> From<AdviceColumn<P>> for Column<Advice> {
+    fn from(column: AdviceColumn<P>) -> Column<Advice> {
+        column.column
+    }
+}
+
+impl<P: TypedPhase> From<AdviceColumn<P>> for Column<Any> {
+    fn from(column: AdviceColumn<P>) -> Column<Any> {
+        column.column.into()
+    }
+}
+
 /// An advice column
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Advice {
@@ -476,6 +578,142 @@ impl Selector {
     pub fn expr<F: Field>(&self) -> Expression<F> {
         Expression::Selector(*self)
     }
+
+    /// Returns the combination active where both this selector and `other`
+    /// are; see [`SelectorExpr`].
+    pub fn and<S: Into<SelectorExpr>>(self, other: S) -> SelectorExpr {
+        SelectorExpr::from(self).and(other)
+    }
+
+    /// Returns the combination active where this selector or `other` is; see
+    /// [`SelectorExpr`].
+    pub fn or<S: Into<SelectorExpr>>(self, other: S) -> SelectorExpr {
+        SelectorExpr::from(self).or(other)
+    }
+}
+
+impl std::ops::Not for Selector {
+    type Output = SelectorExpr;
+
+    fn not(self) -> SelectorExpr {
+        !SelectorExpr::from(self)
+    }
+}
+
+/// A boolean combination of selectors, which activates a gate on the rows it
+/// is true on; see [`VirtualCells::selector_expr`].
+///
+/// ```
+/// use std::ops::Not;
+/// use halo2_proofs::poly::Rotation;
+/// # use halo2curves::pasta::Fp;
+/// # use halo2_proofs::plonk::ConstraintSystem;
+///
+/// # let mut meta = ConstraintSystem::<Fp>::default();
+/// let a = meta.advice_column();
+/// let s1 = meta.selector();
+/// let s2 = meta.selector();
+///
+/// meta.create_gate("s1 but not s2", |meta| {
+///     let s = meta.selector_expr(s1.and(s2.not()));
+///     let a = meta.query_advice(a, Rotation::cur());
+///     vec![s * a]
+/// });
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelectorExpr {
+    /// Active where the selector is enabled.
+    Selector(Selector),
+    /// Active where the combination is not.
+    Not(Box<SelectorExpr>),
+    /// Active where both combinations are.
+    And(Box<SelectorExpr>, Box<SelectorExpr>),
+    /// Active where either combination is.
+    Or(Box<SelectorExpr>, Box<SelectorExpr>),
+}
+
+impl SelectorExpr {
+    /// Returns the combination active where both this one and `other` are.
+    pub fn and<S: Into<SelectorExpr>>(self, other: S) -> SelectorExpr {
+        SelectorExpr::And(Box::new(self), Box::new(other.into()))
+    }
+
+    /// Returns the combination active where this one or `other` is.
+    pub fn or<S: Into<SelectorExpr>>(self, other: S) -> SelectorExpr {
+        SelectorExpr::Or(Box::new(self), Box::new(other.into()))
+    }
+
+    /// Returns the selectors the combination is made of, in order and with
+    /// repetitions.
+    pub fn selectors(&self) -> Vec<Selector> {
+        match self {
+            SelectorExpr::Selector(selector) => vec![*selector],
+            SelectorExpr::Not(a) => a.selectors(),
+            SelectorExpr::And(a, b) | SelectorExpr::Or(a, b) => {
+                let mut selectors = a.selectors();
+                selectors.extend(b.selectors());
+                selectors
+            }
+        }
+    }
+
+    /// Returns whether the combination is active, given whether each of its
+    /// selectors is.
+    pub fn is_active(&self, enabled: &impl Fn(Selector) -> bool) -> bool {
+        match self {
+            SelectorExpr::Selector(selector) => enabled(*selector),
+            SelectorExpr::Not(a) => !a.is_active(enabled),
+            SelectorExpr::And(a, b) => a.is_active(enabled) && b.is_active(enabled),
+            SelectorExpr::Or(a, b) => a.is_active(enabled) || b.is_active(enabled),
+        }
+    }
+
+    /// Returns the combination with each of its selectors replaced with
+    /// `f(selector)`.
+    pub(crate) fn map_selectors(&self, f: &impl Fn(Selector) -> Selector) -> SelectorExpr {
+        match self {
+            SelectorExpr::Selector(selector) => SelectorExpr::Selector(f(*selector)),
+            SelectorExpr::Not(a) => SelectorExpr::Not(Box::new(a.map_selectors(f))),
+            SelectorExpr::And(a, b) => {
+                SelectorExpr::And(Box::new(a.map_selectors(f)), Box::new(b.map_selectors(f)))
+            }
+            SelectorExpr::Or(a, b) => {
+                SelectorExpr::Or(Box::new(a.map_selectors(f)), Box::new(b.map_selectors(f)))
+            }
+        }
+    }
+
+    /// Returns the combination as the product of the expressions of its
+    /// selectors, given by `selector`, which must be 1 on the rows a
+    /// selector is enabled on and 0 elsewhere.
+    pub(crate) fn lower<F: Field>(
+        &self,
+        selector: &impl Fn(Selector) -> Expression<F>,
+    ) -> Expression<F> {
+        match self {
+            SelectorExpr::Selector(s) => selector(*s),
+            SelectorExpr::Not(a) => Expression::Constant(F::ONE) - a.lower(selector),
+            SelectorExpr::And(a, b) => a.lower(selector) * b.lower(selector),
+            SelectorExpr::Or(a, b) => {
+                let (a, b) = (a.lower(selector), b.lower(selector));
+                a.clone() + b.clone() - a * b
+            }
+        }
+    }
+}
+
+impl From<Selector> for SelectorExpr {
+    fn from(selector: Selector) -> SelectorExpr {
+        SelectorExpr::Selector(selector)
+    }
+}
+
+impl std::ops::Not for SelectorExpr {
+    type Output = SelectorExpr;
+
+    fn not(self) -> SelectorExpr {
+        SelectorExpr::Not(Box::new(self))
+    }
 }
 
 /// Query of fixed column at a certain relative location
@@ -583,7 +821,8 @@ impl TableColumn {
     }
 }
 
-/// A challenge squeezed from transcript after advice columns at the phase have been committed.
+/// A challenge squeezed from transcript after advice columns at the phase have been committed,
+/// or a power of one; see [`ConstraintSystem::challenge_power`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct Challenge {
     index: usize,
@@ -786,6 +1025,22 @@ pub trait Circuit<F: Field> {
     /// arrangement, column arrangement, etc.
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config;
 
+    /// Returns the fixed data `synthesize` assigns, for the circuit configured
+    /// with `params` as `config`, if it can be derived from them alone.
+    /// Requires the `circuit-params` feature.
+    ///
+    /// This lets [`keygen_vk_from_params`](crate::plonk::keygen_vk_from_params)
+    /// generate the verifying key without an instance of the circuit. The
+    /// default implementation returns `None`, for circuits whose fixed data
+    /// is only known by synthesizing them.
+    #[cfg(feature = "circuit-params")]
+    fn fixed_assignments(
+        _config: &Self::Config,
+        _params: &Self::Params,
+    ) -> Option<FixedAssignments<F>> {
+        None
+    }
+
     /// Given the provided `cs`, synthesize the circuit. The concrete type of
     /// the caller will be different depending on the context, and they may or
     /// may not expect to have a witness present.
@@ -1173,21 +1428,131 @@ impl<F: Field> Expression<F> {
 
     /// Compute the degree of this polynomial
     pub fn degree(&self) -> usize {
+        self.fold(&ExpressionFold {
+            constant: &|_| 0,
+            selector: &|_| 1,
+            fixed: &|_| 1,
+            advice: &|_| 1,
+            instance: &|_| 1,
+            challenge: &|_| 0,
+            negated: &|a| a,
+            sum: &|a, b| max(a, b),
+            product: &|a, b| a + b,
+            scaled: &|a, _| a,
+        })
+    }
+
+    /// Returns the number of nodes of this expression, counting each
+    /// operation, constant, query and challenge.
+    pub fn node_count(&self) -> usize {
+        self.evaluate(
+            &|_| 1,
+            &|_| 1,
+            &|_| 1,
+            &|_| 1,
+            &|_| 1,
+            &|_| 1,
+            &|a| a + 1,
+            &|a, b| a + b + 1,
+            &|a, b| a + b + 1,
+            &|a, _| a + 1,
+        )
+    }
+
+    /// Returns the number of selector and column queries in this expression,
+    /// counting repeated ones as many times as they appear.
+    pub fn query_count(&self) -> usize {
+        self.evaluate(
+            &|_| 0,
+            &|_| 1,
+            &|_| 1,
+            &|_| 1,
+            &|_| 1,
+            &|_| 0,
+            &|a| a,
+            &|a, b| a + b,
+            &|a, b| a + b,
+            &|a, _| a,
+        )
+    }
+
+    /// Returns an expression with the same value as this one at every row and
+    /// for every assignment, and no greater degree, in which:
+    /// - operations on constants only are folded into a constant,
+    /// - additions of zero, multiplications by one and double negations are
+    ///   removed,
+    /// - multiplications by zero are replaced with zero,
+    /// - products with a constant become scalings, and nested scalings are
+    ///   merged, and
+    /// - the constants of nested sums are added up into a single one.
+    pub fn simplify(&self) -> Expression<F> {
         match self {
-            Expression::Constant(_) => 0,
-            Expression::Selector(_) => 1,
-            Expression::Fixed(_) => 1,
-            Expression::Advice(_) => 1,
-            Expression::Instance(_) => 1,
-            Expression::Challenge(_) => 0,
-            Expression::Negated(poly) => poly.degree(),
-            Expression::Sum(a, b) => max(a.degree(), b.degree()),
-            Expression::Product(a, b) => a.degree() + b.degree(),
-            Expression::Scaled(poly, _) => poly.degree(),
+            Expression::Negated(a) => match a.simplify() {
+                Expression::Constant(c) => Expression::Constant(-c),
+                Expression::Negated(a) => *a,
+                Expression::Scaled(a, f) => (*a).scale(-f),
+                a => Expression::Negated(Box::new(a)),
+            },
+            Expression::Sum(a, b) => {
+                let (a, a_constant) = a.simplify().split_constant();
+                let (b, b_constant) = b.simplify().split_constant();
+                let constant = a_constant + b_constant;
+                let sum = match (a, b) {
+                    (Some(a), Some(b)) => Some(Expression::Sum(Box::new(a), Box::new(b))),
+                    (sum, None) | (None, sum) => sum,
+                };
+                match sum {
+                    None => Expression::Constant(constant),
+                    Some(sum) if constant.is_zero_vartime() => sum,
+                    Some(sum) => {
+                        Expression::Sum(Box::new(sum), Box::new(Expression::Constant(constant)))
+                    }
+                }
+            }
+            Expression::Product(a, b) => match (a.simplify(), b.simplify()) {
+                (Expression::Constant(a), b) => b.scale(a),
+                (a, Expression::Constant(b)) => a.scale(b),
+                (a, b) => Expression::Product(Box::new(a), Box::new(b)),
+            },
+            Expression::Scaled(a, f) => a.simplify().scale(*f),
+            _ => self.clone(),
+        }
+    }
+
+    /// Splits a simplified expression into a non-constant part, if any, and
+    /// the constant added to it.
+    fn split_constant(self) -> (Option<Self>, F) {
+        match self {
+            Expression::Constant(c) => (None, c),
+            Expression::Sum(a, b) => match *b {
+                Expression::Constant(c) => (Some(*a), c),
+                b => (Some(Expression::Sum(a, Box::new(b))), F::ZERO),
+            },
+            a => (Some(a), F::ZERO),
+        }
+    }
+
+    /// Multiplies a simplified expression by `f`, keeping it simplified.
+    fn scale(self, f: F) -> Self {
+        if f.is_zero_vartime() {
+            return Expression::Constant(F::ZERO);
+        }
+        if f == F::ONE {
+            return self;
+        }
+        match self {
+            Expression::Constant(c) => Expression::Constant(c * f),
+            Expression::Negated(a) => (*a).scale(-f),
+            Expression::Scaled(a, g) => (*a).scale(g * f),
+            a => Expression::Scaled(Box::new(a), f),
         }
     }
 
     /// Approximate the computational complexity of this expression.
+    ///
+    /// This is a cost estimate used when laying out selectors; see
+    /// [`Self::node_count`] and [`Self::query_count`] for the size of the
+    /// expression.
     pub fn complexity(&self) -> usize {
         match self {
             Expression::Constant(_) => 0,
@@ -1380,6 +1745,19 @@ pub struct VirtualCell {
     pub(crate) rotation: Rotation,
 }
 
+impl VirtualCell {
+    /// Returns the column of the cell.
+    pub fn column(&self) -> Column<Any> {
+        self.column
+    }
+
+    /// Returns the rotation of the cell, relative to the row the gate is
+    /// applied to.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+}
+
 impl<Col: Into<Column<Any>>> From<(Col, Rotation)> for VirtualCell {
     fn from((column, rotation): (Col, Rotation)) -> Self {
         VirtualCell {
@@ -1518,6 +1896,8 @@ pub struct Gate<F: Field> {
     /// trigger debug checks on gates.
     queried_selectors: Vec<Selector>,
     queried_cells: Vec<VirtualCell>,
+    /// The cost hint given to [`ConstraintSystem::create_gate_with_cost`].
+    cost: Option<usize>,
 }
 
 impl<F: Field> Gate<F> {
@@ -1536,6 +1916,21 @@ impl<F: Field> Gate<F> {
         &self.polys
     }
 
+    /// Returns the relative cost of evaluating this gate at a row, which the
+    /// prover uses to split the evaluation of the gates into parallel tasks.
+    ///
+    /// This is the hint given to [`ConstraintSystem::create_gate_with_cost`]
+    /// if any, and otherwise the number of nodes of the constraints once
+    /// simplified with [`Expression::simplify`].
+    pub fn cost(&self) -> usize {
+        self.cost.unwrap_or_else(|| {
+            self.polys
+                .iter()
+                .map(|poly| poly.simplify().node_count())
+                .sum()
+        })
+    }
+
     pub(crate) fn queried_selectors(&self) -> &[Selector] {
         &self.queried_selectors
     }
@@ -1545,6 +1940,41 @@ impl<F: Field> Gate<F> {
     }
 }
 
+/// The argument used to prove the lookups of a circuit; see
+/// [`ConstraintSystem::set_lookup_backend`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LookupBackend {
+    /// The lookup argument from the halo2 book. Each lookup commits to
+    /// permutations of its inputs and of its table, which the prover has to
+    /// sort, and to a grand product over both.
+    #[default]
+    Permutation,
+    /// The logarithmic-derivative lookup argument, also known as LogUp. Each
+    /// lookup commits to how many times each table row is looked up and to a
+    /// running sum of fractions. Nothing needs to be sorted, and proofs
+    /// carry fewer commitments and evaluations per lookup. Lookups into the
+    /// same table do not share their multiplicities.
+    LogUp,
+}
+
+/// The version of the pinned representation of a constraint system, and of
+/// the verifying keys built from it; see
+/// [`ConstraintSystem::set_pinned_version`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PinnedVersion {
+    /// The representation of keys built before it was versioned. Queries
+    /// are kept in the order `configure` made them in, and the number of
+    /// selectors and the columns set aside for constants are pinned.
+    #[default]
+    V0,
+    /// Queries are sorted by column and rotation once the selectors are
+    /// converted into fixed columns, and the number of selectors and the
+    /// columns set aside for constants are left out, so that reordering or
+    /// renaming things in `configure` keeps the pinned representation. The
+    /// pinned verifying key starts with the version.
+    V1,
+}
+
 /// This is a description of the circuit environment, such as the gate, column and
 /// permutation arrangements.
 #[derive(Debug, Clone)]
@@ -1557,13 +1987,37 @@ pub struct ConstraintSystem<F: Field> {
 
     /// Contains the phase for each advice column. Should have same length as num_advice_columns.
     pub(crate) advice_column_phase: Vec<sealed::Phase>,
+    /// The indices of the advice columns the prover does not blind; see
+    /// `unblinded_advice_column`.
+    pub(crate) unblinded_advice_columns: Vec<usize>,
     /// Contains the phase for each challenge. Should have same length as num_challenges.
     pub(crate) challenge_phase: Vec<sealed::Phase>,
+    /// The challenges that are powers of another rather than squeezed from
+    /// the transcript, as `(challenge, base, exponent)`; see `challenge_power`.
+    pub(crate) challenge_powers: Vec<(usize, usize, u32)>,
+    /// The indices of the simple selectors that are not combined with others
+    /// when compressed; see `uncompressed_selector`.
+    pub(crate) uncompressed_selectors: Vec<usize>,
+    /// The selectors standing for a boolean combination of others, by
+    /// index, which the layouter never enables; see
+    /// `VirtualCells::selector_expr`.
+    pub(crate) selector_combinations: Vec<(usize, SelectorExpr)>,
 
     /// This is a cached vector that maps virtual selectors to the concrete
     /// fixed column that they were compressed into. This is just used by dev
-    /// tooling right now.
+    /// tooling right now. A selector combination lowered to a product of
+    /// other selectors maps to the column of its first selector.
     pub(crate) selector_map: Vec<Column<Fixed>>,
+    /// The expression each virtual selector was replaced with when it was
+    /// compressed, indexed like `selector_map`. Not part of the pinned
+    /// constraint system.
+    pub(crate) selector_replacements: Vec<Expression<F>>,
+    /// The index of the column each virtual selector was compressed into,
+    /// among the columns returned by `compress_selectors`, and the value of
+    /// that column on the rows the selector is enabled on, indexed like
+    /// `selector_map`. `None` for a selector combination lowered to a product
+    /// of other selectors.
+    pub(crate) selector_values: Vec<Option<(usize, F)>>,
 
     pub(crate) gates: Vec<Gate<F>>,
     pub(crate) advice_queries: Vec<(Column<Advice>, Rotation)>,
@@ -1588,16 +2042,268 @@ pub struct ConstraintSystem<F: Field> {
     // List of indexes of Fixed columns which are associated to a circuit-general Column tied to their annotation.
     pub(crate) general_column_annotations: HashMap<metadata::Column, String>,
 
+    // The names given to selectors when they were allocated, by index; see
+    // `selector_named`.
+    pub(crate) selector_names: HashMap<usize, String>,
+
     // Vector of fixed columns, which can be used to store constant values
     // that are copied into advice columns.
     pub(crate) constants: Vec<Column<Fixed>>,
 
+    // The fixed columns whose values are given at configure time, with their
+    // values; see `fixed_column_with_values`.
+    pub(crate) preassigned_fixed: Vec<(Column<Fixed>, Vec<F>)>,
+
     pub(crate) minimum_degree: Option<usize>,
+
+    // The size of the extended domain, if set with `set_extended_k`.
+    pub(crate) extended_k: Option<u32>,
+
+    // The indices the columns of the given names are expected to have; see
+    // `pin_columns`.
+    pub(crate) column_pins: Vec<(String, usize)>,
+
+    // Whether the prover blinds its witness polynomials; see
+    // `disable_zero_knowledge`.
+    pub(crate) zero_knowledge: bool,
+
+    // The argument proving the lookups; see `set_lookup_backend`.
+    pub(crate) lookup_backend: LookupBackend,
+
+    // The version of the pinned representation; see `set_pinned_version`.
+    pub(crate) pinned_version: PinnedVersion,
+
+    // The names of the gates disabled with `disable_gate`, and whether keygen
+    // accepts them; see `allow_disabled_gates`.
+    pub(crate) disabled_gates: Vec<String>,
+    pub(crate) allow_disabled_gates: bool,
+
+    // The largest rotation gates, lookups and shuffles may query, if set with
+    // `set_max_rotation`, and whether any rotation is accepted; see
+    // `allow_large_rotations`.
+    pub(crate) max_rotation: Option<usize>,
+    pub(crate) allow_large_rotations: bool,
+}
+
+/// The largest rotation, in absolute value, that gates, lookups and shuffles
+/// may query by default, unless the circuit needs more blinding factors.
+pub const DEFAULT_MAX_ROTATION: usize = 64;
+
+/// How the number of blinding factors of a [`ConstraintSystem`] is derived,
+/// as returned by [`ConstraintSystem::blinding_factors_breakdown`].
+///
+/// Each witness polynomial the prover commits to is blinded with random
+/// values on as many rows as it can be evaluated at, so that the
+/// evaluations reveal nothing about the witness. A circuit of `n` rows has
+/// `n - blinding_factors - 1` usable rows.
+///
+/// The number of blinding factors only depends on whether the circuit is zero
+/// knowledge and on [`Self::max_advice_queries`]: it does not change when
+/// gates, lookups, shuffles, columns or queries to fixed, instance and
+/// unblinded advice columns are added, as long as no blinded advice column
+/// ends up queried at more than [`Self::argument_queries`] distinct
+/// rotations. A change to this derivation is a breaking change of the crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlindingFactorsBreakdown {
+    /// Whether the circuit is zero knowledge. If not, it has no blinding
+    /// factors.
+    pub zero_knowledge: bool,
+    /// The largest number of distinct queries to a blinded advice column, or
+    /// 1 if there is none.
+    pub max_advice_queries: usize,
+    /// The number of points the permutation argument evaluates its
+    /// polynomials at, which is also enough for the lookup and shuffle
+    /// arguments.
+    pub argument_queries: usize,
+}
+
+impl BlindingFactorsBreakdown {
+    /// Returns the number of additional points the multiopen argument
+    /// evaluates each polynomial at.
+    pub fn multiopen_queries(&self) -> usize {
+        1
+    }
+
+    /// Returns the number of blinding factors added as a slight defense
+    /// against off-by-one errors.
+    pub fn safety_margin(&self) -> usize {
+        1
+    }
+
+    /// Returns the number of blinding factors: the larger of
+    /// `max_advice_queries` and `argument_queries`, plus
+    /// [`Self::multiopen_queries`] and [`Self::safety_margin`], or zero
+    /// without zero knowledge.
+    pub fn total(&self) -> usize {
+        if !self.zero_knowledge {
+            return 0;
+        }
+        max(self.max_advice_queries, self.argument_queries)
+            + self.multiopen_queries()
+            + self.safety_margin()
+    }
+}
+
+/// What the minimum number of rows of a [`ConstraintSystem`] is made of, as
+/// returned by [`ConstraintSystem::minimum_rows_breakdown`].
+///
+/// A circuit needs room for its blinding factors, the row the grand products
+/// end on (`l_last`), the first row (`l_0`) and at least one usable row.
+/// It also needs the rows its gates query from a single row to be distinct,
+/// so that no rotation wraps around the domain onto another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinimumRowsBreakdown {
+    /// Whether the circuit is zero knowledge. If not, it has no blinding
+    /// factors.
+    pub zero_knowledge: bool,
+    /// The largest number of distinct queries to a blinded advice column, or
+    /// 1 if there is none.
+    pub max_advice_queries: usize,
+    /// The number of points the permutation argument evaluates its
+    /// polynomials at, which is also enough for the lookup and shuffle
+    /// arguments.
+    pub argument_queries: usize,
+    /// The number of blinding factors: the larger of `max_advice_queries`
+    /// and `argument_queries`, plus one for the multiopen argument and one
+    /// against off-by-one errors, or zero without zero knowledge.
+    pub blinding_factors: usize,
+    /// The largest rotation queried, or zero.
+    pub max_rotation: i32,
+    /// The smallest rotation queried, or zero.
+    pub min_rotation: i32,
+}
+
+impl MinimumRowsBreakdown {
+    /// Returns the rows needed besides the blinding factors: one for
+    /// `l_last`, one for `l_0` and one usable row.
+    pub fn reserved_rows(&self) -> usize {
+        3
+    }
+
+    /// Returns the number of rows between the smallest and the largest
+    /// rotation queried, both included.
+    pub fn rotation_span(&self) -> usize {
+        (i64::from(self.max_rotation) - i64::from(self.min_rotation) + 1) as usize
+    }
+
+    /// Returns the minimum number of rows, the larger of what the blinding
+    /// factors and the rotations need.
+    pub fn total(&self) -> usize {
+        max(
+            self.blinding_factors + self.reserved_rows(),
+            self.rotation_span(),
+        )
+    }
+}
+
+impl std::fmt::Display for MinimumRowsBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the circuit needs at least {} rows: ", self.total())?;
+        if self.zero_knowledge {
+            write!(
+                f,
+                "{} blinding factors (the most queries to a blinded advice column, {}, or {} for the permutation and lookup arguments if more, plus 1 for the multiopen argument and 1 for safety)",
+                self.blinding_factors, self.max_advice_queries, self.argument_queries
+            )?;
+        } else {
+            write!(f, "no blinding factors (zero knowledge is disabled)")?;
+        }
+        write!(
+            f,
+            ", plus 1 row for l_last, 1 for l_0 and 1 usable row, for {} rows; and {} rows for the rotations from {} to {} queried",
+            self.blinding_factors + self.reserved_rows(),
+            self.rotation_span(),
+            self.min_rotation,
+            self.max_rotation
+        )
+    }
+}
+
+/// Where a constraint of a [`ConstraintSystem`] comes from, as returned by
+/// [`ConstraintSystem::highest_degree_constraint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DegreeSource {
+    /// A constraint of a custom gate.
+    Gate {
+        /// The index of the gate.
+        gate: usize,
+        /// The name of the gate.
+        name: String,
+    },
+    /// The constraints of a lookup.
+    Lookup {
+        /// The index of the lookup.
+        lookup: usize,
+        /// The name of the lookup.
+        name: String,
+    },
+    /// The constraints of a shuffle.
+    Shuffle {
+        /// The index of the shuffle.
+        shuffle: usize,
+        /// The name of the shuffle.
+        name: String,
+    },
+    /// The constraints of the permutation argument.
+    Permutation,
+}
+
+impl std::fmt::Display for DegreeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DegreeSource::Gate { name, .. } => write!(f, "gate '{}'", name),
+            DegreeSource::Lookup { name, .. } => write!(f, "lookup '{}'", name),
+            DegreeSource::Shuffle { name, .. } => write!(f, "shuffle '{}'", name),
+            DegreeSource::Permutation => write!(f, "the permutation argument"),
+        }
+    }
+}
+
+/// A column pinned with [`ConstraintSystem::pin_columns`] that was not
+/// allocated at its pinned index, as returned by
+/// [`ConstraintSystem::column_drift`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnDrift {
+    /// The name the column was pinned by.
+    pub name: String,
+    /// The index the column was pinned at.
+    pub expected: usize,
+    /// The column allocated with that name, or `None` if no column has it.
+    pub actual: Option<Column<Any>>,
+}
+
+impl std::fmt::Display for ColumnDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "column '{}' is pinned at index {}",
+            self.name, self.expected
+        )?;
+        match self.actual {
+            Some(column) => write!(
+                f,
+                " but was allocated as {:?} column {}",
+                column.column_type(),
+                column.index()
+            ),
+            None => write!(f, " but no column has that name"),
+        }
+    }
 }
 
 /// Represents the minimal parameters that determine a `ConstraintSystem`.
+///
+/// The names of gates, constraints, lookups and shuffles, the column
+/// annotations, which selectors are left uncompressed and the combinations of
+/// selectors are not pinned, the latter two as the gates and fixed
+/// commitments already cover their effect. From [`PinnedVersion::V1`] on,
+/// neither are the number of selectors before they are compressed into fixed
+/// columns nor the columns set aside for constants, as changing them changes
+/// neither the proofs nor the fixed commitments that already cover their
+/// effect.
 #[allow(dead_code)]
 pub struct PinnedConstraintSystem<'a, F: Field> {
+    version: &'a PinnedVersion,
     num_fixed_columns: &'a usize,
     num_advice_columns: &'a usize,
     num_instance_columns: &'a usize,
@@ -1605,6 +2311,9 @@ pub struct PinnedConstraintSystem<'a, F: Field> {
     num_challenges: &'a usize,
     advice_column_phase: &'a Vec<sealed::Phase>,
     challenge_phase: &'a Vec<sealed::Phase>,
+    challenge_powers: &'a Vec<(usize, usize, u32)>,
+    unblinded_advice_columns: &'a Vec<usize>,
+    preassigned_fixed: Vec<(usize, [u8; 32])>,
     gates: PinnedGates<'a, F>,
     advice_queries: &'a Vec<(Column<Advice>, Rotation)>,
     instance_queries: &'a Vec<(Column<Instance>, Rotation)>,
@@ -1614,6 +2323,8 @@ pub struct PinnedConstraintSystem<'a, F: Field> {
     shuffles: &'a Vec<shuffle::Argument<F>>,
     constants: &'a Vec<Column<Fixed>>,
     minimum_degree: &'a Option<usize>,
+    zero_knowledge: &'a bool,
+    lookup_backend: &'a LookupBackend,
 }
 
 impl<'a, F: Field> std::fmt::Debug for PinnedConstraintSystem<'a, F> {
@@ -1622,8 +2333,11 @@ impl<'a, F: Field> std::fmt::Debug for PinnedConstraintSystem<'a, F> {
         debug_struct
             .field("num_fixed_columns", self.num_fixed_columns)
             .field("num_advice_columns", self.num_advice_columns)
-            .field("num_instance_columns", self.num_instance_columns)
-            .field("num_selectors", self.num_selectors);
+            .field("num_instance_columns", self.num_instance_columns);
+        // Only show the number of selectors in the legacy representation.
+        if *self.version == PinnedVersion::V0 {
+            debug_struct.field("num_selectors", self.num_selectors);
+        }
         // Only show multi-phase related fields if it's used.
         if *self.num_challenges > 0 {
             debug_struct
@@ -1631,19 +2345,144 @@ impl<'a, F: Field> std::fmt::Debug for PinnedConstraintSystem<'a, F> {
                 .field("advice_column_phase", self.advice_column_phase)
                 .field("challenge_phase", self.challenge_phase);
         }
+        // Only show the powers of challenges if there are any.
+        if !self.challenge_powers.is_empty() {
+            debug_struct.field("challenge_powers", self.challenge_powers);
+        }
+        // Only show the unblinded advice columns if there are any.
+        if !self.unblinded_advice_columns.is_empty() {
+            debug_struct.field("unblinded_advice_columns", self.unblinded_advice_columns);
+        }
+        // Only show the hashes of the preassigned fixed columns if there are
+        // any.
+        if !self.preassigned_fixed.is_empty() {
+            debug_struct.field("preassigned_fixed", &self.preassigned_fixed);
+        }
         debug_struct
             .field("gates", &self.gates)
             .field("advice_queries", self.advice_queries)
             .field("instance_queries", self.instance_queries)
             .field("fixed_queries", self.fixed_queries)
             .field("permutation", self.permutation)
-            .field("lookups", self.lookups)
-            .field("constants", self.constants)
-            .field("minimum_degree", self.minimum_degree);
+            .field("lookups", self.lookups);
+        // Only show the shuffles if there are any, so that circuits without
+        // them keep their pinned representation.
+        if !self.shuffles.is_empty() {
+            debug_struct.field("shuffles", self.shuffles);
+        }
+        // Only show the constants in the legacy representation.
+        if *self.version == PinnedVersion::V0 {
+            debug_struct.field("constants", self.constants);
+        }
+        debug_struct.field("minimum_degree", self.minimum_degree);
+        // Only show the zero-knowledge flag if it's disabled.
+        if !*self.zero_knowledge {
+            debug_struct.field("zero_knowledge", self.zero_knowledge);
+        }
+        // Only show the lookup backend if it isn't the default one.
+        if *self.lookup_backend != LookupBackend::default() {
+            debug_struct.field("lookup_backend", self.lookup_backend);
+        }
         debug_struct.finish()
     }
 }
 
+/// Compares the pinned fields of two constraint systems. The names of the
+/// lookups and shuffles are left out, as they are from the `Debug` output the
+/// transcript representation is computed from.
+impl<'a, F: Field> PartialEq for PinnedConstraintSystem<'a, F> {
+    fn eq(&self, other: &Self) -> bool {
+        // Destructure without `..`, so that a field added to the pinned
+        // constraint system cannot be left out of the comparison.
+        let Self {
+            version,
+            num_fixed_columns,
+            num_advice_columns,
+            num_instance_columns,
+            num_selectors,
+            num_challenges,
+            advice_column_phase,
+            challenge_phase,
+            challenge_powers,
+            unblinded_advice_columns,
+            preassigned_fixed,
+            gates,
+            advice_queries,
+            instance_queries,
+            fixed_queries,
+            permutation,
+            lookups,
+            shuffles,
+            constants,
+            minimum_degree,
+            zero_knowledge,
+            lookup_backend,
+        } = self;
+
+        let same_lookups = lookups.len() == other.lookups.len()
+            && lookups.iter().zip(other.lookups.iter()).all(|(a, b)| {
+                a.input_expressions == b.input_expressions
+                    && a.table_expressions == b.table_expressions
+            });
+        let same_shuffles = shuffles.len() == other.shuffles.len()
+            && shuffles.iter().zip(other.shuffles.iter()).all(|(a, b)| {
+                a.input_expressions == b.input_expressions
+                    && a.shuffle_expressions == b.shuffle_expressions
+            });
+        let polynomials = |gates: &PinnedGates<'a, F>| {
+            gates
+                .0
+                .iter()
+                .flat_map(|gate| gate.polynomials().iter())
+                .collect::<Vec<_>>()
+        };
+
+        let legacy = **version == PinnedVersion::V0;
+
+        *version == other.version
+            && *num_fixed_columns == other.num_fixed_columns
+            && *num_advice_columns == other.num_advice_columns
+            && *num_instance_columns == other.num_instance_columns
+            && (!legacy || *num_selectors == other.num_selectors)
+            && *num_challenges == other.num_challenges
+            && *advice_column_phase == other.advice_column_phase
+            && *challenge_phase == other.challenge_phase
+            && *challenge_powers == other.challenge_powers
+            && *unblinded_advice_columns == other.unblinded_advice_columns
+            && *preassigned_fixed == other.preassigned_fixed
+            && polynomials(gates) == polynomials(&other.gates)
+            && *advice_queries == other.advice_queries
+            && *instance_queries == other.instance_queries
+            && *fixed_queries == other.fixed_queries
+            && *permutation == other.permutation
+            && same_lookups
+            && same_shuffles
+            && (!legacy || *constants == other.constants)
+            && *minimum_degree == other.minimum_degree
+            && *zero_knowledge == other.zero_knowledge
+            && *lookup_backend == other.lookup_backend
+    }
+}
+
+/// Hashes the values of a preassigned fixed column, which are pinned by
+/// their hash rather than written out.
+fn hash_fixed_values<F: Field>(values: &[F]) -> [u8; 32] {
+    let mut hasher = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"Halo2-FixedVals")
+        .to_state();
+    hasher.update(&(values.len() as u64).to_le_bytes());
+    for value in values {
+        let s = format!("{:?}", value);
+        hasher.update(&(s.len() as u64).to_le_bytes());
+        hasher.update(s.as_bytes());
+    }
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(hasher.finalize().as_bytes());
+    hash
+}
+
 struct PinnedGates<'a, F: Field>(&'a Vec<Gate<F>>);
 
 impl<'a, F: Field> std::fmt::Debug for PinnedGates<'a, F> {
@@ -1664,7 +2503,13 @@ impl<F: Field> Default for ConstraintSystem<F> {
             num_challenges: 0,
             advice_column_phase: Vec::new(),
             challenge_phase: Vec::new(),
+            challenge_powers: Vec::new(),
+            unblinded_advice_columns: Vec::new(),
+            uncompressed_selectors: Vec::new(),
+            selector_combinations: Vec::new(),
             selector_map: vec![],
+            selector_replacements: vec![],
+            selector_values: vec![],
             gates: vec![],
             fixed_queries: Vec::new(),
             advice_queries: Vec::new(),
@@ -1674,8 +2519,19 @@ impl<F: Field> Default for ConstraintSystem<F> {
             lookups: Vec::new(),
             shuffles: Vec::new(),
             general_column_annotations: HashMap::new(),
+            selector_names: HashMap::new(),
             constants: vec![],
+            preassigned_fixed: vec![],
             minimum_degree: None,
+            extended_k: None,
+            column_pins: Vec::new(),
+            zero_knowledge: true,
+            lookup_backend: LookupBackend::default(),
+            pinned_version: PinnedVersion::default(),
+            disabled_gates: vec![],
+            allow_disabled_gates: false,
+            max_rotation: None,
+            allow_large_rotations: false,
         }
     }
 }
@@ -1686,6 +2542,7 @@ impl<F: Field> ConstraintSystem<F> {
     /// system.
     pub fn pinned(&self) -> PinnedConstraintSystem<'_, F> {
         PinnedConstraintSystem {
+            version: &self.pinned_version,
             num_fixed_columns: &self.num_fixed_columns,
             num_advice_columns: &self.num_advice_columns,
             num_instance_columns: &self.num_instance_columns,
@@ -1693,6 +2550,13 @@ impl<F: Field> ConstraintSystem<F> {
             num_challenges: &self.num_challenges,
             advice_column_phase: &self.advice_column_phase,
             challenge_phase: &self.challenge_phase,
+            challenge_powers: &self.challenge_powers,
+            unblinded_advice_columns: &self.unblinded_advice_columns,
+            preassigned_fixed: self
+                .preassigned_fixed
+                .iter()
+                .map(|(column, values)| (column.index, hash_fixed_values(values)))
+                .collect(),
             gates: PinnedGates(&self.gates),
             fixed_queries: &self.fixed_queries,
             advice_queries: &self.advice_queries,
@@ -1702,9 +2566,48 @@ impl<F: Field> ConstraintSystem<F> {
             shuffles: &self.shuffles,
             constants: &self.constants,
             minimum_degree: &self.minimum_degree,
+            zero_knowledge: &self.zero_knowledge,
+            lookup_backend: &self.lookup_backend,
         }
     }
 
+    /// Returns a 32-byte hash of the content of this constraint system, to
+    /// tell whether a circuit changed, for example to invalidate cached keys
+    /// or witnesses.
+    ///
+    /// The hash is a BLAKE2b hash, under a versioned personalization, of the
+    /// [pinned](Self::pinned) constraint system (the columns and their
+    /// phases, the queries, gates, lookups, shuffles and permutation
+    /// argument), the number of selectors and the columns they were
+    /// compressed into, and the columns enabled for constants. It is the same
+    /// in every process and on every platform for the same configuration, and
+    /// does not change with what is not part of the circuit, such as the
+    /// names of columns. Any change to what is hashed comes with a new
+    /// personalization.
+    pub fn content_hash(&self) -> [u8; 32] {
+        let mut hasher = blake2b_simd::Params::new()
+            .hash_length(32)
+            .personal(b"Halo2-CS-Hash-v1")
+            .to_state();
+
+        let s = format!(
+            "{:?}",
+            (
+                self.pinned(),
+                self.num_selectors,
+                &self.selector_map,
+                &self.constants
+            )
+        );
+
+        hasher.update(&(s.len() as u64).to_le_bytes());
+        hasher.update(s.as_bytes());
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(hasher.finalize().as_bytes());
+        hash
+    }
+
     /// Enables this fixed column to be used for global constant assignments.
     ///
     /// # Side-effects
@@ -1718,6 +2621,11 @@ impl<F: Field> ConstraintSystem<F> {
     }
 
     /// Enable the ability to enforce equality over cells in this column
+    ///
+    /// Both columns of a copy constraint need equality enabled, whatever
+    /// their types: a copy between a fixed and an instance cell, for example
+    /// to check that a public input equals a constant, fails with
+    /// [`Error::ColumnNotInPermutation`] naming the column that lacks it.
     pub fn enable_equality<C: Into<Column<Any>>>(&mut self, column: C) {
         let column = column.into();
         self.query_any_index(column, Rotation::cur());
@@ -1728,6 +2636,15 @@ impl<F: Field> ConstraintSystem<F> {
     ///
     /// `table_map` returns a map between input expressions and the table columns
     /// they need to match.
+    ///
+    /// The input expressions may query advice, fixed and instance columns at
+    /// any rotation up to [`Self::max_rotation`], so public values can be
+    /// looked up without being copied into advice cells first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an input expression contains a simple selector, or queries a
+    /// rotation larger than [`Self::max_rotation`].
     pub fn lookup<S: AsRef<str>>(
         &mut self,
         name: S,
@@ -1746,6 +2663,8 @@ impl<F: Field> ConstraintSystem<F> {
                 (input, table)
             })
             .collect();
+        let queried_cells = cells.queried_cells;
+        self.check_rotations("lookup", name.as_ref(), &queried_cells);
         let index = self.lookups.len();
 
         self.lookups
@@ -1754,24 +2673,94 @@ impl<F: Field> ConstraintSystem<F> {
         index
     }
 
-    /// Add a lookup argument for some input expressions and table expressions.
+    /// Add a lookup argument for some input expressions into a logical table
+    /// of a [`TableSet`](crate::circuit::TableSet).
     ///
-    /// `table_map` returns a map between input expressions and the table expressions
-    /// they need to match.
-    pub fn lookup_any<S: AsRef<str>>(
+    /// `table_map` returns the expression enabling the lookup, which must be
+    /// 1 on the rows the lookup applies to and 0 elsewhere, and the input
+    /// expressions, one for each column of `table` in order. The inputs are
+    /// multiplied by the enabling expression, and the tag of `table` is
+    /// prepended to them, so that a disabled lookup looks up the padding row
+    /// of the set. [`MockProver`](crate::dev::MockProver) reports failures of
+    /// the lookup along with the name of `table`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of inputs is not the number of columns of
+    /// `table`, or if an expression contains a simple selector.
+    pub fn lookup_in_set<S: AsRef<str>>(
         &mut self,
         name: S,
-        table_map: impl FnOnce(&mut VirtualCells<'_, F>) -> Vec<(Expression<F>, Expression<F>)>,
+        table: &LogicalTable,
+        table_map: impl FnOnce(&mut VirtualCells<'_, F>) -> (Expression<F>, Vec<Expression<F>>),
     ) -> usize {
-        let mut cells = VirtualCells::new(self);
-        let table_map = table_map(&mut cells)
-            .into_iter()
-            .map(|(mut input, mut table)| {
-                input.query_cells(&mut cells);
+        let index = self.lookup(name, |meta| {
+            let (enable, inputs) = table_map(meta);
+            assert_eq!(
+                inputs.len(),
+                table.columns().len(),
+                "table {} has {} columns",
+                table.name(),
+                table.columns().len()
+            );
+            let tag = Expression::Constant(F::from(table.tag()));
+            std::iter::once((enable.clone() * tag, table.tag_column()))
+                .chain(
+                    inputs
+                        .into_iter()
+                        .zip(table.columns())
+                        .map(|(input, column)| (enable.clone() * input, *column)),
+                )
+                .collect()
+        });
+        self.lookups[index].table = Some(table.name().to_string());
+
+        index
+    }
+
+    /// Add a lookup argument for some input expressions and table expressions.
+    ///
+    /// `table_map` returns a map between input expressions and the table expressions
+    /// they need to match.
+    ///
+    /// Unlike with [`Self::lookup`], the table expressions may query advice
+    /// and instance columns as well as fixed ones, so that the table can be
+    /// witness data assigned during synthesis, e.g. the writes of a memory
+    /// that reads are looked up in. The table holds the table expressions
+    /// evaluated at every usable row of the circuit, including the rows
+    /// outside of the assigned regions: these contribute the actual values of
+    /// their cells, which is zero for cells that are never assigned. A table
+    /// expression multiplied by a selector thus always contains zero, and
+    /// inputs should be designed so that a row of zeros is not a valid one
+    /// to look up, or is harmless. Rows reserved for blinding factors are
+    /// never part of the table.
+    ///
+    /// A table read from an instance column holds the instances given for
+    /// that column, followed by zeros up to the last usable row, in
+    /// [`MockProver`](crate::dev::MockProver) as in proofs. This lets the
+    /// verifier supply a public table, such as a set of allowed values,
+    /// without the prover copying it into the circuit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an expression queries a rotation larger than
+    /// [`Self::max_rotation`].
+    pub fn lookup_any<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        table_map: impl FnOnce(&mut VirtualCells<'_, F>) -> Vec<(Expression<F>, Expression<F>)>,
+    ) -> usize {
+        let mut cells = VirtualCells::new(self);
+        let table_map = table_map(&mut cells)
+            .into_iter()
+            .map(|(mut input, mut table)| {
+                input.query_cells(&mut cells);
                 table.query_cells(&mut cells);
                 (input, table)
             })
             .collect();
+        let queried_cells = cells.queried_cells;
+        self.check_rotations("lookup", name.as_ref(), &queried_cells);
         let index = self.lookups.len();
 
         self.lookups
@@ -1781,6 +2770,11 @@ impl<F: Field> ConstraintSystem<F> {
     }
 
     /// Add a shuffle argument for some input expressions and table expressions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an expression queries a rotation larger than
+    /// [`Self::max_rotation`].
     pub fn shuffle<S: AsRef<str>>(
         &mut self,
         name: S,
@@ -1795,6 +2789,8 @@ impl<F: Field> ConstraintSystem<F> {
                 (input, table)
             })
             .collect();
+        let queried_cells = cells.queried_cells;
+        self.check_rotations("shuffle", name.as_ref(), &queried_cells);
         let index = self.shuffles.len();
 
         self.shuffles
@@ -1912,12 +2908,95 @@ impl<F: Field> ConstraintSystem<F> {
         self.minimum_degree = Some(degree);
     }
 
+    /// Sets the size of the extended domain, on which the prover evaluates
+    /// the constraints to compute the quotient polynomial, to
+    /// `2^extended_k` rows, instead of the size derived from
+    /// [`Self::degree`].
+    ///
+    /// This is meant for circuits whose constraints need a smaller extended
+    /// domain than their degree suggests, for example because
+    /// [`Self::set_minimum_degree`] raised it. Keygen fails with
+    /// [`Error::ExtendedDomainTooSmall`], naming the constraint that needs a
+    /// larger one, if `extended_k` is below [`Self::minimum_extended_k`].
+    /// The verifying key commits to the size of the extended domain.
+    pub fn set_extended_k(&mut self, extended_k: u32) {
+        self.extended_k = Some(extended_k);
+    }
+
+    /// Returns the size of the extended domain set with
+    /// [`Self::set_extended_k`], if any.
+    pub fn extended_k(&self) -> Option<u32> {
+        self.extended_k
+    }
+
+    /// Disables zero knowledge for proofs of the circuit.
+    ///
+    /// No rows are then reserved for blinding factors: every row but the last
+    /// one is usable. The prover fills the last row of each witness column
+    /// with zeroes and uses [`Blind::default`] for every commitment, so the
+    /// proofs reveal information about the witness but are still sound. The
+    /// multiopen argument may still draw randomness of its own.
+    ///
+    /// The verifying key commits to this choice.
+    pub fn disable_zero_knowledge(&mut self) {
+        self.zero_knowledge = false;
+    }
+
+    /// Returns whether proofs of the circuit are zero knowledge.
+    pub fn zero_knowledge(&self) -> bool {
+        self.zero_knowledge
+    }
+
+    /// Sets the argument used to prove all the lookups of the circuit.
+    ///
+    /// Lookups are declared the same way whatever the backend, and
+    /// [`MockProver`](crate::dev::MockProver) checks them the same way. The
+    /// verifying key commits to this choice.
+    pub fn set_lookup_backend(&mut self, backend: LookupBackend) {
+        self.lookup_backend = backend;
+    }
+
+    /// Returns the argument used to prove the lookups of the circuit.
+    pub fn lookup_backend(&self) -> LookupBackend {
+        self.lookup_backend
+    }
+
+    /// Sets the version of the pinned representation of the verifying keys
+    /// of the circuit, which [`VerifyingKey::transcript_repr`] and
+    /// [`VerifyingKey::fingerprint`] hash.
+    ///
+    /// Circuits default to [`PinnedVersion::V0`], so that the keys and proofs
+    /// of existing circuits do not change. With [`PinnedVersion::V1`], the
+    /// queries are also reordered, which changes the proofs.
+    ///
+    /// [`VerifyingKey::transcript_repr`]: crate::plonk::VerifyingKey::transcript_repr
+    /// [`VerifyingKey::fingerprint`]: crate::plonk::VerifyingKey::fingerprint
+    pub fn set_pinned_version(&mut self, version: PinnedVersion) {
+        self.pinned_version = version;
+    }
+
+    /// Returns the version of the pinned representation of the circuit.
+    pub fn pinned_version(&self) -> PinnedVersion {
+        self.pinned_version
+    }
+
+    /// Splits the lookups into those proven with
+    /// [`LookupBackend::Permutation`] and those proven with
+    /// [`LookupBackend::LogUp`]. One of the two is always empty.
+    pub(crate) fn lookups_by_backend(&self) -> (&[lookup::Argument<F>], &[lookup::Argument<F>]) {
+        match self.lookup_backend {
+            LookupBackend::Permutation => (&self.lookups, &[]),
+            LookupBackend::LogUp => (&[], &self.lookups),
+        }
+    }
+
     /// Creates a new gate.
     ///
     /// # Panics
     ///
     /// A gate is required to contain polynomial constraints. This method will panic if
-    /// `constraints` returns an empty iterator.
+    /// `constraints` returns an empty iterator, or if it queries a rotation larger
+    /// than [`Self::max_rotation`].
     pub fn create_gate<C: Into<Constraint<F>>, Iter: IntoIterator<Item = C>, S: AsRef<str>>(
         &mut self,
         name: S,
@@ -1941,6 +3020,7 @@ impl<F: Field> ConstraintSystem<F> {
             !polys.is_empty(),
             "Gates must contain at least one constraint."
         );
+        self.check_rotations("gate", name.as_ref(), &queried_cells);
 
         self.gates.push(Gate {
             name: name.as_ref().to_string(),
@@ -1948,14 +3028,177 @@ impl<F: Field> ConstraintSystem<F> {
             polys,
             queried_selectors,
             queried_cells,
+            cost: None,
         });
     }
 
+    /// Creates a new gate as [`Self::create_gate`] does, with a hint of the
+    /// relative cost of evaluating it at a row; see [`Gate::cost`].
+    ///
+    /// The prover splits the rows of costly gates into more parallel tasks
+    /// than those of cheap ones, so that the threads finish at about the same
+    /// time. The hint is only needed when the number of nodes of the
+    /// constraints does not reflect their cost, and does not change the keys
+    /// or the proofs.
+    ///
+    /// # Panics
+    ///
+    /// Panics as [`Self::create_gate`] does.
+    pub fn create_gate_with_cost<
+        C: Into<Constraint<F>>,
+        Iter: IntoIterator<Item = C>,
+        S: AsRef<str>,
+    >(
+        &mut self,
+        name: S,
+        cost: usize,
+        constraints: impl FnOnce(&mut VirtualCells<'_, F>) -> Iter,
+    ) {
+        self.create_gate(name, constraints);
+        self.gates.last_mut().unwrap().cost = Some(cost);
+    }
+
+    /// Simplifies the constraints of the gates created so far with
+    /// [`Expression::simplify`], which does not change their values. Calling
+    /// it at the end of [`Circuit::configure`] keeps the degree of the circuit
+    /// and the work of the prover from counting operations that gadgets left
+    /// in their constraints, such as multiplications by one.
+    ///
+    /// The cells and selectors queried by each gate are left as they were,
+    /// even if a simplified constraint no longer uses them.
+    pub fn simplify_gates(&mut self) {
+        for gate in self.gates.iter_mut() {
+            for poly in gate.polys.iter_mut() {
+                *poly = poly.simplify();
+            }
+        }
+    }
+
+    /// Disables the gates named `name`, for debugging: their constraints are
+    /// replaced with zero, and they no longer query any cell or selector.
+    ///
+    /// The gates keep their name and number of constraints, so the failures
+    /// [`MockProver`](crate::dev::MockProver) reports for the other gates are
+    /// unchanged, which makes it possible to find the gate a failure belongs
+    /// to; see also
+    /// [`MockProver::verify_without_gates`](crate::dev::MockProver::verify_without_gates).
+    /// Keygen fails with [`Error::DisabledGates`](super::Error::DisabledGates)
+    /// on a constraint system with disabled gates, unless
+    /// [`Self::allow_disabled_gates`] has been called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no gate is named `name`.
+    pub fn disable_gate(&mut self, name: &str) {
+        let mut found = false;
+        for gate in self.gates.iter_mut().filter(|gate| gate.name == name) {
+            for poly in gate.polys.iter_mut() {
+                *poly = Expression::Constant(F::ZERO);
+            }
+            gate.queried_selectors.clear();
+            gate.queried_cells.clear();
+            found = true;
+        }
+        assert!(found, "there is no gate named {}", name);
+
+        if !self.disabled_gates.iter().any(|disabled| disabled == name) {
+            self.disabled_gates.push(name.to_string());
+        }
+    }
+
+    /// Lets keygen generate keys for this constraint system even though some
+    /// of its gates were disabled with [`Self::disable_gate`]. The keys are
+    /// then those of a different, weaker circuit: this is only meant for
+    /// tests.
+    pub fn allow_disabled_gates(&mut self) {
+        self.allow_disabled_gates = true;
+    }
+
+    /// Sets the largest rotation, in absolute value, that the gates, lookups
+    /// and shuffles created from now on may query; see
+    /// [`Self::max_rotation`].
+    pub fn set_max_rotation(&mut self, max_rotation: usize) {
+        self.max_rotation = Some(max_rotation);
+    }
+
+    /// Lets the gates, lookups and shuffles created from now on query any
+    /// rotation, for circuits laying out related values far apart on purpose.
+    pub fn allow_large_rotations(&mut self) {
+        self.allow_large_rotations = true;
+    }
+
+    /// Returns the largest rotation, in absolute value, that gates, lookups
+    /// and shuffles may query, or `None` if [`Self::allow_large_rotations`]
+    /// has been called.
+    ///
+    /// It is the bound given to [`Self::set_max_rotation`] if any, and
+    /// otherwise the larger of [`DEFAULT_MAX_ROTATION`] and the number of
+    /// blinding factors, which rows near the end of the circuit already
+    /// reach into. A larger rotation is most often a mistake, such as a
+    /// row offset passed as a rotation: queries wrap around the rows of the
+    /// circuit, so it silently reads cells far away from the row of the
+    /// constraint, and costs as many unusable rows.
+    pub fn max_rotation(&self) -> Option<usize> {
+        if self.allow_large_rotations {
+            return None;
+        }
+        Some(
+            self.max_rotation
+                .unwrap_or_else(|| DEFAULT_MAX_ROTATION.max(self.blinding_factors())),
+        )
+    }
+
+    /// Panics if one of `cells`, queried by the `kind` named `name`, is at a
+    /// rotation larger than [`Self::max_rotation`].
+    fn check_rotations(&self, kind: &str, name: &str, cells: &[VirtualCell]) {
+        let max_rotation = match self.max_rotation() {
+            Some(max_rotation) => max_rotation,
+            None => return,
+        };
+        for cell in cells {
+            let rotation = cell.rotation.0;
+            if rotation.unsigned_abs() as usize <= max_rotation {
+                continue;
+            }
+            let column = match cell.column.column_type() {
+                Any::Advice(_) => format!("advice {}", cell.column.index()),
+                Any::Fixed => format!("fixed {}", cell.column.index()),
+                Any::Instance => format!("instance {}", cell.column.index()),
+            };
+            let column = match self.column_name(cell.column) {
+                Some(column_name) => format!("{} ('{}')", column, column_name),
+                None => column,
+            };
+            panic!(
+                "{} '{}' queries Rotation({}) on {}, beyond the largest rotation allowed, {}: \
+                 queries wrap around the rows of the circuit, so this one reads the cell {} rows \
+                 {} the row of the constraint modulo the number of rows, and costs as many \
+                 unusable rows; use `ConstraintSystem::set_max_rotation` to raise the limit, or \
+                 `ConstraintSystem::allow_large_rotations` if the rotation is intended",
+                kind,
+                name,
+                rotation,
+                column,
+                max_rotation,
+                rotation.unsigned_abs(),
+                if rotation > 0 { "after" } else { "before" },
+            );
+        }
+    }
+
+    /// Returns the names of the gates disabled with [`Self::disable_gate`].
+    pub fn disabled_gates(&self) -> &[String] {
+        &self.disabled_gates
+    }
+
     /// This will compress selectors together depending on their provided
     /// assignments. This `ConstraintSystem` will then be modified to add new
     /// fixed columns (representing the actual selectors) and will return the
     /// polynomials for those columns. Finally, an internal map is updated to
-    /// find which fixed column corresponds with a given `Selector`.
+    /// find which fixed column corresponds with a given `Selector`. From
+    /// [`PinnedVersion::V1`] on, the queries are also sorted by column and
+    /// rotation so that they do not depend on the order `configure` made them
+    /// in.
     ///
     /// Do not call this twice. Yes, this should be a builder pattern instead.
     pub fn compress_selectors(mut self, selectors: Vec<Vec<bool>>) -> (Self, Vec<Vec<F>>) {
@@ -1965,12 +3208,15 @@ impl<F: Field> ConstraintSystem<F> {
 
         // Compute the maximal degree of every selector. We only consider the
         // expressions in gates, as lookup arguments cannot support simple
-        // selectors. Selectors that are complex or do not appear in any gates
-        // will have degree zero.
+        // selectors. Selectors that are complex, uncompressed or do not appear
+        // in any gates will have degree zero, and get a fixed column of their
+        // own.
         let mut degrees = vec![0; selectors.len()];
         for expr in self.gates.iter().flat_map(|gate| gate.polys.iter()) {
             if let Some(selector) = expr.extract_simple_selector() {
-                degrees[selector.0] = max(degrees[selector.0], expr.degree());
+                if !self.uncompressed_selectors.contains(&selector.0) {
+                    degrees[selector.0] = max(degrees[selector.0], expr.degree());
+                }
             }
         }
 
@@ -1978,38 +3224,105 @@ impl<F: Field> ConstraintSystem<F> {
         // ourselves to the largest existing degree constraint.
         let max_degree = self.degree();
 
+        // Selector combinations are compressed after the selectors they are
+        // made of, as they can be replaced with the product of their
+        // expressions.
+        let mut selectors = selectors;
+        self.activate_selector_combinations(&mut selectors);
+        let (descriptions, combinations): (Vec<_>, Vec<_>) = selectors
+            .into_iter()
+            .zip(degrees)
+            .enumerate()
+            .map(
+                |(i, (activations, max_degree))| compress_selectors::SelectorDescription {
+                    selector: i,
+                    activations,
+                    max_degree,
+                },
+            )
+            .partition(|description| {
+                self.selector_combinations
+                    .iter()
+                    .all(|(index, _)| *index != description.selector)
+            });
+
+        fn allocate_column<F: Field>(
+            cs: &mut ConstraintSystem<F>,
+            new_columns: &mut Vec<Column<Fixed>>,
+        ) -> Expression<F> {
+            let column = cs.fixed_column();
+            new_columns.push(column);
+            Expression::Fixed(FixedQuery {
+                index: Some(cs.query_fixed_index(column, Rotation::cur())),
+                column_index: column.index,
+                rotation: Rotation::cur(),
+            })
+        }
+
         let mut new_columns = vec![];
-        let (polys, selector_assignment) = compress_selectors::process(
-            selectors
-                .into_iter()
-                .zip(degrees)
-                .enumerate()
-                .map(
-                    |(i, (activations, max_degree))| compress_selectors::SelectorDescription {
-                        selector: i,
-                        activations,
-                        max_degree,
-                    },
-                )
-                .collect(),
-            max_degree,
-            || {
-                let column = self.fixed_column();
-                new_columns.push(column);
-                Expression::Fixed(FixedQuery {
-                    index: Some(self.query_fixed_index(column, Rotation::cur())),
-                    column_index: column.index,
-                    rotation: Rotation::cur(),
-                })
-            },
-        );
+        let (mut polys, mut selector_assignment) =
+            compress_selectors::process(descriptions, max_degree, || {
+                allocate_column(&mut self, &mut new_columns)
+            });
+
+        // A combination is replaced with the product of the expressions of its
+        // selectors if that does not raise the degree of its gates above the
+        // degree of the circuit, and gets a fixed column otherwise, which can
+        // be shared with other combinations.
+        let mut products = vec![];
+        let mut combinations_with_columns = vec![];
+        for description in combinations {
+            let (_, combination) = self
+                .selector_combinations
+                .iter()
+                .find(|(index, _)| *index == description.selector)
+                .unwrap();
+            let assignment_of = |selector: Selector| {
+                selector_assignment
+                    .iter()
+                    .find(|assignment| assignment.selector == selector.0)
+                    .unwrap()
+            };
+            let product = combination.lower(&|selector| assignment_of(selector).expression.clone());
+            if description.max_degree == 0
+                || description.max_degree - 1 + product.degree() <= max_degree
+            {
+                let first = combination.selectors()[0];
+                products.push(compress_selectors::SelectorAssignment {
+                    selector: description.selector,
+                    combination_index: assignment_of(first).combination_index,
+                    expression: product,
+                    value: None,
+                });
+            } else {
+                combinations_with_columns.push(description);
+            }
+        }
+        let offset = polys.len();
+        let (combination_polys, combination_assignment) =
+            compress_selectors::process(combinations_with_columns, max_degree, || {
+                allocate_column(&mut self, &mut new_columns)
+            });
+        polys.extend(combination_polys);
+        selector_assignment.extend(products);
+        selector_assignment.extend(combination_assignment.into_iter().map(|assignment| {
+            compress_selectors::SelectorAssignment {
+                combination_index: offset + assignment.combination_index,
+                ..assignment
+            }
+        }));
 
         let mut selector_map = vec![None; selector_assignment.len()];
         let mut selector_replacements = vec![None; selector_assignment.len()];
+        let mut selector_values = vec![None; selector_assignment.len()];
         for assignment in selector_assignment {
             selector_replacements[assignment.selector] = Some(assignment.expression);
             selector_map[assignment.selector] = Some(new_columns[assignment.combination_index]);
+            selector_values[assignment.selector] = assignment
+                .value
+                .map(|value| (assignment.combination_index, value));
         }
+        self.selector_values = selector_values;
 
         self.selector_map = selector_map
             .into_iter()
@@ -2019,7 +3332,93 @@ impl<F: Field> ConstraintSystem<F> {
             .into_iter()
             .map(|a| a.unwrap())
             .collect::<Vec<_>>();
+        self.replace_selectors(selector_replacements);
 
+        (self, polys)
+    }
+
+    /// Converts each selector into a fixed column of its own, assigned 1 on
+    /// the rows the selector is enabled on and 0 elsewhere, without combining
+    /// selectors as [`Self::compress_selectors`] does. Selectors are replaced
+    /// with a query of their column at `Rotation::cur()`, so the degree of the
+    /// gates is unchanged. This `ConstraintSystem` is modified the same way as
+    /// by [`Self::compress_selectors`], and the returned polynomials are those
+    /// of the new fixed columns, in the order the selectors were allocated.
+    ///
+    /// Do not call this twice, nor along with [`Self::compress_selectors`].
+    pub fn selectors_to_fixed(mut self, selectors: Vec<Vec<bool>>) -> (Self, Vec<Vec<F>>) {
+        // The number of provided selector assignments must be the number we
+        // counted for this constraint system.
+        assert_eq!(selectors.len(), self.num_selectors);
+
+        let mut selectors = selectors;
+        self.activate_selector_combinations(&mut selectors);
+        let (polys, selector_replacements): (Vec<_>, Vec<_>) = selectors
+            .into_iter()
+            .enumerate()
+            .map(|(index, activations)| {
+                let column = self.fixed_column();
+                self.selector_map.push(column);
+                self.selector_values.push(Some((index, F::ONE)));
+                let poly = activations
+                    .into_iter()
+                    .map(|active| if active { F::ONE } else { F::ZERO })
+                    .collect();
+                let expression = Expression::Fixed(FixedQuery {
+                    index: Some(self.query_fixed_index(column, Rotation::cur())),
+                    column_index: column.index,
+                    rotation: Rotation::cur(),
+                });
+                (poly, expression)
+            })
+            .unzip();
+        self.replace_selectors(selector_replacements);
+
+        (self, polys)
+    }
+
+    /// Returns the values of the fixed columns that the selectors of this
+    /// constraint system were compressed into, as [`Self::compress_selectors`]
+    /// or [`Self::selectors_to_fixed`] returned them, for another assignment of
+    /// the selectors. The selectors are combined into the same columns as
+    /// they were then, so this returns `None` if two selectors sharing a
+    /// column are both enabled on a row of `selectors`, or if the selectors
+    /// of this constraint system were not converted into fixed columns.
+    pub(crate) fn selector_polys(&self, mut selectors: Vec<Vec<bool>>) -> Option<Vec<Vec<F>>> {
+        if selectors.len() != self.num_selectors || self.selector_values.len() != self.num_selectors
+        {
+            return None;
+        }
+        self.activate_selector_combinations(&mut selectors);
+
+        let n = selectors.first().map_or(0, |activations| activations.len());
+        let num_polys = self
+            .selector_values
+            .iter()
+            .flatten()
+            .map(|(poly, _)| poly + 1)
+            .max()
+            .unwrap_or(0);
+        let mut polys = vec![vec![F::ZERO; n]; num_polys];
+        for (activations, value) in selectors.iter().zip(self.selector_values.iter()) {
+            if let Some((poly, value)) = value {
+                for (cell, active) in polys[*poly].iter_mut().zip(activations) {
+                    if *active {
+                        if !bool::from(cell.is_zero()) {
+                            return None;
+                        }
+                        *cell = *value;
+                    }
+                }
+            }
+        }
+        Some(polys)
+    }
+
+    /// Substitutes `selector_replacements`, indexed by selector, for the
+    /// selectors of all gates, lookups and shuffles, once the selectors have
+    /// been converted into fixed columns.
+    fn replace_selectors(&mut self, selector_replacements: Vec<Expression<F>>) {
         fn replace_selectors<F: Field>(
             expr: &mut Expression<F>,
             selector_replacements: &[Expression<F>],
@@ -2073,7 +3472,86 @@ impl<F: Field> ConstraintSystem<F> {
             replace_selectors(expr, &selector_replacements, true);
         }
 
-        (self, polys)
+        self.selector_replacements = selector_replacements;
+        if self.pinned_version != PinnedVersion::V0 {
+            self.canonicalize_queries();
+        }
+    }
+
+    /// Sorts the queries of each column type by column and rotation, and
+    /// renumbers the queries of every expression to match. The order in which
+    /// queries are first made only depends on how `configure` happens to be
+    /// written, but it is the order of the evaluations in proofs and part of
+    /// the pinned constraint system.
+    fn canonicalize_queries(&mut self) {
+        fn sort<C: ColumnType>(queries: &mut [(Column<C>, Rotation)]) -> Vec<usize> {
+            let mut order: Vec<usize> = (0..queries.len()).collect();
+            order.sort_by_key(|&i| (queries[i].0.index, queries[i].1 .0));
+            let mut renumbered = vec![0; queries.len()];
+            for (new, &old) in order.iter().enumerate() {
+                renumbered[old] = new;
+            }
+            queries.sort_by_key(|(column, at)| (column.index, at.0));
+            renumbered
+        }
+
+        let fixed = sort(&mut self.fixed_queries);
+        let advice = sort(&mut self.advice_queries);
+        let instance = sort(&mut self.instance_queries);
+        let renumber = |expr: &mut Expression<F>| {
+            *expr = expr.evaluate(
+                &|constant| Expression::Constant(constant),
+                &|selector| Expression::Selector(selector),
+                &|query| {
+                    Expression::Fixed(FixedQuery {
+                        index: query.index.map(|index| fixed[index]),
+                        ..query
+                    })
+                },
+                &|query| {
+                    Expression::Advice(AdviceQuery {
+                        index: query.index.map(|index| advice[index]),
+                        ..query
+                    })
+                },
+                &|query| {
+                    Expression::Instance(InstanceQuery {
+                        index: query.index.map(|index| instance[index]),
+                        ..query
+                    })
+                },
+                &|challenge| Expression::Challenge(challenge),
+                &|a| -a,
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, f| a * f,
+            );
+        };
+
+        for expr in self.gates.iter_mut().flat_map(|gate| gate.polys.iter_mut()) {
+            renumber(expr);
+        }
+        for lookup in self.lookups.iter_mut() {
+            for expr in lookup
+                .input_expressions
+                .iter_mut()
+                .chain(lookup.table_expressions.iter_mut())
+            {
+                renumber(expr);
+            }
+        }
+        for shuffle in self.shuffles.iter_mut() {
+            for expr in shuffle
+                .input_expressions
+                .iter_mut()
+                .chain(shuffle.shuffle_expressions.iter_mut())
+            {
+                renumber(expr);
+            }
+        }
+        for expr in self.selector_replacements.iter_mut() {
+            renumber(expr);
+        }
     }
 
     /// Allocate a new (simple) selector. Simple selectors cannot be added to
@@ -2086,6 +3564,74 @@ impl<F: Field> ConstraintSystem<F> {
         Selector(index, true)
     }
 
+    /// Allocate a new simple selector that [`Self::compress_selectors`] does
+    /// not combine with other selectors, and converts into a fixed column of
+    /// its own instead, as it does for complex selectors.
+    ///
+    /// Combining selectors saves fixed columns, but replaces each of them with
+    /// an expression of a higher degree, up to the degree of the circuit: the
+    /// gates of a selector used on many rows are then more costly to evaluate.
+    /// The selector keeps the restrictions of [`Self::selector`].
+    ///
+    /// The selector is then replaced with its fixed column alone, which tools
+    /// that handle it specially, such as generated verifiers, can rely on:
+    /// see [`CompressedSelector::shared_with`](super::CompressedSelector::shared_with)
+    /// in the [compression map](super::VerifyingKey::selector_compression_map)
+    /// of a verifying key.
+    pub fn uncompressed_selector(&mut self) -> Selector {
+        let selector = self.selector();
+        self.uncompressed_selectors.push(selector.0);
+        selector
+    }
+
+    /// Returns whether `selector` was allocated with
+    /// [`Self::uncompressed_selector`], and so is never combined with other
+    /// selectors.
+    pub fn is_uncompressed_selector(&self, selector: Selector) -> bool {
+        self.uncompressed_selectors.contains(&selector.0)
+    }
+
+    /// Allocate a new (simple) selector named `name`; see
+    /// [`Self::selector_name`].
+    pub fn selector_named<N: Into<String>>(&mut self, name: N) -> Selector {
+        let selector = self.selector();
+        self.selector_names.insert(selector.0, name.into());
+        selector
+    }
+
+    /// Returns the simple selector standing for `combination`, allocating it
+    /// if it is new; see [`VirtualCells::selector_expr`].
+    fn selector_combination(&mut self, combination: SelectorExpr) -> Selector {
+        for selector in combination.selectors() {
+            assert!(
+                selector.0 < self.num_selectors,
+                "{:?} is not a selector of this constraint system",
+                selector
+            );
+        }
+        if let Some((index, _)) = self
+            .selector_combinations
+            .iter()
+            .find(|(_, other)| *other == combination)
+        {
+            return Selector(*index, true);
+        }
+        let selector = self.selector();
+        self.selector_combinations.push((selector.0, combination));
+        selector
+    }
+
+    /// Sets the activations of the selector combinations from those of the
+    /// selectors they are made of.
+    pub(crate) fn activate_selector_combinations(&self, selectors: &mut [Vec<bool>]) {
+        for (index, combination) in &self.selector_combinations {
+            let activations = (0..selectors[*index].len())
+                .map(|row| combination.is_active(&|selector| selectors[selector.0][row]))
+                .collect();
+            selectors[*index] = activations;
+        }
+    }
+
     /// Allocate a new complex selector that can appear anywhere
     /// within expressions.
     pub fn complex_selector(&mut self) -> Selector {
@@ -2139,11 +3685,71 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Allocate a new fixed column holding `values` on its first rows, and
+    /// zero on the others.
+    ///
+    /// The values are known at configure time and are the same for every
+    /// instance of the circuit, such as round constants: keygen writes them
+    /// into the column before synthesis, instead of the layouter assigning
+    /// them in a region. Assigning to the column during synthesis fails with
+    /// [`Error::PreassignedFixedColumn`](super::Error::PreassignedFixedColumn),
+    /// and keygen fails if there are more values than usable rows. A hash of
+    /// the values is [pinned](Self::pinned).
+    pub fn fixed_column_with_values(&mut self, values: Vec<F>) -> Column<Fixed> {
+        let column = self.fixed_column();
+        self.preassigned_fixed.push((column, values));
+        column
+    }
+
+    /// Returns the values of a fixed column allocated with
+    /// [`Self::fixed_column_with_values`], or `None` for other columns.
+    pub fn fixed_column_values(&self, column: Column<Fixed>) -> Option<&[F]> {
+        self.preassigned_fixed
+            .iter()
+            .find(|(preassigned, _)| *preassigned == column)
+            .map(|(_, values)| &values[..])
+    }
+
+    /// Allocate a new fixed column named `name`; see [`Self::column_name`].
+    pub fn fixed_column_named<N: Into<String>>(&mut self, name: N) -> Column<Fixed> {
+        let column = self.fixed_column();
+        self.name_column(column, name.into());
+        column
+    }
+
     /// Allocate a new advice column at `FirstPhase`
     pub fn advice_column(&mut self) -> Column<Advice> {
         self.advice_column_in(FirstPhase)
     }
 
+    /// Allocate a new advice column at `FirstPhase` named `name`; see
+    /// [`Self::column_name`].
+    pub fn advice_column_named<N: Into<String>>(&mut self, name: N) -> Column<Advice> {
+        let column = self.advice_column();
+        self.name_column(column, name.into());
+        column
+    }
+
+    /// Allocate a new advice column in the phase `P`, whose assignments with
+    /// [`Region::assign_advice_in_phase`] are checked against the phases of
+    /// the challenges they are computed from; see [`AdviceColumn`].
+    ///
+    /// ```
+    /// # use halo2curves::pasta::Fp;
+    /// use halo2_proofs::plonk::{AdviceColumn, ConstraintSystem, SecondPhase};
+    ///
+    /// # let mut meta = ConstraintSystem::<Fp>::default();
+    /// # meta.advice_column();
+    /// let column: AdviceColumn<SecondPhase> = meta.phased_advice_column();
+    /// assert_eq!(column.column().column_type().phase(), 1);
+    /// ```
+    pub fn phased_advice_column<P: TypedPhase>(&mut self) -> AdviceColumn<P> {
+        AdviceColumn {
+            column: self.advice_column_in(NthPhase(P::PHASE)),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
     /// Allocate a new advice column in given phase
     pub fn advice_column_in<P: Phase>(&mut self, phase: P) -> Column<Advice> {
         let phase = phase.to_sealed();
@@ -2164,6 +3770,34 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Allocate a new advice column that the prover does not blind.
+    ///
+    /// Its unusable rows are filled with zeroes rather than random values, and
+    /// it is committed with [`Blind::default`], so its commitment only depends
+    /// on the values of the column: anyone knowing them, for example because
+    /// they are derived from the instances, can compute it. This lets a
+    /// circuit aggregating proofs open the column cheaply, but gives up the
+    /// zero knowledge of its values.
+    ///
+    /// The verifying key commits to the columns that are unblinded.
+    pub fn unblinded_advice_column(&mut self) -> Column<Advice> {
+        self.unblinded_advice_column_in(FirstPhase)
+    }
+
+    /// Allocate a new advice column in given phase that the prover does not
+    /// blind; see [`Self::unblinded_advice_column`].
+    pub fn unblinded_advice_column_in<P: Phase>(&mut self, phase: P) -> Column<Advice> {
+        let column = self.advice_column_in(phase);
+        self.unblinded_advice_columns.push(column.index());
+        column
+    }
+
+    /// Returns whether the prover blinds the advice column with the given
+    /// index.
+    pub(crate) fn is_advice_column_blinded(&self, index: usize) -> bool {
+        !self.unblinded_advice_columns.contains(&index)
+    }
+
     /// Allocate a new instance column
     pub fn instance_column(&mut self) -> Column<Instance> {
         let tmp = Column {
@@ -2174,6 +3808,21 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Allocate a new instance column named `name`; see [`Self::column_name`].
+    pub fn instance_column_named<N: Into<String>>(&mut self, name: N) -> Column<Instance> {
+        let column = self.instance_column();
+        self.name_column(column, name.into());
+        column
+    }
+
+    fn name_column<C: Into<Column<Any>>>(&mut self, column: C, name: String) {
+        let column = column.into();
+        self.general_column_annotations.insert(
+            metadata::Column::from((column.column_type, column.index)),
+            name,
+        );
+    }
+
     /// Requests a challenge that is usable after the given phase.
     pub fn challenge_usable_after<P: Phase>(&mut self, phase: P) -> Challenge {
         let phase = phase.to_sealed();
@@ -2191,6 +3840,98 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Returns a challenge whose value is `challenge` raised to `exponent`.
+    ///
+    /// It is usable after the same phase as `challenge`, and is an ordinary
+    /// challenge for the circuit: it can be queried in gates, where it is a
+    /// single expression of degree 0, and read during synthesis. But it is
+    /// not squeezed from the transcript: the prover and the verifier compute
+    /// it once per proof from `challenge`, rather than every expression
+    /// multiplying copies of `challenge`. Requesting the same power twice
+    /// returns the same challenge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `exponent` is 0.
+    pub fn challenge_power(&mut self, challenge: Challenge, exponent: u32) -> Challenge {
+        assert!(
+            exponent > 0,
+            "a challenge power must have a positive exponent"
+        );
+        // Powers are always taken of a squeezed challenge.
+        let (base, exponent) = match self
+            .challenge_powers
+            .iter()
+            .find(|(index, _, _)| *index == challenge.index)
+        {
+            Some(&(_, base, power)) => (base, power * exponent),
+            None => (challenge.index, exponent),
+        };
+        if exponent == 1 {
+            return Challenge {
+                index: base,
+                phase: challenge.phase,
+            };
+        }
+        if let Some(&(index, _, _)) = self
+            .challenge_powers
+            .iter()
+            .find(|(_, b, e)| *b == base && *e == exponent)
+        {
+            return Challenge {
+                index,
+                phase: challenge.phase,
+            };
+        }
+
+        let power = Challenge {
+            index: self.num_challenges,
+            phase: challenge.phase,
+        };
+        self.num_challenges += 1;
+        self.challenge_phase.push(challenge.phase);
+        self.challenge_powers.push((power.index, base, exponent));
+        power
+    }
+
+    /// Returns the expressions of the powers `challenge^1` to `challenge^n`,
+    /// each a single challenge of degree 0; see [`Self::challenge_power`].
+    ///
+    /// This is the sequence of coefficients of a random linear combination of
+    /// `n` expressions.
+    pub fn challenge_powers(&mut self, challenge: Challenge, n: u32) -> Vec<Expression<F>> {
+        (1..=n)
+            .map(|exponent| self.challenge_power(challenge, exponent).expr())
+            .collect()
+    }
+
+    /// Returns whether the challenge with the given index is squeezed from
+    /// the transcript, rather than a power of another challenge.
+    pub(crate) fn is_challenge_squeezed(&self, index: usize) -> bool {
+        self.challenge_powers
+            .iter()
+            .all(|(challenge, _, _)| *challenge != index)
+    }
+
+    /// Sets the challenges that are powers of another from the values of the
+    /// squeezed ones.
+    pub(crate) fn compute_challenge_powers(&self, challenges: &mut [F]) {
+        for &(challenge, base, exponent) in &self.challenge_powers {
+            challenges[challenge] = challenges[base].pow_vartime([exponent as u64]);
+        }
+    }
+
+    /// Returns the challenges requested so far, in the order of their indices.
+    pub(crate) fn challenges(&self) -> impl Iterator<Item = Challenge> + '_ {
+        self.challenge_phase
+            .iter()
+            .enumerate()
+            .map(|(index, phase)| Challenge {
+                index,
+                phase: *phase,
+            })
+    }
+
     /// Helper funciotn to assert phase exists, to make sure phase-aware resources
     /// are allocated in order, and to avoid any phase to be skipped accidentally
     /// to cause unexpected issue in the future.
@@ -2207,13 +3948,7 @@ impl<F: Field> ConstraintSystem<F> {
     }
 
     pub(crate) fn phases(&self) -> impl Iterator<Item = sealed::Phase> {
-        let max_phase = self
-            .advice_column_phase
-            .iter()
-            .max()
-            .map(|phase| phase.0)
-            .unwrap_or_default();
-        (0..=max_phase).map(sealed::Phase)
+        (0..=self.max_phase()).map(sealed::Phase)
     }
 
     /// Compute the degree of the constraint system (the maximum degree of all
@@ -2259,22 +3994,89 @@ impl<F: Field> ConstraintSystem<F> {
         std::cmp::max(degree, self.minimum_degree.unwrap_or(1))
     }
 
-    /// Compute the number of blinding factors necessary to perfectly blind
-    /// each of the prover's witness polynomials.
-    pub fn blinding_factors(&self) -> usize {
-        // All of the prover's advice columns are evaluated at no more than
-        let factors = *self.num_advice_queries.iter().max().unwrap_or(&1);
-        // distinct points during gate checks.
+    /// Returns the highest degree of the constraints the prover evaluates on
+    /// the extended domain, along with the first constraint of that degree,
+    /// or `None` if there are no constraints.
+    ///
+    /// Unlike [`Self::degree`], this leaves out the degree set with
+    /// [`Self::set_minimum_degree`] and the degree the permutation argument
+    /// would need if it had columns, but counts the columns the permutation
+    /// argument fits into each of its constraints, as many as the degree of
+    /// the circuit allows.
+    pub fn highest_degree_constraint(&self) -> Option<(usize, DegreeSource)> {
+        let gates = self.gates.iter().enumerate().flat_map(|(gate, g)| {
+            g.polynomials().iter().map(move |poly| {
+                let name = g.name.clone();
+                (poly.degree(), DegreeSource::Gate { gate, name })
+            })
+        });
+        let lookups = self.lookups.iter().enumerate().map(|(lookup, l)| {
+            let name = l.name.clone();
+            (l.required_degree(), DegreeSource::Lookup { lookup, name })
+        });
+        let shuffles = self.shuffles.iter().enumerate().map(|(shuffle, s)| {
+            let name = s.name.clone();
+            (s.required_degree(), DegreeSource::Shuffle { shuffle, name })
+        });
+        let columns = self.permutation.columns.len();
+        let permutation = (columns > 0).then(|| {
+            let degree = max(3, columns.min(self.degree() - 2) + 2);
+            (degree, DegreeSource::Permutation)
+        });
+
+        gates
+            .chain(lookups)
+            .chain(shuffles)
+            .chain(permutation)
+            .fold(None, |highest, (degree, source)| match highest {
+                Some(highest) if highest.0 >= degree => Some(highest),
+                _ => Some((degree, source)),
+            })
+    }
 
-        // - The permutation argument witness polynomials are evaluated at most 3 times.
-        // - Each lookup argument has independent witness polynomials, and they are
-        //   evaluated at most 2 times.
-        let factors = std::cmp::max(3, factors);
+    /// Returns the smallest size of the extended domain, as `extended_k`,
+    /// with which the prover can compute the quotient polynomial of this
+    /// circuit with `2^k` rows: the quotient of a constraint of degree `d`
+    /// has degree below `(d - 1) 2^k`, so the extended domain must have at
+    /// least that many rows for [`Self::highest_degree_constraint`].
+    pub fn minimum_extended_k(&self, k: u32) -> u32 {
+        let degree = self
+            .highest_degree_constraint()
+            .map_or(1, |(degree, _)| degree);
+        let mut extended_k = k;
+        while (1u64 << extended_k) < (1u64 << k) * degree.saturating_sub(1) as u64 {
+            extended_k += 1;
+        }
+        extended_k
+    }
+
+    /// Fails with [`Error::ExtendedDomainTooSmall`] if the size of the
+    /// extended domain set with [`Self::set_extended_k`] is below
+    /// [`Self::minimum_extended_k`] for `2^k` rows.
+    pub(crate) fn check_extended_k(&self, k: u32) -> Result<(), Error> {
+        let minimum = self.minimum_extended_k(k);
+        match (self.extended_k, self.highest_degree_constraint()) {
+            (Some(extended_k), Some((degree, constraint))) if extended_k < minimum => {
+                Err(Error::ExtendedDomainTooSmall {
+                    extended_k,
+                    minimum,
+                    degree,
+                    constraint,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
 
-        // Each polynomial is evaluated at most an additional time during
-        // multiopen (at x_3 to produce q_evals):
-        let factors = factors + 1;
+    /// Compute the number of blinding factors necessary to perfectly blind
+    /// each of the prover's witness polynomials; see
+    /// [`Self::blinding_factors_breakdown`] for how it is derived.
+    pub fn blinding_factors(&self) -> usize {
+        self.blinding_factors_breakdown().total()
+    }
 
+    /// Returns the inputs the number of blinding factors is derived from.
+    pub fn blinding_factors_breakdown(&self) -> BlindingFactorsBreakdown {
         // h(x) is derived by the other evaluations so it does not reveal
         // anything; in fact it does not even appear in the proof.
 
@@ -2282,22 +4084,88 @@ impl<F: Field> ConstraintSystem<F> {
         // evaluation of a polynomial in x_1 which has h(x_3) and another random
         // polynomial evaluated at x_3 as coefficients -- this random polynomial
         // is "random_poly" in the vanishing argument.
+        BlindingFactorsBreakdown {
+            zero_knowledge: self.zero_knowledge,
+            // All of the prover's blinded advice columns are evaluated at no
+            // more than this many distinct points during gate checks.
+            max_advice_queries: self.max_blinded_advice_queries(),
+            // The permutation argument witness polynomials are evaluated at
+            // most 3 times, and those of the lookup arguments at most 2 times.
+            argument_queries: 3,
+        }
+    }
+
+    /// Samples a scalar blinding a witness polynomial from `rng`, or returns
+    /// zero if zero knowledge is disabled.
+    pub(crate) fn blinding_scalar<R: RngCore>(&self, rng: R) -> F {
+        if self.zero_knowledge {
+            F::random(rng)
+        } else {
+            F::ZERO
+        }
+    }
 
-        // Add an additional blinding factor as a slight defense against
-        // off-by-one errors.
-        factors + 1
+    /// Samples the blind of a commitment from `rng`, or returns
+    /// [`Blind::default`] if zero knowledge is disabled.
+    pub(crate) fn blind<R: RngCore>(&self, rng: R) -> Blind<F> {
+        if self.zero_knowledge {
+            Blind(F::random(rng))
+        } else {
+            Blind::default()
+        }
     }
 
     /// Returns the minimum necessary rows that need to exist in order to
-    /// account for e.g. blinding factors.
+    /// account for e.g. blinding factors; see
+    /// [`Self::minimum_rows_breakdown`] for what they are made of.
     pub fn minimum_rows(&self) -> usize {
-        self.blinding_factors() // m blinding factors
-            + 1 // for l_{-(m + 1)} (l_last)
-            + 1 // for l_0 (just for extra breathing room for the permutation
-                // argument, to essentially force a separation in the
-                // permutation polynomial between the roles of l_last, l_0
-                // and the interstitial values.)
-            + 1 // for at least one row
+        self.minimum_rows_breakdown().total()
+    }
+
+    /// Returns the largest number of distinct queries to a blinded advice
+    /// column, or 1 if there is none.
+    fn max_blinded_advice_queries(&self) -> usize {
+        self.num_advice_queries
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.is_advice_column_blinded(*index))
+            .map(|(_, queries)| *queries)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Returns what the minimum number of rows of the circuit is made of.
+    pub fn minimum_rows_breakdown(&self) -> MinimumRowsBreakdown {
+        let rotations = self
+            .advice_queries
+            .iter()
+            .map(|(_, rotation)| rotation.0)
+            .chain(self.fixed_queries.iter().map(|(_, rotation)| rotation.0))
+            .chain(self.instance_queries.iter().map(|(_, rotation)| rotation.0));
+
+        let blinding_factors = self.blinding_factors_breakdown();
+        MinimumRowsBreakdown {
+            zero_knowledge: blinding_factors.zero_knowledge,
+            max_advice_queries: blinding_factors.max_advice_queries,
+            argument_queries: blinding_factors.argument_queries,
+            blinding_factors: blinding_factors.total(),
+            max_rotation: rotations.clone().max().unwrap_or(0).max(0),
+            min_rotation: rotations.min().unwrap_or(0).min(0),
+        }
+    }
+
+    /// Returns the rows a circuit of `2^k` rows can assign, which the
+    /// [`MockProver`](crate::dev::MockProver), keygen and the prover all
+    /// agree on. The rows after them hold the blinding factors and the row
+    /// the grand products end on.
+    ///
+    /// A circuit can be keyed and proven at any `k` larger than it needs: the
+    /// extra rows are left unassigned, and a table's column is padded with
+    /// its filler value up to the end of the usable rows, which does not
+    /// change the set of values a lookup accepts. An instance column can hold
+    /// at most as many values as there are usable rows.
+    pub fn usable_rows(&self, k: u32) -> Range<usize> {
+        0..(1 << k) - (self.blinding_factors() + 1)
     }
 
     /// Returns number of fixed columns
@@ -2310,6 +4178,11 @@ impl<F: Field> ConstraintSystem<F> {
         self.num_advice_columns
     }
 
+    /// Returns the indices of the advice columns the prover does not blind
+    pub fn unblinded_advice_columns(&self) -> &[usize] {
+        &self.unblinded_advice_columns
+    }
+
     /// Returns number of instance columns
     pub fn num_instance_columns(&self) -> usize {
         self.num_instance_columns
@@ -2325,7 +4198,7 @@ impl<F: Field> ConstraintSystem<F> {
         self.num_challenges
     }
 
-    /// Returns phase of advice columns
+    /// Returns phase of advice columns, indexed by column
     pub fn advice_column_phase(&self) -> Vec<u8> {
         self.advice_column_phase
             .iter()
@@ -2333,14 +4206,83 @@ impl<F: Field> ConstraintSystem<F> {
             .collect()
     }
 
-    /// Returns phase of challenges
-    pub fn challenge_phase(&self) -> Vec<u8> {
+    /// Returns phase of challenges, indexed by challenge
+    pub fn challenge_phases(&self) -> Vec<u8> {
         self.challenge_phase.iter().map(|phase| phase.0).collect()
     }
 
-    /// Returns gates
-    pub fn gates(&self) -> &Vec<Gate<F>> {
-        &self.gates
+    /// Returns the phase after which `challenge` is squeezed, or computed
+    /// if it is a power of another challenge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `challenge` is not a challenge of this constraint system.
+    pub fn challenge_phase(&self, challenge: Challenge) -> u8 {
+        self.challenge_phase
+            .get(challenge.index)
+            .unwrap_or_else(|| {
+                panic!(
+                    "{:?} is not a challenge of this constraint system",
+                    challenge
+                )
+            })
+            .0
+    }
+
+    /// Returns the last phase of the circuit, the largest phase of its
+    /// advice columns. Witnesses are synthesized, and advice columns
+    /// committed to, in each phase from 0 up to this one.
+    pub fn max_phase(&self) -> u8 {
+        self.advice_column_phase
+            .iter()
+            .max()
+            .map(|phase| phase.0)
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of advice columns in `phase`, whose commitments
+    /// the prover writes together once the phase is synthesized.
+    pub fn num_advice_columns_in_phase(&self, phase: u8) -> usize {
+        self.advice_column_phase
+            .iter()
+            .filter(|column_phase| column_phase.0 == phase)
+            .count()
+    }
+
+    /// Returns the advice columns in `phase`, by index.
+    pub fn advice_columns_in_phase(&self, phase: u8) -> Vec<Column<Advice>> {
+        self.advice_column_phase
+            .iter()
+            .enumerate()
+            .filter(|(_, column_phase)| column_phase.0 == phase)
+            .map(|(index, column_phase)| {
+                Column::new(
+                    index,
+                    Advice {
+                        phase: *column_phase,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the queries of the advice columns in `phase`, in the order
+    /// of [`Self::advice_queries`].
+    pub fn advice_queries_in_phase(&self, phase: u8) -> Vec<(Column<Advice>, Rotation)> {
+        self.advice_queries
+            .iter()
+            .filter(|(column, _)| column.column_type().phase.0 == phase)
+            .copied()
+            .collect()
+    }
+
+    /// Returns the custom gates, in the order they were created; part of
+    /// the reflection API, see [`GateView`].
+    pub fn gates(&self) -> impl ExactSizeIterator<Item = GateView<'_, F>> {
+        self.gates
+            .iter()
+            .enumerate()
+            .map(|(index, gate)| GateView::new(index, gate))
     }
 
     /// Returns general column annotations
@@ -2348,6 +4290,114 @@ impl<F: Field> ConstraintSystem<F> {
         &self.general_column_annotations
     }
 
+    /// Returns the name of a column, given when it was allocated or with
+    /// [`Self::annotate_lookup_any_column`].
+    ///
+    /// Unlike the annotations given during synthesis, names are known to
+    /// tools that only configure the circuit. They are not part of the
+    /// [pinned](Self::pinned) constraint system, so they do not change the
+    /// verifying key.
+    pub fn column_name<C: Into<Column<Any>>>(&self, column: C) -> Option<&str> {
+        let column = column.into();
+        self.general_column_annotations
+            .get(&metadata::Column::from((column.column_type, column.index)))
+            .map(String::as_str)
+    }
+
+    /// Returns the name of a selector, given when it was allocated with
+    /// [`Self::selector_named`].
+    pub fn selector_name(&self, selector: Selector) -> Option<&str> {
+        self.selector_names.get(&selector.0).map(String::as_str)
+    }
+
+    /// Pins the columns of the given names to the given indices, among the
+    /// columns of their type, for tools that address columns by index.
+    ///
+    /// Adding a column to `configure` renumbers the columns of its type that
+    /// are allocated after it; pinning the columns such a tool uses turns
+    /// this into an error, rather than a witness assigned to the wrong
+    /// columns. A name is given to a column when it is allocated, for
+    /// example with [`Self::advice_column_named`], and pinning it again
+    /// replaces the index it was pinned at. Pins can be added before or
+    /// after the columns are allocated: [`Self::check_column_pins`] compares
+    /// them with the columns once the circuit is configured, which keygen and
+    /// [`MockProver`](crate::dev::MockProver) do.
+    ///
+    /// Like names, pins are not part of the [pinned](Self::pinned) constraint
+    /// system, and the columns they resolve to are exported with the
+    /// verifying key; see [`VkExport::names`](super::VkExport::names).
+    ///
+    /// ```
+    /// # use halo2curves::pasta::Fp;
+    /// use halo2_proofs::plonk::ConstraintSystem;
+    ///
+    /// # let mut meta = ConstraintSystem::<Fp>::default();
+    /// meta.pin_columns(&[("state", 0), ("round constant", 0)]);
+    /// let _ = meta.advice_column_named("state");
+    /// let _ = meta.fixed_column_named("round constant");
+    /// assert!(meta.check_column_pins().is_ok());
+    /// ```
+    pub fn pin_columns(&mut self, pins: &[(&str, usize)]) {
+        for &(name, index) in pins {
+            match self
+                .column_pins
+                .iter_mut()
+                .find(|(pinned, _)| pinned == name)
+            {
+                Some(pin) => pin.1 = index,
+                None => self.column_pins.push((name.to_string(), index)),
+            }
+        }
+    }
+
+    /// Returns the names and indices the columns were pinned at with
+    /// [`Self::pin_columns`], in the order they were first pinned.
+    pub fn column_pins(&self) -> &[(String, usize)] {
+        &self.column_pins
+    }
+
+    /// Returns the column named `name` that was pinned with
+    /// [`Self::pin_columns`], if it was allocated at its pinned index.
+    pub fn pinned_column(&self, name: &str) -> Option<Column<Any>> {
+        let &(_, index) = self.column_pins.iter().find(|(pinned, _)| pinned == name)?;
+        self.columns_named(name)
+            .find(|column| column.index() == index)
+    }
+
+    /// Returns the columns pinned with [`Self::pin_columns`] that were not
+    /// allocated at their pinned index, in the order they were pinned.
+    pub fn column_drift(&self) -> Vec<ColumnDrift> {
+        self.column_pins
+            .iter()
+            .filter(|(name, _)| self.pinned_column(name).is_none())
+            .map(|(name, expected)| ColumnDrift {
+                name: name.clone(),
+                expected: *expected,
+                actual: self.columns_named(name).min(),
+            })
+            .collect()
+    }
+
+    /// Fails with [`Error::ColumnDrift`] listing [`Self::column_drift`] if
+    /// any column pinned with [`Self::pin_columns`] was not allocated at its
+    /// pinned index.
+    pub fn check_column_pins(&self) -> Result<(), Error> {
+        let drift = self.column_drift();
+        if drift.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ColumnDrift(drift))
+        }
+    }
+
+    /// Returns the columns named `name`, in no particular order.
+    fn columns_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Column<Any>> + 'a {
+        self.general_column_annotations
+            .iter()
+            .filter(move |(_, annotation)| *annotation == name)
+            .map(|(column, _)| Column::new(column.index(), column.column_type()))
+    }
+
     /// Returns advice queries
     pub fn advice_queries(&self) -> &Vec<(Column<Advice>, Rotation)> {
         &self.advice_queries
@@ -2363,19 +4413,51 @@ impl<F: Field> ConstraintSystem<F> {
         &self.fixed_queries
     }
 
-    /// Returns permutation argument
-    pub fn permutation(&self) -> &permutation::Argument {
-        &self.permutation
+    /// Returns the permutation argument; part of the reflection API, see
+    /// [`GateView`].
+    pub fn permutation(&self) -> PermutationView<'_> {
+        PermutationView::new(&self.permutation.columns)
+    }
+
+    /// Returns the columns equality is enabled on, in the order they appear
+    /// in the permutation argument: the order of their first call to
+    /// [`Self::enable_equality`] or [`Self::enable_constant`].
+    pub fn permutation_columns(&self) -> &[Column<Any>] {
+        &self.permutation.columns
     }
 
-    /// Returns lookup arguments
-    pub fn lookups(&self) -> &Vec<lookup::Argument<F>> {
-        &self.lookups
+    /// Returns the number of columns whose permutation is checked by each
+    /// grand product of the permutation argument, which is as many as fit
+    /// in the degree of the constraint system.
+    pub fn permutation_chunk_size(&self) -> usize {
+        self.degree() - 2
     }
 
-    /// Returns shuffle arguments
-    pub fn shuffles(&self) -> &Vec<shuffle::Argument<F>> {
-        &self.shuffles
+    /// Returns the sets of columns checked by each grand product of the
+    /// permutation argument, in the order the prover commits to the grand
+    /// products.
+    pub fn permutation_chunks(&self) -> impl ExactSizeIterator<Item = &[Column<Any>]> {
+        self.permutation
+            .columns
+            .chunks(self.permutation_chunk_size())
+    }
+
+    /// Returns the lookup arguments, in the order they were created; part of
+    /// the reflection API, see [`GateView`].
+    pub fn lookups(&self) -> impl ExactSizeIterator<Item = LookupView<'_, F>> {
+        self.lookups
+            .iter()
+            .enumerate()
+            .map(|(index, argument)| LookupView::new(index, argument))
+    }
+
+    /// Returns the shuffle arguments, in the order they were created; part of
+    /// the reflection API, see [`GateView`].
+    pub fn shuffles(&self) -> impl ExactSizeIterator<Item = ShuffleView<'_, F>> {
+        self.shuffles
+            .iter()
+            .enumerate()
+            .map(|(index, argument)| ShuffleView::new(index, argument))
     }
 
     /// Returns constants
@@ -2408,6 +4490,29 @@ impl<'a, F: Field> VirtualCells<'a, F> {
         Expression::Selector(selector)
     }
 
+    /// Query a boolean combination of selectors at the current position,
+    /// which is 1 on the rows it is active on and 0 elsewhere; see
+    /// [`SelectorExpr`].
+    ///
+    /// It is a simple selector of its own, of degree 1, that the layouter
+    /// never enables. When the selectors are compressed, it is replaced with
+    /// the product of the expressions of the selectors it is made of if that
+    /// does not raise the degree of the circuit, and with a fixed column
+    /// assigned from the combination of their activations otherwise. Rather
+    /// than multiplying selectors, this keeps the degree of the gate from
+    /// growing with the number of selectors combined, and the selectors it is
+    /// made of simple. Querying the same combination twice returns the same
+    /// selector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a selector of the combination is not one of this constraint
+    /// system.
+    pub fn selector_expr<S: Into<SelectorExpr>>(&mut self, combination: S) -> Expression<F> {
+        let selector = self.meta.selector_combination(combination.into());
+        self.query_selector(selector)
+    }
+
     /// Query a fixed column at a relative position
     pub fn query_fixed(&mut self, column: Column<Fixed>, at: Rotation) -> Expression<F> {
         self.queried_cells.push((column, at).into());
@@ -2497,4 +4602,691 @@ mod tests {
 
         assert_eq!(happened, expected);
     }
+
+    #[test]
+    fn reflection_views() {
+        use super::{Any, Column, ConstraintSystem};
+        use crate::poly::Rotation;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let q = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let f = meta.fixed_column();
+        meta.enable_equality(b);
+        meta.create_gate("product", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![
+                ("b = a^2", q.clone() * (b - a.clone() * a.clone())),
+                ("next = a", q * (next - a)),
+            ]
+        });
+        meta.lookup_any("a in f", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            vec![(q * a, meta.query_fixed(f, Rotation::cur()))]
+        });
+        meta.shuffle("b is a", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![(q * a, b)]
+        });
+
+        assert_eq!(meta.gates().len(), 1);
+        let gate = meta.gates().next().unwrap();
+        assert_eq!((gate.index(), gate.name()), (0, "product"));
+        assert_eq!(
+            gate.constraint_names().collect::<Vec<_>>(),
+            ["b = a^2", "next = a"]
+        );
+        assert_eq!(gate.polynomials().len(), 2);
+        for ((name, polynomial), expected) in gate.constraints().zip(gate.polynomials()) {
+            assert!(!name.is_empty());
+            assert_eq!(polynomial, expected);
+        }
+        assert_eq!(gate.queried_selectors(), [q]);
+        let cells: Vec<(Column<Any>, i32)> = gate
+            .queried_cells()
+            .iter()
+            .map(|cell| (cell.column(), cell.rotation().0))
+            .collect();
+        let (a, b) = (Column::<Any>::from(a), Column::<Any>::from(b));
+        assert_eq!(cells, [(a, 0), (a, 1), (b, 0)]);
+
+        let lookups: Vec<_> = meta.lookups().collect();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!((lookups[0].index(), lookups[0].name()), (0, "a in f"));
+        assert_eq!(lookups[0].input_expressions().len(), 1);
+        assert_eq!(lookups[0].table_expressions().len(), 1);
+
+        let shuffles: Vec<_> = meta.shuffles().collect();
+        assert_eq!(shuffles.len(), 1);
+        assert_eq!((shuffles[0].index(), shuffles[0].name()), (0, "b is a"));
+        assert_eq!(shuffles[0].input_expressions().len(), 1);
+        assert_eq!(shuffles[0].shuffle_expressions().len(), 1);
+
+        assert_eq!(meta.permutation().columns(), [b]);
+    }
+
+    #[test]
+    fn simplify_expressions() {
+        use super::{sealed, AdviceQuery, ConstraintSystem, Selector};
+        use crate::poly::Rotation;
+        use ff::Field;
+
+        let advice = |column_index| {
+            Expression::<Fr>::Advice(AdviceQuery {
+                index: Some(column_index),
+                column_index,
+                rotation: Rotation::cur(),
+                phase: sealed::Phase(0),
+            })
+        };
+        let (q, a, b) = (
+            Expression::Selector(Selector(0, true)),
+            advice(0),
+            advice(1),
+        );
+        let constant = |c: Fr| Expression::Constant(c);
+        let (zero, one) = (constant(Fr::ZERO), constant(Fr::ONE));
+
+        assert_eq!((a.clone() * one.clone() + zero.clone()).simplify(), a);
+        assert_eq!(
+            ((a.clone() + one.clone()) + (one.clone() + one.clone())).simplify(),
+            a.clone() + constant(Fr::from(3))
+        );
+        assert_eq!((-(-a.clone())).simplify(), a);
+        assert_eq!(
+            ((b.clone() - a.clone()) * one.clone() - one.clone()).simplify(),
+            b.clone() - a.clone() + constant(-Fr::ONE)
+        );
+
+        let gated = q.clone() * (a.clone() * a.clone() * a.clone() * zero + b.clone());
+        assert_eq!(
+            (gated.degree(), gated.node_count(), gated.query_count()),
+            (4, 11, 5)
+        );
+        let simplified = gated.simplify();
+        assert_eq!(simplified, q * b);
+        assert_eq!(
+            (
+                simplified.degree(),
+                simplified.node_count(),
+                simplified.query_count()
+            ),
+            (2, 3, 2)
+        );
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let q = meta.selector();
+        let a = meta.advice_column();
+        meta.create_gate("gadget", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let zero = Expression::Constant(Fr::ZERO);
+            vec![q * (a.clone() * a.clone() * a.clone() * zero + a)]
+        });
+        let polynomials = meta.gates[0].polynomials().to_vec();
+        assert_eq!(meta.degree(), 4);
+        meta.simplify_gates();
+        assert_eq!(
+            meta.gates[0].polynomials(),
+            polynomials
+                .iter()
+                .map(Expression::simplify)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(meta.degree(), 3);
+    }
+
+    #[test]
+    fn minimum_rows_breakdown() {
+        use super::{ConstraintSystem, MinimumRowsBreakdown};
+        use crate::poly::Rotation;
+
+        let cs = |rotations: &[i32]| {
+            let mut meta = ConstraintSystem::<Fr>::default();
+            let q = meta.selector();
+            let a = meta.advice_column();
+            meta.create_gate("rotations", |meta| {
+                let q = meta.query_selector(q);
+                rotations
+                    .iter()
+                    .map(|rotation| q.clone() * meta.query_advice(a, Rotation(*rotation)))
+                    .collect::<Vec<_>>()
+            });
+            meta
+        };
+
+        // Four queries to the advice column take more blinding factors than
+        // the permutation argument.
+        let meta = cs(&[-1, 0, 1, 2]);
+        let breakdown = meta.minimum_rows_breakdown();
+        assert_eq!(
+            breakdown,
+            MinimumRowsBreakdown {
+                zero_knowledge: true,
+                max_advice_queries: 4,
+                argument_queries: 3,
+                blinding_factors: 6,
+                max_rotation: 2,
+                min_rotation: -1,
+            }
+        );
+        assert_eq!((breakdown.rotation_span(), breakdown.total()), (4, 9));
+        assert_eq!(meta.minimum_rows(), 9);
+        assert_eq!(
+            breakdown.to_string(),
+            "the circuit needs at least 9 rows: 6 blinding factors (the most queries to a blinded advice column, 4, or 3 for the permutation and lookup arguments if more, plus 1 for the multiopen argument and 1 for safety), plus 1 row for l_last, 1 for l_0 and 1 usable row, for 9 rows; and 4 rows for the rotations from -1 to 2 queried"
+        );
+
+        // A gate reaching 20 rows ahead needs more rows than the blinding
+        // factors.
+        let mut meta = cs(&[0, 20]);
+        let breakdown = meta.minimum_rows_breakdown();
+        assert_eq!(breakdown.blinding_factors, 5);
+        assert_eq!((breakdown.min_rotation, breakdown.max_rotation), (0, 20));
+        assert_eq!((breakdown.rotation_span(), breakdown.total()), (21, 21));
+        assert_eq!(meta.minimum_rows(), 21);
+
+        meta.disable_zero_knowledge();
+        let breakdown = meta.minimum_rows_breakdown();
+        assert_eq!((breakdown.blinding_factors, breakdown.total()), (0, 21));
+        assert!(breakdown
+            .to_string()
+            .contains("no blinding factors (zero knowledge is disabled)"));
+    }
+
+    #[test]
+    fn highest_degree_constraint() {
+        use super::{ConstraintSystem, DegreeSource};
+        use crate::poly::Rotation;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        assert_eq!(meta.highest_degree_constraint(), None);
+        assert_eq!(meta.minimum_extended_k(4), 4);
+
+        // The permutation argument fits both columns into a constraint of
+        // degree 4, however large the degree of the circuit.
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        meta.enable_equality(a);
+        meta.enable_equality(b);
+        meta.set_minimum_degree(9);
+        assert_eq!(
+            meta.highest_degree_constraint(),
+            Some((4, DegreeSource::Permutation))
+        );
+        assert_eq!(meta.minimum_extended_k(4), 6);
+
+        // The first constraint of the highest degree is reported.
+        for name in ["power", "other power"] {
+            meta.create_gate(name, |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![a.clone() * a.clone() * a.clone() * a]
+            });
+        }
+        assert_eq!(
+            meta.highest_degree_constraint(),
+            Some((
+                4,
+                DegreeSource::Gate {
+                    gate: 0,
+                    name: "power".to_string()
+                }
+            ))
+        );
+        assert_eq!(meta.degree(), 9);
+    }
+
+    #[test]
+    fn blinding_factors_breakdown() {
+        use super::{BlindingFactorsBreakdown, ConstraintSystem};
+        use crate::poly::Rotation;
+
+        // A gate querying a blinded advice column at two rotations, with a
+        // lookup, a shuffle and copy constraints.
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let q = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+        let table = meta.lookup_table_column();
+        meta.enable_equality(a);
+        meta.enable_equality(instance);
+        meta.create_gate("step", |meta| {
+            let q = meta.query_selector(q);
+            let cur = meta.query_advice(a, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            vec![q * (next - cur)]
+        });
+        meta.lookup("range", |meta| {
+            let q = meta.query_selector(q);
+            vec![(q * meta.query_advice(a, Rotation::cur()), table)]
+        });
+        meta.shuffle("shuffle", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![(a, b)]
+        });
+
+        let breakdown = meta.blinding_factors_breakdown();
+        assert_eq!(
+            breakdown,
+            BlindingFactorsBreakdown {
+                zero_knowledge: true,
+                max_advice_queries: 2,
+                argument_queries: 3,
+            }
+        );
+        assert_eq!(
+            (breakdown.multiopen_queries(), breakdown.safety_margin()),
+            (1, 1)
+        );
+        assert_eq!(breakdown.total(), 5);
+        assert_eq!(meta.blinding_factors(), 5);
+        assert_eq!(meta.usable_rows(4), 0..10);
+
+        // Queries to fixed, instance and unblinded advice columns leave the
+        // number unchanged, and so do queries to blinded advice columns up
+        // to three rotations.
+        let fixed = meta.fixed_column();
+        let unblinded = meta.unblinded_advice_column();
+        meta.create_gate("more queries", |meta| {
+            let q = meta.query_selector(q);
+            let fixed = meta.query_fixed(fixed, Rotation(-5));
+            let instance = meta.query_instance(instance, Rotation::next());
+            let unblinded = (0..6)
+                .map(|rotation| meta.query_advice(unblinded, Rotation(rotation)))
+                .reduce(|acc, e| acc + e)
+                .unwrap();
+            let a = meta.query_advice(a, Rotation::prev());
+            vec![q * (fixed + instance + unblinded + a)]
+        });
+        assert_eq!(meta.blinding_factors_breakdown().max_advice_queries, 3);
+        assert_eq!(meta.blinding_factors(), 5);
+
+        // A fourth rotation of a blinded advice column takes one more.
+        meta.create_gate("fourth rotation", |meta| {
+            let q = meta.query_selector(q);
+            vec![q * meta.query_advice(a, Rotation(2))]
+        });
+        assert_eq!(meta.blinding_factors_breakdown().max_advice_queries, 4);
+        assert_eq!(meta.blinding_factors(), 6);
+
+        meta.disable_zero_knowledge();
+        assert_eq!(meta.blinding_factors_breakdown().total(), 0);
+        assert_eq!(meta.usable_rows(4), 0..15);
+    }
+
+    #[test]
+    fn query_rotation_report() {
+        use super::{Column, ConstraintSystem, QuerySource};
+        use crate::poly::Rotation;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let q = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column_named("b");
+        let table = meta.lookup_table_column();
+        meta.create_gate("step", |meta| {
+            let q = meta.query_selector(q);
+            let cur = meta.query_advice(a, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            vec![q * (next - cur)]
+        });
+        meta.create_gate("shuffle step", |meta| {
+            let q = meta.query_selector(q);
+            let ahead = meta.query_advice(b, Rotation(63));
+            let cur = meta.query_advice(b, Rotation::cur());
+            vec![q * (ahead - cur)]
+        });
+        meta.lookup("range", |meta| {
+            let q = meta.query_selector(q);
+            vec![(q * meta.query_advice(b, Rotation(-2)), table)]
+        });
+
+        let report = meta.query_rotation_report();
+        assert_eq!(report.minimum_rows, meta.minimum_rows_breakdown());
+        // The table column is queried too, at `Rotation::cur()`.
+        assert_eq!(report.columns.len(), 3);
+
+        let a = report.column(a).unwrap();
+        assert_eq!((a.min_rotation, a.max_rotation), (0, 1));
+        assert_eq!((a.unusable_rows(), a.minimum_rows()), (1, 2));
+
+        let b_column: Column<_> = b.into();
+        let b = report.column(b).unwrap();
+        assert_eq!(b.column, b_column);
+        assert_eq!(b.name.as_deref(), Some("b"));
+        assert_eq!((b.min_rotation, b.max_rotation), (-2, 63));
+        assert_eq!((b.unusable_rows(), b.minimum_rows()), (65, 66));
+        assert_eq!(
+            b.sources_at(63).collect::<Vec<_>>(),
+            [&QuerySource::Gate {
+                gate: 1,
+                name: "shuffle step".to_string()
+            }]
+        );
+        assert_eq!(b.minimum_rows(), report.minimum_rows.rotation_span());
+        assert_eq!(report.costliest(), Some(b));
+        assert_eq!(
+            report.to_string(),
+            "gate 'step' queries Rotation(1) on advice 0, costing 1 unusable rows\n\
+             gate 'shuffle step' queries Rotation(63) on advice 1 ('b'), costing 63 unusable rows\n\
+             lookup 'range' queries Rotation(-2) on advice 1 ('b'), costing 2 unusable rows\n\
+             the rotations from -2 to 63 span 66 rows, of the 66 rows the circuit needs"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "gate 'lookahead' queries Rotation(65) on advice 0 ('a'), \
+                               beyond the largest rotation allowed, 64")]
+    fn large_rotation_in_gate() {
+        use super::ConstraintSystem;
+        use crate::poly::Rotation;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let q = meta.selector();
+        let a = meta.advice_column_named("a");
+        assert_eq!(meta.max_rotation(), Some(64));
+        meta.create_gate("lookahead", |meta| {
+            let q = meta.query_selector(q);
+            let ahead = meta.query_advice(a, Rotation(65));
+            let cur = meta.query_advice(a, Rotation::cur());
+            vec![q * (ahead - cur)]
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "lookup 'range' queries Rotation(-9) on fixed 0, \
+                               beyond the largest rotation allowed, 8")]
+    fn large_rotation_in_lookup() {
+        use super::ConstraintSystem;
+        use crate::poly::Rotation;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let f = meta.fixed_column();
+        let table = meta.lookup_table_column();
+        meta.set_max_rotation(8);
+        meta.lookup("range", |meta| {
+            vec![(meta.query_fixed(f, Rotation(-8)), table)]
+        });
+        meta.lookup("range", |meta| {
+            vec![(meta.query_fixed(f, Rotation(-9)), table)]
+        });
+    }
+
+    #[test]
+    fn allow_large_rotations() {
+        use super::ConstraintSystem;
+        use crate::poly::Rotation;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let table = meta.lookup_table_column();
+        meta.set_max_rotation(8);
+        meta.allow_large_rotations();
+        assert_eq!(meta.max_rotation(), None);
+        meta.create_gate("lookahead", |meta| {
+            let q = meta.query_selector(q);
+            let ahead = meta.query_advice(a, Rotation(1000));
+            let cur = meta.query_advice(a, Rotation::cur());
+            vec![q * (ahead - cur)]
+        });
+        meta.lookup_any("behind", |meta| {
+            let behind = meta.query_advice(a, Rotation(-1000));
+            let table = meta.query_fixed(table.inner(), Rotation::cur());
+            vec![(behind, table)]
+        });
+        assert_eq!(meta.query_rotation_report().columns[0].max_rotation, 1000);
+    }
+
+    #[test]
+    fn selectors_to_fixed() {
+        use super::{ConstraintSystem, Expression};
+        use crate::plonk::test_fixtures;
+        use crate::poly::Rotation;
+        use ff::Field;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let [s1, s2, s3] = [(); 3].map(|_| meta.selector());
+        let c = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        meta.create_gate("square", |meta| {
+            let s1 = meta.query_selector(s1);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![s1 * (a.clone() * a - b)]
+        });
+        meta.create_gate("sum", |meta| {
+            let s2 = meta.query_selector(s2);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            vec![s2 * (a + b - next)]
+        });
+        meta.create_gate("cube", |meta| {
+            let s3 = meta.query_selector(s3);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![s3 * (a.clone() * a.clone() * a - b)]
+        });
+        meta.create_gate("combination", |meta| {
+            let s = meta.selector_expr(s1.and(!s2));
+            let c = meta.query_selector(c);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::prev());
+            vec![s * c * (a - b)]
+        });
+
+        // `s1`, `s2` and `s3` are never enabled on the same row, so they can
+        // share a fixed column.
+        let n = 16;
+        let selectors: Vec<Vec<bool>> = (0..meta.num_selectors)
+            .map(|selector| {
+                (0..n)
+                    .map(|row| match selector {
+                        3 => row % 2 == 0,
+                        selector => row % 3 == selector,
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut rng = test_fixtures::rng();
+        let advice: Vec<Vec<Fr>> = (0..2)
+            .map(|_| (0..n).map(|_| Fr::random(&mut rng)).collect())
+            .collect();
+
+        let num_fixed_columns = meta.num_fixed_columns;
+        let (compressed, compressed_polys) = meta.clone().compress_selectors(selectors.clone());
+        let (direct, direct_polys) = meta.clone().selectors_to_fixed(selectors);
+
+        // One fixed column per selector, and the degree of the gates is kept.
+        assert_eq!(direct_polys.len(), meta.num_selectors);
+        assert_eq!(
+            direct.num_fixed_columns,
+            num_fixed_columns + meta.num_selectors
+        );
+        assert_eq!(direct.degree(), meta.degree());
+        assert!(compressed_polys.len() < direct_polys.len());
+
+        let evaluate = |expression: &Expression<Fr>, fixed: &[Vec<Fr>], row: usize| {
+            let at = |rotation: Rotation| (row as i32 + rotation.0).rem_euclid(n as i32) as usize;
+            expression.evaluate(
+                &|constant| constant,
+                &|_| panic!("selectors should have been replaced"),
+                &|query| fixed[query.column_index - num_fixed_columns][at(query.rotation)],
+                &|query| advice[query.column_index][at(query.rotation)],
+                &|_| unreachable!(),
+                &|_| unreachable!(),
+                &|a| -a,
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, scalar| a * scalar,
+            )
+        };
+        for (gate, direct_gate) in compressed.gates.iter().zip(direct.gates.iter()) {
+            assert_eq!(gate.name, direct_gate.name);
+            for (poly, direct_poly) in gate.polys.iter().zip(direct_gate.polys.iter()) {
+                for row in 0..n {
+                    assert_eq!(
+                        evaluate(poly, &compressed_polys, row),
+                        evaluate(direct_poly, &direct_polys, row),
+                        "gate '{}' differs on row {}",
+                        gate.name,
+                        row
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn phase_accessors() {
+        use super::{ConstraintSystem, FirstPhase, SecondPhase, ThirdPhase};
+        use crate::poly::Rotation;
+        use crate::SerdeFormat;
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let a = meta.advice_column_in(FirstPhase);
+        let b = meta.advice_column_in(ThirdPhase);
+        let c = meta.advice_column_in(SecondPhase);
+        let d = meta.advice_column_in(FirstPhase);
+        let alpha = meta.challenge_usable_after(FirstPhase);
+        let beta = meta.challenge_usable_after(SecondPhase);
+        let beta_squared = meta.challenge_power(beta, 2);
+        meta.create_gate("phases", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::next());
+            let c_cur = meta.query_advice(c, Rotation::cur());
+            let d = meta.query_advice(d, Rotation::cur());
+            let alpha = meta.query_challenge(alpha);
+            let beta = meta.query_challenge(beta_squared);
+            vec![a * alpha - c, c_cur * beta - b + d]
+        });
+
+        let check = |meta: &ConstraintSystem<Fr>| {
+            assert_eq!(meta.max_phase(), 2);
+            assert_eq!(meta.advice_column_phase(), [0, 2, 1, 0]);
+            assert_eq!(
+                (0..=3)
+                    .map(|phase| meta.num_advice_columns_in_phase(phase))
+                    .collect::<Vec<_>>(),
+                [2, 1, 1, 0]
+            );
+            assert_eq!(meta.advice_columns_in_phase(0), [a, d]);
+            assert_eq!(meta.advice_columns_in_phase(1), [c]);
+            assert_eq!(meta.advice_columns_in_phase(2), [b]);
+            assert_eq!(meta.advice_queries_in_phase(0).len(), 2);
+            assert_eq!(
+                meta.advice_queries_in_phase(1),
+                [(c, Rotation::next()), (c, Rotation::cur())]
+            );
+            assert_eq!(meta.advice_queries_in_phase(2), [(b, Rotation::cur())]);
+            assert_eq!(meta.challenge_phases(), [0, 1, 1]);
+            assert_eq!(meta.challenge_phase(alpha), 0);
+            assert_eq!(meta.challenge_phase(beta_squared), 1);
+        };
+        check(&meta);
+
+        let bytes = meta.to_bytes(SerdeFormat::Processed);
+        check(&ConstraintSystem::<Fr>::from_bytes(&bytes, SerdeFormat::Processed).unwrap());
+    }
+
+    mod proptests {
+        use super::super::{sealed, AdviceQuery, Challenge, FixedQuery, Selector};
+        use super::*;
+        use crate::poly::Rotation;
+        use ff::Field;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        fn arb_scalar() -> impl Strategy<Value = Fr> {
+            prop_oneof![
+                Just(Fr::ZERO),
+                Just(Fr::ONE),
+                Just(-Fr::ONE),
+                any::<u64>().prop_map(Fr::from),
+            ]
+        }
+
+        fn arb_expression() -> impl Strategy<Value = Expression<Fr>> {
+            let leaf = prop_oneof![
+                arb_scalar().prop_map(Expression::Constant),
+                (0..2usize).prop_map(|i| Expression::Selector(Selector(i, false))),
+                (0..3usize, -1..=1i32).prop_map(|(column_index, rotation)| {
+                    Expression::Fixed(FixedQuery {
+                        index: None,
+                        column_index,
+                        rotation: Rotation(rotation),
+                    })
+                }),
+                (0..3usize, -1..=1i32).prop_map(|(column_index, rotation)| {
+                    Expression::Advice(AdviceQuery {
+                        index: None,
+                        column_index,
+                        rotation: Rotation(rotation),
+                        phase: sealed::Phase(0),
+                    })
+                }),
+                (0..2usize).prop_map(|index| {
+                    Expression::Challenge(Challenge {
+                        index,
+                        phase: sealed::Phase(0),
+                    })
+                }),
+            ];
+            leaf.prop_recursive(6, 64, 2, |inner| {
+                prop_oneof![
+                    inner.clone().prop_map(|a| Expression::Negated(Box::new(a))),
+                    (inner.clone(), inner.clone())
+                        .prop_map(|(a, b)| Expression::Sum(Box::new(a), Box::new(b))),
+                    (inner.clone(), inner.clone())
+                        .prop_map(|(a, b)| Expression::Product(Box::new(a), Box::new(b))),
+                    (inner, arb_scalar()).prop_map(|(a, f)| Expression::Scaled(Box::new(a), f)),
+                ]
+            })
+        }
+
+        /// Evaluates `expression` with the selectors, the cells of each
+        /// column at each rotation and the challenges set to `values`.
+        fn evaluate(expression: &Expression<Fr>, values: &[u64]) -> Fr {
+            let cell = |offset: usize, column: usize, rotation: Rotation| {
+                Fr::from(values[offset + 3 * column + (rotation.0 + 1) as usize])
+            };
+            expression.evaluate(
+                &|c| c,
+                &|selector| Fr::from(values[selector.0] % 2),
+                &|query| cell(2, query.column_index, query.rotation),
+                &|query| cell(11, query.column_index, query.rotation),
+                &|_| unreachable!(),
+                &|challenge| Fr::from(values[20 + challenge.index()]),
+                &|a| -a,
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, f| a * f,
+            )
+        }
+
+        proptest! {
+            #[test]
+            fn simplify_keeps_values(
+                expression in arb_expression(),
+                values in vec(any::<u64>(), 22),
+            ) {
+                let simplified = expression.simplify();
+                prop_assert_eq!(evaluate(&simplified, &values), evaluate(&expression, &values));
+                prop_assert!(simplified.degree() <= expression.degree());
+                prop_assert!(simplified.node_count() <= expression.node_count());
+            }
+        }
+    }
 }
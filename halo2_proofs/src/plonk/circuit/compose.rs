@@ -0,0 +1,562 @@
+use std::convert::TryFrom;
+
+use ff::Field;
+
+use super::{
+    Advice, AdviceQuery, Any, Challenge, Circuit, Column, ConstraintSystem, Expression, Fixed,
+    FixedQuery, Gate, Instance, InstanceQuery, Selector, TableColumn, VirtualCell,
+};
+use crate::circuit::Layouter;
+use crate::dev::metadata;
+use crate::plonk::{lookup, shuffle, Error};
+
+/// Where [`ConstraintSystem::merge`] moved the columns, selectors and
+/// challenges of the merged constraint system.
+///
+/// The fixed and advice columns, selectors and challenges of the merged
+/// system are appended after those of the system it is merged into, in the
+/// same order, so each of them is moved by a constant offset. The instance
+/// columns are shared: the `i`-th instance column of both systems is the
+/// same column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnRemap {
+    fixed_offset: usize,
+    num_fixed_columns: usize,
+    advice_offset: usize,
+    num_advice_columns: usize,
+    num_instance_columns: usize,
+    selector_offset: usize,
+    num_selectors: usize,
+    challenge_offset: usize,
+    num_challenges: usize,
+}
+
+impl ColumnRemap {
+    /// Returns the column a fixed column of the merged system was moved to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column is not one of the merged system.
+    pub fn fixed(&self, column: Column<Fixed>) -> Column<Fixed> {
+        assert!(
+            column.index < self.num_fixed_columns,
+            "{:?} is not a column of the merged constraint system",
+            column
+        );
+        Column::new(self.fixed_offset + column.index, Fixed)
+    }
+
+    /// Returns the column an advice column of the merged system was moved
+    /// to. It keeps its phase.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column is not one of the merged system.
+    pub fn advice(&self, column: Column<Advice>) -> Column<Advice> {
+        assert!(
+            column.index < self.num_advice_columns,
+            "{:?} is not a column of the merged constraint system",
+            column
+        );
+        Column::new(self.advice_offset + column.index, column.column_type)
+    }
+
+    /// Returns the column an instance column of the merged system is, which
+    /// is the column with the same index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the column is not one of the merged system.
+    pub fn instance(&self, column: Column<Instance>) -> Column<Instance> {
+        assert!(
+            column.index < self.num_instance_columns,
+            "{:?} is not a column of the merged constraint system",
+            column
+        );
+        column
+    }
+
+    /// Returns the column any column of the merged system was moved to.
+    pub fn any(&self, column: Column<Any>) -> Column<Any> {
+        match column.column_type {
+            Any::Advice(_) => self
+                .advice(Column::<Advice>::try_from(column).unwrap())
+                .into(),
+            Any::Fixed => self
+                .fixed(Column::<Fixed>::try_from(column).unwrap())
+                .into(),
+            Any::Instance => self
+                .instance(Column::<Instance>::try_from(column).unwrap())
+                .into(),
+        }
+    }
+
+    /// Returns the table column a table column of the merged system was
+    /// moved to.
+    pub fn table_column(&self, column: TableColumn) -> TableColumn {
+        TableColumn {
+            inner: self.fixed(column.inner),
+        }
+    }
+
+    /// Returns the selector a selector of the merged system was moved to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the selector is not one of the merged system.
+    pub fn selector(&self, selector: Selector) -> Selector {
+        assert!(
+            selector.0 < self.num_selectors,
+            "{:?} is not a selector of the merged constraint system",
+            selector
+        );
+        Selector(self.selector_offset + selector.0, selector.1)
+    }
+
+    /// Returns the challenge a challenge of the merged system was moved to.
+    /// It keeps its phase.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the challenge is not one of the merged system.
+    pub fn challenge(&self, challenge: Challenge) -> Challenge {
+        assert!(
+            challenge.index < self.num_challenges,
+            "{:?} is not a challenge of the merged constraint system",
+            challenge
+        );
+        Challenge {
+            index: self.challenge_offset + challenge.index,
+            phase: challenge.phase,
+        }
+    }
+
+    fn metadata(&self, column: metadata::Column) -> metadata::Column {
+        let column = Column::new(column.index(), column.column_type());
+        let column = self.any(column);
+        metadata::Column::from((column.column_type, column.index))
+    }
+}
+
+/// A circuit configuration that can be moved to the constraint system a
+/// [`ConstraintSystem`] it was configured in is merged into; see
+/// [`ComposedCircuit`].
+///
+/// It is implemented for columns, selectors, challenges and collections of
+/// them, from which the configurations of circuits implement it field by
+/// field.
+pub trait RemapConfig {
+    /// Returns the configuration with its columns, selectors and challenges
+    /// moved as `remap` describes.
+    fn remap(self, remap: &ColumnRemap) -> Self;
+}
+
+impl RemapConfig for Column<Fixed> {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.fixed(self)
+    }
+}
+
+impl RemapConfig for Column<Advice> {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.advice(self)
+    }
+}
+
+impl RemapConfig for Column<Instance> {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.instance(self)
+    }
+}
+
+impl RemapConfig for Column<Any> {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.any(self)
+    }
+}
+
+impl RemapConfig for TableColumn {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.table_column(self)
+    }
+}
+
+impl RemapConfig for Selector {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.selector(self)
+    }
+}
+
+impl RemapConfig for Challenge {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        remap.challenge(self)
+    }
+}
+
+impl RemapConfig for () {
+    fn remap(self, _: &ColumnRemap) -> Self {}
+}
+
+impl<T: RemapConfig, const N: usize> RemapConfig for [T; N] {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        self.map(|config| config.remap(remap))
+    }
+}
+
+impl<T: RemapConfig> RemapConfig for Vec<T> {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        self.into_iter().map(|config| config.remap(remap)).collect()
+    }
+}
+
+impl<T: RemapConfig> RemapConfig for Option<T> {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        self.map(|config| config.remap(remap))
+    }
+}
+
+impl<A: RemapConfig, B: RemapConfig> RemapConfig for (A, B) {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        (self.0.remap(remap), self.1.remap(remap))
+    }
+}
+
+impl<F: Field> ConstraintSystem<F> {
+    /// Appends the constraints of `other`, configured on its own, to this
+    /// constraint system, and returns where its columns, selectors and
+    /// challenges were moved to, with which the configuration of the circuit
+    /// that configured `other` can be moved too; see [`RemapConfig`].
+    ///
+    /// The fixed and advice columns, selectors and challenges of `other` are
+    /// appended after those of this system, and its gates, lookups,
+    /// shuffles, columns taking part in the permutation argument and columns
+    /// for constants after the ones of this system, with the columns they
+    /// refer to moved. The instance columns are shared, so both circuits can
+    /// expose values in the same instance column; the merged system has as
+    /// many instance columns as the system with the most.
+    ///
+    /// Merging the same constraint systems in the same order always moves
+    /// `other` the same way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the selectors of either system were compressed, or if they
+    /// do not agree on zero knowledge, on the lookup backend or on the version
+    /// of their pinned representation.
+    pub fn merge(&mut self, other: ConstraintSystem<F>) -> ColumnRemap {
+        assert!(
+            self.selector_map.is_empty() && other.selector_map.is_empty(),
+            "constraint systems must be merged before their selectors are compressed"
+        );
+        assert_eq!(
+            self.zero_knowledge, other.zero_knowledge,
+            "merged constraint systems must agree on zero knowledge"
+        );
+        assert_eq!(
+            self.lookup_backend, other.lookup_backend,
+            "merged constraint systems must use the same lookup backend"
+        );
+        assert_eq!(
+            self.pinned_version, other.pinned_version,
+            "merged constraint systems must use the same pinned version"
+        );
+
+        let remap = ColumnRemap {
+            fixed_offset: self.num_fixed_columns,
+            num_fixed_columns: other.num_fixed_columns,
+            advice_offset: self.num_advice_columns,
+            num_advice_columns: other.num_advice_columns,
+            num_instance_columns: other.num_instance_columns,
+            selector_offset: self.num_selectors,
+            num_selectors: other.num_selectors,
+            challenge_offset: self.num_challenges,
+            num_challenges: other.num_challenges,
+        };
+
+        // Columns, selectors and challenges
+        self.num_fixed_columns += other.num_fixed_columns;
+        self.num_instance_columns = self.num_instance_columns.max(other.num_instance_columns);
+        for phase in other.advice_column_phase {
+            self.num_advice_columns += 1;
+            self.num_advice_queries.push(0);
+            self.advice_column_phase.push(phase);
+        }
+        self.unblinded_advice_columns.extend(
+            other
+                .unblinded_advice_columns
+                .iter()
+                .map(|index| remap.advice_offset + index),
+        );
+        self.num_selectors += other.num_selectors;
+        self.uncompressed_selectors.extend(
+            other
+                .uncompressed_selectors
+                .iter()
+                .map(|index| remap.selector_offset + index),
+        );
+        self.selector_combinations
+            .extend(
+                other
+                    .selector_combinations
+                    .iter()
+                    .map(|(index, combination)| {
+                        (
+                            remap.selector_offset + index,
+                            combination.map_selectors(&|selector| remap.selector(selector)),
+                        )
+                    }),
+            );
+        self.selector_names.extend(
+            other
+                .selector_names
+                .into_iter()
+                .map(|(index, name)| (remap.selector_offset + index, name)),
+        );
+        self.num_challenges += other.num_challenges;
+        self.challenge_phase.extend(other.challenge_phase);
+        self.challenge_powers
+            .extend(
+                other
+                    .challenge_powers
+                    .iter()
+                    .map(|&(challenge, base, exponent)| {
+                        (
+                            remap.challenge_offset + challenge,
+                            remap.challenge_offset + base,
+                            exponent,
+                        )
+                    }),
+            );
+        // The pins of `other` follow its columns, and those that do not hold
+        // are kept as they are, so that they still fail the check.
+        let column_pins: Vec<_> = other
+            .column_pins
+            .iter()
+            .map(|(name, index)| {
+                let index = other
+                    .pinned_column(name)
+                    .map_or(*index, |column| remap.any(column).index());
+                (name.clone(), index)
+            })
+            .collect();
+        for (name, index) in &column_pins {
+            self.pin_columns(&[(name.as_str(), *index)]);
+        }
+        for (column, name) in other.general_column_annotations {
+            // The annotations of the shared instance columns are kept.
+            self.general_column_annotations
+                .entry(remap.metadata(column))
+                .or_insert(name);
+        }
+
+        // The queries, in the order `other` made them, so that the
+        // expressions below can refer to them.
+        for (column, rotation) in &other.advice_queries {
+            self.query_advice_index(remap.advice(*column), *rotation);
+        }
+        for (column, rotation) in &other.fixed_queries {
+            self.query_fixed_index(remap.fixed(*column), *rotation);
+        }
+        for (column, rotation) in &other.instance_queries {
+            self.query_instance_index(remap.instance(*column), *rotation);
+        }
+
+        // Constraints
+        for gate in other.gates {
+            let polys = gate
+                .polys
+                .iter()
+                .map(|poly| self.remap_expression(poly, &remap))
+                .collect();
+            self.gates.push(Gate {
+                name: gate.name,
+                constraint_names: gate.constraint_names,
+                polys,
+                queried_selectors: gate
+                    .queried_selectors
+                    .into_iter()
+                    .map(|selector| remap.selector(selector))
+                    .collect(),
+                queried_cells: gate
+                    .queried_cells
+                    .into_iter()
+                    .map(|cell| VirtualCell {
+                        column: remap.any(cell.column),
+                        rotation: cell.rotation,
+                    })
+                    .collect(),
+                cost: gate.cost,
+            });
+        }
+        for argument in other.lookups {
+            let remap_all = |expressions: &[Expression<F>]| {
+                expressions
+                    .iter()
+                    .map(|expression| self.remap_expression(expression, &remap))
+                    .collect()
+            };
+            let argument = lookup::Argument {
+                input_expressions: remap_all(&argument.input_expressions),
+                table_expressions: remap_all(&argument.table_expressions),
+                name: argument.name,
+                table: argument.table,
+            };
+            self.lookups.push(argument);
+        }
+        for argument in other.shuffles {
+            let remap_all = |expressions: &[Expression<F>]| {
+                expressions
+                    .iter()
+                    .map(|expression| self.remap_expression(expression, &remap))
+                    .collect()
+            };
+            let argument = shuffle::Argument {
+                input_expressions: remap_all(&argument.input_expressions),
+                shuffle_expressions: remap_all(&argument.shuffle_expressions),
+                name: argument.name,
+            };
+            self.shuffles.push(argument);
+        }
+        for column in other.permutation.columns {
+            self.permutation.add_column(remap.any(column));
+        }
+        self.constants.extend(
+            other
+                .constants
+                .into_iter()
+                .map(|column| remap.fixed(column)),
+        );
+        self.preassigned_fixed.extend(
+            other
+                .preassigned_fixed
+                .into_iter()
+                .map(|(column, values)| (remap.fixed(column), values)),
+        );
+
+        // Settings
+        self.minimum_degree = self.minimum_degree.max(other.minimum_degree);
+        self.extended_k = self.extended_k.max(other.extended_k);
+        self.allow_disabled_gates = (self.allow_disabled_gates || self.disabled_gates.is_empty())
+            && (other.allow_disabled_gates || other.disabled_gates.is_empty());
+        for name in other.disabled_gates {
+            if !self.disabled_gates.contains(&name) {
+                self.disabled_gates.push(name);
+            }
+        }
+
+        remap
+    }
+
+    /// Returns `expression`, of a constraint system merged into this one,
+    /// with its queries, selectors and challenges moved as `remap` describes.
+    /// The queries must already have been made on this system.
+    fn remap_expression(&self, expression: &Expression<F>, remap: &ColumnRemap) -> Expression<F> {
+        expression.evaluate(
+            &Expression::Constant,
+            &|selector| Expression::Selector(remap.selector(selector)),
+            &|query| {
+                let column = remap.fixed(Column::new(query.column_index, Fixed));
+                Expression::Fixed(FixedQuery {
+                    index: Some(self.get_fixed_query_index(column, query.rotation)),
+                    column_index: column.index,
+                    rotation: query.rotation,
+                })
+            },
+            &|query| {
+                let column = remap.advice(Column::new(
+                    query.column_index,
+                    Advice { phase: query.phase },
+                ));
+                Expression::Advice(AdviceQuery {
+                    index: Some(self.get_advice_query_index(column, query.rotation)),
+                    column_index: column.index,
+                    rotation: query.rotation,
+                    phase: query.phase,
+                })
+            },
+            &|query| {
+                let column = remap.instance(Column::new(query.column_index, Instance));
+                Expression::Instance(InstanceQuery {
+                    index: Some(self.get_instance_query_index(column, query.rotation)),
+                    column_index: column.index,
+                    rotation: query.rotation,
+                })
+            },
+            &|challenge| Expression::Challenge(remap.challenge(challenge)),
+            &|a| Expression::Negated(Box::new(a)),
+            &|a, b| Expression::Sum(Box::new(a), Box::new(b)),
+            &|a, b| Expression::Product(Box::new(a), Box::new(b)),
+            &|a, scalar| Expression::Scaled(Box::new(a), scalar),
+        )
+    }
+}
+
+/// Two circuits proven side by side in one proof, each configured on its own
+/// and sharing nothing but the instance columns; see
+/// [`ConstraintSystem::merge`].
+///
+/// The constraint system of `B` is merged into the one of `A`, and the
+/// configuration of `B` is moved with it. Both circuits are then synthesized
+/// in their own namespace, laid out by the floor planner of `A`.
+#[derive(Clone, Debug, Default)]
+pub struct ComposedCircuit<A, B> {
+    /// The first circuit.
+    pub a: A,
+    /// The second circuit.
+    pub b: B,
+}
+
+impl<A, B> ComposedCircuit<A, B> {
+    /// Composes the circuits `a` and `b`.
+    pub fn new(a: A, b: B) -> Self {
+        ComposedCircuit { a, b }
+    }
+}
+
+impl<F: Field, A: Circuit<F>, B: Circuit<F>> Circuit<F> for ComposedCircuit<A, B>
+where
+    B::Config: RemapConfig,
+{
+    type Config = (A::Config, B::Config);
+    type FloorPlanner = A::FloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = (A::Params, B::Params);
+
+    fn without_witnesses(&self) -> Self {
+        ComposedCircuit {
+            a: self.a.without_witnesses(),
+            b: self.b.without_witnesses(),
+        }
+    }
+
+    #[cfg(feature = "circuit-params")]
+    fn params(&self) -> Self::Params {
+        (self.a.params(), self.b.params())
+    }
+
+    #[cfg(feature = "circuit-params")]
+    fn configure_with_params(meta: &mut ConstraintSystem<F>, (a, b): Self::Params) -> Self::Config {
+        let a = A::configure_with_params(meta, a);
+        let mut other = ConstraintSystem::default();
+        let b = B::configure_with_params(&mut other, b);
+        let remap = meta.merge(other);
+        (a, b.remap(&remap))
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let a = A::configure(meta);
+        let mut other = ConstraintSystem::default();
+        let b = B::configure(&mut other);
+        let remap = meta.merge(other);
+        (a, b.remap(&remap))
+    }
+
+    fn synthesize(
+        &self,
+        (a, b): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        self.a.synthesize(a, layouter.namespace(|| "a"))?;
+        self.b.synthesize(b, layouter.namespace(|| "b"))
+    }
+}
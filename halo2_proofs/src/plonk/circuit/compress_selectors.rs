@@ -29,6 +29,10 @@ pub struct SelectorAssignment<F> {
 
     /// The expression we wish to substitute with
     pub expression: Expression<F>,
+
+    /// The value of the combination on the rows this selector is enabled on,
+    /// or `None` if the selector is not assigned a value of its own
+    pub value: Option<F>,
 }
 
 /// This function takes a vector that defines each selector as well as a closure
@@ -87,6 +91,7 @@ where
                 selector: selector.selector,
                 combination_index,
                 expression,
+                value: Some(F::ONE),
             });
 
             false
@@ -212,12 +217,14 @@ where
                 }
             }
 
+            let value = assigned_root;
             assigned_root += F::ONE;
 
             SelectorAssignment {
                 selector: selector.selector,
                 combination_index,
                 expression,
+                value: Some(value),
             }
         }));
         combination_assignments.push(combination_assignment);
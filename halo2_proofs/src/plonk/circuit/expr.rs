@@ -0,0 +1,590 @@
+//! Concise constructors for [`Expression`]s, and a textual form of them that
+//! can be printed and parsed back, for tests and tools that write
+//! expressions by hand rather than in `configure`.
+//!
+//! ```
+//! use halo2_proofs::plonk::{expr, ConstraintSystem, Expression};
+//! use halo2curves::pasta::Fp;
+//!
+//! let mut meta = ConstraintSystem::<Fp>::default();
+//! let a = meta.advice_column();
+//! let f = meta.fixed_column();
+//!
+//! let e: Expression<Fp> = expr::adv(a, 1) * (expr::fixed(f, 0) - expr::c(1u64));
+//! assert_eq!(e.to_string_pretty(), "a0[1] * (f0[0] - 1)");
+//! assert_eq!(Expression::parse("a0[1] * (f0[0] - 1)", &meta), Ok(e));
+//! ```
+//!
+//! In the textual form, `a`, `f` and `i` followed by the index of a column
+//! and a rotation in brackets query an advice, fixed or instance column; `s`
+//! and `c` followed by an index are a selector and a challenge. Constants are
+//! decimal, or hexadecimal with a `0x` prefix, as [`VkExport`] writes them.
+//! Sums, differences, products and negations are written as usual.
+//!
+//! [`VkExport`]: crate::plonk::VkExport
+
+use std::fmt;
+
+use ff::{Field, PrimeField};
+
+use super::{
+    Advice, AdviceQuery, Challenge, Column, ConstraintSystem, Expression, Fixed, FixedQuery,
+    Instance, InstanceQuery, Selector,
+};
+use crate::poly::Rotation;
+
+/// Returns the query of the advice column `column` at `rotation`.
+pub fn adv<F: Field>(column: Column<Advice>, rotation: i32) -> Expression<F> {
+    column.query_cell(Rotation(rotation))
+}
+
+/// Returns the query of the fixed column `column` at `rotation`.
+pub fn fixed<F: Field>(column: Column<Fixed>, rotation: i32) -> Expression<F> {
+    column.query_cell(Rotation(rotation))
+}
+
+/// Returns the query of the instance column `column` at `rotation`.
+pub fn inst<F: Field>(column: Column<Instance>, rotation: i32) -> Expression<F> {
+    column.query_cell(Rotation(rotation))
+}
+
+/// Returns the selector `selector`.
+pub fn sel<F: Field>(selector: Selector) -> Expression<F> {
+    selector.expr()
+}
+
+/// Returns the challenge `challenge`.
+pub fn challenge<F: Field>(challenge: Challenge) -> Expression<F> {
+    challenge.expr()
+}
+
+/// Returns the constant `value`.
+pub fn c<F: Field, V: Into<F>>(value: V) -> Expression<F> {
+    Expression::Constant(value.into())
+}
+
+/// The error returned by [`Expression::parse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The offset in bytes in the parsed text at which the error was found.
+    pub offset: usize,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// The precedences of the nodes of an expression when printed: an operand of
+// a lower precedence than its position requires is put in parentheses.
+const SUM: u8 = 0;
+const PRODUCT: u8 = 1;
+const UNARY: u8 = 2;
+const ATOM: u8 = 3;
+
+impl<F: PrimeField> Expression<F> {
+    /// Parses an expression written in the textual form described in the
+    /// [module documentation](self), such as `a0[1] * (f2[0] - 1)`.
+    ///
+    /// The columns, selectors and challenges are those of `cs`, which gives
+    /// the phases of the advice columns and challenges. Queries that `cs`
+    /// already makes refer to them, so that a gate of `cs` printed with
+    /// [`Self::to_string_pretty`] parses back to an equal expression when it
+    /// has no [scaled](Expression::Scaled) terms, and a selector is simple if
+    /// a gate of `cs` queries it as one.
+    ///
+    /// This is meant for tests and tools, not for the prover.
+    pub fn parse(text: &str, cs: &ConstraintSystem<F>) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            offset: 0,
+            cs,
+        };
+        let expression = parser.sum()?;
+        parser.skip_whitespace();
+        if parser.offset < text.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(expression)
+    }
+
+    /// Returns this expression in the textual form that [`Self::parse`]
+    /// reads, with as few parentheses as its structure allows.
+    ///
+    /// The parsed expression computes the same values, but is not always
+    /// equal: a [scaled](Expression::Scaled) expression is printed as a
+    /// product, and a negative constant as a negation.
+    pub fn to_string_pretty(&self) -> String {
+        let mut text = String::new();
+        write_pretty(self, SUM, &mut text);
+        text
+    }
+}
+
+/// Returns `value` if it fits in a `u64`.
+fn small<F: PrimeField>(value: &F) -> Option<u64> {
+    let repr = value.to_repr();
+    let bytes = repr.as_ref().get(..8)?;
+    let small = u64::from_le_bytes(bytes.try_into().unwrap());
+    (F::from(small) == *value).then_some(small)
+}
+
+fn write_constant<F: PrimeField>(value: &F, text: &mut String) {
+    if let Some(value) = small(value) {
+        text.push_str(&value.to_string());
+    } else if let Some(value) = small(&-*value) {
+        text.push_str(&format!("-{}", value));
+    } else {
+        text.push_str("0x");
+        for byte in value.to_repr().as_ref().iter().rev() {
+            text.push_str(&format!("{:02x}", byte));
+        }
+    }
+}
+
+fn precedence<F: PrimeField>(expression: &Expression<F>) -> u8 {
+    match expression {
+        Expression::Sum(..) => SUM,
+        Expression::Product(..) | Expression::Scaled(..) => PRODUCT,
+        Expression::Negated(_) => UNARY,
+        Expression::Constant(value) if small(value).is_none() && small(&-*value).is_some() => UNARY,
+        _ => ATOM,
+    }
+}
+
+fn write_pretty<F: PrimeField>(expression: &Expression<F>, minimum: u8, text: &mut String) {
+    if precedence(expression) < minimum {
+        text.push('(');
+        write_pretty(expression, SUM, text);
+        text.push(')');
+        return;
+    }
+    match expression {
+        Expression::Constant(value) => write_constant(value, text),
+        Expression::Selector(selector) => text.push_str(&format!("s{}", selector.0)),
+        Expression::Fixed(query) => {
+            text.push_str(&format!("f{}[{}]", query.column_index, query.rotation.0))
+        }
+        Expression::Advice(query) => {
+            text.push_str(&format!("a{}[{}]", query.column_index, query.rotation.0))
+        }
+        Expression::Instance(query) => {
+            text.push_str(&format!("i{}[{}]", query.column_index, query.rotation.0))
+        }
+        Expression::Challenge(challenge) => text.push_str(&format!("c{}", challenge.index)),
+        Expression::Negated(a) => {
+            text.push('-');
+            write_pretty(a, UNARY, text);
+        }
+        Expression::Sum(a, b) => {
+            write_pretty(a, SUM, text);
+            match &**b {
+                Expression::Negated(b) => {
+                    text.push_str(" - ");
+                    write_pretty(b, PRODUCT, text);
+                }
+                b => {
+                    text.push_str(" + ");
+                    write_pretty(b, PRODUCT, text);
+                }
+            }
+        }
+        Expression::Product(a, b) => {
+            write_pretty(a, PRODUCT, text);
+            text.push_str(" * ");
+            write_pretty(b, UNARY, text);
+        }
+        Expression::Scaled(a, factor) => {
+            write_pretty(a, PRODUCT, text);
+            text.push_str(" * ");
+            write_constant(factor, text);
+        }
+    }
+}
+
+/// Returns what the letter `kind` refers to in the textual form.
+fn describe(kind: u8) -> &'static str {
+    match kind {
+        b'a' => "advice column",
+        b'f' => "fixed column",
+        b'i' => "instance column",
+        b's' => "selector",
+        _ => "challenge",
+    }
+}
+
+/// A recursive descent parser of the textual form of expressions.
+struct Parser<'a, F: Field> {
+    text: &'a [u8],
+    offset: usize,
+    cs: &'a ConstraintSystem<F>,
+}
+
+impl<F: PrimeField> Parser<'_, F> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.offset < self.text.len() && self.text[self.offset].is_ascii_whitespace() {
+            self.offset += 1;
+        }
+    }
+
+    /// Skips whitespace, and returns the next character without consuming
+    /// it.
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, character: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(character) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", character as char)))
+        }
+    }
+
+    /// Parses `product (('+' | '-') product)*`.
+    fn sum(&mut self) -> Result<Expression<F>, ParseError> {
+        let mut sum = self.product()?;
+        loop {
+            match self.peek() {
+                Some(b'+') => {
+                    self.offset += 1;
+                    sum = Expression::Sum(Box::new(sum), Box::new(self.product()?));
+                }
+                Some(b'-') => {
+                    self.offset += 1;
+                    let b = Expression::Negated(Box::new(self.product()?));
+                    sum = Expression::Sum(Box::new(sum), Box::new(b));
+                }
+                _ => return Ok(sum),
+            }
+        }
+    }
+
+    /// Parses `unary ('*' unary)*`.
+    fn product(&mut self) -> Result<Expression<F>, ParseError> {
+        let mut product = self.unary()?;
+        while self.peek() == Some(b'*') {
+            self.offset += 1;
+            product = Expression::Product(Box::new(product), Box::new(self.unary()?));
+        }
+        Ok(product)
+    }
+
+    /// Parses `'-' unary`, a parenthesized sum, a constant, a query, a
+    /// selector or a challenge.
+    fn unary(&mut self) -> Result<Expression<F>, ParseError> {
+        match self.peek() {
+            Some(b'-') => {
+                self.offset += 1;
+                Ok(Expression::Negated(Box::new(self.unary()?)))
+            }
+            Some(b'(') => {
+                self.offset += 1;
+                let sum = self.sum()?;
+                self.expect(b')')?;
+                Ok(sum)
+            }
+            Some(b'0'..=b'9') => self.constant(),
+            Some(kind @ (b'a' | b'f' | b'i' | b's' | b'c')) => {
+                let start = self.offset;
+                self.offset += 1;
+                let index = self.index()?;
+                let expression = match kind {
+                    b's' => self.selector(index),
+                    b'c' => self.challenge(index),
+                    _ => {
+                        self.expect(b'[')?;
+                        self.skip_whitespace();
+                        let negative = self.text.get(self.offset) == Some(&b'-');
+                        if negative {
+                            self.offset += 1;
+                        }
+                        let rotation = i32::try_from(self.index()?)
+                            .map_err(|_| self.error("rotation out of range"))?;
+                        let rotation = Rotation(if negative { -rotation } else { rotation });
+                        self.expect(b']')?;
+                        self.query(kind, index, rotation)
+                    }
+                };
+                expression.ok_or(ParseError {
+                    offset: start,
+                    message: format!("no such {}", describe(kind)),
+                })
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of expression")),
+        }
+    }
+
+    /// Parses a decimal number, without a sign.
+    fn index(&mut self) -> Result<usize, ParseError> {
+        let start = self.offset;
+        let mut index: usize = 0;
+        while let Some(digit @ b'0'..=b'9') = self.text.get(self.offset).copied() {
+            index = index
+                .checked_mul(10)
+                .and_then(|index| index.checked_add((digit - b'0') as usize))
+                .ok_or_else(|| self.error("number too large"))?;
+            self.offset += 1;
+        }
+        if self.offset == start {
+            return Err(self.error("expected a number"));
+        }
+        Ok(index)
+    }
+
+    /// Parses a decimal constant, or a hexadecimal one prefixed with `0x`.
+    fn constant(&mut self) -> Result<Expression<F>, ParseError> {
+        let start = self.offset;
+        if self.text[self.offset..].starts_with(b"0x") {
+            self.offset += 2;
+            let digits_start = self.offset;
+            while self
+                .text
+                .get(self.offset)
+                .map_or(false, |digit| digit.is_ascii_hexdigit())
+            {
+                self.offset += 1;
+            }
+            let digits = std::str::from_utf8(&self.text[digits_start..self.offset]).unwrap();
+            let mut repr = F::Repr::default();
+            let bytes = repr.as_mut();
+            if digits.is_empty() || digits.len() > 2 * bytes.len() {
+                return Err(ParseError {
+                    offset: start,
+                    message: "invalid hexadecimal constant".to_string(),
+                });
+            }
+            // The digits are big-endian, from the last byte of the
+            // representation to the first.
+            let padded = format!("{:0>width$}", digits, width = 2 * bytes.len());
+            for (i, byte) in bytes.iter_mut().rev().enumerate() {
+                *byte = u8::from_str_radix(&padded[2 * i..2 * i + 2], 16).unwrap();
+            }
+            Option::from(F::from_repr(repr))
+                .map(Expression::Constant)
+                .ok_or(ParseError {
+                    offset: start,
+                    message: "constant out of range".to_string(),
+                })
+        } else {
+            let mut value = F::ZERO;
+            while let Some(digit @ b'0'..=b'9') = self.text.get(self.offset).copied() {
+                value = value * F::from(10) + F::from((digit - b'0') as u64);
+                self.offset += 1;
+            }
+            Ok(Expression::Constant(value))
+        }
+    }
+
+    fn selector(&self, index: usize) -> Option<Expression<F>> {
+        if index >= self.cs.num_selectors {
+            return None;
+        }
+        let simple = self
+            .cs
+            .gates
+            .iter()
+            .flat_map(|gate| gate.queried_selectors())
+            .any(|selector| selector.0 == index && selector.is_simple());
+        Some(Expression::Selector(Selector(index, simple)))
+    }
+
+    fn challenge(&self, index: usize) -> Option<Expression<F>> {
+        let phase = *self.cs.challenge_phase.get(index)?;
+        Some(Expression::Challenge(Challenge { index, phase }))
+    }
+
+    fn query(&self, kind: u8, column_index: usize, rotation: Rotation) -> Option<Expression<F>> {
+        let cs = self.cs;
+        Some(match kind {
+            b'a' => {
+                let phase = *cs.advice_column_phase.get(column_index)?;
+                Expression::Advice(AdviceQuery {
+                    index: cs
+                        .advice_queries
+                        .iter()
+                        .position(|(column, at)| column.index() == column_index && *at == rotation),
+                    column_index,
+                    rotation,
+                    phase,
+                })
+            }
+            b'f' if column_index < cs.num_fixed_columns => Expression::Fixed(FixedQuery {
+                index: cs
+                    .fixed_queries
+                    .iter()
+                    .position(|(column, at)| column.index() == column_index && *at == rotation),
+                column_index,
+                rotation,
+            }),
+            b'i' if column_index < cs.num_instance_columns => Expression::Instance(InstanceQuery {
+                index: cs
+                    .instance_queries
+                    .iter()
+                    .position(|(column, at)| column.index() == column_index && *at == rotation),
+                column_index,
+                rotation,
+            }),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::{ExpressionFold, FirstPhase, SecondPhase};
+    use halo2curves::pasta::Fp;
+    use proptest::prelude::*;
+
+    /// A constraint system with columns, selectors and challenges of every
+    /// kind.
+    fn test_cs() -> ConstraintSystem<Fp> {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let a = meta.advice_column();
+        let b = meta.advice_column_in(SecondPhase);
+        let f = meta.fixed_column();
+        let _ = meta.fixed_column();
+        let i = meta.instance_column();
+        let q = meta.selector();
+        let _ = meta.complex_selector();
+        let theta = meta.challenge_usable_after(FirstPhase);
+        meta.create_gate("gate", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::next());
+            let b = meta.query_advice(b, Rotation::cur());
+            let f = meta.query_fixed(f, Rotation::prev());
+            let i = meta.query_instance(i, Rotation::cur());
+            let theta = meta.query_challenge(theta);
+            vec![q * (a * (f - Expression::Constant(Fp::from(3))) + b * theta - i)]
+        });
+        meta
+    }
+
+    /// Evaluates `expression` on values derived from `seed`, distinct for
+    /// each query, selector and challenge.
+    fn evaluate(expression: &Expression<Fp>, seed: u64) -> Fp {
+        let value = |kind: u64, index: usize, rotation: i32| {
+            let key = (kind * 100 + index as u64) * 100 + (rotation + 50) as u64;
+            Fp::from(seed ^ key).square() + Fp::from(key)
+        };
+        expression.fold(&ExpressionFold {
+            constant: &|value| value,
+            selector: &|selector| value(0, selector.0, 0),
+            fixed: &|query| value(1, query.column_index, query.rotation.0),
+            advice: &|query| value(2, query.column_index, query.rotation.0),
+            instance: &|query| value(3, query.column_index, query.rotation.0),
+            challenge: &|challenge| value(4, challenge.index, 0),
+            negated: &|a| -a,
+            sum: &|a, b| a + b,
+            product: &|a, b| a * b,
+            scaled: &|a, factor| a * factor,
+        })
+    }
+
+    fn arb_constant() -> impl Strategy<Value = Fp> {
+        prop_oneof![
+            any::<u64>().prop_map(Fp::from),
+            any::<u64>().prop_map(|value| -Fp::from(value)),
+            any::<u64>().prop_map(|value| Fp::from(value).square().square()),
+        ]
+    }
+
+    fn arb_expression() -> impl Strategy<Value = Expression<Fp>> {
+        let leaf = prop_oneof![
+            arb_constant().prop_map(Expression::Constant),
+            (0..2usize).prop_map(|index| Expression::Selector(Selector(index, false))),
+            (0..2usize, -3..3i32).prop_map(|(column_index, rotation)| {
+                Expression::Fixed(FixedQuery {
+                    index: None,
+                    column_index,
+                    rotation: Rotation(rotation),
+                })
+            }),
+            (0..2usize, -3..3i32).prop_map(|(column_index, rotation)| {
+                Expression::Advice(AdviceQuery {
+                    index: None,
+                    column_index,
+                    rotation: Rotation(rotation),
+                    phase: test_cs().advice_column_phase[column_index],
+                })
+            }),
+            (-3..3i32).prop_map(|rotation| {
+                Expression::Instance(InstanceQuery {
+                    index: None,
+                    column_index: 0,
+                    rotation: Rotation(rotation),
+                })
+            }),
+            Just(Expression::Challenge(Challenge {
+                index: 0,
+                phase: test_cs().challenge_phase[0],
+            })),
+        ];
+        leaf.prop_recursive(6, 64, 2, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|a| Expression::Negated(Box::new(a))),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(a, b)| Expression::Sum(Box::new(a), Box::new(b))),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(a, b)| Expression::Product(Box::new(a), Box::new(b))),
+                (inner, arb_constant()).prop_map(|(a, f)| Expression::Scaled(Box::new(a), f)),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn print_and_parse(expression in arb_expression(), seed in any::<u64>()) {
+            let cs = test_cs();
+            let text = expression.to_string_pretty();
+            let parsed = Expression::parse(&text, &cs).unwrap();
+            prop_assert_eq!(evaluate(&parsed, seed), evaluate(&expression, seed), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_gate() {
+        let cs = test_cs();
+        let gate = &cs.gates[0].polys[0];
+        let text = gate.to_string_pretty();
+        assert_eq!(text, "s0 * (a0[1] * (f0[-1] - 3) + a1[0] * c0 - i0[0])");
+        assert_eq!(Expression::parse(&text, &cs).as_ref(), Ok(gate));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cs = test_cs();
+        let error = |text| Expression::<Fp>::parse(text, &cs).unwrap_err();
+        assert_eq!(
+            error("a0[1] * (f2[0] - 1)"),
+            ParseError {
+                offset: 9,
+                message: "no such fixed column".to_string(),
+            }
+        );
+        assert_eq!(
+            error("a0[1] +").to_string(),
+            "unexpected end of expression at offset 7"
+        );
+        assert_eq!(error("a0 + 1").message, "expected '['");
+        assert_eq!(error("(a0[0] + 1").message, "expected ')'");
+        assert_eq!(error("a0[0] a0[1]").offset, 6);
+        assert_eq!(error("0x").message, "invalid hexadecimal constant");
+    }
+}
@@ -0,0 +1,84 @@
+use ff::Field;
+
+use super::{Any, Assignment, Column, Fixed, Selector, TableColumn};
+use crate::circuit::Value;
+use crate::plonk::{Assigned, Error};
+
+/// The fixed data of a circuit: the values of its fixed columns and lookup
+/// tables, the rows its selectors are enabled on and its copy constraints.
+///
+/// Returned by [`Circuit::fixed_assignments`](super::Circuit::fixed_assignments)
+/// for keygen to use instead of synthesizing the circuit. Rows are absolute,
+/// and are checked against the usable rows when keygen assigns them.
+#[derive(Clone, Debug, Default)]
+pub struct FixedAssignments<F: Field> {
+    fixed: Vec<(Column<Fixed>, usize, F)>,
+    tables: Vec<(TableColumn, Vec<F>)>,
+    selectors: Vec<(Selector, usize)>,
+    copies: Vec<(Column<Any>, usize, Column<Any>, usize)>,
+}
+
+impl<F: Field> FixedAssignments<F> {
+    /// Returns empty fixed data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns `value` to `column` at `row`.
+    pub fn assign_fixed(&mut self, column: Column<Fixed>, row: usize, value: F) {
+        self.fixed.push((column, row, value));
+    }
+
+    /// Assigns `values` to the first rows of `table`. As the table layouter
+    /// does, the remaining usable rows are filled with the first value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    pub fn assign_table(&mut self, table: TableColumn, values: Vec<F>) {
+        assert!(!values.is_empty(), "{:?} is assigned no values", table);
+        self.tables.push((table, values));
+    }
+
+    /// Enables `selector` at `row`.
+    pub fn enable_selector(&mut self, selector: Selector, row: usize) {
+        self.selectors.push((selector, row));
+    }
+
+    /// Constrains the cell of `left` at `left_row` to equal the cell of
+    /// `right` at `right_row`. Both columns must have equality enabled.
+    pub fn copy<L: Into<Column<Any>>, R: Into<Column<Any>>>(
+        &mut self,
+        left: L,
+        left_row: usize,
+        right: R,
+        right_row: usize,
+    ) {
+        self.copies
+            .push((left.into(), left_row, right.into(), right_row));
+    }
+
+    /// Assigns the fixed data to `assignment`, as synthesis would.
+    pub(crate) fn assign<A: Assignment<F>>(&self, assignment: &mut A) -> Result<(), Error> {
+        for &(column, row, value) in &self.fixed {
+            assignment.assign_fixed(|| "", column, row, || Value::known(value))?;
+        }
+        for (table, values) in &self.tables {
+            for (row, value) in values.iter().enumerate() {
+                assignment.assign_fixed(|| "", table.inner(), row, || Value::known(*value))?;
+            }
+            assignment.fill_from_row(
+                table.inner(),
+                values.len(),
+                Value::known(Assigned::from(values[0])),
+            )?;
+        }
+        for (selector, row) in &self.selectors {
+            assignment.enable_selector(|| "", selector, *row)?;
+        }
+        for &(left, left_row, right, right_row) in &self.copies {
+            assignment.copy(left, left_row, right, right_row)?;
+        }
+        Ok(())
+    }
+}
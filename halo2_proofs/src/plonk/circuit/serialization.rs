@@ -0,0 +1,1110 @@
+//! A versioned binary format for constraint systems and expressions.
+
+use std::io::{self, Read};
+
+use ff::PrimeField;
+
+use super::{
+    sealed, Advice, AdviceQuery, Any, Challenge, Column, ConstraintSystem, Expression, Fixed,
+    FixedQuery, Gate, Instance, InstanceQuery, LookupBackend, PinnedVersion, Selector,
+    SelectorExpr, VirtualCell,
+};
+use crate::dev::metadata;
+use crate::helpers::SerdePrimeField;
+use crate::plonk::{lookup, permutation, shuffle};
+use crate::poly::Rotation;
+use crate::SerdeFormat;
+
+/// The version of the format, written first. Readers reject any other
+/// version.
+const VERSION: u8 = 1;
+
+/// The deepest nesting of expressions and selector combinations that readers
+/// accept, so that malformed input cannot overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u8<W: io::Write>(writer: &mut W, value: u8) -> io::Result<()> {
+    writer.write_all(&[value])
+}
+
+fn read_u8<R: io::Read>(reader: &mut R) -> io::Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn write_bool<W: io::Write>(writer: &mut W, value: bool) -> io::Result<()> {
+    write_u8(writer, value as u8)
+}
+
+fn read_bool<R: io::Read>(reader: &mut R) -> io::Result<bool> {
+    match read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(invalid("invalid boolean")),
+    }
+}
+
+fn write_u32<W: io::Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    let value = u32::try_from(value).map_err(|_| invalid("value does not fit in 32 bits"))?;
+    writer.write_all(&value.to_be_bytes())
+}
+
+fn read_u32<R: io::Read>(reader: &mut R) -> io::Result<usize> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes) as usize)
+}
+
+fn write_rotation<W: io::Write>(writer: &mut W, rotation: Rotation) -> io::Result<()> {
+    writer.write_all(&rotation.0.to_be_bytes())
+}
+
+fn read_rotation<R: io::Read>(reader: &mut R) -> io::Result<Rotation> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(Rotation(i32::from_be_bytes(bytes)))
+}
+
+fn write_string<W: io::Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_u32(writer, value.len())?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_string<R: io::Read>(reader: &mut R) -> io::Result<String> {
+    let len = read_u32(reader)?;
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("invalid UTF-8 string"))
+}
+
+fn write_vec<W: io::Write, T>(
+    writer: &mut W,
+    items: &[T],
+    mut write_item: impl FnMut(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()> {
+    write_u32(writer, items.len())?;
+    for item in items {
+        write_item(writer, item)?;
+    }
+    Ok(())
+}
+
+fn read_vec<R: io::Read, T>(
+    reader: &mut R,
+    mut read_item: impl FnMut(&mut R) -> io::Result<T>,
+) -> io::Result<Vec<T>> {
+    // The length is not trusted to preallocate the vector.
+    let len = read_u32(reader)?;
+    (0..len).map(|_| read_item(reader)).collect()
+}
+
+fn write_query_index<W: io::Write>(writer: &mut W, index: Option<usize>) -> io::Result<()> {
+    match index {
+        None => write_bool(writer, false),
+        Some(index) => {
+            write_bool(writer, true)?;
+            write_u32(writer, index)
+        }
+    }
+}
+
+fn read_query_index<R: io::Read>(reader: &mut R) -> io::Result<Option<usize>> {
+    if read_bool(reader)? {
+        read_u32(reader).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn write_column<W: io::Write>(writer: &mut W, column: Column<Any>) -> io::Result<()> {
+    match column.column_type {
+        Any::Advice(advice) => {
+            write_u8(writer, 0)?;
+            write_u8(writer, advice.phase.0)?;
+        }
+        Any::Fixed => write_u8(writer, 1)?,
+        Any::Instance => write_u8(writer, 2)?,
+    }
+    write_u32(writer, column.index)
+}
+
+fn read_column<R: io::Read>(reader: &mut R) -> io::Result<Column<Any>> {
+    let column_type = match read_u8(reader)? {
+        0 => Any::Advice(Advice {
+            phase: sealed::Phase(read_u8(reader)?),
+        }),
+        1 => Any::Fixed,
+        2 => Any::Instance,
+        _ => return Err(invalid("invalid column type")),
+    };
+    let index = read_u32(reader)?;
+    Ok(Column { index, column_type })
+}
+
+fn write_expression<W: io::Write, F: SerdePrimeField>(
+    writer: &mut W,
+    expression: &Expression<F>,
+    format: SerdeFormat,
+) -> io::Result<()> {
+    match expression {
+        Expression::Constant(scalar) => {
+            write_u8(writer, 0)?;
+            SerdePrimeField::write(scalar, writer, format)
+        }
+        Expression::Selector(selector) => {
+            write_u8(writer, 1)?;
+            write_u32(writer, selector.0)?;
+            write_bool(writer, selector.1)
+        }
+        Expression::Fixed(query) => {
+            write_u8(writer, 2)?;
+            write_query_index(writer, query.index)?;
+            write_u32(writer, query.column_index)?;
+            write_rotation(writer, query.rotation)
+        }
+        Expression::Advice(query) => {
+            write_u8(writer, 3)?;
+            write_query_index(writer, query.index)?;
+            write_u32(writer, query.column_index)?;
+            write_rotation(writer, query.rotation)?;
+            write_u8(writer, query.phase.0)
+        }
+        Expression::Instance(query) => {
+            write_u8(writer, 4)?;
+            write_query_index(writer, query.index)?;
+            write_u32(writer, query.column_index)?;
+            write_rotation(writer, query.rotation)
+        }
+        Expression::Challenge(challenge) => {
+            write_u8(writer, 5)?;
+            write_u32(writer, challenge.index)?;
+            write_u8(writer, challenge.phase.0)
+        }
+        Expression::Negated(a) => {
+            write_u8(writer, 6)?;
+            write_expression(writer, a, format)
+        }
+        Expression::Sum(a, b) => {
+            write_u8(writer, 7)?;
+            write_expression(writer, a, format)?;
+            write_expression(writer, b, format)
+        }
+        Expression::Product(a, b) => {
+            write_u8(writer, 8)?;
+            write_expression(writer, a, format)?;
+            write_expression(writer, b, format)
+        }
+        Expression::Scaled(a, scalar) => {
+            write_u8(writer, 9)?;
+            write_expression(writer, a, format)?;
+            SerdePrimeField::write(scalar, writer, format)
+        }
+    }
+}
+
+fn read_expression<R: io::Read, F: SerdePrimeField>(
+    reader: &mut R,
+    format: SerdeFormat,
+    depth: usize,
+) -> io::Result<Expression<F>> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("expression nested too deeply"));
+    }
+    let read_boxed = |reader: &mut R| read_expression(reader, format, depth + 1).map(Box::new);
+    Ok(match read_u8(reader)? {
+        0 => Expression::Constant(F::read(reader, format)?),
+        1 => Expression::Selector(Selector(read_u32(reader)?, read_bool(reader)?)),
+        2 => Expression::Fixed(FixedQuery {
+            index: read_query_index(reader)?,
+            column_index: read_u32(reader)?,
+            rotation: read_rotation(reader)?,
+        }),
+        3 => Expression::Advice(AdviceQuery {
+            index: read_query_index(reader)?,
+            column_index: read_u32(reader)?,
+            rotation: read_rotation(reader)?,
+            phase: sealed::Phase(read_u8(reader)?),
+        }),
+        4 => Expression::Instance(InstanceQuery {
+            index: read_query_index(reader)?,
+            column_index: read_u32(reader)?,
+            rotation: read_rotation(reader)?,
+        }),
+        5 => Expression::Challenge(Challenge {
+            index: read_u32(reader)?,
+            phase: sealed::Phase(read_u8(reader)?),
+        }),
+        6 => Expression::Negated(read_boxed(reader)?),
+        7 => Expression::Sum(read_boxed(reader)?, read_boxed(reader)?),
+        8 => Expression::Product(read_boxed(reader)?, read_boxed(reader)?),
+        9 => Expression::Scaled(read_boxed(reader)?, F::read(reader, format)?),
+        _ => return Err(invalid("invalid expression tag")),
+    })
+}
+
+fn write_selector_expr<W: io::Write>(writer: &mut W, combination: &SelectorExpr) -> io::Result<()> {
+    match combination {
+        SelectorExpr::Selector(selector) => {
+            write_u8(writer, 0)?;
+            write_u32(writer, selector.0)?;
+            write_bool(writer, selector.1)
+        }
+        SelectorExpr::Not(a) => {
+            write_u8(writer, 1)?;
+            write_selector_expr(writer, a)
+        }
+        SelectorExpr::And(a, b) => {
+            write_u8(writer, 2)?;
+            write_selector_expr(writer, a)?;
+            write_selector_expr(writer, b)
+        }
+        SelectorExpr::Or(a, b) => {
+            write_u8(writer, 3)?;
+            write_selector_expr(writer, a)?;
+            write_selector_expr(writer, b)
+        }
+    }
+}
+
+fn read_selector_expr<R: io::Read>(reader: &mut R, depth: usize) -> io::Result<SelectorExpr> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("selector combination nested too deeply"));
+    }
+    let read_boxed = |reader: &mut R| read_selector_expr(reader, depth + 1).map(Box::new);
+    Ok(match read_u8(reader)? {
+        0 => SelectorExpr::Selector(Selector(read_u32(reader)?, read_bool(reader)?)),
+        1 => SelectorExpr::Not(read_boxed(reader)?),
+        2 => SelectorExpr::And(read_boxed(reader)?, read_boxed(reader)?),
+        3 => SelectorExpr::Or(read_boxed(reader)?, read_boxed(reader)?),
+        _ => return Err(invalid("invalid selector combination tag")),
+    })
+}
+
+fn read_version<R: io::Read>(reader: &mut R) -> io::Result<()> {
+    if read_u8(reader)? != VERSION {
+        return Err(invalid("unsupported serialization version"));
+    }
+    Ok(())
+}
+
+impl<F: SerdePrimeField> Expression<F> {
+    /// Writes the expression to a buffer, in a versioned format that
+    /// [`Self::read`] reads back exactly. Constants are written according to
+    /// `format`, as with [`VerifyingKey::write`](crate::plonk::VerifyingKey::write).
+    pub fn write<W: io::Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        write_u8(writer, VERSION)?;
+        write_expression(writer, self, format)
+    }
+
+    /// Reads an expression written with [`Self::write`].
+    ///
+    /// The columns, selectors and challenges of the expression are not
+    /// checked, since they only make sense in a constraint system; expressions
+    /// read as part of one with [`ConstraintSystem::read`] are.
+    pub fn read<R: io::Read>(reader: &mut R, format: SerdeFormat) -> io::Result<Self> {
+        read_version(reader)?;
+        read_expression(reader, format, 0)
+    }
+}
+
+/// Checks that `rotation` is within the largest domain of the field.
+fn check_rotation<F: PrimeField>(rotation: Rotation) -> io::Result<()> {
+    match 1u64.checked_shl(F::S) {
+        Some(bound) if u64::from(rotation.0.unsigned_abs()) >= bound => {
+            Err(invalid("rotation out of bounds"))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl<F: SerdePrimeField> ConstraintSystem<F> {
+    /// Writes the constraint system to a buffer, in a versioned format that
+    /// [`Self::read`] reads back exactly: columns and their phases, queries,
+    /// gates, lookups, shuffles, the permutation argument, selectors and how
+    /// they were compressed, names and settings. Constants in
+    /// expressions are written according to `format`, as with
+    /// [`VerifyingKey::write`](crate::plonk::VerifyingKey::write).
+    pub fn write<W: io::Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        let expressions = |writer: &mut W, expressions: &[Expression<F>]| {
+            write_vec(writer, expressions, |writer, expression| {
+                write_expression(writer, expression, format)
+            })
+        };
+
+        write_u8(writer, VERSION)?;
+        write_u32(writer, self.num_fixed_columns)?;
+        write_u32(writer, self.num_advice_columns)?;
+        write_u32(writer, self.num_instance_columns)?;
+        write_u32(writer, self.num_selectors)?;
+        write_u32(writer, self.num_challenges)?;
+        for phase in self.advice_column_phase.iter().chain(&self.challenge_phase) {
+            write_u8(writer, phase.0)?;
+        }
+        write_u8(writer, self.max_phase())?;
+        for phase in 0..=self.max_phase() {
+            write_u32(writer, self.num_advice_columns_in_phase(phase))?;
+        }
+        write_vec(writer, &self.unblinded_advice_columns, |writer, index| {
+            write_u32(writer, *index)
+        })?;
+        write_vec(writer, &self.uncompressed_selectors, |writer, index| {
+            write_u32(writer, *index)
+        })?;
+        write_vec(
+            writer,
+            &self.challenge_powers,
+            |writer, (challenge, base, exponent)| {
+                write_u32(writer, *challenge)?;
+                write_u32(writer, *base)?;
+                write_u32(writer, *exponent as usize)
+            },
+        )?;
+        write_vec(
+            writer,
+            &self.selector_combinations,
+            |writer, (index, combination)| {
+                write_u32(writer, *index)?;
+                write_selector_expr(writer, combination)
+            },
+        )?;
+
+        write_vec(writer, &self.fixed_queries, |writer, (column, rotation)| {
+            write_u32(writer, column.index)?;
+            write_rotation(writer, *rotation)
+        })?;
+        write_vec(
+            writer,
+            &self.advice_queries,
+            |writer, (column, rotation)| {
+                write_u32(writer, column.index)?;
+                write_rotation(writer, *rotation)
+            },
+        )?;
+        write_vec(
+            writer,
+            &self.instance_queries,
+            |writer, (column, rotation)| {
+                write_u32(writer, column.index)?;
+                write_rotation(writer, *rotation)
+            },
+        )?;
+
+        write_vec(writer, &self.permutation.columns, |writer, column| {
+            write_column(writer, *column)
+        })?;
+        write_vec(writer, &self.constants, |writer, column| {
+            write_u32(writer, column.index)
+        })?;
+        write_vec(writer, &self.selector_map, |writer, column| {
+            write_u32(writer, column.index)
+        })?;
+        expressions(writer, &self.selector_replacements)?;
+
+        write_vec(writer, &self.gates, |writer, gate| {
+            write_string(writer, &gate.name)?;
+            write_vec(writer, &gate.constraint_names, |writer, name| {
+                write_string(writer, name)
+            })?;
+            expressions(writer, &gate.polys)?;
+            write_vec(writer, &gate.queried_selectors, |writer, selector| {
+                write_u32(writer, selector.0)?;
+                write_bool(writer, selector.1)
+            })?;
+            write_vec(writer, &gate.queried_cells, |writer, cell| {
+                write_column(writer, cell.column)?;
+                write_rotation(writer, cell.rotation)
+            })
+        })?;
+        write_vec(writer, &self.lookups, |writer, lookup| {
+            write_string(writer, &lookup.name)?;
+            match &lookup.table {
+                None => write_bool(writer, false)?,
+                Some(table) => {
+                    write_bool(writer, true)?;
+                    write_string(writer, table)?;
+                }
+            }
+            expressions(writer, &lookup.input_expressions)?;
+            expressions(writer, &lookup.table_expressions)
+        })?;
+        write_vec(writer, &self.shuffles, |writer, shuffle| {
+            write_string(writer, &shuffle.name)?;
+            expressions(writer, &shuffle.input_expressions)?;
+            expressions(writer, &shuffle.shuffle_expressions)
+        })?;
+
+        // The annotations are sorted, so that equal constraint systems are
+        // written the same way.
+        let mut annotations: Vec<_> = self
+            .general_column_annotations
+            .iter()
+            .map(|(column, annotation)| {
+                let column = Column {
+                    index: column.index(),
+                    column_type: column.column_type(),
+                };
+                (column, annotation)
+            })
+            .collect();
+        annotations.sort();
+        write_vec(writer, &annotations, |writer, (column, annotation)| {
+            write_column(writer, *column)?;
+            write_string(writer, annotation)
+        })?;
+        let mut selector_names: Vec<_> = self.selector_names.iter().collect();
+        selector_names.sort();
+        write_vec(writer, &selector_names, |writer, (selector, name)| {
+            write_u32(writer, **selector)?;
+            write_string(writer, name)
+        })?;
+
+        match self.minimum_degree {
+            None => write_bool(writer, false)?,
+            Some(degree) => {
+                write_bool(writer, true)?;
+                write_u32(writer, degree)?;
+            }
+        }
+        write_bool(writer, self.zero_knowledge)?;
+        write_u8(
+            writer,
+            match self.lookup_backend {
+                LookupBackend::Permutation => 0,
+                LookupBackend::LogUp => 1,
+            },
+        )?;
+        write_u8(
+            writer,
+            match self.pinned_version {
+                PinnedVersion::V0 => 0,
+                PinnedVersion::V1 => 1,
+            },
+        )?;
+        write_vec(writer, &self.disabled_gates, |writer, name| {
+            write_string(writer, name)
+        })?;
+        write_bool(writer, self.allow_disabled_gates)?;
+        write_vec(
+            writer,
+            &self.preassigned_fixed,
+            |writer, (column, values)| {
+                write_u32(writer, column.index)?;
+                write_vec(writer, values, |writer, value| {
+                    SerdePrimeField::write(value, writer, format)
+                })
+            },
+        )?;
+        write_vec(writer, &self.gates, |writer, gate| match gate.cost {
+            None => write_bool(writer, false),
+            Some(cost) => {
+                write_bool(writer, true)?;
+                write_u32(writer, cost)
+            }
+        })?;
+        write_vec(writer, &self.column_pins, |writer, (name, index)| {
+            write_string(writer, name)?;
+            write_u32(writer, *index)
+        })
+    }
+
+    /// Reads a constraint system written with [`Self::write`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the version is not the
+    /// one this crate writes, or if the constraint system is inconsistent: a
+    /// column, selector or challenge out of range, a query that is not the
+    /// one listed at its index, a rotation beyond the largest domain of the
+    /// field, or arguments whose inputs and tables differ in length.
+    pub fn read<R: io::Read>(reader: &mut R, format: SerdeFormat) -> io::Result<Self> {
+        let expressions =
+            |reader: &mut R| read_vec(reader, |reader| read_expression::<_, F>(reader, format, 0));
+
+        read_version(reader)?;
+        let mut cs = ConstraintSystem::<F> {
+            num_fixed_columns: read_u32(reader)?,
+            num_advice_columns: read_u32(reader)?,
+            num_instance_columns: read_u32(reader)?,
+            num_selectors: read_u32(reader)?,
+            num_challenges: read_u32(reader)?,
+            ..Default::default()
+        };
+        // The phases are read one by one, as the number of columns is not
+        // trusted to preallocate them.
+        for _ in 0..cs.num_advice_columns {
+            cs.advice_column_phase.push(sealed::Phase(read_u8(reader)?));
+        }
+        for _ in 0..cs.num_challenges {
+            cs.challenge_phase.push(sealed::Phase(read_u8(reader)?));
+        }
+        // The number of advice columns of each phase is written after the
+        // phases, and must agree with them.
+        if read_u8(reader)? != cs.max_phase() {
+            return Err(invalid("inconsistent advice column phases"));
+        }
+        for phase in 0..=cs.max_phase() {
+            if read_u32(reader)? != cs.num_advice_columns_in_phase(phase) {
+                return Err(invalid("inconsistent advice column phases"));
+            }
+        }
+        // Challenges are squeezed after a phase with advice columns.
+        if cs
+            .challenge_phase
+            .iter()
+            .any(|phase| cs.num_advice_columns_in_phase(phase.0) == 0)
+        {
+            return Err(invalid("challenge after a phase without advice columns"));
+        }
+        cs.unblinded_advice_columns = read_vec(reader, read_u32)?;
+        if cs
+            .unblinded_advice_columns
+            .iter()
+            .any(|index| *index >= cs.num_advice_columns)
+        {
+            return Err(invalid("unblinded advice column out of range"));
+        }
+        cs.uncompressed_selectors = read_vec(reader, read_u32)?;
+        if cs
+            .uncompressed_selectors
+            .iter()
+            .any(|index| *index >= cs.num_selectors)
+        {
+            return Err(invalid("uncompressed selector out of range"));
+        }
+        cs.challenge_powers = read_vec(reader, |reader| {
+            let challenge = read_u32(reader)?;
+            let base = read_u32(reader)?;
+            let exponent = read_u32(reader)? as u32;
+            if challenge >= cs.num_challenges || base >= cs.num_challenges {
+                return Err(invalid("challenge out of range"));
+            }
+            if exponent < 2 || cs.challenge_phase[challenge] != cs.challenge_phase[base] {
+                return Err(invalid("invalid challenge power"));
+            }
+            Ok((challenge, base, exponent))
+        })?;
+        // Powers are taken of squeezed challenges, and each challenge is
+        // computed at most once.
+        if cs
+            .challenge_powers
+            .iter()
+            .enumerate()
+            .any(|(i, (challenge, base, _))| {
+                !cs.is_challenge_squeezed(*base)
+                    || cs.challenge_powers[..i]
+                        .iter()
+                        .any(|(other, _, _)| other == challenge)
+            })
+        {
+            return Err(invalid("invalid challenge power"));
+        }
+        cs.selector_combinations = read_vec(reader, |reader| {
+            Ok((read_u32(reader)?, read_selector_expr(reader, 0)?))
+        })?;
+        // Each combination is a selector of its own, made of selectors that
+        // are not combinations.
+        let is_combination = |selector: usize| {
+            cs.selector_combinations
+                .iter()
+                .any(|(index, _)| *index == selector)
+        };
+        if cs
+            .selector_combinations
+            .iter()
+            .enumerate()
+            .any(|(i, (index, combination))| {
+                *index >= cs.num_selectors
+                    || cs.selector_combinations[..i]
+                        .iter()
+                        .any(|(other, _)| other == index)
+                    || combination.selectors().iter().any(|selector| {
+                        selector.0 >= cs.num_selectors || is_combination(selector.0)
+                    })
+            })
+        {
+            return Err(invalid("invalid selector combination"));
+        }
+
+        cs.fixed_queries = read_vec(reader, |reader| {
+            let index = read_u32(reader)?;
+            let rotation = read_rotation(reader)?;
+            if index >= cs.num_fixed_columns {
+                return Err(invalid("fixed column out of range"));
+            }
+            check_rotation::<F>(rotation)?;
+            Ok((Column::new_fixed(index), rotation))
+        })?;
+        cs.advice_queries = read_vec(reader, |reader| {
+            let index = read_u32(reader)?;
+            let rotation = read_rotation(reader)?;
+            let phase = *cs
+                .advice_column_phase
+                .get(index)
+                .ok_or_else(|| invalid("advice column out of range"))?;
+            check_rotation::<F>(rotation)?;
+            Ok((Column::new_advice(index, phase), rotation))
+        })?;
+        cs.num_advice_queries = vec![0; cs.num_advice_columns];
+        for (column, _) in cs.advice_queries.iter() {
+            cs.num_advice_queries[column.index] += 1;
+        }
+        cs.instance_queries = read_vec(reader, |reader| {
+            let index = read_u32(reader)?;
+            let rotation = read_rotation(reader)?;
+            if index >= cs.num_instance_columns {
+                return Err(invalid("instance column out of range"));
+            }
+            check_rotation::<F>(rotation)?;
+            Ok((Column::new_instance(index), rotation))
+        })?;
+
+        let columns = read_vec(reader, read_column)?;
+        for column in columns.iter() {
+            cs.check_column(*column)?;
+        }
+        cs.permutation = permutation::Argument { columns };
+        let fixed_columns = |reader: &mut R, cs: &Self| {
+            read_vec(reader, |reader| {
+                let index = read_u32(reader)?;
+                if index >= cs.num_fixed_columns {
+                    return Err(invalid("fixed column out of range"));
+                }
+                Ok(Column::new_fixed(index))
+            })
+        };
+        cs.constants = fixed_columns(reader, &cs)?;
+        cs.selector_map = fixed_columns(reader, &cs)?;
+        cs.selector_replacements = expressions(reader)?;
+        if (!cs.selector_map.is_empty() && cs.selector_map.len() != cs.num_selectors)
+            || cs.selector_replacements.len() != cs.selector_map.len()
+        {
+            return Err(invalid("selector map does not cover the selectors"));
+        }
+        for expression in cs.selector_replacements.iter() {
+            cs.check_expression(expression)?;
+        }
+
+        cs.gates = read_vec(reader, |reader| {
+            let gate = Gate {
+                name: read_string(reader)?,
+                constraint_names: read_vec(reader, read_string)?,
+                polys: expressions(reader)?,
+                queried_selectors: read_vec(reader, |reader| {
+                    Ok(Selector(read_u32(reader)?, read_bool(reader)?))
+                })?,
+                queried_cells: read_vec(reader, |reader| {
+                    Ok(VirtualCell {
+                        column: read_column(reader)?,
+                        rotation: read_rotation(reader)?,
+                    })
+                })?,
+                cost: None,
+            };
+            if gate.constraint_names.len() != gate.polys.len() {
+                return Err(invalid("gate constraints and names differ in length"));
+            }
+            for poly in gate.polys.iter() {
+                cs.check_expression(poly)?;
+            }
+            if gate
+                .queried_selectors
+                .iter()
+                .any(|selector| selector.0 >= cs.num_selectors)
+            {
+                return Err(invalid("selector out of range"));
+            }
+            for cell in gate.queried_cells.iter() {
+                cs.check_column(cell.column)?;
+                check_rotation::<F>(cell.rotation)?;
+            }
+            Ok(gate)
+        })?;
+        cs.lookups = read_vec(reader, |reader| {
+            let lookup = lookup::Argument {
+                name: read_string(reader)?,
+                table: if read_bool(reader)? {
+                    Some(read_string(reader)?)
+                } else {
+                    None
+                },
+                input_expressions: expressions(reader)?,
+                table_expressions: expressions(reader)?,
+            };
+            if lookup.input_expressions.len() != lookup.table_expressions.len() {
+                return Err(invalid("lookup inputs and table differ in length"));
+            }
+            for expression in lookup
+                .input_expressions
+                .iter()
+                .chain(&lookup.table_expressions)
+            {
+                cs.check_expression(expression)?;
+            }
+            Ok(lookup)
+        })?;
+        cs.shuffles = read_vec(reader, |reader| {
+            let shuffle = shuffle::Argument {
+                name: read_string(reader)?,
+                input_expressions: expressions(reader)?,
+                shuffle_expressions: expressions(reader)?,
+            };
+            if shuffle.input_expressions.len() != shuffle.shuffle_expressions.len() {
+                return Err(invalid("shuffle inputs and shuffles differ in length"));
+            }
+            for expression in shuffle
+                .input_expressions
+                .iter()
+                .chain(&shuffle.shuffle_expressions)
+            {
+                cs.check_expression(expression)?;
+            }
+            Ok(shuffle)
+        })?;
+
+        let annotations = read_vec(reader, |reader| {
+            let column = read_column(reader)?;
+            cs.check_column(column)?;
+            let column = metadata::Column::from((column.column_type, column.index));
+            Ok((column, read_string(reader)?))
+        })?;
+        cs.general_column_annotations = annotations.into_iter().collect();
+        let selector_names = read_vec(reader, |reader| {
+            let selector = read_u32(reader)?;
+            if selector >= cs.num_selectors {
+                return Err(invalid("selector out of range"));
+            }
+            Ok((selector, read_string(reader)?))
+        })?;
+        cs.selector_names = selector_names.into_iter().collect();
+
+        cs.minimum_degree = if read_bool(reader)? {
+            Some(read_u32(reader)?)
+        } else {
+            None
+        };
+        cs.zero_knowledge = read_bool(reader)?;
+        cs.lookup_backend = match read_u8(reader)? {
+            0 => LookupBackend::Permutation,
+            1 => LookupBackend::LogUp,
+            _ => return Err(invalid("invalid lookup backend")),
+        };
+        cs.pinned_version = match read_u8(reader)? {
+            0 => PinnedVersion::V0,
+            1 => PinnedVersion::V1,
+            _ => return Err(invalid("invalid pinned version")),
+        };
+        cs.disabled_gates = read_vec(reader, read_string)?;
+        cs.allow_disabled_gates = read_bool(reader)?;
+        cs.preassigned_fixed = read_vec(reader, |reader| {
+            let index = read_u32(reader)?;
+            if index >= cs.num_fixed_columns {
+                return Err(invalid("fixed column out of range"));
+            }
+            let values = read_vec(reader, |reader| F::read(reader, format))?;
+            Ok((Column::new_fixed(index), values))
+        })?;
+        let costs = read_vec(reader, |reader| {
+            Ok(if read_bool(reader)? {
+                Some(read_u32(reader)?)
+            } else {
+                None
+            })
+        })?;
+        if costs.len() != cs.gates.len() {
+            return Err(invalid("gate costs and gates differ in length"));
+        }
+        for (gate, cost) in cs.gates.iter_mut().zip(costs) {
+            gate.cost = cost;
+        }
+        cs.column_pins = read_vec(reader, |reader| {
+            Ok((read_string(reader)?, read_u32(reader)?))
+        })?;
+        Ok(cs)
+    }
+
+    /// Writes the constraint system to a vector of bytes using
+    /// [`Self::write`].
+    pub fn to_bytes(&self, format: SerdeFormat) -> Vec<u8> {
+        let mut bytes = vec![];
+        self.write(&mut bytes, format)
+            .expect("Writing to vector should not fail");
+        bytes
+    }
+
+    /// Reads a constraint system from a slice of bytes using [`Self::read`].
+    pub fn from_bytes(mut bytes: &[u8], format: SerdeFormat) -> io::Result<Self> {
+        Self::read(&mut bytes, format)
+    }
+
+    /// Checks that `column` is one of the columns of the constraint system,
+    /// in its phase if it is an advice column.
+    fn check_column(&self, column: Column<Any>) -> io::Result<()> {
+        let in_range = match column.column_type {
+            Any::Advice(advice) => {
+                self.advice_column_phase.get(column.index) == Some(&advice.phase)
+            }
+            Any::Fixed => column.index < self.num_fixed_columns,
+            Any::Instance => column.index < self.num_instance_columns,
+        };
+        if in_range {
+            Ok(())
+        } else {
+            Err(invalid("column out of range"))
+        }
+    }
+
+    /// Checks that the columns, selectors and challenges of `expression`
+    /// belong to the constraint system, and that its queries are those listed
+    /// at their index.
+    fn check_expression(&self, expression: &Expression<F>) -> io::Result<()> {
+        fn check_query<C: PartialEq>(
+            queries: &[(C, Rotation)],
+            index: Option<usize>,
+            column: C,
+            rotation: Rotation,
+        ) -> io::Result<()> {
+            match index {
+                Some(index) if queries.get(index) != Some(&(column, rotation)) => {
+                    Err(invalid("query does not match its index"))
+                }
+                _ => Ok(()),
+            }
+        }
+
+        expression.evaluate(
+            &|_| Ok(()),
+            &|selector| {
+                if selector.0 < self.num_selectors {
+                    Ok(())
+                } else {
+                    Err(invalid("selector out of range"))
+                }
+            },
+            &|query| {
+                if query.column_index >= self.num_fixed_columns {
+                    return Err(invalid("fixed column out of range"));
+                }
+                check_rotation::<F>(query.rotation)?;
+                let column = Column::new_fixed(query.column_index);
+                check_query(&self.fixed_queries, query.index, column, query.rotation)
+            },
+            &|query| {
+                let column = Column::new_advice(query.column_index, query.phase);
+                self.check_column(column.into())?;
+                check_rotation::<F>(query.rotation)?;
+                check_query(&self.advice_queries, query.index, column, query.rotation)
+            },
+            &|query| {
+                if query.column_index >= self.num_instance_columns {
+                    return Err(invalid("instance column out of range"));
+                }
+                check_rotation::<F>(query.rotation)?;
+                let column = Column::new_instance(query.column_index);
+                check_query(&self.instance_queries, query.index, column, query.rotation)
+            },
+            &|challenge| {
+                if self.challenge_phase.get(challenge.index) == Some(&challenge.phase) {
+                    Ok(())
+                } else {
+                    Err(invalid("challenge out of range"))
+                }
+            },
+            &|a| a,
+            &|a, b| a.and(b),
+            &|a, b| a.and(b),
+            &|a, _| a,
+        )
+    }
+}
+
+impl Column<Fixed> {
+    fn new_fixed(index: usize) -> Self {
+        Column {
+            index,
+            column_type: Fixed,
+        }
+    }
+}
+
+impl Column<Advice> {
+    fn new_advice(index: usize, phase: sealed::Phase) -> Self {
+        Column {
+            index,
+            column_type: Advice { phase },
+        }
+    }
+}
+
+impl Column<Instance> {
+    fn new_instance(index: usize) -> Self {
+        Column {
+            index,
+            column_type: Instance,
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_cs() -> ConstraintSystem<halo2curves::bn256::Fr> {
+    use super::{FirstPhase, SecondPhase};
+    use halo2curves::bn256::Fr;
+
+    let mut meta = ConstraintSystem::<Fr>::default();
+    let a = meta.advice_column();
+    let b = meta.unblinded_advice_column_in(SecondPhase);
+    let fixed = meta.fixed_column();
+    let instance = meta.instance_column_named("instance");
+    let q = meta.selector_named("q");
+    let q_lookup = meta.complex_selector();
+    let q_constant = meta.uncompressed_selector();
+    let q_other = meta.selector();
+    let table = meta.lookup_table_column();
+    let challenge = meta.challenge_usable_after(FirstPhase);
+    let challenge_cubed = meta.challenge_power(challenge, 3);
+
+    meta.enable_equality(a);
+    meta.enable_equality(instance);
+    meta.enable_constant(fixed);
+    meta.annotate_lookup_column(table, || "table");
+    meta.annotate_lookup_any_column(a, || "a");
+    meta.pin_columns(&[("a", 0), ("instance", 0)]);
+
+    meta.create_gate("gate", |meta| {
+        let q = meta.query_selector(q);
+        let a_cur = meta.query_advice(a, Rotation::cur());
+        let a_next = meta.query_advice(a, Rotation::next());
+        let b = meta.query_advice(b, Rotation::prev());
+        let fixed = meta.query_fixed(fixed, Rotation::cur());
+        let instance = meta.query_instance(instance, Rotation::cur());
+        let challenge_cubed = meta.query_challenge(challenge_cubed);
+        let challenge = meta.query_challenge(challenge);
+        vec![
+            (
+                "product",
+                q.clone() * (a_cur.clone() * a_next - b * challenge),
+            ),
+            (
+                "scaled",
+                q * (-(a_cur + fixed) * Fr::from(3) - instance * challenge_cubed),
+            ),
+        ]
+    });
+    meta.create_gate("combination", |meta| {
+        let q = meta.selector_expr(q.or(q_other.and(!q_constant)));
+        let a = meta.query_advice(a, Rotation::cur());
+        vec![q * a]
+    });
+    meta.create_gate_with_cost("constant", 40, |meta| {
+        let q_constant = meta.query_selector(q_constant);
+        let a = meta.query_advice(a, Rotation(-3));
+        vec![q_constant * (a - Expression::Constant(-Fr::from(5)))]
+    });
+    meta.lookup("range", |meta| {
+        let q_lookup = meta.query_selector(q_lookup);
+        let a = meta.query_advice(a, Rotation::cur());
+        vec![(q_lookup * a, table)]
+    });
+    meta.shuffle("shuffle", |meta| {
+        let q_lookup = meta.query_selector(q_lookup);
+        let a = meta.query_advice(a, Rotation::cur());
+        let fixed = meta.query_fixed(fixed, Rotation::cur());
+        vec![(q_lookup * a, fixed)]
+    });
+    meta.set_minimum_degree(5);
+    meta.set_lookup_backend(LookupBackend::LogUp);
+    meta.set_pinned_version(PinnedVersion::V1);
+    meta
+}
+
+#[test]
+fn round_trip() {
+    use halo2curves::bn256::Fr;
+
+    let round_trip = |cs: &ConstraintSystem<Fr>| {
+        for format in [SerdeFormat::Processed, SerdeFormat::RawBytes] {
+            let bytes = cs.to_bytes(format);
+            let read = ConstraintSystem::<Fr>::from_bytes(&bytes, format).unwrap();
+            assert_eq!(format!("{:?}", read.pinned()), format!("{:?}", cs.pinned()));
+            assert_eq!(read.to_bytes(format), bytes);
+            assert_eq!(read.minimum_rows(), cs.minimum_rows());
+            assert_eq!(
+                read.general_column_annotations,
+                cs.general_column_annotations
+            );
+            assert_eq!(read.selector_names, cs.selector_names);
+            assert_eq!(read.column_pins, cs.column_pins);
+            for (read, gate) in read.gates.iter().zip(cs.gates.iter()) {
+                assert_eq!(read.cost(), gate.cost());
+            }
+        }
+    };
+
+    let cs = test_cs();
+    round_trip(&cs);
+
+    let (mut cs, _) = cs.compress_selectors(vec![vec![true, false, true, false]; 5]);
+    cs.disable_gate("constant");
+    round_trip(&cs);
+
+    let expression = &cs.gates[0].polys[1];
+    let mut bytes = vec![];
+    expression
+        .write(&mut bytes, SerdeFormat::Processed)
+        .unwrap();
+    let read = Expression::<Fr>::read(&mut &bytes[..], SerdeFormat::Processed).unwrap();
+    assert_eq!(&read, expression);
+}
+
+#[test]
+fn invalid_constraint_systems() {
+    use halo2curves::bn256::Fr;
+
+    let bytes = test_cs().to_bytes(SerdeFormat::Processed);
+    let read = |bytes: &[u8]| {
+        ConstraintSystem::<Fr>::from_bytes(bytes, SerdeFormat::Processed)
+            .unwrap_err()
+            .kind()
+    };
+
+    // Another version.
+    let mut other_version = bytes.clone();
+    other_version[0] = VERSION + 1;
+    assert_eq!(read(&other_version), io::ErrorKind::InvalidData);
+
+    // No fixed column, although some are queried.
+    let mut no_fixed_columns = bytes.clone();
+    no_fixed_columns[1..5].copy_from_slice(&0u32.to_be_bytes());
+    assert_eq!(read(&no_fixed_columns), io::ErrorKind::InvalidData);
+
+    // Truncated.
+    assert_eq!(
+        read(&bytes[..bytes.len() - 1]),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn deeply_nested_expressions() {
+    use halo2curves::bn256::Fr;
+
+    let read = |depth: usize| {
+        // Negations of a challenge.
+        let mut bytes = vec![VERSION];
+        bytes.extend(std::iter::repeat(6).take(depth));
+        bytes.extend([5, 0, 0, 0, 0, 0]);
+        Expression::<Fr>::read(&mut &bytes[..], SerdeFormat::Processed)
+    };
+
+    assert!(read(MAX_DEPTH).is_ok());
+    let err = read(MAX_DEPTH + 1).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "expression nested too deeply");
+    // Far more than would fit on the stack.
+    assert!(read(1 << 20).is_err());
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+
+use ff::Field;
+
+use super::{AdviceQuery, Challenge, Expression, FixedQuery, InstanceQuery, Selector};
+
+/// Walks an [`Expression`] with [`Expression::visit`], one method per kind of
+/// node.
+///
+/// Nodes are visited depth first, each before its operands, and the operands
+/// of a sum or a product from left to right. The methods for the leaves do
+/// nothing by default, and those for the operations visit their operands, so
+/// an implementation only overrides the methods for the nodes it is
+/// interested in. An implementation overriding the method of an operation
+/// must visit the operands itself to go deeper, for example with
+/// `a.visit(self)`.
+///
+/// ```
+/// use halo2_proofs::plonk::{
+///     Challenge, ConstraintSystem, Expression, ExpressionVisitor, FirstPhase,
+/// };
+/// use halo2curves::pasta::Fp;
+///
+/// /// Counts the references to each challenge.
+/// struct ChallengeCounter(Vec<usize>);
+///
+/// impl ExpressionVisitor<Fp> for ChallengeCounter {
+///     fn visit_challenge(&mut self, challenge: Challenge) {
+///         self.0[challenge.index()] += 1;
+///     }
+/// }
+///
+/// let mut meta = ConstraintSystem::<Fp>::default();
+/// let _ = meta.advice_column();
+/// let theta = meta.challenge_usable_after(FirstPhase);
+/// let gamma = meta.challenge_usable_after(FirstPhase);
+///
+/// let expression: Expression<Fp> =
+///     theta.expr() * theta.expr() + gamma.expr() * Fp::from(3) - Expression::Constant(Fp::one());
+/// let mut counter = ChallengeCounter(vec![0; meta.num_challenges()]);
+/// expression.visit(&mut counter);
+/// assert_eq!(counter.0, vec![2, 1]);
+/// ```
+pub trait ExpressionVisitor<F: Field> {
+    /// Visits a constant.
+    fn visit_constant(&mut self, _value: F) {}
+
+    /// Visits a virtual selector.
+    fn visit_selector(&mut self, _selector: Selector) {}
+
+    /// Visits a query to a fixed column.
+    fn visit_fixed(&mut self, _query: FixedQuery) {}
+
+    /// Visits a query to an advice column.
+    fn visit_advice(&mut self, _query: AdviceQuery) {}
+
+    /// Visits a query to an instance column.
+    fn visit_instance(&mut self, _query: InstanceQuery) {}
+
+    /// Visits a challenge.
+    fn visit_challenge(&mut self, _challenge: Challenge) {}
+
+    /// Visits the negation of `a`.
+    fn visit_negated(&mut self, a: &Expression<F>) {
+        a.visit(self);
+    }
+
+    /// Visits the sum of `a` and `b`.
+    fn visit_sum(&mut self, a: &Expression<F>, b: &Expression<F>) {
+        a.visit(self);
+        b.visit(self);
+    }
+
+    /// Visits the product of `a` and `b`.
+    fn visit_product(&mut self, a: &Expression<F>, b: &Expression<F>) {
+        a.visit(self);
+        b.visit(self);
+    }
+
+    /// Visits `a` scaled by `factor`.
+    fn visit_scaled(&mut self, a: &Expression<F>, _factor: F) {
+        a.visit(self);
+    }
+}
+
+/// The functions [`Expression::fold`] computes the value of each kind of
+/// node with, from the values of its operands.
+///
+/// Adding a kind of node to [`Expression`] adds a field to this struct, so
+/// that every fold has to say how to handle it, rather than silently
+/// ignoring it.
+pub struct ExpressionFold<'a, F, T> {
+    /// The value of a constant.
+    pub constant: &'a dyn Fn(F) -> T,
+    /// The value of a virtual selector.
+    pub selector: &'a dyn Fn(Selector) -> T,
+    /// The value of a query to a fixed column.
+    pub fixed: &'a dyn Fn(FixedQuery) -> T,
+    /// The value of a query to an advice column.
+    pub advice: &'a dyn Fn(AdviceQuery) -> T,
+    /// The value of a query to an instance column.
+    pub instance: &'a dyn Fn(InstanceQuery) -> T,
+    /// The value of a challenge.
+    pub challenge: &'a dyn Fn(Challenge) -> T,
+    /// The value of a negation, from the value of its operand.
+    pub negated: &'a dyn Fn(T) -> T,
+    /// The value of a sum, from the values of its operands.
+    pub sum: &'a dyn Fn(T, T) -> T,
+    /// The value of a product, from the values of its operands.
+    pub product: &'a dyn Fn(T, T) -> T,
+    /// The value of a scaled expression, from the value of the expression
+    /// and the factor.
+    pub scaled: &'a dyn Fn(T, F) -> T,
+}
+
+impl<F, T> fmt::Debug for ExpressionFold<'_, F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpressionFold").finish_non_exhaustive()
+    }
+}
+
+impl<F: Field> Expression<F> {
+    /// Visits this expression with `visitor`; see [`ExpressionVisitor`] for
+    /// the order the nodes are visited in.
+    pub fn visit<V: ExpressionVisitor<F> + ?Sized>(&self, visitor: &mut V) {
+        match self {
+            Expression::Constant(value) => visitor.visit_constant(*value),
+            Expression::Selector(selector) => visitor.visit_selector(*selector),
+            Expression::Fixed(query) => visitor.visit_fixed(*query),
+            Expression::Advice(query) => visitor.visit_advice(*query),
+            Expression::Instance(query) => visitor.visit_instance(*query),
+            Expression::Challenge(challenge) => visitor.visit_challenge(*challenge),
+            Expression::Negated(a) => visitor.visit_negated(a),
+            Expression::Sum(a, b) => visitor.visit_sum(a, b),
+            Expression::Product(a, b) => visitor.visit_product(a, b),
+            Expression::Scaled(a, factor) => visitor.visit_scaled(a, *factor),
+        }
+    }
+
+    /// Computes a value of this expression bottom up, with the functions of
+    /// `fold` for each kind of node.
+    ///
+    /// This is [`Self::evaluate`] with the functions named: the operands of
+    /// a node are folded before the node, from left to right, and each node
+    /// is folded once, even if equal subexpressions appear more than once.
+    pub fn fold<T>(&self, fold: &ExpressionFold<'_, F, T>) -> T {
+        self.evaluate(
+            &fold.constant,
+            &fold.selector,
+            &fold.fixed,
+            &fold.advice,
+            &fold.instance,
+            &fold.challenge,
+            &fold.negated,
+            &fold.sum,
+            &fold.product,
+            &fold.scaled,
+        )
+    }
+}
+
+#[test]
+fn visit_order() {
+    use crate::plonk::{ConstraintSystem, FirstPhase};
+    use crate::poly::Rotation;
+    use halo2curves::pasta::Fp;
+
+    /// Writes down the nodes it visits.
+    struct Trace(Vec<String>);
+
+    impl ExpressionVisitor<Fp> for Trace {
+        fn visit_constant(&mut self, value: Fp) {
+            self.0.push(format!("{:?}", value == Fp::ONE));
+        }
+
+        fn visit_advice(&mut self, query: AdviceQuery) {
+            self.0.push(format!("a{}", query.rotation().0));
+        }
+
+        fn visit_challenge(&mut self, challenge: Challenge) {
+            self.0.push(format!("c{}", challenge.index()));
+        }
+
+        fn visit_product(&mut self, a: &Expression<Fp>, b: &Expression<Fp>) {
+            self.0.push("*".to_string());
+            a.visit(self);
+            b.visit(self);
+        }
+    }
+
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let a = meta.advice_column();
+    let theta = meta.challenge_usable_after(FirstPhase);
+    let mut expression = None;
+    meta.create_gate("gate", |meta| {
+        let cur = meta.query_advice(a, Rotation::cur());
+        let next = meta.query_advice(a, Rotation::next());
+        let e = (cur * theta.expr() - next) * Fp::from(2) + Expression::Constant(Fp::ONE);
+        expression = Some(e.clone());
+        vec![e]
+    });
+
+    let mut trace = Trace(vec![]);
+    expression.unwrap().visit(&mut trace);
+    assert_eq!(trace.0, vec!["*", "a0", "c0", "a1", "true"]);
+
+    let nodes = meta.gates[0].polynomials()[0].fold(&ExpressionFold {
+        constant: &|_| 1,
+        selector: &|_| 1,
+        fixed: &|_| 1,
+        advice: &|_| 1,
+        instance: &|_| 1,
+        challenge: &|_| 1,
+        negated: &|a| a + 1,
+        sum: &|a, b| a + b + 1,
+        product: &|a, b| a + b + 1,
+        scaled: &|a, _| a + 1,
+    });
+    assert_eq!(nodes, meta.gates[0].polynomials()[0].node_count());
+}
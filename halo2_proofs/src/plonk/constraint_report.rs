@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ff::{Field, PrimeField};
+
+use super::{Any, Column, Expression, ProvingKey};
+use crate::{
+    arithmetic::{parallelize, CurveAffine},
+    multicore::{IntoParallelIterator, ParallelIterator},
+};
+
+/// A constraint that does not hold for the witness given to
+/// [`ProvingKey::evaluate_constraints`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintFailure {
+    /// A polynomial constraint of a custom gate is not zero.
+    Gate {
+        /// The index of the gate.
+        gate: usize,
+        /// The name of the gate.
+        gate_name: String,
+        /// The index of the constraint within the gate.
+        constraint: usize,
+        /// The name of the constraint.
+        constraint_name: String,
+        /// The row the constraint is not zero on.
+        row: usize,
+    },
+    /// The custom gates combined by the prover's evaluator and the gates
+    /// evaluated one by one disagree on whether the row satisfies them.
+    EvaluatorMismatch {
+        /// The row they disagree on.
+        row: usize,
+    },
+    /// The input expressions of a lookup take values on this row that are
+    /// not in its table.
+    Lookup {
+        /// The index of the lookup.
+        lookup: usize,
+        /// The name of the lookup.
+        name: String,
+        /// The row of the input.
+        row: usize,
+    },
+    /// The input expressions of a shuffle take values on this row that are
+    /// not matched by the values of its shuffle expressions.
+    Shuffle {
+        /// The index of the shuffle.
+        shuffle: usize,
+        /// The name of the shuffle.
+        name: String,
+        /// The row of the input.
+        row: usize,
+    },
+    /// Two cells the permutation argument constrains to be equal are not.
+    Permutation {
+        /// The column of the first cell.
+        column: Column<Any>,
+        /// The row of the first cell.
+        row: usize,
+        /// The column of the cell it is copied to.
+        other_column: Column<Any>,
+        /// The row of the cell it is copied to.
+        other_row: usize,
+    },
+}
+
+impl ConstraintFailure {
+    /// Returns the row the failure is reported on.
+    pub fn row(&self) -> usize {
+        match self {
+            ConstraintFailure::Gate { row, .. }
+            | ConstraintFailure::EvaluatorMismatch { row }
+            | ConstraintFailure::Lookup { row, .. }
+            | ConstraintFailure::Shuffle { row, .. }
+            | ConstraintFailure::Permutation { row, .. } => *row,
+        }
+    }
+}
+
+/// The result of [`ProvingKey::evaluate_constraints`].
+#[derive(Clone, Debug)]
+pub struct ConstraintEvaluationReport<F> {
+    /// For each usable row, the custom gates combined as the prover combines
+    /// them into the quotient polynomial, with a fixed scalar derived from
+    /// the verifying key standing in for the challenge `y`. It is zero on
+    /// every row if the gates are satisfied.
+    pub gate_values: Vec<F>,
+    /// The constraints that do not hold, ordered by row.
+    pub failures: Vec<ConstraintFailure>,
+}
+
+impl<F> ConstraintEvaluationReport<F> {
+    /// Returns whether every constraint holds.
+    pub fn is_satisfied(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Returns the failures on the first row that has any.
+    pub fn first_failures(&self) -> &[ConstraintFailure] {
+        let row = match self.failures.first() {
+            Some(failure) => failure.row(),
+            None => return &[],
+        };
+        let end = self
+            .failures
+            .iter()
+            .position(|failure| failure.row() != row)
+            .unwrap_or(self.failures.len());
+        &self.failures[..end]
+    }
+}
+
+impl<C: CurveAffine> ProvingKey<C>
+where
+    C::Scalar: Ord,
+{
+    /// Evaluates the constraints of the circuit over the usable rows for a
+    /// concrete witness, without creating a proof.
+    ///
+    /// `advice` holds the values of every advice column in column order,
+    /// `instance` those of every instance column, and `challenges` the
+    /// values of the circuit's challenges. Columns shorter than the domain
+    /// are zero-padded, and values past the usable rows are ignored since
+    /// the prover fills those rows with random values. The values can be
+    /// taken from a [`MockProver`](crate::dev::MockProver), or come from an
+    /// external witness generator.
+    ///
+    /// The custom gates are evaluated with the same compiled evaluator the
+    /// prover uses for the quotient polynomial, and each of them on its own
+    /// to attribute the failing rows; lookups, shuffles and copy constraints
+    /// are checked on the values directly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of advice or instance columns or of challenges
+    /// does not match the circuit, or if a column is longer than the domain.
+    pub fn evaluate_constraints(
+        &self,
+        advice: &[&[C::Scalar]],
+        instance: &[&[C::Scalar]],
+        challenges: &[C::Scalar],
+    ) -> ConstraintEvaluationReport<C::Scalar> {
+        let cs = &self.vk.cs;
+        let domain = &self.vk.domain;
+        let n = 1 << domain.k();
+        let usable_rows = cs.usable_rows(domain.k()).end;
+
+        assert_eq!(advice.len(), cs.num_advice_columns, "advice columns");
+        assert_eq!(instance.len(), cs.num_instance_columns, "instance columns");
+        assert_eq!(challenges.len(), cs.num_challenges, "challenges");
+        let to_poly = |values: &&[C::Scalar]| {
+            assert!(values.len() <= n, "a column is longer than the domain");
+            let mut values = values[..values.len().min(usable_rows)].to_vec();
+            values.resize(n, C::Scalar::ZERO);
+            domain.lagrange_from_vec(values)
+        };
+        let advice: Vec<_> = advice.iter().map(to_poly).collect();
+        let instance: Vec<_> = instance.iter().map(to_poly).collect();
+        let fixed = &self.fixed_values[..];
+
+        let value_at = |column: Column<Any>, row: usize| match column.column_type() {
+            Any::Advice(_) => advice[column.index()][row],
+            Any::Fixed => fixed[column.index()][row],
+            Any::Instance => instance[column.index()][row],
+        };
+        let evaluate = |expression: &Expression<C::Scalar>, row: usize| {
+            let at = |rotation: i32| (row as i32 + rotation).rem_euclid(n as i32) as usize;
+            expression.evaluate(
+                &|scalar| scalar,
+                &|_| panic!("virtual selectors are removed during optimization"),
+                &|query| fixed[query.column_index][at(query.rotation.0)],
+                &|query| advice[query.column_index][at(query.rotation.0)],
+                &|query| instance[query.column_index][at(query.rotation.0)],
+                &|challenge| challenges[challenge.index()],
+                &|a| -a,
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, scalar| a * scalar,
+            )
+        };
+
+        // The custom gates, as combined by the prover's evaluator
+        let y = self.vk.transcript_repr;
+        let ev = self.evaluator();
+        let mut gate_values = vec![C::Scalar::ZERO; usable_rows];
+        for custom_gates in ev.custom_gates.iter() {
+            parallelize(&mut gate_values, |values, start| {
+                let mut data = custom_gates.instance();
+                for (i, value) in values.iter_mut().enumerate() {
+                    *value = custom_gates.evaluate(
+                        &mut data,
+                        fixed,
+                        &advice,
+                        &instance,
+                        challenges,
+                        &C::Scalar::ZERO,
+                        &C::Scalar::ZERO,
+                        &C::Scalar::ZERO,
+                        &y,
+                        value,
+                        start + i,
+                        1,
+                        n as i32,
+                    );
+                }
+            });
+        }
+
+        // The custom gates one by one
+        let mut failures: Vec<ConstraintFailure> = (0..usable_rows)
+            .into_par_iter()
+            .map(|row| {
+                let mut failures: Vec<_> = cs
+                    .gates
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(gate_index, gate)| {
+                        gate.polynomials()
+                            .iter()
+                            .enumerate()
+                            .filter(move |(_, poly)| evaluate(*poly, row) != C::Scalar::ZERO)
+                            .map(move |(constraint, _)| ConstraintFailure::Gate {
+                                gate: gate_index,
+                                gate_name: gate.name().to_string(),
+                                constraint,
+                                constraint_name: gate.constraint_name(constraint).to_string(),
+                                row,
+                            })
+                    })
+                    .collect();
+                if failures.is_empty() != (gate_values[row] == C::Scalar::ZERO) {
+                    failures.push(ConstraintFailure::EvaluatorMismatch { row });
+                }
+                failures
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let tuples = |expressions: &[Expression<C::Scalar>]| {
+            (0..usable_rows)
+                .map(|row| {
+                    expressions
+                        .iter()
+                        .map(|expression| evaluate(expression, row))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Lookups
+        for (lookup_index, lookup) in cs.lookups.iter().enumerate() {
+            let table: BTreeSet<_> = tuples(&lookup.table_expressions).into_iter().collect();
+            for (row, input) in tuples(&lookup.input_expressions).iter().enumerate() {
+                if !table.contains(input) {
+                    failures.push(ConstraintFailure::Lookup {
+                        lookup: lookup_index,
+                        name: lookup.name.clone(),
+                        row,
+                    });
+                }
+            }
+        }
+
+        // Shuffles
+        for (shuffle_index, shuffle) in cs.shuffles.iter().enumerate() {
+            let mut remaining = BTreeMap::new();
+            for values in tuples(&shuffle.shuffle_expressions) {
+                *remaining.entry(values).or_insert(0usize) += 1;
+            }
+            for (row, input) in tuples(&shuffle.input_expressions).iter().enumerate() {
+                match remaining.get_mut(input) {
+                    Some(count) if *count > 0 => *count -= 1,
+                    _ => failures.push(ConstraintFailure::Shuffle {
+                        shuffle: shuffle_index,
+                        name: shuffle.name.clone(),
+                        row,
+                    }),
+                }
+            }
+        }
+
+        // Copy constraints: the permutation maps the cell of the `i`-th column
+        // on row `j` to the cell whose identity value is `delta^i omega^j`.
+        let columns = &cs.permutation.columns;
+        let rows: BTreeMap<C::Scalar, usize> =
+            std::iter::successors(Some(C::Scalar::ONE), |x| Some(*x * domain.get_omega()))
+                .take(n)
+                .enumerate()
+                .map(|(row, omega_row)| (omega_row, row))
+                .collect();
+        let delta_inv = C::Scalar::DELTA.invert().unwrap();
+        let delta_inv_powers: Vec<_> =
+            std::iter::successors(Some(C::Scalar::ONE), |x| Some(*x * delta_inv))
+                .take(columns.len())
+                .collect();
+        let cell_of = |value: C::Scalar, hint: usize| {
+            std::iter::once(hint)
+                .chain(0..columns.len())
+                .find_map(|i| rows.get(&(value * delta_inv_powers[i])).map(|&j| (i, j)))
+                .expect("permutation values are of the form delta^i omega^j")
+        };
+        for (i, (&column, sigma)) in columns
+            .iter()
+            .zip(self.permutation.permutations().iter())
+            .enumerate()
+        {
+            for row in 0..usable_rows {
+                let (other, other_row) = cell_of(sigma[row], i);
+                if other_row >= usable_rows || (other, other_row) == (i, row) {
+                    continue;
+                }
+                let other_column = columns[other];
+                if value_at(column, row) != value_at(other_column, other_row) {
+                    failures.push(ConstraintFailure::Permutation {
+                        column,
+                        row,
+                        other_column,
+                        other_row,
+                    });
+                }
+            }
+        }
+
+        failures.sort_by_key(ConstraintFailure::row);
+        ConstraintEvaluationReport {
+            gate_values,
+            failures,
+        }
+    }
+}
@@ -3,7 +3,9 @@ use std::fmt;
 use std::io;
 
 use super::TableColumn;
-use super::{Any, Column};
+use super::{
+    Any, Column, ColumnDrift, DegreeSource, Fixed, MinimumRowsBreakdown, ProofComponentKind,
+};
 
 /// This is an error that could occur during proving or circuit synthesis.
 // TODO: these errors need to be cleaned up
@@ -14,6 +16,9 @@ pub enum Error {
     Synthesis,
     /// The provided instances do not match the circuit parameters.
     InvalidInstances,
+    /// The instances passed to the prover do not have the shape the circuit
+    /// expects.
+    InstanceShape(InstanceMismatch),
     /// The constraint system is not satisfied.
     ConstraintSystemFailure,
     /// Out of bounds index passed to a backend
@@ -22,6 +27,9 @@ pub enum Error {
     Opening,
     /// Transcript error
     Transcript(io::Error),
+    /// The proof is followed by bytes that the verifier did not read; holds
+    /// their number.
+    TrailingBytes(usize),
     /// `k` is too small for the given circuit.
     NotEnoughRowsAvailable {
         /// The current value of `k` being used.
@@ -39,6 +47,87 @@ pub enum Error {
     ColumnNotInPermutation(Column<Any>),
     /// An error relating to a lookup table.
     TableError(TableError),
+    /// The circuit is not the one the provided verifying key was generated
+    /// for; holds the first difference found.
+    VerifyingKeyMismatch(VkMismatch),
+    /// A copy constraint refers to an instance cell beyond the expected length
+    /// of its instance column.
+    InstanceCopyOutOfBounds {
+        /// The region the copy constraint was added in, if any.
+        region: Option<String>,
+        /// The instance column.
+        column: Column<Any>,
+        /// The row of the instance cell.
+        row: usize,
+        /// The expected number of rows of the instance column.
+        length: usize,
+    },
+    /// An externally generated witness does not have one column per advice
+    /// column of the given phase.
+    InvalidWitness {
+        /// The index of the proof the witness was provided for.
+        circuit: usize,
+        /// The phase of the advice columns.
+        phase: u8,
+        /// The number of advice columns in that phase.
+        expected: usize,
+        /// The number of columns provided.
+        provided: usize,
+    },
+    /// Keygen was given a constraint system with disabled gates, without
+    /// [`ConstraintSystem::allow_disabled_gates`] having been called; holds
+    /// the names of the disabled gates.
+    ///
+    /// [`ConstraintSystem::allow_disabled_gates`]: crate::plonk::ConstraintSystem::allow_disabled_gates
+    DisabledGates(Vec<String>),
+    /// A value assigned to an advice column was computed from a challenge
+    /// squeezed after the phase of the column, which the prover does not
+    /// know yet when it commits to the column.
+    ChallengeFromLaterPhase {
+        /// The region the cell was assigned in, if known.
+        region: Option<String>,
+        /// The advice column.
+        column: Column<Any>,
+        /// The absolute row of the cell, if known.
+        row: Option<usize>,
+        /// The phase after which the challenge is squeezed.
+        challenge_phase: u8,
+    },
+    /// A cell was assigned in a fixed column whose values were given by
+    /// [`ConstraintSystem::fixed_column_with_values`].
+    ///
+    /// [`ConstraintSystem::fixed_column_with_values`]: crate::plonk::ConstraintSystem::fixed_column_with_values
+    PreassignedFixedColumn {
+        /// The fixed column.
+        column: Column<Fixed>,
+        /// The absolute row of the cell.
+        row: usize,
+    },
+    /// The circuit does not give its fixed data without being synthesized,
+    /// through [`Circuit::fixed_assignments`].
+    ///
+    /// [`Circuit::fixed_assignments`]: crate::plonk::Circuit::fixed_assignments
+    FixedAssignmentsUnavailable,
+    /// The extended domain set with [`ConstraintSystem::set_extended_k`] is
+    /// too small for the constraints of the circuit.
+    ///
+    /// [`ConstraintSystem::set_extended_k`]: crate::plonk::ConstraintSystem::set_extended_k
+    ExtendedDomainTooSmall {
+        /// The size of the extended domain, as the base-2 logarithm of its
+        /// number of rows.
+        extended_k: u32,
+        /// The smallest size the extended domain can have.
+        minimum: u32,
+        /// The highest degree of the constraints of the circuit.
+        degree: usize,
+        /// The first constraint of that degree.
+        constraint: DegreeSource,
+    },
+    /// Columns pinned with [`ConstraintSystem::pin_columns`] were not
+    /// allocated at their pinned indices; holds each of them.
+    ///
+    /// [`ConstraintSystem::pin_columns`]: crate::plonk::ConstraintSystem::pin_columns
+    ColumnDrift(Vec<ColumnDrift>),
 }
 
 impl From<io::Error> for Error {
@@ -53,6 +142,33 @@ impl Error {
     pub(crate) fn not_enough_rows_available(current_k: u32) -> Self {
         Error::NotEnoughRowsAvailable { current_k }
     }
+
+    /// Constructs an `Error::NotEnoughRowsAvailable` for a `k` below the
+    /// minimum number of rows of the circuit, logging what that minimum is
+    /// made of.
+    pub(crate) fn below_minimum_rows(current_k: u32, minimum_rows: MinimumRowsBreakdown) -> Self {
+        tracing::debug!("k = {} is too small: {}", current_k, minimum_rows);
+        Error::NotEnoughRowsAvailable { current_k }
+    }
+
+    /// Constructs an `Error::NotEnoughRowsAvailable` for an assignment at
+    /// `row` that fell outside of the usable rows, logging the region and
+    /// column being assigned.
+    pub(crate) fn not_enough_rows_available_at(
+        current_k: u32,
+        region: Option<&str>,
+        column: Option<Column<Any>>,
+        row: usize,
+    ) -> Self {
+        tracing::debug!(
+            "k = {} is too small: region {:?}, column {:?}, row {} is not usable",
+            current_k,
+            region,
+            column,
+            row
+        );
+        Error::NotEnoughRowsAvailable { current_k }
+    }
 }
 
 impl fmt::Display for Error {
@@ -60,10 +176,12 @@ impl fmt::Display for Error {
         match self {
             Error::Synthesis => write!(f, "General synthesis error"),
             Error::InvalidInstances => write!(f, "Provided instances do not match the circuit"),
+            Error::InstanceShape(mismatch) => write!(f, "Invalid instances: {}", mismatch),
             Error::ConstraintSystemFailure => write!(f, "The constraint system is not satisfied"),
             Error::BoundsFailure => write!(f, "An out-of-bounds index was passed to the backend"),
             Error::Opening => write!(f, "Multi-opening proof was invalid"),
             Error::Transcript(e) => write!(f, "Transcript error: {}", e),
+            Error::TrailingBytes(len) => write!(f, "The proof is followed by {} bytes", len),
             Error::NotEnoughRowsAvailable { current_k } => write!(
                 f,
                 "k = {} is too small for the given circuit. Try using a larger value of k",
@@ -78,10 +196,93 @@ impl fmt::Display for Error {
             }
             Error::ColumnNotInPermutation(column) => write!(
                 f,
-                "Column {:?} must be included in the permutation. Help: try applying `meta.enable_equalty` on the column",
+                "Column {:?} must be included in the permutation. Help: try applying `meta.enable_equality` on the column",
                 column
             ),
-            Error::TableError(error) => write!(f, "{}", error)
+            Error::TableError(error) => write!(f, "{}", error),
+            Error::VerifyingKeyMismatch(mismatch) => write!(
+                f,
+                "Circuit does not match the verifying key: {}",
+                mismatch
+            ),
+            Error::InstanceCopyOutOfBounds {
+                region,
+                column,
+                row,
+                length,
+            } => {
+                write!(
+                    f,
+                    "Copy constraint on {:?} at row {} exceeds its expected length of {}",
+                    column, row, length
+                )?;
+                if let Some(region) = region {
+                    write!(f, " (in region '{}')", region)?;
+                }
+                Ok(())
+            }
+            Error::InvalidWitness {
+                circuit,
+                phase,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "Witness {} provides {} columns for phase {}, but the circuit has {} advice columns in that phase",
+                circuit, provided, phase, expected
+            ),
+            Error::DisabledGates(gates) => write!(
+                f,
+                "Refusing to generate keys with disabled gates {:?}",
+                gates
+            ),
+            Error::ChallengeFromLaterPhase {
+                region,
+                column,
+                row,
+                challenge_phase,
+            } => {
+                write!(
+                    f,
+                    "Value assigned to {:?} is computed from a challenge squeezed after phase {}, which is not known when the column is committed to",
+                    column, challenge_phase
+                )?;
+                match (region, row) {
+                    (Some(region), Some(row)) => write!(f, " (region '{}', row {})", region, row),
+                    (Some(region), None) => write!(f, " (region '{}')", region),
+                    (None, Some(row)) => write!(f, " (row {})", row),
+                    (None, None) => Ok(()),
+                }
+            }
+            Error::PreassignedFixedColumn { column, row } => write!(
+                f,
+                "Cannot assign row {} of {:?}, whose values are given by ConstraintSystem::fixed_column_with_values",
+                row, column
+            ),
+            Error::FixedAssignmentsUnavailable => write!(
+                f,
+                "The circuit does not implement Circuit::fixed_assignments, so keys can only be generated from an instance of it"
+            ),
+            Error::ExtendedDomainTooSmall {
+                extended_k,
+                minimum,
+                degree,
+                constraint,
+            } => write!(
+                f,
+                "An extended domain of 2^{} rows is too small: {} has degree {}, which needs at least 2^{} rows",
+                extended_k, constraint, degree, minimum
+            ),
+            Error::ColumnDrift(drift) => {
+                write!(f, "Columns were not allocated at their pinned indices: ")?;
+                for (i, drift) in drift.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", drift)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -95,34 +296,137 @@ impl error::Error for Error {
     }
 }
 
+/// Why [`verify_proof_detailed`](crate::plonk::verify_proof_detailed)
+/// rejected a proof.
+#[derive(Debug)]
+pub enum VerificationFailure {
+    /// The proof cannot be checked against the given inputs, for example
+    /// because the instances do not match the verifying key.
+    Input(Error),
+    /// A point or scalar of the proof could not be read, because the proof
+    /// ends early or holds an invalid encoding.
+    Read {
+        /// The component the point or scalar belongs to.
+        component: ProofComponentKind,
+        /// The circuit the component belongs to; 0 for the components shared
+        /// by all circuits.
+        index: usize,
+        /// The position of the point or scalar within the component.
+        position: usize,
+        /// The error returned by the transcript.
+        error: io::Error,
+    },
+    /// The final check of the multiopen argument failed: an evaluation does
+    /// not match its commitment, or the evaluations do not satisfy the
+    /// constraints of the circuit.
+    Opening,
+}
+
+impl From<VerificationFailure> for Error {
+    fn from(failure: VerificationFailure) -> Self {
+        match failure {
+            VerificationFailure::Input(error) => error,
+            VerificationFailure::Read { error, .. } => Error::Transcript(error),
+            VerificationFailure::Opening => Error::Opening,
+        }
+    }
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationFailure::Input(error) => write!(f, "{}", error),
+            VerificationFailure::Read {
+                component,
+                index,
+                position,
+                error,
+            } => write!(
+                f,
+                "Could not read item {} of {:?} for circuit {}: {}",
+                position, component, index, error
+            ),
+            VerificationFailure::Opening => write!(
+                f,
+                "The evaluations do not match the commitments or do not satisfy the circuit"
+            ),
+        }
+    }
+}
+
+impl error::Error for VerificationFailure {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            VerificationFailure::Input(error) => Some(error),
+            VerificationFailure::Read { error, .. } => Some(error),
+            VerificationFailure::Opening => None,
+        }
+    }
+}
+
 /// This is an error that could occur during table synthesis.
 #[derive(Debug)]
 pub enum TableError {
-    /// A `TableColumn` has not been assigned.
-    ColumnNotAssigned(TableColumn),
+    /// Some rows of a `TableColumn` were left unassigned.
+    ColumnNotAssigned {
+        /// The name of the table.
+        table: String,
+        /// The column with unassigned rows.
+        column: TableColumn,
+        /// The unassigned rows, below the last row assigned in the column.
+        missing_rows: Vec<usize>,
+    },
     /// A Table has columns of uneven lengths.
-    UnevenColumnLengths((TableColumn, usize), (TableColumn, usize)),
+    UnevenColumnLengths {
+        /// The name of the table.
+        table: String,
+        /// The length of every column in the table, ordered by column.
+        lengths: Vec<(TableColumn, usize)>,
+    },
     /// Attempt to assign a used `TableColumn`
     UsedColumn(TableColumn),
     /// Attempt to overwrite a default value
     OverwriteDefault(TableColumn, String, String),
+    /// A row of a logical table of a `TableSet` does not have one value per
+    /// column of the table.
+    RowWidth {
+        /// The name of the logical table.
+        table: String,
+        /// The index of the row in the logical table.
+        row: usize,
+        /// The number of values in the row.
+        width: usize,
+        /// The number of columns of the logical table.
+        columns: usize,
+    },
 }
 
 impl fmt::Display for TableError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TableError::ColumnNotAssigned(col) => {
+            TableError::ColumnNotAssigned {
+                table,
+                column,
+                missing_rows,
+            } => {
                 write!(
                     f,
-                    "{:?} not fully assigned. Help: assign a value at offset 0.",
-                    col
-                )
+                    "{:?} in table '{}' not fully assigned: rows {:?} are missing.",
+                    column, table, missing_rows
+                )?;
+                if missing_rows.first() == Some(&0) {
+                    write!(f, " Help: assign a value at offset 0.")?;
+                }
+                Ok(())
+            }
+            TableError::UnevenColumnLengths { table, lengths } => {
+                let longest = lengths.iter().map(|(_, len)| *len).max().unwrap_or(0);
+                write!(f, "Table '{}' has columns of uneven lengths:", table)?;
+                for (column, len) in lengths.iter().filter(|(_, len)| *len < longest) {
+                    write!(f, " {:?} has length {},", column, len)?;
+                }
+                write!(f, " shorter than the longest column length {}", longest)
             }
-            TableError::UnevenColumnLengths((col, col_len), (table, table_len)) => write!(
-                f,
-                "{:?} has length {} while {:?} has length {}",
-                col, col_len, table, table_len
-            ),
             TableError::UsedColumn(col) => {
                 write!(f, "{:?} has already been used", col)
             }
@@ -133,6 +437,141 @@ impl fmt::Display for TableError {
                     default, val, col
                 )
             }
+            TableError::RowWidth {
+                table,
+                row,
+                width,
+                columns,
+            } => {
+                write!(
+                    f,
+                    "Row {} of table '{}' has {} values for {} columns",
+                    row, table, width, columns
+                )
+            }
+        }
+    }
+}
+
+/// Describes how the instances passed to the prover differ from what the
+/// circuit expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstanceMismatch {
+    /// There is not one set of instance columns per proof.
+    ProofCount {
+        /// The number of proofs being created.
+        expected: usize,
+        /// The number of sets of instance columns.
+        provided: usize,
+    },
+    /// A proof is given the wrong number of instance columns.
+    ColumnCount {
+        /// The index of the proof.
+        circuit: usize,
+        /// The number of instance columns of the circuit.
+        expected: usize,
+        /// The number of instance columns provided.
+        provided: usize,
+    },
+    /// An instance column has more values than there are usable rows.
+    ColumnTooLong {
+        /// The index of the proof.
+        circuit: usize,
+        /// The index of the instance column.
+        column: usize,
+        /// The number of usable rows.
+        usable_rows: usize,
+        /// The number of values provided.
+        provided: usize,
+    },
+}
+
+impl fmt::Display for InstanceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceMismatch::ProofCount { expected, provided } => write!(
+                f,
+                "expected instances for {} circuit instances, got {}",
+                expected, provided
+            ),
+            InstanceMismatch::ColumnCount {
+                circuit,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "expected {} instance columns, got {} for circuit instance {}",
+                expected, provided, circuit
+            ),
+            InstanceMismatch::ColumnTooLong {
+                circuit,
+                column,
+                usable_rows,
+                provided,
+            } => write!(
+                f,
+                "instance column {} of circuit instance {} has {} values, but only {} rows are usable",
+                column, circuit, provided, usable_rows
+            ),
+        }
+    }
+}
+
+/// Describes where a circuit diverges from the verifying key it was checked
+/// against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VkMismatch {
+    /// The verifying key was generated for a different number of rows.
+    K {
+        /// The `k` of the verifying key.
+        vk: u32,
+        /// The `k` of the parameters passed to keygen.
+        circuit: u32,
+    },
+    /// The circuit configures a different number of columns of a given type.
+    ColumnCount {
+        /// The type of the columns, e.g. `"advice"`.
+        column_type: &'static str,
+        /// The number of columns in the verifying key.
+        vk: usize,
+        /// The number of columns configured by the circuit.
+        circuit: usize,
+    },
+    /// The gate at this index has different constraints.
+    Gate(usize),
+    /// The circuit configures a different constraint system, in a way not
+    /// covered by the other variants.
+    ConstraintSystem,
+    /// The fixed column at this index commits to different values.
+    FixedCommitment(usize),
+    /// The column at this index among those taking part in the permutation
+    /// argument has different copy constraints.
+    Permutation(usize),
+}
+
+impl fmt::Display for VkMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VkMismatch::K { vk, circuit } => write!(f, "k is {} in the vk but {}", vk, circuit),
+            VkMismatch::ColumnCount {
+                column_type,
+                vk,
+                circuit,
+            } => write!(
+                f,
+                "number of {} columns is {} in the vk but {}",
+                column_type, vk, circuit
+            ),
+            VkMismatch::Gate(gate) => write!(f, "gate {} differs", gate),
+            VkMismatch::ConstraintSystem => write!(f, "the constraint systems differ"),
+            VkMismatch::FixedCommitment(column) => {
+                write!(f, "the commitment to fixed column {} differs", column)
+            }
+            VkMismatch::Permutation(column) => write!(
+                f,
+                "the copy constraints of permutation column {} differ",
+                column
+            ),
         }
     }
 }
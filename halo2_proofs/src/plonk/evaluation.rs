@@ -1,13 +1,136 @@
 use crate::multicore;
-use crate::plonk::{lookup, permutation, Any, ProvingKey};
+use crate::plonk::{lookup, permutation, Any, ChunkSize, GateTiming, ProvingKey};
 use crate::poly::Basis;
 use crate::{
-    arithmetic::{parallelize, CurveAffine},
-    poly::{Coeff, ExtendedLagrangeCoeff, Polynomial, Rotation},
+    arithmetic::{parallelize, CurveAffine, Engine},
+    poly::{Coeff, ExtendedLagrangeCoeff, Polynomial, Rotation, ScratchPool},
 };
 use group::ff::{Field, PrimeField, WithSmallOrderMulGroup};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
-use super::{shuffle, ConstraintSystem, Expression};
+use super::{shuffle, ConstraintSystem, Expression, Gate};
+
+/// Under [`ChunkSize::Auto`], the part of each thread's share of the rows
+/// that is evaluated on its own to measure the cost of a row.
+const AUTO_PROBE_FRACTION: usize = 16;
+
+/// Under [`ChunkSize::Auto`], how long each task should take, long enough for
+/// the cost of spawning it to be negligible.
+const AUTO_TASK_DURATION: Duration = Duration::from_micros(500);
+
+/// The largest total cost of the gates evaluated together in one pass over
+/// the rows; see [`Gate::cost`]. A gate costing more is evaluated on its own.
+const GATE_GROUP_COST: usize = 256;
+
+/// The largest factor by which the rows of a costly gate are split into more
+/// tasks than those of a group costing [`GATE_GROUP_COST`].
+const MAX_GATE_SPLIT: usize = 16;
+
+/// Consecutive gates evaluated together in one pass over the rows of the
+/// extended domain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct GateGroup {
+    /// The indices of the gates in the constraint system.
+    gates: Range<usize>,
+    /// The sum of the costs of the gates.
+    cost: usize,
+}
+
+impl GateGroup {
+    /// Groups consecutive `gates` costing at most [`GATE_GROUP_COST`] in
+    /// total, each gate costing more making up a group of its own.
+    fn split<F: Field>(gates: &[Gate<F>]) -> Vec<Self> {
+        let mut groups: Vec<Self> = vec![];
+        for (index, gate) in gates.iter().enumerate() {
+            let cost = gate.cost();
+            match groups.last_mut() {
+                Some(group) if group.cost + cost <= GATE_GROUP_COST => {
+                    group.gates.end = index + 1;
+                    group.cost += cost;
+                }
+                _ => groups.push(GateGroup {
+                    gates: index..index + 1,
+                    cost,
+                }),
+            }
+        }
+        groups
+    }
+
+    /// Returns how to split `rows` rows into tasks for this group, if they
+    /// are split as `chunk_size` for a group costing at most
+    /// [`GATE_GROUP_COST`]. The rows of a costlier gate are split into
+    /// proportionally more tasks, so that the threads done with theirs can
+    /// take over the remaining ones.
+    fn chunk_size(&self, chunk_size: ChunkSize, rows: usize) -> ChunkSize {
+        let split = ((self.cost + GATE_GROUP_COST - 1) / GATE_GROUP_COST).clamp(1, MAX_GATE_SPLIT);
+        match chunk_size {
+            _ if split == 1 => chunk_size,
+            ChunkSize::PerThread => {
+                let tasks = multicore::current_num_threads() * split;
+                ChunkSize::Rows((rows + tasks - 1) / tasks)
+            }
+            ChunkSize::Rows(task_rows) => ChunkSize::Rows((task_rows / split).max(1)),
+            // The rows are timed for each group anyway.
+            ChunkSize::Auto => ChunkSize::Auto,
+        }
+    }
+}
+
+/// Calls `f` on chunks of `values` in parallel, along with the index of the
+/// first value of the chunk, splitting `values` as given by `chunk_size`.
+fn parallelize_chunked<T: Send, F: Fn(&mut [T], usize) + Send + Sync + Clone>(
+    values: &mut [T],
+    chunk_size: ChunkSize,
+    f: F,
+) {
+    let num_threads = multicore::current_num_threads();
+    match chunk_size {
+        ChunkSize::PerThread => parallelize(values, f),
+        ChunkSize::Rows(rows) => spawn_chunks(values, 0, rows, &f),
+        ChunkSize::Auto => {
+            let probe = (values.len() / (num_threads * AUTO_PROBE_FRACTION)).max(1);
+            let (first, rest) = values.split_at_mut(probe.min(values.len()));
+            let start = Instant::now();
+            f(first, 0);
+            let rows = auto_chunk_rows(start.elapsed(), first.len(), rest.len(), num_threads);
+            spawn_chunks(rest, first.len(), rows, &f)
+        }
+    }
+}
+
+/// Spawns a task calling `f` for each chunk of `rows` values, the values
+/// starting at index `offset`.
+fn spawn_chunks<T: Send, F: Fn(&mut [T], usize) + Send + Sync>(
+    values: &mut [T],
+    offset: usize,
+    rows: usize,
+    f: &F,
+) {
+    let rows = rows.max(1);
+    multicore::scope(|scope| {
+        for (chunk_idx, chunk) in values.chunks_mut(rows).enumerate() {
+            scope.spawn(move |_| f(chunk, offset + chunk_idx * rows));
+        }
+    });
+}
+
+/// Returns how many rows a task should evaluate so that it takes about
+/// [`AUTO_TASK_DURATION`], given that `probe_rows` rows took `elapsed`,
+/// without leaving any of the `num_threads` threads idle.
+fn auto_chunk_rows(
+    elapsed: Duration,
+    probe_rows: usize,
+    remaining_rows: usize,
+    num_threads: usize,
+) -> usize {
+    let nanos_per_row = (elapsed.as_nanos() / probe_rows.max(1) as u128).max(1);
+    let rows = (AUTO_TASK_DURATION.as_nanos() / nanos_per_row) as usize;
+    let rows_per_thread = (remaining_rows + num_threads - 1) / num_threads;
+    rows.clamp(1, rows_per_thread.max(1))
+}
 
 /// Return the index in the polynomial of size `isize` after rotation `rot`.
 fn get_rotation_idx(idx: usize, rot: i32, rot_scale: i32, isize: i32) -> usize {
@@ -163,14 +286,68 @@ impl Calculation {
 }
 
 /// Evaluator
-#[derive(Clone, Default, Debug)]
-pub struct Evaluator<C: CurveAffine> {
-    ///  Custom gates evalution
-    pub custom_gates: GraphEvaluator<C>,
+#[derive(Clone, Default)]
+pub struct Evaluator<C: CurveAffine, G = GraphEvaluator<C>> {
+    ///  Custom gates evalution, one for each group of gates evaluated in a
+    ///  pass over the rows, each folding its gates into the previous value
+    pub custom_gates: Vec<G>,
     ///  Lookups evalution
-    pub lookups: Vec<GraphEvaluator<C>>,
+    pub lookups: Vec<G>,
+    ///  LogUp lookups evalution
+    pub logups: Vec<G>,
     ///  Shuffle evalution
-    pub shuffles: Vec<GraphEvaluator<C>>,
+    pub shuffles: Vec<G>,
+    /// The names of the lookups, in the order of `lookups` or of the pairs of
+    /// `logups`, whichever the backend of the circuit uses.
+    lookup_names: Vec<String>,
+    /// The gates evaluated by each of `custom_gates`.
+    gate_groups: Vec<GateGroup>,
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine, G: std::fmt::Debug> std::fmt::Debug for Evaluator<C, G> {
+    /// Shows the evaluators of each group of gates along with the gates it
+    /// evaluates, and of each lookup along with its name.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let custom_gates = self.gate_groups.iter().zip(self.custom_gates.iter());
+        let lookups = self.lookup_names.iter().zip(self.lookups.iter());
+        let logups = self.lookup_names.iter().zip(self.logups.chunks(2));
+        f.debug_struct("Evaluator")
+            .field("custom_gates", &custom_gates.collect::<Vec<_>>())
+            .field("lookups", &lookups.collect::<Vec<_>>())
+            .field("logups", &logups.collect::<Vec<_>>())
+            .field("shuffles", &self.shuffles)
+            .finish()
+    }
+}
+
+/// Evaluates an expression of the constraint system, or a combination of
+/// them, at a row of the extended domain.
+pub trait RowEvaluator<C: CurveAffine>: Sync {
+    /// The scratch space of a thread evaluating rows.
+    type Data;
+
+    /// Creates the scratch space of a thread.
+    fn instance(&self) -> Self::Data;
+
+    /// Evaluates the expression at row `idx`.
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate<B: Basis>(
+        &self,
+        data: &mut Self::Data,
+        fixed: &[Polynomial<C::ScalarExt, B>],
+        advice: &[Polynomial<C::ScalarExt, B>],
+        instance: &[Polynomial<C::ScalarExt, B>],
+        challenges: &[C::ScalarExt],
+        beta: &C::ScalarExt,
+        gamma: &C::ScalarExt,
+        theta: &C::ScalarExt,
+        y: &C::ScalarExt,
+        previous_value: &C::ScalarExt,
+        idx: usize,
+        rot_scale: i32,
+        isize: i32,
+    ) -> C::ScalarExt;
 }
 
 /// GraphEvaluator
@@ -207,25 +384,36 @@ pub struct CalculationInfo {
 impl<C: CurveAffine> Evaluator<C> {
     /// Creates a new evaluation structure
     pub fn new(cs: &ConstraintSystem<C::ScalarExt>) -> Self {
-        let mut ev = Evaluator::default();
+        let mut ev = Self {
+            lookup_names: cs
+                .lookups
+                .iter()
+                .map(|lookup| lookup.name.clone())
+                .collect(),
+            gate_groups: GateGroup::split(&cs.gates),
+            ..Self::default()
+        };
 
         // Custom gates
-        let mut parts = Vec::new();
-        for gate in cs.gates.iter() {
-            parts.extend(
-                gate.polynomials()
-                    .iter()
-                    .map(|poly| ev.custom_gates.add_expression(poly)),
-            );
+        for group in ev.gate_groups.iter() {
+            let mut graph = GraphEvaluator::default();
+            let parts = cs.gates[group.gates.clone()]
+                .iter()
+                .flat_map(|gate| gate.polynomials().iter())
+                .map(|poly| graph.add_expression(poly))
+                .collect();
+            graph.add_calculation(Calculation::Horner(
+                ValueSource::PreviousValue(),
+                parts,
+                ValueSource::Y(),
+            ));
+            ev.custom_gates.push(graph);
         }
-        ev.custom_gates.add_calculation(Calculation::Horner(
-            ValueSource::PreviousValue(),
-            parts,
-            ValueSource::Y(),
-        ));
+
+        let (lookups, logups) = cs.lookups_by_backend();
 
         // Lookups
-        for lookup in cs.lookups.iter() {
+        for lookup in lookups.iter() {
             let mut graph = GraphEvaluator::default();
 
             let mut evaluate_lc = |expressions: &Vec<Expression<_>>| {
@@ -258,6 +446,40 @@ impl<C: CurveAffine> Evaluator<C> {
             ev.lookups.push(graph);
         }
 
+        // LogUp lookups
+        for logup in logups.iter() {
+            let evaluate_lc = |expressions: &Vec<Expression<_>>, graph: &mut GraphEvaluator<C>| {
+                let parts = expressions
+                    .iter()
+                    .map(|expr| graph.add_expression(expr))
+                    .collect();
+                graph.add_calculation(Calculation::Horner(
+                    ValueSource::Constant(0),
+                    parts,
+                    ValueSource::Theta(),
+                ))
+            };
+
+            // a(X) + \beta
+            let mut graph_input = GraphEvaluator::default();
+            let compressed_input_coset = evaluate_lc(&logup.input_expressions, &mut graph_input);
+            let _ = graph_input.add_calculation(Calculation::Add(
+                compressed_input_coset,
+                ValueSource::Beta(),
+            ));
+
+            // t(X) + \beta
+            let mut graph_table = GraphEvaluator::default();
+            let compressed_table_coset = evaluate_lc(&logup.table_expressions, &mut graph_table);
+            let _ = graph_table.add_calculation(Calculation::Add(
+                compressed_table_coset,
+                ValueSource::Beta(),
+            ));
+
+            ev.logups.push(graph_input);
+            ev.logups.push(graph_table);
+        }
+
         // Shuffles
         for shuffle in cs.shuffles.iter() {
             let evaluate_lc = |expressions: &Vec<Expression<_>>, graph: &mut GraphEvaluator<C>| {
@@ -293,12 +515,101 @@ impl<C: CurveAffine> Evaluator<C> {
 
         ev
     }
+}
+
+impl<'a, C: CurveAffine> Evaluator<C, ExpressionEvaluator<'a, C>> {
+    /// Creates an evaluator that walks the expressions of `cs` at each row,
+    /// combining them as [`Evaluator::new`] does.
+    pub fn interpreted(cs: &'a ConstraintSystem<C::ScalarExt>) -> Self {
+        let (lookups, logups) = cs.lookups_by_backend();
+        let compressed = |expressions: &'a [Expression<C::ScalarExt>], blind| {
+            ExpressionEvaluator::Compressed(expressions, blind)
+        };
+        let gate_groups = GateGroup::split(&cs.gates);
+
+        Evaluator {
+            custom_gates: gate_groups
+                .iter()
+                .map(|group| {
+                    ExpressionEvaluator::Gates(
+                        cs.gates[group.gates.clone()]
+                            .iter()
+                            .flat_map(|gate| gate.polynomials().iter())
+                            .collect(),
+                    )
+                })
+                .collect(),
+            // (a'(X) + \beta) (s'(X) + \gamma)
+            lookups: lookups
+                .iter()
+                .map(|lookup| {
+                    ExpressionEvaluator::Product(
+                        Box::new(compressed(&lookup.input_expressions, ValueSource::Beta())),
+                        Box::new(compressed(&lookup.table_expressions, ValueSource::Gamma())),
+                    )
+                })
+                .collect(),
+            // a(X) + \beta and t(X) + \beta
+            logups: logups
+                .iter()
+                .flat_map(|logup| {
+                    [
+                        compressed(&logup.input_expressions, ValueSource::Beta()),
+                        compressed(&logup.table_expressions, ValueSource::Beta()),
+                    ]
+                })
+                .collect(),
+            // a(X) + \gamma and s(X) + \gamma
+            shuffles: cs
+                .shuffles
+                .iter()
+                .flat_map(|shuffle| {
+                    [
+                        compressed(&shuffle.input_expressions, ValueSource::Gamma()),
+                        compressed(&shuffle.shuffle_expressions, ValueSource::Gamma()),
+                    ]
+                })
+                .collect(),
+            lookup_names: cs
+                .lookups
+                .iter()
+                .map(|lookup| lookup.name.clone())
+                .collect(),
+            gate_groups,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C: CurveAffine, G> Evaluator<C, G> {
+    /// Returns the groups of gates of `cs`, which this evaluator was created
+    /// from, that are evaluated together, with no time spent on them yet.
+    pub(in crate::plonk) fn gate_timings(
+        &self,
+        cs: &ConstraintSystem<C::ScalarExt>,
+    ) -> Vec<GateTiming> {
+        self.gate_groups
+            .iter()
+            .map(|group| GateTiming {
+                gates: cs.gates[group.gates.clone()]
+                    .iter()
+                    .map(|gate| gate.name().to_string())
+                    .collect(),
+                cost: group.cost,
+                duration: Duration::ZERO,
+            })
+            .collect()
+    }
+}
 
-    /// Evaluate h poly
+impl<C: CurveAffine, G: RowEvaluator<C>> Evaluator<C, G> {
+    /// Evaluate h poly, adding the time spent on each group of gates to
+    /// `gate_timings` if given, as returned by [`Self::gate_timings`]
     #[allow(clippy::too_many_arguments)]
     pub(in crate::plonk) fn evaluate_h(
         &self,
         pk: &ProvingKey<C>,
+        engine: &dyn Engine<C>,
         advice_polys: &[&[Polynomial<C::ScalarExt, Coeff>]],
         instance_polys: &[&[Polynomial<C::ScalarExt, Coeff>]],
         challenges: &[C::ScalarExt],
@@ -307,80 +618,86 @@ impl<C: CurveAffine> Evaluator<C> {
         gamma: C::ScalarExt,
         theta: C::ScalarExt,
         lookups: &[Vec<lookup::prover::Committed<C>>],
+        logups: &[Vec<lookup::logup::prover::Committed<C>>],
         shuffles: &[Vec<shuffle::prover::Committed<C>>],
         permutations: &[permutation::prover::Committed<C>],
+        gate_chunks: ChunkSize,
+        argument_chunks: ChunkSize,
+        scratch: &ScratchPool<C::ScalarExt>,
+        mut gate_timings: Option<&mut [GateTiming]>,
     ) -> Polynomial<C::ScalarExt, ExtendedLagrangeCoeff> {
         let domain = &pk.vk.domain;
         let size = domain.extended_len();
         let rot_scale = 1 << (domain.extended_k() - domain.k());
-        let fixed = &pk.fixed_cosets[..];
+        // Recomputed for this proof if the key doesn't keep them.
+        let cosets = pk.cosets_with_engine(engine);
+        let fixed = &cosets.fixed[..];
         let extended_omega = domain.get_extended_omega();
         let isize = size as i32;
         let one = C::ScalarExt::ONE;
-        let l0 = &pk.l0;
-        let l_last = &pk.l_last;
-        let l_active_row = &pk.l_active_row;
+        let l0 = &cosets.l0;
+        let l_last = &cosets.l_last;
+        let l_active_row = &cosets.l_active_row;
         let p = &pk.vk.cs.permutation;
 
-        // Calculate the advice and instance cosets
-        let advice: Vec<Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>> = advice_polys
-            .iter()
-            .map(|advice_polys| {
-                advice_polys
-                    .iter()
-                    .map(|poly| domain.coeff_to_extended(poly.clone()))
-                    .collect()
-            })
-            .collect();
-        let instance: Vec<Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>> = instance_polys
-            .iter()
-            .map(|instance_polys| {
-                instance_polys
-                    .iter()
-                    .map(|poly| domain.coeff_to_extended(poly.clone()))
-                    .collect()
-            })
-            .collect();
+        // Every coset below is written into a buffer of the scratch pool, and
+        // given back as soon as it is no longer needed, so that all circuits
+        // and arguments share the same few buffers.
+        let to_coset = |poly: &Polynomial<C::ScalarExt, Coeff>| {
+            domain.coeff_to_extended_in(engine, poly, scratch)
+        };
 
         let mut values = domain.empty_extended();
 
         // Core expression evaluations
-        let num_threads = multicore::current_num_threads();
-        for ((((advice, instance), lookups), shuffles), permutation) in advice
-            .iter()
-            .zip(instance.iter())
-            .zip(lookups.iter())
-            .zip(shuffles.iter())
-            .zip(permutations.iter())
+        for (((((advice_polys, instance_polys), lookups), logups), shuffles), permutation) in
+            advice_polys
+                .iter()
+                .zip(instance_polys.iter())
+                .zip(lookups.iter())
+                .zip(logups.iter())
+                .zip(shuffles.iter())
+                .zip(permutations.iter())
         {
-            // Custom gates
-            multicore::scope(|scope| {
-                let chunk_size = (size + num_threads - 1) / num_threads;
-                for (thread_idx, values) in values.chunks_mut(chunk_size).enumerate() {
-                    let start = thread_idx * chunk_size;
-                    scope.spawn(move |_| {
-                        let mut eval_data = self.custom_gates.instance();
-                        for (i, value) in values.iter_mut().enumerate() {
-                            let idx = start + i;
-                            *value = self.custom_gates.evaluate(
-                                &mut eval_data,
-                                fixed,
-                                advice,
-                                instance,
-                                challenges,
-                                &beta,
-                                &gamma,
-                                &theta,
-                                &y,
-                                value,
-                                idx,
-                                rot_scale,
-                                isize,
-                            );
-                        }
-                    });
+            // Calculate the advice and instance cosets of this circuit
+            let advice_cosets: Vec<_> = advice_polys.iter().map(to_coset).collect();
+            let instance_cosets: Vec<_> = instance_polys.iter().map(to_coset).collect();
+            let (advice, instance) = (&advice_cosets[..], &instance_cosets[..]);
+
+            // Custom gates, a group at a time
+            for (index, (group, custom_gates)) in self
+                .gate_groups
+                .iter()
+                .zip(self.custom_gates.iter())
+                .enumerate()
+            {
+                let group_start = gate_timings.as_ref().map(|_| Instant::now());
+                let chunk_size = group.chunk_size(gate_chunks, size);
+                parallelize_chunked(&mut values, chunk_size, |values, start| {
+                    let mut eval_data = custom_gates.instance();
+                    for (i, value) in values.iter_mut().enumerate() {
+                        let idx = start + i;
+                        *value = custom_gates.evaluate(
+                            &mut eval_data,
+                            fixed,
+                            advice,
+                            instance,
+                            challenges,
+                            &beta,
+                            &gamma,
+                            &theta,
+                            &y,
+                            value,
+                            idx,
+                            rot_scale,
+                            isize,
+                        );
+                    }
+                });
+                if let (Some(timings), Some(group_start)) = (gate_timings.as_mut(), group_start) {
+                    timings[index].duration += group_start.elapsed();
                 }
-            });
+            }
 
             // Permutations
             let sets = &permutation.sets;
@@ -394,7 +711,7 @@ impl<C: CurveAffine> Evaluator<C> {
                 let last_set = sets.last().unwrap();
 
                 // Permutation constraints
-                parallelize(&mut values, |values, start| {
+                parallelize_chunked(&mut values, argument_chunks, |values, start| {
                     let mut beta_term = extended_omega.pow_vartime([start as u64, 0, 0, 0]);
                     for (i, value) in values.iter_mut().enumerate() {
                         let idx = start + i;
@@ -432,7 +749,7 @@ impl<C: CurveAffine> Evaluator<C> {
                         for ((set, columns), cosets) in sets
                             .iter()
                             .zip(p.columns.chunks(chunk_len))
-                            .zip(pk.permutation.cosets.chunks(chunk_len))
+                            .zip(cosets.permutation.chunks(chunk_len))
                         {
                             let mut left = set.permutation_product_coset[r_next];
                             for (values, permutation) in columns
@@ -469,18 +786,12 @@ impl<C: CurveAffine> Evaluator<C> {
                 // Polynomials required for this lookup.
                 // Calculated here so these only have to be kept in memory for the short time
                 // they are actually needed.
-                let product_coset = pk.vk.domain.coeff_to_extended(lookup.product_poly.clone());
-                let permuted_input_coset = pk
-                    .vk
-                    .domain
-                    .coeff_to_extended(lookup.permuted_input_poly.clone());
-                let permuted_table_coset = pk
-                    .vk
-                    .domain
-                    .coeff_to_extended(lookup.permuted_table_poly.clone());
+                let product_coset = to_coset(&lookup.product_poly);
+                let permuted_input_coset = to_coset(&lookup.permuted_input_poly);
+                let permuted_table_coset = to_coset(&lookup.permuted_table_poly);
 
                 // Lookup constraints
-                parallelize(&mut values, |values, start| {
+                parallelize_chunked(&mut values, argument_chunks, |values, start| {
                     let lookup_evaluator = &self.lookups[n];
                     let mut eval_data = lookup_evaluator.instance();
                     for (i, value) in values.iter_mut().enumerate() {
@@ -537,14 +848,85 @@ impl<C: CurveAffine> Evaluator<C> {
                                 * l_active_row[idx]);
                     }
                 });
+                scratch.give_poly(product_coset);
+                scratch.give_poly(permuted_input_coset);
+                scratch.give_poly(permuted_table_coset);
+            }
+
+            // LogUp constraints
+            for (n, logup) in logups.iter().enumerate() {
+                let running_sum_coset = to_coset(&logup.running_sum_poly);
+                let multiplicity_coset = to_coset(&logup.multiplicity_poly);
+
+                parallelize_chunked(&mut values, argument_chunks, |values, start| {
+                    let input_evaluator = &self.logups[2 * n];
+                    let table_evaluator = &self.logups[2 * n + 1];
+                    let mut eval_data_input = input_evaluator.instance();
+                    let mut eval_data_table = table_evaluator.instance();
+                    for (i, value) in values.iter_mut().enumerate() {
+                        let idx = start + i;
+
+                        let input_value = input_evaluator.evaluate(
+                            &mut eval_data_input,
+                            fixed,
+                            advice,
+                            instance,
+                            challenges,
+                            &beta,
+                            &gamma,
+                            &theta,
+                            &y,
+                            &C::ScalarExt::ZERO,
+                            idx,
+                            rot_scale,
+                            isize,
+                        );
+
+                        let table_value = table_evaluator.evaluate(
+                            &mut eval_data_table,
+                            fixed,
+                            advice,
+                            instance,
+                            challenges,
+                            &beta,
+                            &gamma,
+                            &theta,
+                            &y,
+                            &C::ScalarExt::ZERO,
+                            idx,
+                            rot_scale,
+                            isize,
+                        );
+
+                        let r_next = get_rotation_idx(idx, 1, rot_scale, isize);
+
+                        // l_0(X) * \phi(X) = 0
+                        *value = *value * y + running_sum_coset[idx] * l0[idx];
+                        // l_last(X) * \phi(X) = 0
+                        *value = *value * y + running_sum_coset[idx] * l_last[idx];
+                        // (1 - (l_last(X) + l_blind(X))) * (
+                        //   (\phi(\omega X) - \phi(X)) (a(X) + \beta) (t(X) + \beta)
+                        //   - (t(X) + \beta) + m(X) (a(X) + \beta)
+                        // ) = 0
+                        *value = *value * y
+                            + l_active_row[idx]
+                                * ((running_sum_coset[r_next] - running_sum_coset[idx])
+                                    * input_value
+                                    * table_value
+                                    - table_value
+                                    + multiplicity_coset[idx] * input_value);
+                    }
+                });
+                scratch.give_poly(running_sum_coset);
+                scratch.give_poly(multiplicity_coset);
             }
 
             // Shuffle constraints
             for (n, shuffle) in shuffles.iter().enumerate() {
-                let product_coset = pk.vk.domain.coeff_to_extended(shuffle.product_poly.clone());
+                let product_coset = to_coset(&shuffle.product_poly);
 
                 // Shuffle constraints
-                parallelize(&mut values, |values, start| {
+                parallelize_chunked(&mut values, argument_chunks, |values, start| {
                     let input_evaluator = &self.shuffles[2 * n];
                     let shuffle_evaluator = &self.shuffles[2 * n + 1];
                     let mut eval_data_input = shuffle_evaluator.instance();
@@ -599,6 +981,11 @@ impl<C: CurveAffine> Evaluator<C> {
                                     - product_coset[idx] * input_value)
                     }
                 });
+                scratch.give_poly(product_coset);
+            }
+
+            for coset in advice_cosets.into_iter().chain(instance_cosets) {
+                scratch.give_poly(coset);
             }
         }
         values
@@ -829,6 +1216,149 @@ impl<C: CurveAffine> GraphEvaluator<C> {
     }
 }
 
+impl<C: CurveAffine> RowEvaluator<C> for GraphEvaluator<C> {
+    type Data = EvaluationData<C>;
+
+    fn instance(&self) -> Self::Data {
+        GraphEvaluator::instance(self)
+    }
+
+    fn evaluate<B: Basis>(
+        &self,
+        data: &mut Self::Data,
+        fixed: &[Polynomial<C::ScalarExt, B>],
+        advice: &[Polynomial<C::ScalarExt, B>],
+        instance: &[Polynomial<C::ScalarExt, B>],
+        challenges: &[C::ScalarExt],
+        beta: &C::ScalarExt,
+        gamma: &C::ScalarExt,
+        theta: &C::ScalarExt,
+        y: &C::ScalarExt,
+        previous_value: &C::ScalarExt,
+        idx: usize,
+        rot_scale: i32,
+        isize: i32,
+    ) -> C::ScalarExt {
+        GraphEvaluator::evaluate(
+            self,
+            data,
+            fixed,
+            advice,
+            instance,
+            challenges,
+            beta,
+            gamma,
+            theta,
+            y,
+            previous_value,
+            idx,
+            rot_scale,
+            isize,
+        )
+    }
+}
+
+/// A [`RowEvaluator`] that walks the expressions of the constraint system at
+/// each row. Unlike a [`GraphEvaluator`], it keeps no table of intermediate
+/// values, at the cost of evaluating the subexpressions shared between
+/// constraints once for each of them.
+#[derive(Clone, Debug)]
+pub enum ExpressionEvaluator<'a, C: CurveAffine> {
+    /// The custom gates, folded into the previous value with `y`.
+    Gates(Vec<&'a Expression<C::ScalarExt>>),
+    /// The expressions compressed with `theta`, plus `beta` or `gamma`.
+    Compressed(&'a [Expression<C::ScalarExt>], ValueSource),
+    /// The product of two evaluations.
+    Product(Box<Self>, Box<Self>),
+}
+
+impl<C: CurveAffine> RowEvaluator<C> for ExpressionEvaluator<'_, C> {
+    type Data = ();
+
+    fn instance(&self) -> Self::Data {}
+
+    fn evaluate<B: Basis>(
+        &self,
+        data: &mut Self::Data,
+        fixed: &[Polynomial<C::ScalarExt, B>],
+        advice: &[Polynomial<C::ScalarExt, B>],
+        instance: &[Polynomial<C::ScalarExt, B>],
+        challenges: &[C::ScalarExt],
+        beta: &C::ScalarExt,
+        gamma: &C::ScalarExt,
+        theta: &C::ScalarExt,
+        y: &C::ScalarExt,
+        previous_value: &C::ScalarExt,
+        idx: usize,
+        rot_scale: i32,
+        isize: i32,
+    ) -> C::ScalarExt {
+        let at = |rotation: Rotation| get_rotation_idx(idx, rotation.0, rot_scale, isize);
+        let evaluate = |expression: &Expression<C::ScalarExt>| {
+            expression.evaluate(
+                &|scalar| scalar,
+                &|_| panic!("virtual selectors are removed during optimization"),
+                &|query| fixed[query.column_index][at(query.rotation)],
+                &|query| advice[query.column_index][at(query.rotation)],
+                &|query| instance[query.column_index][at(query.rotation)],
+                &|challenge| challenges[challenge.index()],
+                &|a| -a,
+                &|a, b| a + b,
+                &|a, b| a * b,
+                &|a, scalar| a * scalar,
+            )
+        };
+
+        match self {
+            ExpressionEvaluator::Gates(polys) => polys
+                .iter()
+                .fold(*previous_value, |acc, poly| acc * y + evaluate(poly)),
+            ExpressionEvaluator::Compressed(expressions, blind) => {
+                let compressed = expressions
+                    .iter()
+                    .fold(C::ScalarExt::ZERO, |acc, expression| {
+                        acc * theta + evaluate(expression)
+                    });
+                compressed
+                    + blind.get(
+                        &[],
+                        &[],
+                        &[],
+                        fixed,
+                        advice,
+                        instance,
+                        challenges,
+                        beta,
+                        gamma,
+                        theta,
+                        y,
+                        previous_value,
+                    )
+            }
+            ExpressionEvaluator::Product(a, b) => {
+                let mut evaluate = |evaluator: &Self| {
+                    evaluator.evaluate(
+                        data,
+                        fixed,
+                        advice,
+                        instance,
+                        challenges,
+                        beta,
+                        gamma,
+                        theta,
+                        y,
+                        previous_value,
+                        idx,
+                        rot_scale,
+                        isize,
+                    )
+                };
+                evaluate(a) * evaluate(b)
+            }
+        }
+    }
+}
+
 /// Simple evaluation of an expression
 pub fn evaluate<F: Field, B: Basis>(
     expression: &Expression<F>,
@@ -869,3 +1399,86 @@ pub fn evaluate<F: Field, B: Basis>(
     });
     values
 }
+
+#[test]
+fn evaluator_debug_names_lookups() {
+    use crate::plonk::LookupBackend;
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    for backend in [LookupBackend::Permutation, LookupBackend::LogUp] {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        cs.set_lookup_backend(backend);
+        let value = cs.advice_column();
+        let table = cs.lookup_table_column();
+        cs.lookup("byte range", |meta| {
+            vec![(meta.query_advice(value, Rotation::cur()), table)]
+        });
+
+        let debug = format!("{:?}", Evaluator::<G1Affine>::new(&cs));
+        assert!(debug.contains("\"byte range\""), "{}", debug);
+    }
+}
+
+#[test]
+fn gate_groups_by_cost() {
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    let mut cs = ConstraintSystem::<Fr>::default();
+    let a = cs.advice_column();
+    let cheap = |cs: &mut ConstraintSystem<Fr>, name| {
+        cs.create_gate(name, |meta| vec![meta.query_advice(a, Rotation::cur())])
+    };
+    cheap(&mut cs, "first");
+    cheap(&mut cs, "second");
+    cs.create_gate_with_cost("heavy", GATE_GROUP_COST * 3, |meta| {
+        vec![meta.query_advice(a, Rotation::next())]
+    });
+    cheap(&mut cs, "third");
+    cs.create_gate_with_cost("almost full", GATE_GROUP_COST - 1, |meta| {
+        vec![meta.query_advice(a, Rotation::prev())]
+    });
+
+    let groups = GateGroup::split(&cs.gates);
+    assert_eq!(
+        groups,
+        vec![
+            GateGroup {
+                gates: 0..2,
+                cost: 2
+            },
+            GateGroup {
+                gates: 2..3,
+                cost: GATE_GROUP_COST * 3
+            },
+            GateGroup {
+                gates: 3..5,
+                cost: GATE_GROUP_COST
+            },
+        ]
+    );
+
+    // Only the rows of the heavy gate are split into more tasks.
+    for group in [&groups[0], &groups[2]] {
+        assert_eq!(
+            group.chunk_size(ChunkSize::Rows(64), 1024),
+            ChunkSize::Rows(64)
+        );
+    }
+    assert_eq!(
+        groups[1].chunk_size(ChunkSize::Rows(64), 1024),
+        ChunkSize::Rows(21)
+    );
+    let tasks = multicore::current_num_threads() * 3;
+    assert_eq!(
+        groups[1].chunk_size(ChunkSize::PerThread, 1024),
+        ChunkSize::Rows((1024 + tasks - 1) / tasks)
+    );
+
+    let ev = Evaluator::<G1Affine>::new(&cs);
+    assert_eq!(ev.custom_gates.len(), 3);
+    let timings = ev.gate_timings(&cs);
+    assert_eq!(timings[0].gates, vec!["first", "second"]);
+    assert_eq!(timings[2].gates, vec!["third", "almost full"]);
+    let interpreted = Evaluator::<G1Affine, ExpressionEvaluator<'_, G1Affine>>::interpreted(&cs);
+    assert_eq!(interpreted.custom_gates.len(), 3);
+}
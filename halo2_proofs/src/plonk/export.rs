@@ -0,0 +1,669 @@
+use ff::{Field, PrimeField};
+use halo2curves::Coordinates;
+
+use super::{
+    Advice, Any, Column, ColumnType, ConstraintSystem, Expression, Fixed, Instance, LookupBackend,
+    VerifyingKey,
+};
+use crate::{arithmetic::CurveAffine, poly::Rotation};
+
+/// Everything a verifier needs to know about a [`VerifyingKey`], in a form
+/// that does not depend on this crate, for generating verifiers in other
+/// languages. Obtained from [`VerifyingKey::export`].
+///
+/// Field elements are written as `0x` followed by the bytes of their
+/// representation in reverse order, which is their big-endian hexadecimal
+/// value for the curves of this crate. Points are written as their affine
+/// coordinates, with the identity as `(0, 0)`.
+///
+/// The evaluations in a proof, and the queries of the multiopen argument,
+/// follow the order of the queries listed here. For each proof, the
+/// instance queries (only for commitment schemes that query the instance
+/// columns), then the advice queries, then the permutation, lookup and
+/// shuffle arguments in order; then the fixed queries, the permutation
+/// commitments at `x`, and the vanishing argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VkExport {
+    /// The base-2 logarithm of the number of rows.
+    pub k: u32,
+    /// The number of rows.
+    pub n: u64,
+    /// The generator of the domain of `n` rows.
+    pub omega: String,
+    /// The base-2 logarithm of the size of the extended domain.
+    pub extended_k: u32,
+    /// The number of pieces of the quotient polynomial.
+    pub quotient_poly_degree: usize,
+    /// The maximum degree of the constraints.
+    pub degree: usize,
+    /// The number of rows at the end of the domain that hold random values;
+    /// see [`ConstraintSystem::blinding_factors_breakdown`].
+    pub blinding_factors: usize,
+    /// The number of rows the circuit can assign, `n - blinding_factors - 1`.
+    pub usable_rows: usize,
+    /// Whether the advice columns are blinded.
+    pub zero_knowledge: bool,
+    /// The scalar the transcript absorbs for the verifying key.
+    pub transcript_repr: String,
+    /// The number of fixed columns.
+    pub num_fixed_columns: usize,
+    /// The phase of each advice column.
+    pub advice_column_phases: Vec<u8>,
+    /// The number of advice columns of each phase, from the first to the
+    /// last; the commitments to the advice columns of a phase are read
+    /// together, in the order of their indices.
+    pub num_advice_columns_per_phase: Vec<usize>,
+    /// The indices in `advice_queries` of the queries to the advice columns
+    /// of each phase, from the first to the last.
+    pub advice_queries_per_phase: Vec<Vec<usize>>,
+    /// The number of instance columns.
+    pub num_instance_columns: usize,
+    /// The phase after which each challenge is squeezed, or computed for
+    /// the powers of challenges.
+    pub challenge_phases: Vec<u8>,
+    /// The challenges that are not squeezed from the transcript but computed
+    /// as powers of another.
+    pub challenge_powers: Vec<ChallengePowerExport>,
+    /// The commitments to the fixed columns.
+    pub fixed_commitments: Vec<PointExport>,
+    /// The queries to fixed columns, in the order of their evaluations.
+    pub fixed_queries: Vec<QueryExport>,
+    /// The queries to advice columns, in the order of their evaluations.
+    pub advice_queries: Vec<QueryExport>,
+    /// The queries to instance columns, in the order of their evaluations.
+    pub instance_queries: Vec<QueryExport>,
+    /// The custom gates.
+    pub gates: Vec<GateExport>,
+    /// The permutation argument.
+    pub permutation: PermutationExport,
+    /// The lookup argument, either `"Permutation"` or `"LogUp"`.
+    pub lookup_backend: String,
+    /// The lookups.
+    pub lookups: Vec<LookupExport>,
+    /// The shuffles.
+    pub shuffles: Vec<ShuffleExport>,
+    /// The selectors of the circuit and the fixed columns they were
+    /// compressed into.
+    pub selectors: Vec<SelectorExport>,
+    /// The names of the columns and selectors.
+    pub names: NamesExport,
+}
+
+/// A point, as its affine coordinates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PointExport {
+    /// The `x` coordinate.
+    pub x: String,
+    /// The `y` coordinate.
+    pub y: String,
+}
+
+/// A challenge computed as a power of a challenge squeezed from the
+/// transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChallengePowerExport {
+    /// The index of the challenge.
+    pub challenge: usize,
+    /// The index of the squeezed challenge it is a power of.
+    pub base: usize,
+    /// The exponent.
+    pub exponent: u32,
+}
+
+/// A query to a column of a given type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryExport {
+    /// The index of the column among the columns of its type.
+    pub column: usize,
+    /// The rotation the column is queried at.
+    pub rotation: i32,
+}
+
+/// A column of any type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColumnExport {
+    /// An advice column.
+    Advice {
+        /// The index of the column.
+        index: usize,
+        /// The phase of the column.
+        phase: u8,
+    },
+    /// A fixed column.
+    Fixed {
+        /// The index of the column.
+        index: usize,
+    },
+    /// An instance column.
+    Instance {
+        /// The index of the column.
+        index: usize,
+    },
+}
+
+/// A polynomial expression, in which each query refers to the evaluation at
+/// the same index in its list of queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExpressionExport {
+    /// A constant.
+    Constant(String),
+    /// A selector that was not compressed into a fixed column.
+    Selector(usize),
+    /// A query to a fixed column.
+    Fixed {
+        /// The index of the query among the fixed queries.
+        query: usize,
+        /// The index of the column.
+        column: usize,
+        /// The rotation of the query.
+        rotation: i32,
+    },
+    /// A query to an advice column.
+    Advice {
+        /// The index of the query among the advice queries.
+        query: usize,
+        /// The index of the column.
+        column: usize,
+        /// The rotation of the query.
+        rotation: i32,
+        /// The phase of the column.
+        phase: u8,
+    },
+    /// A query to an instance column.
+    Instance {
+        /// The index of the query among the instance queries.
+        query: usize,
+        /// The index of the column.
+        column: usize,
+        /// The rotation of the query.
+        rotation: i32,
+    },
+    /// A challenge.
+    Challenge {
+        /// The index of the challenge.
+        index: usize,
+        /// The phase after which it is squeezed.
+        phase: u8,
+    },
+    /// The negation of an expression.
+    Negated(Box<ExpressionExport>),
+    /// The sum of two expressions.
+    Sum(Box<ExpressionExport>, Box<ExpressionExport>),
+    /// The product of two expressions.
+    Product(Box<ExpressionExport>, Box<ExpressionExport>),
+    /// An expression multiplied by a constant.
+    Scaled(Box<ExpressionExport>, String),
+}
+
+/// A custom gate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GateExport {
+    /// The name of the gate.
+    pub name: String,
+    /// The names of the constraints of the gate, in order.
+    pub constraint_names: Vec<String>,
+    /// The constraints of the gate, in order.
+    pub constraints: Vec<ExpressionExport>,
+}
+
+/// The permutation argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PermutationExport {
+    /// The columns in the argument, in order.
+    pub columns: Vec<ColumnExport>,
+    /// The number of columns of each grand product.
+    pub chunk_len: usize,
+    /// The commitments to the permutation of each column.
+    pub commitments: Vec<PointExport>,
+}
+
+/// A lookup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LookupExport {
+    /// The name of the lookup.
+    pub name: String,
+    /// The input expressions.
+    pub inputs: Vec<ExpressionExport>,
+    /// The table expressions.
+    pub tables: Vec<ExpressionExport>,
+}
+
+/// A shuffle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShuffleExport {
+    /// The name of the shuffle.
+    pub name: String,
+    /// The input expressions.
+    pub inputs: Vec<ExpressionExport>,
+    /// The shuffle expressions.
+    pub shuffles: Vec<ExpressionExport>,
+}
+
+/// A selector compressed into a fixed column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelectorExport {
+    /// The index of the selector.
+    pub selector: usize,
+    /// The fixed column it was compressed into.
+    pub column: usize,
+    /// The expression that replaced it.
+    pub expression: ExpressionExport,
+}
+
+/// The names given to columns and selectors when they were allocated, or
+/// with [`ConstraintSystem::annotate_lookup_any_column`], by index. They do
+/// not affect verification.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NamesExport {
+    /// The names of the fixed columns.
+    pub fixed_columns: Vec<Option<String>>,
+    /// The names of the advice columns.
+    pub advice_columns: Vec<Option<String>>,
+    /// The names of the instance columns.
+    pub instance_columns: Vec<Option<String>>,
+    /// The names of the selectors.
+    pub selectors: Vec<Option<String>>,
+    /// The columns pinned with [`ConstraintSystem::pin_columns`], in the
+    /// order they were pinned, for tools that address columns by index to
+    /// check the indices they expect.
+    pub pinned_columns: Vec<PinnedColumnExport>,
+}
+
+/// A column pinned by name with [`ConstraintSystem::pin_columns`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PinnedColumnExport {
+    /// The name of the column.
+    pub name: String,
+    /// The column.
+    pub column: ColumnExport,
+}
+
+/// Returns the hexadecimal form of a field element.
+fn hex<F: PrimeField>(value: &F) -> String {
+    let digits: String = value
+        .to_repr()
+        .as_ref()
+        .iter()
+        .rev()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("0x{}", digits)
+}
+
+fn point<C: CurveAffine>(point: &C) -> PointExport {
+    let coordinates: Option<Coordinates<C>> = point.coordinates().into();
+    match coordinates {
+        Some(coordinates) => PointExport {
+            x: hex(coordinates.x()),
+            y: hex(coordinates.y()),
+        },
+        None => PointExport {
+            x: hex(&C::Base::ZERO),
+            y: hex(&C::Base::ZERO),
+        },
+    }
+}
+
+fn expression<F: PrimeField>(expression: &Expression<F>) -> ExpressionExport {
+    match expression {
+        Expression::Constant(constant) => ExpressionExport::Constant(hex(constant)),
+        Expression::Selector(selector) => ExpressionExport::Selector(selector.index()),
+        Expression::Fixed(query) => ExpressionExport::Fixed {
+            query: query.index.expect("queries are indexed"),
+            column: query.column_index,
+            rotation: query.rotation.0,
+        },
+        Expression::Advice(query) => ExpressionExport::Advice {
+            query: query.index.expect("queries are indexed"),
+            column: query.column_index,
+            rotation: query.rotation.0,
+            phase: query.phase.0,
+        },
+        Expression::Instance(query) => ExpressionExport::Instance {
+            query: query.index.expect("queries are indexed"),
+            column: query.column_index,
+            rotation: query.rotation.0,
+        },
+        Expression::Challenge(challenge) => ExpressionExport::Challenge {
+            index: challenge.index(),
+            phase: challenge.phase(),
+        },
+        Expression::Negated(a) => ExpressionExport::Negated(Box::new(expression(a))),
+        Expression::Sum(a, b) => {
+            ExpressionExport::Sum(Box::new(expression(a)), Box::new(expression(b)))
+        }
+        Expression::Product(a, b) => {
+            ExpressionExport::Product(Box::new(expression(a)), Box::new(expression(b)))
+        }
+        Expression::Scaled(a, scalar) => {
+            ExpressionExport::Scaled(Box::new(expression(a)), hex(scalar))
+        }
+    }
+}
+
+fn expressions<F: PrimeField>(expressions: &[Expression<F>]) -> Vec<ExpressionExport> {
+    expressions.iter().map(expression).collect()
+}
+
+fn names<F: PrimeField>(cs: &ConstraintSystem<F>) -> NamesExport {
+    let name = |column: Column<Any>| cs.column_name(column).map(str::to_string);
+    NamesExport {
+        fixed_columns: (0..cs.num_fixed_columns)
+            .map(|index| name(Column::new(index, Fixed).into()))
+            .collect(),
+        advice_columns: cs
+            .advice_column_phase
+            .iter()
+            .enumerate()
+            .map(|(index, &phase)| name(Column::new(index, Advice { phase }).into()))
+            .collect(),
+        instance_columns: (0..cs.num_instance_columns)
+            .map(|index| name(Column::new(index, Instance).into()))
+            .collect(),
+        selectors: (0..cs.num_selectors)
+            .map(|index| cs.selector_names.get(&index).cloned())
+            .collect(),
+        // Keygen fails if a pin does not hold, so every pin of a key resolves
+        // to a column.
+        pinned_columns: cs
+            .column_pins()
+            .iter()
+            .filter_map(|(name, _)| {
+                Some(PinnedColumnExport {
+                    name: name.clone(),
+                    column: column_export(cs.pinned_column(name)?),
+                })
+            })
+            .collect(),
+    }
+}
+
+fn column_export(column: Column<Any>) -> ColumnExport {
+    match column.column_type() {
+        Any::Advice(advice) => ColumnExport::Advice {
+            index: column.index(),
+            phase: advice.phase(),
+        },
+        Any::Fixed => ColumnExport::Fixed {
+            index: column.index(),
+        },
+        Any::Instance => ColumnExport::Instance {
+            index: column.index(),
+        },
+    }
+}
+
+fn queries<T: ColumnType>(queries: &[(Column<T>, Rotation)]) -> Vec<QueryExport> {
+    queries
+        .iter()
+        .map(|(column, rotation)| QueryExport {
+            column: column.index(),
+            rotation: rotation.0,
+        })
+        .collect()
+}
+
+impl<C: CurveAffine> VerifyingKey<C> {
+    /// Exports everything a verifier needs to know about this key, for
+    /// generating verifiers in other languages.
+    pub fn export(&self) -> VkExport {
+        let cs = &self.cs;
+        VkExport {
+            k: self.domain.k(),
+            n: 1 << self.domain.k(),
+            omega: hex(&self.domain.get_omega()),
+            extended_k: self.domain.extended_k(),
+            quotient_poly_degree: self.domain.get_quotient_poly_degree(),
+            degree: self.cs_degree,
+            blinding_factors: cs.blinding_factors(),
+            usable_rows: cs.usable_rows(self.domain.k()).end,
+            zero_knowledge: cs.zero_knowledge,
+            transcript_repr: hex(&self.transcript_repr),
+            num_fixed_columns: cs.num_fixed_columns,
+            advice_column_phases: cs.advice_column_phase(),
+            num_advice_columns_per_phase: (0..=cs.max_phase())
+                .map(|phase| cs.num_advice_columns_in_phase(phase))
+                .collect(),
+            advice_queries_per_phase: (0..=cs.max_phase())
+                .map(|phase| {
+                    cs.advice_queries
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (column, _))| column.column_type().phase() == phase)
+                        .map(|(index, _)| index)
+                        .collect()
+                })
+                .collect(),
+            num_instance_columns: cs.num_instance_columns,
+            challenge_phases: cs.challenge_phases(),
+            challenge_powers: cs
+                .challenge_powers
+                .iter()
+                .map(|&(challenge, base, exponent)| ChallengePowerExport {
+                    challenge,
+                    base,
+                    exponent,
+                })
+                .collect(),
+            fixed_commitments: self.fixed_commitments.iter().map(point).collect(),
+            fixed_queries: queries(&cs.fixed_queries),
+            advice_queries: queries(&cs.advice_queries),
+            instance_queries: queries(&cs.instance_queries),
+            gates: cs
+                .gates
+                .iter()
+                .map(|gate| GateExport {
+                    name: gate.name().to_string(),
+                    constraint_names: (0..gate.polynomials().len())
+                        .map(|i| gate.constraint_name(i).to_string())
+                        .collect(),
+                    constraints: expressions(gate.polynomials()),
+                })
+                .collect(),
+            permutation: PermutationExport {
+                columns: cs
+                    .permutation
+                    .get_columns()
+                    .iter()
+                    .map(|column| column_export(*column))
+                    .collect(),
+                chunk_len: self.permutation_chunk_size(),
+                commitments: self.permutation.commitments().iter().map(point).collect(),
+            },
+            lookup_backend: match cs.lookup_backend {
+                LookupBackend::Permutation => "Permutation",
+                LookupBackend::LogUp => "LogUp",
+            }
+            .to_string(),
+            lookups: cs
+                .lookups
+                .iter()
+                .map(|lookup| LookupExport {
+                    name: lookup.name.clone(),
+                    inputs: expressions(&lookup.input_expressions),
+                    tables: expressions(&lookup.table_expressions),
+                })
+                .collect(),
+            shuffles: cs
+                .shuffles
+                .iter()
+                .map(|shuffle| ShuffleExport {
+                    name: shuffle.name.clone(),
+                    inputs: expressions(&shuffle.input_expressions),
+                    shuffles: expressions(&shuffle.shuffle_expressions),
+                })
+                .collect(),
+            selectors: cs
+                .selector_map
+                .iter()
+                .zip(cs.selector_replacements.iter())
+                .enumerate()
+                .map(|(selector, (column, replacement))| SelectorExport {
+                    selector,
+                    column: column.index(),
+                    expression: expression(replacement),
+                })
+                .collect(),
+            names: names(cs),
+        }
+    }
+}
+
+#[test]
+fn test_export() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use crate::plonk::{keygen_vk, Circuit, Error};
+    use crate::poly::kzg::commitment::ParamsKZG;
+    use halo2curves::bn256::{Bn256, Fr};
+    use rand_core::OsRng;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Column<Fixed>,
+        a: Column<Advice>,
+    }
+
+    #[derive(Clone, Copy, Default)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q = meta.fixed_column();
+            let a = meta.advice_column_named("a");
+            let instance: Column<Instance> = meta.instance_column();
+            meta.pin_columns(&[("a", 0)]);
+            meta.enable_equality(a);
+            meta.enable_equality(instance);
+            meta.create_gate("square", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a_next = meta.query_advice(a, Rotation::next());
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![q * (a_next - a.clone() * a)]
+            });
+            MyConfig { q, a }
+        }
+
+        fn synthesize(
+            &self,
+            config: MyConfig,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "square",
+                |mut region| {
+                    region.assign_fixed(|| "q", config.q, 0, || Value::known(Fr::ONE))?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(3)))?;
+                    region.assign_advice(|| "a", config.a, 1, || Value::known(Fr::from(9)))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = ParamsKZG::<Bn256>::setup(4, OsRng);
+    let vk = keygen_vk(&params, &MyCircuit).unwrap();
+    let export = vk.export();
+
+    let query = |column, rotation| QueryExport { column, rotation };
+    let advice = |query, rotation| {
+        Box::new(ExpressionExport::Advice {
+            query,
+            column: 0,
+            rotation,
+            phase: 0,
+        })
+    };
+    let gate = ExpressionExport::Product(
+        Box::new(ExpressionExport::Fixed {
+            query: 0,
+            column: 0,
+            rotation: 0,
+        }),
+        Box::new(ExpressionExport::Sum(
+            advice(1, 1),
+            Box::new(ExpressionExport::Negated(Box::new(
+                ExpressionExport::Product(advice(0, 0), advice(0, 0)),
+            ))),
+        )),
+    );
+
+    // The commitments and the transcript representation depend on the
+    // parameters, so they are checked against the key itself.
+    assert_eq!(
+        export,
+        VkExport {
+            k: 4,
+            n: 16,
+            omega: "0x21082ca216cbbf4e1c6e4f4594dd508c996dfbe1174efb98b11509c6e306460b".to_string(),
+            extended_k: 5,
+            quotient_poly_degree: 2,
+            degree: 3,
+            blinding_factors: 5,
+            usable_rows: 10,
+            zero_knowledge: true,
+            transcript_repr: hex(&vk.transcript_repr),
+            num_fixed_columns: 1,
+            advice_column_phases: vec![0],
+            num_advice_columns_per_phase: vec![1],
+            advice_queries_per_phase: vec![vec![0, 1]],
+            num_instance_columns: 1,
+            challenge_phases: vec![],
+            challenge_powers: vec![],
+            fixed_commitments: vk.fixed_commitments.iter().map(point).collect(),
+            fixed_queries: vec![query(0, 0)],
+            advice_queries: vec![query(0, 0), query(0, 1)],
+            instance_queries: vec![query(0, 0)],
+            gates: vec![GateExport {
+                name: "square".to_string(),
+                constraint_names: vec!["".to_string()],
+                constraints: vec![gate],
+            }],
+            permutation: PermutationExport {
+                columns: vec![
+                    ColumnExport::Advice { index: 0, phase: 0 },
+                    ColumnExport::Instance { index: 0 },
+                ],
+                chunk_len: 1,
+                commitments: vk.permutation.commitments().iter().map(point).collect(),
+            },
+            lookup_backend: "Permutation".to_string(),
+            lookups: vec![],
+            shuffles: vec![],
+            selectors: vec![],
+            names: NamesExport {
+                fixed_columns: vec![None],
+                advice_columns: vec![Some("a".to_string())],
+                instance_columns: vec![None],
+                selectors: vec![],
+                pinned_columns: vec![PinnedColumnExport {
+                    name: "a".to_string(),
+                    column: ColumnExport::Advice { index: 0, phase: 0 },
+                }],
+            },
+        }
+    );
+    assert_eq!(export.fixed_commitments.len(), 1);
+    assert_eq!(export.permutation.commitments.len(), 2);
+}
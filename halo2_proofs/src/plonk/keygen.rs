@@ -2,28 +2,41 @@
 
 use std::ops::Range;
 
-use ff::{Field, FromUniformBytes};
+use blake2b_simd::Params as Blake2bParams;
+use ff::{Field, FromUniformBytes, PrimeField};
 use group::Curve;
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
 
+#[cfg(test)]
+use super::test_fixtures;
 use super::{
     circuit::{
         Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Fixed, FloorPlanner, Instance,
         Selector,
     },
-    evaluation::Evaluator,
     permutation, Assigned, Challenge, Error, LagrangeCoeff, Polynomial, ProvingKey, VerifyingKey,
+    VkMismatch,
 };
 use crate::{
-    arithmetic::{parallelize, CurveAffine},
+    arithmetic::{parallelize, CpuEngine, CurveAffine, Engine},
     circuit::Value,
+    helpers::{SerdeCurveAffine, SerdePrimeField},
+    multicore::{self, IntoParallelIterator, ParallelIterator},
     poly::{
         batch_invert_assigned,
         commitment::{Blind, Params},
         EvaluationDomain,
     },
+    SerdeFormat,
 };
 
-pub(crate) fn create_domain<C, ConcreteCircuit>(
+/// Configures `ConcreteCircuit` and creates the evaluation domain it needs for
+/// `2^k` rows.
+///
+/// Callers must use the returned constraint system rather than one configured
+/// separately, since it is the one keygen works with.
+pub fn create_domain<C, ConcreteCircuit>(
     k: u32,
     #[cfg(feature = "circuit-params")] params: ConcreteCircuit::Params,
 ) -> (
@@ -41,36 +54,200 @@ where
     #[cfg(not(feature = "circuit-params"))]
     let config = ConcreteCircuit::configure(&mut cs);
 
+    create_domain_from_cs::<C, _>(k, cs, config)
+}
+
+/// Same as [`create_domain`], with an extended domain of `2^extended_k` rows
+/// whatever [`Circuit::configure`] sets; see
+/// [`ConstraintSystem::set_extended_k`].
+///
+/// Fails with [`Error::ExtendedDomainTooSmall`], naming the constraint that
+/// needs a larger extended domain, if `extended_k` is below
+/// [`ConstraintSystem::minimum_extended_k`]. Keygen configures the circuit
+/// again, so for keys to use this extended domain, `configure` has to call
+/// [`ConstraintSystem::set_extended_k`] itself; keygen then checks the size
+/// again once the selectors are compressed, which may raise the degree of
+/// the gates.
+pub fn create_domain_with_extended_k<C, ConcreteCircuit>(
+    k: u32,
+    extended_k: u32,
+    #[cfg(feature = "circuit-params")] params: ConcreteCircuit::Params,
+) -> Result<
+    (
+        EvaluationDomain<C::Scalar>,
+        ConstraintSystem<C::Scalar>,
+        ConcreteCircuit::Config,
+    ),
+    Error,
+>
+where
+    C: CurveAffine,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let mut cs = ConstraintSystem::default();
+    #[cfg(feature = "circuit-params")]
+    let config = ConcreteCircuit::configure_with_params(&mut cs, params);
+    #[cfg(not(feature = "circuit-params"))]
+    let config = ConcreteCircuit::configure(&mut cs);
+
+    cs.set_extended_k(extended_k);
+    cs.check_extended_k(k)?;
+    Ok(create_domain_from_cs::<C, _>(k, cs, config))
+}
+
+/// Creates the evaluation domain for an already configured constraint system
+/// with `2^k` rows, passing `config` through.
+///
+/// This is [`create_domain`] for callers that run `configure` themselves. As
+/// there, the returned constraint system is the one to keep using: any
+/// transformation keygen applies before sizing the domain is reflected in it,
+/// not in the one passed in.
+pub fn create_domain_from_cs<C, Config>(
+    k: u32,
+    cs: ConstraintSystem<C::Scalar>,
+    config: Config,
+) -> (
+    EvaluationDomain<C::Scalar>,
+    ConstraintSystem<C::Scalar>,
+    Config,
+)
+where
+    C: CurveAffine,
+{
     let degree = cs.degree();
 
-    let domain = EvaluationDomain::new(degree as u32, k);
+    let domain = match cs.extended_k {
+        // Keygen rejects an extended domain too small for the constraints.
+        Some(extended_k) if extended_k >= cs.minimum_extended_k(k) => {
+            EvaluationDomain::with_extended_k(degree as u32, k, extended_k)
+        }
+        _ => EvaluationDomain::new(degree as u32, k),
+    };
 
     (domain, cs, config)
 }
 
+/// A fixed column being assigned during keygen. Columns are only allocated once
+/// they are assigned a non-zero value, so circuits with many sparsely used
+/// fixed columns don't pay for all of them up front.
+#[derive(Clone, Debug)]
+enum FixedColumn<F: Field> {
+    Empty,
+    Dense(Vec<Assigned<F>>),
+}
+
+impl<F: Field> FixedColumn<F> {
+    /// Returns the values of this column, allocating `n` zeroes if it was empty.
+    fn dense_mut(&mut self, n: usize) -> &mut [Assigned<F>] {
+        if let FixedColumn::Empty = self {
+            *self = FixedColumn::Dense(vec![Assigned::Zero; n]);
+        }
+        match self {
+            FixedColumn::Dense(values) => values,
+            FixedColumn::Empty => unreachable!(),
+        }
+    }
+}
+
+/// Inverts the dense fixed columns in a single batch. Columns that were never
+/// allocated are returned as `None` and stand for the zero polynomial.
+fn batch_invert_fixed<F: Field>(
+    domain: &EvaluationDomain<F>,
+    fixed: Vec<FixedColumn<F>>,
+) -> Vec<Option<Polynomial<F, LagrangeCoeff>>> {
+    let is_dense: Vec<bool> = fixed
+        .iter()
+        .map(|column| matches!(column, FixedColumn::Dense(_)))
+        .collect();
+    let dense = fixed
+        .into_iter()
+        .filter_map(|column| match column {
+            FixedColumn::Dense(values) => Some(domain.lagrange_assigned_from_vec(values)),
+            FixedColumn::Empty => None,
+        })
+        .collect();
+
+    let mut inverted = batch_invert_assigned(dense).into_iter();
+    is_dense
+        .into_iter()
+        .map(|is_dense| if is_dense { inverted.next() } else { None })
+        .collect()
+}
+
+/// Replaces the fixed columns that were never allocated with zero polynomials.
+fn densify_fixed<F: Field>(
+    domain: &EvaluationDomain<F>,
+    fixed: Vec<Option<Polynomial<F, LagrangeCoeff>>>,
+) -> Vec<Polynomial<F, LagrangeCoeff>> {
+    fixed
+        .into_iter()
+        .map(|poly| poly.unwrap_or_else(|| domain.empty_lagrange()))
+        .collect()
+}
+
 /// Assembly to be used in circuit synthesis.
 #[derive(Debug)]
 struct Assembly<F: Field> {
     k: u32,
-    fixed: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
+    fixed: Vec<FixedColumn<F>>,
+    // Whether each fixed column was given its values at configure time.
+    preassigned: Vec<bool>,
     permutation: permutation::keygen::Assembly,
     selectors: Vec<Vec<bool>>,
     // A range of available rows for assignment and copies.
     usable_rows: Range<usize>,
+    // The region currently being assigned, reported when rows run out.
+    current_region: Option<String>,
+    // The expected length of each instance column, if the caller provided them.
+    instance_lengths: Option<Vec<usize>>,
     _marker: std::marker::PhantomData<F>,
 }
 
+impl<F: Field> Assembly<F> {
+    /// Checks that `row` is usable, naming the current region and `column`
+    /// in the error otherwise.
+    fn check_usable(&self, column: Option<Column<Any>>, row: usize) -> Result<(), Error> {
+        if self.usable_rows.contains(&row) {
+            Ok(())
+        } else {
+            Err(Error::not_enough_rows_available_at(
+                self.k,
+                self.current_region.as_deref(),
+                column,
+                row,
+            ))
+        }
+    }
+
+    /// Checks that a copy constraint on `column` at `row` stays within the
+    /// expected instance lengths, if there are any.
+    fn check_instance_length(&self, column: Column<Any>, row: usize) -> Result<(), Error> {
+        if let (Any::Instance, Some(lengths)) = (column.column_type(), &self.instance_lengths) {
+            let length = lengths[column.index()];
+            if row >= length {
+                return Err(Error::InstanceCopyOutOfBounds {
+                    region: self.current_region.clone(),
+                    column,
+                    row,
+                    length,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<F: Field> Assignment<F> for Assembly<F> {
-    fn enter_region<NR, N>(&mut self, _: N)
+    fn enter_region<NR, N>(&mut self, name: N)
     where
         NR: Into<String>,
         N: FnOnce() -> NR,
     {
-        // Do nothing; we don't care about regions in this context.
+        self.current_region = Some(name().into());
     }
 
     fn exit_region(&mut self) {
-        // Do nothing; we don't care about regions in this context.
+        self.current_region = None;
     }
 
     fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
@@ -78,19 +255,15 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
-        if !self.usable_rows.contains(&row) {
-            return Err(Error::not_enough_rows_available(self.k));
-        }
+        self.check_usable(None, row)?;
 
         self.selectors[selector.0][row] = true;
 
         Ok(())
     }
 
-    fn query_instance(&self, _: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
-        if !self.usable_rows.contains(&row) {
-            return Err(Error::not_enough_rows_available(self.k));
-        }
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+        self.check_usable(Some(column.into()), row)?;
 
         // There is no instance in this context.
         Ok(Value::unknown())
@@ -126,15 +299,24 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
-        if !self.usable_rows.contains(&row) {
-            return Err(Error::not_enough_rows_available(self.k));
+        self.check_usable(Some(column.into()), row)?;
+        if self.preassigned.get(column.index()) == Some(&true) {
+            return Err(Error::PreassignedFixedColumn { column, row });
         }
 
-        *self
+        let n = 1 << self.k;
+        let col = self
             .fixed
             .get_mut(column.index())
-            .and_then(|v| v.get_mut(row))
-            .ok_or(Error::BoundsFailure)? = to().into_field().assign()?;
+            .ok_or(Error::BoundsFailure)?;
+        if row >= n {
+            return Err(Error::BoundsFailure);
+        }
+
+        let value = to().into_field().assign()?;
+        if !(value.is_zero_vartime() && matches!(col, FixedColumn::Empty)) {
+            col.dense_mut(n)[row] = value;
+        }
 
         Ok(())
     }
@@ -146,9 +328,10 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         right_column: Column<Any>,
         right_row: usize,
     ) -> Result<(), Error> {
-        if !self.usable_rows.contains(&left_row) || !self.usable_rows.contains(&right_row) {
-            return Err(Error::not_enough_rows_available(self.k));
-        }
+        self.check_usable(Some(left_column), left_row)?;
+        self.check_usable(Some(right_column), right_row)?;
+        self.check_instance_length(left_column, left_row)?;
+        self.check_instance_length(right_column, right_row)?;
 
         self.permutation
             .copy(left_column, left_row, right_column, right_row)
@@ -160,9 +343,7 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         from_row: usize,
         to: Value<Assigned<F>>,
     ) -> Result<(), Error> {
-        if !self.usable_rows.contains(&from_row) {
-            return Err(Error::not_enough_rows_available(self.k));
-        }
+        self.check_usable(Some(column.into()), from_row)?;
 
         let col = self
             .fixed
@@ -170,6 +351,10 @@ impl<F: Field> Assignment<F> for Assembly<F> {
             .ok_or(Error::BoundsFailure)?;
 
         let filler = to.assign()?;
+        if filler.is_zero_vartime() && matches!(col, FixedColumn::Empty) {
+            return Ok(());
+        }
+        let col = col.dense_mut(1 << self.k);
         for row in self.usable_rows.clone().skip(from_row) {
             col[row] = filler;
         }
@@ -202,16 +387,306 @@ impl<F: Field> Assignment<F> for Assembly<F> {
     }
 }
 
-/// Generate a `VerifyingKey` from an instance of `Circuit`.
-pub fn keygen_vk<'params, C, P, ConcreteCircuit>(
+/// A stage of key generation, as reported to the callback passed to
+/// [`keygen_vk_with_progress`] or [`keygen_pk_with_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeygenStage {
+    /// Synthesizing the circuit to obtain the fixed columns and copy constraints.
+    Synthesis,
+    /// Combining simple selectors into fixed columns.
+    SelectorCompression,
+    /// Committing to the fixed columns.
+    FixedCommitments,
+    /// Building the permutation argument.
+    PermutationBuild,
+    /// Converting the fixed columns into coefficient form.
+    LagrangeToCoeff,
+}
+
+const VK_STAGES: &[KeygenStage] = &[
+    KeygenStage::Synthesis,
+    KeygenStage::SelectorCompression,
+    KeygenStage::FixedCommitments,
+    KeygenStage::PermutationBuild,
+];
+
+const PK_STAGES: &[KeygenStage] = &[
+    KeygenStage::Synthesis,
+    KeygenStage::SelectorCompression,
+    KeygenStage::FixedCommitments,
+    KeygenStage::PermutationBuild,
+    KeygenStage::LagrangeToCoeff,
+];
+
+/// Turns progress within a single stage into the fraction of the whole keygen
+/// call that has completed.
+struct Progress<CB: FnMut(KeygenStage, f32)> {
+    stages: &'static [KeygenStage],
+    cb: CB,
+}
+
+impl<CB: FnMut(KeygenStage, f32)> Progress<CB> {
+    fn report(&mut self, stage: KeygenStage, done: f32) {
+        let index = self
+            .stages
+            .iter()
+            .position(|s| *s == stage)
+            .expect("stage is part of this keygen call");
+        (self.cb)(stage, (index as f32 + done) / self.stages.len() as f32);
+    }
+}
+
+/// Commits to the fixed columns in parallel, one batch of columns per
+/// available thread, calling `report` with the fraction committed after each
+/// batch. Empty columns all share a single commitment to the zero polynomial.
+fn commit_fixed<'params, C, P>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    fixed: &[Option<Polynomial<C::Scalar, LagrangeCoeff>>],
+    mut report: impl FnMut(f32),
+) -> Vec<C>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+{
+    let zero_commitment = fixed.iter().any(Option::is_none).then(|| {
+        params
+            .commit_lagrange_with_engine(engine, &domain.empty_lagrange(), Blind::default())
+            .to_affine()
+    });
+
+    let mut commitments = Vec::with_capacity(fixed.len());
+    for batch in fixed.chunks(multicore::current_num_threads()) {
+        commitments.extend(
+            (0..batch.len())
+                .into_par_iter()
+                .map(|i| match &batch[i] {
+                    Some(poly) => params
+                        .commit_lagrange_with_engine(engine, poly, Blind::default())
+                        .to_affine(),
+                    None => zero_commitment.expect("computed when a column is empty"),
+                })
+                .collect::<Vec<_>>(),
+        );
+        report(commitments.len() as f32 / fixed.len() as f32);
+    }
+    commitments
+}
+
+/// Returns whether `columns`, of which `None` stands for an empty column,
+/// commit to `commitments`.
+///
+/// The columns are checked together, by committing to a random linear
+/// combination of them and comparing it with the same combination of
+/// `commitments`, which takes a single multiexponentiation of the size of a
+/// column. The factors of the combination are drawn from a seed derived from
+/// `vk` and `personal`; this catches a circuit that does not match its key,
+/// not one crafted to match it.
+fn commitments_match<'params, C, P>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    columns: &[Option<&Polynomial<C::Scalar, LagrangeCoeff>>],
+    commitments: &[C],
+    vk: &VerifyingKey<C>,
+    personal: &[u8; 16],
+) -> bool
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+{
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(
+        Blake2bParams::new()
+            .hash_length(32)
+            .personal(personal)
+            .to_state()
+            .update(vk.transcript_repr.to_repr().as_ref())
+            .finalize()
+            .as_bytes(),
+    );
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    let factors: Vec<C::Scalar> = columns
+        .iter()
+        .map(|_| C::Scalar::random(&mut rng))
+        .collect();
+
+    // Commitments are linear in both the values and the blind, so the blind
+    // of the combination is the combination of the default blinds.
+    let mut combination = domain.empty_lagrange();
+    for (column, factor) in columns.iter().zip(factors.iter()) {
+        if let Some(column) = column {
+            parallelize(&mut combination, |combination, start| {
+                for (value, column) in combination.iter_mut().zip(column[start..].iter()) {
+                    *value += *column * factor;
+                }
+            });
+        }
+    }
+    let blind = factors
+        .iter()
+        .fold(C::Scalar::ZERO, |sum, factor| sum + factor);
+    let combined = params.commit_lagrange_with_engine(engine, &combination, Blind(blind));
+    combined == engine.best_multiexp(&factors, commitments)
+}
+
+/// Returns the first fixed column of `fixed` whose commitment differs from
+/// the one in `vk`, if any.
+///
+/// The columns are checked together with [`commitments_match`]. Only if that
+/// check fails are the columns committed to one by one, to find the first
+/// that differs.
+fn mismatched_fixed_column<'params, C, P>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    fixed: &[Option<Polynomial<C::Scalar, LagrangeCoeff>>],
+    vk: &VerifyingKey<C>,
+) -> Option<usize>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+{
+    if fixed.len() != vk.fixed_commitments.len() {
+        return Some(fixed.len().min(vk.fixed_commitments.len()));
+    }
+
+    let columns: Vec<_> = fixed.iter().map(Option::as_ref).collect();
+    if commitments_match(
+        params,
+        engine,
+        domain,
+        &columns,
+        &vk.fixed_commitments,
+        vk,
+        b"Halo2-FixedCheck",
+    ) {
+        return None;
+    }
+
+    commit_fixed(params, engine, domain, fixed, |_| {})
+        .iter()
+        .zip(vk.fixed_commitments.iter())
+        .position(|(a, b)| a != b)
+}
+
+/// Returns the first column of the permutation argument whose permutation
+/// polynomial in `permutation` differs from the one `vk` commits to, if any.
+///
+/// As for the fixed columns, the polynomials are checked together with
+/// [`commitments_match`], and only committed to one by one if that fails.
+fn mismatched_permutation_column<'params, C, P>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    permutation: &permutation::ProvingKey<C>,
+    vk: &VerifyingKey<C>,
+) -> Option<usize>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+{
+    let polys = permutation.permutations();
+    let commitments = vk.permutation.commitments();
+    if polys.len() != commitments.len() {
+        return Some(polys.len().min(commitments.len()));
+    }
+
+    let columns: Vec<_> = polys.iter().map(Some).collect();
+    if commitments_match(
+        params,
+        engine,
+        domain,
+        &columns,
+        commitments,
+        vk,
+        b"Halo2-PermsCheck",
+    ) {
+        return None;
+    }
+
+    polys
+        .iter()
+        .zip(commitments.iter())
+        .position(|(poly, commitment)| {
+            params
+                .commit_lagrange_with_engine(engine, poly, Blind::default())
+                .to_affine()
+                != *commitment
+        })
+}
+
+/// Compares the constraint system of a verifying key with the one configured by
+/// a circuit, returning the first difference found.
+fn compare_constraint_systems<F: Field>(
+    vk: &ConstraintSystem<F>,
+    circuit: &ConstraintSystem<F>,
+) -> Option<VkMismatch> {
+    for (column_type, vk, circuit) in [
+        ("fixed", vk.num_fixed_columns, circuit.num_fixed_columns),
+        ("advice", vk.num_advice_columns, circuit.num_advice_columns),
+        (
+            "instance",
+            vk.num_instance_columns,
+            circuit.num_instance_columns,
+        ),
+    ] {
+        if vk != circuit {
+            return Some(VkMismatch::ColumnCount {
+                column_type,
+                vk,
+                circuit,
+            });
+        }
+    }
+
+    if let Some(gate) = vk
+        .gates
+        .iter()
+        .zip(circuit.gates.iter())
+        .position(|(a, b)| a.polynomials() != b.polynomials())
+    {
+        return Some(VkMismatch::Gate(gate));
+    }
+
+    if vk.pinned() != circuit.pinned() {
+        return Some(VkMismatch::ConstraintSystem);
+    }
+
+    None
+}
+
+/// The fixed data of a circuit, obtained by synthesizing it.
+struct SynthesizedFixed<F: Field> {
+    domain: EvaluationDomain<F>,
+    /// The constraint system, after selector compression.
+    cs: ConstraintSystem<F>,
+    /// The fixed columns, followed by the columns selectors were compressed into.
+    /// Columns that were never assigned a non-zero value are `None`.
+    fixed: Vec<Option<Polynomial<F, LagrangeCoeff>>>,
+    permutation: permutation::keygen::Assembly,
+    selectors: Vec<Vec<bool>>,
+    /// Whether the selectors were compressed, or converted into fixed columns
+    /// one by one.
+    compress_selectors: bool,
+}
+
+/// Synthesizes `circuit` and compresses its selectors, or converts each of
+/// them into a fixed column if `compress_selectors` is false, calling
+/// `report` as each of the two stages starts. If `instance_lengths` is given,
+/// copy constraints on instance cells beyond those lengths are rejected.
+fn synthesize_fixed<'params, C, P, ConcreteCircuit>(
     params: &P,
     circuit: &ConcreteCircuit,
-) -> Result<VerifyingKey<C>, Error>
+    instance_lengths: Option<&[usize]>,
+    compress_selectors: bool,
+    mut report: impl FnMut(KeygenStage),
+) -> Result<SynthesizedFixed<C::Scalar>, Error>
 where
     C: CurveAffine,
     P: Params<'params, C>,
     ConcreteCircuit: Circuit<C::Scalar>,
-    C::Scalar: FromUniformBytes<64>,
 {
     let (domain, cs, config) = create_domain::<C, ConcreteCircuit>(
         params.k(),
@@ -219,160 +694,1679 @@ where
         circuit.params(),
     );
 
-    if (params.n() as usize) < cs.minimum_rows() {
-        return Err(Error::not_enough_rows_available(params.k()));
-    }
-
-    let mut assembly: Assembly<C::Scalar> = Assembly {
-        k: params.k(),
-        fixed: vec![domain.empty_lagrange_assigned(); cs.num_fixed_columns],
-        permutation: permutation::keygen::Assembly::new(params.n() as usize, &cs.permutation),
-        selectors: vec![vec![false; params.n() as usize]; cs.num_selectors],
-        usable_rows: 0..params.n() as usize - (cs.blinding_factors() + 1),
-        _marker: std::marker::PhantomData,
-    };
+    let constants = cs.constants.clone();
+    assemble_fixed(
+        params,
+        domain,
+        cs,
+        instance_lengths,
+        compress_selectors,
+        report,
+        |assembly| ConcreteCircuit::FloorPlanner::synthesize(assembly, circuit, config, constants),
+    )
+}
 
-    // Synthesize the circuit to obtain URS
-    ConcreteCircuit::FloorPlanner::synthesize(
-        &mut assembly,
-        circuit,
-        config,
-        cs.constants.clone(),
-    )?;
+/// Assigns the fixed data of a circuit configured as `cs` with `assign`, then
+/// processes its selectors as [`synthesize_fixed`] does.
+fn assemble_fixed<'params, C, P>(
+    params: &P,
+    domain: EvaluationDomain<C::Scalar>,
+    cs: ConstraintSystem<C::Scalar>,
+    instance_lengths: Option<&[usize]>,
+    compress_selectors: bool,
+    mut report: impl FnMut(KeygenStage),
+    assign: impl FnOnce(&mut Assembly<C::Scalar>) -> Result<(), Error>,
+) -> Result<SynthesizedFixed<C::Scalar>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C>,
+{
+    let mut assembly = assign_fixed::<C, P>(params, &cs, instance_lengths, &mut report, assign)?;
 
-    let mut fixed = batch_invert_assigned(assembly.fixed);
-    let (cs, selector_polys) = cs.compress_selectors(assembly.selectors.clone());
+    report(KeygenStage::SelectorCompression);
+    let mut fixed = batch_invert_fixed(&domain, assembly.fixed);
+    cs.activate_selector_combinations(&mut assembly.selectors);
+    let (cs, selector_polys) = if compress_selectors {
+        cs.compress_selectors(assembly.selectors.clone())
+    } else {
+        cs.selectors_to_fixed(assembly.selectors.clone())
+    };
+    // Compressed selectors may raise the degree of the gates.
+    cs.check_extended_k(params.k())?;
     fixed.extend(
         selector_polys
             .into_iter()
-            .map(|poly| domain.lagrange_from_vec(poly)),
+            .map(|poly| Some(domain.lagrange_from_vec(poly))),
     );
 
-    let permutation_vk = assembly
-        .permutation
-        .build_vk(params, &domain, &cs.permutation);
-
-    let fixed_commitments = fixed
-        .iter()
-        .map(|poly| params.commit_lagrange(poly, Blind::default()).to_affine())
-        .collect();
-
-    Ok(VerifyingKey::from_parts(
+    Ok(SynthesizedFixed {
         domain,
-        fixed_commitments,
-        permutation_vk,
         cs,
-        assembly.selectors,
-    ))
+        fixed,
+        permutation: assembly.permutation,
+        selectors: assembly.selectors,
+        compress_selectors,
+    })
 }
 
-/// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`.
-pub fn keygen_pk<'params, C, P, ConcreteCircuit>(
+/// Checks that a circuit configured as `cs` fits in `params`, then assigns its
+/// fixed data with `assign`.
+fn assign_fixed<'params, C, P>(
     params: &P,
-    vk: VerifyingKey<C>,
-    circuit: &ConcreteCircuit,
-) -> Result<ProvingKey<C>, Error>
+    cs: &ConstraintSystem<C::Scalar>,
+    instance_lengths: Option<&[usize]>,
+    mut report: impl FnMut(KeygenStage),
+    assign: impl FnOnce(&mut Assembly<C::Scalar>) -> Result<(), Error>,
+) -> Result<Assembly<C::Scalar>, Error>
 where
     C: CurveAffine,
     P: Params<'params, C>,
-    ConcreteCircuit: Circuit<C::Scalar>,
 {
-    let mut cs = ConstraintSystem::default();
-    #[cfg(feature = "circuit-params")]
-    let config = ConcreteCircuit::configure_with_params(&mut cs, circuit.params());
-    #[cfg(not(feature = "circuit-params"))]
-    let config = ConcreteCircuit::configure(&mut cs);
+    let minimum_rows = cs.minimum_rows_breakdown();
+    if (params.n() as usize) < minimum_rows.total() {
+        return Err(Error::below_minimum_rows(params.k(), minimum_rows));
+    }
+
+    if !cs.disabled_gates.is_empty() && !cs.allow_disabled_gates {
+        return Err(Error::DisabledGates(cs.disabled_gates.clone()));
+    }
+    cs.check_column_pins()?;
 
-    let cs = cs;
+    if let Some(lengths) = instance_lengths {
+        if lengths.len() != cs.num_instance_columns {
+            return Err(Error::InvalidInstances);
+        }
+    }
 
-    if (params.n() as usize) < cs.minimum_rows() {
-        return Err(Error::not_enough_rows_available(params.k()));
+    let n = params.n() as usize;
+    let usable_rows = cs.usable_rows(params.k());
+    let mut fixed = vec![FixedColumn::Empty; cs.num_fixed_columns];
+    let mut preassigned = vec![false; cs.num_fixed_columns];
+    for (column, values) in &cs.preassigned_fixed {
+        if values.len() > usable_rows.end {
+            return Err(Error::not_enough_rows_available_at(
+                params.k(),
+                None,
+                Some((*column).into()),
+                values.len() - 1,
+            ));
+        }
+        let dense = fixed[column.index()].dense_mut(n);
+        for (cell, value) in dense.iter_mut().zip(values) {
+            *cell = Assigned::from(*value);
+        }
+        preassigned[column.index()] = true;
     }
 
     let mut assembly: Assembly<C::Scalar> = Assembly {
         k: params.k(),
-        fixed: vec![vk.domain.empty_lagrange_assigned(); cs.num_fixed_columns],
-        permutation: permutation::keygen::Assembly::new(params.n() as usize, &cs.permutation),
-        selectors: vec![vec![false; params.n() as usize]; cs.num_selectors],
-        usable_rows: 0..params.n() as usize - (cs.blinding_factors() + 1),
+        fixed,
+        preassigned,
+        permutation: permutation::keygen::Assembly::new(n, &cs.permutation),
+        selectors: vec![vec![false; n]; cs.num_selectors],
+        usable_rows,
+        current_region: None,
+        instance_lengths: instance_lengths.map(|lengths| lengths.to_vec()),
         _marker: std::marker::PhantomData,
     };
 
     // Synthesize the circuit to obtain URS
-    ConcreteCircuit::FloorPlanner::synthesize(
-        &mut assembly,
+    report(KeygenStage::Synthesis);
+    assign(&mut assembly)?;
+
+    Ok(assembly)
+}
+
+/// Generate a `VerifyingKey` from an instance of `Circuit`.
+pub fn keygen_vk<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    keygen_vk_with_progress(params, circuit, |_, _| {})
+}
+
+/// Generate a `VerifyingKey` from an instance of `Circuit`, reporting progress
+/// to `cb`.
+///
+/// `cb` receives the current [`KeygenStage`] and the fraction of the whole
+/// call completed so far, reaching `1.0` once the key is built. It is only
+/// ever called from the calling thread.
+pub fn keygen_vk_with_progress<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+    cb: impl FnMut(KeygenStage, f32),
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    keygen_vk_impl(params, circuit, None, true, &CpuEngine, cb)
+}
+
+/// Generate a `VerifyingKey` from an instance of `Circuit`, converting each
+/// selector into a fixed column of its own if `compress_selectors` is false.
+///
+/// Without compression, the circuit has more fixed columns, but its gates
+/// keep the degree they were configured with, and the fixed columns are those
+/// returned by [`ConstraintSystem::selectors_to_fixed`], which tools outside
+/// of this crate can reproduce. The choice is stored in the verifying key, and
+/// [`keygen_pk`] makes the same one.
+pub fn keygen_vk_custom<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+    compress_selectors: bool,
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    keygen_vk_impl(
+        params,
         circuit,
-        config,
-        cs.constants.clone(),
-    )?;
+        None,
+        compress_selectors,
+        &CpuEngine,
+        |_, _| {},
+    )
+}
 
-    let mut fixed = batch_invert_assigned(assembly.fixed);
-    let (cs, selector_polys) = cs.compress_selectors(assembly.selectors);
-    fixed.extend(
-        selector_polys
-            .into_iter()
-            .map(|poly| vk.domain.lagrange_from_vec(poly)),
-    );
+/// Generate a `VerifyingKey` from an instance of `Circuit`, checking copy
+/// constraints on instance columns against the number of public inputs that
+/// will be provided.
+///
+/// `instance_lengths` holds the expected length of each instance column. A copy
+/// constraint on an instance cell at or beyond that length fails with
+/// [`Error::InstanceCopyOutOfBounds`], rather than only once proofs are
+/// created or verified.
+pub fn keygen_vk_with_instance_lengths<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+    instance_lengths: &[usize],
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    keygen_vk_impl(
+        params,
+        circuit,
+        Some(instance_lengths),
+        true,
+        &CpuEngine,
+        |_, _| {},
+    )
+}
 
-    let fixed_polys: Vec<_> = fixed
-        .iter()
-        .map(|poly| vk.domain.lagrange_to_coeff(poly.clone()))
-        .collect();
+/// Generate a `VerifyingKey` from an instance of `Circuit`, performing the
+/// multiexponentiations of the fixed and permutation commitments with `engine`.
+pub fn keygen_vk_with_engine<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+    engine: &dyn Engine<C>,
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    keygen_vk_impl(params, circuit, None, true, engine, |_, _| {})
+}
 
-    let fixed_cosets = fixed_polys
-        .iter()
-        .map(|poly| vk.domain.coeff_to_extended(poly.clone()))
-        .collect();
+fn keygen_vk_impl<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+    instance_lengths: Option<&[usize]>,
+    compress_selectors: bool,
+    engine: &dyn Engine<C>,
+    cb: impl FnMut(KeygenStage, f32),
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    let mut progress = Progress {
+        stages: VK_STAGES,
+        cb,
+    };
 
-    let permutation_pk = assembly
-        .permutation
-        .build_pk(params, &vk.domain, &cs.permutation);
-
-    // Compute l_0(X)
-    // TODO: this can be done more efficiently
-    let mut l0 = vk.domain.empty_lagrange();
-    l0[0] = C::Scalar::ONE;
-    let l0 = vk.domain.lagrange_to_coeff(l0);
-    let l0 = vk.domain.coeff_to_extended(l0);
-
-    // Compute l_blind(X) which evaluates to 1 for each blinding factor row
-    // and 0 otherwise over the domain.
-    let mut l_blind = vk.domain.empty_lagrange();
-    for evaluation in l_blind[..].iter_mut().rev().take(cs.blinding_factors()) {
-        *evaluation = C::Scalar::ONE;
-    }
-    let l_blind = vk.domain.lagrange_to_coeff(l_blind);
-    let l_blind = vk.domain.coeff_to_extended(l_blind);
-
-    // Compute l_last(X) which evaluates to 1 on the first inactive row (just
-    // before the blinding factors) and 0 otherwise over the domain
-    let mut l_last = vk.domain.empty_lagrange();
-    l_last[params.n() as usize - cs.blinding_factors() - 1] = C::Scalar::ONE;
-    let l_last = vk.domain.lagrange_to_coeff(l_last);
-    let l_last = vk.domain.coeff_to_extended(l_last);
-
-    // Compute l_active_row(X)
-    let one = C::Scalar::ONE;
-    let mut l_active_row = vk.domain.empty_extended();
-    parallelize(&mut l_active_row, |values, start| {
-        for (i, value) in values.iter_mut().enumerate() {
-            let idx = i + start;
-            *value = one - (l_last[idx] + l_blind[idx]);
-        }
-    });
+    let synthesized = synthesize_fixed(
+        params,
+        circuit,
+        instance_lengths,
+        compress_selectors,
+        |stage| progress.report(stage, 0.0),
+    )?;
 
-    // Compute the optimized evaluation data structure
-    let ev = Evaluator::new(&vk.cs);
+    Ok(commit_synthesized_fixed(
+        params,
+        engine,
+        synthesized,
+        &mut progress,
+    ))
+}
 
-    Ok(ProvingKey {
+/// Commits to the fixed data of a circuit and builds its verifying key.
+fn commit_synthesized_fixed<'params, C, P, CB>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    synthesized: SynthesizedFixed<C::Scalar>,
+    progress: &mut Progress<CB>,
+) -> VerifyingKey<C>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    CB: FnMut(KeygenStage, f32),
+    C::Scalar: FromUniformBytes<64>,
+{
+    let SynthesizedFixed {
+        domain,
+        cs,
+        fixed,
+        permutation: permutation_assembly,
+        selectors,
+        compress_selectors,
+    } = synthesized;
+
+    progress.report(KeygenStage::FixedCommitments, 0.0);
+    let fixed_commitments = commit_fixed(params, engine, &domain, &fixed, |done| {
+        progress.report(KeygenStage::FixedCommitments, done)
+    });
+
+    progress.report(KeygenStage::PermutationBuild, 0.0);
+    let permutation_vk = permutation_assembly.build_vk(params, engine, &domain, &cs.permutation);
+    progress.report(KeygenStage::PermutationBuild, 1.0);
+
+    VerifyingKey::from_parts(
+        domain,
+        fixed_commitments,
+        permutation_vk,
+        cs,
+        selectors,
+        compress_selectors,
+    )
+}
+
+/// Generate a `VerifyingKey` from the parameters of `ConcreteCircuit` alone,
+/// without an instance of the circuit.
+///
+/// The fixed data is given by [`Circuit::fixed_assignments`] instead of
+/// synthesizing the circuit, so the key can be generated wherever no witness
+/// is at hand, such as in a build script. The key is the one [`keygen_vk`]
+/// generates from an instance of the circuit with these parameters, as long
+/// as `fixed_assignments` matches what `synthesize` assigns. Fails with
+/// [`Error::FixedAssignmentsUnavailable`] if the circuit does not implement
+/// `fixed_assignments`.
+#[cfg(feature = "circuit-params")]
+pub fn keygen_vk_from_params<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit_params: ConcreteCircuit::Params,
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    ConcreteCircuit::Params: Clone,
+    C::Scalar: FromUniformBytes<64>,
+{
+    let (domain, cs, config) =
+        create_domain::<C, ConcreteCircuit>(params.k(), circuit_params.clone());
+    let assignments = ConcreteCircuit::fixed_assignments(&config, &circuit_params)
+        .ok_or(Error::FixedAssignmentsUnavailable)?;
+
+    let mut progress = Progress {
+        stages: VK_STAGES,
+        cb: |_: KeygenStage, _: f32| {},
+    };
+    let synthesized = assemble_fixed(
+        params,
+        domain,
+        cs,
+        None,
+        true,
+        |stage| progress.report(stage, 0.0),
+        |assembly| assignments.assign(assembly),
+    )?;
+
+    Ok(commit_synthesized_fixed(
+        params,
+        &CpuEngine,
+        synthesized,
+        &mut progress,
+    ))
+}
+
+/// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`.
+///
+/// The circuit is checked against `vk`: its `k`, constraint system, fixed
+/// columns and copy constraints must be those `vk` was generated for, or
+/// [`Error::VerifyingKeyMismatch`] describes the first difference found. Use
+/// [`keygen_pk_unchecked`] to skip the checks.
+pub fn keygen_pk<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk: VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    keygen_pk_with_progress(params, vk, circuit, |_, _| {})
+}
+
+/// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`,
+/// reporting progress to `cb`.
+///
+/// See [`keygen_vk_with_progress`] for how progress is reported.
+pub fn keygen_pk_with_progress<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk: VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+    cb: impl FnMut(KeygenStage, f32),
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    keygen_pk_impl(params, vk, circuit, None, true, &CpuEngine, cb)
+}
+
+/// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`,
+/// checking copy constraints on instance columns as described in
+/// [`keygen_vk_with_instance_lengths`].
+pub fn keygen_pk_with_instance_lengths<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk: VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+    instance_lengths: &[usize],
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    keygen_pk_impl(
+        params,
+        vk,
+        circuit,
+        Some(instance_lengths),
+        true,
+        &CpuEngine,
+        |_, _| {},
+    )
+}
+
+/// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`,
+/// without checking that the verifying key was generated for this circuit.
+///
+/// [`keygen_pk`] returns [`Error::VerifyingKeyMismatch`] when the circuit does not
+/// match `vk`; this skips those checks, along with committing to the fixed
+/// columns. A mismatched `vk` results in a proving key whose proofs do not
+/// verify.
+pub fn keygen_pk_unchecked<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk: VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    keygen_pk_impl(params, vk, circuit, None, false, &CpuEngine, |_, _| {})
+}
+
+/// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`,
+/// performing the multiexponentiations and FFTs of the fixed and permutation
+/// columns with `engine`.
+pub fn keygen_pk_with_engine<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk: VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+    engine: &dyn Engine<C>,
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    keygen_pk_impl(params, vk, circuit, None, true, engine, |_, _| {})
+}
+
+fn keygen_pk_impl<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk: VerifyingKey<C>,
+    circuit: &ConcreteCircuit,
+    instance_lengths: Option<&[usize]>,
+    check_vk: bool,
+    engine: &dyn Engine<C>,
+    cb: impl FnMut(KeygenStage, f32),
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let mut progress = Progress {
+        stages: PK_STAGES,
+        cb,
+    };
+
+    if check_vk && vk.domain.k() != params.k() {
+        return Err(Error::VerifyingKeyMismatch(VkMismatch::K {
+            vk: vk.domain.k(),
+            circuit: params.k(),
+        }));
+    }
+
+    let SynthesizedFixed {
+        domain,
+        cs,
+        fixed,
+        permutation: permutation_assembly,
+        ..
+    } = synthesize_fixed(
+        params,
+        circuit,
+        instance_lengths,
+        vk.compress_selectors,
+        |stage| progress.report(stage, 0.0),
+    )?;
+    // The vk owns the only copy of the constraint system kept around; this one
+    // is just for the comparison.
+    if check_vk {
+        if let Some(mismatch) = compare_constraint_systems(&vk.cs, &cs) {
+            return Err(Error::VerifyingKeyMismatch(mismatch));
+        }
+    }
+    drop(cs);
+
+    // The vk already commits to the fixed columns, so check the synthesized
+    // values against its commitments rather than trusting that the circuit
+    // matches.
+    progress.report(KeygenStage::FixedCommitments, 0.0);
+    if check_vk {
+        if let Some(column) = mismatched_fixed_column(params, engine, &domain, &fixed, &vk) {
+            return Err(Error::VerifyingKeyMismatch(VkMismatch::FixedCommitment(
+                column,
+            )));
+        }
+        progress.report(KeygenStage::FixedCommitments, 1.0);
+    }
+
+    progress.report(KeygenStage::PermutationBuild, 0.0);
+    let permutation_pk =
+        permutation_assembly.build_pk(params, engine, &vk.domain, &vk.cs.permutation);
+    // The copy constraints are not covered by the checks above, so check the
+    // permutation polynomials against the commitments of the vk as well.
+    if check_vk {
+        if let Some(column) =
+            mismatched_permutation_column(params, engine, &domain, &permutation_pk, &vk)
+        {
+            return Err(Error::VerifyingKeyMismatch(VkMismatch::Permutation(column)));
+        }
+    }
+
+    Ok(ProvingKey::from_parts_with_progress(
         vk,
-        l0,
-        l_last,
-        l_active_row,
-        fixed_values: fixed,
-        fixed_polys,
-        fixed_cosets,
-        permutation: permutation_pk,
-        ev,
+        densify_fixed(&domain, fixed),
+        permutation_pk,
+        engine,
+        |stage, done| progress.report(stage, done),
+    ))
+}
+
+/// Generate a `ProvingKey` from the serialized bytes of a `VerifyingKey` and an
+/// instance of `Circuit`.
+///
+/// The verifying key is read with [`VerifyingKey::from_bytes`] and then checked
+/// against `circuit` as in [`keygen_pk`], so a circuit that differs from the one
+/// the key was generated for is rejected with [`Error::VerifyingKeyMismatch`].
+pub fn keygen_pk_from_vk_bytes<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    vk_bytes: &[u8],
+    format: SerdeFormat,
+    circuit: &ConcreteCircuit,
+) -> Result<ProvingKey<C>, Error>
+where
+    C: SerdeCurveAffine,
+    C::Scalar: SerdePrimeField + FromUniformBytes<64>,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let vk = VerifyingKey::from_bytes::<ConcreteCircuit>(
+        vk_bytes,
+        format,
+        #[cfg(feature = "circuit-params")]
+        circuit.params(),
+    )?;
+    keygen_pk(params, vk, circuit)
+}
+
+/// Generate proving keys for a family of circuits that configure the same
+/// constraint system, such as instances of one circuit that only differ in the
+/// contents of their fixed columns.
+///
+/// Each circuit is synthesized and committed to on its own, but the selectors
+/// are only compressed for the first one, and the domain, the constraint
+/// system, the helper polynomials and the evaluator are shared by all of the
+/// returned keys. Returns [`Error::VerifyingKeyMismatch`] if a circuit does not
+/// configure the same constraint system as the first one, or enables
+/// selectors on the same rows that the first one let share a fixed column.
+pub fn keygen_pk_batch<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuits: &[ConcreteCircuit],
+) -> Result<Vec<ProvingKey<C>>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    let configure = |circuit: &ConcreteCircuit| {
+        let mut cs = ConstraintSystem::default();
+        #[cfg(feature = "circuit-params")]
+        let config = ConcreteCircuit::configure_with_params(&mut cs, circuit.params());
+        #[cfg(not(feature = "circuit-params"))]
+        let config = {
+            let _ = circuit;
+            ConcreteCircuit::configure(&mut cs)
+        };
+        (cs, config)
+    };
+
+    let first = match circuits.first() {
+        Some(first) => first,
+        None => return Ok(vec![]),
+    };
+    let (vk, fixed, permutation_pk) =
+        build_keys(params, synthesize_fixed(params, first, None, true, |_| {})?);
+    let first = ProvingKey::from_parts(vk, fixed, permutation_pk);
+
+    // The other circuits are checked against the constraint system the first
+    // one configures, before its selectors were compressed.
+    let (configured, _) = configure(&circuits[0]);
+    let domain = first.vk.domain.clone();
+    let cs = first.vk.cs.clone();
+
+    let mut pks = Vec::with_capacity(circuits.len());
+    for circuit in &circuits[1..] {
+        let (circuit_cs, config) = configure(circuit);
+        if let Some(mismatch) = compare_constraint_systems(&configured, &circuit_cs) {
+            return Err(Error::VerifyingKeyMismatch(mismatch));
+        }
+
+        let constants = circuit_cs.constants.clone();
+        let assembly = assign_fixed::<C, P>(
+            params,
+            &circuit_cs,
+            None,
+            |_| {},
+            |assembly| {
+                ConcreteCircuit::FloorPlanner::synthesize(assembly, circuit, config, constants)
+            },
+        )?;
+        let selector_polys = cs
+            .selector_polys(assembly.selectors.clone())
+            .ok_or(Error::VerifyingKeyMismatch(VkMismatch::ConstraintSystem))?;
+        let mut fixed = batch_invert_fixed(&domain, assembly.fixed);
+        fixed.extend(
+            selector_polys
+                .into_iter()
+                .map(|poly| Some(domain.lagrange_from_vec(poly))),
+        );
+        let mut selectors = assembly.selectors;
+        cs.activate_selector_combinations(&mut selectors);
+
+        let fixed_commitments = commit_fixed(params, &CpuEngine, &domain, &fixed, |_| {});
+        let permutation_pk =
+            assembly
+                .permutation
+                .build_pk(params, &CpuEngine, &domain, &cs.permutation);
+        let permutation_vk = permutation_pk.build_vk(params);
+        let vk = VerifyingKey::from_shared_parts(
+            domain.clone(),
+            fixed_commitments,
+            permutation_vk,
+            cs.clone(),
+            selectors,
+            true,
+        );
+        pks.push(ProvingKey::from_parts_sharing(
+            vk,
+            densify_fixed(&domain, fixed),
+            permutation_pk,
+            &first,
+        ));
+    }
+    pks.insert(0, first);
+
+    Ok(pks)
+}
+
+/// Builds the verifying key of a synthesized circuit, along with the parts of
+/// its proving key that depend on the circuit's assignments.
+fn build_keys<'params, C, P>(
+    params: &P,
+    synthesized: SynthesizedFixed<C::Scalar>,
+) -> (
+    VerifyingKey<C>,
+    Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    permutation::ProvingKey<C>,
+)
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    C::Scalar: FromUniformBytes<64>,
+{
+    let SynthesizedFixed {
+        domain,
+        cs,
+        fixed,
+        permutation: permutation_assembly,
+        selectors,
+        compress_selectors,
+    } = synthesized;
+
+    let fixed_commitments = commit_fixed(params, &CpuEngine, &domain, &fixed, |_| {});
+    let permutation_pk =
+        permutation_assembly.build_pk(params, &CpuEngine, &domain, &cs.permutation);
+    let permutation_vk = permutation_pk.build_vk(params);
+    let fixed = densify_fixed(&domain, fixed);
+    let vk = VerifyingKey::from_parts(
+        domain,
+        fixed_commitments,
+        permutation_vk,
+        cs,
+        selectors,
+        compress_selectors,
+    );
+
+    (vk, fixed, permutation_pk)
+}
+
+/// Generate a `ProvingKey` for `circuit`, reusing the parts of `old_pk` that
+/// do not depend on what changed in the circuit since `old_pk` was generated.
+///
+/// Only the fixed columns whose values changed are converted and committed to
+/// again, and likewise for the columns of the permutation argument. If the
+/// circuit no longer configures the same constraint system as `old_pk`, or
+/// `params` are for a different `k`, the keys are generated from scratch.
+pub fn keygen_pk_incremental<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    old_pk: &ProvingKey<C>,
+    circuit: &ConcreteCircuit,
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    P: Params<'params, C> + Sync,
+    ConcreteCircuit: Circuit<C::Scalar>,
+    C::Scalar: FromUniformBytes<64>,
+{
+    let synthesized =
+        synthesize_fixed(params, circuit, None, old_pk.vk.compress_selectors, |_| {})?;
+    let old_vk = &old_pk.vk;
+    if synthesized.domain.k() != old_vk.domain.k()
+        || compare_constraint_systems(&old_vk.cs, &synthesized.cs).is_some()
+    {
+        let (vk, fixed, permutation_pk) = build_keys(params, synthesized);
+        return Ok(ProvingKey::from_parts(vk, fixed, permutation_pk));
+    }
+
+    let SynthesizedFixed {
+        domain,
+        cs,
+        fixed,
+        permutation: permutation_assembly,
+        selectors,
+        compress_selectors,
+    } = synthesized;
+
+    let fixed = densify_fixed(&domain, fixed);
+    let fixed_commitments = (0..fixed.len())
+        .into_par_iter()
+        .map(|i| {
+            if fixed[i][..] == old_pk.fixed_values[i][..] {
+                old_vk.fixed_commitments[i]
+            } else {
+                params
+                    .commit_lagrange_with_engine(&CpuEngine, &fixed[i], Blind::default())
+                    .to_affine()
+            }
+        })
+        .collect();
+
+    let (permutation_pk, permutation_vk) = permutation_assembly.build_keys_reusing(
+        params,
+        &CpuEngine,
+        &domain,
+        &cs.permutation,
+        &old_pk.permutation,
+        &old_vk.permutation,
+    );
+    let vk = VerifyingKey::from_parts(
+        domain,
+        fixed_commitments,
+        permutation_vk,
+        cs,
+        selectors,
+        compress_selectors,
+    );
+
+    Ok(ProvingKey::from_parts_reusing(
+        vk,
+        fixed,
+        permutation_pk,
+        old_pk,
+    ))
+}
+
+#[test]
+fn test_parallel_fixed_commitments() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner};
+
+    const NUM_FIXED: usize = 8;
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = Vec<Column<Fixed>>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            (0..NUM_FIXED).map(|_| meta.fixed_column()).collect()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "fixed",
+                |mut region| {
+                    for (i, column) in config.iter().enumerate() {
+                        for row in 0..4 {
+                            region.assign_fixed(
+                                || "fixed",
+                                *column,
+                                row,
+                                || Value::known(F::from((i * 4 + row) as u64 + 1)),
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    // The parallel computation must match the sequential one column by column.
+    let commitments: Vec<_> = pk
+        .fixed_values
+        .iter()
+        .map(|poly| params.commit_lagrange(poly, Blind::default()).to_affine())
+        .collect();
+    assert_eq!(commitments.len(), NUM_FIXED);
+    assert_eq!(pk.vk.fixed_commitments, commitments);
+
+    for (poly, values) in pk.fixed_polys.iter().zip(pk.fixed_values.iter()) {
+        let expected = pk.vk.domain.lagrange_to_coeff(values.clone());
+        assert_eq!(&poly[..], &expected[..]);
+    }
+}
+
+#[test]
+fn test_keygen_pk_vk_mismatch() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner};
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit(u64);
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = [Column<Fixed>; 2];
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            [meta.fixed_column(), meta.fixed_column()]
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "fixed",
+                |mut region| {
+                    region.assign_fixed(|| "a", config[0], 0, || Value::known(F::ONE))?;
+                    region.assign_fixed(|| "b", config[1], 0, || Value::known(F::from(self.0)))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit(1)).expect("keygen_vk should not fail");
+
+    assert!(matches!(
+        keygen_pk(&params, vk.clone(), &MyCircuit(2)),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::FixedCommitment(1)))
+    ));
+    keygen_pk(&params, vk.clone(), &MyCircuit(1)).expect("keygen_pk should not fail");
+
+    // The same checks apply when starting from a serialized vk.
+    let vk_bytes = vk.to_bytes(SerdeFormat::RawBytes);
+    assert!(matches!(
+        keygen_pk_from_vk_bytes(&params, &vk_bytes, SerdeFormat::RawBytes, &MyCircuit(2)),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::FixedCommitment(1)))
+    ));
+    keygen_pk_from_vk_bytes(&params, &vk_bytes, SerdeFormat::RawBytes, &MyCircuit(1))
+        .expect("keygen_pk_from_vk_bytes should not fail");
+}
+
+#[test]
+fn test_keygen_progress() {
+    use crate::circuit::SimpleFloorPlanner;
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            meta.fixed_column();
+        }
+
+        fn synthesize(
+            &self,
+            _config: Self::Config,
+            _layouter: impl crate::circuit::Layouter<F>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn check(events: &[(KeygenStage, f32)], stages: &[KeygenStage]) {
+        let mut seen: Vec<KeygenStage> = events.iter().map(|(stage, _)| *stage).collect();
+        seen.dedup();
+        assert_eq!(seen, stages);
+        assert!(events.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(events.last().unwrap().1, 1.0);
+    }
+
+    let params = test_fixtures::params(3);
+
+    let mut events = vec![];
+    let vk = keygen_vk_with_progress(&params, &MyCircuit, |stage, done| {
+        events.push((stage, done))
     })
+    .expect("keygen_vk should not fail");
+    check(&events, VK_STAGES);
+
+    let mut events = vec![];
+    keygen_pk_with_progress(&params, vk, &MyCircuit, |stage, done| {
+        events.push((stage, done))
+    })
+    .expect("keygen_pk should not fail");
+    check(&events, PK_STAGES);
+}
+
+#[test]
+fn test_keygen_pk_batch() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        poly::Rotation,
+    };
+    use std::sync::Arc;
+
+    /// Fills a table with multiples of the first field, and enables two
+    /// selectors on the rows given by the second.
+    #[derive(Clone, Copy)]
+    struct MyCircuit(u64, [usize; 2]);
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = (Column<Fixed>, [Selector; 2]);
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let fixed = meta.fixed_column();
+            meta.enable_equality(fixed);
+            let selectors = [meta.selector(), meta.selector()];
+            meta.create_gate("selected", |meta| {
+                let fixed = meta.query_fixed(fixed, Rotation::cur());
+                selectors.map(|selector| meta.query_selector(selector) * fixed.clone())
+            });
+            (fixed, selectors)
+        }
+
+        fn synthesize(
+            &self,
+            (fixed, selectors): Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "table",
+                |mut region| {
+                    for row in 0..4 {
+                        region.assign_fixed(
+                            || "entry",
+                            fixed,
+                            row,
+                            || Value::known(F::from(self.0 * row as u64)),
+                        )?;
+                    }
+                    for (selector, row) in selectors.iter().zip(self.1) {
+                        selector.enable(&mut region, row)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let circuits = [
+        MyCircuit(1, [0, 1]),
+        MyCircuit(2, [1, 0]),
+        MyCircuit(3, [2, 3]),
+    ];
+    let pks = keygen_pk_batch(&params, &circuits).expect("keygen_pk_batch should not fail");
+    assert_eq!(pks.len(), circuits.len());
+    // Both selectors share a column.
+    assert_eq!(pks[0].vk.cs.num_fixed_columns, 2);
+
+    for (pk, circuit) in pks.iter().zip(circuits.iter()) {
+        let vk = keygen_vk(&params, circuit).expect("keygen_vk should not fail");
+        assert_eq!(pk.vk.transcript_repr(), vk.transcript_repr());
+        let single = keygen_pk(&params, vk, circuit).expect("keygen_pk should not fail");
+        for (batch, single) in pk.fixed_values.iter().zip(single.fixed_values.iter()) {
+            assert_eq!(batch[..], single[..]);
+        }
+        let (cosets, single) = (pk.cosets.as_ref().unwrap(), single.cosets.unwrap());
+        assert_eq!(&cosets.l_active_row[..], &single.l_active_row[..]);
+        assert!(Arc::ptr_eq(&cosets.l0, &pks[0].cosets.as_ref().unwrap().l0));
+        assert!(Arc::ptr_eq(&pk.ev, &pks[0].ev));
+        assert!(Arc::ptr_eq(&pk.vk.cs, &pks[0].vk.cs));
+        assert!(Arc::ptr_eq(&pk.vk.domain, &pks[0].vk.domain));
+    }
+
+    // Selectors sharing a column in the first circuit cannot be enabled on the
+    // same row in the others.
+    assert!(matches!(
+        keygen_pk_batch(&params, &[MyCircuit(1, [0, 1]), MyCircuit(2, [0, 0])]),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::ConstraintSystem))
+    ));
+}
+
+#[test]
+fn test_region_past_usable_rows() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner};
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = Column<Fixed>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            meta.fixed_column()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "too tall",
+                |mut region| {
+                    for row in 0..16 {
+                        region.assign_fixed(|| "fixed", config, row, || Value::known(F::ONE))?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    assert!(matches!(
+        keygen_vk(&params, &MyCircuit).unwrap_err(),
+        Error::NotEnoughRowsAvailable { current_k: 4 }
+    ));
+}
+
+#[test]
+fn test_below_minimum_rows() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        poly::Rotation,
+    };
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = meta.advice_column();
+            meta.create_gate("far ahead", |meta| {
+                let cur = meta.query_advice(a, Rotation::cur());
+                let ahead = meta.query_advice(a, Rotation(20));
+                vec![cur - ahead]
+            });
+        }
+
+        fn synthesize(&self, _: Self::Config, _: impl Layouter<F>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    // The gate spans 21 rows, more than the 16 rows of k = 4.
+    let params = test_fixtures::params(4);
+    assert!(matches!(
+        keygen_vk(&params, &MyCircuit).unwrap_err(),
+        Error::NotEnoughRowsAvailable { current_k: 4 }
+    ));
+    let mut cs = ConstraintSystem::<halo2curves::bn256::Fr>::default();
+    <MyCircuit as Circuit<_>>::configure(&mut cs);
+    let minimum_rows = cs.minimum_rows_breakdown();
+    assert_eq!(
+        (minimum_rows.rotation_span(), minimum_rows.total()),
+        (21, 21)
+    );
+    assert!(minimum_rows
+        .to_string()
+        .contains("21 rows for the rotations from 0 to 20 queried"));
+
+    let params = test_fixtures::params(5);
+    assert!(keygen_vk(&params, &MyCircuit).is_ok());
+}
+
+#[test]
+fn test_instance_copy_out_of_bounds() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner};
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = (Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(advice);
+            meta.enable_equality(instance);
+            (advice, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (advice, instance): Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "load public input",
+                |mut region| {
+                    region.assign_advice_from_instance(|| "input", instance, 5, advice, 0)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(5);
+
+    let err = keygen_vk_with_instance_lengths(&params, &MyCircuit, &[2]).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::InstanceCopyOutOfBounds {
+            region: Some(ref region),
+            row: 5,
+            length: 2,
+            ..
+        } if region == "load public input"
+    ));
+    assert!(err.to_string().contains("region 'load public input'"));
+
+    assert!(matches!(
+        keygen_vk_with_instance_lengths(&params, &MyCircuit, &[2, 2]),
+        Err(Error::InvalidInstances)
+    ));
+
+    let vk = keygen_vk_with_instance_lengths(&params, &MyCircuit, &[6]).unwrap();
+    assert!(matches!(
+        keygen_pk_with_instance_lengths(&params, vk.clone(), &MyCircuit, &[2]),
+        Err(Error::InstanceCopyOutOfBounds { .. })
+    ));
+    keygen_pk_with_instance_lengths(&params, vk, &MyCircuit, &[6]).unwrap();
+
+    // Without bounds, keygen behaves as before.
+    let vk = keygen_vk(&params, &MyCircuit).unwrap();
+    keygen_pk(&params, vk, &MyCircuit).unwrap();
+}
+
+#[test]
+fn test_lazy_fixed_columns() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2curves::bn256::Fr;
+
+    const NUM_FIXED: usize = 16;
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = Vec<Column<Fixed>>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            (0..NUM_FIXED).map(|_| meta.fixed_column()).collect()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "sparse",
+                |mut region| {
+                    region.assign_fixed(|| "value", config[3], 1, || Value::known(F::ONE))?;
+                    region.assign_fixed(|| "zero", config[5], 2, || Value::known(F::ZERO))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+
+    // Only the column holding a non-zero value is allocated.
+    let synthesized =
+        synthesize_fixed::<_, _, MyCircuit>(&params, &MyCircuit, None, true, |_| {}).unwrap();
+    let dense: Vec<_> = synthesized
+        .fixed
+        .iter()
+        .map(|column| column.is_some())
+        .collect();
+    assert_eq!(dense, (0..NUM_FIXED).map(|i| i == 3).collect::<Vec<_>>());
+
+    // The commitments must be the ones of the fully materialized columns.
+    let vk = keygen_vk(&params, &MyCircuit).unwrap();
+    let pk = keygen_pk(&params, vk, &MyCircuit).unwrap();
+    let expected: Vec<_> = pk
+        .fixed_values
+        .iter()
+        .map(|poly| params.commit_lagrange(poly, Blind::default()).to_affine())
+        .collect();
+    assert_eq!(pk.vk.fixed_commitments, expected);
+    for (i, poly) in pk.fixed_values.iter().enumerate() {
+        let expected_one = usize::from(i == 3);
+        assert_eq!(
+            poly.iter().filter(|value| **value != Fr::ZERO).count(),
+            expected_one
+        );
+    }
+}
+
+#[test]
+fn test_create_domain_from_cs() {
+    use crate::circuit::{Layouter, SimpleFloorPlanner};
+    use crate::poly::Rotation;
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = Column<Advice>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = meta.advice_column();
+            meta.create_gate("cube", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![a.clone() * a.clone() * a]
+            });
+            a
+        }
+
+        fn synthesize(&self, _: Self::Config, _: impl Layouter<F>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    let (domain, cs, _) = create_domain::<G1Affine, MyCircuit>(
+        5,
+        #[cfg(feature = "circuit-params")]
+        (),
+    );
+
+    let mut configured = ConstraintSystem::<Fr>::default();
+    let config = MyCircuit::configure(&mut configured);
+    let (domain_from_cs, cs_from_cs, config_from_cs) =
+        create_domain_from_cs::<G1Affine, _>(5, configured, config);
+
+    assert_eq!(config_from_cs, config);
+    assert_eq!(domain_from_cs.k(), domain.k());
+    assert_eq!(domain_from_cs.extended_k(), domain.extended_k());
+    assert_eq!(
+        format!("{:?}", cs_from_cs.pinned()),
+        format!("{:?}", cs.pinned())
+    );
+}
+
+#[test]
+fn test_lagrange_helpers_shared() {
+    use crate::helpers::write_polynomial_slice;
+    use halo2curves::bn256::G1Affine;
+    use std::sync::Arc;
+    use test_fixtures::FixedCircuit;
+
+    let params = test_fixtures::params(5);
+    let vk = keygen_vk(&params, &FixedCircuit(1)).unwrap();
+    let pk = keygen_pk(&params, vk.clone(), &FixedCircuit(1)).unwrap();
+
+    let [l0, l_last, l_active_row] = ProvingKey::<G1Affine>::compute_lagrange_helpers(
+        &pk.vk.domain,
+        pk.vk.cs.blinding_factors(),
+    );
+    let cosets = pk.cosets.as_ref().unwrap();
+    assert_eq!(&cosets.l0[..], &l0[..]);
+    assert_eq!(&cosets.l_last[..], &l_last[..]);
+    assert_eq!(&cosets.l_active_row[..], &l_active_row[..]);
+
+    // While `pk` is alive, other keys with the same parameters reuse its
+    // helper polynomials.
+    let other = keygen_pk(&params, vk, &FixedCircuit(1)).unwrap();
+    let other = other.cosets.as_ref().unwrap();
+    assert!(Arc::ptr_eq(&cosets.l0, &other.l0));
+    assert!(Arc::ptr_eq(&cosets.l_last, &other.l_last));
+    assert!(Arc::ptr_eq(&cosets.l_active_row, &other.l_active_row));
+
+    // The helper polynomials are not serialized, but recovered on read.
+    let bytes = pk.to_bytes(SerdeFormat::RawBytes);
+    assert_eq!(bytes.len(), pk.bytes_length());
+    let read = ProvingKey::<G1Affine>::read::<_, FixedCircuit>(
+        &mut &bytes[..],
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .unwrap();
+    assert!(Arc::ptr_eq(&cosets.l0, &read.cosets.as_ref().unwrap().l0));
+    assert_eq!(read.to_bytes(SerdeFormat::RawBytes), bytes);
+
+    // Keys written before the layout was versioned store the helper
+    // polynomials and the cosets, which are skipped and recomputed.
+    let vk_bytes = pk.vk.to_bytes(SerdeFormat::RawBytes);
+    let mut legacy = [&vk_bytes[1..5], &vk_bytes[6..vk_bytes.len() - 1]].concat();
+    let format = SerdeFormat::RawBytes;
+    for poly in [&cosets.l0, &cosets.l_last, &cosets.l_active_row] {
+        poly.write(&mut legacy, format).unwrap();
+    }
+    write_polynomial_slice(&pk.fixed_values, &mut legacy, format).unwrap();
+    write_polynomial_slice(&pk.fixed_polys, &mut legacy, format).unwrap();
+    write_polynomial_slice(&cosets.fixed, &mut legacy, format).unwrap();
+    write_polynomial_slice(pk.permutation.permutations(), &mut legacy, format).unwrap();
+    write_polynomial_slice(pk.permutation.polys(), &mut legacy, format).unwrap();
+    write_polynomial_slice(&cosets.permutation, &mut legacy, format).unwrap();
+    let read = ProvingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+        &legacy,
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .unwrap();
+    assert_eq!(read.to_bytes(SerdeFormat::RawBytes), bytes);
+
+    // Keys written in another layout are rejected.
+    assert_eq!(bytes[0], ProvingKey::<G1Affine>::FORMAT_VERSION);
+    let mut other_version = bytes.clone();
+    other_version[0] = ProvingKey::<G1Affine>::FORMAT_VERSION + 1;
+    let err = ProvingKey::<G1Affine>::from_bytes::<FixedCircuit>(
+        &other_version,
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_lagrange_helpers_pruned() {
+    use crate::plonk::LAGRANGE_HELPERS;
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    // Numbers of blinding factors no circuit in the tests uses, so that no
+    // other test shares these entries.
+    let domain = EvaluationDomain::<Fr>::new(3, 6);
+    let helpers = ProvingKey::<G1Affine>::lagrange_helpers(&domain, 40);
+    let cached = |blinding_factors| {
+        LAGRANGE_HELPERS
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|((_, k, _, b), _)| *k == 6 && *b == blinding_factors)
+    };
+    assert!(cached(40));
+
+    // Adding an entry removes the entries of the helpers that were freed.
+    drop(helpers);
+    let _helpers = ProvingKey::<G1Affine>::lagrange_helpers(&domain, 41);
+    assert!(!cached(40));
+    assert!(cached(41));
+}
+
+#[test]
+#[ignore]
+fn test_lagrange_helpers_cached_timing() {
+    use std::time::Instant;
+    use test_fixtures::FixedCircuit;
+
+    let params = test_fixtures::params(16);
+    let vk = keygen_vk(&params, &FixedCircuit(1)).unwrap();
+
+    let start = Instant::now();
+    let first = keygen_pk(&params, vk.clone(), &FixedCircuit(1)).unwrap();
+    let uncached = start.elapsed();
+
+    let start = Instant::now();
+    let second = keygen_pk(&params, vk, &FixedCircuit(1)).unwrap();
+    let cached = start.elapsed();
+
+    // With a single fixed column, the helper polynomials account for most of
+    // the FFTs done by keygen_pk.
+    assert!(
+        cached * 2 < uncached,
+        "cached keygen_pk took {:?}, uncached {:?}",
+        cached,
+        uncached
+    );
+    drop((first, second));
+}
+
+#[test]
+fn test_keygen_pk_vk_mismatch_details() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        poly::Rotation,
+    };
+
+    /// Has `ADVICE` advice columns, constrained by a gate scaling the first one
+    /// by `SCALE`, and a fixed column holding `self.0`. The first two cells of
+    /// the first advice column are copied to each other if `self.1` is set.
+    #[derive(Clone, Copy)]
+    struct MyCircuit<const ADVICE: usize, const SCALE: u64>(u64, bool);
+
+    impl<F: Field, const ADVICE: usize, const SCALE: u64> Circuit<F> for MyCircuit<ADVICE, SCALE> {
+        type Config = (Column<Fixed>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice: Vec<_> = (0..ADVICE).map(|_| meta.advice_column()).collect();
+            let fixed = meta.fixed_column();
+            meta.create_gate("scale", |meta| {
+                let a = meta.query_advice(advice[0], Rotation::cur());
+                let f = meta.query_fixed(fixed, Rotation::cur());
+                vec![f * a * Expression::Constant(F::from(SCALE))]
+            });
+            meta.enable_equality(advice[0]);
+            (fixed, advice[0])
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "fixed",
+                |mut region| {
+                    region.assign_fixed(|| "f", config.0, 0, || Value::known(F::from(self.0)))?;
+                    if self.1 {
+                        let a =
+                            region.assign_advice(|| "a", config.1, 0, || Value::<F>::unknown())?;
+                        let b =
+                            region.assign_advice(|| "b", config.1, 1, || Value::<F>::unknown())?;
+                        region.constrain_equal(a.cell(), b.cell())?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit::<1, 2>(1, false)).unwrap();
+
+    let other_params = test_fixtures::params(5);
+    assert!(matches!(
+        keygen_pk(&other_params, vk.clone(), &MyCircuit::<1, 2>(1, false)),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::K {
+            vk: 4,
+            circuit: 5
+        }))
+    ));
+
+    let err = keygen_pk(&params, vk.clone(), &MyCircuit::<2, 2>(1, false)).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::VerifyingKeyMismatch(VkMismatch::ColumnCount {
+            column_type: "advice",
+            vk: 1,
+            circuit: 2,
+        })
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Circuit does not match the verifying key: number of advice columns is 1 in the vk but 2"
+    );
+
+    // A changed gate.
+    assert!(matches!(
+        keygen_pk(&params, vk.clone(), &MyCircuit::<1, 3>(1, false)),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::Gate(0)))
+    ));
+
+    // A changed fixed assignment.
+    assert!(matches!(
+        keygen_pk(&params, vk.clone(), &MyCircuit::<1, 2>(7, false)),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::FixedCommitment(0)))
+    ));
+
+    // A changed copy constraint.
+    assert!(matches!(
+        keygen_pk(&params, vk.clone(), &MyCircuit::<1, 2>(1, true)),
+        Err(Error::VerifyingKeyMismatch(VkMismatch::Permutation(0)))
+    ));
+
+    // The checks can be skipped, keeping the fixed values of the circuit.
+    let pk = keygen_pk_unchecked(&params, vk, &MyCircuit::<1, 2>(7, false)).unwrap();
+    assert_eq!(pk.fixed_values[0][0], halo2curves::bn256::Fr::from(7));
+}
+
+#[test]
+fn test_keygen_pk_incremental() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::TableColumn,
+        poly::Rotation,
+    };
+
+    /// Looks up an advice column in a table whose entry at row 3 is `self.0`.
+    #[derive(Clone, Copy)]
+    struct MyCircuit(u64);
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = (Column<Advice>, Column<Fixed>, TableColumn);
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advice = meta.advice_column();
+            let constant = meta.fixed_column();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(advice);
+            meta.enable_equality(constant);
+            meta.lookup("table", |meta| {
+                vec![(meta.query_advice(advice, Rotation::cur()), table)]
+            });
+            (advice, constant, table)
+        }
+
+        fn synthesize(
+            &self,
+            (advice, constant, table): Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut t| {
+                    for row in 0..8 {
+                        let entry = if row == 3 { self.0 } else { row as u64 };
+                        t.assign_cell(|| "entry", table, row, || Value::known(F::from(entry)))?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "copy",
+                |mut region| {
+                    let cell =
+                        region.assign_fixed(|| "constant", constant, 0, || Value::known(F::ONE))?;
+                    let value =
+                        region.assign_advice(|| "value", advice, 0, || Value::known(F::ONE))?;
+                    region.constrain_equal(cell.cell(), value.cell())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(5);
+    let vk = keygen_vk(&params, &MyCircuit(3)).unwrap();
+    let old_pk = keygen_pk(&params, vk, &MyCircuit(3)).unwrap();
+
+    let pk = keygen_pk_incremental(&params, &old_pk, &MyCircuit(100)).unwrap();
+    let (_, _, table) =
+        MyCircuit::configure(&mut ConstraintSystem::<halo2curves::bn256::Fr>::default());
+    let changed: Vec<_> = (0..pk.vk.fixed_commitments.len())
+        .filter(|&i| pk.vk.fixed_commitments[i] != old_pk.vk.fixed_commitments[i])
+        .collect();
+    assert_eq!(changed, vec![table.inner().index()]);
+    assert_eq!(
+        pk.vk.permutation.commitments(),
+        old_pk.vk.permutation.commitments()
+    );
+
+    // The result is the same as generating the keys from scratch.
+    let vk = keygen_vk(&params, &MyCircuit(100)).unwrap();
+    let expected = keygen_pk(&params, vk, &MyCircuit(100)).unwrap();
+    assert_eq!(
+        pk.to_bytes(SerdeFormat::RawBytes),
+        expected.to_bytes(SerdeFormat::RawBytes)
+    );
+
+    // Different parameters fall back to full keygen.
+    let other_params = test_fixtures::params(6);
+    let pk = keygen_pk_incremental(&other_params, &old_pk, &MyCircuit(100)).unwrap();
+    let vk = keygen_vk(&other_params, &MyCircuit(100)).unwrap();
+    assert_eq!(pk.vk.transcript_repr(), vk.transcript_repr());
 }
@@ -0,0 +1,523 @@
+use group::ff::PrimeField;
+use std::marker::PhantomData;
+
+use super::{CommitmentKind, ProverListener, VerifierListener, VerifyingKey};
+use crate::{
+    arithmetic::CurveAffine,
+    poly::commitment::{CommitmentScheme, Verifier},
+};
+
+/// What a [`ProofComponent`] holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofComponentKind {
+    /// The commitments to the advice columns of a phase.
+    Advice {
+        /// The phase of the columns.
+        phase: u8,
+    },
+    /// The commitments to the permuted input and table columns of the
+    /// lookups, or to their multiplicity columns under
+    /// [`LookupBackend::LogUp`](crate::plonk::LookupBackend::LogUp).
+    LookupPermuted,
+    /// The commitments to the grand product columns of the permutation
+    /// argument.
+    PermutationProduct,
+    /// The commitments to the grand product columns of the lookups, or to
+    /// their running sum columns under
+    /// [`LookupBackend::LogUp`](crate::plonk::LookupBackend::LogUp).
+    LookupProduct,
+    /// The commitments to the grand product columns of the shuffles.
+    ShuffleProduct,
+    /// The commitment to the random polynomial blinding the quotient
+    /// polynomial.
+    VanishingRandom,
+    /// The commitments to the pieces of the quotient polynomial.
+    Quotient,
+    /// The evaluations of every polynomial at the points it is queried at.
+    Evaluations,
+    /// The multiopen argument, whose contents depend on the commitment
+    /// scheme.
+    Multiopen,
+}
+
+impl From<CommitmentKind> for ProofComponentKind {
+    fn from(kind: CommitmentKind) -> Self {
+        match kind {
+            CommitmentKind::Advice { phase } => ProofComponentKind::Advice { phase },
+            CommitmentKind::LookupPermuted => ProofComponentKind::LookupPermuted,
+            CommitmentKind::PermutationProduct => ProofComponentKind::PermutationProduct,
+            CommitmentKind::LookupProduct => ProofComponentKind::LookupProduct,
+            CommitmentKind::ShuffleProduct => ProofComponentKind::ShuffleProduct,
+            CommitmentKind::VanishingRandom => ProofComponentKind::VanishingRandom,
+            CommitmentKind::Quotient => ProofComponentKind::Quotient,
+            CommitmentKind::Multiopen => ProofComponentKind::Multiopen,
+        }
+    }
+}
+
+/// A contiguous range of the bytes of a proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofComponent {
+    /// What the bytes hold.
+    pub kind: ProofComponentKind,
+    /// The circuit the component belongs to; 0 for the components shared by
+    /// all circuits.
+    pub index: usize,
+    /// The offset of the first byte.
+    pub offset: usize,
+    /// The number of bytes.
+    pub len: usize,
+    /// The number of points and scalars.
+    pub count: usize,
+}
+
+/// Where each component of a proof lies within its bytes, in the order they
+/// are written.
+///
+/// Obtained from the prover with
+/// [`ProverOptions::record_layout`](crate::plonk::ProverOptions::record_layout),
+/// or computed from the verifying key with [`ProofLayout::parse`]. Points and
+/// scalars are assumed to take up the size of their compressed encoding, as
+/// they do in the transcripts of this crate other than the EVM ones with
+/// [`PointEncoding::Uncompressed`](crate::transcript::PointEncoding).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofLayout {
+    components: Vec<ProofComponent>,
+}
+
+impl ProofLayout {
+    /// Returns the components, in the order they are written.
+    pub fn components(&self) -> &[ProofComponent] {
+        &self.components
+    }
+
+    /// Returns the offset of the end of the last component.
+    pub fn end(&self) -> usize {
+        self.components
+            .last()
+            .map_or(0, |component| component.offset + component.len)
+    }
+
+    /// Returns the bytes of each component of `proof`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `proof` is shorter than [`Self::end`].
+    pub fn split<'a>(&self, proof: &'a [u8]) -> Vec<&'a [u8]> {
+        self.components
+            .iter()
+            .map(|component| &proof[component.offset..component.offset + component.len])
+            .collect()
+    }
+
+    /// Returns the kind and index of the component holding the point or
+    /// scalar at `position` among all those of the proof, and its position
+    /// within the component. Positions past the end of the layout belong to
+    /// the multiopen argument.
+    pub(crate) fn locate(&self, mut position: usize) -> (ProofComponentKind, usize, usize) {
+        for component in self.components.iter() {
+            if position < component.count {
+                return (component.kind, component.index, position);
+            }
+            position -= component.count;
+        }
+        (ProofComponentKind::Multiopen, 0, position)
+    }
+
+    /// Computes the layout of a proof for `num_instances` circuits, each
+    /// with its own instance, under the verifying key `vk`, as the verifier
+    /// `V` reads it.
+    ///
+    /// The layout ends with the evaluations: the size of the multiopen
+    /// argument depends on the commitment scheme, so it is left out. It takes
+    /// up the rest of the proof, starting at [`Self::end`].
+    pub fn parse<'params, Scheme: CommitmentScheme, V: Verifier<'params, Scheme>>(
+        vk: &VerifyingKey<Scheme::Curve>,
+        num_instances: usize,
+    ) -> Self {
+        let cs = &vk.cs;
+        let mut layout = LayoutBuilder::<Scheme::Curve>::default();
+        let mut commitments = |kind: CommitmentKind, index: usize, count: usize| {
+            for _ in 0..count {
+                layout.point(kind.into(), index);
+            }
+        };
+
+        for phase in cs.phases() {
+            let columns = cs.num_advice_columns_in_phase(phase.0);
+            for index in 0..num_instances {
+                commitments(CommitmentKind::Advice { phase: phase.0 }, index, columns);
+            }
+        }
+        let (lookups, logups) = cs.lookups_by_backend();
+        // Two permuted columns per lookup, or one multiplicity column
+        let permuted = 2 * lookups.len() + logups.len();
+        let chunk_len = vk.cs_degree - 2;
+        let permutation_products = cs.permutation.columns.chunks(chunk_len).len();
+        for (kind, count) in [
+            (CommitmentKind::LookupPermuted, permuted),
+            (CommitmentKind::PermutationProduct, permutation_products),
+            (CommitmentKind::LookupProduct, cs.lookups.len()),
+            (CommitmentKind::ShuffleProduct, cs.shuffles.len()),
+        ] {
+            for index in 0..num_instances {
+                commitments(kind, index, count);
+            }
+        }
+        commitments(CommitmentKind::VanishingRandom, 0, 1);
+        commitments(
+            CommitmentKind::Quotient,
+            0,
+            vk.domain.get_quotient_poly_degree(),
+        );
+
+        let instance_evals = if V::QUERY_INSTANCE {
+            cs.instance_queries.len()
+        } else {
+            0
+        };
+        let permutation_evals = match permutation_products {
+            0 => 0,
+            sets => 3 * sets - 1,
+        };
+        let lookup_evals = 5 * lookups.len() + 3 * logups.len();
+        let evals = num_instances
+            * (instance_evals
+                + cs.advice_queries.len()
+                + permutation_evals
+                + lookup_evals
+                + 2 * cs.shuffles.len())
+            + cs.fixed_queries.len()
+            + 1 // the random polynomial
+            + vk.permutation.commitments().len();
+        for _ in 0..evals {
+            layout.scalar();
+        }
+
+        layout.finish()
+    }
+}
+
+/// Builds a [`ProofLayout`] from the points and scalars of a proof, in
+/// transcript order. The scalars written before the multiopen argument are
+/// the evaluations; the multiopen argument starts with a point in every
+/// commitment scheme.
+#[derive(Debug)]
+pub(crate) struct LayoutBuilder<C: CurveAffine> {
+    layout: ProofLayout,
+    in_multiopen: bool,
+    _marker: PhantomData<C>,
+}
+
+impl<C: CurveAffine> Default for LayoutBuilder<C> {
+    fn default() -> Self {
+        LayoutBuilder {
+            layout: ProofLayout::default(),
+            in_multiopen: false,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C: CurveAffine> LayoutBuilder<C> {
+    fn push(&mut self, kind: ProofComponentKind, index: usize, len: usize) {
+        let offset = self.layout.end();
+        match self.layout.components.last_mut() {
+            Some(last) if last.kind == kind && last.index == index => {
+                last.len += len;
+                last.count += 1;
+            }
+            _ => self.layout.components.push(ProofComponent {
+                kind,
+                index,
+                offset,
+                len,
+                count: 1,
+            }),
+        }
+    }
+
+    fn point(&mut self, kind: ProofComponentKind, index: usize) {
+        self.in_multiopen |= kind == ProofComponentKind::Multiopen;
+        self.push(kind, index, C::Repr::default().as_ref().len());
+    }
+
+    fn scalar(&mut self) {
+        let kind = if self.in_multiopen {
+            ProofComponentKind::Multiopen
+        } else {
+            ProofComponentKind::Evaluations
+        };
+        self.push(
+            kind,
+            0,
+            <C::Scalar as PrimeField>::Repr::default().as_ref().len(),
+        );
+    }
+
+    pub(crate) fn finish(self) -> ProofLayout {
+        self.layout
+    }
+}
+
+impl<C: CurveAffine> ProverListener<C> for LayoutBuilder<C> {
+    fn commitment(&mut self, kind: CommitmentKind, index: usize, _commitment: &C) {
+        self.point(kind.into(), index);
+    }
+
+    fn evaluation(&mut self, _evaluation: &C::Scalar) {
+        self.scalar();
+    }
+}
+
+impl<C: CurveAffine> VerifierListener<C> for LayoutBuilder<C> {
+    fn commitment(&mut self, kind: CommitmentKind, index: usize, _commitment: &C) {
+        self.point(kind.into(), index);
+    }
+
+    fn evaluation(&mut self, _evaluation: &C::Scalar) {
+        self.scalar();
+    }
+}
+
+#[test]
+fn test_proof_layout() {
+    use crate::{
+        arithmetic::Field,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{
+            create_proof_with_options, keygen_pk, keygen_vk, verify_proof_listened, Advice,
+            Challenge, Circuit, Column, ConstraintSystem, Error, FirstPhase, Instance,
+            LookupBackend, ProverOptions, SecondPhase, Selector, TableColumn,
+        },
+        poly::{
+            commitment::ParamsProver,
+            ipa::{
+                commitment::{IPACommitmentScheme, ParamsIPA},
+                multiopen::{ProverIPA, VerifierIPA},
+                strategy::SingleStrategy as IPASingleStrategy,
+            },
+            kzg::{
+                commitment::{KZGCommitmentScheme, ParamsKZG},
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy as KZGSingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::{bn256::Bn256, pasta::EqAffine};
+    use rand_core::OsRng;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Selector,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Challenge,
+        d: Column<Advice>,
+        table: TableColumn,
+        instance: Column<Instance>,
+    }
+
+    /// Checks that `b = a * c` for a challenge `c` squeezed after `a`, looks
+    /// `a` up in a table, shuffles it into `d`, and copies `a` into the
+    /// instance.
+    #[derive(Clone, Copy)]
+    struct MyCircuit<const LOGUP: bool>;
+
+    impl<F: PrimeField, const LOGUP: bool> Circuit<F> for MyCircuit<LOGUP> {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            if LOGUP {
+                meta.set_lookup_backend(LookupBackend::LogUp);
+            }
+            let q = meta.complex_selector();
+            let a = meta.advice_column_in(FirstPhase);
+            let b = meta.advice_column_in(SecondPhase);
+            let c = meta.challenge_usable_after(FirstPhase);
+            let d = meta.advice_column();
+            let table = meta.lookup_table_column();
+            let instance = meta.instance_column();
+            for column in [a, b, d] {
+                meta.enable_equality(column);
+            }
+            meta.enable_equality(instance);
+
+            meta.create_gate("b = a * c", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_challenge(c);
+                vec![q * (b - a * c)]
+            });
+            meta.lookup("a in table", |meta| {
+                let q = meta.query_selector(q);
+                vec![(q * meta.query_advice(a, Rotation::cur()), table)]
+            });
+            meta.shuffle("d is a shuffle of a", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let d = meta.query_advice(d, Rotation::cur());
+                vec![(q.clone() * a, q * d)]
+            });
+
+            MyConfig {
+                q,
+                a,
+                b,
+                c,
+                d,
+                table,
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    for i in 0..8 {
+                        let value = Value::known(F::from(i as u64));
+                        table.assign_cell(|| "value", config.table, i, || value)?;
+                    }
+                    Ok(())
+                },
+            )?;
+            let c = layouter.get_challenge(config.c);
+            let first = layouter.assign_region(
+                || "values",
+                |mut region| {
+                    let mut first = None;
+                    for row in 0..4 {
+                        let a = Value::known(F::from(row as u64 + 1));
+                        let d = Value::known(F::from(4 - row as u64));
+                        config.q.enable(&mut region, row)?;
+                        let cell = region.assign_advice(|| "a", config.a, row, || a)?;
+                        region.assign_advice(|| "b", config.b, row, || a * c)?;
+                        region.assign_advice(|| "d", config.d, row, || d)?;
+                        first.get_or_insert(cell);
+                    }
+                    Ok(first.unwrap())
+                },
+            )?;
+            layouter.constrain_instance(first.cell(), config.instance, 0)
+        }
+    }
+
+    // Proves two circuits, checks the layout against the proof and against
+    // what the verifier reads, and returns it along with the proof.
+    macro_rules! check {
+        ($params:expr, $scheme:ty, $field:ty, $circuit:expr, $prover:ident, $verifier:ident, $strategy:ident) => {{
+            let params = $params;
+            let circuit = $circuit;
+            let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+            let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+            let instance = [<$field>::ONE];
+            let instances: &[&[&[$field]]] = &[&[&instance], &[&instance]];
+
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            let artifacts = create_proof_with_options::<$scheme, $prover<_>, _, _, _, _>(
+                &params,
+                &pk,
+                &[circuit, circuit],
+                instances,
+                OsRng,
+                &mut transcript,
+                &ProverOptions::new().record_layout(),
+            )
+            .expect("proof generation should not fail");
+            let layout = artifacts.layout().expect("the layout was recorded").clone();
+            let proof = transcript.finalize();
+
+            // The components cover the proof, in order.
+            assert_eq!(layout.end(), proof.len());
+            assert_eq!(layout.split(&proof).concat(), proof);
+
+            // The verifier reads the same components.
+            let mut read = LayoutBuilder::default();
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            verify_proof_listened::<_, $verifier<_>, _, _, _, _>(
+                params.verifier_params(),
+                pk.get_vk(),
+                $strategy::new(&params),
+                instances,
+                &mut transcript,
+                &mut read,
+            )
+            .expect("proof should verify");
+            assert_eq!(read.finish(), layout);
+
+            // Everything but the multiopen argument can be computed ahead.
+            let parsed = ProofLayout::parse::<_, $verifier<_>>(pk.get_vk(), 2);
+            let (multiopen, components) = layout.components().split_last().unwrap();
+            assert_eq!(parsed.components(), components);
+            assert_eq!(multiopen.kind, ProofComponentKind::Multiopen);
+            assert_eq!(multiopen.offset, parsed.end());
+
+            layout
+        }};
+    }
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(5, OsRng);
+    let layout = check!(
+        &params,
+        KZGCommitmentScheme<_>,
+        halo2curves::bn256::Fr,
+        MyCircuit::<false>,
+        ProverSHPLONK,
+        VerifierSHPLONK,
+        KZGSingleStrategy
+    );
+    let kinds: Vec<_> = layout
+        .components()
+        .iter()
+        .map(|component| (component.kind, component.index, component.count))
+        .collect();
+    assert_eq!(
+        &kinds[..6],
+        &[
+            (ProofComponentKind::Advice { phase: 0 }, 0, 2),
+            (ProofComponentKind::Advice { phase: 0 }, 1, 2),
+            (ProofComponentKind::Advice { phase: 1 }, 0, 1),
+            (ProofComponentKind::Advice { phase: 1 }, 1, 1),
+            (ProofComponentKind::LookupPermuted, 0, 2),
+            (ProofComponentKind::LookupPermuted, 1, 2),
+        ]
+    );
+    check!(
+        &params,
+        KZGCommitmentScheme<_>,
+        halo2curves::bn256::Fr,
+        MyCircuit::<true>,
+        ProverSHPLONK,
+        VerifierSHPLONK,
+        KZGSingleStrategy
+    );
+
+    // The IPA verifier also reads the evaluations of the instance columns.
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(5);
+    check!(
+        &params,
+        IPACommitmentScheme<_>,
+        halo2curves::pasta::Fp,
+        MyCircuit::<false>,
+        ProverIPA,
+        VerifierIPA,
+        IPASingleStrategy
+    );
+}
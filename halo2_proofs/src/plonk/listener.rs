@@ -0,0 +1,353 @@
+use std::{cell::RefCell, fmt, io, time::Duration};
+
+use super::{CommitmentKind, ProverObserver};
+use crate::{
+    arithmetic::CurveAffine,
+    transcript::{EncodedChallenge, Transcript, TranscriptRead, TranscriptWrite},
+};
+
+/// A value sent through the transcript, as reported to a [`ProverListener`]
+/// or a [`VerifierListener`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent<C: CurveAffine> {
+    /// A commitment of `kind` for the `index`-th circuit.
+    Commitment {
+        /// The group of commitments it belongs to.
+        kind: CommitmentKind,
+        /// The circuit it belongs to; 0 for the groups shared by all
+        /// circuits.
+        index: usize,
+        /// The commitment.
+        commitment: C,
+    },
+    /// A challenge squeezed from the transcript.
+    Challenge(C::Scalar),
+    /// A claimed evaluation, or any other scalar of the multiopen argument.
+    Evaluation(C::Scalar),
+}
+
+/// Receives every value the prover writes to the transcript during
+/// [`create_proof_listened`](crate::plonk::create_proof_listened).
+///
+/// Values arrive in transcript order, a stage at a time: once all the
+/// commitments of a [`CommitmentKind`] are written, the listener receives
+/// them along with the challenges and evaluations that preceded them. If the
+/// prover fails, the values of the stage it failed in are not reported. All
+/// callbacks do nothing by default.
+pub trait ProverListener<C: CurveAffine> {
+    /// Called for each commitment of `kind` written for the `index`-th
+    /// circuit.
+    fn commitment(&mut self, _kind: CommitmentKind, _index: usize, _commitment: &C) {}
+
+    /// Called for each challenge squeezed from the transcript.
+    fn challenge(&mut self, _challenge: &C::Scalar) {}
+
+    /// Called for each scalar written to the transcript.
+    fn evaluation(&mut self, _evaluation: &C::Scalar) {}
+}
+
+/// Receives every value the verifier reads from the transcript during
+/// [`verify_proof_listened`](crate::plonk::verify_proof_listened), in the
+/// same order and grouped the same way as a [`ProverListener`] receives them
+/// from the prover.
+pub trait VerifierListener<C: CurveAffine> {
+    /// Called for each commitment of `kind` read for the `index`-th proof.
+    fn commitment(&mut self, _kind: CommitmentKind, _index: usize, _commitment: &C) {}
+
+    /// Called for each challenge squeezed from the transcript.
+    fn challenge(&mut self, _challenge: &C::Scalar) {}
+
+    /// Called for each scalar read from the transcript.
+    fn evaluation(&mut self, _evaluation: &C::Scalar) {}
+}
+
+/// A [`ProverListener`] and [`VerifierListener`] that records the events it
+/// receives, so that the two sides can be compared with
+/// [`diff_transcripts`].
+#[derive(Clone, Debug, Default)]
+pub struct TranscriptRecorder<C: CurveAffine> {
+    /// The events received so far, in transcript order.
+    pub events: Vec<TranscriptEvent<C>>,
+}
+
+impl<C: CurveAffine> TranscriptRecorder<C> {
+    /// Creates a recorder with no events.
+    pub fn new() -> Self {
+        TranscriptRecorder { events: vec![] }
+    }
+}
+
+impl<C: CurveAffine> ProverListener<C> for TranscriptRecorder<C> {
+    fn commitment(&mut self, kind: CommitmentKind, index: usize, commitment: &C) {
+        self.events.push(TranscriptEvent::Commitment {
+            kind,
+            index,
+            commitment: *commitment,
+        });
+    }
+
+    fn challenge(&mut self, challenge: &C::Scalar) {
+        self.events.push(TranscriptEvent::Challenge(*challenge));
+    }
+
+    fn evaluation(&mut self, evaluation: &C::Scalar) {
+        self.events.push(TranscriptEvent::Evaluation(*evaluation));
+    }
+}
+
+impl<C: CurveAffine> VerifierListener<C> for TranscriptRecorder<C> {
+    fn commitment(&mut self, kind: CommitmentKind, index: usize, commitment: &C) {
+        ProverListener::commitment(self, kind, index, commitment);
+    }
+
+    fn challenge(&mut self, challenge: &C::Scalar) {
+        ProverListener::challenge(self, challenge);
+    }
+
+    fn evaluation(&mut self, evaluation: &C::Scalar) {
+        ProverListener::evaluation(self, evaluation);
+    }
+}
+
+/// The first point at which the prover's and the verifier's views of a
+/// transcript differ, as found by [`diff_transcripts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptDivergence<C: CurveAffine> {
+    /// The position of the first differing event.
+    pub position: usize,
+    /// The prover's event at `position`, if the prover got that far.
+    pub prover: Option<TranscriptEvent<C>>,
+    /// The verifier's event at `position`, if the verifier got that far.
+    pub verifier: Option<TranscriptEvent<C>>,
+}
+
+impl<C: CurveAffine> fmt::Display for TranscriptDivergence<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |event: &Option<TranscriptEvent<C>>| match event {
+            Some(event) => format!("{:?}", event),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "transcripts diverge at event {}: the prover sent {} but the verifier saw {}",
+            self.position,
+            describe(&self.prover),
+            describe(&self.verifier),
+        )
+    }
+}
+
+/// Compares the events a [`ProverListener`] and a [`VerifierListener`]
+/// received for the same proof, and returns the first one that differs, if
+/// any.
+///
+/// A commitment tagged differently on both sides means that the prover and
+/// the verifier disagree on the shape of the circuit; a challenge that
+/// differs after identical commitments means that they disagree on the
+/// verifying key or the instances.
+pub fn diff_transcripts<C: CurveAffine>(
+    prover_events: &[TranscriptEvent<C>],
+    verifier_events: &[TranscriptEvent<C>],
+) -> Option<TranscriptDivergence<C>> {
+    let position = prover_events
+        .iter()
+        .zip(verifier_events.iter())
+        .position(|(prover, verifier)| prover != verifier)
+        .unwrap_or_else(|| prover_events.len().min(verifier_events.len()));
+    if position == prover_events.len() && position == verifier_events.len() {
+        return None;
+    }
+
+    Some(TranscriptDivergence {
+        position,
+        prover: prover_events.get(position).cloned(),
+        verifier: verifier_events.get(position).cloned(),
+    })
+}
+
+/// A value that went through a transcript, before it is known which stage
+/// it belongs to.
+enum Pending<C: CurveAffine> {
+    Point(C),
+    Challenge(C::Scalar),
+    Scalar(C::Scalar),
+}
+
+/// The values that went through a transcript since the end of the last
+/// stage.
+pub(crate) struct PendingEvents<C: CurveAffine>(RefCell<Vec<Pending<C>>>);
+
+impl<C: CurveAffine> PendingEvents<C> {
+    pub(crate) fn new() -> Self {
+        PendingEvents(RefCell::new(vec![]))
+    }
+
+    fn push(&self, value: Pending<C>) {
+        self.0.borrow_mut().push(value);
+    }
+
+    /// Ends the current stage, whose commitments are of `kind` for the
+    /// `index`-th circuit, and returns its events.
+    fn take(&self, kind: CommitmentKind, index: usize) -> Vec<TranscriptEvent<C>> {
+        self.0
+            .borrow_mut()
+            .drain(..)
+            .map(|value| match value {
+                Pending::Point(commitment) => TranscriptEvent::Commitment {
+                    kind,
+                    index,
+                    commitment,
+                },
+                Pending::Challenge(challenge) => TranscriptEvent::Challenge(challenge),
+                Pending::Scalar(scalar) => TranscriptEvent::Evaluation(scalar),
+            })
+            .collect()
+    }
+
+    /// Ends the current stage and reports its events to `listener`.
+    pub(crate) fn report_to_verifier<L: VerifierListener<C>>(
+        &self,
+        kind: CommitmentKind,
+        index: usize,
+        listener: &mut L,
+    ) {
+        for event in self.take(kind, index) {
+            match event {
+                TranscriptEvent::Commitment {
+                    kind,
+                    index,
+                    commitment,
+                } => listener.commitment(kind, index, &commitment),
+                TranscriptEvent::Challenge(challenge) => listener.challenge(&challenge),
+                TranscriptEvent::Evaluation(evaluation) => listener.evaluation(&evaluation),
+            }
+        }
+    }
+}
+
+/// A transcript that records the values going through another one.
+pub(crate) struct ListenedTranscript<'a, C: CurveAffine, T> {
+    inner: &'a mut T,
+    pending: &'a PendingEvents<C>,
+}
+
+impl<'a, C: CurveAffine, T> ListenedTranscript<'a, C, T> {
+    pub(crate) fn new(inner: &'a mut T, pending: &'a PendingEvents<C>) -> Self {
+        ListenedTranscript { inner, pending }
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: Transcript<C, E>> Transcript<C, E>
+    for ListenedTranscript<'_, C, T>
+{
+    fn squeeze_challenge(&mut self) -> E {
+        let challenge = self.inner.squeeze_challenge();
+        self.pending
+            .push(Pending::Challenge(challenge.get_scalar()));
+        challenge
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.inner.common_point(point)
+    }
+
+    fn common_points(&mut self, points: &[C]) -> io::Result<()> {
+        self.inner.common_points(points)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.inner.common_scalar(scalar)
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptWrite<C, E>> TranscriptWrite<C, E>
+    for ListenedTranscript<'_, C, T>
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.inner.write_point(point)?;
+        self.pending.push(Pending::Point(point));
+        Ok(())
+    }
+
+    fn write_points(&mut self, points: &[C]) -> io::Result<()> {
+        self.inner.write_points(points)?;
+        for point in points {
+            self.pending.push(Pending::Point(*point));
+        }
+        Ok(())
+    }
+
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.inner.write_scalar(scalar)?;
+        self.pending.push(Pending::Scalar(scalar));
+        Ok(())
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> TranscriptRead<C, E>
+    for ListenedTranscript<'_, C, T>
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let point = self.inner.read_point()?;
+        self.pending.push(Pending::Point(point));
+        Ok(point)
+    }
+
+    fn read_points(&mut self, n: usize) -> io::Result<Vec<C>> {
+        let points = self.inner.read_points(n)?;
+        for point in &points {
+            self.pending.push(Pending::Point(*point));
+        }
+        Ok(points)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = self.inner.read_scalar()?;
+        self.pending.push(Pending::Scalar(scalar));
+        Ok(scalar)
+    }
+}
+
+/// A [`ProverObserver`] that ends a stage of a [`ListenedTranscript`] each
+/// time the prover is done with a group of commitments, and reports the
+/// stage's events to a [`ProverListener`].
+pub(crate) struct ListenerObserver<'a, C: CurveAffine, L> {
+    pending: &'a PendingEvents<C>,
+    listener: &'a mut L,
+}
+
+impl<'a, C: CurveAffine, L: ProverListener<C>> ListenerObserver<'a, C, L> {
+    pub(crate) fn new(pending: &'a PendingEvents<C>, listener: &'a mut L) -> Self {
+        ListenerObserver { pending, listener }
+    }
+
+    fn report(&mut self, kind: CommitmentKind, index: usize) {
+        for event in self.pending.take(kind, index) {
+            match event {
+                TranscriptEvent::Commitment {
+                    kind,
+                    index,
+                    commitment,
+                } => self.listener.commitment(kind, index, &commitment),
+                TranscriptEvent::Challenge(challenge) => self.listener.challenge(&challenge),
+                TranscriptEvent::Evaluation(evaluation) => self.listener.evaluation(&evaluation),
+            }
+        }
+    }
+}
+
+impl<C: CurveAffine, L: ProverListener<C>> ProverObserver for ListenerObserver<'_, C, L> {
+    const TIMED: bool = false;
+
+    fn commitment_done(&mut self, kind: CommitmentKind, index: usize, _duration: Duration) {
+        self.report(kind, index);
+    }
+
+    fn quotient_done(&mut self, _duration: Duration) {
+        self.report(CommitmentKind::Quotient, 0);
+    }
+
+    fn multiopen_done(&mut self, _duration: Duration) {
+        self.report(CommitmentKind::Multiopen, 0);
+    }
+}
@@ -2,12 +2,16 @@ use super::circuit::Expression;
 use ff::Field;
 use std::fmt::{self, Debug};
 
+pub(crate) mod logup;
 pub(crate) mod prover;
 pub(crate) mod verifier;
 
 #[derive(Clone)]
 pub struct Argument<F: Field> {
     pub(crate) name: String,
+    /// The name of the logical table of a `TableSet` the lookup is into, if
+    /// it was created with `ConstraintSystem::lookup_in_set`.
+    pub(crate) table: Option<String>,
     pub(crate) input_expressions: Vec<Expression<F>>,
     pub(crate) table_expressions: Vec<Expression<F>>,
 }
@@ -29,6 +33,7 @@ impl<F: Field> Argument<F> {
         let (input_expressions, table_expressions) = table_map.into_iter().unzip();
         Argument {
             name: name.as_ref().to_string(),
+            table: None,
             input_expressions,
             table_expressions,
         }
@@ -0,0 +1,25 @@
+//! The logarithmic-derivative lookup argument, used for the lookups of
+//! circuits configured with [`LookupBackend::LogUp`](super::super::LookupBackend::LogUp).
+//!
+//! Given a lookup with inputs compressed into $a(X)$ and a table compressed
+//! into $t(X)$, the prover commits to the multiplicities $m(X)$, where
+//! $m(\omega^i)$ is the number of usable rows whose input equals
+//! $t(\omega^i)$, counted on the first row holding that table value only.
+//! The inputs are all in the table exactly when
+//! $$
+//! \sum_i \frac{1}{a(\omega^i) + \beta} = \sum_i \frac{m(\omega^i)}{t(\omega^i) + \beta}
+//! $$
+//! over the usable rows, which the running sum $\phi(X)$ checks with
+//! - $l_0(X) \cdot \phi(X) = 0$,
+//! - $l_{last}(X) \cdot \phi(X) = 0$, and
+//! - $(1 - (l_{last}(X) + l_{blind}(X))) \cdot \big((\phi(\omega X) - \phi(X))(a(X) + \beta)(t(X) + \beta) - (t(X) + \beta) + m(X)(a(X) + \beta)\big) = 0$.
+//!
+//! These constraints have the same degree as the ones of the permutation-based
+//! lookup argument.
+//!
+//! Every lookup commits to its own $m(X)$ and $\phi(X)$, even when several
+//! lookups use the same table: multiplicities are not shared, so two lookups
+//! into one table cost two multiplicity commitments.
+
+pub(crate) mod prover;
+pub(crate) mod verifier;
@@ -0,0 +1,289 @@
+use super::super::super::{ChallengeBeta, ChallengeTheta, ChallengeX, Error, ProvingKey};
+use super::super::Argument;
+use crate::{
+    arithmetic::{eval_polynomial, CurveAffine, Engine},
+    poly::{
+        commitment::{Blind, Params},
+        Coeff, EvaluationDomain, LagrangeCoeff, Polynomial, ProverQuery, Rotation,
+    },
+    transcript::{EncodedChallenge, TranscriptWrite},
+};
+use ff::WithSmallOrderMulGroup;
+use group::{
+    ff::{BatchInvert, Field},
+    Curve,
+};
+use rand_core::RngCore;
+use std::{
+    collections::BTreeMap,
+    iter,
+    ops::{Mul, MulAssign},
+};
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Multiplicities<C: CurveAffine> {
+    compressed_input_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    compressed_table_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    multiplicities: Polynomial<C::Scalar, LagrangeCoeff>,
+    multiplicity_poly: Polynomial<C::Scalar, Coeff>,
+    multiplicity_blind: Blind<C::Scalar>,
+    multiplicity_commitment: C,
+}
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Committed<C: CurveAffine> {
+    pub(in crate::plonk) multiplicity_poly: Polynomial<C::Scalar, Coeff>,
+    multiplicity_blind: Blind<C::Scalar>,
+    pub(in crate::plonk) running_sum_poly: Polynomial<C::Scalar, Coeff>,
+    running_sum_blind: Blind<C::Scalar>,
+}
+
+pub(in crate::plonk) struct Evaluated<C: CurveAffine> {
+    constructed: Committed<C>,
+}
+
+impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
+    /// Compresses the input expressions A_0, ..., A_{m-1} and the table
+    /// expressions S_0, ..., S_{m-1} of this lookup with powers of theta into
+    /// a and t, and counts for each usable row of t how many usable rows of
+    /// a hold its value. Values repeated in t are only counted on their first
+    /// row.
+    ///
+    /// As for [`Argument::commit_permuted`], the commitment to the
+    /// multiplicities is not written to the transcript; see
+    /// [`Multiplicities::write_commitments`].
+    #[allow(clippy::too_many_arguments)]
+    pub(in crate::plonk) fn commit_multiplicities<
+        'a,
+        'params: 'a,
+        C,
+        P: Params<'params, C>,
+        R: RngCore,
+    >(
+        &self,
+        pk: &ProvingKey<C>,
+        params: &P,
+        engine: &dyn Engine<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        fixed_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        challenges: &'a [C::Scalar],
+        mut rng: R,
+    ) -> Result<Multiplicities<C>, Error>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        C::Curve: Mul<F, Output = C::Curve> + MulAssign<F>,
+    {
+        let (compressed_input_expression, compressed_table_expression) = self.compress_expressions(
+            pk,
+            params,
+            domain,
+            theta,
+            advice_values,
+            fixed_values,
+            instance_values,
+            challenges,
+        );
+
+        let blinding_factors = pk.vk.cs.blinding_factors();
+        let usable_rows = params.n() as usize - (blinding_factors + 1);
+
+        // The first row of each distinct value in the table
+        let table_rows: BTreeMap<C::Scalar, usize> = compressed_table_expression
+            .iter()
+            .take(usable_rows)
+            .enumerate()
+            .fold(BTreeMap::new(), |mut acc, (row, value)| {
+                acc.entry(*value).or_insert(row);
+                acc
+            });
+
+        let mut counts = vec![0u64; usable_rows];
+        for input_value in compressed_input_expression.iter().take(usable_rows) {
+            // Return error if input_value not found
+            let row = table_rows
+                .get(input_value)
+                .ok_or(Error::ConstraintSystemFailure)?;
+            counts[*row] += 1;
+        }
+
+        let multiplicities = domain.lagrange_from_vec(
+            counts
+                .into_iter()
+                .map(C::Scalar::from)
+                .chain((0..(blinding_factors + 1)).map(|_| pk.vk.cs.blinding_scalar(&mut rng)))
+                .collect(),
+        );
+        assert_eq!(multiplicities.len(), params.n() as usize);
+
+        let multiplicity_blind = pk.vk.cs.blind(&mut rng);
+        let multiplicity_commitment = params
+            .commit_lagrange_with_engine(engine, &multiplicities, multiplicity_blind)
+            .to_affine();
+        let multiplicity_poly =
+            domain.lagrange_to_coeff_with_engine(engine, multiplicities.clone());
+
+        Ok(Multiplicities {
+            compressed_input_expression,
+            compressed_table_expression,
+            multiplicities,
+            multiplicity_poly,
+            multiplicity_blind,
+            multiplicity_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> Multiplicities<C> {
+    /// Writes the commitment to the multiplicities to the transcript.
+    pub(in crate::plonk) fn write_commitments<E: EncodedChallenge<C>, T: TranscriptWrite<C, E>>(
+        &self,
+        transcript: &mut T,
+    ) -> Result<(), Error> {
+        transcript.write_point(self.multiplicity_commitment)?;
+
+        Ok(())
+    }
+
+    /// Computes and commits to the running sum phi, with phi(\omega^0) = 0 and
+    ///
+    /// phi(\omega^{i+1}) = phi(\omega^i) + 1 / (a(\omega^i) + \beta) - m(\omega^i) / (t(\omega^i) + \beta)
+    ///
+    /// over the usable rows, so that phi is zero again on the last row if
+    /// every input is in the table.
+    #[allow(clippy::too_many_arguments)]
+    pub(in crate::plonk) fn commit_running_sum<
+        'params,
+        P: Params<'params, C>,
+        E: EncodedChallenge<C>,
+        R: RngCore,
+        T: TranscriptWrite<C, E>,
+    >(
+        self,
+        pk: &ProvingKey<C>,
+        params: &P,
+        engine: &dyn Engine<C>,
+        beta: ChallengeBeta<C>,
+        mut rng: R,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let blinding_factors = pk.vk.cs.blinding_factors();
+        let usable_rows = params.n() as usize - (blinding_factors + 1);
+
+        // 1 / (a(\omega^i) + \beta) and 1 / (t(\omega^i) + \beta)
+        let mut input_inverses: Vec<_> = self.compressed_input_expression[..usable_rows]
+            .iter()
+            .map(|input_value| *input_value + *beta)
+            .collect();
+        let mut table_inverses: Vec<_> = self.compressed_table_expression[..usable_rows]
+            .iter()
+            .map(|table_value| *table_value + *beta)
+            .collect();
+        input_inverses
+            .iter_mut()
+            .chain(table_inverses.iter_mut())
+            .batch_invert();
+
+        let running_sum = iter::once(C::Scalar::ZERO)
+            .chain(
+                input_inverses
+                    .iter()
+                    .zip(table_inverses.iter())
+                    .zip(self.multiplicities.iter())
+                    .scan(
+                        C::Scalar::ZERO,
+                        |state, ((input_inverse, table_inverse), m)| {
+                            *state += *input_inverse - *m * table_inverse;
+                            Some(*state)
+                        },
+                    ),
+            )
+            // Chain random blinding factors.
+            .chain((0..blinding_factors).map(|_| pk.vk.cs.blinding_scalar(&mut rng)))
+            .collect::<Vec<_>>();
+        assert_eq!(running_sum.len(), params.n() as usize);
+
+        #[cfg(feature = "sanity-checks")]
+        // The sum of the fractions over the usable rows is zero if the lookup
+        // is satisfied.
+        assert_eq!(running_sum[usable_rows], C::Scalar::ZERO);
+
+        let running_sum = pk.vk.domain.lagrange_from_vec(running_sum);
+        let running_sum_blind = pk.vk.cs.blind(&mut rng);
+        let running_sum_commitment = params
+            .commit_lagrange_with_engine(engine, &running_sum, running_sum_blind)
+            .to_affine();
+        let running_sum_poly = pk
+            .vk
+            .domain
+            .lagrange_to_coeff_with_engine(engine, running_sum);
+
+        // Hash running sum commitment
+        transcript.write_point(running_sum_commitment)?;
+
+        Ok(Committed {
+            multiplicity_poly: self.multiplicity_poly,
+            multiplicity_blind: self.multiplicity_blind,
+            running_sum_poly,
+            running_sum_blind,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(in crate::plonk) fn evaluate<E: EncodedChallenge<C>, T: TranscriptWrite<C, E>>(
+        self,
+        pk: &ProvingKey<C>,
+        x: ChallengeX<C>,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        let running_sum_eval = eval_polynomial(&self.running_sum_poly, *x);
+        let running_sum_next_eval = eval_polynomial(&self.running_sum_poly, x_next);
+        let multiplicity_eval = eval_polynomial(&self.multiplicity_poly, *x);
+
+        // Hash each evaluation
+        for eval in iter::empty()
+            .chain(Some(running_sum_eval))
+            .chain(Some(running_sum_next_eval))
+            .chain(Some(multiplicity_eval))
+        {
+            transcript.write_scalar(eval)?;
+        }
+
+        Ok(Evaluated { constructed: self })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn open<'a>(
+        &'a self,
+        pk: &'a ProvingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = ProverQuery<'a, C>> + Clone {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open running sum commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                poly: &self.constructed.running_sum_poly,
+                blind: self.constructed.running_sum_blind,
+            }))
+            // Open multiplicity commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                poly: &self.constructed.multiplicity_poly,
+                blind: self.constructed.multiplicity_blind,
+            }))
+            // Open running sum commitments at x_next
+            .chain(Some(ProverQuery {
+                point: x_next,
+                poly: &self.constructed.running_sum_poly,
+                blind: self.constructed.running_sum_blind,
+            }))
+    }
+}
@@ -0,0 +1,174 @@
+use std::iter;
+
+use super::super::super::{circuit::Expression, ChallengeBeta, ChallengeTheta, ChallengeX};
+use super::super::Argument;
+use crate::{
+    arithmetic::CurveAffine,
+    plonk::{Error, VerifyingKey},
+    poly::{commitment::MSM, Rotation, VerifierQuery},
+    transcript::{EncodedChallenge, TranscriptRead},
+};
+use ff::Field;
+
+pub struct MultiplicityCommitment<C: CurveAffine> {
+    multiplicity_commitment: C,
+}
+
+pub struct Committed<C: CurveAffine> {
+    multiplicity: MultiplicityCommitment<C>,
+    running_sum_commitment: C,
+}
+
+pub struct Evaluated<C: CurveAffine> {
+    committed: Committed<C>,
+    running_sum_eval: C::Scalar,
+    running_sum_next_eval: C::Scalar,
+    multiplicity_eval: C::Scalar,
+}
+
+impl<F: Field> Argument<F> {
+    pub(in crate::plonk) fn read_multiplicity_commitment<
+        C: CurveAffine,
+        E: EncodedChallenge<C>,
+        T: TranscriptRead<C, E>,
+    >(
+        &self,
+        transcript: &mut T,
+    ) -> Result<MultiplicityCommitment<C>, Error> {
+        let multiplicity_commitment = transcript.read_point()?;
+
+        Ok(MultiplicityCommitment {
+            multiplicity_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> MultiplicityCommitment<C> {
+    pub(in crate::plonk) fn read_running_sum_commitment<
+        E: EncodedChallenge<C>,
+        T: TranscriptRead<C, E>,
+    >(
+        self,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let running_sum_commitment = transcript.read_point()?;
+
+        Ok(Committed {
+            multiplicity: self,
+            running_sum_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(crate) fn evaluate<E: EncodedChallenge<C>, T: TranscriptRead<C, E>>(
+        self,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let running_sum_eval = transcript.read_scalar()?;
+        let running_sum_next_eval = transcript.read_scalar()?;
+        let multiplicity_eval = transcript.read_scalar()?;
+
+        Ok(Evaluated {
+            committed: self,
+            running_sum_eval,
+            running_sum_next_eval,
+            multiplicity_eval,
+        })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    #[allow(clippy::too_many_arguments)]
+    pub(in crate::plonk) fn expressions<'a>(
+        &'a self,
+        l_0: C::Scalar,
+        l_last: C::Scalar,
+        l_blind: C::Scalar,
+        argument: &'a Argument<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        beta: ChallengeBeta<C>,
+        advice_evals: &[C::Scalar],
+        fixed_evals: &[C::Scalar],
+        instance_evals: &[C::Scalar],
+        challenges: &[C::Scalar],
+    ) -> impl Iterator<Item = C::Scalar> + 'a {
+        let active_rows = C::Scalar::ONE - (l_last + l_blind);
+
+        let running_sum_expression = || {
+            let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+                expressions
+                    .iter()
+                    .map(|expression| {
+                        expression.evaluate(
+                            &|scalar| scalar,
+                            &|_| panic!("virtual selectors are removed during optimization"),
+                            &|query| fixed_evals[query.index.unwrap()],
+                            &|query| advice_evals[query.index.unwrap()],
+                            &|query| instance_evals[query.index.unwrap()],
+                            &|challenge| challenges[challenge.index()],
+                            &|a| -a,
+                            &|a, b| a + &b,
+                            &|a, b| a * &b,
+                            &|a, scalar| a * &scalar,
+                        )
+                    })
+                    .fold(C::Scalar::ZERO, |acc, eval| acc * &*theta + &eval)
+            };
+            let input = compress_expressions(&argument.input_expressions) + &*beta;
+            let table = compress_expressions(&argument.table_expressions) + &*beta;
+
+            // (\phi(\omega X) - \phi(X)) (a(X) + \beta) (t(X) + \beta)
+            // - (t(X) + \beta) + m(X) (a(X) + \beta)
+            let left = (self.running_sum_next_eval - &self.running_sum_eval) * &input * &table;
+            let right = table - &(self.multiplicity_eval * &input);
+
+            (left - &right) * &active_rows
+        };
+
+        std::iter::empty()
+            .chain(
+                // l_0(X) * \phi(X) = 0
+                Some(l_0 * &self.running_sum_eval),
+            )
+            .chain(
+                // l_last(X) * \phi(X) = 0
+                Some(l_last * &self.running_sum_eval),
+            )
+            .chain(
+                // (1 - (l_last(X) + l_blind(X))) * (
+                //   (\phi(\omega X) - \phi(X)) (a(X) + \beta) (t(X) + \beta)
+                //   - (t(X) + \beta) + m(X) (a(X) + \beta)
+                // ) = 0
+                Some(running_sum_expression()),
+            )
+    }
+
+    pub(in crate::plonk) fn queries<'r, M: MSM<C> + 'r>(
+        &'r self,
+        vk: &'r VerifyingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = VerifierQuery<'r, C, M>> + Clone {
+        let x_next = vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open running sum commitment at x
+            .chain(Some(VerifierQuery::new_commitment(
+                &self.committed.running_sum_commitment,
+                *x,
+                self.running_sum_eval,
+            )))
+            // Open multiplicity commitment at x
+            .chain(Some(VerifierQuery::new_commitment(
+                &self.committed.multiplicity.multiplicity_commitment,
+                *x,
+                self.multiplicity_eval,
+            )))
+            // Open running sum commitment at \omega x
+            .chain(Some(VerifierQuery::new_commitment(
+                &self.committed.running_sum_commitment,
+                x_next,
+                self.running_sum_next_eval,
+            )))
+    }
+}
@@ -5,7 +5,7 @@ use super::super::{
 use super::Argument;
 use crate::plonk::evaluation::evaluate;
 use crate::{
-    arithmetic::{eval_polynomial, parallelize, CurveAffine},
+    arithmetic::{eval_polynomial, parallelize, CurveAffine, Engine},
     poly::{
         commitment::{Blind, Params},
         Coeff, EvaluationDomain, LagrangeCoeff, Polynomial, ProverQuery, Rotation,
@@ -34,6 +34,8 @@ pub(in crate::plonk) struct Permuted<C: CurveAffine> {
     permuted_table_expression: Polynomial<C::Scalar, LagrangeCoeff>,
     permuted_table_poly: Polynomial<C::Scalar, Coeff>,
     permuted_table_blind: Blind<C::Scalar>,
+    permuted_input_commitment: C,
+    permuted_table_commitment: C,
 }
 
 #[derive(Debug)]
@@ -60,19 +62,14 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
     /// - constructs Permuted<C> struct using permuted_input_value = A', and
     ///   permuted_table_expression = S'.
     /// The Permuted<C> struct is used to update the Lookup, and is then returned.
+    /// The commitments to A' and S' are not written to the transcript, so that
+    /// lookups can be permuted concurrently; see [`Permuted::write_commitments`].
     #[allow(clippy::too_many_arguments)]
-    pub(in crate::plonk) fn commit_permuted<
-        'a,
-        'params: 'a,
-        C,
-        P: Params<'params, C>,
-        E: EncodedChallenge<C>,
-        R: RngCore,
-        T: TranscriptWrite<C, E>,
-    >(
+    pub(in crate::plonk) fn commit_permuted<'a, 'params: 'a, C, P: Params<'params, C>, R: RngCore>(
         &self,
         pk: &ProvingKey<C>,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         theta: ChallengeTheta<C>,
         advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
@@ -80,38 +77,21 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
         instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
         challenges: &'a [C::Scalar],
         mut rng: R,
-        transcript: &mut T,
     ) -> Result<Permuted<C>, Error>
     where
         C: CurveAffine<ScalarExt = F>,
         C::Curve: Mul<F, Output = C::Curve> + MulAssign<F>,
     {
-        // Closure to get values of expressions and compress them
-        let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
-            let compressed_expression = expressions
-                .iter()
-                .map(|expression| {
-                    pk.vk.domain.lagrange_from_vec(evaluate(
-                        expression,
-                        params.n() as usize,
-                        1,
-                        fixed_values,
-                        advice_values,
-                        instance_values,
-                        challenges,
-                    ))
-                })
-                .fold(domain.empty_lagrange(), |acc, expression| {
-                    acc * *theta + &expression
-                });
-            compressed_expression
-        };
-
-        // Get values of input expressions involved in the lookup and compress them
-        let compressed_input_expression = compress_expressions(&self.input_expressions);
-
-        // Get values of table expressions involved in the lookup and compress them
-        let compressed_table_expression = compress_expressions(&self.table_expressions);
+        let (compressed_input_expression, compressed_table_expression) = self.compress_expressions(
+            pk,
+            params,
+            domain,
+            theta,
+            advice_values,
+            fixed_values,
+            instance_values,
+            challenges,
+        );
 
         // Permute compressed (InputExpression, TableExpression) pair
         let (permuted_input_expression, permuted_table_expression) = permute_expression_pair(
@@ -125,9 +105,14 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
 
         // Closure to construct commitment to vector of values
         let mut commit_values = |values: &Polynomial<C::Scalar, LagrangeCoeff>| {
-            let poly = pk.vk.domain.lagrange_to_coeff(values.clone());
-            let blind = Blind(C::Scalar::random(&mut rng));
-            let commitment = params.commit_lagrange(values, blind).to_affine();
+            let poly = pk
+                .vk
+                .domain
+                .lagrange_to_coeff_with_engine(engine, values.clone());
+            let blind = pk.vk.cs.blind(&mut rng);
+            let commitment = params
+                .commit_lagrange_with_engine(engine, values, blind)
+                .to_affine();
             (poly, blind, commitment)
         };
 
@@ -139,12 +124,6 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
         let (permuted_table_poly, permuted_table_blind, permuted_table_commitment) =
             commit_values(&permuted_table_expression);
 
-        // Hash permuted input commitment
-        transcript.write_point(permuted_input_commitment)?;
-
-        // Hash permuted table commitment
-        transcript.write_point(permuted_table_commitment)?;
-
         Ok(Permuted {
             compressed_input_expression,
             permuted_input_expression,
@@ -154,16 +133,80 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
             permuted_table_expression,
             permuted_table_poly,
             permuted_table_blind,
+            permuted_input_commitment,
+            permuted_table_commitment,
         })
     }
+
+    /// Evaluates the input and the table expressions of this lookup over all
+    /// rows, and compresses each side into a single column with powers of
+    /// `theta`.
+    #[allow(clippy::too_many_arguments)]
+    pub(in crate::plonk) fn compress_expressions<'params, C, P: Params<'params, C>>(
+        &self,
+        pk: &ProvingKey<C>,
+        params: &P,
+        domain: &EvaluationDomain<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        advice_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+        fixed_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+        instance_values: &[Polynomial<C::Scalar, LagrangeCoeff>],
+        challenges: &[C::Scalar],
+    ) -> ExpressionPair<C::Scalar>
+    where
+        C: CurveAffine<ScalarExt = F>,
+    {
+        // Closure to get values of expressions and compress them
+        let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+            expressions
+                .iter()
+                .map(|expression| {
+                    pk.vk.domain.lagrange_from_vec(evaluate(
+                        expression,
+                        params.n() as usize,
+                        1,
+                        fixed_values,
+                        advice_values,
+                        instance_values,
+                        challenges,
+                    ))
+                })
+                .fold(domain.empty_lagrange(), |acc, expression| {
+                    acc * *theta + &expression
+                })
+        };
+
+        (
+            // Get values of input expressions involved in the lookup and compress them
+            compress_expressions(&self.input_expressions),
+            // Get values of table expressions involved in the lookup and compress them
+            compress_expressions(&self.table_expressions),
+        )
+    }
 }
 
 impl<C: CurveAffine> Permuted<C> {
+    /// Writes the commitments to the permuted input and table expressions to
+    /// the transcript.
+    pub(in crate::plonk) fn write_commitments<E: EncodedChallenge<C>, T: TranscriptWrite<C, E>>(
+        &self,
+        transcript: &mut T,
+    ) -> Result<(), Error> {
+        // Hash permuted input and table commitments
+        transcript.write_points(&[
+            self.permuted_input_commitment,
+            self.permuted_table_commitment,
+        ])?;
+
+        Ok(())
+    }
+
     /// Given a Lookup with input expressions, table expressions, and the permuted
     /// input expression and permuted table expression, this method constructs the
     /// grand product polynomial over the lookup. The grand product polynomial
     /// is used to populate the Product<C> struct. The Product<C> struct is
     /// added to the Lookup and finally returned by the method.
+    #[allow(clippy::too_many_arguments)]
     pub(in crate::plonk) fn commit_product<
         'params,
         P: Params<'params, C>,
@@ -174,6 +217,7 @@ impl<C: CurveAffine> Permuted<C> {
         self,
         pk: &ProvingKey<C>,
         params: &P,
+        engine: &dyn Engine<C>,
         beta: ChallengeBeta<C>,
         gamma: ChallengeGamma<C>,
         mut rng: R,
@@ -244,7 +288,7 @@ impl<C: CurveAffine> Permuted<C> {
             // be a boolean (and ideally 1, else soundness is broken)
             .take(params.n() as usize - blinding_factors)
             // Chain random blinding factors.
-            .chain((0..blinding_factors).map(|_| C::Scalar::random(&mut rng)))
+            .chain((0..blinding_factors).map(|_| pk.vk.cs.blinding_scalar(&mut rng)))
             .collect::<Vec<_>>();
         assert_eq!(z.len(), params.n() as usize);
         let z = pk.vk.domain.lagrange_from_vec(z);
@@ -287,9 +331,11 @@ impl<C: CurveAffine> Permuted<C> {
             assert_eq!(z[u], C::Scalar::ONE);
         }
 
-        let product_blind = Blind(C::Scalar::random(rng));
-        let product_commitment = params.commit_lagrange(&z, product_blind).to_affine();
-        let z = pk.vk.domain.lagrange_to_coeff(z);
+        let product_blind = pk.vk.cs.blind(rng);
+        let product_commitment = params
+            .commit_lagrange_with_engine(engine, &z, product_blind)
+            .to_affine();
+        let z = pk.vk.domain.lagrange_to_coeff_with_engine(engine, z);
 
         // Hash product commitment
         transcript.write_point(product_commitment)?;
@@ -448,8 +494,9 @@ fn permute_expression_pair<'params, C: CurveAffine, P: Params<'params, C>, R: Rn
     assert!(repeated_input_rows.is_empty());
 
     permuted_input_expression
-        .extend((0..(blinding_factors + 1)).map(|_| C::Scalar::random(&mut rng)));
-    permuted_table_coeffs.extend((0..(blinding_factors + 1)).map(|_| C::Scalar::random(&mut rng)));
+        .extend((0..(blinding_factors + 1)).map(|_| pk.vk.cs.blinding_scalar(&mut rng)));
+    permuted_table_coeffs
+        .extend((0..(blinding_factors + 1)).map(|_| pk.vk.cs.blinding_scalar(&mut rng)));
     assert_eq!(permuted_input_expression.len(), params.n() as usize);
     assert_eq!(permuted_table_coeffs.len(), params.n() as usize);
 
@@ -40,8 +40,9 @@ impl<F: Field> Argument<F> {
         &self,
         transcript: &mut T,
     ) -> Result<PermutationCommitments<C>, Error> {
-        let permuted_input_commitment = transcript.read_point()?;
-        let permuted_table_commitment = transcript.read_point()?;
+        let commitments = transcript.read_points(2)?;
+        let (permuted_input_commitment, permuted_table_commitment) =
+            (commitments[0], commitments[1]);
 
         Ok(PermutationCommitments {
             permuted_input_commitment,
@@ -0,0 +1,76 @@
+use std::fmt::Debug;
+
+use ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
+use halo2curves::pairing::MultiMillerLoop;
+use rand_core::RngCore;
+
+use super::{create_proof, verify_proof_exact, Circuit, Error, ProvingKey, VerifyingKey};
+use crate::helpers::SerdeCurveAffine;
+use crate::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use crate::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use crate::poly::kzg::strategy::SingleStrategy;
+use crate::transcript::{Blake2bRead, Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+
+/// Creates a proof for `circuits` with the KZG commitment scheme, returning
+/// its bytes; `instances` holds the instance columns of each circuit.
+///
+/// This fixes the configuration [`verify`] checks proofs with: the SHPLONK
+/// multiopen argument, and a Blake2b transcript with [`Challenge255`]
+/// challenges. The proof is the same as the one [`create_proof`] writes with
+/// [`ProverSHPLONK`] and [`Blake2bWrite`], so either function can be
+/// combined with the lower-level API of the other side.
+pub fn prove<E, ConcreteCircuit, R>(
+    params: &ParamsKZG<E>,
+    pk: &ProvingKey<E::G1Affine>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[E::Scalar]]],
+    rng: R,
+) -> Result<Vec<u8>, Error>
+where
+    E: MultiMillerLoop + Debug,
+    E::Scalar: PrimeField + Ord + WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    ConcreteCircuit: Circuit<E::Scalar>,
+    R: RngCore,
+{
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<E>, ProverSHPLONK<_>, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        &mut transcript,
+    )?;
+    Ok(transcript.finalize())
+}
+
+/// Verifies a proof created by [`prove`], for circuits with the given
+/// instance columns.
+///
+/// The proof is read with a Blake2b transcript and checked with the SHPLONK
+/// multiopen verifier, using a [`SingleStrategy`] so that the final pairing
+/// check is done before returning: `Ok(())` means the proof is valid, with
+/// nothing left to the caller. A proof followed by extra bytes is rejected
+/// with [`Error::TrailingBytes`].
+pub fn verify<E>(
+    params: &ParamsKZG<E>,
+    vk: &VerifyingKey<E::G1Affine>,
+    instances: &[&[&[E::Scalar]]],
+    proof: &[u8],
+) -> Result<(), Error>
+where
+    E: MultiMillerLoop + Debug,
+    E::Scalar: PrimeField + Ord + WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    verify_proof_exact::<
+        KZGCommitmentScheme<E>,
+        VerifierSHPLONK<_>,
+        _,
+        Blake2bRead<_, _, Challenge255<_>>,
+        _,
+    >(params, vk, SingleStrategy::new(params), instances, proof)
+}
@@ -4,10 +4,10 @@ use super::circuit::{Any, Column};
 use crate::{
     arithmetic::CurveAffine,
     helpers::{
-        polynomial_slice_byte_length, read_polynomial_vec, write_polynomial_slice,
-        SerdeCurveAffine, SerdePrimeField,
+        polynomial_slice_byte_length, read_polynomial_vec, skip_polynomial_vec,
+        write_polynomial_slice, SerdeCurveAffine, SerdePrimeField,
     },
-    poly::{Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial},
+    poly::{Coeff, LagrangeCoeff, Polynomial},
     SerdeFormat,
 };
 
@@ -18,9 +18,11 @@ pub(crate) mod verifier;
 pub use keygen::Assembly;
 
 use std::io;
+#[cfg(feature = "disk-offload")]
+use std::path::Path;
 
 /// A permutation argument.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Argument {
     /// A sequence of columns involved in the argument.
     pub(super) columns: Vec<Column<Any>>,
@@ -124,10 +126,9 @@ impl<C: CurveAffine> VerifyingKey<C> {
 
 /// The proving key for a single permutation argument.
 #[derive(Clone, Debug)]
-pub(crate) struct ProvingKey<C: CurveAffine> {
+pub struct ProvingKey<C: CurveAffine> {
     permutations: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
     polys: Vec<Polynomial<C::Scalar, Coeff>>,
-    pub(super) cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
 }
 
 impl<C: SerdeCurveAffine> ProvingKey<C>
@@ -138,14 +139,23 @@ where
     pub(super) fn read<R: io::Read>(reader: &mut R, format: SerdeFormat) -> io::Result<Self> {
         let permutations = read_polynomial_vec(reader, format)?;
         let polys = read_polynomial_vec(reader, format)?;
-        let cosets = read_polynomial_vec(reader, format)?;
         Ok(ProvingKey {
             permutations,
             polys,
-            cosets,
         })
     }
 
+    /// Reads a proving key written before the layout of proving keys was
+    /// versioned, skipping the cosets it stores.
+    pub(super) fn read_legacy<R: io::Read>(
+        reader: &mut R,
+        format: SerdeFormat,
+    ) -> io::Result<Self> {
+        let pk = Self::read(reader, format)?;
+        skip_polynomial_vec::<R, C::Scalar>(reader, format)?;
+        Ok(pk)
+    }
+
     /// Writes proving key for a single permutation argument to buffer using `Polynomial::write`.  
     pub(super) fn write<W: io::Write>(
         &self,
@@ -154,7 +164,6 @@ where
     ) -> io::Result<()> {
         write_polynomial_slice(&self.permutations, writer, format)?;
         write_polynomial_slice(&self.polys, writer, format)?;
-        write_polynomial_slice(&self.cosets, writer, format)?;
         Ok(())
     }
 }
@@ -162,8 +171,37 @@ where
 impl<C: CurveAffine> ProvingKey<C> {
     /// Gets the total number of bytes in the serialization of `self`
     pub(super) fn bytes_length(&self) -> usize {
-        polynomial_slice_byte_length(&self.permutations)
-            + polynomial_slice_byte_length(&self.polys)
-            + polynomial_slice_byte_length(&self.cosets)
+        polynomial_slice_byte_length(&self.permutations) + polynomial_slice_byte_length(&self.polys)
+    }
+
+    /// Returns the permutation polynomials in Lagrange form.
+    pub(super) fn permutations(&self) -> &[Polynomial<C::Scalar, LagrangeCoeff>] {
+        &self.permutations
+    }
+
+    /// Returns the permutation polynomials in coefficient form.
+    pub(super) fn polys(&self) -> &[Polynomial<C::Scalar, Coeff>] {
+        &self.polys
+    }
+
+    /// Returns whether the `i`-th permutation polynomial is the same in
+    /// `other`.
+    pub(super) fn unchanged_from(&self, other: &Self, i: usize) -> bool {
+        other
+            .permutations
+            .get(i)
+            .map_or(false, |old| old[..] == self.permutations[i][..])
+    }
+
+    /// Moves the permutation polynomials, in both bases, to files in `dir`.
+    #[cfg(feature = "disk-offload")]
+    pub(super) fn offload(&mut self, dir: &Path) -> io::Result<()> {
+        for poly in &mut self.permutations {
+            poly.offload(dir)?;
+        }
+        for poly in &mut self.polys {
+            poly.offload(dir)?;
+        }
+        Ok(())
     }
 }
@@ -3,16 +3,18 @@ use group::Curve;
 
 use super::{Argument, ProvingKey, VerifyingKey};
 use crate::{
-    arithmetic::{parallelize, CurveAffine},
+    arithmetic::{parallelize, CurveAffine, Engine},
     plonk::{Any, Column, Error},
     poly::{
         commitment::{Blind, Params},
-        EvaluationDomain,
+        EvaluationDomain, LagrangeCoeff, Polynomial,
     },
 };
 
+use crate::multicore::{IntoParallelIterator, ParallelIterator};
+
 #[cfg(feature = "multicore")]
-use crate::multicore::{IndexedParallelIterator, ParallelIterator};
+use crate::multicore::IndexedParallelIterator;
 
 #[cfg(feature = "thread-safe-region")]
 use std::collections::{BTreeSet, HashMap};
@@ -25,10 +27,56 @@ pub struct Assembly {
     columns: Vec<Column<Any>>,
     /// Mapping of the actual copies done.
     mapping: Vec<Vec<(usize, usize)>>,
-    /// Some aux data used to swap positions directly when sorting.
-    aux: Vec<Vec<(usize, usize)>>,
-    /// More aux data
-    sizes: Vec<Vec<usize>>,
+    /// Which cells are already in the same cycle of `mapping`.
+    cycles: DisjointSets,
+}
+
+#[cfg(not(feature = "thread-safe-region"))]
+/// Disjoint sets over the cells of the permutation, with cell `(i, j)` stored
+/// at index `i * n + j`. Uses union by rank and path halving, so that checking
+/// whether two cells are in the same cycle does not require walking it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct DisjointSets {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+#[cfg(not(feature = "thread-safe-region"))]
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        DisjointSets {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merges the sets containing `a` and `b`, returning `false` if they were
+    /// already the same set.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+
+        let (root, child) = if self.rank[a] < self.rank[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.parent[child] = root;
+        if self.rank[root] == self.rank[child] {
+            self.rank[root] += 1;
+        }
+        true
+    }
 }
 
 #[cfg(not(feature = "thread-safe-region"))]
@@ -43,13 +91,11 @@ impl Assembly {
         }
 
         // Before any equality constraints are applied, every cell in the permutation is
-        // in a 1-cycle; therefore mapping and aux are identical, because every cell is
-        // its own distinguished element.
+        // in a 1-cycle of its own.
         Assembly {
             columns: p.columns.clone(),
-            mapping: columns.clone(),
-            aux: columns,
-            sizes: vec![vec![1usize; n]; p.columns.len()],
+            mapping: columns,
+            cycles: DisjointSets::new(p.columns.len() * n),
         }
     }
 
@@ -80,29 +126,16 @@ impl Assembly {
 
         // See book/src/design/permutation.md for a description of this algorithm.
 
-        let mut left_cycle = self.aux[left_column][left_row];
-        let mut right_cycle = self.aux[right_column][right_row];
-
-        // If left and right are in the same cycle, do nothing.
-        if left_cycle == right_cycle {
+        // If left and right are in the same cycle, do nothing. Otherwise,
+        // swapping their images merges the two cycles.
+        let n = self.mapping[0].len();
+        if !self
+            .cycles
+            .union(left_column * n + left_row, right_column * n + right_row)
+        {
             return Ok(());
         }
 
-        if self.sizes[left_cycle.0][left_cycle.1] < self.sizes[right_cycle.0][right_cycle.1] {
-            std::mem::swap(&mut left_cycle, &mut right_cycle);
-        }
-
-        // Merge the right cycle into the left one.
-        self.sizes[left_cycle.0][left_cycle.1] += self.sizes[right_cycle.0][right_cycle.1];
-        let mut i = right_cycle;
-        loop {
-            self.aux[i.0][i.1] = left_cycle;
-            i = self.mapping[i.0][i.1];
-            if i == right_cycle {
-                break;
-            }
-        }
-
         let tmp = self.mapping[left_column][left_row];
         self.mapping[left_column][left_row] = self.mapping[right_column][right_row];
         self.mapping[right_column][right_row] = tmp;
@@ -110,22 +143,44 @@ impl Assembly {
         Ok(())
     }
 
-    pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C>>(
+    pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C> + Sync>(
         self,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         p: &Argument,
     ) -> VerifyingKey<C> {
-        build_vk(params, domain, p, |i, j| self.mapping[i][j])
+        build_vk(params, engine, domain, p, |i, j| self.mapping[i][j])
     }
 
     pub(crate) fn build_pk<'params, C: CurveAffine, P: Params<'params, C>>(
         self,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         p: &Argument,
     ) -> ProvingKey<C> {
-        build_pk(params, domain, p, |i, j| self.mapping[i][j])
+        build_pk(params, engine, domain, p, |i, j| self.mapping[i][j])
+    }
+
+    pub(crate) fn build_keys_reusing<'params, C: CurveAffine, P: Params<'params, C> + Sync>(
+        self,
+        params: &P,
+        engine: &dyn Engine<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        p: &Argument,
+        old_pk: &ProvingKey<C>,
+        old_vk: &VerifyingKey<C>,
+    ) -> (ProvingKey<C>, VerifyingKey<C>) {
+        build_keys_reusing(
+            params,
+            engine,
+            domain,
+            p,
+            |i, j| self.mapping[i][j],
+            old_pk,
+            old_vk,
+        )
     }
 
     /// Returns columns that participate in the permutation argument.
@@ -290,24 +345,47 @@ impl Assembly {
         }
     }
 
-    pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C>>(
-        &mut self,
+    pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C> + Sync>(
+        mut self,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         p: &Argument,
     ) -> VerifyingKey<C> {
         self.build_ordered_mapping();
-        build_vk(params, domain, p, |i, j| self.mapping_at_idx(i, j))
+        build_vk(params, engine, domain, p, |i, j| self.mapping_at_idx(i, j))
     }
 
     pub(crate) fn build_pk<'params, C: CurveAffine, P: Params<'params, C>>(
-        &mut self,
+        mut self,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         p: &Argument,
     ) -> ProvingKey<C> {
         self.build_ordered_mapping();
-        build_pk(params, domain, p, |i, j| self.mapping_at_idx(i, j))
+        build_pk(params, engine, domain, p, |i, j| self.mapping_at_idx(i, j))
+    }
+
+    pub(crate) fn build_keys_reusing<'params, C: CurveAffine, P: Params<'params, C> + Sync>(
+        mut self,
+        params: &P,
+        engine: &dyn Engine<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        p: &Argument,
+        old_pk: &ProvingKey<C>,
+        old_vk: &VerifyingKey<C>,
+    ) -> (ProvingKey<C>, VerifyingKey<C>) {
+        self.build_ordered_mapping();
+        build_keys_reusing(
+            params,
+            engine,
+            domain,
+            p,
+            |i, j| self.mapping_at_idx(i, j),
+            old_pk,
+            old_vk,
+        )
     }
 
     /// Returns columns that participate in the permutation argument.
@@ -336,12 +414,32 @@ impl Assembly {
     }
 }
 
-pub(crate) fn build_pk<'params, C: CurveAffine, P: Params<'params, C>>(
+impl<C: CurveAffine> ProvingKey<C> {
+    /// Commits to the permutation polynomials, giving the same verifying key
+    /// as building it from the assembly this proving key was built from.
+    pub(crate) fn build_vk<'params, P: Params<'params, C>>(&self, params: &P) -> VerifyingKey<C> {
+        let commitments = self
+            .permutations
+            .iter()
+            .map(|permutation| {
+                params
+                    .commit_lagrange(permutation, Blind::default())
+                    .to_affine()
+            })
+            .collect();
+
+        VerifyingKey { commitments }
+    }
+}
+
+/// Computes the permutation polynomials in Lagrange form, assigning
+/// `delta^i * omega^j` to the cell that `(i, j)` is mapped to.
+fn build_permutations<'params, C: CurveAffine, P: Params<'params, C>>(
     params: &P,
     domain: &EvaluationDomain<C::Scalar>,
     p: &Argument,
     mapping: impl Fn(usize, usize) -> (usize, usize) + Sync,
-) -> ProvingKey<C> {
+) -> Vec<Polynomial<C::Scalar, LagrangeCoeff>> {
     // Compute [omega^0, omega^1, ..., omega^{params.n - 1}]
     let mut omega_powers = vec![C::Scalar::ZERO; params.n() as usize];
     {
@@ -364,112 +462,272 @@ pub(crate) fn build_pk<'params, C: CurveAffine, P: Params<'params, C>>(
                 for v in omega_powers {
                     *v *= &cur;
                 }
-                cur *= &C::Scalar::DELTA;
+                cur *= &<C::Scalar as PrimeField>::DELTA;
             }
         });
     }
 
-    // Compute permutation polynomials, convert to coset form.
+    // Computes the permutation polynomial based on the permutation
+    // description in the assembly. Columns are filled one after another with
+    // their rows split across threads, as there are usually far fewer
+    // columns than threads.
     let mut permutations = vec![domain.empty_lagrange(); p.columns.len()];
-    {
-        parallelize(&mut permutations, |o, start| {
-            for (x, permutation_poly) in o.iter_mut().enumerate() {
-                let i = start + x;
-                for (j, p) in permutation_poly.iter_mut().enumerate() {
-                    let (permuted_i, permuted_j) = mapping(i, j);
-                    *p = deltaomega[permuted_i][permuted_j];
-                }
+    for (i, permutation_poly) in permutations.iter_mut().enumerate() {
+        parallelize(permutation_poly, |o, start| {
+            for (x, p) in o.iter_mut().enumerate() {
+                let (permuted_i, permuted_j) = mapping(i, start + x);
+                *p = deltaomega[permuted_i][permuted_j];
             }
         });
     }
 
-    let mut polys = vec![domain.empty_coeff(); p.columns.len()];
-    {
-        parallelize(&mut polys, |o, start| {
-            for (x, poly) in o.iter_mut().enumerate() {
-                let i = start + x;
-                let permutation_poly = permutations[i].clone();
-                *poly = domain.lagrange_to_coeff(permutation_poly);
-            }
-        });
-    }
+    permutations
+}
 
-    let mut cosets = vec![domain.empty_extended(); p.columns.len()];
-    {
-        parallelize(&mut cosets, |o, start| {
-            for (x, coset) in o.iter_mut().enumerate() {
-                let i = start + x;
-                let poly = polys[i].clone();
-                *coset = domain.coeff_to_extended(poly);
-            }
-        });
-    }
+pub(crate) fn build_pk<'params, C: CurveAffine, P: Params<'params, C>>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    p: &Argument,
+    mapping: impl Fn(usize, usize) -> (usize, usize) + Sync,
+) -> ProvingKey<C> {
+    let permutations = build_permutations(params, domain, p, mapping);
+
+    let polys: Vec<_> = (0..permutations.len())
+        .into_par_iter()
+        .map(|i| domain.lagrange_to_coeff_with_engine(engine, permutations[i].clone()))
+        .collect();
 
     ProvingKey {
         permutations,
         polys,
-        cosets,
     }
 }
 
-pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C>>(
+pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C> + Sync>(
     params: &P,
+    engine: &dyn Engine<C>,
     domain: &EvaluationDomain<C::Scalar>,
     p: &Argument,
     mapping: impl Fn(usize, usize) -> (usize, usize) + Sync,
 ) -> VerifyingKey<C> {
-    // Compute [omega^0, omega^1, ..., omega^{params.n - 1}]
-    let mut omega_powers = vec![C::Scalar::ZERO; params.n() as usize];
-    {
-        let omega = domain.get_omega();
-        parallelize(&mut omega_powers, |o, start| {
-            let mut cur = omega.pow_vartime([start as u64]);
-            for v in o.iter_mut() {
-                *v = cur;
-                cur *= &omega;
+    let permutations = build_permutations(params, domain, p, mapping);
+
+    // Pre-compute commitments for the URS.
+    let commitments = (0..permutations.len())
+        .into_par_iter()
+        .map(|i| {
+            params
+                .commit_lagrange_with_engine(engine, &permutations[i], Blind::default())
+                .to_affine()
+        })
+        .collect();
+
+    VerifyingKey { commitments }
+}
+
+/// Builds the proving and verifying keys like [`build_pk`] and [`build_vk`],
+/// taking the coefficient forms and commitments of the columns whose
+/// permutation polynomial is unchanged from `old_pk` and `old_vk`.
+pub(crate) fn build_keys_reusing<'params, C: CurveAffine, P: Params<'params, C> + Sync>(
+    params: &P,
+    engine: &dyn Engine<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    p: &Argument,
+    mapping: impl Fn(usize, usize) -> (usize, usize) + Sync,
+    old_pk: &ProvingKey<C>,
+    old_vk: &VerifyingKey<C>,
+) -> (ProvingKey<C>, VerifyingKey<C>) {
+    let permutations = build_permutations(params, domain, p, mapping);
+    let changed: Vec<bool> = permutations
+        .iter()
+        .enumerate()
+        .map(|(i, permutation)| {
+            old_pk
+                .permutations
+                .get(i)
+                .map_or(true, |old| old[..] != permutation[..])
+        })
+        .collect();
+
+    let polys: Vec<_> = (0..permutations.len())
+        .into_par_iter()
+        .map(|i| {
+            if changed[i] {
+                domain.lagrange_to_coeff_with_engine(engine, permutations[i].clone())
+            } else {
+                old_pk.polys[i].clone()
             }
         })
-    }
+        .collect();
+
+    let commitments = (0..permutations.len())
+        .into_par_iter()
+        .map(|i| {
+            if changed[i] {
+                params
+                    .commit_lagrange_with_engine(engine, &permutations[i], Blind::default())
+                    .to_affine()
+            } else {
+                old_vk.commitments[i]
+            }
+        })
+        .collect();
+
+    (
+        ProvingKey {
+            permutations,
+            polys,
+        },
+        VerifyingKey { commitments },
+    )
+}
 
-    // Compute [omega_powers * \delta^0, omega_powers * \delta^1, ..., omega_powers * \delta^m]
-    let mut deltaomega = vec![omega_powers; p.columns.len()];
-    {
-        parallelize(&mut deltaomega, |o, start| {
-            let mut cur = C::Scalar::DELTA.pow_vartime([start as u64]);
-            for omega_powers in o.iter_mut() {
-                for v in omega_powers {
-                    *v *= &cur;
-                }
-                cur *= &<C::Scalar as PrimeField>::DELTA;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{arithmetic::CpuEngine, poly::kzg::commitment::ParamsKZG};
+    use halo2curves::bn256::{Bn256, Fr, G1Affine};
+    use rand_core::OsRng;
+
+    #[test]
+    fn build_matches_sequential() {
+        const K: u32 = 4;
+        const NUM_COLUMNS: usize = 12;
+
+        let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+        let domain = EvaluationDomain::<Fr>::new(3, K);
+        let n = params.n() as usize;
+
+        let columns: Vec<Column<Any>> = (0..NUM_COLUMNS)
+            .map(|i| Column::new(i, Any::advice()))
+            .collect();
+        let mut argument = Argument::new();
+        for column in columns.iter() {
+            argument.add_column(*column);
+        }
+
+        // Link row 2 of every column with row 3 of the next one, giving
+        // disjoint 2-cycles.
+        let mut assembly = Assembly::new(n, &argument);
+        for i in 0..NUM_COLUMNS - 1 {
+            assembly.copy(columns[i], 2, columns[i + 1], 3).unwrap();
+        }
+
+        let pk: ProvingKey<G1Affine> = assembly
+            .clone()
+            .build_pk(&params, &CpuEngine, &domain, &argument);
+        let vk: VerifyingKey<G1Affine> = assembly.build_vk(&params, &CpuEngine, &domain, &argument);
+
+        let omega = domain.get_omega();
+        for (i, permutation) in pk.permutations.iter().enumerate() {
+            for (j, value) in permutation.iter().enumerate() {
+                let (target_i, target_j) = match j {
+                    2 if i + 1 < NUM_COLUMNS => (i + 1, 3),
+                    3 if i > 0 => (i - 1, 2),
+                    _ => (i, j),
+                };
+                let expected =
+                    Fr::DELTA.pow_vartime([target_i as u64]) * omega.pow_vartime([target_j as u64]);
+                assert_eq!(*value, expected);
             }
-        });
+        }
+
+        for (poly, permutation) in pk.polys.iter().zip(pk.permutations.iter()) {
+            let expected = domain.lagrange_to_coeff(permutation.clone());
+            assert_eq!(&poly[..], &expected[..]);
+        }
+
+        let commitments: Vec<G1Affine> = pk
+            .permutations
+            .iter()
+            .map(|permutation| {
+                params
+                    .commit_lagrange(permutation, Blind::default())
+                    .to_affine()
+            })
+            .collect();
+        assert_eq!(vk.commitments, commitments);
     }
 
-    // Computes the permutation polynomial based on the permutation
-    // description in the assembly.
-    let mut permutations = vec![domain.empty_lagrange(); p.columns.len()];
-    {
-        parallelize(&mut permutations, |o, start| {
-            for (x, permutation_poly) in o.iter_mut().enumerate() {
-                let i = start + x;
-                for (j, p) in permutation_poly.iter_mut().enumerate() {
-                    let (permuted_i, permuted_j) = mapping(i, j);
-                    *p = deltaomega[permuted_i][permuted_j];
+    #[cfg(not(feature = "thread-safe-region"))]
+    #[test]
+    fn union_find_matches_cycle_walk() {
+        use rand_chacha::ChaCha20Rng;
+        use rand_core::{RngCore, SeedableRng};
+
+        const N: usize = 1024;
+        const NUM_COLUMNS: usize = 8;
+        const NUM_COPIES: usize = 3000;
+
+        let columns: Vec<Column<Any>> = (0..NUM_COLUMNS)
+            .map(|i| Column::new(i, Any::advice()))
+            .collect();
+        let mut argument = Argument::new();
+        for column in columns.iter() {
+            argument.add_column(*column);
+        }
+        let mut assembly = Assembly::new(N, &argument);
+
+        // The cycle-walking assembly that tracked cycle membership in `aux`.
+        let mut mapping: Vec<Vec<(usize, usize)>> = (0..NUM_COLUMNS)
+            .map(|i| (0..N).map(|j| (i, j)).collect())
+            .collect();
+        let mut aux = mapping.clone();
+        let mut sizes = vec![vec![1usize; N]; NUM_COLUMNS];
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..NUM_COPIES {
+            let (left_column, left_row) = (
+                rng.next_u32() as usize % NUM_COLUMNS,
+                rng.next_u32() as usize % N,
+            );
+            let (right_column, right_row) = (
+                rng.next_u32() as usize % NUM_COLUMNS,
+                rng.next_u32() as usize % N,
+            );
+            assembly
+                .copy(
+                    columns[left_column],
+                    left_row,
+                    columns[right_column],
+                    right_row,
+                )
+                .unwrap();
+
+            let mut left_cycle = aux[left_column][left_row];
+            let mut right_cycle = aux[right_column][right_row];
+            if left_cycle == right_cycle {
+                continue;
+            }
+            if sizes[left_cycle.0][left_cycle.1] < sizes[right_cycle.0][right_cycle.1] {
+                std::mem::swap(&mut left_cycle, &mut right_cycle);
+            }
+            sizes[left_cycle.0][left_cycle.1] += sizes[right_cycle.0][right_cycle.1];
+            let mut i = right_cycle;
+            loop {
+                aux[i.0][i.1] = left_cycle;
+                i = mapping[i.0][i.1];
+                if i == right_cycle {
+                    break;
                 }
             }
-        });
-    }
+            let tmp = mapping[left_column][left_row];
+            mapping[left_column][left_row] = mapping[right_column][right_row];
+            mapping[right_column][right_row] = tmp;
+        }
 
-    // Pre-compute commitments for the URS.
-    let mut commitments = Vec::with_capacity(p.columns.len());
-    for permutation in &permutations {
-        // Compute commitment to permutation polynomial
-        commitments.push(
-            params
-                .commit_lagrange(permutation, Blind::default())
-                .to_affine(),
-        );
+        assert_eq!(assembly.mapping, mapping);
+
+        // Out-of-range rows and unknown columns are still rejected.
+        assert!(matches!(
+            assembly.copy(columns[0], N, columns[1], 0),
+            Err(Error::BoundsFailure)
+        ));
+        let unknown = Column::new(NUM_COLUMNS, Any::advice());
+        assert!(matches!(
+            assembly.copy(unknown, 0, columns[1], 0),
+            Err(Error::ColumnNotInPermutation(column)) if column == unknown
+        ));
     }
-
-    VerifyingKey { commitments }
 }
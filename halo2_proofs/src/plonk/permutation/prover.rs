@@ -9,7 +9,7 @@ use std::iter::{self, ExactSizeIterator};
 use super::super::{circuit::Any, ChallengeBeta, ChallengeGamma, ChallengeX};
 use super::{Argument, ProvingKey};
 use crate::{
-    arithmetic::{eval_polynomial, parallelize, CurveAffine},
+    arithmetic::{eval_polynomial, parallelize, CurveAffine, Engine},
     plonk::{self, Error},
     poly::{
         commitment::{Blind, Params},
@@ -53,6 +53,7 @@ impl Argument {
     >(
         &self,
         params: &P,
+        engine: &dyn Engine<C>,
         pk: &plonk::ProvingKey<C>,
         pkey: &ProvingKey<C>,
         advice: &[Polynomial<C::Scalar, LagrangeCoeff>],
@@ -80,6 +81,7 @@ impl Argument {
         let mut last_z = C::Scalar::ONE;
 
         let mut sets = vec![];
+        let mut commitments = vec![];
 
         for (columns, permutations) in self
             .columns
@@ -161,25 +163,21 @@ impl Argument {
             let mut z = domain.lagrange_from_vec(z);
             // Set blinding factors
             for z in &mut z[params.n() as usize - blinding_factors..] {
-                *z = C::Scalar::random(&mut rng);
+                *z = pk.vk.cs.blinding_scalar(&mut rng);
             }
             // Set new last_z
             last_z = z[params.n() as usize - (blinding_factors + 1)];
 
-            let blind = Blind(C::Scalar::random(&mut rng));
+            let blind = pk.vk.cs.blind(&mut rng);
 
-            let permutation_product_commitment_projective = params.commit_lagrange(&z, blind);
+            let permutation_product_commitment_projective =
+                params.commit_lagrange_with_engine(engine, &z, blind);
             let permutation_product_blind = blind;
-            let z = domain.lagrange_to_coeff(z);
-            let permutation_product_poly = z.clone();
+            let z = domain.lagrange_to_coeff_with_engine(engine, z);
+            let permutation_product_coset = domain.coeff_to_extended_with_engine(engine, z.clone());
+            let permutation_product_poly = z;
 
-            let permutation_product_coset = domain.coeff_to_extended(z.clone());
-
-            let permutation_product_commitment =
-                permutation_product_commitment_projective.to_affine();
-
-            // Hash the permutation product commitment
-            transcript.write_point(permutation_product_commitment)?;
+            commitments.push(permutation_product_commitment_projective.to_affine());
 
             sets.push(CommittedSet {
                 permutation_product_poly,
@@ -188,6 +186,9 @@ impl Argument {
             });
         }
 
+        // Hash the permutation product commitments
+        transcript.write_points(&commitments)?;
+
         Ok(Committed { sets })
     }
 }
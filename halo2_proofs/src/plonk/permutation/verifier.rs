@@ -41,11 +41,8 @@ impl Argument {
     ) -> Result<Committed<C>, Error> {
         let chunk_len = vk.cs_degree - 2;
 
-        let permutation_product_commitments = self
-            .columns
-            .chunks(chunk_len)
-            .map(|_| transcript.read_point())
-            .collect::<Result<Vec<_>, _>>()?;
+        let permutation_product_commitments =
+            transcript.read_points(self.columns.chunks(chunk_len).len())?;
 
         Ok(Committed {
             permutation_product_commitments,
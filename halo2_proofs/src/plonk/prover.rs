@@ -1,27 +1,41 @@
-use ff::{Field, FromUniformBytes, WithSmallOrderMulGroup};
+use ff::{Field, FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
 use group::Curve;
-use rand_core::RngCore;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
 use std::collections::BTreeSet;
 use std::ops::RangeTo;
+#[cfg(feature = "disk-offload")]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "multicore")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, iter};
 
+#[cfg(test)]
+use super::test_fixtures;
 use super::{
     circuit::{
         sealed::{self},
         Advice, Any, Assignment, Challenge, Circuit, Column, ConstraintSystem, Fixed, FloorPlanner,
         Instance, Selector,
     },
+    layout::{LayoutBuilder, ProofLayout},
+    listener::{ListenedTranscript, ListenerObserver, PendingEvents, ProverListener},
     lookup, permutation, shuffle, vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta,
-    ChallengeX, ChallengeY, Error, ProvingKey,
+    ChallengeX, ChallengeY, Error, Evaluator, InstanceMismatch, ProvingKey,
 };
 
+#[cfg(feature = "multicore")]
+use crate::{arithmetic::PoolEngine, multicore::ThreadPool};
 use crate::{
-    arithmetic::{eval_polynomial, CurveAffine},
+    arithmetic::{eval_polynomial, CpuEngine, CurveAffine, Engine},
     circuit::Value,
+    multicore::{IntoParallelIterator, ParallelIterator},
     plonk::Assigned,
     poly::{
         commitment::{Blind, CommitmentScheme, Params, Prover},
-        Basis, Coeff, LagrangeCoeff, Polynomial, ProverQuery,
+        Basis, Coeff, LagrangeCoeff, Polynomial, ProverQuery, ScratchPool,
     },
 };
 use crate::{
@@ -29,11 +43,33 @@ use crate::{
     transcript::{EncodedChallenge, TranscriptWrite},
 };
 use group::prime::PrimeCurveAffine;
+use tracing::debug;
+
+mod batch;
+pub use batch::BatchProver;
 
 /// This creates a proof for the provided `circuit` when given the public
 /// parameters `params` and the proving key [`ProvingKey`] that was
 /// generated previously for the same circuit. The provided `instances`
 /// are zero-padded internally.
+///
+/// All the randomness of the proof is drawn from `rng`, in this order:
+/// - for each phase and each circuit, the blinding rows of the phase's advice
+///   columns, then the commitment blinds of those columns, skipping the
+///   unblinded ones;
+/// - for each circuit, a seed for each lookup, from which the blinding rows
+///   and blinds of the lookup's permuted input and table columns are drawn,
+///   or those of its multiplicity column under
+///   [`LookupBackend::LogUp`](crate::plonk::LookupBackend::LogUp);
+/// - for each circuit, the blinding rows and blinds of the permutation's
+///   product columns, then of each lookup's product or running sum column and
+///   of each shuffle's product column;
+/// - the seed of the vanishing argument's random polynomial and its blind,
+///   then the blinds of the pieces of the quotient polynomial;
+/// - whatever the multiopen argument `P` draws.
+///
+/// Proving twice with `rng`s in the same state therefore yields the same
+/// proof; see [`create_proof_deterministic`].
 pub fn create_proof<
     'params,
     Scheme: CommitmentScheme,
@@ -47,153 +83,907 @@ pub fn create_proof<
     pk: &ProvingKey<Scheme::Curve>,
     circuits: &[ConcreteCircuit],
     instances: &[&[&[Scheme::Scalar]]],
-    mut rng: R,
+    rng: R,
     transcript: &mut T,
 ) -> Result<(), Error>
 where
     Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
 {
-    if circuits.len() != instances.len() {
-        return Err(Error::InvalidInstances);
-    }
+    create_proof_with_engine::<Scheme, P, _, _, _, _>(
+        params, pk, circuits, instances, rng, transcript, &CpuEngine,
+    )
+}
 
-    for instance in instances.iter() {
-        if instance.len() != pk.vk.cs.num_instance_columns {
-            return Err(Error::InvalidInstances);
-        }
-    }
+/// Same as [`create_proof`], drawing the randomness of the proof from a
+/// ChaCha RNG seeded with `seed`, so that the same seed yields byte-identical
+/// proofs. The resulting proofs are only zero-knowledge if `seed` is secret
+/// and never reused.
+pub fn create_proof_deterministic<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    seed: [u8; 32],
+    transcript: &mut T,
+) -> Result<(), Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    create_proof::<Scheme, P, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        ChaCha20Rng::from_seed(seed),
+        transcript,
+    )
+}
 
-    // Hash verification key into transcript
-    pk.vk.hash_into(transcript)?;
+/// Same as [`create_proof`], performing the multiexponentiations and FFTs of
+/// the circuit's polynomials with `engine`. The multiopen argument `P` still
+/// runs on the CPU.
+pub fn create_proof_with_engine<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+    engine: &dyn Engine<Scheme::Curve>,
+) -> Result<(), Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    create_proof_impl::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        transcript,
+        engine,
+        &mut NoopObserver,
+        &ProverOptions::default(),
+    )
+    .map(|_| ())
+}
 
-    let domain = &pk.vk.domain;
-    let mut meta = ConstraintSystem::default();
-    #[cfg(feature = "circuit-params")]
-    let config = ConcreteCircuit::configure_with_params(&mut meta, circuits[0].params());
-    #[cfg(not(feature = "circuit-params"))]
-    let config = ConcreteCircuit::configure(&mut meta);
+/// Same as [`create_proof`], additionally returning the challenges sampled
+/// and the advice commitments written while proving.
+pub fn create_proof_with_artifacts<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+) -> Result<ProofArtifacts<Scheme::Curve>, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    create_proof_impl::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        transcript,
+        &CpuEngine,
+        &mut NoopObserver,
+        &ProverOptions::default(),
+    )
+}
 
-    // Selector optimizations cannot be applied here; use the ConstraintSystem
-    // from the verification key.
-    let meta = &pk.vk.cs;
+/// Same as [`create_proof`], reporting the progress of the proof to
+/// `observer`.
+pub fn create_proof_observed<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+    O: ProverObserver,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+    observer: &mut O,
+) -> Result<(), Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    create_proof_impl::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        transcript,
+        &CpuEngine,
+        observer,
+        &ProverOptions::default(),
+    )
+    .map(|_| ())
+}
 
-    struct InstanceSingle<C: CurveAffine> {
-        pub instance_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
-        pub instance_polys: Vec<Polynomial<C::Scalar, Coeff>>,
+/// Same as [`create_proof`], reporting every commitment, challenge and
+/// evaluation that goes through the transcript to `listener`.
+///
+/// Record them with a [`TranscriptRecorder`](crate::plonk::TranscriptRecorder)
+/// on both sides, the verifier's with
+/// [`verify_proof_listened`](crate::plonk::verify_proof_listened), and
+/// [`diff_transcripts`](crate::plonk::diff_transcripts) points at where a
+/// proof that does not verify went wrong.
+pub fn create_proof_listened<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+    L: ProverListener<Scheme::Curve>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+    listener: &mut L,
+) -> Result<(), Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    let pending = PendingEvents::new();
+    create_proof_observed::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        &mut ListenedTranscript::new(transcript, &pending),
+        &mut ListenerObserver::new(&pending, listener),
+    )
+}
+
+/// Same as [`create_proof`], additionally returning how long each stage of
+/// the prover took.
+pub fn create_proof_timed<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+) -> Result<ProofTimings, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    let mut timings = ProofTimings::default();
+    create_proof_observed::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        transcript,
+        &mut timings,
+    )?;
+    Ok(timings)
+}
+
+/// Same as [`create_proof`], with the prover configured by `options`,
+/// additionally returning the [`ProofArtifacts`] of the proof.
+pub fn create_proof_with_options<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+    options: &ProverOptions,
+) -> Result<ProofArtifacts<Scheme::Curve>, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    if !options.record_layout {
+        return create_proof_impl::<Scheme, P, _, _, _, _, _>(
+            params,
+            pk,
+            circuits,
+            instances,
+            rng,
+            transcript,
+            &CpuEngine,
+            &mut NoopObserver,
+            options,
+        );
     }
 
-    let instance: Vec<InstanceSingle<Scheme::Curve>> = instances
-        .iter()
-        .map(|instance| -> Result<InstanceSingle<Scheme::Curve>, Error> {
-            let instance_values = instance
-                .iter()
-                .map(|values| {
-                    let mut poly = domain.empty_lagrange();
-                    assert_eq!(poly.len(), params.n() as usize);
-                    if values.len() > (poly.len() - (meta.blinding_factors() + 1)) {
-                        return Err(Error::InstanceTooLarge);
-                    }
-                    for (poly, value) in poly.iter_mut().zip(values.iter()) {
-                        if !P::QUERY_INSTANCE {
-                            transcript.common_scalar(*value)?;
-                        }
-                        *poly = *value;
-                    }
-                    Ok(poly)
-                })
-                .collect::<Result<Vec<_>, _>>()?;
+    let pending = PendingEvents::new();
+    let mut layout = LayoutBuilder::default();
+    let mut artifacts = create_proof_impl::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        circuits,
+        instances,
+        rng,
+        &mut ListenedTranscript::new(transcript, &pending),
+        &CpuEngine,
+        &mut ListenerObserver::new(&pending, &mut layout),
+        options,
+    )?;
+    artifacts.layout = Some(layout.finish());
+    Ok(artifacts)
+}
 
-            if P::QUERY_INSTANCE {
-                let instance_commitments_projective: Vec<_> = instance_values
-                    .iter()
-                    .map(|poly| params.commit_lagrange(poly, Blind::default()))
-                    .collect();
-                let mut instance_commitments =
-                    vec![Scheme::Curve::identity(); instance_commitments_projective.len()];
-                <Scheme::Curve as CurveAffine>::CurveExt::batch_normalize(
-                    &instance_commitments_projective,
-                    &mut instance_commitments,
-                );
-                let instance_commitments = instance_commitments;
-                drop(instance_commitments_projective);
+/// Options for [`create_proof_with_options`]. The default options prove as
+/// [`create_proof`] does.
+#[derive(Clone, Debug, Default)]
+pub struct ProverOptions {
+    #[cfg(feature = "disk-offload")]
+    disk_offload: Option<PathBuf>,
+    gate_chunks: ChunkSize,
+    argument_chunks: ChunkSize,
+    fresh_buffers: bool,
+    evaluation: EvaluationStrategy,
+    record_layout: bool,
+    #[cfg(feature = "multicore")]
+    compute_pool: Option<Arc<ThreadPool>>,
+}
 
-                for commitment in &instance_commitments {
-                    transcript.common_point(*commitment)?;
-                }
-            }
+/// How the rows of the extended domain are split into parallel tasks while
+/// computing the quotient polynomial.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChunkSize {
+    /// One task per thread, each with the same number of rows give or take
+    /// one.
+    #[default]
+    PerThread,
+    /// Tasks of this many rows.
+    Rows(usize),
+    /// Evaluates the first rows on their own and times them, then splits the
+    /// remaining rows into tasks that are long enough to amortize the cost of
+    /// spawning them, but no longer than one per thread. The measurement is
+    /// repeated each time the rows are split, as the cost of a row differs
+    /// between the gates and each argument.
+    Auto,
+}
 
-            let instance_polys: Vec<_> = instance_values
-                .iter()
-                .map(|poly| {
-                    let lagrange_vec = domain.lagrange_from_vec(poly.to_vec());
-                    domain.lagrange_to_coeff(lagrange_vec)
-                })
-                .collect();
+/// How the constraints are evaluated on the rows of the extended domain
+/// while computing the quotient polynomial. The proof is the same with
+/// either strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvaluationStrategy {
+    /// Evaluates the constraints with the evaluator compiled from the
+    /// constraint system, which computes the subexpressions shared between
+    /// constraints once per row but keeps a table of intermediate values for
+    /// each thread. The proving key builds it for the first proof that uses
+    /// it.
+    #[default]
+    Compiled,
+    /// Walks the expressions of the constraint system at each row. This needs
+    /// no precomputation and no table of intermediate values, but evaluates
+    /// the subexpressions shared between constraints once for each of them.
+    /// The rows are still split as given by the chunk sizes.
+    Interpreted,
+}
 
-            Ok(InstanceSingle {
-                instance_values,
-                instance_polys,
-            })
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+impl ProverOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    #[derive(Clone)]
-    struct AdviceSingle<C: CurveAffine, B: Basis> {
-        pub advice_polys: Vec<Polynomial<C::Scalar, B>>,
-        pub advice_blinds: Vec<Blind<C::Scalar>>,
+    /// Keeps the Lagrange-basis values of the instance columns, and those of
+    /// the advice columns of each phase once they are committed, in files
+    /// created in the directory `path` and mapped into memory, so that the
+    /// operating system can page them out instead of running out of memory.
+    /// Proving is slower, but the proof is the same.
+    ///
+    /// The files are removed as the prover drops the values. To also move the
+    /// fixed and permutation columns of the proving key, which are the
+    /// largest, see [`ProvingKey::offload_to_disk`].
+    ///
+    /// Only available with the `disk-offload` feature.
+    #[cfg(feature = "disk-offload")]
+    pub fn disk_offload(mut self, path: impl Into<PathBuf>) -> Self {
+        self.disk_offload = Some(path.into());
+        self
     }
 
-    struct WitnessCollection<'a, F: Field> {
-        k: u32,
-        current_phase: sealed::Phase,
-        advice: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
-        challenges: &'a HashMap<usize, F>,
-        instances: &'a [&'a [F]],
-        usable_rows: RangeTo<usize>,
-        _marker: std::marker::PhantomData<F>,
+    /// Splits the rows as given by `chunk_size` when evaluating the custom
+    /// gates for the quotient polynomial.
+    pub fn gate_chunk_size(mut self, chunk_size: ChunkSize) -> Self {
+        self.gate_chunks = chunk_size;
+        self
     }
 
-    impl<'a, F: Field> Assignment<F> for WitnessCollection<'a, F> {
-        fn enter_region<NR, N>(&mut self, _: N)
-        where
-            NR: Into<String>,
-            N: FnOnce() -> NR,
-        {
-            // Do nothing; we don't care about regions in this context.
-        }
+    /// Splits the rows as given by `chunk_size` when evaluating the
+    /// constraints of the permutation, lookup and shuffle arguments for the
+    /// quotient polynomial.
+    pub fn argument_chunk_size(mut self, chunk_size: ChunkSize) -> Self {
+        self.argument_chunks = chunk_size;
+        self
+    }
 
-        fn exit_region(&mut self) {
-            // Do nothing; we don't care about regions in this context.
-        }
+    /// Whether the cosets computed while evaluating the quotient polynomial
+    /// reuse the buffers of those the prover is done with, which is the
+    /// default, or are each allocated anew. Reusing them saves an allocation
+    /// of the size of the extended domain per column and per argument of each
+    /// circuit, but keeps the buffers alive until the evaluation is done. The
+    /// proof is the same either way.
+    pub fn reuse_buffers(mut self, reuse: bool) -> Self {
+        self.fresh_buffers = !reuse;
+        self
+    }
 
-        fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
-        where
-            A: FnOnce() -> AR,
-            AR: Into<String>,
-        {
-            // We only care about advice columns here
+    /// Evaluates the constraints for the quotient polynomial as given by
+    /// `strategy`.
+    pub fn evaluation_strategy(mut self, strategy: EvaluationStrategy) -> Self {
+        self.evaluation = strategy;
+        self
+    }
 
-            Ok(())
-        }
+    /// Records where each component of the proof lies within the bytes
+    /// written to the transcript, as derived from the values the prover
+    /// wrote, for [`ProofArtifacts::layout`] to return. See [`ProofLayout`].
+    pub fn record_layout(mut self) -> Self {
+        self.record_layout = true;
+        self
+    }
 
-        fn annotate_column<A, AR>(&mut self, _annotation: A, _column: Column<Any>)
-        where
-            A: FnOnce() -> AR,
-            AR: Into<String>,
-        {
-            // Do nothing
+    /// Runs the multiexponentiations and FFTs of the circuit's polynomials,
+    /// and the evaluation of the quotient polynomial, in `pool` instead of
+    /// the pool of the calling thread.
+    ///
+    /// The circuits are still synthesized on the calling thread, so any
+    /// parallel work of `synthesize` runs in the pool the prover is called
+    /// from: the global one by default, or the one whose
+    /// [`ThreadPool::install`] the prover is called within. Keeping the two
+    /// apart prevents long synthesis tasks from delaying the FFTs, and the
+    /// other way around. The multiopen argument also runs in the pool of the
+    /// calling thread.
+    #[cfg(feature = "multicore")]
+    pub fn compute_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.compute_pool = Some(pool);
+        self
+    }
+
+    /// Runs `f` in the compute pool, if any.
+    fn in_compute_pool<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        #[cfg(feature = "multicore")]
+        if let Some(pool) = &self.compute_pool {
+            return pool.install(f);
         }
+        f()
+    }
 
-        fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
-            if !self.usable_rows.contains(&row) {
-                return Err(Error::not_enough_rows_available(self.k));
+    /// Moves `polys` to disk if disk offloading is enabled.
+    fn offload<F: PrimeField, B>(&self, polys: &mut [Polynomial<F, B>]) -> Result<(), Error> {
+        #[cfg(feature = "disk-offload")]
+        if let Some(dir) = &self.disk_offload {
+            for poly in polys {
+                poly.offload(dir)?;
             }
-
-            self.instances
-                .get(column.index())
-                .and_then(|column| column.get(row))
-                .map(|v| Value::known(*v))
-                .ok_or(Error::BoundsFailure)
+        }
+        #[cfg(not(feature = "disk-offload"))]
+        let _ = polys;
+        Ok(())
+    }
+}
+
+/// A group of commitments written by the prover, as reported to
+/// [`ProverObserver::commitment_done`] and to [`ProverListener`]s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentKind {
+    /// The advice columns of a phase.
+    Advice {
+        /// The phase of the columns.
+        phase: u8,
+    },
+    /// The permuted input and table columns of the lookups, or their
+    /// multiplicity columns under
+    /// [`LookupBackend::LogUp`](crate::plonk::LookupBackend::LogUp).
+    LookupPermuted,
+    /// The grand product column of the permutation argument.
+    PermutationProduct,
+    /// The grand product columns of the lookups, or their running sum
+    /// columns under
+    /// [`LookupBackend::LogUp`](crate::plonk::LookupBackend::LogUp).
+    LookupProduct,
+    /// The grand product columns of the shuffles.
+    ShuffleProduct,
+    /// The random polynomial blinding the quotient polynomial.
+    VanishingRandom,
+    /// The pieces of the quotient polynomial. Only reported to listeners;
+    /// observers get [`ProverObserver::quotient_done`] instead.
+    Quotient,
+    /// The commitments of the multiopen argument. Only reported to
+    /// listeners; observers get [`ProverObserver::multiopen_done`] instead.
+    Multiopen,
+}
+
+/// Receives the progress of a proof created with [`create_proof_observed`].
+///
+/// Callbacks arrive in the order of the protocol; all of them do nothing by
+/// default.
+pub trait ProverObserver {
+    /// Whether the prover measures the durations passed to the callbacks. If
+    /// `false`, they are all zero and the prover never reads the clock.
+    const TIMED: bool = true;
+
+    /// Called before the advice columns of `phase` are synthesized.
+    fn phase_started(&mut self, _phase: u8) {}
+
+    /// Called once the advice columns of `phase` have been synthesized for
+    /// the `index`-th circuit.
+    fn synthesis_done(&mut self, _phase: u8, _index: usize, _duration: Duration) {}
+
+    /// Called once the commitments of `kind` for the `index`-th circuit have
+    /// been written to the transcript. [`CommitmentKind::VanishingRandom`] is
+    /// shared by all circuits and always has index 0.
+    fn commitment_done(&mut self, _kind: CommitmentKind, _index: usize, _duration: Duration) {}
+
+    /// Called while computing the quotient polynomial, before
+    /// [`Self::quotient_split`], with the time spent on each group of custom
+    /// gates over all circuits. Only called if [`Self::TIMED`] is `true`.
+    fn gates_evaluated(&mut self, _timings: &[GateTiming]) {}
+
+    /// Called just before [`Self::quotient_done`] with the parts of the
+    /// quotient's duration spent evaluating the constraints and performing
+    /// FFTs.
+    fn quotient_split(&mut self, _evaluation: Duration, _fft: Duration) {}
+
+    /// Called once the quotient polynomial has been computed and committed to.
+    fn quotient_done(&mut self, _duration: Duration) {}
+
+    /// Called once the evaluations of all polynomials at the challenge `x`
+    /// have been written to the transcript.
+    fn evaluations_done(&mut self, _duration: Duration) {}
+
+    /// Called once the multiopen argument has been written to the transcript.
+    fn multiopen_done(&mut self, _duration: Duration) {}
+}
+
+/// A [`ProverObserver`] that ignores all callbacks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopObserver;
+
+impl ProverObserver for NoopObserver {
+    const TIMED: bool = false;
+}
+
+/// A [`ProverObserver`] that emits every callback as a `tracing` event at the
+/// `DEBUG` level.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoggingObserver;
+
+impl ProverObserver for LoggingObserver {
+    fn phase_started(&mut self, phase: u8) {
+        debug!(target: "prover", phase, "phase started");
+    }
+
+    fn synthesis_done(&mut self, phase: u8, index: usize, duration: Duration) {
+        debug!(target: "prover", phase, index, ?duration, "witness synthesized");
+    }
+
+    fn commitment_done(&mut self, kind: CommitmentKind, index: usize, duration: Duration) {
+        debug!(target: "prover", ?kind, index, ?duration, "commitments written");
+    }
+
+    fn quotient_done(&mut self, duration: Duration) {
+        debug!(target: "prover", ?duration, "quotient committed");
+    }
+
+    fn gates_evaluated(&mut self, timings: &[GateTiming]) {
+        for timing in timings {
+            let gates = &timing.gates;
+            let duration = timing.duration;
+            debug!(target: "prover", ?gates, cost = timing.cost, ?duration, "gates evaluated");
+        }
+    }
+
+    fn quotient_split(&mut self, evaluation: Duration, fft: Duration) {
+        debug!(target: "prover", ?evaluation, ?fft, "quotient computed");
+    }
+
+    fn evaluations_done(&mut self, duration: Duration) {
+        debug!(target: "prover", ?duration, "evaluations written");
+    }
+
+    fn multiopen_done(&mut self, duration: Duration) {
+        debug!(target: "prover", ?duration, "multiopen done");
+    }
+}
+
+/// The time spent evaluating a group of custom gates while computing the
+/// quotient polynomial, as reported to [`ProverObserver::gates_evaluated`].
+///
+/// The prover evaluates consecutive gates together, up to a total cost, and
+/// each costlier gate on its own; see [`Gate::cost`](crate::plonk::Gate::cost).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GateTiming {
+    /// The names of the gates of the group.
+    pub gates: Vec<String>,
+    /// The sum of the costs of the gates.
+    pub cost: usize,
+    /// The time spent evaluating the gates on every row of the extended
+    /// domain, over all circuits.
+    pub duration: Duration,
+}
+
+/// The time spent in each stage of a proof, as returned by
+/// [`create_proof_timed`].
+///
+/// The stages do not overlap, so their durations add up to roughly the time
+/// taken by the whole proof; see [`ProofTimings::total`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofTimings {
+    /// The time spent synthesizing the witness of each phase, over all
+    /// circuits.
+    pub synthesis: Vec<Duration>,
+    /// The time spent committing to advice columns.
+    pub advice_commitments: Duration,
+    /// The time spent computing and committing to the permuted columns and
+    /// the grand products of the lookups.
+    pub lookups: Duration,
+    /// The time spent computing and committing to the grand products of the
+    /// permutation argument.
+    pub permutation: Duration,
+    /// The time spent computing and committing to the grand products of the
+    /// shuffles.
+    pub shuffles: Duration,
+    /// The time spent committing to the vanishing argument's random
+    /// polynomial and computing and committing to the quotient polynomial.
+    pub quotient: Duration,
+    /// The part of [`Self::quotient`] spent evaluating the constraints over
+    /// the extended domain.
+    pub quotient_evaluation: Duration,
+    /// The part of [`Self::quotient`] spent performing FFTs.
+    pub quotient_fft: Duration,
+    /// The part of [`Self::quotient_evaluation`] spent on each group of
+    /// custom gates.
+    pub gates: Vec<GateTiming>,
+    /// The time spent evaluating polynomials at the challenge `x`.
+    pub evaluations: Duration,
+    /// The time spent in the multiopen argument.
+    pub multiopen: Duration,
+}
+
+impl ProofTimings {
+    /// Returns the sum of the durations of all stages.
+    pub fn total(&self) -> Duration {
+        self.synthesis.iter().sum::<Duration>()
+            + self.advice_commitments
+            + self.lookups
+            + self.permutation
+            + self.shuffles
+            + self.quotient
+            + self.evaluations
+            + self.multiopen
+    }
+}
+
+impl ProverObserver for ProofTimings {
+    fn synthesis_done(&mut self, phase: u8, _index: usize, duration: Duration) {
+        let phase = phase as usize;
+        if self.synthesis.len() <= phase {
+            self.synthesis.resize(phase + 1, Duration::ZERO);
+        }
+        self.synthesis[phase] += duration;
+    }
+
+    fn commitment_done(&mut self, kind: CommitmentKind, _index: usize, duration: Duration) {
+        *match kind {
+            CommitmentKind::Advice { .. } => &mut self.advice_commitments,
+            CommitmentKind::LookupPermuted | CommitmentKind::LookupProduct => &mut self.lookups,
+            CommitmentKind::PermutationProduct => &mut self.permutation,
+            CommitmentKind::ShuffleProduct => &mut self.shuffles,
+            CommitmentKind::VanishingRandom | CommitmentKind::Quotient => &mut self.quotient,
+            CommitmentKind::Multiopen => &mut self.multiopen,
+        } += duration;
+    }
+
+    fn gates_evaluated(&mut self, timings: &[GateTiming]) {
+        self.gates = timings.to_vec();
+    }
+
+    fn quotient_split(&mut self, evaluation: Duration, fft: Duration) {
+        self.quotient_evaluation += evaluation;
+        self.quotient_fft += fft;
+    }
+
+    fn quotient_done(&mut self, duration: Duration) {
+        self.quotient += duration;
+    }
+
+    fn evaluations_done(&mut self, duration: Duration) {
+        self.evaluations += duration;
+    }
+
+    fn multiopen_done(&mut self, duration: Duration) {
+        self.multiopen += duration;
+    }
+}
+
+/// An [`Engine`] that measures the time another engine spends in FFTs.
+struct TimedEngine<'a, C: CurveAffine> {
+    inner: &'a dyn Engine<C>,
+    fft_nanos: AtomicU64,
+}
+
+impl<'a, C: CurveAffine> TimedEngine<'a, C> {
+    fn new(inner: &'a dyn Engine<C>) -> Self {
+        TimedEngine {
+            inner,
+            fft_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the time spent in FFTs so far.
+    fn fft_time(&self) -> Duration {
+        Duration::from_nanos(self.fft_nanos.load(Ordering::Relaxed))
+    }
+
+    fn record_fft(&self, start: Instant) {
+        self.fft_nanos
+            .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl<C: CurveAffine> Engine<C> for TimedEngine<'_, C> {
+    fn best_multiexp(&self, coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+        self.inner.best_multiexp(coeffs, bases)
+    }
+
+    fn fft(&self, values: &mut [C::Scalar], omega: C::Scalar, log_n: u32) {
+        let start = Instant::now();
+        self.inner.fft(values, omega, log_n);
+        self.record_fft(start);
+    }
+
+    fn batch_multiexp(&self, msms: &[(&[C::Scalar], &[C])]) -> Vec<C::Curve> {
+        self.inner.batch_multiexp(msms)
+    }
+
+    fn batch_fft(&self, values: &mut [&mut [C::Scalar]], omega: C::Scalar, log_n: u32) {
+        let start = Instant::now();
+        self.inner.batch_fft(values, omega, log_n);
+        self.record_fft(start);
+    }
+}
+
+/// Starts measuring a duration for `O`, unless it ignores durations.
+fn start_timer<O: ProverObserver>() -> Option<Instant> {
+    if O::TIMED {
+        Some(Instant::now())
+    } else {
+        None
+    }
+}
+
+fn elapsed(start: Option<Instant>) -> Duration {
+    start.map_or(Duration::ZERO, |start| start.elapsed())
+}
+
+/// Values derived while creating a proof that a caller may want to reuse,
+/// such as the challenges a later protocol built on top of the proof is bound
+/// to.
+#[derive(Clone, Debug)]
+pub struct ProofArtifacts<C: CurveAffine> {
+    challenges: HashMap<Challenge, C::Scalar>,
+    /// The advice commitments of each proof, per phase, in column order.
+    advice_commitments: Vec<Vec<Vec<C>>>,
+    layout: Option<ProofLayout>,
+}
+
+impl<C: CurveAffine> ProofArtifacts<C> {
+    /// Returns the value sampled for every challenge of the circuit.
+    pub fn challenges(&self) -> &HashMap<Challenge, C::Scalar> {
+        &self.challenges
+    }
+
+    /// Returns the value sampled for `challenge`, or `None` if the circuit
+    /// does not declare it.
+    pub fn challenge(&self, challenge: Challenge) -> Option<C::Scalar> {
+        self.challenges.get(&challenge).copied()
+    }
+
+    /// Returns the commitments to the advice columns of `phase` of the
+    /// `circuit`-th proof, in the order in which they were written to the
+    /// transcript.
+    pub fn advice_commitments(&self, circuit: usize, phase: u8) -> Option<&[C]> {
+        self.advice_commitments
+            .get(circuit)?
+            .get(phase as usize)
+            .map(Vec::as_slice)
+    }
+
+    /// Returns where each component of the proof lies within the bytes
+    /// written to the transcript, if it was recorded with
+    /// [`ProverOptions::record_layout`].
+    pub fn layout(&self) -> Option<&ProofLayout> {
+        self.layout.as_ref()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_proof_impl<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+    O: ProverObserver,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+    engine: &dyn Engine<Scheme::Curve>,
+    observer: &mut O,
+    options: &ProverOptions,
+) -> Result<ProofArtifacts<Scheme::Curve>, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    check_instances(pk, params.n(), circuits.len(), instances)?;
+
+    let mut meta = ConstraintSystem::default();
+    #[cfg(feature = "circuit-params")]
+    let config = ConcreteCircuit::configure_with_params(&mut meta, circuits[0].params());
+    #[cfg(not(feature = "circuit-params"))]
+    let config = ConcreteCircuit::configure(&mut meta);
+
+    prove_circuits::<Scheme, P, _, _, _, _, _>(
+        params, pk, &config, circuits, instances, rng, transcript, engine, observer, options,
+    )
+}
+
+/// Synthesizes `circuits` with their `config` to obtain the witness of each
+/// proof. The `instances` must have been validated with [`check_instances`].
+#[allow(clippy::too_many_arguments)]
+fn prove_circuits<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+    O: ProverObserver,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    config: &ConcreteCircuit::Config,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[Scheme::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+    engine: &dyn Engine<Scheme::Curve>,
+    observer: &mut O,
+    options: &ProverOptions,
+) -> Result<ProofArtifacts<Scheme::Curve>, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    // Selector optimizations cannot be applied here; use the ConstraintSystem
+    // from the verification key.
+    let meta = &pk.vk.cs;
+    let domain = &pk.vk.domain;
+
+    struct WitnessCollection<'a, F: Field> {
+        k: u32,
+        current_phase: sealed::Phase,
+        /// The columns of `current_phase`; the others are never allocated.
+        advice: Vec<Option<Polynomial<Assigned<F>, LagrangeCoeff>>>,
+        challenges: &'a HashMap<usize, F>,
+        instances: &'a [&'a [F]],
+        usable_rows: RangeTo<usize>,
+        _marker: std::marker::PhantomData<F>,
+    }
+
+    impl<'a, F: Field> Assignment<F> for WitnessCollection<'a, F> {
+        fn enter_region<NR, N>(&mut self, _: N)
+        where
+            NR: Into<String>,
+            N: FnOnce() -> NR,
+        {
+            // Do nothing; we don't care about regions in this context.
+        }
+
+        fn exit_region(&mut self) {
+            // Do nothing; we don't care about regions in this context.
+        }
+
+        fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            // We only care about advice columns here
+
+            Ok(())
+        }
+
+        fn annotate_column<A, AR>(&mut self, _annotation: A, _column: Column<Any>)
+        where
+            A: FnOnce() -> AR,
+            AR: Into<String>,
+        {
+            // Do nothing
+        }
+
+        fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Value<F>, Error> {
+            if !self.usable_rows.contains(&row) {
+                return Err(Error::not_enough_rows_available(self.k));
+            }
+
+            self.instances
+                .get(column.index())
+                .and_then(|column| column.get(row))
+                .map(|v| Value::known(*v))
+                .ok_or(Error::BoundsFailure)
         }
 
         fn assign_advice<V, VR, A, AR>(
@@ -221,6 +1011,7 @@ where
             *self
                 .advice
                 .get_mut(column.index())
+                .and_then(|v| v.as_mut())
                 .and_then(|v| v.get_mut(row))
                 .ok_or(Error::BoundsFailure)? = to().into_field().assign()?;
 
@@ -287,116 +1078,436 @@ where
         }
     }
 
-    let (advice, challenges) = {
-        let mut advice = vec![
-            AdviceSingle::<Scheme::Curve, LagrangeCoeff> {
-                advice_polys: vec![domain.empty_lagrange(); meta.num_advice_columns],
-                advice_blinds: vec![Blind::default(); meta.num_advice_columns],
-            };
-            instances.len()
-        ];
-        let mut challenges = HashMap::<usize, Scheme::Scalar>::with_capacity(meta.num_challenges);
-
-        let unusable_rows_start = params.n() as usize - (meta.blinding_factors() + 1);
-        for current_phase in pk.vk.cs.phases() {
-            let column_indices = meta
-                .advice_column_phase
-                .iter()
-                .enumerate()
-                .filter_map(|(column_index, phase)| {
-                    if current_phase == *phase {
-                        Some(column_index)
-                    } else {
-                        None
-                    }
+    // The prover will not be allowed to assign values to advice cells that
+    // exist within inactive rows, which include some number of blinding
+    // factors and an extra row for use in the permutation argument.
+    let unusable_rows_start = meta.usable_rows(params.k()).end;
+    let synthesize = |circuit_index: usize,
+                      current_phase: sealed::Phase,
+                      column_indices: &BTreeSet<usize>,
+                      challenges: &HashMap<usize, Scheme::Scalar>| {
+        let mut witness = WitnessCollection {
+            k: params.k(),
+            current_phase,
+            advice: (0..meta.num_advice_columns)
+                .map(|column_index| {
+                    column_indices
+                        .contains(&column_index)
+                        .then(|| domain.empty_lagrange_assigned())
                 })
-                .collect::<BTreeSet<_>>();
-
-            for ((circuit, advice), instances) in
-                circuits.iter().zip(advice.iter_mut()).zip(instances)
-            {
-                let mut witness = WitnessCollection {
-                    k: params.k(),
-                    current_phase,
-                    advice: vec![domain.empty_lagrange_assigned(); meta.num_advice_columns],
-                    instances,
-                    challenges: &challenges,
-                    // The prover will not be allowed to assign values to advice
-                    // cells that exist within inactive rows, which include some
-                    // number of blinding factors and an extra row for use in the
-                    // permutation argument.
-                    usable_rows: ..unusable_rows_start,
-                    _marker: std::marker::PhantomData,
-                };
+                .collect(),
+            instances: instances[circuit_index],
+            challenges,
+            usable_rows: ..unusable_rows_start,
+            _marker: std::marker::PhantomData,
+        };
 
-                // Synthesize the circuit to obtain the witness and other information.
-                ConcreteCircuit::FloorPlanner::synthesize(
-                    &mut witness,
-                    circuit,
-                    config.clone(),
-                    meta.constants.clone(),
-                )?;
+        // Synthesize the circuit to obtain the witness and other information.
+        ConcreteCircuit::FloorPlanner::synthesize(
+            &mut witness,
+            &circuits[circuit_index],
+            config.clone(),
+            meta.constants.clone(),
+        )?;
 
-                let mut advice_values = batch_invert_assigned::<Scheme::Scalar>(
-                    witness
-                        .advice
-                        .into_iter()
-                        .enumerate()
-                        .filter_map(|(column_index, advice)| {
-                            if column_indices.contains(&column_index) {
-                                Some(advice)
-                            } else {
-                                None
-                            }
-                        })
-                        .collect(),
-                );
+        Ok(batch_invert_assigned::<Scheme::Scalar>(
+            witness.advice.into_iter().flatten().collect(),
+        ))
+    };
 
-                // Add blinding factors to advice columns
-                for advice_values in &mut advice_values {
-                    for cell in &mut advice_values[unusable_rows_start..] {
-                        *cell = Scheme::Scalar::random(&mut rng);
-                    }
-                }
+    prove::<Scheme, P, _, _, _, _, _>(
+        params, pk, instances, synthesize, rng, transcript, engine, observer, options,
+    )
+}
 
-                // Compute commitments to advice column polynomials
-                let blinds: Vec<_> = advice_values
-                    .iter()
-                    .map(|_| Blind(Scheme::Scalar::random(&mut rng)))
-                    .collect();
-                let advice_commitments_projective: Vec<_> = advice_values
-                    .iter()
-                    .zip(blinds.iter())
-                    .map(|(poly, blind)| params.commit_lagrange(poly, *blind))
-                    .collect();
-                let mut advice_commitments =
-                    vec![Scheme::Curve::identity(); advice_commitments_projective.len()];
-                <Scheme::Curve as CurveAffine>::CurveExt::batch_normalize(
-                    &advice_commitments_projective,
-                    &mut advice_commitments,
-                );
-                let advice_commitments = advice_commitments;
-                drop(advice_commitments_projective);
+/// The challenges squeezed so far while proving with
+/// [`create_proof_from_witness`], passed to the callback that supplies the
+/// advice columns of a later phase.
+#[derive(Debug)]
+pub struct Challenges<'a, F> {
+    circuit: usize,
+    phase: u8,
+    values: &'a HashMap<usize, F>,
+}
 
-                for commitment in &advice_commitments {
-                    transcript.write_point(*commitment)?;
-                }
-                for ((column_index, advice_values), blind) in
-                    column_indices.iter().zip(advice_values).zip(blinds)
-                {
-                    advice.advice_polys[*column_index] = advice_values;
-                    advice.advice_blinds[*column_index] = blind;
-                }
-            }
+impl<'a, F: Copy> Challenges<'a, F> {
+    /// Returns the index, among the circuits being proven, of the circuit
+    /// whose advice columns are requested.
+    pub fn circuit(&self) -> usize {
+        self.circuit
+    }
 
-            for (index, phase) in meta.challenge_phase.iter().enumerate() {
-                if current_phase == *phase {
-                    let existing =
-                        challenges.insert(index, *transcript.squeeze_challenge_scalar::<()>());
-                    assert!(existing.is_none());
-                }
-            }
-        }
+    /// Returns the phase whose advice columns are requested.
+    pub fn phase(&self) -> u8 {
+        self.phase
+    }
+
+    /// Returns the value of `challenge`, or `None` if it is squeezed in this
+    /// phase or a later one.
+    pub fn get(&self, challenge: Challenge) -> Option<F> {
+        self.values.get(&challenge.index()).copied()
+    }
+}
+
+/// This creates a proof from an externally generated witness, without
+/// synthesizing a [`Circuit`].
+///
+/// `advice` holds, for each proof, the values of the first-phase advice
+/// columns in column order. The columns of every later phase are requested
+/// from `later_phases` once the challenges they may depend on are known.
+/// Columns may be shorter than the usable rows, in which case they are
+/// zero-padded; the blinding rows are always filled by the prover.
+pub fn create_proof_from_witness<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    L: FnMut(&Challenges<'_, Scheme::Scalar>) -> Vec<Vec<Scheme::Scalar>>,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    mut advice: Vec<Vec<Vec<Scheme::Scalar>>>,
+    instances: &[&[&[Scheme::Scalar]]],
+    mut later_phases: L,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    check_instances(pk, params.n(), advice.len(), instances)?;
+
+    let n = params.n() as usize;
+    let unusable_rows_start = pk.vk.cs.usable_rows(params.k()).end;
+    let witness = |circuit: usize,
+                   current_phase: sealed::Phase,
+                   column_indices: &BTreeSet<usize>,
+                   challenges: &HashMap<usize, Scheme::Scalar>| {
+        let columns = if current_phase.0 == 0 {
+            std::mem::take(&mut advice[circuit])
+        } else {
+            later_phases(&Challenges {
+                circuit,
+                phase: current_phase.0,
+                values: challenges,
+            })
+        };
+
+        if columns.len() != column_indices.len() {
+            return Err(Error::InvalidWitness {
+                circuit,
+                phase: current_phase.0,
+                expected: column_indices.len(),
+                provided: columns.len(),
+            });
+        }
+
+        column_indices
+            .iter()
+            .zip(columns)
+            .map(|(&column_index, mut values)| {
+                if values.len() > unusable_rows_start {
+                    return Err(Error::not_enough_rows_available_at(
+                        params.k(),
+                        None,
+                        Some(Column::new(
+                            column_index,
+                            Any::Advice(Advice {
+                                phase: current_phase,
+                            }),
+                        )),
+                        unusable_rows_start,
+                    ));
+                }
+                values.resize(n, Scheme::Scalar::ZERO);
+                Ok(pk.vk.domain.lagrange_from_vec(values))
+            })
+            .collect()
+    };
+
+    prove::<Scheme, P, _, _, _, _, _>(
+        params,
+        pk,
+        instances,
+        witness,
+        rng,
+        transcript,
+        &CpuEngine,
+        &mut NoopObserver,
+        &ProverOptions::default(),
+    )
+    .map(|_| ())
+}
+
+/// Checks that there are instances for each of the `proofs`, with one column
+/// per instance column of the circuit and no more values than usable rows.
+fn check_instances<C: CurveAffine>(
+    pk: &ProvingKey<C>,
+    n: u64,
+    proofs: usize,
+    instances: &[&[&[C::Scalar]]],
+) -> Result<(), Error> {
+    if instances.len() != proofs {
+        return Err(Error::InstanceShape(InstanceMismatch::ProofCount {
+            expected: proofs,
+            provided: instances.len(),
+        }));
+    }
+
+    let usable_rows = n as usize - (pk.vk.cs.blinding_factors() + 1);
+    for (circuit, instance) in instances.iter().enumerate() {
+        if instance.len() != pk.vk.cs.num_instance_columns {
+            return Err(Error::InstanceShape(InstanceMismatch::ColumnCount {
+                circuit,
+                expected: pk.vk.cs.num_instance_columns,
+                provided: instance.len(),
+            }));
+        }
+        for (column, values) in instance.iter().enumerate() {
+            if values.len() > usable_rows {
+                return Err(Error::InstanceShape(InstanceMismatch::ColumnTooLong {
+                    circuit,
+                    column,
+                    usable_rows,
+                    provided: values.len(),
+                }));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the protocol for the given `instances`, obtaining the values of the
+/// advice columns of each phase from `witness`. The `instances` must have been
+/// validated with [`check_instances`].
+#[allow(clippy::too_many_arguments)]
+fn prove<
+    'params,
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    R: RngCore,
+    T: TranscriptWrite<Scheme::Curve, E>,
+    W: FnMut(
+        usize,
+        sealed::Phase,
+        &BTreeSet<usize>,
+        &HashMap<usize, Scheme::Scalar>,
+    ) -> Result<Vec<Polynomial<Scheme::Scalar, LagrangeCoeff>>, Error>,
+    O: ProverObserver,
+>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    instances: &[&[&[Scheme::Scalar]]],
+    mut witness: W,
+    mut rng: R,
+    transcript: &mut T,
+    engine: &dyn Engine<Scheme::Curve>,
+    observer: &mut O,
+    options: &ProverOptions,
+) -> Result<ProofArtifacts<Scheme::Curve>, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    #[cfg(feature = "multicore")]
+    let pool_engine;
+    #[cfg(feature = "multicore")]
+    let engine: &dyn Engine<Scheme::Curve> = match &options.compute_pool {
+        Some(pool) => {
+            pool_engine = PoolEngine::new(pool.clone(), engine);
+            &pool_engine
+        }
+        None => engine,
+    };
+
+    // Only measure the FFTs if the observer reads the durations
+    let timed_engine = TimedEngine::new(engine);
+    let engine: &dyn Engine<Scheme::Curve> = if O::TIMED { &timed_engine } else { engine };
+
+    // Hash verification key into transcript
+    pk.vk.hash_into(transcript)?;
+
+    let domain = &pk.vk.domain;
+    let meta = &pk.vk.cs;
+
+    struct InstanceSingle<C: CurveAffine> {
+        pub instance_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        pub instance_polys: Vec<Polynomial<C::Scalar, Coeff>>,
+    }
+
+    let mut instance: Vec<InstanceSingle<Scheme::Curve>> = instances
+        .iter()
+        .map(|instance| -> Result<InstanceSingle<Scheme::Curve>, Error> {
+            let mut instance_values = instance
+                .iter()
+                .map(|values| {
+                    let mut poly = domain.empty_lagrange();
+                    assert_eq!(poly.len(), params.n() as usize);
+                    if values.len() > meta.usable_rows(params.k()).end {
+                        return Err(Error::InstanceTooLarge);
+                    }
+                    for (poly, value) in poly.iter_mut().zip(values.iter()) {
+                        if !P::QUERY_INSTANCE {
+                            transcript.common_scalar(*value)?;
+                        }
+                        *poly = *value;
+                    }
+                    Ok(poly)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if P::QUERY_INSTANCE {
+                let instance_commitments_projective: Vec<_> = instance_values
+                    .iter()
+                    .map(|poly| params.commit_lagrange_with_engine(engine, poly, Blind::default()))
+                    .collect();
+                let mut instance_commitments =
+                    vec![Scheme::Curve::identity(); instance_commitments_projective.len()];
+                <Scheme::Curve as CurveAffine>::CurveExt::batch_normalize(
+                    &instance_commitments_projective,
+                    &mut instance_commitments,
+                );
+                let instance_commitments = instance_commitments;
+                drop(instance_commitments_projective);
+
+                for commitment in &instance_commitments {
+                    transcript.common_point(*commitment)?;
+                }
+            }
+
+            let instance_polys = domain.batch_lagrange_to_coeff_with_engine(
+                engine,
+                instance_values
+                    .iter()
+                    .map(|poly| domain.lagrange_from_vec(poly.to_vec()))
+                    .collect(),
+            );
+
+            options.offload(&mut instance_values)?;
+
+            Ok(InstanceSingle {
+                instance_values,
+                instance_polys,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    #[derive(Clone)]
+    struct AdviceSingle<C: CurveAffine, B: Basis> {
+        pub advice_polys: Vec<Polynomial<C::Scalar, B>>,
+        pub advice_blinds: Vec<Blind<C::Scalar>>,
+    }
+
+    let mut advice_commitments_per_phase = vec![vec![]; instances.len()];
+    let (advice, challenges) = {
+        let mut advice = vec![
+            AdviceSingle::<Scheme::Curve, LagrangeCoeff> {
+                advice_polys: vec![domain.empty_lagrange(); meta.num_advice_columns],
+                advice_blinds: vec![Blind::default(); meta.num_advice_columns],
+            };
+            instances.len()
+        ];
+        let mut challenges = HashMap::<usize, Scheme::Scalar>::with_capacity(meta.num_challenges);
+
+        let unusable_rows_start = meta.usable_rows(params.k()).end;
+        for current_phase in pk.vk.cs.phases() {
+            observer.phase_started(current_phase.0);
+            let column_indices = meta
+                .advice_columns_in_phase(current_phase.0)
+                .iter()
+                .map(|column| column.index())
+                .collect::<BTreeSet<_>>();
+
+            for ((circuit_index, advice), advice_commitments_per_phase) in advice
+                .iter_mut()
+                .enumerate()
+                .zip(advice_commitments_per_phase.iter_mut())
+            {
+                let start = start_timer::<O>();
+                let mut advice_values =
+                    witness(circuit_index, current_phase, &column_indices, &challenges)?;
+                observer.synthesis_done(current_phase.0, circuit_index, elapsed(start));
+                let start = start_timer::<O>();
+                assert_eq!(advice_values.len(), column_indices.len());
+
+                // Add blinding factors to advice columns, leaving zeroes in
+                // the unblinded ones
+                for (column_index, advice_values) in column_indices.iter().zip(&mut advice_values) {
+                    let blinded = meta.is_advice_column_blinded(*column_index);
+                    for cell in &mut advice_values[unusable_rows_start..] {
+                        *cell = if blinded {
+                            meta.blinding_scalar(&mut rng)
+                        } else {
+                            Scheme::Scalar::ZERO
+                        };
+                    }
+                }
+
+                // Compute commitments to advice column polynomials
+                let blinds: Vec<_> = column_indices
+                    .iter()
+                    .map(|column_index| {
+                        if meta.is_advice_column_blinded(*column_index) {
+                            meta.blind(&mut rng)
+                        } else {
+                            Blind::default()
+                        }
+                    })
+                    .collect();
+                let advice_commitments_projective: Vec<_> = advice_values
+                    .iter()
+                    .zip(blinds.iter())
+                    .map(|(poly, blind)| params.commit_lagrange_with_engine(engine, poly, *blind))
+                    .collect();
+                let mut advice_commitments =
+                    vec![Scheme::Curve::identity(); advice_commitments_projective.len()];
+                <Scheme::Curve as CurveAffine>::CurveExt::batch_normalize(
+                    &advice_commitments_projective,
+                    &mut advice_commitments,
+                );
+                let advice_commitments = advice_commitments;
+                drop(advice_commitments_projective);
+
+                transcript.write_points(&advice_commitments)?;
+                advice_commitments_per_phase.push(advice_commitments);
+                observer.commitment_done(
+                    CommitmentKind::Advice {
+                        phase: current_phase.0,
+                    },
+                    circuit_index,
+                    elapsed(start),
+                );
+                // The phase is done with its columns' values until the
+                // lookups and the quotient are computed.
+                options.offload(&mut advice_values)?;
+                for ((column_index, advice_values), blind) in
+                    column_indices.iter().zip(advice_values).zip(blinds)
+                {
+                    advice.advice_polys[*column_index] = advice_values;
+                    advice.advice_blinds[*column_index] = blind;
+                }
+            }
+
+            for (index, phase) in meta.challenge_phase.iter().enumerate() {
+                if current_phase == *phase && meta.is_challenge_squeezed(index) {
+                    let existing =
+                        challenges.insert(index, *transcript.squeeze_challenge_scalar::<()>());
+                    assert!(existing.is_none());
+                }
+            }
+            for &(index, base, exponent) in &meta.challenge_powers {
+                if current_phase == meta.challenge_phase[index] {
+                    let power = challenges[&base].pow_vartime([exponent as u64]);
+                    let existing = challenges.insert(index, power);
+                    assert!(existing.is_none());
+                }
+            }
+        }
 
         assert_eq!(challenges.len(), meta.num_challenges);
         let challenges = (0..meta.num_challenges)
@@ -409,32 +1520,89 @@ where
     // Sample theta challenge for keeping lookup columns linearly independent
     let theta: ChallengeTheta<_> = transcript.squeeze_challenge_scalar();
 
-    let lookups: Vec<Vec<lookup::prover::Permuted<Scheme::Curve>>> = instance
+    // Only one of these is non-empty, depending on the lookup backend.
+    let (lookup_arguments, logup_arguments) = pk.vk.cs.lookups_by_backend();
+
+    let (lookups, logups): (
+        Vec<Vec<lookup::prover::Permuted<Scheme::Curve>>>,
+        Vec<Vec<lookup::logup::prover::Multiplicities<Scheme::Curve>>>,
+    ) = instance
         .iter()
         .zip(advice.iter())
-        .map(|(instance, advice)| -> Result<Vec<_>, Error> {
-            // Construct and commit to permuted values for each lookup
-            pk.vk
-                .cs
-                .lookups
-                .iter()
-                .map(|lookup| {
-                    lookup.commit_permuted(
-                        pk,
-                        params,
-                        domain,
-                        theta,
-                        &advice.advice_polys,
-                        &pk.fixed_values,
-                        &instance.instance_values,
-                        &challenges,
-                        &mut rng,
-                        transcript,
-                    )
-                })
-                .collect()
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+        .enumerate()
+        .map(
+            |(index, (instance, advice))| -> Result<(Vec<_>, Vec<_>), Error> {
+                let start = start_timer::<O>();
+                // Draw the randomness of each lookup up front, in the canonical
+                // order, so that the proof does not depend on how the lookups are
+                // scheduled.
+                let mut draw_seed = || {
+                    let mut seed = [0u8; 32];
+                    rng.fill_bytes(&mut seed);
+                    seed
+                };
+                let jobs: Vec<_> = lookup_arguments
+                    .iter()
+                    .map(|lookup| (lookup, draw_seed()))
+                    .collect();
+                let logup_jobs: Vec<_> = logup_arguments
+                    .iter()
+                    .map(|logup| (logup, draw_seed()))
+                    .collect();
+
+                // Construct and commit to permuted values for each lookup, in
+                // parallel
+                let lookups = jobs
+                    .into_par_iter()
+                    .map(|(lookup, seed)| {
+                        lookup.commit_permuted(
+                            pk,
+                            params,
+                            engine,
+                            domain,
+                            theta,
+                            &advice.advice_polys,
+                            &pk.fixed_values,
+                            &instance.instance_values,
+                            &challenges,
+                            ChaCha20Rng::from_seed(seed),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // Count the multiplicities of each LogUp lookup, in parallel
+                let logups = logup_jobs
+                    .into_par_iter()
+                    .map(|(logup, seed)| {
+                        logup.commit_multiplicities(
+                            pk,
+                            params,
+                            engine,
+                            domain,
+                            theta,
+                            &advice.advice_polys,
+                            &pk.fixed_values,
+                            &instance.instance_values,
+                            &challenges,
+                            ChaCha20Rng::from_seed(seed),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // Write the commitments in the canonical lookup order
+                for lookup in &lookups {
+                    lookup.write_commitments(transcript)?;
+                }
+                for logup in &logups {
+                    logup.write_commitments(transcript)?;
+                }
+                observer.commitment_done(CommitmentKind::LookupPermuted, index, elapsed(start));
+                Ok((lookups, logups))
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
 
     // Sample beta challenge
     let beta: ChallengeBeta<_> = transcript.squeeze_challenge_scalar();
@@ -446,9 +1614,12 @@ where
     let permutations: Vec<permutation::prover::Committed<Scheme::Curve>> = instance
         .iter()
         .zip(advice.iter())
-        .map(|(instance, advice)| {
-            pk.vk.cs.permutation.commit(
+        .enumerate()
+        .map(|(index, (instance, advice))| {
+            let start = start_timer::<O>();
+            let permutation = pk.vk.cs.permutation.commit(
                 params,
+                engine,
                 pk,
                 &pk.permutation,
                 &advice.advice_polys,
@@ -458,27 +1629,53 @@ where
                 gamma,
                 &mut rng,
                 transcript,
-            )
+            )?;
+            observer.commitment_done(CommitmentKind::PermutationProduct, index, elapsed(start));
+            Ok(permutation)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let lookups: Vec<Vec<lookup::prover::Committed<Scheme::Curve>>> = lookups
+    let (lookups, logups): (
+        Vec<Vec<lookup::prover::Committed<Scheme::Curve>>>,
+        Vec<Vec<lookup::logup::prover::Committed<Scheme::Curve>>>,
+    ) = lookups
         .into_iter()
-        .map(|lookups| -> Result<Vec<_>, _> {
-            // Construct and commit to products for each lookup
-            lookups
-                .into_iter()
-                .map(|lookup| lookup.commit_product(pk, params, beta, gamma, &mut rng, transcript))
-                .collect::<Result<Vec<_>, _>>()
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+        .zip(logups)
+        .enumerate()
+        .map(
+            |(index, (lookups, logups))| -> Result<(Vec<_>, Vec<_>), Error> {
+                let start = start_timer::<O>();
+                // Construct and commit to products for each lookup
+                let lookups = lookups
+                    .into_iter()
+                    .map(|lookup| {
+                        lookup.commit_product(pk, params, engine, beta, gamma, &mut rng, transcript)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Construct and commit to running sums for each LogUp lookup
+                let logups = logups
+                    .into_iter()
+                    .map(|logup| {
+                        logup.commit_running_sum(pk, params, engine, beta, &mut rng, transcript)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                observer.commitment_done(CommitmentKind::LookupProduct, index, elapsed(start));
+                Ok((lookups, logups))
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
 
     let shuffles: Vec<Vec<shuffle::prover::Committed<Scheme::Curve>>> = instance
         .iter()
         .zip(advice.iter())
-        .map(|(instance, advice)| -> Result<Vec<_>, _> {
+        .enumerate()
+        .map(|(index, (instance, advice))| -> Result<Vec<_>, _> {
+            let start = start_timer::<O>();
             // Compress expressions for each shuffle
-            pk.vk
+            let shuffles = pk
+                .vk
                 .cs
                 .shuffles
                 .iter()
@@ -486,6 +1683,7 @@ where
                     shuffle.commit_product(
                         pk,
                         params,
+                        engine,
                         domain,
                         theta,
                         gamma,
@@ -497,226 +1695,2529 @@ where
                         transcript,
                     )
                 })
-                .collect::<Result<Vec<_>, _>>()
+                .collect::<Result<Vec<_>, _>>()?;
+            observer.commitment_done(CommitmentKind::ShuffleProduct, index, elapsed(start));
+            Ok(shuffles)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    // The instance columns are only needed in Lagrange form to build the
+    // lookup, permutation and shuffle arguments, which are all committed by
+    // now; release them before the quotient polynomial is computed.
+    for instance in instance.iter_mut() {
+        instance.instance_values = vec![];
+    }
+
     // Commit to the vanishing argument's random polynomial for blinding h(x_3)
-    let vanishing = vanishing::Argument::commit(params, domain, &mut rng, transcript)?;
+    let start = start_timer::<O>();
+    let vanishing = vanishing::Argument::commit(
+        params,
+        engine,
+        domain,
+        meta.zero_knowledge(),
+        &mut rng,
+        transcript,
+    )?;
+    observer.commitment_done(CommitmentKind::VanishingRandom, 0, elapsed(start));
+
+    // Obtain challenge for keeping all separate gates linearly independent
+    let y: ChallengeY<_> = transcript.squeeze_challenge_scalar();
+
+    let start = start_timer::<O>();
+    let fft_start = timed_engine.fft_time();
+
+    // Calculate the advice polys, converting the columns of each circuit
+    // together and in place, so that no copy of their Lagrange form is kept
+    let advice: Vec<AdviceSingle<Scheme::Curve, Coeff>> = advice
+        .into_iter()
+        .map(
+            |AdviceSingle {
+                 advice_polys,
+                 advice_blinds,
+             }| {
+                AdviceSingle {
+                    advice_polys: domain.batch_lagrange_to_coeff_with_engine(engine, advice_polys),
+                    advice_blinds,
+                }
+            },
+        )
+        .collect();
+
+    // Evaluate the h(X) polynomial
+    let evaluation_start = start_timer::<O>();
+    let evaluation_fft_start = timed_engine.fft_time();
+    let scratch = ScratchPool::new(!options.fresh_buffers);
+    let advice_polys: Vec<_> = advice.iter().map(|a| a.advice_polys.as_slice()).collect();
+    let instance_polys: Vec<_> = instance
+        .iter()
+        .map(|i| i.instance_polys.as_slice())
+        .collect();
+    let (h_poly, gate_timings) = options.in_compute_pool(|| match options.evaluation {
+        EvaluationStrategy::Compiled => {
+            let evaluator = pk.evaluator();
+            let mut gate_timings = O::TIMED.then(|| evaluator.gate_timings(&pk.vk.cs));
+            let h_poly = evaluator.evaluate_h(
+                pk,
+                engine,
+                &advice_polys,
+                &instance_polys,
+                &challenges,
+                *y,
+                *beta,
+                *gamma,
+                *theta,
+                &lookups,
+                &logups,
+                &shuffles,
+                &permutations,
+                options.gate_chunks,
+                options.argument_chunks,
+                &scratch,
+                gate_timings.as_deref_mut(),
+            );
+            (h_poly, gate_timings)
+        }
+        EvaluationStrategy::Interpreted => {
+            let evaluator = Evaluator::interpreted(&pk.vk.cs);
+            let mut gate_timings = O::TIMED.then(|| evaluator.gate_timings(&pk.vk.cs));
+            let h_poly = evaluator.evaluate_h(
+                pk,
+                engine,
+                &advice_polys,
+                &instance_polys,
+                &challenges,
+                *y,
+                *beta,
+                *gamma,
+                *theta,
+                &lookups,
+                &logups,
+                &shuffles,
+                &permutations,
+                options.gate_chunks,
+                options.argument_chunks,
+                &scratch,
+                gate_timings.as_deref_mut(),
+            );
+            (h_poly, gate_timings)
+        }
+    });
+    let evaluation =
+        elapsed(evaluation_start).saturating_sub(timed_engine.fft_time() - evaluation_fft_start);
+
+    // Construct the vanishing argument's h(X) commitments
+    let vanishing = vanishing.construct(params, engine, domain, h_poly, &mut rng, transcript)?;
+    if let Some(gate_timings) = gate_timings {
+        observer.gates_evaluated(&gate_timings);
+    }
+    observer.quotient_split(evaluation, timed_engine.fft_time() - fft_start);
+    observer.quotient_done(elapsed(start));
+
+    let x: ChallengeX<_> = transcript.squeeze_challenge_scalar();
+    let start = start_timer::<O>();
+    let xn = x.pow([params.n()]);
+
+    if P::QUERY_INSTANCE {
+        // Compute and hash instance evals for each circuit instance
+        for instance in instance.iter() {
+            // Evaluate polynomials at omega^i x
+            let instance_evals: Vec<_> = meta
+                .instance_queries
+                .iter()
+                .map(|&(column, at)| {
+                    eval_polynomial(
+                        &instance.instance_polys[column.index()],
+                        domain.rotate_omega(*x, at),
+                    )
+                })
+                .collect();
+
+            // Hash each instance column evaluation
+            for eval in instance_evals.iter() {
+                transcript.write_scalar(*eval)?;
+            }
+        }
+    }
+
+    // Compute and hash advice evals for each circuit instance
+    for advice in advice.iter() {
+        // Evaluate polynomials at omega^i x
+        let advice_evals: Vec<_> = meta
+            .advice_queries
+            .iter()
+            .map(|&(column, at)| {
+                eval_polynomial(
+                    &advice.advice_polys[column.index()],
+                    domain.rotate_omega(*x, at),
+                )
+            })
+            .collect();
+
+        // Hash each advice column evaluation
+        for eval in advice_evals.iter() {
+            transcript.write_scalar(*eval)?;
+        }
+    }
+
+    // Compute and hash fixed evals (shared across all circuit instances)
+    let fixed_evals: Vec<_> = meta
+        .fixed_queries
+        .iter()
+        .map(|&(column, at)| {
+            eval_polynomial(&pk.fixed_polys[column.index()], domain.rotate_omega(*x, at))
+        })
+        .collect();
+
+    // Hash each fixed column evaluation
+    for eval in fixed_evals.iter() {
+        transcript.write_scalar(*eval)?;
+    }
+
+    let vanishing = vanishing.evaluate(x, xn, domain, transcript)?;
+
+    // Evaluate common permutation data
+    pk.permutation.evaluate(x, transcript)?;
+
+    // Evaluate the permutations, if any, at omega^i x.
+    let permutations: Vec<permutation::prover::Evaluated<Scheme::Curve>> = permutations
+        .into_iter()
+        .map(|permutation| -> Result<_, _> { permutation.construct().evaluate(pk, x, transcript) })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Evaluate the lookups, if any, at omega^i x.
+    let lookups: Vec<Vec<lookup::prover::Evaluated<Scheme::Curve>>> = lookups
+        .into_iter()
+        .map(|lookups| -> Result<Vec<_>, _> {
+            lookups
+                .into_iter()
+                .map(|p| p.evaluate(pk, x, transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Evaluate the LogUp lookups, if any, at omega^i x.
+    let logups: Vec<Vec<lookup::logup::prover::Evaluated<Scheme::Curve>>> = logups
+        .into_iter()
+        .map(|logups| -> Result<Vec<_>, _> {
+            logups
+                .into_iter()
+                .map(|p| p.evaluate(pk, x, transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Evaluate the shuffles, if any, at omega^i x.
+    let shuffles: Vec<Vec<shuffle::prover::Evaluated<Scheme::Curve>>> = shuffles
+        .into_iter()
+        .map(|shuffles| -> Result<Vec<_>, _> {
+            shuffles
+                .into_iter()
+                .map(|p| p.evaluate(pk, x, transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    observer.evaluations_done(elapsed(start));
+
+    let instances =
+        instance
+            .iter()
+            .zip(advice.iter())
+            .zip(permutations.iter())
+            .zip(lookups.iter())
+            .zip(logups.iter())
+            .zip(shuffles.iter())
+            .flat_map(
+                |(((((instance, advice), permutation), lookups), logups), shuffles)| {
+                    iter::empty()
+                        .chain(
+                            P::QUERY_INSTANCE
+                                .then_some(pk.vk.cs.instance_queries.iter().map(
+                                    move |&(column, at)| ProverQuery {
+                                        point: domain.rotate_omega(*x, at),
+                                        poly: &instance.instance_polys[column.index()],
+                                        blind: Blind::default(),
+                                    },
+                                ))
+                                .into_iter()
+                                .flatten(),
+                        )
+                        .chain(pk.vk.cs.advice_queries.iter().map(move |&(column, at)| {
+                            ProverQuery {
+                                point: domain.rotate_omega(*x, at),
+                                poly: &advice.advice_polys[column.index()],
+                                blind: advice.advice_blinds[column.index()],
+                            }
+                        }))
+                        .chain(permutation.open(pk, x))
+                        .chain(lookups.iter().flat_map(move |p| p.open(pk, x)))
+                        .chain(logups.iter().flat_map(move |p| p.open(pk, x)))
+                        .chain(shuffles.iter().flat_map(move |p| p.open(pk, x)))
+                },
+            )
+            .chain(
+                pk.vk
+                    .cs
+                    .fixed_queries
+                    .iter()
+                    .map(|&(column, at)| ProverQuery {
+                        point: domain.rotate_omega(*x, at),
+                        poly: &pk.fixed_polys[column.index()],
+                        blind: Blind::default(),
+                    }),
+            )
+            .chain(pk.permutation.open(x))
+            // We query the h(X) polynomial at x
+            .chain(vanishing.open(x));
+
+    let start = start_timer::<O>();
+    let prover = P::new(params);
+    prover
+        .create_proof(rng, transcript, instances)
+        .map_err(|_| Error::ConstraintSystemFailure)?;
+    observer.multiopen_done(elapsed(start));
+
+    Ok(ProofArtifacts {
+        challenges: meta.challenges().zip(challenges).collect(),
+        advice_commitments: advice_commitments_per_phase,
+        layout: None,
+    })
+}
+
+#[test]
+fn test_create_proof() {
+    use crate::{
+        circuit::SimpleFloorPlanner,
+        plonk::{keygen_pk, keygen_vk},
+        poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(_meta: &mut ConstraintSystem<F>) -> Self::Config {}
+
+        fn synthesize(
+            &self,
+            _config: Self::Config,
+            _layouter: impl crate::circuit::Layouter<F>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    let params = test_fixtures::params(3);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+
+    // Create proof with wrong number of instances
+    let proof = create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit, MyCircuit],
+        &[],
+        test_fixtures::rng(),
+        &mut transcript,
+    );
+    assert!(matches!(
+        proof.unwrap_err(),
+        Error::InstanceShape(InstanceMismatch::ProofCount {
+            expected: 2,
+            provided: 0,
+        })
+    ));
+
+    // Create proof with correct number of instances
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit, MyCircuit],
+        &[&[], &[]],
+        test_fixtures::rng(),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+}
+
+#[test]
+fn test_create_proof_degenerate_circuits() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{keygen_pk, keygen_vk, verify_proof, TableColumn},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::Fr;
+
+    /// Fixed columns and copy constraints between them, but no advice columns,
+    /// gates or lookups.
+    #[derive(Clone, Copy)]
+    struct FixedOnly;
+
+    impl<F: Field> Circuit<F> for FixedOnly {
+        type Config = [Column<Fixed>; 2];
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let columns = [meta.fixed_column(), meta.fixed_column()];
+            for column in columns {
+                meta.enable_equality(column);
+            }
+            columns
+        }
+
+        fn synthesize(
+            &self,
+            [a, b]: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "constants",
+                |mut region| {
+                    for row in 0..4 {
+                        let value = Value::known(F::from(row as u64 + 1));
+                        let left = region.assign_fixed(|| "a", a, row, || value)?;
+                        let right = region.assign_fixed(|| "b", b, row, || value)?;
+                        region.constrain_equal(left.cell(), right.cell())?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    /// An advice column looked up in a fixed table, without any gates.
+    #[derive(Clone, Copy)]
+    struct LookupOnly;
+
+    impl Circuit<Fr> for LookupOnly {
+        type Config = (Column<Advice>, TableColumn);
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let advice = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.lookup("in table", |meta| {
+                vec![(meta.query_advice(advice, Rotation::cur()), table)]
+            });
+            (advice, table)
+        }
+
+        fn synthesize(
+            &self,
+            (advice, table): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut t| {
+                    for row in 0..4 {
+                        t.assign_cell(
+                            || "entry",
+                            table,
+                            row,
+                            || Value::known(Fr::from(row as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "witness",
+                |mut region| {
+                    region.assign_advice(|| "value", advice, 0, || Value::known(Fr::from(2)))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn prove_and_verify<ConcreteCircuit: Circuit<Fr> + Copy>(circuit: ConcreteCircuit) {
+        let params = test_fixtures::params(4);
+        let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[circuit, circuit],
+            &[&[], &[]],
+            test_fixtures::rng(),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[], &[]],
+            &mut transcript,
+        )
+        .expect("proof should verify");
+    }
+
+    prove_and_verify(FixedOnly);
+    prove_and_verify(LookupOnly);
+}
+
+#[test]
+fn test_create_proof_from_witness() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof, FirstPhase, SecondPhase},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Selector,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Challenge,
+    }
+
+    /// Checks that `b = a * c` for a challenge `c` squeezed after `a`.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    const A: [u64; 4] = [1, 2, 3, 4];
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q = meta.selector();
+            let a = meta.advice_column_in(FirstPhase);
+            let b = meta.advice_column_in(SecondPhase);
+            let c = meta.challenge_usable_after(FirstPhase);
+            meta.create_gate("b = a * c", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_challenge(c);
+                vec![q * (b - a * c)]
+            });
+            MyConfig { q, a, b, c }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let c = layouter.get_challenge(config.c);
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    for (row, a) in A.iter().enumerate() {
+                        let a = Value::known(Fr::from(*a));
+                        config.q.enable(&mut region, row)?;
+                        region.assign_advice(|| "a", config.a, row, || a)?;
+                        region.assign_advice(|| "b", config.b, row, || a * c)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let c = MyCircuit::configure(&mut ConstraintSystem::default()).c;
+
+    let a: Vec<Fr> = A.iter().map(|a| Fr::from(*a)).collect();
+    let second_phase = |challenges: &Challenges<'_, Fr>| {
+        assert_eq!(challenges.phase(), 1);
+        let c = challenges
+            .get(c)
+            .expect("c is squeezed after the first phase");
+        vec![a.iter().map(|a| *a * c).collect()]
+    };
+
+    let verify = |proof: Vec<u8>| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<G1Affine>>::init(&proof[..]);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[], &[]],
+            &mut transcript,
+        )
+        .expect("proof should verify");
+    };
+
+    // Prove via synthesis.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit, MyCircuit],
+        &[&[], &[]],
+        test_fixtures::rng(),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    verify(transcript.finalize());
+
+    // Prove via an injected witness.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_from_witness::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        vec![vec![a.clone()], vec![a.clone()]],
+        &[&[], &[]],
+        second_phase,
+        test_fixtures::rng(),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    verify(transcript.finalize());
+
+    // Witnesses of the wrong shape are rejected.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let proof = create_proof_from_witness::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        vec![vec![a.clone(), a.clone()]],
+        &[&[]],
+        second_phase,
+        test_fixtures::rng(),
+        &mut transcript,
+    );
+    assert!(matches!(
+        proof.unwrap_err(),
+        Error::InvalidWitness {
+            circuit: 0,
+            phase: 0,
+            expected: 1,
+            provided: 2,
+        }
+    ));
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let proof = create_proof_from_witness::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        vec![vec![vec![Fr::ONE; params.n() as usize]]],
+        &[&[]],
+        second_phase,
+        test_fixtures::rng(),
+        &mut transcript,
+    );
+    assert!(matches!(
+        proof.unwrap_err(),
+        Error::NotEnoughRowsAvailable { .. }
+    ));
+}
+
+#[test]
+fn test_create_proof_with_engine() {
+    use crate::{
+        arithmetic::best_fft,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk_with_engine, keygen_vk, keygen_vk_with_engine, verify_proof},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine, G1};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Runs everything on the CPU, counting how often it is called.
+    #[derive(Default)]
+    struct CountingEngine {
+        multiexps: AtomicUsize,
+        ffts: AtomicUsize,
+    }
+
+    impl Engine<G1Affine> for CountingEngine {
+        fn best_multiexp(&self, coeffs: &[Fr], bases: &[G1Affine]) -> G1 {
+            self.multiexps.fetch_add(1, Ordering::Relaxed);
+            crate::arithmetic::best_multiexp(coeffs, bases)
+        }
+
+        fn fft(&self, values: &mut [Fr], omega: Fr, log_n: u32) {
+            self.ffts.fetch_add(1, Ordering::Relaxed);
+            best_fft(values, omega, log_n)
+        }
+    }
+
+    impl CountingEngine {
+        fn take(&self) -> (usize, usize) {
+            (
+                self.multiexps.swap(0, Ordering::Relaxed),
+                self.ffts.swap(0, Ordering::Relaxed),
+            )
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        q: Column<Fixed>,
+    }
+
+    /// Doubles a value row by row, copying each result into the next row.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let a = meta.advice_column();
+            let q = meta.fixed_column();
+            meta.enable_equality(a);
+            meta.create_gate("double", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let next = meta.query_advice(a, Rotation::next());
+                vec![q * (next - a.clone() - a)]
+            });
+            MyConfig { a, q }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut value = Fr::ONE;
+            let mut cell = layouter.assign_region(
+                || "start",
+                |mut region| region.assign_advice(|| "a", config.a, 0, || Value::known(value)),
+            )?;
+            for _ in 0..3 {
+                let doubled = value.double();
+                cell = layouter.assign_region(
+                    || "double",
+                    |mut region| {
+                        region.assign_fixed(|| "q", config.q, 0, || Value::known(Fr::ONE))?;
+                        cell.copy_advice(|| "a", &mut region, config.a, 0)?;
+                        region.assign_advice(|| "a", config.a, 1, || Value::known(doubled))
+                    },
+                )?;
+                value = doubled;
+            }
+            Ok(())
+        }
+    }
+
+    let engine = CountingEngine::default();
+    let params = test_fixtures::params(4);
+
+    // Keygen dispatches to the engine, and gives the same keys as on the CPU.
+    let vk =
+        keygen_vk_with_engine(&params, &MyCircuit, &engine).expect("keygen_vk should not fail");
+    let (multiexps, _) = engine.take();
+    assert!(multiexps > 0);
+    assert_eq!(
+        vk.transcript_repr(),
+        keygen_vk(&params, &MyCircuit)
+            .expect("keygen_vk should not fail")
+            .transcript_repr()
+    );
+    let pk =
+        keygen_pk_with_engine(&params, vk, &MyCircuit, &engine).expect("keygen_pk should not fail");
+    let (multiexps, ffts) = engine.take();
+    assert!(multiexps > 0 && ffts > 0);
+
+    // So does proving, and the proof verifies.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_engine::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        &[&[]],
+        test_fixtures::rng(),
+        &mut transcript,
+        &engine,
+    )
+    .expect("proof generation should not fail");
+    let (multiexps, ffts) = engine.take();
+    assert!(multiexps > 0 && ffts > 0);
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
+
+#[test]
+fn test_create_proof_with_artifacts() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, FirstPhase, SecondPhase},
+        poly::{
+            kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, Transcript, TranscriptRead,
+            TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Challenge,
+    }
+
+    /// Assigns `a` in the first phase and `a * c` in the second.
+    #[derive(Clone, Copy)]
+    struct MyCircuit(u64);
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let a = meta.advice_column_in(FirstPhase);
+            let b = meta.advice_column_in(SecondPhase);
+            let c = meta.challenge_usable_after(FirstPhase);
+            meta.create_gate("b = a * c", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let c = meta.query_challenge(c);
+                vec![b - a * c]
+            });
+            MyConfig { a, b, c }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let c = layouter.get_challenge(config.c);
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    let a = Value::known(Fr::from(self.0));
+                    region.assign_advice(|| "a", config.a, 0, || a)?;
+                    region.assign_advice(|| "b", config.b, 0, || a * c)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit(0)).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit(0)).expect("keygen_pk should not fail");
+    let c = MyCircuit::configure(&mut ConstraintSystem::default()).c;
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let artifacts =
+        create_proof_with_artifacts::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit(2), MyCircuit(3)],
+            &[&[], &[]],
+            test_fixtures::rng(),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+    assert_eq!(artifacts.challenges().len(), 1);
+
+    // Replay the start of the proof as the verifier does, re-deriving the
+    // challenge from the advice commitments.
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+    pk.get_vk().hash_into(&mut transcript).unwrap();
+    for phase in 0..2 {
+        for circuit in 0..2 {
+            let commitments = artifacts.advice_commitments(circuit, phase).unwrap();
+            assert_eq!(commitments.len(), 1);
+            for commitment in commitments {
+                assert_eq!(transcript.read_point().unwrap(), *commitment);
+            }
+        }
+        if phase == 0 {
+            let sampled = *transcript.squeeze_challenge_scalar::<()>();
+            assert_eq!(artifacts.challenge(c), Some(sampled));
+        }
+    }
+    assert!(artifacts.advice_commitments(2, 0).is_none());
+    assert!(artifacts.advice_commitments(0, 2).is_none());
+    // The layout is only recorded when asked for.
+    assert!(artifacts.layout().is_none());
+}
+
+#[test]
+fn test_create_proof_deterministic() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, TableColumn},
+        poly::{
+            commitment::ParamsProver,
+            ipa::{
+                commitment::{IPACommitmentScheme, ParamsIPA},
+                multiopen::ProverIPA,
+            },
+            Rotation,
+        },
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+    use halo2curves::pasta::{EqAffine, Fp};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        table: TableColumn,
+    }
+
+    /// Looks up a copied advice value in a small table, so that every
+    /// argument draws blinding factors.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(a);
+            meta.lookup("a in table", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+            MyConfig { a, table }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    for i in 0..4 {
+                        table.assign_cell(
+                            || "t",
+                            config.table,
+                            i,
+                            || Value::known(Fp::from(i as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    let a =
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(3)))?;
+                    a.copy_advice(|| "a", &mut region, config.a, 1)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let prove = |seed: [u8; 32]| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof_deterministic::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit],
+            &[&[]],
+            seed,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+
+    assert_eq!(prove([1; 32]), prove([1; 32]));
+    assert_ne!(prove([1; 32]), prove([2; 32]));
+}
+
+#[test]
+fn test_create_proof_observed() {
+    use crate::{
+        circuit::SimpleFloorPlanner,
+        plonk::{keygen_pk, keygen_vk},
+        poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl<F: Field> Circuit<F> for MyCircuit {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(_meta: &mut ConstraintSystem<F>) -> Self::Config {}
+
+        fn synthesize(
+            &self,
+            _config: Self::Config,
+            _layouter: impl crate::circuit::Layouter<F>,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Phase(u8),
+        Commitment(CommitmentKind, usize),
+        Quotient,
+        Multiopen,
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl ProverObserver for Recorder {
+        fn phase_started(&mut self, phase: u8) {
+            self.0.push(Event::Phase(phase));
+        }
+
+        fn commitment_done(&mut self, kind: CommitmentKind, index: usize, _: Duration) {
+            self.0.push(Event::Commitment(kind, index));
+        }
+
+        fn quotient_done(&mut self, _: Duration) {
+            self.0.push(Event::Quotient);
+        }
+
+        fn multiopen_done(&mut self, _: Duration) {
+            self.0.push(Event::Multiopen);
+        }
+    }
+
+    let params = test_fixtures::params(3);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let mut recorder = Recorder::default();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_observed::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit, MyCircuit],
+        &[&[], &[]],
+        test_fixtures::rng(),
+        &mut transcript,
+        &mut recorder,
+    )
+    .expect("proof generation should not fail");
+
+    let mut expected = vec![Event::Phase(0)];
+    for kind in [
+        CommitmentKind::Advice { phase: 0 },
+        CommitmentKind::LookupPermuted,
+        CommitmentKind::PermutationProduct,
+        CommitmentKind::LookupProduct,
+        CommitmentKind::ShuffleProduct,
+    ] {
+        expected.extend((0..2).map(|index| Event::Commitment(kind, index)));
+    }
+    expected.extend([
+        Event::Commitment(CommitmentKind::VanishingRandom, 0),
+        Event::Quotient,
+        Event::Multiopen,
+    ]);
+    assert_eq!(recorder.0, expected);
+}
+
+#[test]
+fn test_create_proof_without_zero_knowledge() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof, TableColumn},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    const K: u32 = 4;
+    // Every row but the last one is usable without zero knowledge.
+    const ROWS: usize = (1 << K) - 1;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        table: TableColumn,
+    }
+
+    /// Fills every usable row with values looked up in a small table, copying
+    /// the first value into the last row.
+    #[derive(Clone, Copy)]
+    struct MyCircuit<const ZK: bool>;
+
+    impl<const ZK: bool> Circuit<Fr> for MyCircuit<ZK> {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            if !ZK {
+                meta.disable_zero_knowledge();
+            }
+            let a = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(a);
+            meta.lookup("a in table", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+            MyConfig { a, table }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    for i in 0..4 {
+                        table.assign_cell(
+                            || "t",
+                            config.table,
+                            i,
+                            || Value::known(Fr::from(i as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    let first =
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(3)))?;
+                    for row in 1..ROWS - 1 {
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            row,
+                            || Value::known(Fr::from((row % 4) as u64)),
+                        )?;
+                    }
+                    first.copy_advice(|| "a", &mut region, config.a, ROWS - 1)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(K);
+
+    // The rows are not available when they are reserved for blinding factors.
+    assert!(matches!(
+        keygen_vk(&params, &MyCircuit::<true>).unwrap_err(),
+        Error::NotEnoughRowsAvailable { .. }
+    ));
+
+    let vk = keygen_vk(&params, &MyCircuit::<false>).expect("keygen_vk should not fail");
+    assert!(!vk.cs().zero_knowledge());
+    assert_eq!(vk.cs().blinding_factors(), 0);
+    let pk = keygen_pk(&params, vk, &MyCircuit::<false>).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit::<false>],
+        &[&[]],
+        test_fixtures::rng(),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
+
+#[test]
+fn test_batch_prover() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+    }
+
+    /// Exposes its witness as a public input.
+    #[derive(Clone, Copy)]
+    struct MyCircuit(u64);
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let a = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.create_gate("a = instance", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                let instance = meta.query_instance(instance, Rotation::cur());
+                vec![a - instance]
+            });
+            MyConfig { a }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "a",
+                |mut region| {
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(self.0)))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit(0)).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit(0)).expect("keygen_pk should not fail");
+
+    let verify = |proof: &[u8], value: u64| {
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[&[Fr::from(value)]]],
+            &mut transcript,
+        )
+        .is_ok()
+    };
+
+    let mut prover = BatchProver::<KZGCommitmentScheme<_>, _>::new(&params, &pk);
+    for value in [1, 2] {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        prover
+            .prove::<ProverSHPLONK<_>, _, _, _>(
+                &mut transcript,
+                &[&[&[Fr::from(value)]]],
+                &[MyCircuit(value)],
+                test_fixtures::rng(),
+            )
+            .expect("proof generation should not fail");
+        assert!(verify(&transcript.finalize(), value));
+    }
+
+    let values = [3, 4, 5];
+    let circuits: Vec<_> = values.iter().map(|value| MyCircuit(*value)).collect();
+    let instances: Vec<_> = values.iter().map(|value| [Fr::from(*value)]).collect();
+    let instances: Vec<_> = instances.iter().map(|instance| [&instance[..]]).collect();
+    let instances: Vec<_> = instances.iter().map(|instance| &instance[..]).collect();
+    let transcripts = prover
+        .prove_many::<ProverSHPLONK<_>, _, _, _>(&circuits, &instances, |_| {
+            (
+                test_fixtures::rng(),
+                Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]),
+            )
+        })
+        .expect("proof generation should not fail");
+    assert_eq!(transcripts.len(), values.len());
+    for (transcript, value) in transcripts.into_iter().zip(values) {
+        let proof = transcript.finalize();
+        assert!(verify(&proof, value));
+        assert!(!verify(&proof, value + 1));
+    }
+}
+
+#[test]
+fn test_create_proof_instance_shape() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{keygen_pk, keygen_vk},
+        poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+    use halo2curves::bn256::Fr;
+
+    /// A circuit with two instance columns that must never be synthesized
+    /// while proving.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            meta.instance_column();
+            meta.instance_column();
+        }
+
+        fn synthesize(&self, _: Self::Config, _: impl Layouter<Fr>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct Unsynthesizable;
+
+    impl Circuit<Fr> for Unsynthesizable {
+        type Config = ();
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MyCircuit::configure(meta)
+        }
+
+        fn synthesize(&self, _: Self::Config, _: impl Layouter<Fr>) -> Result<(), Error> {
+            panic!("instances should be validated before synthesis");
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let usable_rows = params.n() as usize - (pk.get_vk().cs().blinding_factors() + 1);
+
+    let prove = |instances: &[&[&[Fr]]]| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[Unsynthesizable, Unsynthesizable],
+            instances,
+            test_fixtures::rng(),
+            &mut transcript,
+        )
+        .unwrap_err()
+    };
+
+    let short = [Fr::ONE; 1];
+    let long = vec![Fr::ONE; usable_rows + 1];
+    assert!(matches!(
+        prove(&[&[&short, &short]]),
+        Error::InstanceShape(InstanceMismatch::ProofCount {
+            expected: 2,
+            provided: 1,
+        })
+    ));
+    let err = prove(&[&[&short, &short], &[&short]]);
+    assert_eq!(
+        err.to_string(),
+        "Invalid instances: expected 2 instance columns, got 1 for circuit instance 1"
+    );
+    assert!(matches!(
+        err,
+        Error::InstanceShape(InstanceMismatch::ColumnCount {
+            circuit: 1,
+            expected: 2,
+            provided: 1,
+        })
+    ));
+    assert!(matches!(
+        prove(&[&[&short, &short], &[&short, &long]]),
+        Error::InstanceShape(InstanceMismatch::ColumnTooLong {
+            circuit: 1,
+            column: 1,
+            usable_rows: rows,
+            provided,
+        }) if rows == usable_rows && provided == usable_rows + 1
+    ));
+}
+
+#[test]
+fn test_create_proof_deterministic_all_arguments() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof, Instance, TableColumn},
+        poly::{
+            commitment::ParamsProver,
+            ipa::{
+                commitment::{IPACommitmentScheme, ParamsIPA},
+                multiopen::{ProverIPA, VerifierIPA},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::pasta::{EqAffine, Fp};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        instance: Column<Instance>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        table: TableColumn,
+    }
+
+    /// Copies a public input into an advice column, looks its values up in a
+    /// table and shuffles them into a second advice column, so that the
+    /// instance columns are used by every argument.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let instance = meta.instance_column();
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(instance);
+            meta.enable_equality(a);
+            meta.lookup("a in table", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+            meta.shuffle("b is a shuffle of a", |meta| {
+                vec![(
+                    meta.query_advice(a, Rotation::cur()),
+                    meta.query_advice(b, Rotation::cur()),
+                )]
+            });
+            MyConfig {
+                instance,
+                a,
+                b,
+                table,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    for i in 0..4 {
+                        table.assign_cell(
+                            || "t",
+                            config.table,
+                            i,
+                            || Value::known(Fp::from(i as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    region.assign_advice_from_instance(|| "a", config.instance, 0, config.a, 0)?;
+                    for (row, value) in [(1, 1), (2, 2)] {
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            row,
+                            || Value::known(Fp::from(value)),
+                        )?;
+                    }
+                    for (row, value) in [(0, 1), (1, 2), (2, 3)] {
+                        region.assign_advice(
+                            || "b",
+                            config.b,
+                            row,
+                            || Value::known(Fp::from(value)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(5);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let public = [Fp::from(3)];
+    let instances: &[&[&[Fp]]] = &[&[&public], &[&public]];
+    let prove = || {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof_deterministic::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit, MyCircuit],
+            instances,
+            [7; 32],
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+
+    let proof = prove();
+    assert_eq!(proof, prove());
+
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        instances,
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
+
+#[test]
+fn test_create_proof_timed() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, TableColumn},
+        poly::{
+            kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+            Rotation,
+        },
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+    use halo2curves::bn256::Fr;
+
+    const K: u32 = 10;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        s: Selector,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        table: TableColumn,
+    }
+
+    /// Squares looked-up values over most of the rows, so that every stage
+    /// of the prover has some work to do.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let s = meta.selector();
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(a);
+            meta.create_gate("square", |meta| {
+                let s = meta.query_selector(s);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![s * (a.clone() * a - b)]
+            });
+            meta.lookup("a in table", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+            meta.shuffle("a is a shuffle of itself", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                vec![(a.clone(), a)]
+            });
+            MyConfig { s, a, b, table }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    for i in 0..256 {
+                        table.assign_cell(
+                            || "t",
+                            config.table,
+                            i,
+                            || Value::known(Fr::from(i as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "squares",
+                |mut region| {
+                    for row in 0..(1 << (K - 1)) {
+                        let a = Fr::from((row % 256) as u64);
+                        config.s.enable(&mut region, row)?;
+                        region.assign_advice(|| "a", config.a, row, || Value::known(a))?;
+                        region.assign_advice(|| "b", config.b, row, || Value::known(a * a))?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(K);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let start = Instant::now();
+    let timings = create_proof_timed::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit, MyCircuit],
+        &[&[], &[]],
+        test_fixtures::rng(),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let wall = start.elapsed();
+
+    assert_eq!(timings.synthesis.len(), 1);
+    for stage in [
+        timings.synthesis[0],
+        timings.advice_commitments,
+        timings.lookups,
+        timings.permutation,
+        timings.shuffles,
+        timings.quotient_evaluation,
+        timings.quotient_fft,
+        timings.evaluations,
+        timings.multiopen,
+    ] {
+        assert!(stage > Duration::ZERO, "{:?}", timings);
+    }
+    assert!(timings.quotient_evaluation + timings.quotient_fft <= timings.quotient);
+    match &timings.gates[..] {
+        [square] => {
+            assert_eq!(square.gates, vec!["square".to_string()]);
+            assert!(square.duration > Duration::ZERO);
+            assert!(square.duration <= timings.quotient_evaluation);
+        }
+        gates => panic!("unexpected gate timings {:?}", gates),
+    }
+
+    // The stages cover everything but configuring the circuit and hashing
+    // the verifying key and the instances into the transcript.
+    let total = timings.total();
+    assert!(total <= wall, "{:?} > {:?}", total, wall);
+    assert!(total >= wall / 2, "{:?} < {:?}", total, wall);
+}
+
+#[test]
+fn test_lookup_backends_equivalent() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{keygen_pk, keygen_vk, verify_proof, LookupBackend, TableColumn},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::{KZGCommitmentScheme, ParamsKZG},
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Bn256, Fr};
+
+    const K: u32 = 8;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        table: TableColumn,
+    }
+
+    /// Looks up two columns in a table with a repeated value, under the
+    /// lookup argument selected by `LOGUP`. If `bad` is set, one of the
+    /// inputs is not in the table.
+    #[derive(Clone, Copy)]
+    struct MyCircuit<const LOGUP: bool> {
+        bad: bool,
+    }
+
+    impl<const LOGUP: bool> Circuit<Fr> for MyCircuit<LOGUP> {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            if LOGUP {
+                meta.set_lookup_backend(LookupBackend::LogUp);
+            }
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.lookup("a in table", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+            meta.lookup("b in table", |meta| {
+                vec![(meta.query_advice(b, Rotation::cur()), table)]
+            });
+            MyConfig { a, b, table }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    // 0..16, then 5 again
+                    for (i, value) in (0..16).chain(Some(5)).enumerate() {
+                        table.assign_cell(
+                            || "t",
+                            config.table,
+                            i,
+                            || Value::known(Fr::from(value)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "inputs",
+                |mut region| {
+                    for row in 0..200 {
+                        let a = if self.bad && row == 3 { 100 } else { row % 16 };
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            row,
+                            || Value::known(Fr::from(a as u64)),
+                        )?;
+                        region.assign_advice(
+                            || "b",
+                            config.b,
+                            row,
+                            || Value::known(Fr::from(5)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn prove<const LOGUP: bool>(
+        params: &ParamsKZG<Bn256>,
+        bad: bool,
+    ) -> (Result<Vec<u8>, Error>, [u8; 32]) {
+        let circuit = MyCircuit::<LOGUP> { bad };
+        let vk = keygen_vk(params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(params, vk, &circuit).expect("keygen_pk should not fail");
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        let proof = create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            params,
+            &pk,
+            &[circuit],
+            &[&[]],
+            test_fixtures::rng(),
+            &mut transcript,
+        )
+        .map(|_| transcript.finalize());
+
+        if let Ok(proof) = &proof {
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+                params.verifier_params(),
+                pk.get_vk(),
+                SingleStrategy::new(params),
+                &[&[]],
+                &mut transcript,
+            )
+            .expect("proof should verify");
+        }
+        (proof, pk.get_vk().fingerprint())
+    }
+
+    for bad in [false, true] {
+        let permutation = MockProver::run(K, &MyCircuit::<false> { bad }, vec![]).unwrap();
+        let logup = MockProver::run(K, &MyCircuit::<true> { bad }, vec![]).unwrap();
+        assert_eq!(permutation.verify().is_ok(), !bad);
+        assert_eq!(logup.verify().is_ok(), !bad);
+    }
+
+    let params = test_fixtures::params(K);
+    let (permutation_proof, permutation_fingerprint) = prove::<false>(&params, false);
+    let (logup_proof, logup_fingerprint) = prove::<true>(&params, false);
+    let (permutation_proof, logup_proof) = (permutation_proof.unwrap(), logup_proof.unwrap());
+
+    // Each lookup saves one commitment and two evaluations.
+    assert!(logup_proof.len() < permutation_proof.len());
+    assert_eq!(
+        permutation_proof.len() - logup_proof.len(),
+        2 * (32 + 2 * 32)
+    );
+    assert_ne!(permutation_fingerprint, logup_fingerprint);
+
+    assert!(matches!(
+        prove::<true>(&params, true).0,
+        Err(Error::ConstraintSystemFailure)
+    ));
+}
+
+#[test]
+fn test_diff_transcripts() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{
+            diff_transcripts, keygen_pk, keygen_vk, verify_proof_listened, TranscriptEvent,
+            TranscriptRecorder,
+        },
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        s: Selector,
+        a: Column<Advice>,
+        i: Column<Instance>,
+    }
+
+    /// Constrains an advice cell to equal the first instance value.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let s = meta.selector();
+            let a = meta.advice_column();
+            let i = meta.instance_column();
+            meta.create_gate("a equals i", |meta| {
+                let s = meta.query_selector(s);
+                let a = meta.query_advice(a, Rotation::cur());
+                let i = meta.query_instance(i, Rotation::cur());
+                vec![s * (a - i)]
+            });
+            MyConfig { s, a, i }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "a",
+                |mut region| {
+                    config.s.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(1)))?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let mut prover_events = TranscriptRecorder::<G1Affine>::new();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_listened::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        &[&[&[Fr::from(1)]]],
+        test_fixtures::rng(),
+        &mut transcript,
+        &mut prover_events,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let events = &prover_events.events;
+    assert!(matches!(
+        events[0],
+        TranscriptEvent::Commitment {
+            kind: CommitmentKind::Advice { phase: 0 },
+            index: 0,
+            ..
+        }
+    ));
+    for kind in [
+        CommitmentKind::PermutationProduct,
+        CommitmentKind::VanishingRandom,
+        CommitmentKind::Quotient,
+        CommitmentKind::Multiopen,
+    ] {
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TranscriptEvent::Commitment { kind: k, .. } if *k == kind
+        )));
+    }
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, TranscriptEvent::Evaluation(_))));
+
+    let verify = |instance: Fr| {
+        let mut verifier_events = TranscriptRecorder::new();
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        let result = verify_proof_listened::<_, VerifierSHPLONK<_>, _, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[&[instance]]],
+            &mut transcript,
+            &mut verifier_events,
+        );
+        (result.is_ok(), verifier_events.events)
+    };
+
+    // Both sides see the same transcript.
+    let (verified, verifier_events) = verify(Fr::from(1));
+    assert!(verified);
+    assert_eq!(diff_transcripts(events, &verifier_events), None);
+
+    // With the wrong instance, the commitments still match but the first
+    // challenge, theta, does not.
+    let (verified, verifier_events) = verify(Fr::from(2));
+    assert!(!verified);
+    let divergence =
+        diff_transcripts(events, &verifier_events).expect("transcripts should diverge");
+    assert_eq!(divergence.position, 1);
+    assert!(matches!(
+        (&divergence.prover, &divergence.verifier),
+        (
+            Some(TranscriptEvent::Challenge(_)),
+            Some(TranscriptEvent::Challenge(_))
+        )
+    ));
+}
+
+#[cfg(feature = "disk-offload")]
+#[test]
+fn test_create_proof_with_disk_offload() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::KZGCommitmentScheme,
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::Fr;
+    use std::fs;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        s: Selector,
+        a: Column<Advice>,
+        i: Column<Instance>,
+    }
+
+    /// Constrains an advice cell to equal the first instance value.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let s = meta.selector();
+            let a = meta.advice_column();
+            let i = meta.instance_column();
+            meta.enable_equality(a);
+            meta.create_gate("a equals i", |meta| {
+                let s = meta.query_selector(s);
+                let a = meta.query_advice(a, Rotation::cur());
+                let i = meta.query_instance(i, Rotation::cur());
+                vec![s * (a - i)]
+            });
+            MyConfig { s, a, i }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "a",
+                |mut region| {
+                    config.s.enable(&mut region, 0)?;
+                    let a =
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(3)))?;
+                    a.copy_advice(|| "a", &mut region, config.a, 1)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("halo2-offload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let files = || fs::read_dir(&dir).unwrap().count();
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk.clone(), &MyCircuit).expect("keygen_pk should not fail");
+    let mut offloaded_pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    offloaded_pk
+        .offload_to_disk(&dir)
+        .expect("offloading should not fail");
+    assert!(offloaded_pk
+        .fixed_values()
+        .iter()
+        .all(|poly| poly.is_on_disk()));
+    assert!(offloaded_pk
+        .cosets
+        .as_ref()
+        .unwrap()
+        .fixed
+        .iter()
+        .all(|poly| poly.is_on_disk()));
+    let key_files = files();
+    assert!(key_files > 0);
+
+    let instances: &[&[&[Fr]]] = &[&[&[Fr::from(3)]]];
+    let seed = [7u8; 32];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_deterministic::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        seed,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let expected = transcript.finalize();
+
+    // Offloading the key and the witness does not change the proof, and the
+    // witness's files are removed once the proof is done.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_options::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &offloaded_pk,
+        &[MyCircuit],
+        instances,
+        ChaCha20Rng::from_seed(seed),
+        &mut transcript,
+        &ProverOptions::new().disk_offload(&dir),
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+    assert_eq!(proof, expected);
+    assert_eq!(files(), key_files);
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        offloaded_pk.get_vk(),
+        SingleStrategy::new(&params),
+        instances,
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    drop(offloaded_pk);
+    assert_eq!(files(), 0);
+    fs::remove_dir(&dir).unwrap();
+}
 
-    // Obtain challenge for keeping all separate gates linearly independent
-    let y: ChallengeY<_> = transcript.squeeze_challenge_scalar();
+#[test]
+fn test_quotient_chunk_sizes() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, TableColumn},
+        poly::{
+            kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+            Rotation,
+        },
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    };
+    use halo2curves::bn256::Fr;
 
-    // Calculate the advice polys
-    let advice: Vec<AdviceSingle<Scheme::Curve, Coeff>> = advice
-        .into_iter()
-        .map(
-            |AdviceSingle {
-                 advice_polys,
-                 advice_blinds,
-             }| {
-                AdviceSingle {
-                    advice_polys: advice_polys
-                        .into_iter()
-                        .map(|poly| domain.lagrange_to_coeff(poly))
-                        .collect::<Vec<_>>(),
-                    advice_blinds,
-                }
-            },
-        )
-        .collect();
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Column<Fixed>,
+        a: Column<Advice>,
+        table: TableColumn,
+    }
 
-    // Evaluate the h(X) polynomial
-    let h_poly = pk.ev.evaluate_h(
-        pk,
-        &advice
-            .iter()
-            .map(|a| a.advice_polys.as_slice())
-            .collect::<Vec<_>>(),
-        &instance
-            .iter()
-            .map(|i| i.instance_polys.as_slice())
-            .collect::<Vec<_>>(),
-        &challenges,
-        *y,
-        *beta,
-        *gamma,
-        *theta,
-        &lookups,
-        &shuffles,
-        &permutations,
-    );
+    /// Doubles a value row by row, looking each one up in a table of small
+    /// values.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
 
-    // Construct the vanishing argument's h(X) commitments
-    let vanishing = vanishing.construct(params, domain, h_poly, &mut rng, transcript)?;
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
-    let x: ChallengeX<_> = transcript.squeeze_challenge_scalar();
-    let xn = x.pow([params.n()]);
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
 
-    if P::QUERY_INSTANCE {
-        // Compute and hash instance evals for each circuit instance
-        for instance in instance.iter() {
-            // Evaluate polynomials at omega^i x
-            let instance_evals: Vec<_> = meta
-                .instance_queries
-                .iter()
-                .map(|&(column, at)| {
-                    eval_polynomial(
-                        &instance.instance_polys[column.index()],
-                        domain.rotate_omega(*x, at),
-                    )
-                })
-                .collect();
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q = meta.fixed_column();
+            let a = meta.advice_column();
+            let table = meta.lookup_table_column();
+            meta.enable_equality(a);
+            meta.create_gate("double", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let next = meta.query_advice(a, Rotation::next());
+                vec![q * (next - a.clone() - a)]
+            });
+            meta.lookup("small", |meta| {
+                vec![(meta.query_advice(a, Rotation::cur()), table)]
+            });
+            MyConfig { q, a, table }
+        }
 
-            // Hash each instance column evaluation
-            for eval in instance_evals.iter() {
-                transcript.write_scalar(*eval)?;
-            }
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "small values",
+                |mut table| {
+                    for i in 0..32 {
+                        let value = Value::known(Fr::from(i as u64));
+                        table.assign_cell(|| "t", config.table, i, || value)?;
+                    }
+                    Ok(())
+                },
+            )?;
+            let a = layouter.assign_region(
+                || "doubles",
+                |mut region| {
+                    for i in 0..4 {
+                        region.assign_fixed(|| "q", config.q, i, || Value::known(Fr::ONE))?;
+                        let value = Value::known(Fr::from(1 << i));
+                        region.assign_advice(|| "a", config.a, i, || value)?;
+                    }
+                    region.assign_advice(|| "a", config.a, 4, || Value::known(Fr::from(16)))
+                },
+            )?;
+            layouter.assign_region(
+                || "copy",
+                |mut region| a.copy_advice(|| "a", &mut region, config.a, 0).map(|_| ()),
+            )
         }
     }
 
-    // Compute and hash advice evals for each circuit instance
-    for advice in advice.iter() {
-        // Evaluate polynomials at omega^i x
-        let advice_evals: Vec<_> = meta
-            .advice_queries
-            .iter()
-            .map(|&(column, at)| {
-                eval_polynomial(
-                    &advice.advice_polys[column.index()],
-                    domain.rotate_omega(*x, at),
-                )
-            })
-            .collect();
+    let params = test_fixtures::params(5);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
 
-        // Hash each advice column evaluation
-        for eval in advice_evals.iter() {
-            transcript.write_scalar(*eval)?;
-        }
+    let prove = |options: ProverOptions| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof_with_options::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit],
+            &[&[]],
+            ChaCha20Rng::from_seed([3u8; 32]),
+            &mut transcript,
+            &options,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+
+    // The split of the rows does not change the proof.
+    let expected = prove(ProverOptions::new());
+    for chunk_size in [
+        ChunkSize::Rows(1),
+        ChunkSize::Rows(7),
+        ChunkSize::Rows(1 << 10),
+        ChunkSize::Auto,
+    ] {
+        let options = ProverOptions::new()
+            .gate_chunk_size(chunk_size)
+            .argument_chunk_size(chunk_size);
+        assert_eq!(prove(options), expected, "{:?}", chunk_size);
     }
+}
 
-    // Compute and hash fixed evals (shared across all circuit instances)
-    let fixed_evals: Vec<_> = meta
-        .fixed_queries
-        .iter()
-        .map(|&(column, at)| {
-            eval_polynomial(&pk.fixed_polys[column.index()], domain.rotate_omega(*x, at))
-        })
-        .collect();
+#[test]
+fn test_proof_without_cached_cosets() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk},
+        poly::{
+            kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+            Rotation,
+        },
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+        SerdeFormat,
+    };
+    use halo2curves::bn256::{Fr, G1Affine};
 
-    // Hash each fixed column evaluation
-    for eval in fixed_evals.iter() {
-        transcript.write_scalar(*eval)?;
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Column<Fixed>,
+        c: Column<Fixed>,
+        a: Column<Advice>,
     }
 
-    let vanishing = vanishing.evaluate(x, xn, domain, transcript)?;
+    /// Adds a constant to a value, and copies the result.
+    #[derive(Clone, Copy)]
+    struct MyCircuit;
 
-    // Evaluate common permutation data
-    pk.permutation.evaluate(x, transcript)?;
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
-    // Evaluate the permutations, if any, at omega^i x.
-    let permutations: Vec<permutation::prover::Evaluated<Scheme::Curve>> = permutations
-        .into_iter()
-        .map(|permutation| -> Result<_, _> { permutation.construct().evaluate(pk, x, transcript) })
-        .collect::<Result<Vec<_>, _>>()?;
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
 
-    // Evaluate the lookups, if any, at omega^i x.
-    let lookups: Vec<Vec<lookup::prover::Evaluated<Scheme::Curve>>> = lookups
-        .into_iter()
-        .map(|lookups| -> Result<Vec<_>, _> {
-            lookups
-                .into_iter()
-                .map(|p| p.evaluate(pk, x, transcript))
-                .collect::<Result<Vec<_>, _>>()
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q = meta.fixed_column();
+            let c = meta.fixed_column();
+            let a = meta.advice_column();
+            meta.enable_equality(a);
+            meta.create_gate("add constant", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let c = meta.query_fixed(c, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let next = meta.query_advice(a, Rotation::next());
+                vec![q * (next - a - c)]
+            });
+            MyConfig { q, c, a }
+        }
 
-    // Evaluate the shuffles, if any, at omega^i x.
-    let shuffles: Vec<Vec<shuffle::prover::Evaluated<Scheme::Curve>>> = shuffles
-        .into_iter()
-        .map(|shuffles| -> Result<Vec<_>, _> {
-            shuffles
-                .into_iter()
-                .map(|p| p.evaluate(pk, x, transcript))
-                .collect::<Result<Vec<_>, _>>()
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let sum = layouter.assign_region(
+                || "add",
+                |mut region| {
+                    region.assign_fixed(|| "q", config.q, 0, || Value::known(Fr::ONE))?;
+                    region.assign_fixed(|| "c", config.c, 0, || Value::known(Fr::from(5)))?;
+                    region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(2)))?;
+                    region.assign_advice(|| "sum", config.a, 1, || Value::known(Fr::from(7)))
+                },
+            )?;
+            layouter.assign_region(
+                || "copy",
+                |mut region| {
+                    sum.copy_advice(|| "sum", &mut region, config.a, 0)
+                        .map(|_| ())
+                },
+            )
+        }
+    }
 
-    let instances = instance
-        .iter()
-        .zip(advice.iter())
-        .zip(permutations.iter())
-        .zip(lookups.iter())
-        .zip(shuffles.iter())
-        .flat_map(|((((instance, advice), permutation), lookups), shuffles)| {
-            iter::empty()
-                .chain(
-                    P::QUERY_INSTANCE
-                        .then_some(pk.vk.cs.instance_queries.iter().map(move |&(column, at)| {
-                            ProverQuery {
-                                point: domain.rotate_omega(*x, at),
-                                poly: &instance.instance_polys[column.index()],
-                                blind: Blind::default(),
-                            }
-                        }))
-                        .into_iter()
-                        .flatten(),
-                )
-                .chain(
-                    pk.vk
-                        .cs
-                        .advice_queries
-                        .iter()
-                        .map(move |&(column, at)| ProverQuery {
-                            point: domain.rotate_omega(*x, at),
-                            poly: &advice.advice_polys[column.index()],
-                            blind: advice.advice_blinds[column.index()],
-                        }),
-                )
-                .chain(permutation.open(pk, x))
-                .chain(lookups.iter().flat_map(move |p| p.open(pk, x)))
-                .chain(shuffles.iter().flat_map(move |p| p.open(pk, x)))
-        })
-        .chain(
-            pk.vk
-                .cs
-                .fixed_queries
-                .iter()
-                .map(|&(column, at)| ProverQuery {
-                    point: domain.rotate_omega(*x, at),
-                    poly: &pk.fixed_polys[column.index()],
-                    blind: Blind::default(),
-                }),
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let mut pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    assert!(pk.has_cosets());
+
+    let prove = |pk: &ProvingKey<G1Affine>| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof_deterministic::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _>(
+            &params,
+            pk,
+            &[MyCircuit],
+            &[&[]],
+            [11u8; 32],
+            &mut transcript,
         )
-        .chain(pk.permutation.open(x))
-        // We query the h(X) polynomial at x
-        .chain(vanishing.open(x));
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
 
-    let prover = P::new(params);
-    prover
-        .create_proof(rng, transcript, instances)
-        .map_err(|_| Error::ConstraintSystemFailure)
+    // The cosets are computed for each proof once cleared, giving the same
+    // proofs, and leave the serialized key unchanged.
+    let expected = prove(&pk);
+    let bytes = pk.to_bytes(SerdeFormat::RawBytes);
+    pk.clear_cosets();
+    assert!(!pk.has_cosets());
+    assert_eq!(prove(&pk), expected);
+    assert_eq!(pk.to_bytes(SerdeFormat::RawBytes), bytes);
+
+    pk.compute_cosets();
+    assert!(pk.has_cosets());
+    assert_eq!(prove(&pk), expected);
+
+    // Keys read back compute their cosets.
+    let read = ProvingKey::<G1Affine>::from_bytes::<MyCircuit>(
+        &bytes,
+        SerdeFormat::RawBytes,
+        #[cfg(feature = "circuit-params")]
+        (),
+    )
+    .expect("reading the key should not fail");
+    assert!(read.has_cosets());
+    assert_eq!(prove(&read), expected);
 }
 
 #[test]
-fn test_create_proof() {
+fn test_unblinded_advice_column() {
     use crate::{
-        circuit::SimpleFloorPlanner,
-        plonk::{keygen_pk, keygen_vk},
-        poly::kzg::{
-            commitment::{KZGCommitmentScheme, ParamsKZG},
-            multiopen::ProverSHPLONK,
+        arithmetic::best_multiexp,
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof},
+        poly::{
+            commitment::ParamsProver,
+            ipa::{
+                commitment::{IPACommitmentScheme, ParamsIPA},
+                multiopen::{ProverIPA, VerifierIPA},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
         },
-        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
     };
-    use halo2curves::bn256::Bn256;
-    use rand_core::OsRng;
+    use halo2curves::pasta::{EqAffine, Fp};
+
+    const VALUES: [u64; 7] = [1, 2, 3, 4, 5, 6, 7];
 
+    /// The sum of the public values around `row`.
+    fn window(row: usize) -> u64 {
+        VALUES[row - 1..row + 3].iter().sum()
+    }
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Column<Fixed>,
+        public: Column<Advice>,
+        private: Column<Advice>,
+    }
+
+    /// Sums windows of values that are public by construction into a blinded
+    /// column.
     #[derive(Clone, Copy)]
     struct MyCircuit;
 
-    impl<F: Field> Circuit<F> for MyCircuit {
-        type Config = ();
+    impl Circuit<Fp> for MyCircuit {
+        type Config = MyConfig;
         type FloorPlanner = SimpleFloorPlanner;
         #[cfg(feature = "circuit-params")]
         type Params = ();
@@ -725,41 +4226,268 @@ fn test_create_proof() {
             *self
         }
 
-        fn configure(_meta: &mut ConstraintSystem<F>) -> Self::Config {}
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let q = meta.fixed_column();
+            let public = meta.unblinded_advice_column();
+            let private = meta.advice_column();
+            meta.create_gate("window", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let sum = [-1, 0, 1, 2]
+                    .into_iter()
+                    .map(|rotation| meta.query_advice(public, Rotation(rotation)))
+                    .reduce(|acc, value| acc + value)
+                    .unwrap();
+                let private = meta.query_advice(private, Rotation::cur());
+                vec![q * (private - sum)]
+            });
+            MyConfig { q, public, private }
+        }
 
         fn synthesize(
             &self,
-            _config: Self::Config,
-            _layouter: impl crate::circuit::Layouter<F>,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
         ) -> Result<(), Error> {
-            Ok(())
+            layouter.assign_region(
+                || "windows",
+                |mut region| {
+                    for (row, value) in VALUES.iter().enumerate() {
+                        let value = Value::known(Fp::from(*value));
+                        region.assign_advice(|| "public", config.public, row, || value)?;
+                    }
+                    for row in 1..=4 {
+                        let sum = Value::known(Fp::from(window(row)));
+                        region.assign_fixed(|| "q", config.q, row, || Value::known(Fp::ONE))?;
+                        region.assign_advice(|| "private", config.private, row, || sum)?;
+                    }
+                    Ok(())
+                },
+            )
         }
     }
 
-    let params: ParamsKZG<Bn256> = ParamsKZG::setup(3, OsRng);
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(4);
     let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    // Only the blinded column counts towards the blinding factors, although
+    // the unblinded one is queried more often.
+    assert_eq!(vk.cs().unblinded_advice_columns(), &[0]);
+    assert_eq!(vk.cs().blinding_factors(), 5);
+    assert!(format!("{:?}", vk.pinned()).contains("unblinded_advice_columns: [0]"));
     let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
     let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let artifacts =
+        create_proof_with_artifacts::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit],
+            &[&[]],
+            test_fixtures::rng(),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+    let proof = transcript.finalize();
 
-    // Create proof with wrong number of instances
-    let proof = create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
-        &params,
-        &pk,
-        &[MyCircuit, MyCircuit],
-        &[],
-        OsRng,
-        &mut transcript,
-    );
-    assert!(matches!(proof.unwrap_err(), Error::InvalidInstances));
+    // The unblinded column is committed to as the plain multiexponentiation of
+    // its values, padded with zeroes, unlike the blinded one.
+    let msm = |values: &[u64]| {
+        let mut scalars = vec![Fp::ZERO; params.n() as usize];
+        for (scalar, value) in scalars.iter_mut().zip(values) {
+            *scalar = Fp::from(*value);
+        }
+        best_multiexp(&scalars, &params.g_lagrange).to_affine()
+    };
+    let commitments = artifacts.advice_commitments(0, 0).unwrap();
+    assert_eq!(commitments[0], msm(&VALUES));
+    let sums: Vec<_> = (0..=4)
+        .map(|row| if row == 0 { 0 } else { window(row) })
+        .collect();
+    assert_ne!(commitments[1], msm(&sums));
 
-    // Create proof with correct number of instances
-    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
-        &params,
-        &pk,
-        &[MyCircuit, MyCircuit],
-        &[&[], &[]],
-        OsRng,
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
         &mut transcript,
     )
-    .expect("proof generation should not fail");
+    .expect("proof should verify");
+}
+
+#[test]
+fn test_evaluation_strategies_equivalent() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{keygen_pk, keygen_vk, verify_proof, LookupBackend, TableColumn},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::{KZGCommitmentScheme, ParamsKZG},
+                multiopen::{ProverSHPLONK, VerifierSHPLONK},
+                strategy::SingleStrategy,
+            },
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::{Bn256, Fr};
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        q: Column<Fixed>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        values: TableColumn,
+        squares: TableColumn,
+    }
+
+    /// Squares a few values, looking each pair up in a table of squares, and
+    /// checks that a third column holds the same values in reverse.
+    #[derive(Clone, Copy)]
+    struct MyCircuit<const LOGUP: bool>;
+
+    impl<const LOGUP: bool> Circuit<Fr> for MyCircuit<LOGUP> {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            if LOGUP {
+                meta.set_lookup_backend(LookupBackend::LogUp);
+            }
+            let q = meta.fixed_column();
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.advice_column();
+            let values = meta.lookup_table_column();
+            let squares = meta.lookup_table_column();
+
+            meta.create_gate("square", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![q * (b - a.clone() * a)]
+            });
+            meta.lookup("square table", |meta| {
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![(a, values), (b, squares)]
+            });
+            meta.shuffle("reversed", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let c = meta.query_advice(c, Rotation::cur());
+                vec![(q.clone() * a, q * c)]
+            });
+
+            MyConfig {
+                q,
+                a,
+                b,
+                c,
+                values,
+                squares,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "squares",
+                |mut table| {
+                    for i in 0..8 {
+                        let (value, square) = (Fr::from(i as u64), Fr::from((i * i) as u64));
+                        table.assign_cell(|| "x", config.values, i, || Value::known(value))?;
+                        table.assign_cell(|| "x^2", config.squares, i, || Value::known(square))?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "values",
+                |mut region| {
+                    for i in 0..4 {
+                        let value = i as u64;
+                        region.assign_fixed(|| "q", config.q, i, || Value::known(Fr::ONE))?;
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            i,
+                            || Value::known(Fr::from(value)),
+                        )?;
+                        region.assign_advice(
+                            || "b",
+                            config.b,
+                            i,
+                            || Value::known(Fr::from(value * value)),
+                        )?;
+                        region.assign_advice(
+                            || "c",
+                            config.c,
+                            i,
+                            || Value::known(Fr::from(3 - value)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn check<const LOGUP: bool>(params: &ParamsKZG<Bn256>) {
+        let circuit = MyCircuit::<LOGUP>;
+        let vk = keygen_vk(params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(params, vk, &circuit).expect("keygen_pk should not fail");
+
+        let prove = |strategy: EvaluationStrategy| {
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof_with_options::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+                params,
+                &pk,
+                &[circuit, circuit],
+                &[&[], &[]],
+                ChaCha20Rng::from_seed([5u8; 32]),
+                &mut transcript,
+                &ProverOptions::new()
+                    .evaluation_strategy(strategy)
+                    .argument_chunk_size(ChunkSize::Rows(7)),
+            )
+            .expect("proof generation should not fail");
+            transcript.finalize()
+        };
+
+        // Only the compiled strategy builds the key's evaluator.
+        assert!(pk.ev.lock().unwrap().is_none());
+        let interpreted = prove(EvaluationStrategy::Interpreted);
+        assert!(pk.ev.lock().unwrap().is_none());
+        let compiled = prove(EvaluationStrategy::Compiled);
+        assert!(pk.ev.lock().unwrap().is_some());
+        assert_eq!(interpreted, compiled);
+
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&interpreted[..]);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(params),
+            &[&[], &[]],
+            &mut transcript,
+        )
+        .expect("proof should verify");
+    }
+
+    let params = test_fixtures::params(5);
+    check::<false>(&params);
+    check::<true>(&params);
 }
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use ff::{FromUniformBytes, WithSmallOrderMulGroup};
+use rand_core::RngCore;
+
+use super::{check_instances, prove_circuits, NoopObserver, ProverOptions};
+use crate::{
+    arithmetic::CpuEngine,
+    multicore::IntoParallelIterator,
+    plonk::{Circuit, ConstraintSystem, Error, ProvingKey},
+    poly::commitment::{CommitmentScheme, Params, Prover},
+    transcript::{EncodedChallenge, TranscriptWrite},
+};
+
+#[cfg(feature = "multicore")]
+use crate::multicore::{IndexedParallelIterator, ParallelIterator};
+
+/// A prover that creates many proofs for the same circuit.
+///
+/// Everything that does not depend on the witness is computed once: the
+/// proving key already holds the fixed and permutation polynomials in every
+/// basis the prover needs, and the circuit is configured when the first proof
+/// is created rather than once per proof.
+///
+/// The batch prover holds no memory beyond the circuit's configuration, but
+/// [`BatchProver::prove_many`] works on as many proofs at a time as there are
+/// threads, each needing as much memory as a call to
+/// [`create_proof`](crate::plonk::create_proof).
+pub struct BatchProver<'params, 'pk, Scheme: CommitmentScheme, ConcreteCircuit>
+where
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+{
+    params: &'params Scheme::ParamsProver,
+    pk: &'pk ProvingKey<Scheme::Curve>,
+    config: Option<ConcreteCircuit::Config>,
+}
+
+impl<'params, 'pk, Scheme: CommitmentScheme, ConcreteCircuit> fmt::Debug
+    for BatchProver<'params, 'pk, Scheme, ConcreteCircuit>
+where
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchProver")
+            .field("configured", &self.config.is_some())
+            .finish()
+    }
+}
+
+impl<'params, 'pk, Scheme: CommitmentScheme, ConcreteCircuit>
+    BatchProver<'params, 'pk, Scheme, ConcreteCircuit>
+where
+    ConcreteCircuit: Circuit<Scheme::Scalar>,
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    Scheme::ParamsProver: Sync,
+{
+    /// Constructs a batch prover for the circuit of `pk`.
+    pub fn new(params: &'params Scheme::ParamsProver, pk: &'pk ProvingKey<Scheme::Curve>) -> Self {
+        BatchProver {
+            params,
+            pk,
+            config: None,
+        }
+    }
+
+    /// Configures the circuit, unless a previous proof already did.
+    fn config(&mut self, _circuit: &ConcreteCircuit) -> &ConcreteCircuit::Config {
+        self.config.get_or_insert_with(|| {
+            let mut meta = ConstraintSystem::default();
+            #[cfg(feature = "circuit-params")]
+            let config = ConcreteCircuit::configure_with_params(&mut meta, _circuit.params());
+            #[cfg(not(feature = "circuit-params"))]
+            let config = ConcreteCircuit::configure(&mut meta);
+            config
+        })
+    }
+
+    /// Creates a proof for `circuits` and their `instances`, as
+    /// [`create_proof`](crate::plonk::create_proof) does.
+    pub fn prove<
+        P: Prover<'params, Scheme>,
+        E: EncodedChallenge<Scheme::Curve>,
+        R: RngCore,
+        T: TranscriptWrite<Scheme::Curve, E>,
+    >(
+        &mut self,
+        transcript: &mut T,
+        instances: &[&[&[Scheme::Scalar]]],
+        circuits: &[ConcreteCircuit],
+        rng: R,
+    ) -> Result<(), Error> {
+        check_instances(self.pk, self.params.n(), circuits.len(), instances)?;
+        let circuit = circuits.first().ok_or(Error::InvalidInstances)?;
+        let (params, pk) = (self.params, self.pk);
+        let config = self.config(circuit);
+        prove_circuits::<Scheme, P, _, _, _, _, _>(
+            params,
+            pk,
+            config,
+            circuits,
+            instances,
+            rng,
+            transcript,
+            &CpuEngine,
+            &mut NoopObserver,
+            &ProverOptions::default(),
+        )
+        .map(|_| ())
+    }
+
+    /// Creates a separate proof for each of `circuits` in parallel, the
+    /// `i`-th one with the instance columns `instances[i]`. The randomness
+    /// and the transcript of the `i`-th proof are obtained from `init(i)`,
+    /// and the transcripts are returned in the same order.
+    pub fn prove_many<
+        P: Prover<'params, Scheme>,
+        E: EncodedChallenge<Scheme::Curve>,
+        R: RngCore + Send,
+        T: TranscriptWrite<Scheme::Curve, E> + Send,
+    >(
+        &mut self,
+        circuits: &[ConcreteCircuit],
+        instances: &[&[&[Scheme::Scalar]]],
+        init: impl FnMut(usize) -> (R, T),
+    ) -> Result<Vec<T>, Error>
+    where
+        ConcreteCircuit: Sync,
+        ConcreteCircuit::Config: Sync,
+    {
+        check_instances(self.pk, self.params.n(), circuits.len(), instances)?;
+        let circuit = match circuits.first() {
+            Some(circuit) => circuit,
+            None => return Ok(vec![]),
+        };
+        let (params, pk) = (self.params, self.pk);
+        let config = &*self.config(circuit);
+
+        let jobs: Vec<_> = (0..circuits.len()).map(init).collect();
+        jobs.into_par_iter()
+            .enumerate()
+            .map(|(i, (rng, mut transcript))| {
+                prove_circuits::<Scheme, P, _, _, _, _, _>(
+                    params,
+                    pk,
+                    config,
+                    std::slice::from_ref(&circuits[i]),
+                    std::slice::from_ref(&instances[i]),
+                    rng,
+                    &mut transcript,
+                    &CpuEngine,
+                    &mut NoopObserver,
+                    &ProverOptions::default(),
+                )?;
+                Ok(transcript)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,172 @@
+use ff::Field;
+
+use super::{lookup, shuffle, Any, Column, Expression, Gate, Selector, VirtualCell};
+
+/// A custom gate of a [`ConstraintSystem`](super::ConstraintSystem), as
+/// returned by [`ConstraintSystem::gates`](super::ConstraintSystem::gates).
+///
+/// This is part of the reflection API, along with [`LookupView`],
+/// [`ShuffleView`] and [`PermutationView`], meant for analysis tools and
+/// documentation generators. The views are read-only and stable: their
+/// accessors keep their signatures and meaning across minor versions, and
+/// new ones may be added. They show the constraint system as configured; in
+/// the constraint system of a key, selectors have been replaced with fixed
+/// columns, which the expressions query instead.
+#[derive(Clone, Copy, Debug)]
+pub struct GateView<'a, F: Field> {
+    index: usize,
+    gate: &'a Gate<F>,
+}
+
+impl<'a, F: Field> GateView<'a, F> {
+    pub(crate) fn new(index: usize, gate: &'a Gate<F>) -> Self {
+        GateView { index, gate }
+    }
+
+    /// Returns the index of the gate, in the order the gates were created.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the gate.
+    pub fn name(&self) -> &'a str {
+        self.gate.name()
+    }
+
+    /// Returns the names of the constraints of the gate, in the order of
+    /// [`Self::polynomials`].
+    pub fn constraint_names(&self) -> impl ExactSizeIterator<Item = &'a str> {
+        let gate = self.gate;
+        (0..gate.polynomials().len()).map(move |i| gate.constraint_name(i))
+    }
+
+    /// Returns the polynomial constraints of the gate.
+    pub fn polynomials(&self) -> &'a [Expression<F>] {
+        self.gate.polynomials()
+    }
+
+    /// Returns the name and the polynomial of each constraint of the gate.
+    pub fn constraints(&self) -> impl ExactSizeIterator<Item = (&'a str, &'a Expression<F>)> {
+        self.constraint_names().zip(self.polynomials().iter())
+    }
+
+    /// Returns the cells the gate queries, as columns and rotations, in the
+    /// order they were first queried. Selectors are listed separately.
+    pub fn queried_cells(&self) -> &'a [VirtualCell] {
+        self.gate.queried_cells()
+    }
+
+    /// Returns the selectors the gate queries.
+    pub fn queried_selectors(&self) -> &'a [Selector] {
+        self.gate.queried_selectors()
+    }
+
+    /// Returns the relative cost of evaluating the gate at a row; see
+    /// [`Gate::cost`].
+    pub fn cost(&self) -> usize {
+        self.gate.cost()
+    }
+}
+
+/// A lookup argument of a [`ConstraintSystem`](super::ConstraintSystem), as
+/// returned by
+/// [`ConstraintSystem::lookups`](super::ConstraintSystem::lookups). See
+/// [`GateView`] for what the reflection API guarantees.
+#[derive(Clone, Copy, Debug)]
+pub struct LookupView<'a, F: Field> {
+    index: usize,
+    argument: &'a lookup::Argument<F>,
+}
+
+impl<'a, F: Field> LookupView<'a, F> {
+    pub(crate) fn new(index: usize, argument: &'a lookup::Argument<F>) -> Self {
+        LookupView { index, argument }
+    }
+
+    /// Returns the index of the lookup, in the order the lookups were
+    /// created.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the lookup.
+    pub fn name(&self) -> &'a str {
+        &self.argument.name
+    }
+
+    /// Returns the name of the logical table of a
+    /// [`TableSet`](crate::circuit::TableSet) the lookup is into, if it was
+    /// created with
+    /// [`ConstraintSystem::lookup_in_set`](super::ConstraintSystem::lookup_in_set).
+    pub fn table(&self) -> Option<&'a str> {
+        self.argument.table.as_deref()
+    }
+
+    /// Returns the expressions looked up, one per table expression.
+    pub fn input_expressions(&self) -> &'a [Expression<F>] {
+        &self.argument.input_expressions
+    }
+
+    /// Returns the expressions of the table.
+    pub fn table_expressions(&self) -> &'a [Expression<F>] {
+        &self.argument.table_expressions
+    }
+}
+
+/// A shuffle argument of a [`ConstraintSystem`](super::ConstraintSystem), as
+/// returned by
+/// [`ConstraintSystem::shuffles`](super::ConstraintSystem::shuffles). See
+/// [`GateView`] for what the reflection API guarantees.
+#[derive(Clone, Copy, Debug)]
+pub struct ShuffleView<'a, F: Field> {
+    index: usize,
+    argument: &'a shuffle::Argument<F>,
+}
+
+impl<'a, F: Field> ShuffleView<'a, F> {
+    pub(crate) fn new(index: usize, argument: &'a shuffle::Argument<F>) -> Self {
+        ShuffleView { index, argument }
+    }
+
+    /// Returns the index of the shuffle, in the order the shuffles were
+    /// created.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the shuffle.
+    pub fn name(&self) -> &'a str {
+        &self.argument.name
+    }
+
+    /// Returns the expressions shuffled, one per shuffle expression.
+    pub fn input_expressions(&self) -> &'a [Expression<F>] {
+        &self.argument.input_expressions
+    }
+
+    /// Returns the expressions the inputs are a shuffle of.
+    pub fn shuffle_expressions(&self) -> &'a [Expression<F>] {
+        &self.argument.shuffle_expressions
+    }
+}
+
+/// The permutation argument of a
+/// [`ConstraintSystem`](super::ConstraintSystem), as returned by
+/// [`ConstraintSystem::permutation`](super::ConstraintSystem::permutation).
+/// See [`GateView`] for what the reflection API guarantees.
+#[derive(Clone, Copy, Debug)]
+pub struct PermutationView<'a> {
+    columns: &'a [Column<Any>],
+}
+
+impl<'a> PermutationView<'a> {
+    pub(crate) fn new(columns: &'a [Column<Any>]) -> Self {
+        PermutationView { columns }
+    }
+
+    /// Returns the columns equality is enabled on, in the order their
+    /// permutation polynomials are committed to.
+    pub fn columns(&self) -> &'a [Column<Any>] {
+        self.columns
+    }
+}
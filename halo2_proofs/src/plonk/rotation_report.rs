@@ -0,0 +1,321 @@
+use std::fmt;
+
+use ff::Field;
+
+use super::{Advice, Any, Column, ConstraintSystem, Expression, MinimumRowsBreakdown};
+
+/// What queries a column, as reported by
+/// [`ConstraintSystem::query_rotation_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuerySource {
+    /// A custom gate.
+    Gate {
+        /// The index of the gate.
+        gate: usize,
+        /// The name of the gate.
+        name: String,
+    },
+    /// The input or table expressions of a lookup.
+    Lookup {
+        /// The index of the lookup.
+        lookup: usize,
+        /// The name of the lookup.
+        name: String,
+    },
+    /// The input or shuffle expressions of a shuffle.
+    Shuffle {
+        /// The index of the shuffle.
+        shuffle: usize,
+        /// The name of the shuffle.
+        name: String,
+    },
+}
+
+impl fmt::Display for QuerySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuerySource::Gate { name, .. } => write!(f, "gate '{}'", name),
+            QuerySource::Lookup { name, .. } => write!(f, "lookup '{}'", name),
+            QuerySource::Shuffle { name, .. } => write!(f, "shuffle '{}'", name),
+        }
+    }
+}
+
+/// The rotations a column is queried at, as reported by
+/// [`ConstraintSystem::query_rotation_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnRotations {
+    /// The column.
+    pub column: Column<Any>,
+    /// The name of the column, if it has one.
+    pub name: Option<String>,
+    /// The smallest rotation the column is queried at.
+    pub min_rotation: i32,
+    /// The largest rotation the column is queried at.
+    pub max_rotation: i32,
+    /// The gates, lookups and shuffles querying the column, with each
+    /// rotation they query it at, in the order they were created.
+    pub queries: Vec<(QuerySource, i32)>,
+}
+
+impl ColumnRotations {
+    /// Returns the rows the queries of the column reach past the row they
+    /// are made from: the largest rotation if it is positive, plus the
+    /// smallest one negated if it is negative. A gate enabled on one of the
+    /// last (or first) of these usable rows reads the unusable rows of the
+    /// circuit, so they cost as many rows of `minimum_rows`.
+    pub fn unusable_rows(&self) -> usize {
+        (i64::from(self.max_rotation.max(0)) - i64::from(self.min_rotation.min(0))) as usize
+    }
+
+    /// Returns the number of rows the queries of the column alone need to
+    /// be distinct, which [`MinimumRowsBreakdown::rotation_span`] is the
+    /// largest of.
+    pub fn minimum_rows(&self) -> usize {
+        self.unusable_rows() + 1
+    }
+
+    /// Returns the sources querying the column at `rotation`.
+    pub fn sources_at(&self, rotation: i32) -> impl Iterator<Item = &QuerySource> {
+        self.queries
+            .iter()
+            .filter(move |(_, queried)| *queried == rotation)
+            .map(|(source, _)| source)
+    }
+
+    fn fmt_column(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.column.column_type() {
+            Any::Advice(_) => write!(f, "advice {}", self.column.index())?,
+            Any::Fixed => write!(f, "fixed {}", self.column.index())?,
+            Any::Instance => write!(f, "instance {}", self.column.index())?,
+        }
+        if let Some(name) = &self.name {
+            write!(f, " ('{}')", name)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ColumnRotations {
+    /// Writes a line for each source of the largest and of the smallest
+    /// rotation of the column, if they are not `Rotation::cur()`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let extremes = [self.max_rotation.max(0), self.min_rotation.min(0)];
+        for rotation in extremes.into_iter().filter(|rotation| *rotation != 0) {
+            let mut sources = self.sources_at(rotation).peekable();
+            let unattributed = sources.peek().is_none();
+            for source in sources.map(Some).chain(unattributed.then_some(None)) {
+                if !first {
+                    writeln!(f)?;
+                }
+                first = false;
+                match source {
+                    Some(source) => write!(f, "{} queries", source)?,
+                    None => write!(
+                        f,
+                        "a query outside of the gates, lookups and shuffles reads"
+                    )?,
+                }
+                write!(f, " Rotation({}) on ", rotation)?;
+                self.fmt_column(f)?;
+                write!(f, ", costing {} unusable rows", rotation.unsigned_abs())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The rotations every column of a [`ConstraintSystem`] is queried at, and
+/// what queries them, as returned by
+/// [`ConstraintSystem::query_rotation_report`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryRotationReport {
+    /// The queried columns, instance columns first, then advice and fixed
+    /// columns, each by index.
+    pub columns: Vec<ColumnRotations>,
+    /// What the minimum number of rows of the circuit is made of.
+    pub minimum_rows: MinimumRowsBreakdown,
+}
+
+impl QueryRotationReport {
+    /// Returns the column costing the most unusable rows, if any does.
+    pub fn costliest(&self) -> Option<&ColumnRotations> {
+        self.columns
+            .iter()
+            .filter(|column| column.unusable_rows() > 0)
+            .max_by_key(|column| column.unusable_rows())
+    }
+
+    /// Returns the rotations of `column`, if it is queried.
+    pub fn column<C: Into<Column<Any>>>(&self, column: C) -> Option<&ColumnRotations> {
+        let column = column.into();
+        self.columns
+            .iter()
+            .find(|rotations| rotations.column == column)
+    }
+}
+
+impl fmt::Display for QueryRotationReport {
+    /// Writes a line for each query at the largest or smallest rotation of
+    /// a column, then how the rotations add up to the minimum number of
+    /// rows.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for column in self
+            .columns
+            .iter()
+            .filter(|column| column.unusable_rows() > 0)
+        {
+            writeln!(f, "{}", column)?;
+        }
+        write!(
+            f,
+            "the rotations from {} to {} span {} rows, of the {} rows the circuit needs",
+            self.minimum_rows.min_rotation,
+            self.minimum_rows.max_rotation,
+            self.minimum_rows.rotation_span(),
+            self.minimum_rows.total()
+        )
+    }
+}
+
+/// Returns the columns and rotations `expression` queries.
+fn queried_cells<F: Field>(expression: &Expression<F>) -> Vec<(Column<Any>, i32)> {
+    expression.evaluate(
+        &|_| vec![],
+        &|_| vec![],
+        &|query| {
+            vec![(
+                Column::new(query.column_index, Any::Fixed),
+                query.rotation.0,
+            )]
+        },
+        &|query| {
+            let advice = Any::Advice(Advice { phase: query.phase });
+            vec![(Column::new(query.column_index, advice), query.rotation.0)]
+        },
+        &|query| {
+            vec![(
+                Column::new(query.column_index, Any::Instance),
+                query.rotation.0,
+            )]
+        },
+        &|_| vec![],
+        &|a| a,
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|a, _| a,
+    )
+}
+
+impl<F: Field> ConstraintSystem<F> {
+    /// Returns, for each column, the smallest and largest rotations it is
+    /// queried at and the gates, lookups and shuffles responsible, along
+    /// with what they add to [`Self::minimum_rows`].
+    ///
+    /// A large rotation, such as `Rotation(63)` in a gate reading a table
+    /// laid out over the following rows, costs as many unusable rows: the
+    /// circuit needs the rows it spans to be distinct, which can make it
+    /// need more rows than its blinding factors do.
+    pub fn query_rotation_report(&self) -> QueryRotationReport {
+        let mut columns: Vec<ColumnRotations> = vec![];
+        let queries = self
+            .instance_queries
+            .iter()
+            .map(|(column, rotation)| (Column::<Any>::from(*column), rotation.0))
+            .chain(
+                self.advice_queries
+                    .iter()
+                    .map(|(column, rotation)| ((*column).into(), rotation.0)),
+            )
+            .chain(
+                self.fixed_queries
+                    .iter()
+                    .map(|(column, rotation)| ((*column).into(), rotation.0)),
+            );
+        for (column, rotation) in queries {
+            match columns
+                .iter_mut()
+                .find(|rotations| rotations.column == column)
+            {
+                Some(rotations) => {
+                    rotations.min_rotation = rotations.min_rotation.min(rotation);
+                    rotations.max_rotation = rotations.max_rotation.max(rotation);
+                }
+                None => columns.push(ColumnRotations {
+                    column,
+                    name: self.column_name(column).map(str::to_string),
+                    min_rotation: rotation,
+                    max_rotation: rotation,
+                    queries: vec![],
+                }),
+            }
+        }
+        let kind = |column: &Column<Any>| match column.column_type() {
+            Any::Instance => 0,
+            Any::Advice(_) => 1,
+            Any::Fixed => 2,
+        };
+        columns.sort_by_key(|rotations| (kind(&rotations.column), rotations.column.index()));
+
+        let gates = self.gates.iter().enumerate().map(|(gate, g)| {
+            let cells = g
+                .queried_cells()
+                .iter()
+                .map(|cell| (cell.column(), cell.rotation().0))
+                .collect::<Vec<_>>();
+            let source = QuerySource::Gate {
+                gate,
+                name: g.name().to_string(),
+            };
+            (source, cells)
+        });
+        let lookups = self.lookups.iter().enumerate().map(|(lookup, argument)| {
+            let cells = argument
+                .input_expressions
+                .iter()
+                .chain(argument.table_expressions.iter())
+                .flat_map(queried_cells)
+                .collect();
+            let source = QuerySource::Lookup {
+                lookup,
+                name: argument.name.clone(),
+            };
+            (source, cells)
+        });
+        let shuffles = self.shuffles.iter().enumerate().map(|(shuffle, argument)| {
+            let cells = argument
+                .input_expressions
+                .iter()
+                .chain(argument.shuffle_expressions.iter())
+                .flat_map(queried_cells)
+                .collect();
+            let source = QuerySource::Shuffle {
+                shuffle,
+                name: argument.name.clone(),
+            };
+            (source, cells)
+        });
+        for (source, cells) in gates.chain(lookups).chain(shuffles) {
+            for (column, rotation) in cells {
+                if let Some(rotations) = columns.iter_mut().find(|r| r.column == column) {
+                    let query = (source.clone(), rotation);
+                    if !rotations.queries.contains(&query) {
+                        rotations.queries.push(query);
+                    }
+                }
+            }
+        }
+
+        QueryRotationReport {
+            columns,
+            minimum_rows: self.minimum_rows_breakdown(),
+        }
+    }
+}
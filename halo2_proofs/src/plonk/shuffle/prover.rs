@@ -4,7 +4,7 @@ use super::super::{
 use super::Argument;
 use crate::plonk::evaluation::evaluate;
 use crate::{
-    arithmetic::{eval_polynomial, parallelize, CurveAffine},
+    arithmetic::{eval_polynomial, parallelize, CurveAffine, Engine},
     poly::{
         commitment::{Blind, Params},
         Coeff, EvaluationDomain, LagrangeCoeff, Polynomial, ProverQuery, Rotation,
@@ -106,6 +106,7 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
         &self,
         pk: &ProvingKey<C>,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         theta: ChallengeTheta<C>,
         gamma: ChallengeGamma<C>,
@@ -164,7 +165,7 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
             // be a boolean (and ideally 1, else soundness is broken)
             .take(params.n() as usize - blinding_factors)
             // Chain random blinding factors.
-            .chain((0..blinding_factors).map(|_| C::Scalar::random(&mut rng)))
+            .chain((0..blinding_factors).map(|_| pk.vk.cs.blinding_scalar(&mut rng)))
             .collect::<Vec<_>>();
         assert_eq!(z.len(), params.n() as usize);
         let z = pk.vk.domain.lagrange_from_vec(z);
@@ -186,9 +187,11 @@ impl<F: WithSmallOrderMulGroup<3>> Argument<F> {
             assert_eq!(z[u], C::Scalar::ONE);
         }
 
-        let product_blind = Blind(C::Scalar::random(rng));
-        let product_commitment = params.commit_lagrange(&z, product_blind).to_affine();
-        let z = pk.vk.domain.lagrange_to_coeff(z);
+        let product_blind = pk.vk.cs.blind(rng);
+        let product_commitment = params
+            .commit_lagrange_with_engine(engine, &z, product_blind)
+            .to_affine();
+        let z = pk.vk.domain.lagrange_to_coeff_with_engine(engine, z);
 
         // Hash product commitment
         transcript.write_point(product_commitment)?;
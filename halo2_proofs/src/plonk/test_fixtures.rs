@@ -0,0 +1,58 @@
+//! Parameters and circuits shared by the unit tests of the proving system.
+
+use ff::Field;
+use halo2curves::bn256::Bn256;
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+
+use super::{Circuit, Column, ConstraintSystem, Error, Fixed};
+use crate::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    poly::kzg::commitment::ParamsKZG,
+};
+
+/// Returns a random number generator with a fixed seed, so that failures can
+/// be reproduced.
+pub(crate) fn rng() -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(0)
+}
+
+/// Returns KZG parameters for circuits with `2^k` rows, generated from
+/// [`rng`].
+pub(crate) fn params(k: u32) -> ParamsKZG<Bn256> {
+    ParamsKZG::setup(k, rng())
+}
+
+/// A circuit with a single fixed column, holding the given value on its first
+/// row.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FixedCircuit(pub(crate) u64);
+
+impl<F: Field> Circuit<F> for FixedCircuit {
+    type Config = Column<Fixed>;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        meta.fixed_column()
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "fixed",
+            |mut region| {
+                region.assign_fixed(|| "a", config, 0, || Value::known(F::from(self.0)))?;
+                Ok(())
+            },
+        )
+    }
+}
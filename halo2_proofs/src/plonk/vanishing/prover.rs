@@ -1,4 +1,4 @@
-use std::{collections::HashMap, iter};
+use std::iter;
 
 use ff::Field;
 use group::Curve;
@@ -7,8 +7,7 @@ use rand_core::{RngCore, SeedableRng};
 
 use super::Argument;
 use crate::{
-    arithmetic::{eval_polynomial, parallelize, CurveAffine},
-    multicore::current_num_threads,
+    arithmetic::{eval_polynomial, parallelize, CurveAffine, Engine},
     plonk::{ChallengeX, Error},
     poly::{
         commitment::{Blind, ParamsProver},
@@ -17,9 +16,14 @@ use crate::{
     transcript::{EncodedChallenge, TranscriptWrite},
 };
 
+/// The number of coefficients of the vanishing argument's random polynomial
+/// drawn from each ChaCha stream.
+const RANDOM_POLY_BLOCK: usize = 1 << 10;
+
 pub(in crate::plonk) struct Committed<C: CurveAffine> {
     random_poly: Polynomial<C::Scalar, Coeff>,
     random_blind: Blind<C::Scalar>,
+    zero_knowledge: bool,
 }
 
 pub(in crate::plonk) struct Constructed<C: CurveAffine> {
@@ -43,38 +47,48 @@ impl<C: CurveAffine> Argument<C> {
         T: TranscriptWrite<C, E>,
     >(
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
+        zero_knowledge: bool,
         mut rng: R,
         transcript: &mut T,
     ) -> Result<Committed<C>, Error> {
-        // Sample a random polynomial of degree n - 1
+        // Sample a random polynomial of degree n - 1, or use the zero
+        // polynomial if h(x_3) need not be hidden
         let n = 1usize << domain.k() as usize;
         let mut rand_vec = vec![C::Scalar::ZERO; n];
+        if !zero_knowledge {
+            return Self::commit_random_poly(
+                params,
+                engine,
+                domain.coeff_from_vec(rand_vec),
+                Blind::default(),
+                false,
+                transcript,
+            );
+        }
 
-        let num_threads = current_num_threads();
-        let chunk_size = n / num_threads;
-        let thread_seeds = (0..)
-            .step_by(chunk_size + 1)
-            .take(n % num_threads)
-            .chain(
-                (chunk_size != 0)
-                    .then(|| ((n % num_threads) * (chunk_size + 1)..).step_by(chunk_size))
-                    .into_iter()
-                    .flatten(),
-            )
-            .take(num_threads)
-            .zip(iter::repeat_with(|| {
-                let mut seed = [0u8; 32];
-                rng.fill_bytes(&mut seed);
-                ChaCha20Rng::from_seed(seed)
-            }))
-            .collect::<HashMap<_, _>>();
-
+        // The coefficients are drawn in fixed-size blocks, each from its own
+        // ChaCha stream, so that they depend only on `rng` and not on how the
+        // work is split among threads.
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let block_rng = |block: usize| {
+            let mut rng = ChaCha20Rng::from_seed(seed);
+            rng.set_stream(block as u64);
+            rng
+        };
         parallelize(&mut rand_vec, |chunk, offset| {
-            let mut rng = thread_seeds[&offset].clone();
-            chunk
-                .iter_mut()
-                .for_each(|v| *v = C::Scalar::random(&mut rng));
+            let mut rng = block_rng(offset / RANDOM_POLY_BLOCK);
+            for _ in 0..offset % RANDOM_POLY_BLOCK {
+                C::Scalar::random(&mut rng);
+            }
+            for (index, v) in (offset..).zip(chunk.iter_mut()) {
+                if index % RANDOM_POLY_BLOCK == 0 {
+                    rng = block_rng(index / RANDOM_POLY_BLOCK);
+                }
+                *v = C::Scalar::random(&mut rng);
+            }
         });
 
         let random_poly: Polynomial<C::Scalar, Coeff> = domain.coeff_from_vec(rand_vec);
@@ -82,13 +96,31 @@ impl<C: CurveAffine> Argument<C> {
         // Sample a random blinding factor
         let random_blind = Blind(C::Scalar::random(rng));
 
-        // Commit
-        let c = params.commit(&random_poly, random_blind).to_affine();
+        Self::commit_random_poly(params, engine, random_poly, random_blind, true, transcript)
+    }
+
+    fn commit_random_poly<
+        'params,
+        P: ParamsProver<'params, C>,
+        E: EncodedChallenge<C>,
+        T: TranscriptWrite<C, E>,
+    >(
+        params: &P,
+        engine: &dyn Engine<C>,
+        random_poly: Polynomial<C::Scalar, Coeff>,
+        random_blind: Blind<C::Scalar>,
+        zero_knowledge: bool,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let c = params
+            .commit_with_engine(engine, &random_poly, random_blind)
+            .to_affine();
         transcript.write_point(c)?;
 
         Ok(Committed {
             random_poly,
             random_blind,
+            zero_knowledge,
         })
     }
 }
@@ -103,6 +135,7 @@ impl<C: CurveAffine> Committed<C> {
     >(
         self,
         params: &P,
+        engine: &dyn Engine<C>,
         domain: &EvaluationDomain<C::Scalar>,
         h_poly: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
         mut rng: R,
@@ -112,7 +145,7 @@ impl<C: CurveAffine> Committed<C> {
         let h_poly = domain.divide_by_vanishing_poly(h_poly);
 
         // Obtain final h(X) polynomial
-        let h_poly = domain.extended_to_coeff(h_poly);
+        let h_poly = domain.extended_to_coeff_with_engine(engine, h_poly);
 
         // Split h(X) up into pieces
         let h_pieces = h_poly
@@ -122,14 +155,20 @@ impl<C: CurveAffine> Committed<C> {
         drop(h_poly);
         let h_blinds: Vec<_> = h_pieces
             .iter()
-            .map(|_| Blind(C::Scalar::random(&mut rng)))
+            .map(|_| {
+                if self.zero_knowledge {
+                    Blind(C::Scalar::random(&mut rng))
+                } else {
+                    Blind::default()
+                }
+            })
             .collect();
 
         // Compute commitments to each h(X) piece
         let h_commitments_projective: Vec<_> = h_pieces
             .iter()
             .zip(h_blinds.iter())
-            .map(|(h_piece, blind)| params.commit(h_piece, *blind))
+            .map(|(h_piece, blind)| params.commit_with_engine(engine, h_piece, *blind))
             .collect();
         let mut h_commitments = vec![C::identity(); h_commitments_projective.len()];
         C::Curve::batch_normalize(&h_commitments_projective, &mut h_commitments);
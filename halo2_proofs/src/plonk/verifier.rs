@@ -1,25 +1,51 @@
 use ff::{Field, FromUniformBytes, WithSmallOrderMulGroup};
 use group::Curve;
-use std::iter;
+use std::{io, iter};
 
 use super::{
-    vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX, ChallengeY, Error,
-    VerifyingKey,
+    listener::{ListenedTranscript, PendingEvents, VerifierListener},
+    vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX, ChallengeY,
+    CommitmentKind, Error, ProofLayout, VerificationFailure, VerifyingKey,
 };
-use crate::arithmetic::compute_inner_product;
+use crate::arithmetic::{compute_inner_product, CurveAffine};
 use crate::poly::commitment::{CommitmentScheme, Verifier};
 use crate::poly::VerificationStrategy;
 use crate::poly::{
     commitment::{Blind, Params},
     VerifierQuery,
 };
-use crate::transcript::{read_n_scalars, EncodedChallenge, TranscriptRead};
+use crate::transcript::{
+    read_n_scalars, EncodedChallenge, ProofReader, Transcript, TranscriptRead, TranscriptReadBuffer,
+};
 
 #[cfg(feature = "batch")]
 mod batch;
 #[cfg(feature = "batch")]
 pub use batch::BatchVerifier;
 
+/// An instance column given to [`verify_proof_with_committed_instances`].
+#[derive(Clone, Copy, Debug)]
+pub enum InstanceColumn<'a, C: CurveAffine> {
+    /// The values of the column, as given to [`verify_proof`].
+    Values(&'a [C::Scalar]),
+    /// The commitment to the column, as the prover computes it: the
+    /// commitment to its values in Lagrange form, zero-padded to the size of
+    /// the domain, with the default blinding factor.
+    Committed(C),
+}
+
+impl<'a, C: CurveAffine> From<&'a [C::Scalar]> for InstanceColumn<'a, C> {
+    fn from(values: &'a [C::Scalar]) -> Self {
+        InstanceColumn::Values(values)
+    }
+}
+
+impl<'a, C: CurveAffine> From<&'a Vec<C::Scalar>> for InstanceColumn<'a, C> {
+    fn from(values: &'a Vec<C::Scalar>) -> Self {
+        InstanceColumn::Values(values)
+    }
+}
+
 /// Returns a boolean indicating whether or not the proof is valid
 pub fn verify_proof<
     'params,
@@ -35,28 +61,393 @@ pub fn verify_proof<
     instances: &[&[&[Scheme::Scalar]]],
     transcript: &mut T,
 ) -> Result<Strategy::Output, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    verify::<Scheme, V, _, _, _, _, _>(
+        params,
+        vk,
+        strategy,
+        &instance_values(instances),
+        transcript,
+        &mut |_, _| {},
+    )
+}
+
+/// Same as [`verify_proof`], reading the proof from `proof` with a new
+/// transcript over a [`ProofReader`], and returning [`Error::TrailingBytes`]
+/// if the proof is valid but does not take up all of `proof`, for example
+/// when a framing bug concatenates two proofs.
+pub fn verify_proof_exact<
+    'params,
+    'proof,
+    Scheme: CommitmentScheme,
+    V: Verifier<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptReadBuffer<ProofReader<'proof>, Scheme::Curve, E>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    strategy: Strategy,
+    instances: &[&[&[Scheme::Scalar]]],
+    proof: &'proof [u8],
+) -> Result<Strategy::Output, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    let (reader, read) = ProofReader::new(proof);
+    let mut transcript = T::init(reader);
+    let output = verify_proof::<Scheme, V, E, T, Strategy>(
+        params,
+        vk,
+        strategy,
+        instances,
+        &mut transcript,
+    )?;
+    match proof.len().checked_sub(read.get()) {
+        Some(0) => Ok(output),
+        Some(trailing) => Err(Error::TrailingBytes(trailing)),
+        None => Err(Error::Transcript(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "more bytes were read than the proof has",
+        ))),
+    }
+}
+
+/// Same as [`verify_proof`], taking the commitments to some of the instance
+/// columns in place of their values, for example when they were committed to
+/// by another component.
+///
+/// The transcript absorbs the commitments to the instance columns of each
+/// proof in column order, after the verifying key, exactly as the prover
+/// writes them, whether they are given or computed from the values. Their
+/// evaluations are read from the proof and opened against the commitments
+/// along with those of the other columns, so a commitment that does not
+/// match the values the proof was created with fails verification.
+///
+/// Committed columns require a verifier that queries the instance columns,
+/// such as [`VerifierIPA`](crate::poly::ipa::multiopen::VerifierIPA); other
+/// verifiers absorb and evaluate the values themselves, and return
+/// [`Error::InvalidInstances`] if any column is committed.
+pub fn verify_proof_with_committed_instances<
+    'params,
+    Scheme: CommitmentScheme,
+    V: Verifier<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptRead<Scheme::Curve, E>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    strategy: Strategy,
+    instances: &[&[InstanceColumn<'_, Scheme::Curve>]],
+    transcript: &mut T,
+) -> Result<Strategy::Output, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    verify::<Scheme, V, _, _, _, _, _>(params, vk, strategy, instances, transcript, &mut |_, _| {})
+}
+
+/// Same as [`verify_proof`], reporting every commitment, challenge and
+/// evaluation read from the transcript to `listener`; see
+/// [`create_proof_listened`](crate::plonk::create_proof_listened).
+pub fn verify_proof_listened<
+    'params,
+    Scheme: CommitmentScheme,
+    V: Verifier<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptRead<Scheme::Curve, E>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+    L: VerifierListener<Scheme::Curve>,
+>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    strategy: Strategy,
+    instances: &[&[&[Scheme::Scalar]]],
+    transcript: &mut T,
+    listener: &mut L,
+) -> Result<Strategy::Output, Error>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    let pending = PendingEvents::new();
+    verify::<Scheme, V, _, _, _, _, _>(
+        params,
+        vk,
+        strategy,
+        &instance_values(instances),
+        &mut ListenedTranscript::new(transcript, &pending),
+        &mut |kind, index| pending.report_to_verifier(kind, index, listener),
+    )
+}
+
+/// Same as [`verify_proof`], describing why a proof is rejected.
+///
+/// A point or scalar that cannot be read is located within the proof by
+/// its component, as laid out by [`ProofLayout::parse`]. Past that, the
+/// gates and the permutation, lookup and shuffle arguments are checked all
+/// at once: the verifier derives the evaluation of the quotient polynomial
+/// from the other evaluations, and opens it along with every other query in
+/// the multiopen argument. A proof that breaks any of them fails that final
+/// check, as does one whose evaluations do not match its commitments, and
+/// both are reported as [`VerificationFailure::Opening`].
+///
+/// The reason tells the prover which part of its proof was rejected.
+/// Deployments that verify proofs from untrusted provers may not want to
+/// give them that information, and should only report to them whether
+/// [`verify_proof`] succeeded.
+pub fn verify_proof_detailed<
+    'params,
+    Scheme: CommitmentScheme,
+    V: Verifier<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptRead<Scheme::Curve, E>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    strategy: Strategy,
+    instances: &[&[&[Scheme::Scalar]]],
+    transcript: &mut T,
+) -> Result<Strategy::Output, VerificationFailure>
+where
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    let mut transcript = CountingTranscript {
+        inner: transcript,
+        read: 0,
+        error: None,
+    };
+    let result = verify::<Scheme, V, _, _, _, _, _>(
+        params,
+        vk,
+        strategy,
+        &instance_values(instances),
+        &mut transcript,
+        &mut |_, _| {},
+    );
+    match (result, transcript.error) {
+        (Ok(output), _) => Ok(output),
+        // The multiopen argument reports its read errors as opening errors
+        (Err(Error::Transcript(_) | Error::Opening), Some(error)) => {
+            let layout = ProofLayout::parse::<Scheme, V>(vk, instances.len());
+            let (component, index, position) = layout.locate(transcript.read);
+            Err(VerificationFailure::Read {
+                component,
+                index,
+                position,
+                error,
+            })
+        }
+        // The strategies report a failed final check as an unsatisfied
+        // constraint system
+        (Err(Error::Opening | Error::ConstraintSystemFailure), None) => {
+            Err(VerificationFailure::Opening)
+        }
+        (Err(error), _) => Err(VerificationFailure::Input(error)),
+    }
+}
+
+/// Counts the points and scalars read from a transcript, and keeps the error
+/// of the first read that fails.
+struct CountingTranscript<'a, T> {
+    inner: &'a mut T,
+    read: usize,
+    error: Option<io::Error>,
+}
+
+impl<T> CountingTranscript<'_, T> {
+    fn count<R>(&mut self, result: io::Result<R>) -> io::Result<R> {
+        match result {
+            Ok(value) => {
+                self.read += 1;
+                Ok(value)
+            }
+            Err(error) => {
+                let copy = io::Error::new(error.kind(), error.to_string());
+                self.error.get_or_insert(error);
+                Err(copy)
+            }
+        }
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: Transcript<C, E>> Transcript<C, E>
+    for CountingTranscript<'_, T>
+{
+    fn squeeze_challenge(&mut self) -> E {
+        self.inner.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.inner.common_point(point)
+    }
+
+    fn common_points(&mut self, points: &[C]) -> io::Result<()> {
+        self.inner.common_points(points)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.inner.common_scalar(scalar)
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> TranscriptRead<C, E>
+    for CountingTranscript<'_, T>
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let result = self.inner.read_point();
+        self.count(result)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let result = self.inner.read_scalar();
+        self.count(result)
+    }
+}
+
+/// Wraps the values of the instance columns of each proof.
+fn instance_values<'a, C: CurveAffine>(
+    instances: &[&[&'a [C::Scalar]]],
+) -> Vec<Vec<InstanceColumn<'a, C>>> {
+    instances
+        .iter()
+        .map(|instance| {
+            instance
+                .iter()
+                .map(|&values| InstanceColumn::from(values))
+                .collect()
+        })
+        .collect()
+}
+
+/// Evaluates the instance columns of each proof at `x`, for each instance
+/// query of `vk` in order, as the verifiers that do not read these
+/// evaluations from the proof compute them.
+///
+/// The columns are read from `instances` as they are, without being padded
+/// to the size of the domain, so that callers that commit to the instance
+/// columns themselves can check the evaluations a proof claims for them.
+///
+/// # Panics
+///
+/// Panics if a proof has fewer instance columns than `vk` queries.
+pub fn instance_evaluations<C: CurveAffine>(
+    vk: &VerifyingKey<C>,
+    x: C::Scalar,
+    instances: &[&[&[C::Scalar]]],
+) -> Vec<Vec<C::Scalar>> {
+    let xn = x.pow([1 << vk.domain.k()]);
+    let (min_rotation, max_rotation) =
+        vk.cs
+            .instance_queries
+            .iter()
+            .fold((0, 0), |(min, max), (_, rotation)| {
+                if rotation.0 < min {
+                    (rotation.0, max)
+                } else if rotation.0 > max {
+                    (min, rotation.0)
+                } else {
+                    (min, max)
+                }
+            });
+    let max_instance_len = instances
+        .iter()
+        .flat_map(|instance| instance.iter().map(|instance| instance.len()))
+        .max_by(Ord::cmp)
+        .unwrap_or_default();
+    let l_i_s = &vk.domain.l_i_range(
+        x,
+        xn,
+        -max_rotation..max_instance_len as i32 + min_rotation.abs(),
+    );
+    instances
+        .iter()
+        .map(|instances| {
+            vk.cs
+                .instance_queries
+                .iter()
+                .map(|(column, rotation)| {
+                    let instances = instances[column.index()];
+                    let offset = (max_rotation - rotation.0) as usize;
+                    compute_inner_product(instances, &l_i_s[offset..offset + instances.len()])
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Verifies a proof, calling `stage_done` each time all the commitments of a
+/// [`CommitmentKind`] have been read for a proof, in the same order as the
+/// prover reports them to its observer.
+fn verify<
+    'params,
+    'a,
+    Scheme: CommitmentScheme,
+    V: Verifier<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptRead<Scheme::Curve, E>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+    D: FnMut(CommitmentKind, usize),
+    I: AsRef<[InstanceColumn<'a, Scheme::Curve>]>,
+>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    strategy: Strategy,
+    instances: &[I],
+    transcript: &mut T,
+    stage_done: &mut D,
+) -> Result<Strategy::Output, Error>
 where
     Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
 {
     // Check that instances matches the expected number of instance columns
     for instances in instances.iter() {
-        if instances.len() != vk.cs.num_instance_columns {
+        if instances.as_ref().len() != vk.cs.num_instance_columns {
             return Err(Error::InvalidInstances);
         }
     }
 
+    // Without instance queries, the values of every column are needed
+    let values = if V::QUERY_INSTANCE {
+        vec![]
+    } else {
+        instances
+            .iter()
+            .map(|instance| {
+                instance
+                    .as_ref()
+                    .iter()
+                    .map(|column| match column {
+                        InstanceColumn::Values(values) => Ok(*values),
+                        InstanceColumn::Committed(_) => Err(Error::InvalidInstances),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
     let instance_commitments = if V::QUERY_INSTANCE {
         instances
             .iter()
             .map(|instance| {
                 instance
+                    .as_ref()
                     .iter()
-                    .map(|instance| {
-                        if instance.len() > params.n() as usize - (vk.cs.blinding_factors() + 1) {
+                    .map(|column| {
+                        let instance = match column {
+                            InstanceColumn::Values(values) => values,
+                            InstanceColumn::Committed(commitment) => return Ok(*commitment),
+                        };
+                        if instance.len() > vk.cs.usable_rows(params.k()).end {
                             return Err(Error::InstanceTooLarge);
                         }
-                        let mut poly = instance.to_vec();
-                        poly.resize(params.n() as usize, Scheme::Scalar::ZERO);
+                        // The only copy of the column, zero-padded to the
+                        // size of the domain
+                        let mut poly = vec![Scheme::Scalar::ZERO; params.n() as usize];
+                        poly[..instance.len()].copy_from_slice(instance);
                         let poly = vk.domain.lagrange_from_vec(poly);
 
                         Ok(params.commit_lagrange(&poly, Blind::default()).to_affine())
@@ -81,7 +472,7 @@ where
             }
         }
     } else {
-        for instance in instances.iter() {
+        for instance in values.iter() {
             for instance in instance.iter() {
                 for value in instance.iter() {
                     transcript.common_scalar(*value)?;
@@ -97,7 +488,9 @@ where
         let mut challenges = vec![Scheme::Scalar::ZERO; vk.cs.num_challenges];
 
         for current_phase in vk.cs.phases() {
-            for advice_commitments in advice_commitments.iter_mut() {
+            let num_columns = vk.cs.num_advice_columns_in_phase(current_phase.0);
+            for (index, advice_commitments) in advice_commitments.iter_mut().enumerate() {
+                let mut read = transcript.read_points(num_columns)?.into_iter();
                 for (phase, commitment) in vk
                     .cs
                     .advice_column_phase
@@ -105,16 +498,29 @@ where
                     .zip(advice_commitments.iter_mut())
                 {
                     if current_phase == *phase {
-                        *commitment = transcript.read_point()?;
+                        *commitment = read.next().unwrap();
                     }
                 }
+                stage_done(
+                    CommitmentKind::Advice {
+                        phase: current_phase.0,
+                    },
+                    index,
+                );
             }
-            for (phase, challenge) in vk.cs.challenge_phase.iter().zip(challenges.iter_mut()) {
-                if current_phase == *phase {
+            for (index, (phase, challenge)) in vk
+                .cs
+                .challenge_phase
+                .iter()
+                .zip(challenges.iter_mut())
+                .enumerate()
+            {
+                if current_phase == *phase && vk.cs.is_challenge_squeezed(index) {
                     *challenge = *transcript.squeeze_challenge_scalar::<()>();
                 }
             }
         }
+        vk.cs.compute_challenge_powers(&mut challenges);
 
         (advice_commitments, challenges)
     };
@@ -122,14 +528,30 @@ where
     // Sample theta challenge for keeping lookup columns linearly independent
     let theta: ChallengeTheta<_> = transcript.squeeze_challenge_scalar();
 
+    // Only one of these is non-empty, depending on the lookup backend.
+    let (lookup_arguments, logup_arguments) = vk.cs.lookups_by_backend();
+
     let lookups_permuted = (0..num_proofs)
-        .map(|_| -> Result<Vec<_>, _> {
+        .map(|index| -> Result<Vec<_>, Error> {
             // Hash each lookup permuted commitment
-            vk.cs
-                .lookups
+            let lookups = lookup_arguments
                 .iter()
                 .map(|argument| argument.read_permuted_commitments(transcript))
-                .collect::<Result<Vec<_>, _>>()
+                .collect::<Result<Vec<_>, _>>()?;
+            stage_done(CommitmentKind::LookupPermuted, index);
+            Ok(lookups)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let logups_multiplicities = (0..num_proofs)
+        .map(|index| -> Result<Vec<_>, Error> {
+            // Hash each LogUp multiplicity commitment
+            let logups = logup_arguments
+                .iter()
+                .map(|argument| argument.read_multiplicity_commitment(transcript))
+                .collect::<Result<Vec<_>, _>>()?;
+            stage_done(CommitmentKind::LookupPermuted, index);
+            Ok(logups)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
@@ -140,40 +562,64 @@ where
     let gamma: ChallengeGamma<_> = transcript.squeeze_challenge_scalar();
 
     let permutations_committed = (0..num_proofs)
-        .map(|_| {
+        .map(|index| -> Result<_, Error> {
             // Hash each permutation product commitment
-            vk.cs.permutation.read_product_commitments(vk, transcript)
+            let permutation = vk.cs.permutation.read_product_commitments(vk, transcript)?;
+            stage_done(CommitmentKind::PermutationProduct, index);
+            Ok(permutation)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
     let lookups_committed = lookups_permuted
         .into_iter()
-        .map(|lookups| {
+        .enumerate()
+        .map(|(index, lookups)| -> Result<Vec<_>, Error> {
             // Hash each lookup product commitment
-            lookups
+            let lookups = lookups
                 .into_iter()
                 .map(|lookup| lookup.read_product_commitment(transcript))
-                .collect::<Result<Vec<_>, _>>()
+                .collect::<Result<Vec<_>, _>>()?;
+            stage_done(CommitmentKind::LookupProduct, index);
+            Ok(lookups)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let logups_committed = logups_multiplicities
+        .into_iter()
+        .enumerate()
+        .map(|(index, logups)| -> Result<Vec<_>, Error> {
+            // Hash each LogUp running sum commitment
+            let logups = logups
+                .into_iter()
+                .map(|logup| logup.read_running_sum_commitment(transcript))
+                .collect::<Result<Vec<_>, _>>()?;
+            stage_done(CommitmentKind::LookupProduct, index);
+            Ok(logups)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
     let shuffles_committed = (0..num_proofs)
-        .map(|_| -> Result<Vec<_>, _> {
+        .map(|index| -> Result<Vec<_>, Error> {
             // Hash each shuffle product commitment
-            vk.cs
+            let shuffles = vk
+                .cs
                 .shuffles
                 .iter()
                 .map(|argument| argument.read_product_commitment(transcript))
-                .collect::<Result<Vec<_>, _>>()
+                .collect::<Result<Vec<_>, _>>()?;
+            stage_done(CommitmentKind::ShuffleProduct, index);
+            Ok(shuffles)
         })
         .collect::<Result<Vec<_>, _>>()?;
 
     let vanishing = vanishing::Argument::read_commitments_before_y(transcript)?;
+    stage_done(CommitmentKind::VanishingRandom, 0);
 
     // Sample y challenge, which keeps the gates linearly independent.
     let y: ChallengeY<_> = transcript.squeeze_challenge_scalar();
 
     let vanishing = vanishing.read_commitments_after_y(vk, transcript)?;
+    stage_done(CommitmentKind::Quotient, 0);
 
     // Sample x challenge, which is used to ensure the circuit is
     // satisfied with high probability.
@@ -185,44 +631,8 @@ where
             })
             .collect::<Result<Vec<_>, _>>()?
     } else {
-        let xn = x.pow([params.n()]);
-        let (min_rotation, max_rotation) =
-            vk.cs
-                .instance_queries
-                .iter()
-                .fold((0, 0), |(min, max), (_, rotation)| {
-                    if rotation.0 < min {
-                        (rotation.0, max)
-                    } else if rotation.0 > max {
-                        (min, rotation.0)
-                    } else {
-                        (min, max)
-                    }
-                });
-        let max_instance_len = instances
-            .iter()
-            .flat_map(|instance| instance.iter().map(|instance| instance.len()))
-            .max_by(Ord::cmp)
-            .unwrap_or_default();
-        let l_i_s = &vk.domain.l_i_range(
-            *x,
-            xn,
-            -max_rotation..max_instance_len as i32 + min_rotation.abs(),
-        );
-        instances
-            .iter()
-            .map(|instances| {
-                vk.cs
-                    .instance_queries
-                    .iter()
-                    .map(|(column, rotation)| {
-                        let instances = instances[column.index()];
-                        let offset = (max_rotation - rotation.0) as usize;
-                        compute_inner_product(instances, &l_i_s[offset..offset + instances.len()])
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>()
+        let values: Vec<_> = values.iter().map(|instance| &instance[..]).collect();
+        instance_evaluations(vk, *x, &values)
     };
 
     let advice_evals = (0..num_proofs)
@@ -250,6 +660,16 @@ where
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let logups_evaluated = logups_committed
+        .into_iter()
+        .map(|logups| -> Result<Vec<_>, _> {
+            logups
+                .into_iter()
+                .map(|logup| logup.evaluate(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     let shuffles_evaluated = shuffles_committed
         .into_iter()
         .map(|shuffles| -> Result<Vec<_>, _> {
@@ -283,9 +703,10 @@ where
             .zip(instance_evals.iter())
             .zip(permutations_evaluated.iter())
             .zip(lookups_evaluated.iter())
+            .zip(logups_evaluated.iter())
             .zip(shuffles_evaluated.iter())
             .flat_map(
-                |((((advice_evals, instance_evals), permutation), lookups), shuffles)| {
+                |(((((advice_evals, instance_evals), permutation), lookups), logups), shuffles)| {
                     let challenges = &challenges;
                     let fixed_evals = &fixed_evals;
                     std::iter::empty()
@@ -322,7 +743,7 @@ where
                             gamma,
                             x,
                         ))
-                        .chain(lookups.iter().zip(vk.cs.lookups.iter()).flat_map(
+                        .chain(lookups.iter().zip(lookup_arguments.iter()).flat_map(
                             move |(p, argument)| {
                                 p.expressions(
                                     l_0,
@@ -339,6 +760,22 @@ where
                                 )
                             },
                         ))
+                        .chain(logups.iter().zip(logup_arguments.iter()).flat_map(
+                            move |(p, argument)| {
+                                p.expressions(
+                                    l_0,
+                                    l_last,
+                                    l_blind,
+                                    argument,
+                                    theta,
+                                    beta,
+                                    advice_evals,
+                                    fixed_evals,
+                                    instance_evals,
+                                    challenges,
+                                )
+                            },
+                        ))
                         .chain(shuffles.iter().zip(vk.cs.shuffles.iter()).flat_map(
                             move |(p, argument)| {
                                 p.expressions(
@@ -368,18 +805,22 @@ where
         .zip(advice_evals.iter())
         .zip(permutations_evaluated.iter())
         .zip(lookups_evaluated.iter())
+        .zip(logups_evaluated.iter())
         .zip(shuffles_evaluated.iter())
         .flat_map(
             |(
                 (
                     (
                         (
-                            ((instance_commitments, instance_evals), advice_commitments),
-                            advice_evals,
+                            (
+                                ((instance_commitments, instance_evals), advice_commitments),
+                                advice_evals,
+                            ),
+                            permutation,
                         ),
-                        permutation,
+                        lookups,
                     ),
-                    lookups,
+                    logups,
                 ),
                 shuffles,
             )| {
@@ -409,6 +850,7 @@ where
                     ))
                     .chain(permutation.queries(vk, x))
                     .chain(lookups.iter().flat_map(move |p| p.queries(vk, x)))
+                    .chain(logups.iter().flat_map(move |p| p.queries(vk, x)))
                     .chain(shuffles.iter().flat_map(move |p| p.queries(vk, x)))
             },
         )
@@ -432,9 +874,11 @@ where
     // polynomial commitments open to the correct values.
 
     let verifier = V::new(params);
-    strategy.process(|msm| {
+    let result = strategy.process(|msm| {
         verifier
             .verify_proof(transcript, queries, msm)
             .map_err(|_| Error::Opening)
-    })
+    });
+    stage_done(CommitmentKind::Multiopen, 0);
+    result
 }
@@ -4,19 +4,26 @@
 
 use crate::arithmetic::parallelize;
 use crate::helpers::SerdePrimeField;
+use crate::multicore::{IntoParallelIterator, ParallelIterator};
 use crate::plonk::Assigned;
 use crate::SerdeFormat;
 
+#[cfg(feature = "disk-offload")]
+use group::ff::PrimeField;
 use group::ff::{BatchInvert, Field};
 use std::fmt::Debug;
 use std::io;
 use std::marker::PhantomData;
 use std::ops::{Add, Deref, DerefMut, Index, IndexMut, Mul, RangeFrom, RangeFull, Sub};
+#[cfg(feature = "disk-offload")]
+use std::path::Path;
 
 /// Generic commitment scheme structures
 pub mod commitment;
 mod domain;
 mod query;
+mod scratch;
+mod storage;
 mod strategy;
 
 /// Inner product argument commitment scheme
@@ -32,6 +39,9 @@ pub use domain::*;
 pub use query::{ProverQuery, VerifierQuery};
 pub use strategy::{Guard, VerificationStrategy};
 
+pub(crate) use scratch::ScratchPool;
+use storage::Storage;
+
 /// This is an error that could occur during proving or circuit synthesis.
 // TODO: these errors need to be cleaned up
 #[derive(Debug)]
@@ -65,7 +75,7 @@ impl Basis for ExtendedLagrangeCoeff {}
 /// basis.
 #[derive(Clone, Debug)]
 pub struct Polynomial<F, B> {
-    values: Vec<F>,
+    values: Storage<F>,
     _marker: PhantomData<B>,
 }
 
@@ -73,13 +83,13 @@ impl<F, B> Index<usize> for Polynomial<F, B> {
     type Output = F;
 
     fn index(&self, index: usize) -> &F {
-        self.values.index(index)
+        &self.values[index]
     }
 }
 
 impl<F, B> IndexMut<usize> for Polynomial<F, B> {
     fn index_mut(&mut self, index: usize) -> &mut F {
-        self.values.index_mut(index)
+        &mut self.values[index]
     }
 }
 
@@ -87,13 +97,13 @@ impl<F, B> Index<RangeFrom<usize>> for Polynomial<F, B> {
     type Output = [F];
 
     fn index(&self, index: RangeFrom<usize>) -> &[F] {
-        self.values.index(index)
+        &self.values[index]
     }
 }
 
 impl<F, B> IndexMut<RangeFrom<usize>> for Polynomial<F, B> {
     fn index_mut(&mut self, index: RangeFrom<usize>) -> &mut [F] {
-        self.values.index_mut(index)
+        &mut self.values[index]
     }
 }
 
@@ -101,13 +111,13 @@ impl<F, B> Index<RangeFull> for Polynomial<F, B> {
     type Output = [F];
 
     fn index(&self, index: RangeFull) -> &[F] {
-        self.values.index(index)
+        &self.values[index]
     }
 }
 
 impl<F, B> IndexMut<RangeFull> for Polynomial<F, B> {
     fn index_mut(&mut self, index: RangeFull) -> &mut [F] {
-        self.values.index_mut(index)
+        &mut self.values[index]
     }
 }
 
@@ -145,6 +155,20 @@ impl<F, B> Polynomial<F, B> {
     }
 }
 
+#[cfg(feature = "disk-offload")]
+impl<F: PrimeField, B> Polynomial<F, B> {
+    /// Moves the values of this polynomial into a new file in `dir`, mapped
+    /// into memory. The file is removed when the polynomial is dropped.
+    pub(crate) fn offload(&mut self, dir: &Path) -> io::Result<()> {
+        self.values.offload(dir)
+    }
+
+    /// Returns whether the values of this polynomial are stored on disk.
+    pub(crate) fn is_on_disk(&self) -> bool {
+        self.values.is_on_disk()
+    }
+}
+
 impl<F: SerdePrimeField, B> Polynomial<F, B> {
     /// Reads polynomial from buffer using `SerdePrimeField::read`.  
     pub(crate) fn read<R: io::Read>(reader: &mut R, format: SerdeFormat) -> io::Result<Self> {
@@ -156,7 +180,7 @@ impl<F: SerdePrimeField, B> Polynomial<F, B> {
             .map(|_| F::read(reader, format))
             .collect::<io::Result<Vec<_>>>()
             .map(|values| Self {
-                values,
+                values: values.into(),
                 _marker: PhantomData,
             })
     }
@@ -175,49 +199,41 @@ impl<F: SerdePrimeField, B> Polynomial<F, B> {
     }
 }
 
+/// Evaluates the `Assigned` values of each polynomial.
+///
+/// The polynomials are evaluated in parallel, each with its own batch
+/// inversion, and each one is freed as soon as it has been evaluated, so that
+/// the `Assigned` and evaluated forms of all the polynomials never coexist.
 pub(crate) fn batch_invert_assigned<F: Field>(
     assigned: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
 ) -> Vec<Polynomial<F, LagrangeCoeff>> {
-    let mut assigned_denominators: Vec<_> = assigned
-        .iter()
-        .map(|f| {
-            f.iter()
-                .map(|value| value.denominator())
-                .collect::<Vec<_>>()
-        })
-        .collect();
-
-    assigned_denominators
-        .iter_mut()
-        .flat_map(|f| {
-            f.iter_mut()
-                // If the denominator is trivial, we can skip it, reducing the
-                // size of the batch inversion.
-                .filter_map(|d| d.as_mut())
-        })
-        .batch_invert();
-
-    assigned
-        .iter()
-        .zip(assigned_denominators)
-        .map(|(poly, inv_denoms)| poly.invert(inv_denoms.into_iter().map(|d| d.unwrap_or(F::ONE))))
-        .collect()
+    assigned.into_par_iter().map(|poly| poly.invert()).collect()
 }
 
 impl<F: Field> Polynomial<Assigned<F>, LagrangeCoeff> {
-    pub(crate) fn invert(
-        &self,
-        inv_denoms: impl Iterator<Item = F> + ExactSizeIterator,
-    ) -> Polynomial<F, LagrangeCoeff> {
-        assert_eq!(inv_denoms.len(), self.values.len());
+    /// Evaluates the `Assigned` values of this polynomial, batch-inverting
+    /// their non-trivial denominators.
+    pub(crate) fn invert(self) -> Polynomial<F, LagrangeCoeff> {
+        // If the denominator is trivial, we can skip it, reducing the size of
+        // the batch inversion.
+        let mut inv_denoms: Vec<_> = self
+            .values
+            .iter()
+            .filter_map(|value| value.denominator())
+            .collect();
+        inv_denoms.iter_mut().batch_invert();
+
+        let mut inv_denoms = inv_denoms.into_iter();
         Polynomial {
             values: self
                 .values
                 .iter()
-                .zip(inv_denoms)
-                .map(|(a, inv_den)| a.numerator() * inv_den)
+                .map(|value| match value.denominator() {
+                    Some(_) => value.numerator() * inv_denoms.next().unwrap(),
+                    None => value.numerator(),
+                })
                 .collect(),
-            _marker: self._marker,
+            _marker: PhantomData,
         }
     }
 }
@@ -272,7 +288,7 @@ impl<F: Field, B: Basis> Mul<F> for Polynomial<F, B> {
     fn mul(mut self, rhs: F) -> Polynomial<F, B> {
         if rhs == F::ZERO {
             return Polynomial {
-                values: vec![F::ZERO; self.len()],
+                values: vec![F::ZERO; self.len()].into(),
                 _marker: PhantomData,
             };
         }
@@ -3,6 +3,7 @@ use super::{
     strategy::Guard,
     Coeff, LagrangeCoeff, Polynomial,
 };
+use crate::arithmetic::{CpuEngine, Engine};
 use crate::poly::Error;
 use crate::transcript::{EncodedChallenge, TranscriptRead, TranscriptWrite};
 use ff::Field;
@@ -51,8 +52,26 @@ pub trait Params<'params, C: CurveAffine>: Sized + Clone {
     fn n(&self) -> u64;
 
     /// Downsize `Params` with smaller `k`.
+    ///
+    /// The bases in the monomial basis are truncated to the first `2^k`, and
+    /// the bases in the Lagrange basis, which depend on the size of the
+    /// domain, are recomputed from them by an inverse FFT. The resulting
+    /// parameters are the same as parameters set up at `k` directly, from the
+    /// same secret for KZG.
     fn downsize(&mut self, k: u32);
 
+    /// Returns these `Params` downsized to a smaller `k`, as
+    /// [`Params::downsize`] would leave them, without changing them.
+    ///
+    /// This lets one large set of parameters serve circuits of any smaller
+    /// size. The default implementation clones all of the parameters first;
+    /// the implementations of this crate copy only the bases they keep.
+    fn downsized(&self, k: u32) -> Self {
+        let mut params = self.clone();
+        params.downsize(k);
+        params
+    }
+
     /// Generates an empty multiscalar multiplication struct using the
     /// appropriate params.
     fn empty_msm(&'params self) -> Self::MSM;
@@ -64,6 +83,17 @@ pub trait Params<'params, C: CurveAffine>: Sized + Clone {
         &self,
         poly: &Polynomial<C::ScalarExt, LagrangeCoeff>,
         r: Blind<C::ScalarExt>,
+    ) -> C::CurveExt {
+        self.commit_lagrange_with_engine(&CpuEngine, poly, r)
+    }
+
+    /// Same as [`Self::commit_lagrange`], performing the multiexponentiation
+    /// with `engine`.
+    fn commit_lagrange_with_engine(
+        &self,
+        engine: &dyn Engine<C>,
+        poly: &Polynomial<C::ScalarExt, LagrangeCoeff>,
+        r: Blind<C::ScalarExt>,
     ) -> C::CurveExt;
 
     /// Writes params to a buffer.
@@ -84,8 +114,22 @@ pub trait ParamsProver<'params, C: CurveAffine>: Params<'params, C> {
     /// This computes a commitment to a polynomial described by the provided
     /// slice of coefficients. The commitment may be blinded by the blinding
     /// factor `r`.
-    fn commit(&self, poly: &Polynomial<C::ScalarExt, Coeff>, r: Blind<C::ScalarExt>)
-        -> C::CurveExt;
+    fn commit(
+        &self,
+        poly: &Polynomial<C::ScalarExt, Coeff>,
+        r: Blind<C::ScalarExt>,
+    ) -> C::CurveExt {
+        self.commit_with_engine(&CpuEngine, poly, r)
+    }
+
+    /// Same as [`Self::commit`], performing the multiexponentiation with
+    /// `engine`.
+    fn commit_with_engine(
+        &self,
+        engine: &dyn Engine<C>,
+        poly: &Polynomial<C::ScalarExt, Coeff>,
+        r: Blind<C::ScalarExt>,
+    ) -> C::CurveExt;
 
     /// Getter for g generators
     fn get_g(&self) -> &[C];
@@ -2,11 +2,11 @@
 //! domain that is of a suitable size for the application.
 
 use crate::{
-    arithmetic::{best_fft, parallelize},
+    arithmetic::{best_fft, parallelize, CurveAffine, Engine},
     plonk::Assigned,
 };
 
-use super::{Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial, Rotation};
+use super::{Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial, Rotation, ScratchPool};
 use ff::WithSmallOrderMulGroup;
 use group::ff::{BatchInvert, Field};
 
@@ -37,21 +37,39 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// This constructs a new evaluation domain object based on the provided
     /// values $j, k$.
     pub fn new(j: u32, k: u32) -> Self {
-        // quotient_poly_degree * params.n - 1 is the degree of the quotient polynomial
-        let quotient_poly_degree = (j - 1) as u64;
-
-        // n = 2^k
-        let n = 1u64 << k;
-
         // We need to work within an extended domain, not params.k but params.k + i
         // for some integer i such that 2^(params.k + i) is sufficiently large to
         // describe the quotient polynomial.
         let mut extended_k = k;
-        while (1 << extended_k) < (n * quotient_poly_degree) {
+        while (1u64 << extended_k) < ((1u64 << k) * (j - 1) as u64) {
             extended_k += 1;
         }
 
-        // ensure extended_k <= S
+        Self::with_extended_k(j, k, extended_k)
+    }
+
+    /// Same as [`Self::new`], with an extended domain of size
+    /// $2^{extended\_k}$ rather than the smallest one that fits a quotient
+    /// polynomial of degree $(j - 1) 2^k$.
+    ///
+    /// The quotient polynomial is still split into $j - 1$ pieces, so a
+    /// smaller extended domain is only correct if the quotient polynomial
+    /// has a lower degree; see
+    /// [`ConstraintSystem::minimum_extended_k`](crate::plonk::ConstraintSystem::minimum_extended_k).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `extended_k` is below `k`, or above the two-adicity of the
+    /// field.
+    pub fn with_extended_k(j: u32, k: u32, extended_k: u32) -> Self {
+        // quotient_poly_degree * params.n - 1 is the degree of the quotient polynomial
+        let quotient_poly_degree = (j - 1) as u64;
+
+        // n = 2^k
+        let n = 1u64 << k;
+
+        // ensure k <= extended_k <= S
+        assert!(k <= extended_k);
         assert!(extended_k <= F::S);
 
         let mut extended_omega = F::ROOT_OF_UNITY;
@@ -151,7 +169,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
         assert_eq!(values.len(), self.n as usize);
 
         Polynomial {
-            values,
+            values: values.into(),
             _marker: PhantomData,
         }
     }
@@ -163,7 +181,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
         assert_eq!(values.len(), self.n as usize);
 
         Polynomial {
-            values,
+            values: values.into(),
             _marker: PhantomData,
         }
     }
@@ -171,7 +189,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// Returns an empty (zero) polynomial in the coefficient basis
     pub fn empty_coeff(&self) -> Polynomial<F, Coeff> {
         Polynomial {
-            values: vec![F::ZERO; self.n as usize],
+            values: vec![F::ZERO; self.n as usize].into(),
             _marker: PhantomData,
         }
     }
@@ -179,7 +197,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// Returns an empty (zero) polynomial in the Lagrange coefficient basis
     pub fn empty_lagrange(&self) -> Polynomial<F, LagrangeCoeff> {
         Polynomial {
-            values: vec![F::ZERO; self.n as usize],
+            values: vec![F::ZERO; self.n as usize].into(),
             _marker: PhantomData,
         }
     }
@@ -188,7 +206,22 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// deferred inversions.
     pub(crate) fn empty_lagrange_assigned(&self) -> Polynomial<Assigned<F>, LagrangeCoeff> {
         Polynomial {
-            values: vec![F::ZERO.into(); self.n as usize],
+            values: vec![F::ZERO.into(); self.n as usize].into(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Obtains a polynomial in Lagrange form with deferred inversions when given
+    /// a vector of Lagrange coefficients of size `n`; panics if the provided
+    /// vector is the wrong length.
+    pub(crate) fn lagrange_assigned_from_vec(
+        &self,
+        values: Vec<Assigned<F>>,
+    ) -> Polynomial<Assigned<F>, LagrangeCoeff> {
+        assert_eq!(values.len(), self.n as usize);
+
+        Polynomial {
+            values: values.into(),
             _marker: PhantomData,
         }
     }
@@ -196,7 +229,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// Returns a constant polynomial in the Lagrange coefficient basis
     pub fn constant_lagrange(&self, scalar: F) -> Polynomial<F, LagrangeCoeff> {
         Polynomial {
-            values: vec![scalar; self.n as usize],
+            values: vec![scalar; self.n as usize].into(),
             _marker: PhantomData,
         }
     }
@@ -205,7 +238,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// basis
     pub fn empty_extended(&self) -> Polynomial<F, ExtendedLagrangeCoeff> {
         Polynomial {
-            values: vec![F::ZERO; self.extended_len()],
+            values: vec![F::ZERO; self.extended_len()].into(),
             _marker: PhantomData,
         }
     }
@@ -214,7 +247,7 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// basis
     pub fn constant_extended(&self, scalar: F) -> Polynomial<F, ExtendedLagrangeCoeff> {
         Polynomial {
-            values: vec![scalar; self.extended_len()],
+            values: vec![scalar; self.extended_len()].into(),
             _marker: PhantomData,
         }
     }
@@ -223,11 +256,68 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     ///
     /// This function will panic if the provided vector is not the correct
     /// length.
-    pub fn lagrange_to_coeff(&self, mut a: Polynomial<F, LagrangeCoeff>) -> Polynomial<F, Coeff> {
+    pub fn lagrange_to_coeff(&self, a: Polynomial<F, LagrangeCoeff>) -> Polynomial<F, Coeff> {
+        self.lagrange_to_coeff_with(a, best_fft)
+    }
+
+    /// Same as [`Self::lagrange_to_coeff`], performing the FFT with `engine`.
+    pub fn lagrange_to_coeff_with_engine<C: CurveAffine<ScalarExt = F>>(
+        &self,
+        engine: &dyn Engine<C>,
+        a: Polynomial<F, LagrangeCoeff>,
+    ) -> Polynomial<F, Coeff> {
+        self.lagrange_to_coeff_with(a, |a, omega, log_n| engine.fft(a, omega, log_n))
+    }
+
+    /// Same as [`Self::lagrange_to_coeff_with_engine`] for several
+    /// polynomials, whose FFTs are performed together with
+    /// [`Engine::batch_fft`].
+    pub fn batch_lagrange_to_coeff_with_engine<C: CurveAffine<ScalarExt = F>>(
+        &self,
+        engine: &dyn Engine<C>,
+        mut a: Vec<Polynomial<F, LagrangeCoeff>>,
+    ) -> Vec<Polynomial<F, Coeff>> {
+        let mut values: Vec<&mut [F]> = a
+            .iter_mut()
+            .map(|a| {
+                assert_eq!(a.values.len(), 1 << self.k);
+                &mut a.values[..]
+            })
+            .collect();
+        engine.batch_fft(&mut values, self.omega_inv, self.k);
+        let divisor = self.ifft_divisor;
+        for values in values {
+            parallelize(values, |a, _| {
+                for a in a {
+                    // Finish iFFT
+                    *a *= &divisor;
+                }
+            });
+        }
+
+        a.into_iter()
+            .map(|a| Polynomial {
+                values: a.values,
+                _marker: PhantomData,
+            })
+            .collect()
+    }
+
+    fn lagrange_to_coeff_with(
+        &self,
+        mut a: Polynomial<F, LagrangeCoeff>,
+        fft: impl FnOnce(&mut [F], F, u32),
+    ) -> Polynomial<F, Coeff> {
         assert_eq!(a.values.len(), 1 << self.k);
 
         // Perform inverse FFT to obtain the polynomial in coefficient form
-        Self::ifft(&mut a.values, self.omega_inv, self.k, self.ifft_divisor);
+        Self::ifft(
+            &mut a.values,
+            self.omega_inv,
+            self.k,
+            self.ifft_divisor,
+            fft,
+        );
 
         Polynomial {
             values: a.values,
@@ -238,14 +328,31 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// This takes us from an n-length coefficient vector into a coset of the extended
     /// evaluation domain, rotating by `rotation` if desired.
     pub fn coeff_to_extended(
+        &self,
+        a: Polynomial<F, Coeff>,
+    ) -> Polynomial<F, ExtendedLagrangeCoeff> {
+        self.coeff_to_extended_with(a, best_fft)
+    }
+
+    /// Same as [`Self::coeff_to_extended`], performing the FFT with `engine`.
+    pub fn coeff_to_extended_with_engine<C: CurveAffine<ScalarExt = F>>(
+        &self,
+        engine: &dyn Engine<C>,
+        a: Polynomial<F, Coeff>,
+    ) -> Polynomial<F, ExtendedLagrangeCoeff> {
+        self.coeff_to_extended_with(a, |a, omega, log_n| engine.fft(a, omega, log_n))
+    }
+
+    fn coeff_to_extended_with(
         &self,
         mut a: Polynomial<F, Coeff>,
+        fft: impl FnOnce(&mut [F], F, u32),
     ) -> Polynomial<F, ExtendedLagrangeCoeff> {
         assert_eq!(a.values.len(), 1 << self.k);
 
         self.distribute_powers_zeta(&mut a.values, true);
         a.values.resize(self.extended_len(), F::ZERO);
-        best_fft(&mut a.values, self.extended_omega, self.extended_k);
+        fft(&mut a.values, self.extended_omega, self.extended_k);
 
         Polynomial {
             values: a.values,
@@ -253,6 +360,34 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
         }
     }
 
+    /// Same as [`Self::coeff_to_extended_with_engine`], without consuming `a`:
+    /// the extended polynomial is written into a buffer taken from `scratch`,
+    /// which can be given back once the caller is done with it.
+    pub(crate) fn coeff_to_extended_in<C: CurveAffine<ScalarExt = F>>(
+        &self,
+        engine: &dyn Engine<C>,
+        a: &Polynomial<F, Coeff>,
+        scratch: &ScratchPool<F>,
+    ) -> Polynomial<F, ExtendedLagrangeCoeff> {
+        assert_eq!(a.values.len(), 1 << self.k);
+
+        let mut values = scratch.take(self.extended_len());
+        let (head, tail) = values.split_at_mut(a.values.len());
+        head.copy_from_slice(&a.values);
+        parallelize(tail, |tail, _| {
+            for value in tail {
+                *value = F::ZERO;
+            }
+        });
+        self.distribute_powers_zeta(head, true);
+        engine.fft(&mut values, self.extended_omega, self.extended_k);
+
+        Polynomial {
+            values: values.into(),
+            _marker: PhantomData,
+        }
+    }
+
     /// Rotate the extended domain polynomial over the original domain.
     pub fn rotate_extended(
         &self,
@@ -278,7 +413,24 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
     /// This function will panic if the provided vector is not the correct
     /// length.
     // TODO/FIXME: caller should be responsible for truncating
-    pub fn extended_to_coeff(&self, mut a: Polynomial<F, ExtendedLagrangeCoeff>) -> Vec<F> {
+    pub fn extended_to_coeff(&self, a: Polynomial<F, ExtendedLagrangeCoeff>) -> Vec<F> {
+        self.extended_to_coeff_with(a, best_fft)
+    }
+
+    /// Same as [`Self::extended_to_coeff`], performing the FFT with `engine`.
+    pub fn extended_to_coeff_with_engine<C: CurveAffine<ScalarExt = F>>(
+        &self,
+        engine: &dyn Engine<C>,
+        a: Polynomial<F, ExtendedLagrangeCoeff>,
+    ) -> Vec<F> {
+        self.extended_to_coeff_with(a, |a, omega, log_n| engine.fft(a, omega, log_n))
+    }
+
+    fn extended_to_coeff_with(
+        &self,
+        mut a: Polynomial<F, ExtendedLagrangeCoeff>,
+        fft: impl FnOnce(&mut [F], F, u32),
+    ) -> Vec<F> {
         assert_eq!(a.values.len(), self.extended_len());
 
         // Inverse FFT
@@ -287,19 +439,22 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
             self.extended_omega_inv,
             self.extended_k,
             self.extended_ifft_divisor,
+            fft,
         );
 
         // Distribute powers to move from coset; opposite from the
         // transformation we performed earlier.
         self.distribute_powers_zeta(&mut a.values, false);
 
-        // Truncate it to match the size of the quotient polynomial; the
+        // Resize it to match the size of the quotient polynomial; the
         // evaluation domain might be slightly larger than necessary because
-        // it always lies on a power-of-two boundary.
+        // it always lies on a power-of-two boundary, or smaller if the
+        // quotient polynomial has a lower degree than its number of pieces
+        // allows.
         a.values
-            .truncate((&self.n * self.quotient_poly_degree) as usize);
+            .resize((self.n * self.quotient_poly_degree) as usize, F::ZERO);
 
-        a.values
+        a.values.into_vec()
     }
 
     /// This divides the polynomial (in the extended domain) by the vanishing
@@ -350,8 +505,8 @@ impl<F: WithSmallOrderMulGroup<3>> EvaluationDomain<F> {
         });
     }
 
-    fn ifft(a: &mut [F], omega_inv: F, log_n: u32, divisor: F) {
-        best_fft(a, omega_inv, log_n);
+    fn ifft(a: &mut [F], omega_inv: F, log_n: u32, divisor: F, fft: impl FnOnce(&mut [F], F, u32)) {
+        fft(a, omega_inv, log_n);
         parallelize(a, |a, _| {
             for a in a {
                 // Finish iFFT
@@ -526,6 +681,38 @@ fn test_rotate() {
     );
 }
 
+#[test]
+fn test_batch_lagrange_to_coeff() {
+    use rand_core::OsRng;
+
+    use crate::arithmetic::CpuEngine;
+    use halo2curves::pasta::{pallas::Scalar, EpAffine};
+
+    let domain = EvaluationDomain::<Scalar>::new(1, 6);
+    // Fewer and more polynomials than threads take different paths.
+    for count in [1, 2 * crate::multicore::current_num_threads() + 1] {
+        let polys: Vec<_> = (0..count)
+            .map(|_| {
+                let mut poly = domain.empty_lagrange();
+                for value in poly.iter_mut() {
+                    *value = Scalar::random(OsRng);
+                }
+                poly
+            })
+            .collect();
+        let expected: Vec<_> = polys
+            .iter()
+            .map(|poly| domain.lagrange_to_coeff(poly.clone()))
+            .collect();
+        let engine: &dyn Engine<EpAffine> = &CpuEngine;
+        let polys = domain.batch_lagrange_to_coeff_with_engine(engine, polys);
+        assert_eq!(polys.len(), count);
+        for (poly, expected) in polys.iter().zip(expected.iter()) {
+            assert_eq!(poly[..], expected[..]);
+        }
+    }
+}
+
 #[test]
 fn test_l_i() {
     use rand_core::OsRng;
@@ -3,7 +3,7 @@
 //!
 //! [halo]: https://eprint.iacr.org/2019/1021
 
-use crate::arithmetic::{best_multiexp, g_to_lagrange, parallelize, CurveAffine, CurveExt};
+use crate::arithmetic::{g_to_lagrange, parallelize, CurveAffine, CurveExt, Engine};
 use crate::helpers::CurveRead;
 use crate::poly::commitment::{Blind, CommitmentScheme, Params, ParamsProver, ParamsVerifier};
 use crate::poly::ipa::msm::MSMIPA;
@@ -78,6 +78,25 @@ impl<'params, C: CurveAffine> Params<'params, C> for ParamsIPA<C> {
         self.g_lagrange = g_to_lagrange(self.g.iter().map(|g| g.to_curve()).collect(), k);
     }
 
+    fn downsized(&self, k: u32) -> Self {
+        assert!(k <= self.k);
+        if k == self.k {
+            return self.clone();
+        }
+
+        let n = 1 << k;
+        let g = self.g[..n as usize].to_vec();
+        let g_lagrange = g_to_lagrange(g.iter().map(|g| g.to_curve()).collect(), k);
+        Self {
+            k,
+            n,
+            g,
+            g_lagrange,
+            w: self.w,
+            u: self.u,
+        }
+    }
+
     fn empty_msm(&'params self) -> MSMIPA<C> {
         MSMIPA::new(self)
     }
@@ -85,8 +104,9 @@ impl<'params, C: CurveAffine> Params<'params, C> for ParamsIPA<C> {
     /// This commits to a polynomial using its evaluations over the $2^k$ size
     /// evaluation domain. The commitment will be blinded by the blinding factor
     /// `r`.
-    fn commit_lagrange(
+    fn commit_lagrange_with_engine(
         &self,
+        engine: &dyn Engine<C>,
         poly: &Polynomial<C::Scalar, LagrangeCoeff>,
         r: Blind<C::Scalar>,
     ) -> C::Curve {
@@ -99,7 +119,7 @@ impl<'params, C: CurveAffine> Params<'params, C> for ParamsIPA<C> {
         tmp_bases.extend(self.g_lagrange.iter());
         tmp_bases.push(self.w);
 
-        best_multiexp::<C>(&tmp_scalars, &tmp_bases)
+        engine.best_multiexp(&tmp_scalars, &tmp_bases)
     }
 
     /// Writes params to a buffer.
@@ -209,7 +229,12 @@ impl<'params, C: CurveAffine> ParamsProver<'params, C> for ParamsIPA<C> {
     /// This computes a commitment to a polynomial described by the provided
     /// slice of coefficients. The commitment will be blinded by the blinding
     /// factor `r`.
-    fn commit(&self, poly: &Polynomial<C::Scalar, Coeff>, r: Blind<C::Scalar>) -> C::Curve {
+    fn commit_with_engine(
+        &self,
+        engine: &dyn Engine<C>,
+        poly: &Polynomial<C::Scalar, Coeff>,
+        r: Blind<C::Scalar>,
+    ) -> C::Curve {
         let mut tmp_scalars = Vec::with_capacity(poly.len() + 1);
         let mut tmp_bases = Vec::with_capacity(poly.len() + 1);
 
@@ -219,7 +244,7 @@ impl<'params, C: CurveAffine> ParamsProver<'params, C> for ParamsIPA<C> {
         tmp_bases.extend(self.g.iter());
         tmp_bases.push(self.w);
 
-        best_multiexp::<C>(&tmp_scalars, &tmp_bases)
+        engine.best_multiexp(&tmp_scalars, &tmp_bases)
     }
 
     fn get_g(&self) -> &[C] {
@@ -75,12 +75,12 @@ impl<'params, C: CurveAffine> Prover<'params, IPACommitmentScheme<C>> for Prover
             .fold(None, |q_prime_poly, (points, poly)| {
                 let mut poly = points
                     .iter()
-                    .fold(poly.clone().unwrap().values, |poly, point| {
+                    .fold(poly.clone().unwrap().values.into_vec(), |poly, point| {
                         kate_division(&poly, *point)
                     });
                 poly.resize(self.params.n as usize, C::Scalar::ZERO);
                 let poly = Polynomial {
-                    values: poly,
+                    values: poly.into(),
                     _marker: PhantomData,
                 };
 
@@ -1,4 +1,4 @@
-use crate::arithmetic::{best_multiexp, g_to_lagrange, parallelize};
+use crate::arithmetic::{g_to_lagrange, parallelize, Engine as ArithmeticEngine};
 use crate::helpers::SerdeCurveAffine;
 use crate::poly::commitment::{Blind, CommitmentScheme, Params, ParamsProver, ParamsVerifier};
 use crate::poly::{Coeff, LagrangeCoeff, Polynomial};
@@ -298,12 +298,32 @@ where
         self.g_lagrange = g_to_lagrange(self.g.iter().map(|g| g.to_curve()).collect(), k);
     }
 
+    fn downsized(&self, k: u32) -> Self {
+        assert!(k <= self.k);
+        if k == self.k {
+            return self.clone();
+        }
+
+        let n = 1 << k;
+        let g = self.g[..n as usize].to_vec();
+        let g_lagrange = g_to_lagrange(g.iter().map(|g| g.to_curve()).collect(), k);
+        Self {
+            k,
+            n,
+            g,
+            g_lagrange,
+            g2: self.g2,
+            s_g2: self.s_g2,
+        }
+    }
+
     fn empty_msm(&'params self) -> MSMKZG<E> {
         MSMKZG::new()
     }
 
-    fn commit_lagrange(
+    fn commit_lagrange_with_engine(
         &self,
+        engine: &dyn ArithmeticEngine<E::G1Affine>,
         poly: &Polynomial<E::Scalar, LagrangeCoeff>,
         _: Blind<E::Scalar>,
     ) -> E::G1 {
@@ -312,7 +332,7 @@ where
         let bases = &self.g_lagrange;
         let size = scalars.len();
         assert!(bases.len() >= size);
-        best_multiexp(&scalars, &bases[0..size])
+        engine.best_multiexp(&scalars, &bases[0..size])
     }
 
     /// Writes params to a buffer.
@@ -350,13 +370,18 @@ where
         Self::setup(k, OsRng)
     }
 
-    fn commit(&self, poly: &Polynomial<E::Scalar, Coeff>, _: Blind<E::Scalar>) -> E::G1 {
+    fn commit_with_engine(
+        &self,
+        engine: &dyn ArithmeticEngine<E::G1Affine>,
+        poly: &Polynomial<E::Scalar, Coeff>,
+        _: Blind<E::Scalar>,
+    ) -> E::G1 {
         let mut scalars = Vec::with_capacity(poly.len());
         scalars.extend(poly.iter());
         let bases = &self.g;
         let size = scalars.len();
         assert!(bases.len() >= size);
-        best_multiexp(&scalars, &bases[0..size])
+        engine.best_multiexp(&scalars, &bases[0..size])
     }
 
     fn get_g(&self) -> &[E::G1Affine] {
@@ -418,4 +443,28 @@ mod test {
         assert_eq!(params0.g2, params1.g2);
         assert_eq!(params0.s_g2, params1.s_g2);
     }
+
+    #[test]
+    fn test_downsized() {
+        use crate::halo2curves::bn256::Bn256;
+        use rand_chacha::ChaCha20Rng;
+        use rand_core::SeedableRng;
+
+        let params = ParamsKZG::<Bn256>::setup(8, ChaCha20Rng::seed_from_u64(1));
+        for k in [1, 4, 8] {
+            let fresh = ParamsKZG::<Bn256>::setup(k, ChaCha20Rng::seed_from_u64(1));
+            let downsized = params.downsized(k);
+            assert_eq!(downsized.k, fresh.k);
+            assert_eq!(downsized.n, fresh.n);
+            assert_eq!(downsized.g, fresh.g);
+            assert_eq!(downsized.g_lagrange, fresh.g_lagrange);
+            assert_eq!(downsized.g2, fresh.g2);
+            assert_eq!(downsized.s_g2, fresh.s_g2);
+
+            let mut in_place = params.clone();
+            in_place.downsize(k);
+            assert_eq!(in_place.g, fresh.g);
+            assert_eq!(in_place.g_lagrange, fresh.g_lagrange);
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
 use super::commitment::ParamsKZG;
@@ -5,7 +7,7 @@ use crate::{
     arithmetic::{best_multiexp, parallelize},
     poly::commitment::MSM,
 };
-use group::{Curve, Group};
+use group::{Curve, Group, GroupEncoding};
 use halo2curves::pairing::{Engine, MillerLoopResult, MultiMillerLoop};
 
 /// A multiscalar multiplication in the polynomial commitment scheme
@@ -121,6 +123,9 @@ pub struct DualMSM<'a, E: Engine> {
     pub(crate) params: &'a ParamsKZG<E>,
     pub(crate) left: MSMKZG<E>,
     pub(crate) right: MSMKZG<E>,
+    /// The position of the term of each point added with `add_commitment`
+    /// among those of `right`, keyed by its encoding.
+    commitments: HashMap<Vec<u8>, usize>,
 }
 
 impl<'a, E: MultiMillerLoop + Debug> DualMSM<'a, E> {
@@ -130,6 +135,22 @@ impl<'a, E: MultiMillerLoop + Debug> DualMSM<'a, E> {
             params,
             left: MSMKZG::new(),
             right: MSMKZG::new(),
+            commitments: HashMap::new(),
+        }
+    }
+
+    /// Adds `scalar` times `commitment` to the right MSM. A commitment added
+    /// more than once this way keeps a single term.
+    pub(crate) fn add_commitment(&mut self, scalar: E::Scalar, commitment: &E::G1Affine) {
+        match self
+            .commitments
+            .entry(commitment.to_bytes().as_ref().to_vec())
+        {
+            Entry::Occupied(entry) => self.right.scalars[*entry.get()] += scalar,
+            Entry::Vacant(entry) => {
+                entry.insert(self.right.scalars.len());
+                self.right.append_term(scalar, (*commitment).into());
+            }
         }
     }
 
@@ -166,3 +187,93 @@ impl<'a, E: MultiMillerLoop + Debug> DualMSM<'a, E> {
         )
     }
 }
+
+/// The two MSMs of the final pairing check of KZG proofs, left unchecked by
+/// [`DeferredStrategy`](super::strategy::DeferredStrategy) so that the check
+/// can be combined with that of other proofs, or done in a circuit.
+///
+/// The proofs are valid if `e(L, [s]G2) = e(R, G2)`, where `L` is the sum of
+/// the [`left`](Self::left) terms, and `R` the sum of the
+/// [`right`](Self::right) terms and of the [`generator`](Self::generator)
+/// scalar times the first point of the parameters.
+#[derive(Clone, Debug)]
+pub struct AccumulatedMSM<E: Engine> {
+    left: Vec<(E::Scalar, E::G1Affine)>,
+    right: Vec<(E::Scalar, E::G1Affine)>,
+    generator: E::Scalar,
+}
+
+impl<E: MultiMillerLoop + Debug> AccumulatedMSM<E> {
+    /// Collects the terms of a [`DualMSM`], separating those of the first
+    /// point of the parameters from the others.
+    pub fn new(msm: &DualMSM<'_, E>) -> Self {
+        use ff::Field;
+        use group::prime::PrimeCurveAffine;
+
+        let affine = |msm: &MSMKZG<E>| {
+            let mut bases = vec![E::G1Affine::identity(); msm.bases.len()];
+            E::G1::batch_normalize(&msm.bases, &mut bases);
+            msm.scalars.iter().copied().zip(bases).collect::<Vec<_>>()
+        };
+        let generator_point = msm.params.g[0];
+        let mut generator = E::Scalar::ZERO;
+        let mut right = vec![];
+        for (scalar, base) in affine(&msm.right) {
+            if base == generator_point {
+                generator += scalar;
+            } else {
+                right.push((scalar, base));
+            }
+        }
+        AccumulatedMSM {
+            left: affine(&msm.left),
+            right,
+            generator,
+        }
+    }
+
+    /// Returns the terms of the MSM paired with `[s]G2`.
+    pub fn left(&self) -> &[(E::Scalar, E::G1Affine)] {
+        &self.left
+    }
+
+    /// Returns the terms of the MSM paired with `G2`, other than that of the
+    /// first point of the parameters.
+    pub fn right(&self) -> &[(E::Scalar, E::G1Affine)] {
+        &self.right
+    }
+
+    /// Returns the scalar of the first point of the parameters in the MSM
+    /// paired with `G2`.
+    pub fn generator(&self) -> E::Scalar {
+        self.generator
+    }
+
+    /// Adds the terms of `other`, multiplied by `challenge`. The result
+    /// checks if both accumulators check, and, for a random `challenge`,
+    /// fails to check with overwhelming probability otherwise.
+    pub fn merge(&mut self, other: &Self, challenge: E::Scalar) {
+        let scaled = |terms: &[(E::Scalar, E::G1Affine)]| {
+            terms
+                .iter()
+                .map(|(scalar, base)| (*scalar * challenge, *base))
+                .collect::<Vec<_>>()
+        };
+        self.left.extend(scaled(&other.left));
+        self.right.extend(scaled(&other.right));
+        self.generator += other.generator * challenge;
+    }
+
+    /// Performs the pairing check with the given parameters.
+    pub fn check(&self, params: &ParamsKZG<E>) -> bool {
+        let mut msm = DualMSM::new(params);
+        for (scalar, base) in self.left.iter() {
+            msm.left.append_term(*scalar, (*base).into());
+        }
+        for (scalar, base) in self.right.iter() {
+            msm.right.append_term(*scalar, (*base).into());
+        }
+        msm.right.append_term(self.generator, params.g[0].into());
+        msm.check()
+    }
+}
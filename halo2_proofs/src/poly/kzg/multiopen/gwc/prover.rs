@@ -5,7 +5,7 @@ use crate::poly::commitment::ParamsProver;
 use crate::poly::commitment::Prover;
 use crate::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
 use crate::poly::query::ProverQuery;
-use crate::poly::{commitment::Blind, Polynomial};
+use crate::poly::{commitment::Blind, Polynomial, ScratchPool};
 use crate::transcript::{EncodedChallenge, TranscriptWrite};
 
 use ff::PrimeField;
@@ -54,10 +54,13 @@ where
     {
         let v: ChallengeV<_> = transcript.squeeze_challenge_scalar();
         let commitment_data = construct_intermediate_sets(queries);
+        // Buffers of the domain size, for the scaled copies of the polynomials
+        // opened at each point.
+        let scratch = ScratchPool::new(true);
 
         for commitment_at_a_point in commitment_data.iter() {
             let z = commitment_at_a_point.point;
-            let (poly_batch, eval_batch) = commitment_at_a_point
+            let (mut poly_batch, eval_batch) = commitment_at_a_point
                 .queries
                 .iter()
                 .zip(powers(*v))
@@ -67,16 +70,21 @@ where
                     let poly = query.get_commitment().poly;
                     let eval = query.get_eval();
 
-                    (poly.clone() * power_of_v, eval * power_of_v)
+                    (scratch.copy_poly(poly) * power_of_v, eval * power_of_v)
+                })
+                .reduce(|(poly_acc, eval_acc), (poly, eval)| {
+                    let poly_acc = poly_acc + &poly;
+                    scratch.give_poly(poly);
+                    (poly_acc, eval_acc + eval)
                 })
-                .reduce(|(poly_acc, eval_acc), (poly, eval)| (poly_acc + &poly, eval_acc + eval))
                 .unwrap();
 
-            let poly_batch = &poly_batch - eval_batch;
+            poly_batch[0] -= eval_batch;
             let witness_poly = Polynomial {
-                values: kate_division(&poly_batch.values, z),
+                values: kate_division(&poly_batch.values[..], z).into(),
                 _marker: PhantomData,
             };
+            scratch.give_poly(poly_batch);
             let w = self
                 .params
                 .commit(&witness_poly, Blind::default())
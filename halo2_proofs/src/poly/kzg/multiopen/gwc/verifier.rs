@@ -18,6 +18,21 @@ use halo2curves::pairing::{Engine, MultiMillerLoop};
 
 #[derive(Debug)]
 /// Concrete KZG verifier with GWC variant
+///
+/// Each opened commitment is added to the right MSM of the accumulator as a
+/// single term, into which its openings at other points and in other proofs
+/// are folded. Checking proofs for the same verifying key together with an
+/// [`AccumulatorStrategy`](crate::poly::kzg::strategy::AccumulatorStrategy)
+/// thus only costs one term per commitment of the key, however many proofs
+/// there are. Commitments that differ, like those of the proofs themselves,
+/// keep a term each, as when proofs are folded independently.
+///
+/// This does not affect soundness: the strategy multiplies what is
+/// accumulated by a random scalar sampled after the proofs are read, and
+/// adding the scalars of two terms with the same point does not change the
+/// value of the MSM. If any of the proofs fails its pairing check, the
+/// combined check thus fails except with probability about the number of
+/// proofs over the size of the scalar field.
 pub struct VerifierGWC<'params, E: Engine> {
     params: &'params ParamsKZG<E>,
 }
@@ -62,7 +77,6 @@ where
 
         let u: ChallengeU<_> = transcript.squeeze_challenge_scalar();
 
-        let mut commitment_multi = MSMKZG::<E>::new();
         let mut eval_multi = E::Scalar::ZERO;
 
         let mut witness = MSMKZG::<E>::new();
@@ -74,37 +88,23 @@ where
             assert!(!commitment_at_a_point.queries.is_empty());
             let z = commitment_at_a_point.point;
 
-            let (mut commitment_batch, eval_batch) = commitment_at_a_point
-                .queries
-                .iter()
-                .zip(powers(*v))
-                .map(|(query, power_of_v)| {
-                    assert_eq!(query.get_point(), z);
-
-                    let commitment = match query.get_commitment() {
-                        CommitmentReference::Commitment(c) => {
-                            let mut msm = MSMKZG::<E>::new();
-                            msm.append_term(power_of_v, (*c).into());
-                            msm
-                        }
-                        CommitmentReference::MSM(msm) => {
-                            let mut msm = msm.clone();
-                            msm.scale(power_of_v);
-                            msm
-                        }
-                    };
-                    let eval = power_of_v * query.get_eval();
-
-                    (commitment, eval)
-                })
-                .reduce(|(mut commitment_acc, eval_acc), (commitment, eval)| {
-                    commitment_acc.add_msm(&commitment);
-                    (commitment_acc, eval_acc + eval)
-                })
-                .unwrap();
-
-            commitment_batch.scale(power_of_u);
-            commitment_multi.add_msm(&commitment_batch);
+            let mut eval_batch = E::Scalar::ZERO;
+            for (query, power_of_v) in commitment_at_a_point.queries.iter().zip(powers(*v)) {
+                assert_eq!(query.get_point(), z);
+
+                let scalar = power_of_u * power_of_v;
+                match query.get_commitment() {
+                    // Commitments share a term of the accumulator across the
+                    // points they are opened at and the proofs it holds.
+                    CommitmentReference::Commitment(c) => msm_accumulator.add_commitment(scalar, c),
+                    CommitmentReference::MSM(msm) => {
+                        let mut msm = msm.clone();
+                        msm.scale(scalar);
+                        msm_accumulator.right.add_msm(&msm);
+                    }
+                }
+                eval_batch += power_of_v * query.get_eval();
+            }
             eval_multi += power_of_u * eval_batch;
 
             witness_with_aux.append_term(power_of_u * z, wi.into());
@@ -114,10 +114,137 @@ where
         msm_accumulator.left.add_msm(&witness);
 
         msm_accumulator.right.add_msm(&witness_with_aux);
-        msm_accumulator.right.add_msm(&commitment_multi);
-        let g0: E::G1 = self.params.g[0].into();
-        msm_accumulator.right.append_term(eval_multi, -g0);
+        msm_accumulator.add_commitment(-eval_multi, &self.params.g[0]);
 
         Ok(Self::Guard::new(msm_accumulator))
     }
 }
+
+#[test]
+fn test_gwc_batch_shares_vk_commitments() {
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{
+            create_proof, keygen_pk, keygen_vk, test_fixtures, verify_proof, Advice, Circuit,
+            Column, ConstraintSystem, Error as PlonkError, Instance,
+        },
+        poly::{
+            commitment::ParamsProver,
+            kzg::{multiopen::ProverGWC, strategy::AccumulatorStrategy},
+            Rotation,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+        },
+    };
+    use halo2curves::bn256::Fr;
+
+    #[derive(Clone, Copy)]
+    struct MyConfig {
+        a: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    /// Exposes the square of a witness.
+    #[derive(Clone, Copy)]
+    struct MyCircuit(Value<Fr>);
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self(Value::unknown())
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q = meta.selector();
+            let a = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(a);
+            meta.enable_equality(instance);
+            meta.create_gate("square", |meta| {
+                let q = meta.query_selector(q);
+                let cur = meta.query_advice(a, Rotation::cur());
+                let next = meta.query_advice(a, Rotation::next());
+                vec![q * (cur.clone() * cur - next)]
+            });
+            MyConfig { a, instance }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), PlonkError> {
+            let square = layouter.assign_region(
+                || "square",
+                |mut region| {
+                    region.assign_advice(|| "a", config.a, 0, || self.0)?;
+                    region.assign_advice(|| "a^2", config.a, 1, || self.0.map(|a| a * a))
+                },
+            )?;
+            layouter.constrain_instance(square.cell(), config.instance, 0)
+        }
+    }
+
+    let params = test_fixtures::params(4);
+    let vk = keygen_vk(&params, &MyCircuit(Value::unknown())).expect("keygen_vk should not fail");
+    let pk =
+        keygen_pk(&params, vk, &MyCircuit(Value::unknown())).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let proofs: Vec<_> = (1..=3u64)
+        .map(|a| {
+            let instance = [Fr::from(a * a)];
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof::<KZGCommitmentScheme<_>, ProverGWC<_>, _, _, _, _>(
+                &params,
+                &pk,
+                &[MyCircuit(Value::known(Fr::from(a)))],
+                &[&[&instance[..]]],
+                test_fixtures::rng(),
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            (instance, transcript.finalize())
+        })
+        .collect();
+
+    let batch = |tamper: Option<usize>| {
+        let mut strategy = AccumulatorStrategy::new(&params);
+        let mut terms = vec![];
+        for (i, (instance, proof)) in proofs.iter().enumerate() {
+            let mut instance = *instance;
+            if tamper == Some(i) {
+                instance[0] += Fr::ONE;
+            }
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            strategy = verify_proof::<_, VerifierGWC<_>, _, _, _>(
+                params.verifier_params(),
+                vk,
+                strategy,
+                &[&[&instance[..]]],
+                &mut transcript,
+            )
+            .expect("proof should be read");
+            terms.push(strategy.msm_accumulator.right.bases.len());
+        }
+        (terms, strategy.msm_accumulator.check())
+    };
+
+    let (terms, valid) = batch(None);
+    assert!(valid);
+    // The commitments of the key and the first point of the parameters get a
+    // single term for the whole batch.
+    let shared = vk.fixed_commitments().len() + vk.permutation().commitments().len() + 1;
+    assert_eq!(terms[1] - terms[0], terms[0] - shared);
+    assert_eq!(terms[2] - terms[1], terms[0] - shared);
+
+    // A single invalid proof fails the batch.
+    for tamper in 0..proofs.len() {
+        assert!(!batch(Some(tamper)).1);
+    }
+}
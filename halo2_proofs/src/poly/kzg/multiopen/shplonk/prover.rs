@@ -9,7 +9,7 @@ use crate::helpers::SerdeCurveAffine;
 use crate::poly::commitment::{Blind, ParamsProver, Prover};
 use crate::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
 use crate::poly::query::{PolynomialPointer, ProverQuery};
-use crate::poly::{Coeff, Polynomial};
+use crate::poly::{Coeff, Polynomial, ScratchPool};
 use crate::transcript::{EncodedChallenge, TranscriptWrite};
 
 use crate::multicore::IntoParallelIterator;
@@ -25,12 +25,34 @@ use std::ops::MulAssign;
 #[cfg(feature = "multicore")]
 use crate::multicore::ParallelIterator;
 
-fn div_by_vanishing<F: Field>(poly: Polynomial<F, Coeff>, roots: &[F]) -> Vec<F> {
-    let poly = roots
-        .iter()
-        .fold(poly.values, |poly, point| kate_division(&poly, *point));
+fn div_by_vanishing<F: Field>(
+    poly: Polynomial<F, Coeff>,
+    roots: &[F],
+    scratch: &ScratchPool<F>,
+) -> Vec<F> {
+    let (first, rest) = roots.split_first().unwrap();
+    let quotient = kate_division(&poly.values[..], *first);
+    scratch.give_poly(poly);
+
+    rest.iter()
+        .fold(quotient, |poly, point| kate_division(&poly, *point))
+}
 
-    poly
+/// Returns the sum of `polys` weighted by the powers of `x`, giving each of
+/// them but the first back to `scratch`.
+fn linear_combination<F: Field>(
+    polys: Vec<Polynomial<F, Coeff>>,
+    x: F,
+    scratch: &ScratchPool<F>,
+) -> Polynomial<F, Coeff> {
+    let mut polys = polys.into_iter().zip(powers(x));
+    let (first, _) = polys.next().unwrap();
+    polys.fold(first, |acc, (poly, power_of_x)| {
+        let poly = poly * power_of_x;
+        let acc = acc + &poly;
+        scratch.give_poly(poly);
+        acc
+    })
 }
 
 struct CommitmentExtension<'a, C: CurveAffine> {
@@ -43,7 +65,7 @@ impl<'a, C: CurveAffine> Commitment<C::Scalar, PolynomialPointer<'a, C>> {
         let poly = lagrange_interpolate(points, &self.evals()[..]);
 
         let low_degree_equivalent = Polynomial {
-            values: poly,
+            values: poly.into(),
             _marker: PhantomData,
         };
 
@@ -55,15 +77,23 @@ impl<'a, C: CurveAffine> Commitment<C::Scalar, PolynomialPointer<'a, C>> {
 }
 
 impl<'a, C: CurveAffine> CommitmentExtension<'a, C> {
-    fn linearisation_contribution(&self, u: C::Scalar) -> Polynomial<C::Scalar, Coeff> {
-        let p_x = self.commitment.get().poly;
+    fn linearisation_contribution(
+        &self,
+        u: C::Scalar,
+        scratch: &ScratchPool<C::Scalar>,
+    ) -> Polynomial<C::Scalar, Coeff> {
+        let mut p_x = scratch.copy_poly(self.commitment.get().poly);
         let r_eval = eval_polynomial(&self.low_degree_equivalent.values[..], u);
-        p_x - r_eval
+        p_x[0] -= r_eval;
+        p_x
     }
 
-    fn quotient_contribution(&self) -> Polynomial<C::Scalar, Coeff> {
+    fn quotient_contribution(
+        &self,
+        scratch: &ScratchPool<C::Scalar>,
+    ) -> Polynomial<C::Scalar, Coeff> {
         let len = self.low_degree_equivalent.len();
-        let mut p_x = self.commitment.get().poly.clone();
+        let mut p_x = scratch.copy_poly(self.commitment.get().poly);
         parallelize(&mut p_x.values[0..len], |lhs, start| {
             for (lhs, rhs) in lhs
                 .iter_mut()
@@ -138,6 +168,10 @@ where
         // for different sets that are already combined with anoter challenge
         let y: ChallengeY<_> = transcript.squeeze_challenge_scalar();
 
+        // Buffers of the domain size, for the numerators and contributions
+        // below, which are only needed until they are combined.
+        let scratch = ScratchPool::new(true);
+
         let quotient_contribution = |rotation_set: &RotationSetExtension<E::G1Affine>| {
             // [P_i_0(X) - R_i_0(X), P_i_1(X) - R_i_1(X), ... ]
             #[allow(clippy::needless_collect)]
@@ -145,7 +179,7 @@ where
                 .commitments
                 .as_slice()
                 .into_par_iter()
-                .map(|commitment| commitment.quotient_contribution())
+                .map(|commitment| commitment.quotient_contribution(&scratch))
                 .collect::<Vec<_>>();
 
             // define numerator polynomial as
@@ -153,23 +187,18 @@ where
             // and combine polynomials with same evaluation point set
             // N_i(X) = linear_combinination(y, N_i_j(X))
             // where y is random scalar to combine numerator polynomials
-            let n_x = numerators
-                .into_iter()
-                .zip(powers(*y))
-                .map(|(numerator, power_of_y)| numerator * power_of_y)
-                .reduce(|acc, numerator| acc + &numerator)
-                .unwrap();
+            let n_x = linear_combination(numerators, *y, &scratch);
 
             let points = &rotation_set.points[..];
 
             // quotient contribution of this evaluation set is
             // Q_i(X) = N_i(X) / Z_i(X) where
             // Z_i(X) = (x - r_i_0) * (x - r_i_1) * ...
-            let mut poly = div_by_vanishing(n_x, points);
+            let mut poly = div_by_vanishing(n_x, points, &scratch);
             poly.resize(self.params.n as usize, E::Scalar::ZERO);
 
             Polynomial {
-                values: poly,
+                values: poly.into(),
                 _marker: PhantomData,
             }
         };
@@ -202,12 +231,7 @@ where
             .map(quotient_contribution)
             .collect::<Vec<_>>();
 
-        let h_x: Polynomial<E::Scalar, Coeff> = quotient_polynomials
-            .into_iter()
-            .zip(powers(*v))
-            .map(|(poly, power_of_v)| poly * power_of_v)
-            .reduce(|acc, poly| acc + &poly)
-            .unwrap();
+        let h_x = linear_combination(quotient_polynomials, *v, &scratch);
 
         let h = self.params.commit(&h_x, Blind::default()).to_affine();
         transcript.write_point(h)?;
@@ -232,7 +256,7 @@ where
                 .commitments
                 .as_slice()
                 .into_par_iter()
-                .map(|commitment| commitment.linearisation_contribution(*u))
+                .map(|commitment| commitment.linearisation_contribution(*u, &scratch))
                 .collect::<Vec<_>>();
 
             // define inner contributor polynomial as
@@ -240,12 +264,7 @@ where
             // and combine polynomials with same evaluation point set
             // L_i(X) = linear_combinination(y, L_i_j(X))
             // where y is random scalar to combine inner contibutors
-            let l_x: Polynomial<E::Scalar, Coeff> = inner_contributions
-                .into_iter()
-                .zip(powers(*y))
-                .map(|(poly, power_of_y)| poly * power_of_y)
-                .reduce(|acc, poly| acc + &poly)
-                .unwrap();
+            let l_x = linear_combination(inner_contributions, *y, &scratch);
 
             // finally scale l_x by difference vanishing polynomial evaluation z_i
             (l_x * z_i, z_i)
@@ -260,16 +279,13 @@ where
             .map(linearisation_contribution)
             .unzip();
 
-        let l_x: Polynomial<E::Scalar, Coeff> = linearisation_contibutions
-            .into_iter()
-            .zip(powers(*v))
-            .map(|(poly, power_of_v)| poly * power_of_v)
-            .reduce(|acc, poly| acc + &poly)
-            .unwrap();
+        let l_x = linear_combination(linearisation_contibutions, *v, &scratch);
 
         let super_point_set = super_point_set.into_iter().collect::<Vec<_>>();
         let zt_eval = evaluate_vanishing_polynomial(&super_point_set[..], *u);
-        let l_x = l_x - &(h_x * zt_eval);
+        let h_x = h_x * zt_eval;
+        let l_x = l_x - &h_x;
+        scratch.give_poly(h_x);
 
         // sanity check
         #[cfg(debug_assertions)]
@@ -278,7 +294,7 @@ where
             assert_eq!(must_be_zero, E::Scalar::ZERO);
         }
 
-        let mut h_x = div_by_vanishing(l_x, &[*u]);
+        let mut h_x = div_by_vanishing(l_x, &[*u], &scratch);
 
         // normalize coefficients by the coefficient of the first polynomial
         let z_0_diff_inv = z_diffs[0].invert().unwrap();
@@ -287,7 +303,7 @@ where
         }
 
         let h_x = Polynomial {
-            values: h_x,
+            values: h_x.into(),
             _marker: PhantomData,
         };
 
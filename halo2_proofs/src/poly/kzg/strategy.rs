@@ -1,6 +1,6 @@
 use super::{
     commitment::{KZGCommitmentScheme, ParamsKZG},
-    msm::DualMSM,
+    msm::{AccumulatedMSM, DualMSM},
 };
 use crate::{
     helpers::SerdeCurveAffine,
@@ -152,3 +152,57 @@ where
         unreachable!();
     }
 }
+
+/// A verifier that checks everything about a single proof but its final
+/// pairing, and returns the MSMs of that pairing for the caller to check,
+/// for example along with those of other proofs or in a circuit.
+///
+/// The output of [`verify_proof`](crate::plonk::verify_proof) only means the
+/// proof is valid once [`AccumulatedMSM::check`] returns `true`.
+#[derive(Clone, Debug)]
+pub struct DeferredStrategy<'params, E: Engine> {
+    msm: DualMSM<'params, E>,
+}
+
+impl<'params, E: MultiMillerLoop + Debug> DeferredStrategy<'params, E> {
+    /// Constructs a verifier that defers the final pairing check
+    pub fn new(params: &'params ParamsKZG<E>) -> Self {
+        DeferredStrategy {
+            msm: DualMSM::new(params),
+        }
+    }
+}
+
+impl<
+        'params,
+        E: MultiMillerLoop + Debug,
+        V: Verifier<
+            'params,
+            KZGCommitmentScheme<E>,
+            MSMAccumulator = DualMSM<'params, E>,
+            Guard = GuardKZG<'params, E>,
+        >,
+    > VerificationStrategy<'params, KZGCommitmentScheme<E>, V> for DeferredStrategy<'params, E>
+where
+    E::Scalar: PrimeField,
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    type Output = AccumulatedMSM<E>;
+
+    fn new(params: &'params ParamsKZG<E>) -> Self {
+        Self::new(params)
+    }
+
+    fn process(
+        self,
+        f: impl FnOnce(V::MSMAccumulator) -> Result<V::Guard, Error>,
+    ) -> Result<Self::Output, Error> {
+        let guard = f(self.msm)?;
+        Ok(AccumulatedMSM::new(&guard.msm_accumulator))
+    }
+
+    fn finalize(self) -> bool {
+        unreachable!();
+    }
+}
@@ -0,0 +1,84 @@
+//! Buffers of field elements reused across the stages of a single proof, so
+//! that the prover doesn't allocate and free a new polynomial for each
+//! temporary it needs.
+
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use group::ff::Field;
+
+use super::{Basis, Polynomial, Storage};
+
+/// A pool of buffers, handed out by length.
+///
+/// The pool can be shared by the tasks of a parallel section: buffers are
+/// taken and given back under a lock, and are owned by a single task in
+/// between.
+#[derive(Debug)]
+pub(crate) struct ScratchPool<F> {
+    // `None` if buffers are not kept for reuse.
+    buffers: Option<Mutex<Vec<Vec<F>>>>,
+}
+
+impl<F: Field> ScratchPool<F> {
+    /// Returns an empty pool, that keeps the buffers given back to it if
+    /// `reuse` is set, and drops them otherwise.
+    pub(crate) fn new(reuse: bool) -> Self {
+        ScratchPool {
+            buffers: reuse.then(|| Mutex::new(vec![])),
+        }
+    }
+
+    /// Takes a buffer of `len` elements out of the pool, or allocates one if
+    /// there is none of that length. Its values are arbitrary.
+    pub(crate) fn take(&self, len: usize) -> Vec<F> {
+        if let Some(buffers) = &self.buffers {
+            let mut buffers = buffers.lock().unwrap();
+            if let Some(position) = buffers.iter().position(|buffer| buffer.len() == len) {
+                return buffers.swap_remove(position);
+            }
+        }
+        vec![F::ZERO; len]
+    }
+
+    /// Returns a copy of `poly` in a buffer taken from the pool.
+    pub(crate) fn copy_poly<B: Basis>(&self, poly: &Polynomial<F, B>) -> Polynomial<F, B> {
+        let mut values = self.take(poly.len());
+        values.copy_from_slice(poly);
+        Polynomial {
+            values: values.into(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Gives `buffer` back to the pool.
+    pub(crate) fn give(&self, buffer: Vec<F>) {
+        if let Some(buffers) = &self.buffers {
+            buffers.lock().unwrap().push(buffer);
+        }
+    }
+
+    /// Gives the values of `poly` back to the pool, unless they are on disk.
+    pub(crate) fn give_poly<B: Basis>(&self, poly: Polynomial<F, B>) {
+        if let Some(values) = poly.values.into_memory() {
+            self.give(values);
+        }
+    }
+}
+
+#[test]
+fn test_scratch_pool_reuse() {
+    use halo2curves::pasta::Fp;
+
+    let pool = ScratchPool::<Fp>::new(true);
+    let buffer = pool.take(8);
+    let ptr = buffer.as_ptr();
+    pool.give(buffer);
+    // Buffers are only handed out for their own length.
+    assert_eq!(pool.take(4).len(), 4);
+    assert_eq!(pool.take(8).as_ptr(), ptr);
+
+    let pool = ScratchPool::<Fp>::new(false);
+    pool.give(vec![Fp::ONE; 8]);
+    assert_eq!(pool.take(8), vec![Fp::ZERO; 8]);
+}
@@ -0,0 +1,245 @@
+//! The backing storage of the values of a [`Polynomial`](super::Polynomial):
+//! either a vector in memory, or, with the `disk-offload` feature, a file on
+//! disk mapped into memory, which the operating system pages in and out as
+//! the prover touches it.
+
+// Viewing a mapped file as a slice of field elements requires `unsafe`.
+#![cfg_attr(feature = "disk-offload", allow(unsafe_code))]
+
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "disk-offload")]
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    marker::PhantomData,
+    mem,
+    path::{Path, PathBuf},
+    slice,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "disk-offload")]
+use ff::PrimeField;
+#[cfg(feature = "disk-offload")]
+use memmap2::MmapMut;
+
+/// Distinguishes the files of the buffers created by this process.
+#[cfg(feature = "disk-offload")]
+static NEXT_BUFFER: AtomicUsize = AtomicUsize::new(0);
+
+/// The values of a polynomial.
+///
+/// Both variants are accessed as a slice, so that FFTs and `parallelize` work
+/// on them unchanged. Anything that would reallocate the values, such as
+/// resizing or cloning them, moves them back into memory.
+pub(crate) enum Storage<F> {
+    Memory(Vec<F>),
+    #[cfg(feature = "disk-offload")]
+    Disk(DiskBuffer<F>),
+}
+
+#[cfg(feature = "disk-offload")]
+impl<F: PrimeField> Storage<F> {
+    /// Moves the values into a new file in `dir`. Empty polynomials and
+    /// polynomials already on disk are left as they are.
+    pub(crate) fn offload(&mut self, dir: &Path) -> io::Result<()> {
+        if let Storage::Memory(values) = self {
+            if !values.is_empty() {
+                *self = Storage::Disk(DiskBuffer::new(dir, values)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: Copy> Storage<F> {
+    /// Returns whether the values are stored on disk.
+    #[cfg(feature = "disk-offload")]
+    pub(crate) fn is_on_disk(&self) -> bool {
+        matches!(self, Storage::Disk(_))
+    }
+
+    /// Returns the values in memory, reading them from disk if needed.
+    pub(crate) fn into_vec(self) -> Vec<F> {
+        match self {
+            Storage::Memory(values) => values,
+            #[cfg(feature = "disk-offload")]
+            Storage::Disk(buffer) => buffer.to_vec(),
+        }
+    }
+
+    /// Returns the values if they are in memory.
+    pub(crate) fn into_memory(self) -> Option<Vec<F>> {
+        match self {
+            Storage::Memory(values) => Some(values),
+            #[cfg(feature = "disk-offload")]
+            Storage::Disk(_) => None,
+        }
+    }
+
+    /// Moves the values into memory and returns them.
+    fn as_vec_mut(&mut self) -> &mut Vec<F> {
+        #[cfg(feature = "disk-offload")]
+        if let Storage::Disk(buffer) = self {
+            *self = Storage::Memory(buffer.to_vec());
+        }
+        match self {
+            Storage::Memory(values) => values,
+            #[cfg(feature = "disk-offload")]
+            Storage::Disk(_) => unreachable!(),
+        }
+    }
+
+    /// Same as [`Vec::resize`].
+    pub(crate) fn resize(&mut self, new_len: usize, value: F) {
+        self.as_vec_mut().resize(new_len, value);
+    }
+}
+
+impl<F> Deref for Storage<F> {
+    type Target = [F];
+
+    fn deref(&self) -> &[F] {
+        match self {
+            Storage::Memory(values) => &values[..],
+            #[cfg(feature = "disk-offload")]
+            Storage::Disk(buffer) => &buffer[..],
+        }
+    }
+}
+
+impl<F> DerefMut for Storage<F> {
+    fn deref_mut(&mut self) -> &mut [F] {
+        match self {
+            Storage::Memory(values) => &mut values[..],
+            #[cfg(feature = "disk-offload")]
+            Storage::Disk(buffer) => &mut buffer[..],
+        }
+    }
+}
+
+impl<F: Clone> Clone for Storage<F> {
+    fn clone(&self) -> Self {
+        Storage::Memory(self.to_vec())
+    }
+}
+
+impl<F: fmt::Debug> fmt::Debug for Storage<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<F> From<Vec<F>> for Storage<F> {
+    fn from(values: Vec<F>) -> Self {
+        Storage::Memory(values)
+    }
+}
+
+impl<F> FromIterator<F> for Storage<F> {
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        Storage::Memory(iter.into_iter().collect())
+    }
+}
+
+/// Values held in a file mapped into memory. The file is removed when the
+/// buffer is dropped.
+///
+/// The values are written to and read from the file as raw bytes, which is
+/// only sound for types without padding bytes and for which any bytes written
+/// from a valid value read back as that value. Buffers can therefore only be
+/// created for [`PrimeField`] elements, whose representations are arrays of
+/// limbs.
+#[cfg(feature = "disk-offload")]
+pub(crate) struct DiskBuffer<F> {
+    // Only `None` while the buffer is dropped, so that the file is unmapped
+    // before it is removed.
+    map: Option<MmapMut>,
+    len: usize,
+    path: PathBuf,
+    _marker: PhantomData<F>,
+}
+
+#[cfg(feature = "disk-offload")]
+impl<F: PrimeField> DiskBuffer<F> {
+    /// Copies `values` into a new file in `dir`.
+    fn new(dir: &Path, values: &[F]) -> io::Result<Self> {
+        let path = dir.join(format!(
+            "halo2-{}-{}.poly",
+            std::process::id(),
+            NEXT_BUFFER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        let map = match Self::write_and_map(&file, values) {
+            Ok(map) => map,
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                return Err(e);
+            }
+        };
+        Ok(DiskBuffer {
+            map: Some(map),
+            len: values.len(),
+            path,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Writes `values` to `file` and maps it. The values are written before
+    /// the file is mapped, rather than through the mapping, so that running
+    /// out of disk space is reported here as an error instead of faulting on
+    /// a page of a sparse file.
+    fn write_and_map(mut file: &File, values: &[F]) -> io::Result<MmapMut> {
+        // SAFETY: `values` is a slice of `mem::size_of_val(values)` bytes
+        // that lives for the duration of this borrow. Field elements are
+        // arrays of limbs, which have no padding bytes, so all of these bytes
+        // are initialized.
+        let bytes = unsafe {
+            slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values))
+        };
+        file.write_all(bytes)?;
+        // SAFETY: the file was just created by this process, under a name no
+        // other buffer uses, so nothing else modifies it while it is mapped.
+        unsafe { MmapMut::map_mut(file) }
+    }
+}
+
+#[cfg(feature = "disk-offload")]
+impl<F> Deref for DiskBuffer<F> {
+    type Target = [F];
+
+    fn deref(&self) -> &[F] {
+        let map = self.map.as_ref().unwrap();
+        // SAFETY: the mapping is aligned for `F` and was filled with
+        // `self.len` valid values of type `F` by `DiskBuffer::new`, which
+        // requires `F: PrimeField` so that a bitwise copy of the values is
+        // valid. It is only ever modified through `deref_mut`.
+        unsafe { slice::from_raw_parts(map.as_ptr() as *const F, self.len) }
+    }
+}
+
+#[cfg(feature = "disk-offload")]
+impl<F> DerefMut for DiskBuffer<F> {
+    fn deref_mut(&mut self) -> &mut [F] {
+        let map = self.map.as_mut().unwrap();
+        // SAFETY: as in `deref`; the mutable borrow of `self` makes this the
+        // only view of the mapping.
+        unsafe { slice::from_raw_parts_mut(map.as_mut_ptr() as *mut F, self.len) }
+    }
+}
+
+#[cfg(feature = "disk-offload")]
+impl<F> Drop for DiskBuffer<F> {
+    fn drop(&mut self) {
+        drop(self.map.take());
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -8,8 +8,13 @@ use std::convert::TryInto;
 
 use halo2curves::{Coordinates, CurveAffine};
 
+use std::cell::Cell;
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
+use std::rc::Rc;
+
+mod poseidon;
+pub use poseidon::*;
 
 /// Prefix to a prover's message soliciting a challenge
 const BLAKE2B_PREFIX_CHALLENGE: u8 = 0;
@@ -54,6 +59,16 @@ pub trait Transcript<C: CurveAffine, E: EncodedChallenge<C>> {
     /// treating it as a common input.
     fn common_point(&mut self, point: C) -> io::Result<()>;
 
+    /// Writing the points to the transcript without writing them to the
+    /// proof, in order. The transcript is left in the same state as by
+    /// calling [`Self::common_point`] on each of them.
+    fn common_points(&mut self, points: &[C]) -> io::Result<()> {
+        for point in points {
+            self.common_point(*point)?;
+        }
+        Ok(())
+    }
+
     /// Writing the scalar to the transcript without writing it to the proof,
     /// treating it as a common input.
     fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()>;
@@ -65,6 +80,12 @@ pub trait TranscriptRead<C: CurveAffine, E: EncodedChallenge<C>>: Transcript<C,
     /// Read a curve point from the prover.
     fn read_point(&mut self) -> io::Result<C>;
 
+    /// Read `n` curve points from the prover, leaving the transcript in the
+    /// same state as `n` calls to [`Self::read_point`].
+    fn read_points(&mut self, n: usize) -> io::Result<Vec<C>> {
+        (0..n).map(|_| self.read_point()).collect()
+    }
+
     /// Read a curve scalar from the prover.
     fn read_scalar(&mut self) -> io::Result<C::Scalar>;
 }
@@ -75,6 +96,16 @@ pub trait TranscriptWrite<C: CurveAffine, E: EncodedChallenge<C>>: Transcript<C,
     /// Write a curve point to the proof and the transcript.
     fn write_point(&mut self, point: C) -> io::Result<()>;
 
+    /// Write the points to the proof and the transcript, in order. The proof
+    /// and the transcript are the same as after calling
+    /// [`Self::write_point`] on each of them.
+    fn write_points(&mut self, points: &[C]) -> io::Result<()> {
+        for point in points {
+            self.write_point(*point)?;
+        }
+        Ok(())
+    }
+
     /// Write a scalar to the proof and the transcript.
     fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()>;
 }
@@ -98,6 +129,39 @@ pub trait TranscriptWriterBuffer<W: Write, C: CurveAffine, E: EncodedChallenge<C
     fn finalize(self) -> W;
 }
 
+/// A proof being read by [`verify_proof_exact`](crate::plonk::verify_proof_exact),
+/// which counts the bytes the transcript reads from it, so that the bytes
+/// left over after the proof can be told apart. Callers doing their own
+/// framing can read proofs through it the same way.
+#[derive(Debug, Clone)]
+pub struct ProofReader<'proof> {
+    proof: &'proof [u8],
+    read: Rc<Cell<usize>>,
+}
+
+impl<'proof> ProofReader<'proof> {
+    /// Returns a reader over `proof`, and a handle on the number of bytes
+    /// read from it, which clones of the reader share.
+    pub fn new(proof: &'proof [u8]) -> (Self, Rc<Cell<usize>>) {
+        let read = Rc::new(Cell::new(0));
+        (
+            ProofReader {
+                proof,
+                read: read.clone(),
+            },
+            read,
+        )
+    }
+}
+
+impl Read for ProofReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.proof.read(buf)?;
+        self.read.set(self.read.get() + n);
+        Ok(n)
+    }
+}
+
 /// We will replace BLAKE2b with an algebraic hash function in a later version.
 #[derive(Debug, Clone)]
 pub struct Blake2bRead<R: Read, C: CurveAffine, E: EncodedChallenge<C>> {
@@ -165,6 +229,24 @@ where
         Ok(point)
     }
 
+    fn read_points(&mut self, n: usize) -> io::Result<Vec<C>> {
+        let mut compressed = vec![C::Repr::default(); n];
+        for repr in compressed.iter_mut() {
+            self.reader.read_exact(repr.as_mut())?;
+        }
+        let points = compressed
+            .iter()
+            .map(|repr| {
+                Option::from(C::from_bytes(repr)).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
+                })
+            })
+            .collect::<io::Result<Vec<C>>>()?;
+        self.common_points(&points)?;
+
+        Ok(points)
+    }
+
     fn read_scalar(&mut self) -> io::Result<C::Scalar> {
         let mut data = <C::Scalar as PrimeField>::Repr::default();
         self.reader.read_exact(data.as_mut())?;
@@ -236,6 +318,12 @@ where
         Ok(())
     }
 
+    fn common_points(&mut self, points: &[C]) -> io::Result<()> {
+        self.state.update(&blake2b_points_input(points)?);
+
+        Ok(())
+    }
+
     fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
         self.state.update(&[BLAKE2B_PREFIX_SCALAR]);
         self.state.update(scalar.to_repr().as_ref());
@@ -360,6 +448,14 @@ where
         let compressed = point.to_bytes();
         self.writer.write_all(compressed.as_ref())
     }
+    fn write_points(&mut self, points: &[C]) -> io::Result<()> {
+        self.common_points(points)?;
+        let mut compressed = vec![];
+        for point in points {
+            compressed.extend_from_slice(point.to_bytes().as_ref());
+        }
+        self.writer.write_all(&compressed)
+    }
     fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
         self.common_scalar(scalar)?;
         let data = scalar.to_repr();
@@ -410,6 +506,12 @@ where
         Ok(())
     }
 
+    fn common_points(&mut self, points: &[C]) -> io::Result<()> {
+        self.state.update(&blake2b_points_input(points)?);
+
+        Ok(())
+    }
+
     fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
         self.state.update(&[BLAKE2B_PREFIX_SCALAR]);
         self.state.update(scalar.to_repr().as_ref());
@@ -462,6 +564,305 @@ where
     }
 }
 
+/// How a transcript writes points to the proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointEncoding {
+    /// The compressed encoding of the curve, as [`Blake2bWrite`] writes
+    /// points.
+    Compressed,
+    /// The affine coordinates, as two 32-byte big-endian words.
+    Uncompressed,
+}
+
+impl PointEncoding {
+    /// Returns the number of bytes a point of `C` takes up in this encoding.
+    pub fn len<C: CurveAffine>(&self) -> usize {
+        match self {
+            PointEncoding::Compressed => C::Repr::default().as_ref().len(),
+            PointEncoding::Uncompressed => {
+                2 * <C::Base as PrimeField>::Repr::default().as_ref().len()
+            }
+        }
+    }
+}
+
+/// Keccak256 hash function reader following the byte layout of EVM
+/// verifiers, for curves whose fields have 32-byte little-endian
+/// representations, such as BN254.
+///
+/// Unlike [`Keccak256Read`], which hashes prefixed little-endian encodings
+/// and reads compressed points, it reads and absorbs points as their
+/// uncompressed affine coordinates and scalars as 32-byte big-endian words,
+/// without any prefix or domain separator. Transcripts created with
+/// [`Self::init_with_encoding`] and [`PointEncoding::Compressed`] read and
+/// absorb the compressed encoding of the curve instead, halving the size of
+/// the points; a proof must be read with the encoding it was written with.
+///
+/// A challenge is the Keccak256 hash of the words absorbed since the
+/// previous challenge, preceded by that challenge's hash, as a big-endian
+/// integer reduced into the scalar field; if nothing was absorbed since the
+/// previous challenge, the byte `1` is appended to its hash.
+#[derive(Debug, Clone)]
+pub struct EvmKeccak256Read<R: Read, C: CurveAffine, E: EncodedChallenge<C>> {
+    state: EvmKeccak256State,
+    encoding: PointEncoding,
+    reader: R,
+    _marker: PhantomData<(C, E)>,
+}
+
+/// Keccak256 hash function writer following the byte layout of EVM
+/// verifiers; see [`EvmKeccak256Read`].
+#[derive(Debug, Clone)]
+pub struct EvmKeccak256Write<W: Write, C: CurveAffine, E: EncodedChallenge<C>> {
+    state: EvmKeccak256State,
+    encoding: PointEncoding,
+    writer: W,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<R: Read, C: CurveAffine> TranscriptReadBuffer<R, C, Challenge255<C>>
+    for EvmKeccak256Read<R, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    /// Initialize a transcript given an input buffer.
+    fn init(reader: R) -> Self {
+        Self::init_with_encoding(reader, PointEncoding::Uncompressed)
+    }
+}
+
+impl<R: Read, C: CurveAffine, E: EncodedChallenge<C>> EvmKeccak256Read<R, C, E> {
+    /// Initializes a transcript given an input buffer, reading points in the
+    /// given encoding.
+    pub fn init_with_encoding(reader: R, encoding: PointEncoding) -> Self {
+        EvmKeccak256Read {
+            state: EvmKeccak256State::default(),
+            encoding,
+            reader,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R: Read, C: CurveAffine> TranscriptRead<C, Challenge255<C>>
+    for EvmKeccak256Read<R, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let point = match self.encoding {
+            PointEncoding::Compressed => {
+                let mut compressed = C::Repr::default();
+                self.reader.read_exact(compressed.as_mut())?;
+                Option::from(C::from_bytes(&compressed))
+            }
+            PointEncoding::Uncompressed => {
+                let mut x = [0u8; 32];
+                let mut y = [0u8; 32];
+                self.reader.read_exact(&mut x)?;
+                self.reader.read_exact(&mut y)?;
+                from_evm_word(x)
+                    .zip(from_evm_word(y))
+                    .and_then(|(x, y)| Option::from(C::from_xy(x, y)))
+            }
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof"))?;
+        self.common_point(point)?;
+
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let mut word = [0u8; 32];
+        self.reader.read_exact(&mut word)?;
+        let scalar = from_evm_word(word).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "invalid field element encoding in proof",
+            )
+        })?;
+        self.common_scalar(scalar)?;
+
+        Ok(scalar)
+    }
+}
+
+impl<R: Read, C: CurveAffine> Transcript<C, Challenge255<C>>
+    for EvmKeccak256Read<R, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        self.state.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.state.absorb(&evm_point_bytes(point, self.encoding)?);
+
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.state.absorb(&evm_word(&scalar)?);
+
+        Ok(())
+    }
+}
+
+impl<W: Write, C: CurveAffine> TranscriptWriterBuffer<W, C, Challenge255<C>>
+    for EvmKeccak256Write<W, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    /// Initialize a transcript given an output buffer.
+    fn init(writer: W) -> Self {
+        Self::init_with_encoding(writer, PointEncoding::Uncompressed)
+    }
+
+    /// Conclude the interaction and return the output buffer (writer).
+    fn finalize(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, C: CurveAffine> TranscriptWrite<C, Challenge255<C>>
+    for EvmKeccak256Write<W, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        let bytes = evm_point_bytes(point, self.encoding)?;
+        self.state.absorb(&bytes);
+        self.writer.write_all(&bytes)
+    }
+
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        let word = evm_word(&scalar)?;
+        self.state.absorb(&word);
+        self.writer.write_all(&word)
+    }
+}
+
+impl<W: Write, C: CurveAffine> Transcript<C, Challenge255<C>>
+    for EvmKeccak256Write<W, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        self.state.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.state.absorb(&evm_point_bytes(point, self.encoding)?);
+
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.state.absorb(&evm_word(&scalar)?);
+
+        Ok(())
+    }
+}
+
+impl<W: Write, C: CurveAffine, E: EncodedChallenge<C>> EvmKeccak256Write<W, C, E> {
+    /// Initializes a transcript given an output buffer, writing points in
+    /// the given encoding.
+    pub fn init_with_encoding(writer: W, encoding: PointEncoding) -> Self {
+        EvmKeccak256Write {
+            state: EvmKeccak256State::default(),
+            encoding,
+            writer,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The bytes absorbed by the EVM transcripts since their last challenge,
+/// starting with the hash of that challenge.
+#[derive(Debug, Clone, Default)]
+struct EvmKeccak256State {
+    buf: Vec<u8>,
+    /// Whether nothing was absorbed since the last challenge.
+    squeezed: bool,
+}
+
+impl EvmKeccak256State {
+    fn absorb(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+        self.squeezed = false;
+    }
+
+    fn squeeze_challenge<C: CurveAffine>(&mut self) -> Challenge255<C>
+    where
+        C::Scalar: FromUniformBytes<64>,
+    {
+        if self.squeezed {
+            self.buf.push(1);
+        }
+        let hash: [u8; 32] = Keccak256::digest(&self.buf).as_slice().try_into().unwrap();
+        self.buf = hash.to_vec();
+        self.squeezed = true;
+
+        // The hash is a big-endian integer, which the wide reduction of its
+        // little-endian bytes, zero-extended, reduces into the field.
+        let mut wide = [0u8; 64];
+        for (wide, byte) in wide.iter_mut().zip(hash.iter().rev()) {
+            *wide = *byte;
+        }
+        Challenge255::<C>::new(&wide)
+    }
+}
+
+/// Returns the 32-byte big-endian word of a field element whose
+/// representation is 32 little-endian bytes.
+fn evm_word<F: PrimeField>(value: &F) -> io::Result<[u8; 32]> {
+    let mut word: [u8; 32] = value.to_repr().as_ref().try_into().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "the EVM transcript requires 32-byte field elements",
+        )
+    })?;
+    word.reverse();
+    Ok(word)
+}
+
+/// Returns the field element of a 32-byte big-endian word, if it is
+/// canonical.
+fn from_evm_word<F: PrimeField>(mut word: [u8; 32]) -> Option<F> {
+    word.reverse();
+    let mut repr = F::Repr::default();
+    if repr.as_ref().len() != word.len() {
+        return None;
+    }
+    repr.as_mut().copy_from_slice(&word);
+    Option::from(F::from_repr(repr))
+}
+
+/// Returns the bytes of a point in the given encoding, as the EVM transcripts
+/// write and absorb it.
+fn evm_point_bytes<C: CurveAffine>(point: C, encoding: PointEncoding) -> io::Result<Vec<u8>> {
+    match encoding {
+        PointEncoding::Compressed => Ok(point.to_bytes().as_ref().to_vec()),
+        PointEncoding::Uncompressed => Ok(evm_point_words(point)?.to_vec()),
+    }
+}
+
+/// Returns the uncompressed affine coordinates of a point as two 32-byte
+/// big-endian words.
+fn evm_point_words<C: CurveAffine>(point: C) -> io::Result<[u8; 64]> {
+    let coords: Coordinates<C> = Option::from(point.coordinates()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "cannot write points at infinity to the transcript",
+        )
+    })?;
+    let mut words = [0u8; 64];
+    words[..32].copy_from_slice(&evm_word(coords.x())?);
+    words[32..].copy_from_slice(&evm_word(coords.y())?);
+    Ok(words)
+}
+
 /// The scalar representation of a verifier challenge.
 ///
 /// The `Type` type can be used to scope the challenge to a specific context, or
@@ -539,11 +940,30 @@ where
     }
 }
 
+/// Returns the input the BLAKE2b transcripts absorb for `points`: the same
+/// bytes as absorbing each point on its own, so that they can be hashed in a
+/// single update.
+fn blake2b_points_input<C: CurveAffine>(points: &[C]) -> io::Result<Vec<u8>> {
+    let mut input = vec![];
+    for point in points {
+        let coords: Coordinates<C> = Option::from(point.coordinates()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "cannot write points at infinity to the transcript",
+            )
+        })?;
+        input.push(BLAKE2B_PREFIX_POINT);
+        input.extend_from_slice(coords.x().to_repr().as_ref());
+        input.extend_from_slice(coords.y().to_repr().as_ref());
+    }
+    Ok(input)
+}
+
 pub(crate) fn read_n_points<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>>(
     transcript: &mut T,
     n: usize,
 ) -> io::Result<Vec<C>> {
-    (0..n).map(|_| transcript.read_point()).collect()
+    transcript.read_points(n)
 }
 
 pub(crate) fn read_n_scalars<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>>(
@@ -552,3 +972,181 @@ pub(crate) fn read_n_scalars<C: CurveAffine, E: EncodedChallenge<C>, T: Transcri
 ) -> io::Result<Vec<C::Scalar>> {
     (0..n).map(|_| transcript.read_scalar()).collect()
 }
+
+#[test]
+fn test_batched_points_same_challenges() {
+    use group::{ff::Field, prime::PrimeCurveAffine, Curve};
+    use halo2curves::bn256::{Fr, G1Affine};
+    use rand_core::OsRng;
+
+    let points: Vec<G1Affine> = (0..5)
+        .map(|_| (G1Affine::generator() * Fr::random(OsRng)).to_affine())
+        .collect();
+    let scalar = Fr::random(OsRng);
+
+    // Absorbs a scalar, a batch of points and a common batch, squeezing a
+    // challenge after each.
+    fn write<T: TranscriptWriterBuffer<Vec<u8>, G1Affine, Challenge255<G1Affine>>>(
+        points: &[G1Affine],
+        scalar: Fr,
+        batched: bool,
+    ) -> (Vec<u8>, Vec<Fr>) {
+        let mut transcript = T::init(vec![]);
+        let mut challenges = vec![];
+        transcript.write_scalar(scalar).unwrap();
+        challenges.push(transcript.squeeze_challenge().get_scalar());
+        if batched {
+            transcript.write_points(points).unwrap();
+        } else {
+            for point in points {
+                transcript.write_point(*point).unwrap();
+            }
+        }
+        challenges.push(transcript.squeeze_challenge().get_scalar());
+        if batched {
+            transcript.common_points(&points[1..]).unwrap();
+        } else {
+            for point in &points[1..] {
+                transcript.common_point(*point).unwrap();
+            }
+        }
+        challenges.push(transcript.squeeze_challenge().get_scalar());
+        (transcript.finalize(), challenges)
+    }
+
+    fn read<T: TranscriptRead<G1Affine, Challenge255<G1Affine>>>(
+        transcript: &mut T,
+        points: &[G1Affine],
+        batched: bool,
+    ) -> Vec<Fr> {
+        let mut challenges = vec![];
+        transcript.read_scalar().unwrap();
+        challenges.push(transcript.squeeze_challenge().get_scalar());
+        let read = if batched {
+            transcript.read_points(points.len()).unwrap()
+        } else {
+            (0..points.len())
+                .map(|_| transcript.read_point().unwrap())
+                .collect()
+        };
+        assert_eq!(read, points);
+        challenges.push(transcript.squeeze_challenge().get_scalar());
+        transcript.common_points(&points[1..]).unwrap();
+        challenges.push(transcript.squeeze_challenge().get_scalar());
+        challenges
+    }
+
+    let (proof, challenges) = write::<Blake2bWrite<_, _, _>>(&points, scalar, false);
+    assert_eq!(
+        write::<Blake2bWrite<_, _, _>>(&points, scalar, true),
+        (proof.clone(), challenges.clone())
+    );
+    for batched in [false, true] {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        assert_eq!(read(&mut transcript, &points, batched), challenges);
+    }
+
+    // The default implementations behave the same.
+    let (proof, challenges) = write::<Keccak256Write<_, _, _>>(&points, scalar, false);
+    assert_eq!(
+        write::<Keccak256Write<_, _, _>>(&points, scalar, true),
+        (proof.clone(), challenges.clone())
+    );
+    for batched in [false, true] {
+        let mut transcript = Keccak256Read::<_, _, Challenge255<_>>::init(&proof[..]);
+        assert_eq!(read(&mut transcript, &points, batched), challenges);
+    }
+
+    // A point at infinity fails the batch without changing the transcript.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let mut expected = transcript.clone();
+    assert!(transcript
+        .common_points(&[points[0], G1Affine::identity()])
+        .is_err());
+    assert_eq!(
+        transcript.squeeze_challenge().get_scalar(),
+        expected.squeeze_challenge().get_scalar()
+    );
+}
+
+#[test]
+fn test_evm_keccak256_vectors() {
+    use group::prime::PrimeCurveAffine;
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    // Parses a big-endian hexadecimal scalar.
+    fn fr(hex: &str) -> Fr {
+        let hex = format!("{:0>64}", hex);
+        let mut repr = [0u8; 32];
+        for (i, byte) in repr.iter_mut().rev().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        Fr::from_repr(repr).unwrap()
+    }
+
+    let mut transcript = EvmKeccak256Write::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    transcript.write_point(G1Affine::generator()).unwrap();
+    transcript.write_scalar(Fr::from(3)).unwrap();
+    let first = transcript.squeeze_challenge().get_scalar();
+    transcript.common_scalar(Fr::from(5)).unwrap();
+    let second = transcript.squeeze_challenge().get_scalar();
+    let third = transcript.squeeze_challenge().get_scalar();
+    let proof = transcript.finalize();
+
+    // The generator (1, 2) and the scalar 3, as big-endian words
+    let mut expected = vec![0u8; 96];
+    expected[31] = 1;
+    expected[63] = 2;
+    expected[95] = 3;
+    assert_eq!(proof, expected);
+
+    // keccak256(words), keccak256(previous hash || 5) and
+    // keccak256(previous hash || 0x01), each reduced modulo r
+    let challenges = [
+        fr("d43c5933e4f0b80c25defb26e3c6a4cf3a149d3772d0383d254f4c541f2949a"),
+        fr("f8a940445670c3a52a482c2f3672ccd5db86ef3ec9923beff23932dcf8e264"),
+        fr("e6cb70d1b44eb55128b4305b06a99eb93d61cb33584ecf950c88b734e90e874"),
+    ];
+    assert_eq!([first, second, third], challenges);
+
+    let mut transcript = EvmKeccak256Read::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+    assert_eq!(transcript.read_point().unwrap(), G1Affine::generator());
+    assert_eq!(transcript.read_scalar().unwrap(), Fr::from(3));
+    assert_eq!(transcript.squeeze_challenge().get_scalar(), challenges[0]);
+    transcript.common_scalar(Fr::from(5)).unwrap();
+    assert_eq!(transcript.squeeze_challenge().get_scalar(), challenges[1]);
+    assert_eq!(transcript.squeeze_challenge().get_scalar(), challenges[2]);
+
+    // A single word absorbed before the first challenge is hashed as it is,
+    // even though it is as long as a hash: keccak256(5) reduced modulo r
+    let mut transcript = EvmKeccak256Write::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    transcript.common_scalar(Fr::from(5)).unwrap();
+    assert_eq!(
+        transcript.squeeze_challenge().get_scalar(),
+        fr("36b6384b5eca791c62761152d0c79bb0604c104a5fb6f4eb0703f3154bb3db0")
+    );
+
+    // Points off the curve and non-canonical scalars are rejected.
+    let mut off_curve = proof.clone();
+    off_curve[63] = 3;
+    let mut transcript = EvmKeccak256Read::<_, G1Affine, Challenge255<_>>::init(&off_curve[..]);
+    assert!(transcript.read_point().is_err());
+    let mut transcript = EvmKeccak256Read::<_, G1Affine, Challenge255<_>>::init(&[0xff; 32][..]);
+    assert!(transcript.read_scalar().is_err());
+}
+
+#[test]
+fn test_proof_reader_counts_bytes() {
+    use halo2curves::pasta::{EqAffine, Fp};
+
+    let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(vec![]);
+    transcript.write_scalar(Fp::from(3)).unwrap();
+    let mut proof = transcript.finalize();
+    proof.push(0);
+
+    let (reader, read) = ProofReader::new(&proof);
+    let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(reader);
+    assert_eq!(read.get(), 0);
+    assert_eq!(transcript.read_scalar().unwrap(), Fp::from(3));
+    assert_eq!(read.get(), proof.len() - 1);
+}
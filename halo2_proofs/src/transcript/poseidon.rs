@@ -0,0 +1,391 @@
+//! A transcript hashing with a Poseidon sponge over the scalar field, so that
+//! verifiers can be implemented cheaply in circuits.
+
+use group::ff::{FromUniformBytes, PrimeField};
+use halo2curves::{Coordinates, CurveAffine};
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use super::{Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptWrite};
+
+/// The parameters of a Poseidon permutation over `F`, and the rate of the
+/// sponge built from it.
+///
+/// The permutation applies `full_rounds / 2` full rounds, then
+/// `partial_rounds` partial rounds, then `full_rounds / 2` full rounds. Each
+/// round adds its round constants to the state, raises every element (full
+/// rounds) or only the first one (partial rounds) to the power `alpha`, and
+/// multiplies the state by the MDS matrix, as `state[i] = sum_j mds[i][j] *
+/// state[j]`.
+#[derive(Clone, Debug)]
+pub struct PoseidonSpec<F> {
+    rate: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    alpha: u64,
+    round_constants: Vec<Vec<F>>,
+    mds: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> PoseidonSpec<F> {
+    /// Creates a specification from its parameters. The width of the state
+    /// is the size of the MDS matrix, and its capacity is the width minus
+    /// `rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `full_rounds` is odd, if the MDS matrix is not square, if
+    /// `rate` is not less than its size, or if there are not
+    /// `full_rounds + partial_rounds` rows of round constants of the width of
+    /// the state.
+    pub fn new(
+        rate: usize,
+        full_rounds: usize,
+        partial_rounds: usize,
+        alpha: u64,
+        round_constants: Vec<Vec<F>>,
+        mds: Vec<Vec<F>>,
+    ) -> Self {
+        let width = mds.len();
+        assert_eq!(full_rounds % 2, 0, "the full rounds are split in halves");
+        assert!(
+            mds.iter().all(|row| row.len() == width),
+            "the MDS matrix is not square"
+        );
+        assert!(0 < rate && rate < width, "the capacity must be positive");
+        assert_eq!(round_constants.len(), full_rounds + partial_rounds);
+        assert!(round_constants.iter().all(|row| row.len() == width));
+        PoseidonSpec {
+            rate,
+            full_rounds,
+            partial_rounds,
+            alpha,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// Returns the number of elements of the state.
+    pub fn width(&self) -> usize {
+        self.mds.len()
+    }
+
+    /// Returns the number of elements absorbed per permutation.
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    /// Applies the permutation to `state`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `state` is not of the width of the permutation.
+    pub fn permute(&self, state: &mut [F]) {
+        assert_eq!(state.len(), self.width());
+        let half = self.full_rounds / 2;
+        let mut mixed = vec![F::ZERO; state.len()];
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            for (value, constant) in state.iter_mut().zip(constants.iter()) {
+                *value += constant;
+            }
+            if round < half || round >= half + self.partial_rounds {
+                for value in state.iter_mut() {
+                    *value = value.pow_vartime([self.alpha]);
+                }
+            } else {
+                state[0] = state[0].pow_vartime([self.alpha]);
+            }
+            for (mixed, row) in mixed.iter_mut().zip(self.mds.iter()) {
+                *mixed = row
+                    .iter()
+                    .zip(state.iter())
+                    .fold(F::ZERO, |acc, (m, value)| acc + *m * value);
+            }
+            state.copy_from_slice(&mixed);
+        }
+    }
+}
+
+/// The sponge shared by [`PoseidonRead`] and [`PoseidonWrite`].
+#[derive(Clone, Debug)]
+struct PoseidonSponge<'a, F> {
+    spec: &'a PoseidonSpec<F>,
+    state: Vec<F>,
+    buf: Vec<F>,
+}
+
+impl<'a, F: PrimeField> PoseidonSponge<'a, F> {
+    fn new(spec: &'a PoseidonSpec<F>) -> Self {
+        PoseidonSponge {
+            spec,
+            state: vec![F::ZERO; spec.width()],
+            buf: Vec::with_capacity(spec.rate()),
+        }
+    }
+
+    fn absorb(&mut self, value: F) {
+        self.buf.push(value);
+        if self.buf.len() == self.spec.rate() {
+            self.permute();
+        }
+    }
+
+    fn squeeze(&mut self) -> F {
+        self.buf.push(F::ONE);
+        self.permute();
+        self.state[0]
+    }
+
+    /// Adds the buffered elements to the first elements of the state, and
+    /// applies the permutation.
+    fn permute(&mut self) {
+        for (value, absorbed) in self.state.iter_mut().zip(self.buf.drain(..)) {
+            *value += absorbed;
+        }
+        self.spec.permute(&mut self.state);
+    }
+
+    fn absorb_point<C: CurveAffine<ScalarExt = F>>(&mut self, point: C) -> io::Result<()> {
+        let coords: Coordinates<C> = Option::from(point.coordinates()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "cannot write points at infinity to the transcript",
+            )
+        })?;
+        for coordinate in [coords.x(), coords.y()] {
+            for limb in coordinate.to_repr().as_ref().chunks(16) {
+                let mut repr = F::Repr::default();
+                repr.as_mut()[..limb.len()].copy_from_slice(limb);
+                self.absorb(F::from_repr(repr).unwrap());
+            }
+        }
+        Ok(())
+    }
+
+    fn squeeze_challenge<C: CurveAffine<ScalarExt = F>>(&mut self) -> Challenge255<C>
+    where
+        F: FromUniformBytes<64>,
+    {
+        // The challenge is below the modulus, so the wide reduction of its
+        // representation, zero-extended, leaves it unchanged.
+        let mut wide = [0u8; 64];
+        let challenge = self.squeeze().to_repr();
+        wide[..challenge.as_ref().len()].copy_from_slice(challenge.as_ref());
+        Challenge255::new(&wide)
+    }
+}
+
+/// Poseidon sponge reader, for a proof to be verified in a circuit.
+///
+/// The sponge starts from a state of zeros. Every element absorbed is
+/// buffered, and once `rate` of them are, they are added to the first `rate`
+/// elements of the state, which is then permuted. Squeezing a challenge
+/// buffers a `1` and does the same with the (possibly fewer than `rate`)
+/// buffered elements, and the challenge is the first element of the state.
+///
+/// Scalars are absorbed as they are. A point is absorbed as its affine `x`
+/// and then `y` coordinates, each split into 128-bit limbs from the least
+/// significant one, so the fields must have little-endian representations
+/// of at least 16 bytes, as BN254 and the Pasta curves do. Points and
+/// scalars are read from the proof in the same encoding as in the BLAKE2b
+/// transcripts.
+#[derive(Debug, Clone)]
+pub struct PoseidonRead<'a, R: Read, C: CurveAffine, E: EncodedChallenge<C>> {
+    sponge: PoseidonSponge<'a, C::Scalar>,
+    reader: R,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<'a, R: Read, C: CurveAffine> PoseidonRead<'a, R, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    /// Initializes a transcript hashing with `spec`, given an input buffer.
+    pub fn init(spec: &'a PoseidonSpec<C::Scalar>, reader: R) -> Self {
+        PoseidonRead {
+            sponge: PoseidonSponge::new(spec),
+            reader,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R: Read, C: CurveAffine> TranscriptRead<C, Challenge255<C>>
+    for PoseidonRead<'_, R, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let mut compressed = C::Repr::default();
+        self.reader.read_exact(compressed.as_mut())?;
+        let point: C = Option::from(C::from_bytes(&compressed)).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
+        })?;
+        self.common_point(point)?;
+
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let mut data = <C::Scalar as PrimeField>::Repr::default();
+        self.reader.read_exact(data.as_mut())?;
+        let scalar: C::Scalar = Option::from(C::Scalar::from_repr(data)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "invalid field element encoding in proof",
+            )
+        })?;
+        self.common_scalar(scalar)?;
+
+        Ok(scalar)
+    }
+}
+
+impl<R: Read, C: CurveAffine> Transcript<C, Challenge255<C>>
+    for PoseidonRead<'_, R, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        self.sponge.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.sponge.absorb_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.sponge.absorb(scalar);
+
+        Ok(())
+    }
+}
+
+/// Poseidon sponge writer; see [`PoseidonRead`].
+#[derive(Debug, Clone)]
+pub struct PoseidonWrite<'a, W: Write, C: CurveAffine, E: EncodedChallenge<C>> {
+    sponge: PoseidonSponge<'a, C::Scalar>,
+    writer: W,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<'a, W: Write, C: CurveAffine> PoseidonWrite<'a, W, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    /// Initializes a transcript hashing with `spec`, given an output buffer.
+    pub fn init(spec: &'a PoseidonSpec<C::Scalar>, writer: W) -> Self {
+        PoseidonWrite {
+            sponge: PoseidonSponge::new(spec),
+            writer,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Concludes the interaction and returns the output buffer.
+    pub fn finalize(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, C: CurveAffine> TranscriptWrite<C, Challenge255<C>>
+    for PoseidonWrite<'_, W, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.common_point(point)?;
+        let compressed = point.to_bytes();
+        self.writer.write_all(compressed.as_ref())
+    }
+
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        let data = scalar.to_repr();
+        self.writer.write_all(data.as_ref())
+    }
+}
+
+impl<W: Write, C: CurveAffine> Transcript<C, Challenge255<C>>
+    for PoseidonWrite<'_, W, C, Challenge255<C>>
+where
+    C::Scalar: FromUniformBytes<64>,
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        self.sponge.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.sponge.absorb_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.sponge.absorb(scalar);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_poseidon_vectors() {
+    use group::{ff::Field, prime::PrimeCurveAffine};
+    use halo2curves::bn256::{Fr, G1Affine};
+
+    // A toy specification over a state of three elements: the round
+    // constants count up from 1, and the MDS matrix is the Cauchy matrix
+    // `1 / (i + 3 + j)`.
+    let width = 3;
+    let rounds = 8 + 5;
+    let round_constants = (0..rounds)
+        .map(|round| {
+            (0..width)
+                .map(|i| Fr::from((round * width + i + 1) as u64))
+                .collect()
+        })
+        .collect();
+    let mds = (0..width)
+        .map(|i| {
+            (0..width)
+                .map(|j| Fr::from((i + width + j) as u64).invert().unwrap())
+                .collect()
+        })
+        .collect();
+    let spec = PoseidonSpec::new(2, 8, 5, 5, round_constants, mds);
+
+    // Parses a big-endian hexadecimal scalar.
+    fn fr(hex: &str) -> Fr {
+        let hex = format!("{:0>64}", hex);
+        let mut repr = [0u8; 32];
+        for (i, byte) in repr.iter_mut().rev().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        Fr::from_repr(repr).unwrap()
+    }
+
+    // The generator (1, 2) is absorbed as [1, 0, 2, 0], then 3, then a
+    // challenge is squeezed; then 5 and two challenges.
+    let mut transcript = PoseidonWrite::<_, G1Affine, Challenge255<_>>::init(&spec, vec![]);
+    transcript.write_point(G1Affine::generator()).unwrap();
+    transcript.write_scalar(Fr::from(3)).unwrap();
+    let first = transcript.squeeze_challenge().get_scalar();
+    transcript.common_scalar(Fr::from(5)).unwrap();
+    let second = transcript.squeeze_challenge().get_scalar();
+    let third = transcript.squeeze_challenge().get_scalar();
+    let proof = transcript.finalize();
+
+    let challenges = [
+        fr("3bfdd23d1bcb7a36913e0210317e24947d5a31e52f0133160b33bc1bf79ebb"),
+        fr("1a9da8ba918241e337417ac6e2eb3dbdcbafb1fd84dc900646e1b992e4be60be"),
+        fr("1d25e134c82bcd99799919b29d6bbe058196791ffad0c16fc293e41b25337bac"),
+    ];
+    assert_eq!([first, second, third], challenges);
+
+    let mut transcript = PoseidonRead::<_, G1Affine, Challenge255<_>>::init(&spec, &proof[..]);
+    assert_eq!(transcript.read_point().unwrap(), G1Affine::generator());
+    assert_eq!(transcript.read_scalar().unwrap(), Fr::from(3));
+    assert_eq!(transcript.squeeze_challenge().get_scalar(), challenges[0]);
+    transcript.common_scalar(Fr::from(5)).unwrap();
+    assert_eq!(transcript.squeeze_challenge().get_scalar(), challenges[1]);
+    assert_eq!(transcript.squeeze_challenge().get_scalar(), challenges[2]);
+}
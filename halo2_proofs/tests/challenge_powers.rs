@@ -0,0 +1,154 @@
+//! Checks a random linear combination of 16 values, with the powers of a
+//! challenge from `ConstraintSystem::challenge_powers`.
+
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Challenge, Circuit, Column,
+    ConstraintSystem, Error, Expression, FirstPhase, SecondPhase, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 6;
+const N: u32 = 16;
+
+#[derive(Clone, Copy)]
+struct RlcConfig {
+    q: Selector,
+    values: Column<Advice>,
+    rlc: Column<Advice>,
+    theta: Challenge,
+    theta_n: Challenge,
+}
+
+/// Checks that `rlc` is the sum of `values[i] * theta^(i + 1)`, for the 16
+/// values on the rows from the one `q` is enabled on. With `BAD`, the last
+/// power is left out of the witness.
+#[derive(Clone, Copy)]
+struct Rlc<const BAD: bool>;
+
+impl<const BAD: bool> Circuit<Fr> for Rlc<BAD> {
+    type Config = RlcConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let values = meta.advice_column_in(FirstPhase);
+        let rlc = meta.advice_column_in(SecondPhase);
+        let theta = meta.challenge_usable_after(FirstPhase);
+        let powers = meta.challenge_powers(theta, N);
+        let theta_n = meta.challenge_power(theta, N);
+
+        meta.create_gate("rlc", |meta| {
+            let q = meta.query_selector(q);
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+            let combination = powers
+                .iter()
+                .enumerate()
+                .map(|(i, power)| meta.query_advice(values, Rotation(i as i32)) * power.clone())
+                .fold(Expression::Constant(Fr::ZERO), |acc, term| acc + term);
+            vec![q * (combination - rlc)]
+        });
+
+        RlcConfig {
+            q,
+            values,
+            rlc,
+            theta,
+            theta_n,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let theta = layouter.get_challenge(config.theta);
+        let theta_n = layouter.get_challenge(config.theta_n);
+        theta
+            .zip(theta_n)
+            .assert_if_known(|(theta, theta_n)| theta.pow_vartime([N as u64]) == *theta_n);
+
+        layouter.assign_region(
+            || "rlc",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let mut rlc = Value::known(Fr::ZERO);
+                let mut power = Value::known(Fr::ONE);
+                for i in 0..N as usize {
+                    let value = Fr::from(i as u64 + 1);
+                    region.assign_advice(|| "value", config.values, i, || Value::known(value))?;
+                    power = power * theta;
+                    if !(BAD && i == N as usize - 1) {
+                        rlc = rlc + power * Value::known(value);
+                    }
+                }
+                region.assign_advice(|| "rlc", config.rlc, 0, || rlc)?;
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn challenge_powers() {
+    let mut cs = ConstraintSystem::<Fr>::default();
+    let config = Rlc::<false>::configure(&mut cs);
+    // theta itself, and its powers from 2 to 16, the last being reused.
+    assert_eq!(cs.num_challenges(), N as usize);
+    assert_eq!(config.theta_n.phase(), config.theta.phase());
+    let gate = cs.gates().next().unwrap();
+    assert_eq!(gate.polynomials()[0].degree(), 2);
+    let theta_n: Expression<Fr> = config.theta_n.expr();
+    assert_eq!(theta_n.degree(), 0);
+    assert_eq!(theta_n.node_count(), 1);
+
+    let prover = MockProver::run(K, &Rlc::<false>, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &Rlc::<true>, vec![]).unwrap();
+    assert!(prover.verify().is_err());
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Rlc::<false>).expect("keygen_vk should not fail");
+    assert_eq!(vk.export().challenge_powers.len(), N as usize - 1);
+    let pk = keygen_pk(&params, vk, &Rlc::<false>).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Rlc::<false>],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
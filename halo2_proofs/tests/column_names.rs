@@ -0,0 +1,147 @@
+//! Checks that the names given to columns and selectors when they are
+//! allocated reach the dev tooling and the verifying key export, without
+//! changing the verifying key.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{CircuitGates, MockProver};
+use halo2_proofs::plonk::{
+    keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Expression, Instance, Selector,
+};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::SerdeFormat;
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct CounterConfig {
+    q: Selector,
+    counter: Column<Advice>,
+    start: Column<Instance>,
+}
+
+/// Checks that a counter starts at the instance and increases by one. With
+/// `BAD`, it increases by two. With `NAMED`, the columns and the selector
+/// are named.
+#[derive(Clone, Copy, Default)]
+struct Counter<const NAMED: bool, const BAD: bool>;
+
+impl<const NAMED: bool, const BAD: bool> Circuit<Fr> for Counter<NAMED, BAD> {
+    type Config = CounterConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let (q, counter, start) = if NAMED {
+            (
+                meta.selector_named("q_step"),
+                meta.advice_column_named("rw_counter"),
+                meta.instance_column_named("start"),
+            )
+        } else {
+            (
+                meta.selector(),
+                meta.advice_column(),
+                meta.instance_column(),
+            )
+        };
+        meta.enable_equality(counter);
+        meta.enable_equality(start);
+
+        meta.create_gate("step", |meta| {
+            let q = meta.query_selector(q);
+            let cur = meta.query_advice(counter, Rotation::cur());
+            let next = meta.query_advice(counter, Rotation::next());
+            vec![q * (next - cur - Expression::Constant(Fr::from(1)))]
+        });
+
+        CounterConfig { q, counter, start }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "counter",
+            |mut region| {
+                region.assign_advice_from_instance(
+                    || "start",
+                    config.start,
+                    0,
+                    config.counter,
+                    0,
+                )?;
+                config.q.enable(&mut region, 0)?;
+                let step = if BAD { 2 } else { 1 };
+                region.assign_advice(
+                    || "next",
+                    config.counter,
+                    1,
+                    || Value::known(Fr::from(step)),
+                )?;
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn named_columns() {
+    let mut cs = ConstraintSystem::<Fr>::default();
+    let config = Counter::<true, false>::configure(&mut cs);
+    assert_eq!(cs.column_name(config.counter), Some("rw_counter"));
+    assert_eq!(cs.column_name(config.start), Some("start"));
+    assert_eq!(cs.selector_name(config.q), Some("q_step"));
+
+    // The names are kept by the serialized constraint system.
+    let read = ConstraintSystem::<Fr>::from_bytes(
+        &cs.to_bytes(SerdeFormat::Processed),
+        SerdeFormat::Processed,
+    )
+    .unwrap();
+    assert_eq!(
+        read.general_column_annotations(),
+        cs.general_column_annotations()
+    );
+    assert_eq!(read.selector_name(config.q), Some("q_step"));
+
+    #[cfg(feature = "circuit-params")]
+    let gates = CircuitGates::collect::<Fr, Counter<true, false>>(());
+    #[cfg(not(feature = "circuit-params"))]
+    let gates = CircuitGates::collect::<Fr, Counter<true, false>>();
+    assert!(gates
+        .to_string()
+        .starts_with("step:\n- q_step * (rw_counter@1 - rw_counter@0"));
+
+    let prover = MockProver::run(K, &Counter::<true, false>, vec![vec![Fr::from(0)]]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &Counter::<true, true>, vec![vec![Fr::from(0)]]).unwrap();
+    let failures = prover.verify().unwrap_err();
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].to_string().contains("rw_counter"));
+}
+
+#[test]
+fn names_do_not_change_the_verifying_key() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let anonymous =
+        keygen_vk(&params, &Counter::<false, false>).expect("keygen_vk should not fail");
+    let vk = keygen_vk(&params, &Counter::<true, false>).expect("keygen_vk should not fail");
+    assert_eq!(vk.transcript_repr(), anonymous.transcript_repr());
+
+    let names = vk.export().names;
+    assert_eq!(names.advice_columns, [Some("rw_counter".to_string())]);
+    assert_eq!(names.instance_columns, [Some("start".to_string())]);
+    assert_eq!(names.selectors, [Some("q_step".to_string())]);
+    assert!(names.fixed_columns.iter().all(Option::is_none));
+    assert!(anonymous.export().names.advice_columns[0].is_none());
+}
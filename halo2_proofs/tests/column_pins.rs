@@ -0,0 +1,176 @@
+//! Checks that `ConstraintSystem::pin_columns` catches a column inserted
+//! before pinned columns, and that the pinned columns are exported with the
+//! verifying key.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    keygen_vk, Advice, Any, Circuit, Column, ColumnDrift, ColumnExport, ConstraintSystem, Error,
+    PinnedColumnExport, Selector,
+};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::Rotation;
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct SumConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+}
+
+/// Checks that `a + b = c`, with `a`, `b` and `c` pinned at the first three
+/// advice columns. With `INSERTED`, an advice column is allocated between
+/// `a` and `b`, as a change to `configure` might.
+#[derive(Clone, Copy, Default)]
+struct Sum<const INSERTED: bool>;
+
+impl<const INSERTED: bool> Circuit<Fr> for Sum<INSERTED> {
+    type Config = SumConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        meta.pin_columns(&[("a", 0), ("b", 1), ("c", 2)]);
+        let q = meta.selector();
+        let a = meta.advice_column_named("a");
+        if INSERTED {
+            let _ = meta.advice_column();
+        }
+        let b = meta.advice_column_named("b");
+        let c = meta.advice_column_named("c");
+
+        meta.create_gate("sum", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            vec![q * (a + b - c)]
+        });
+
+        SumConfig { q, a, b, c }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "sum",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                for (column, value) in [(config.a, 2), (config.b, 3), (config.c, 5)] {
+                    region.assign_advice(
+                        || "value",
+                        column,
+                        0,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn configure<const INSERTED: bool>() -> ConstraintSystem<Fr> {
+    let mut meta = ConstraintSystem::default();
+    Sum::<INSERTED>::configure(&mut meta);
+    meta
+}
+
+#[test]
+fn pinned_columns() {
+    let meta = configure::<false>();
+    assert!(meta.column_drift().is_empty());
+    assert_eq!(
+        meta.pinned_column("b"),
+        Some(Column::<Any>::from(meta.advice_queries()[1].0))
+    );
+
+    let prover = MockProver::run(K, &Sum::<false>, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Sum::<false>).expect("keygen_vk should not fail");
+    let pinned = |name: &str, index| PinnedColumnExport {
+        name: name.to_string(),
+        column: ColumnExport::Advice { index, phase: 0 },
+    };
+    assert_eq!(
+        vk.export().names.pinned_columns,
+        vec![pinned("a", 0), pinned("b", 1), pinned("c", 2)]
+    );
+}
+
+#[test]
+fn inserted_column() {
+    let meta = configure::<true>();
+    let drift = meta.column_drift();
+    assert_eq!(
+        drift
+            .iter()
+            .map(|drift| &drift.name[..])
+            .collect::<Vec<_>>(),
+        vec!["b", "c"]
+    );
+    assert_eq!(drift[0].expected, 1);
+    assert_eq!(
+        drift[0].actual.map(|column| column.index()),
+        Some(2),
+        "b was allocated after the inserted column"
+    );
+    assert_eq!(
+        meta.pinned_column("a").map(|column| column.index()),
+        Some(0)
+    );
+    assert_eq!(meta.pinned_column("b"), None);
+
+    let error = meta.check_column_pins().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Columns were not allocated at their pinned indices: \
+         column 'b' is pinned at index 1 but was allocated as Advice column 2; \
+         column 'c' is pinned at index 2 but was allocated as Advice column 3"
+    );
+
+    assert!(matches!(
+        MockProver::run(K, &Sum::<true>, vec![]),
+        Err(Error::ColumnDrift(drift)) if drift.len() == 2
+    ));
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    assert!(matches!(
+        keygen_vk(&params, &Sum::<true>),
+        Err(Error::ColumnDrift(drift)) if drift.len() == 2
+    ));
+}
+
+#[test]
+fn unallocated_pinned_column() {
+    let mut meta = configure::<false>();
+    meta.pin_columns(&[("d", 3)]);
+    assert_eq!(
+        meta.column_drift(),
+        vec![ColumnDrift {
+            name: "d".to_string(),
+            expected: 3,
+            actual: None,
+        }]
+    );
+
+    // Pinning a name again replaces its index.
+    meta.pin_columns(&[("c", 3)]);
+    meta.pin_columns(&[("c", 2), ("d", 3)]);
+    assert_eq!(meta.column_pins().len(), 4);
+    assert_eq!(meta.column_drift().len(), 1);
+}
@@ -0,0 +1,267 @@
+//! Verifies proofs given the commitments to some of their instance columns
+//! rather than their values.
+
+use group::Curve;
+use halo2_proofs::arithmetic::{eval_polynomial, Field};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, instance_evaluations, keygen_pk, keygen_vk, verify_proof,
+    verify_proof_with_committed_instances, Advice, Circuit, Column, ConstraintSystem, Error,
+    Instance, InstanceColumn, Selector, VerifyingKey,
+};
+use halo2_proofs::poly::commitment::{Blind, Params, ParamsProver};
+use halo2_proofs::poly::ipa::commitment::{IPACommitmentScheme, ParamsIPA};
+use halo2_proofs::poly::ipa::multiopen::{ProverIPA, VerifierIPA};
+use halo2_proofs::poly::ipa::strategy::SingleStrategy;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy as KZGSingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    public: Column<Instance>,
+    doubled: Column<Instance>,
+}
+
+/// Exposes a few values in one instance column and their doubles in
+/// another, copying the former and checking the latter with a gate.
+#[derive(Clone, Copy)]
+struct MyCircuit;
+
+const ROWS: u64 = 4;
+
+fn public<F: From<u64>>() -> Vec<F> {
+    (0..ROWS).map(|i| F::from(i + 3)).collect()
+}
+
+fn doubled<F: From<u64>>() -> Vec<F> {
+    (0..ROWS).map(|i| F::from(2 * (i + 3))).collect()
+}
+
+impl<F: group::ff::PrimeField> Circuit<F> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let public = meta.instance_column();
+        let doubled = meta.instance_column();
+        meta.enable_equality(a);
+        meta.enable_equality(public);
+
+        meta.create_gate("doubled = 2a", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let doubled = meta.query_instance(doubled, Rotation::cur());
+            vec![q * (doubled - a.clone() - a)]
+        });
+
+        MyConfig {
+            q,
+            a,
+            public,
+            doubled,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let cells = layouter.assign_region(
+            || "values",
+            |mut region| {
+                public::<F>()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(row, value)| {
+                        config.q.enable(&mut region, row)?;
+                        region.assign_advice(|| "a", config.a, row, || Value::known(value))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        for (row, cell) in cells.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), config.public, row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Commits to an instance column as the prover does.
+fn commit(params: &ParamsIPA<EqAffine>, vk: &VerifyingKey<EqAffine>, values: &[Fp]) -> EqAffine {
+    let mut values = values.to_vec();
+    values.resize(params.n() as usize, Fp::ZERO);
+    let poly = vk.get_domain().lagrange_from_vec(values);
+    params.commit_lagrange(&poly, Blind::default()).to_affine()
+}
+
+#[test]
+fn committed_instances() {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let public = public::<Fp>();
+    let doubled = doubled::<Fp>();
+    let instances: &[&[&[Fp]]] = &[&[&public[..], &doubled[..]]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |instance: &[InstanceColumn<'_, EqAffine>]| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof_with_committed_instances::<_, VerifierIPA<_>, _, _, _>(
+            &params,
+            vk,
+            SingleStrategy::new(&params),
+            &[instance],
+            &mut transcript,
+        )
+    };
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        &params,
+        vk,
+        SingleStrategy::new(&params),
+        instances,
+        &mut transcript,
+    )
+    .is_ok());
+
+    // Any column can be given by its commitment instead of its values.
+    let public_commitment = commit(&params, vk, &public);
+    let doubled_commitment = commit(&params, vk, &doubled);
+    assert!(verify(&[
+        InstanceColumn::from(&public),
+        InstanceColumn::from(&doubled)
+    ])
+    .is_ok());
+    assert!(verify(&[
+        InstanceColumn::Committed(public_commitment),
+        InstanceColumn::Values(&doubled)
+    ])
+    .is_ok());
+    assert!(verify(&[
+        InstanceColumn::Committed(public_commitment),
+        InstanceColumn::Committed(doubled_commitment)
+    ])
+    .is_ok());
+
+    // A commitment to other values fails verification.
+    let mut other = doubled.clone();
+    other[0] += Fp::ONE;
+    assert!(verify(&[
+        InstanceColumn::Values(&public),
+        InstanceColumn::Committed(commit(&params, vk, &other))
+    ])
+    .is_err());
+    assert!(verify(&[
+        InstanceColumn::Committed(doubled_commitment),
+        InstanceColumn::Committed(public_commitment)
+    ])
+    .is_err());
+}
+
+#[test]
+fn committed_instances_need_instance_queries() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let public = public::<Fr>();
+    let doubled = doubled::<Fr>();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        &[&[&public[..], &doubled[..]]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |doubled: InstanceColumn<'_, _>| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof_with_committed_instances::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            KZGSingleStrategy::new(&params),
+            &[&[InstanceColumn::Values(&public), doubled]],
+            &mut transcript,
+        )
+    };
+    assert!(verify(InstanceColumn::Values(&doubled)).is_ok());
+    let commitment = params
+        .commit_lagrange(
+            &pk.get_vk().get_domain().lagrange_from_vec({
+                let mut values = doubled.clone();
+                values.resize(params.n() as usize, Fr::ZERO);
+                values
+            }),
+            Blind::default(),
+        )
+        .to_affine();
+    assert!(matches!(
+        verify(InstanceColumn::Committed(commitment)),
+        Err(Error::InvalidInstances)
+    ));
+}
+
+#[test]
+fn instance_evaluations_match_interpolation() {
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let domain = vk.get_domain();
+
+    let public = public::<Fp>();
+    let doubled = doubled::<Fp>();
+    let x = Fp::random(OsRng);
+    let evals = instance_evaluations(&vk, x, &[&[&public[..], &doubled[..]]]);
+    assert_eq!(evals.len(), 1);
+
+    let columns = [&public, &doubled];
+    let expected: Vec<_> = vk
+        .cs()
+        .instance_queries()
+        .iter()
+        .map(|(column, rotation)| {
+            let mut values = columns[column.index()].clone();
+            values.resize(params.n() as usize, Fp::ZERO);
+            let poly = domain.lagrange_to_coeff(domain.lagrange_from_vec(values));
+            eval_polynomial(&poly, domain.rotate_omega(x, *rotation))
+        })
+        .collect();
+    assert_eq!(evals[0], expected);
+}
@@ -0,0 +1,262 @@
+//! Checks that two circuits configured on their own can be proven side by
+//! side with `ComposedCircuit`, sharing their instance column.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ColumnRemap,
+    ComposedCircuit, ConstraintSystem, Error, Instance, RemapConfig, Selector, TableColumn,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct SquareConfig {
+    q: Selector,
+    x: Column<Advice>,
+    y: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Exposes the square of `x` on the first row of the instance column.
+#[derive(Clone, Copy, Default)]
+struct Square {
+    x: u64,
+}
+
+impl Circuit<Fr> for Square {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let x = meta.advice_column();
+        let y = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(y);
+        meta.enable_equality(instance);
+
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(q);
+            let x = meta.query_advice(x, Rotation::cur());
+            let y = meta.query_advice(y, Rotation::cur());
+            vec![q * (x.clone() * x - y)]
+        });
+
+        SquareConfig { q, x, y, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let y = layouter.assign_region(
+            || "square",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let x = Value::known(Fr::from(self.x));
+                region.assign_advice(|| "x", config.x, 0, || x)?;
+                region.assign_advice(|| "y", config.y, 0, || x * x)
+            },
+        )?;
+        layouter.constrain_instance(y.cell(), config.instance, 0)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RangeConfig {
+    q: Selector,
+    value: Column<Advice>,
+    table: TableColumn,
+    instance: Column<Instance>,
+}
+
+impl RemapConfig for RangeConfig {
+    fn remap(self, remap: &ColumnRemap) -> Self {
+        RangeConfig {
+            q: self.q.remap(remap),
+            value: self.value.remap(remap),
+            table: self.table.remap(remap),
+            instance: self.instance.remap(remap),
+        }
+    }
+}
+
+/// Exposes a value below 8 on the second row of the instance column.
+#[derive(Clone, Copy, Default)]
+struct Range {
+    value: u64,
+}
+
+impl Circuit<Fr> for Range {
+    type Config = RangeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.complex_selector();
+        let value = meta.advice_column();
+        let table = meta.lookup_table_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(value);
+        meta.enable_equality(instance);
+
+        meta.lookup("range", |meta| {
+            let q = meta.query_selector(q);
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![(q * value, table)]
+        });
+
+        RangeConfig {
+            q,
+            value,
+            table,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range",
+            |mut table| {
+                for value in 0..8 {
+                    table.assign_cell(
+                        || "value",
+                        config.table,
+                        value as usize,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        let value = layouter.assign_region(
+            || "value",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(
+                    || "value",
+                    config.value,
+                    0,
+                    || Value::known(Fr::from(self.value)),
+                )
+            },
+        )?;
+        layouter.constrain_instance(value.cell(), config.instance, 1)
+    }
+}
+
+fn composed(x: u64, value: u64) -> ComposedCircuit<Square, Range> {
+    ComposedCircuit::new(Square { x }, Range { value })
+}
+
+#[test]
+fn remap() {
+    let merge = || {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        let square = Square::configure(&mut cs);
+        let mut other = ConstraintSystem::<Fr>::default();
+        let range = Range::configure(&mut other);
+        let remap = cs.merge(other);
+        (cs, square, range, remap)
+    };
+    let (cs, square, range, remap) = merge();
+    assert_eq!(merge().3, remap);
+
+    // The columns of `Range` come after those of `Square`, except the
+    // instance column they share.
+    let range = range.remap(&remap);
+    assert_eq!(range.value.index(), 2);
+    assert_eq!(range.instance, square.instance);
+    assert_eq!(cs.num_advice_columns(), 3);
+    assert_eq!(cs.num_instance_columns(), 1);
+    assert_eq!(cs.num_selectors(), 2);
+    assert_eq!(cs.lookups().len(), 1);
+    assert_eq!(
+        cs.permutation_columns(),
+        &[square.y.into(), square.instance.into(), range.value.into()]
+    );
+
+    // The composed circuit is configured the same way.
+    let mut composed_cs = ConstraintSystem::<Fr>::default();
+    let (_, composed_range) = ComposedCircuit::<Square, Range>::configure(&mut composed_cs);
+    assert_eq!(composed_range.value, range.value);
+    assert_eq!(
+        format!("{:?}", composed_cs.pinned()),
+        format!("{:?}", cs.pinned())
+    );
+}
+
+#[test]
+fn mock_prover() {
+    let instances = vec![vec![Fr::from(9), Fr::from(5)]];
+    let prover = MockProver::run(K, &composed(3, 5), instances).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // 12 is out of the range of the second circuit.
+    let instances = vec![vec![Fr::from(9), Fr::from(12)]];
+    let prover = MockProver::run(K, &composed(3, 12), instances).unwrap();
+    match &prover.verify().unwrap_err()[..] {
+        [VerifyFailure::Lookup { name, .. }] => assert_eq!(name, "range"),
+        failures => panic!("unexpected failures {:?}", failures),
+    }
+}
+
+#[test]
+fn prove_and_verify() {
+    let circuit = composed(3, 5);
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let again = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    assert_eq!(vk.transcript_repr(), again.transcript_repr());
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let instances = [Fr::from(9), Fr::from(5)];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&instances]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[&instances]],
+        &mut transcript,
+    )
+    .is_ok());
+}
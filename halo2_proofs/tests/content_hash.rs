@@ -0,0 +1,116 @@
+//! Checks that `ConstraintSystem::content_hash` changes with the constraints
+//! of a circuit, and not with what is not part of them.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector,
+};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::Rotation;
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+/// How the circuit differs from the original one.
+const ORIGINAL: u8 = 0;
+/// The region and a column are renamed, and the gate is built differently.
+const REFACTORED: u8 = 1;
+/// The gate checks another relation.
+const CHANGED: u8 = 2;
+
+#[derive(Clone, Copy)]
+struct SquareConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks that `b = a^2`, or `b = a^2 + a` if `CHANGED`.
+#[derive(Clone, Copy, Default)]
+struct Square<const VARIANT: u8>;
+
+impl<const VARIANT: u8> Circuit<Fr> for Square<VARIANT> {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = if VARIANT == REFACTORED {
+            meta.advice_column_named("a")
+        } else {
+            meta.advice_column()
+        };
+        let b = meta.advice_column();
+
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let square = match VARIANT {
+                REFACTORED => [a.clone(), a].into_iter().product::<Expression<_>>(),
+                CHANGED => a.clone() * a.clone() + a,
+                _ => a.clone() * a,
+            };
+            vec![q * (square - b)]
+        });
+
+        SquareConfig { q, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let name = if VARIANT == REFACTORED {
+            "squaring"
+        } else {
+            "square"
+        };
+        layouter.assign_region(
+            || name,
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let a = Value::known(Fr::from(3));
+                region.assign_advice(|| "a", config.a, 0, || a)?;
+                region.assign_advice(|| "b", config.b, 0, || a * a)?;
+                Ok(())
+            },
+        )
+    }
+}
+
+fn content_hash<const VARIANT: u8>() -> [u8; 32] {
+    let mut cs = ConstraintSystem::default();
+    Square::<VARIANT>::configure(&mut cs);
+    cs.content_hash()
+}
+
+#[test]
+fn content_hash_of_constraint_systems() {
+    let original = content_hash::<ORIGINAL>();
+    assert_eq!(content_hash::<ORIGINAL>(), original);
+    assert_eq!(content_hash::<REFACTORED>(), original);
+    assert_ne!(content_hash::<CHANGED>(), original);
+}
+
+#[test]
+fn content_hash_of_verifying_keys() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Square::<ORIGINAL>).expect("keygen_vk should not fail");
+    let refactored = keygen_vk(&params, &Square::<REFACTORED>).expect("keygen_vk should not fail");
+    let changed = keygen_vk(&params, &Square::<CHANGED>).expect("keygen_vk should not fail");
+
+    assert_eq!(vk.content_hash(), vk.cs().content_hash());
+    assert_eq!(refactored.content_hash(), vk.content_hash());
+    assert_ne!(changed.content_hash(), vk.content_hash());
+    // The selectors of the key are compressed.
+    assert_ne!(vk.content_hash(), content_hash::<ORIGINAL>());
+}
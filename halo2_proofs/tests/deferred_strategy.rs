@@ -0,0 +1,145 @@
+//! Verifies proofs with the final pairing check deferred, and checks the
+//! accumulated MSMs of several proofs at once.
+
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Instance, ProofComponentKind, ProofLayout, ProvingKey, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::msm::AccumulatedMSM;
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::DeferredStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Squares a value and exposes its square.
+#[derive(Clone, Copy)]
+struct MyCircuit(u64);
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(b);
+        meta.enable_equality(instance);
+
+        meta.create_gate("b = a^2", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (b - a.clone() * a)]
+        });
+
+        MyConfig { q, a, b, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let b = layouter.assign_region(
+            || "square",
+            |mut region| {
+                let a = Fr::from(self.0);
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || Value::known(a))?;
+                region.assign_advice(|| "b", config.b, 0, || Value::known(a * a))
+            },
+        )?;
+        layouter.constrain_instance(b.cell(), config.instance, 0)
+    }
+}
+
+fn prove(params: &ParamsKZG<Bn256>, pk: &ProvingKey<G1Affine>, value: u64) -> Vec<u8> {
+    let instance = [Fr::from(value * value)];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        params,
+        pk,
+        &[MyCircuit(value)],
+        &[&[&instance[..]]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+#[test]
+fn deferred_strategy() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, OsRng);
+    let vk = keygen_vk(&params, &MyCircuit(0)).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit(0)).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let accumulate = |proof: &[u8], value: u64| -> AccumulatedMSM<Bn256> {
+        let instance = [Fr::from(value * value)];
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            vk,
+            DeferredStrategy::new(&params),
+            &[&[&instance[..]]],
+            &mut transcript,
+        )
+        .expect("everything but the pairing should be checked")
+    };
+
+    let first = accumulate(&prove(&params, &pk, 3), 3);
+    let second = accumulate(&prove(&params, &pk, 5), 5);
+    assert!(first.check(&params));
+    assert!(second.check(&params));
+    assert!(!first.left().is_empty() && !first.right().is_empty());
+    assert_ne!(first.generator(), Fr::ZERO);
+
+    let mut merged = first.clone();
+    merged.merge(&second, Fr::random(OsRng));
+    assert_eq!(
+        merged.left().len() + merged.right().len(),
+        first.left().len() + first.right().len() + second.left().len() + second.right().len()
+    );
+    assert!(merged.check(&params));
+
+    // Another evaluation is read without error, and only fails the pairing.
+    let layout = ProofLayout::parse::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<_>>(vk, 1);
+    let evaluations = layout
+        .components()
+        .iter()
+        .find(|component| component.kind == ProofComponentKind::Evaluations)
+        .unwrap();
+    let mut corrupted = prove(&params, &pk, 5);
+    corrupted[evaluations.offset] ^= 1;
+    let corrupted = accumulate(&corrupted, 5);
+    assert!(!corrupted.check(&params));
+
+    let mut merged = first;
+    merged.merge(&corrupted, Fr::random(OsRng));
+    assert!(!merged.check(&params));
+}
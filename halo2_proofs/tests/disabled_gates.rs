@@ -0,0 +1,119 @@
+//! Checks that disabled gates are ignored by the mock prover, and refused by
+//! keygen unless explicitly allowed.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector,
+};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::Rotation;
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct GatesConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks that `a` is one and that `b` is two, in two gates. `b` is assigned
+/// three. With `DISABLE`, configure disables the gate on `b`, and with
+/// `ALLOW`, it also lets keygen use the constraint system.
+#[derive(Clone, Copy, Default)]
+struct Gates<const DISABLE: bool, const ALLOW: bool>;
+
+impl<const DISABLE: bool, const ALLOW: bool> Circuit<Fr> for Gates<DISABLE, ALLOW> {
+    type Config = GatesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        meta.create_gate("a is one", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            vec![q * (a - Expression::Constant(Fr::from(1)))]
+        });
+        meta.create_gate("b is two", |meta| {
+            let q = meta.query_selector(q);
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (b - Expression::Constant(Fr::from(2)))]
+        });
+
+        if DISABLE {
+            meta.disable_gate("b is two");
+        }
+        if ALLOW {
+            meta.allow_disabled_gates();
+        }
+
+        GatesConfig { q, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(1)))?;
+                region.assign_advice(|| "b", config.b, 0, || Value::known(Fr::from(3)))?;
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn verify_without_gates() {
+    let prover = MockProver::run(K, &Gates::<false, false>, vec![]).unwrap();
+    match &prover.verify().unwrap_err()[..] {
+        [VerifyFailure::ConstraintNotSatisfied { constraint, .. }] => {
+            assert_eq!(
+                constraint.to_string(),
+                "Constraint 0 in gate 1 ('b is two')"
+            )
+        }
+        failures => panic!("unexpected failures {:?}", failures),
+    }
+    assert!(prover.verify_without_gates(&["a is one"]).is_err());
+    assert_eq!(prover.verify_without_gates(&["b is two"]), Ok(()));
+
+    let prover = MockProver::run(K, &Gates::<true, false>, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "there is no gate named c is three")]
+fn verify_without_unknown_gate() {
+    let prover = MockProver::run(K, &Gates::<false, false>, vec![]).unwrap();
+    let _ = prover.verify_without_gates(&["c is three"]);
+}
+
+#[test]
+fn keygen_with_disabled_gates() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    assert!(keygen_vk(&params, &Gates::<false, false>).is_ok());
+    match keygen_vk(&params, &Gates::<true, false>) {
+        Err(Error::DisabledGates(gates)) => assert_eq!(gates, ["b is two"]),
+        _ => panic!("keygen should refuse disabled gates"),
+    }
+
+    let vk = keygen_vk(&params, &Gates::<true, true>).expect("keygen_vk should not fail");
+    assert_eq!(vk.cs().disabled_gates(), ["b is two"]);
+}
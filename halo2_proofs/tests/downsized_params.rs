@@ -0,0 +1,148 @@
+//! Checks that proofs made with parameters downsized from a larger setup
+//! verify with parameters set up at the smaller size from the same secret,
+//! and the other way around.
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Selector,
+};
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::ipa::commitment::ParamsIPA;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use halo2curves::pasta::EqAffine;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, SeedableRng};
+
+/// The size of the shipped setup.
+const LARGE_K: u32 = 10;
+const K: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct SquareConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks that `b = a^2`.
+#[derive(Clone, Copy, Default)]
+struct Square;
+
+impl Circuit<Fr> for Square {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (a.clone() * a - b)]
+        });
+
+        SquareConfig { q, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "square",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let a = Value::known(Fr::from(3));
+                region.assign_advice(|| "a", config.a, 0, || a)?;
+                region.assign_advice(|| "b", config.b, 0, || a * a)?;
+                Ok(())
+            },
+        )
+    }
+}
+
+fn to_bytes<'params, P: Params<'params, C>, C: CurveAffine>(params: &P) -> Vec<u8> {
+    let mut bytes = vec![];
+    params.write(&mut bytes).unwrap();
+    bytes
+}
+
+fn prove(params: &ParamsKZG<Bn256>) -> Vec<u8> {
+    let vk = keygen_vk(params, &Square).expect("keygen_vk should not fail");
+    let pk = keygen_pk(params, vk, &Square).expect("keygen_pk should not fail");
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        params,
+        &pk,
+        &[Square],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+fn verify(params: &ParamsKZG<Bn256>, proof: &[u8]) -> bool {
+    let vk = keygen_vk(params, &Square).expect("keygen_vk should not fail");
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+    verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        &vk,
+        SingleStrategy::new(params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok()
+}
+
+#[test]
+fn downsized_kzg_params() {
+    let large = ParamsKZG::<Bn256>::setup(LARGE_K, ChaCha20Rng::seed_from_u64(7));
+    let downsized = large.downsized(K);
+    let fresh = ParamsKZG::<Bn256>::setup(K, ChaCha20Rng::seed_from_u64(7));
+    assert_eq!(downsized.k(), K);
+    assert_eq!(to_bytes(&downsized), to_bytes(&fresh));
+    // The large parameters are left as they were.
+    assert_eq!(large.k(), LARGE_K);
+
+    let proof = prove(&downsized);
+    assert!(verify(&downsized, &proof));
+    assert!(verify(&fresh, &proof));
+    assert!(verify(&downsized, &prove(&fresh)));
+
+    // A different secret gives different parameters, which do not verify the
+    // proof.
+    let other = ParamsKZG::<Bn256>::setup(K, ChaCha20Rng::seed_from_u64(8));
+    assert!(!verify(&other, &proof));
+}
+
+#[test]
+fn downsized_ipa_params() {
+    let large = ParamsIPA::<EqAffine>::new(LARGE_K);
+    let fresh = ParamsIPA::<EqAffine>::new(K);
+    assert_eq!(to_bytes(&large.downsized(K)), to_bytes(&fresh));
+
+    let mut in_place = large;
+    in_place.downsize(K);
+    assert_eq!(to_bytes(&in_place), to_bytes(&fresh));
+}
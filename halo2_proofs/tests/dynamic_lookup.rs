@@ -0,0 +1,223 @@
+//! Checks lookups into tables of advice columns, with a memory whose reads
+//! are looked up in the writes assigned during synthesis.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Fixed, LookupBackend, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct MemoryConfig {
+    q_write: Column<Fixed>,
+    addr: Column<Advice>,
+    value: Column<Advice>,
+    q_read: Selector,
+    read_addr: Column<Advice>,
+    read_value: Column<Advice>,
+}
+
+/// Writes values at addresses of a memory, and reads some back. Each read is
+/// looked up in the writes, so it must return a value written at its
+/// address. The `LOGUP` variant proves the lookup with
+/// [`LookupBackend::LogUp`].
+#[derive(Clone, Default)]
+struct Memory<const LOGUP: bool> {
+    writes: Vec<(u64, u64)>,
+    reads: Vec<(u64, u64)>,
+}
+
+impl<const LOGUP: bool> Circuit<Fr> for Memory<LOGUP> {
+    type Config = MemoryConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        if LOGUP {
+            meta.set_lookup_backend(LookupBackend::LogUp);
+        }
+        let q_write = meta.fixed_column();
+        let addr = meta.advice_column();
+        let value = meta.advice_column();
+        let q_read = meta.complex_selector();
+        let read_addr = meta.advice_column();
+        let read_value = meta.advice_column();
+
+        meta.lookup_any("memory read", |meta| {
+            let q_write = meta.query_fixed(q_write, Rotation::cur());
+            let addr = meta.query_advice(addr, Rotation::cur());
+            let value = meta.query_advice(value, Rotation::cur());
+            let q_read = meta.query_selector(q_read);
+            let read_addr = meta.query_advice(read_addr, Rotation::cur());
+            let read_value = meta.query_advice(read_value, Rotation::cur());
+            vec![
+                (q_read.clone() * read_addr, q_write.clone() * addr),
+                (q_read * read_value, q_write * value),
+            ]
+        });
+
+        MemoryConfig {
+            q_write,
+            addr,
+            value,
+            q_read,
+            read_addr,
+            read_value,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "writes",
+            |mut region| {
+                for (offset, &(addr, value)) in self.writes.iter().enumerate() {
+                    region.assign_fixed(
+                        || "q_write",
+                        config.q_write,
+                        offset,
+                        || Value::known(Fr::from(1)),
+                    )?;
+                    region.assign_advice(
+                        || "addr",
+                        config.addr,
+                        offset,
+                        || Value::known(Fr::from(addr)),
+                    )?;
+                    region.assign_advice(
+                        || "value",
+                        config.value,
+                        offset,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "reads",
+            |mut region| {
+                for (offset, &(addr, value)) in self.reads.iter().enumerate() {
+                    config.q_read.enable(&mut region, offset)?;
+                    region.assign_advice(
+                        || "read addr",
+                        config.read_addr,
+                        offset,
+                        || Value::known(Fr::from(addr)),
+                    )?;
+                    region.assign_advice(
+                        || "read value",
+                        config.read_value,
+                        offset,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn memory<const LOGUP: bool>(reads: &[(u64, u64)]) -> Memory<LOGUP> {
+    Memory {
+        writes: vec![(1, 10), (2, 20), (3, 30), (4, 40)],
+        reads: reads.to_vec(),
+    }
+}
+
+fn prove_and_verify<const LOGUP: bool>(circuit: Memory<LOGUP>) {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+#[test]
+fn reads_of_written_values() {
+    let reads = [(3, 30), (1, 10), (3, 30), (4, 40)];
+    let prover = MockProver::run(K, &memory::<false>(&reads), vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    prove_and_verify(memory::<false>(&reads));
+    prove_and_verify(memory::<true>(&reads));
+}
+
+#[test]
+fn read_of_unwritten_value() {
+    // The value written at address 2 is 20.
+    let reads = [(1, 10), (2, 21)];
+    let failures = [
+        MockProver::run(K, &memory::<false>(&reads), vec![])
+            .unwrap()
+            .verify(),
+        MockProver::run(K, &memory::<true>(&reads), vec![])
+            .unwrap()
+            .verify(),
+    ];
+    for failures in failures {
+        match &failures.unwrap_err()[..] {
+            [VerifyFailure::Lookup {
+                name,
+                lookup_index: 0,
+                ..
+            }] => assert_eq!(name, "memory read"),
+            failures => panic!("unexpected failures {:?}", failures),
+        }
+    }
+}
+
+#[test]
+fn rows_outside_of_regions_are_in_the_table() {
+    // The table expressions are zero on every row without a write, so a row
+    // of zeros is in the table even though nothing was written at address 0.
+    let reads = [(0, 0), (2, 20)];
+    let prover = MockProver::run(K, &memory::<false>(&reads), vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    prove_and_verify(memory::<true>(&reads));
+
+    let reads = [(0, 1)];
+    let prover = MockProver::run(K, &memory::<false>(&reads), vec![]).unwrap();
+    assert!(prover.verify().is_err());
+}
@@ -0,0 +1,219 @@
+//! Proves and verifies with the transcript following the byte layout of EVM
+//! verifiers.
+
+use group::ff::PrimeField;
+use group::GroupEncoding;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Instance, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bWrite, Challenge255, EvmKeccak256Read, EvmKeccak256Write, PointEncoding,
+    TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2curves::CurveAffine;
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Squares a few values, exposing the last square.
+#[derive(Clone, Copy)]
+struct MyCircuit;
+
+const ROWS: u64 = 4;
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(b);
+        meta.enable_equality(instance);
+
+        meta.create_gate("b = a^2", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (b - a.clone() * a)]
+        });
+
+        MyConfig { q, a, b, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let last = layouter.assign_region(
+            || "squares",
+            |mut region| {
+                let mut last = None;
+                for row in 0..ROWS {
+                    let offset = row as usize;
+                    let a = Fr::from(row + 2);
+                    config.q.enable(&mut region, offset)?;
+                    region.assign_advice(|| "a", config.a, offset, || Value::known(a))?;
+                    last = Some(region.assign_advice(
+                        || "b",
+                        config.b,
+                        offset,
+                        || Value::known(a * a),
+                    )?);
+                }
+                Ok(last.unwrap())
+            },
+        )?;
+        layouter.constrain_instance(last.cell(), config.instance, 0)
+    }
+}
+
+/// Returns the big-endian word of a field element.
+fn word<F: PrimeField>(value: &F) -> Vec<u8> {
+    value.to_repr().as_ref().iter().rev().copied().collect()
+}
+
+#[test]
+fn evm_transcript() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, ChaCha20Rng::from_seed([1; 32]));
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let instance = [Fr::from((ROWS + 1) * (ROWS + 1))];
+    let instances: &[&[&[Fr]]] = &[&[&instance[..]]];
+
+    let mut transcript = EvmKeccak256Write::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        ChaCha20Rng::from_seed([2; 32]),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |proof: &[u8]| {
+        let mut transcript = EvmKeccak256Read::<_, _, Challenge255<_>>::init(proof);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            instances,
+            &mut transcript,
+        )
+    };
+    assert!(verify(&proof).is_ok());
+    let mut tampered = proof.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(verify(&tampered).is_err());
+
+    // The first advice commitment does not depend on the transcript, so the
+    // proof starts with that of a BLAKE2b proof made with the same
+    // randomness, as uncompressed big-endian coordinates.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        ChaCha20Rng::from_seed([2; 32]),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let blake2b_proof = transcript.finalize();
+    let mut compressed = <G1Affine as GroupEncoding>::Repr::default();
+    compressed
+        .as_mut()
+        .copy_from_slice(&blake2b_proof[..compressed.as_ref().len()]);
+    let first = G1Affine::from_bytes(&compressed).unwrap();
+    let coordinates = first.coordinates().unwrap();
+    let expected = [word(coordinates.x()), word(coordinates.y())].concat();
+    assert_eq!(&proof[..64], &expected[..]);
+}
+
+#[test]
+fn evm_transcript_compressed() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, ChaCha20Rng::from_seed([1; 32]));
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let instance = [Fr::from((ROWS + 1) * (ROWS + 1))];
+    let instances: &[&[&[Fr]]] = &[&[&instance[..]]];
+
+    let prove = |encoding| {
+        let mut transcript =
+            EvmKeccak256Write::<_, _, Challenge255<_>>::init_with_encoding(vec![], encoding);
+        create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit],
+            instances,
+            ChaCha20Rng::from_seed([2; 32]),
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+    let verify = |proof: &[u8], encoding| {
+        let mut transcript =
+            EvmKeccak256Read::<_, _, Challenge255<_>>::init_with_encoding(proof, encoding);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            instances,
+            &mut transcript,
+        )
+    };
+    let compressed = prove(PointEncoding::Compressed);
+    let uncompressed = prove(PointEncoding::Uncompressed);
+    assert!(verify(&compressed, PointEncoding::Compressed).is_ok());
+    assert!(verify(&uncompressed, PointEncoding::Uncompressed).is_ok());
+
+    // Each point takes up 32 bytes fewer, as in a BLAKE2b proof.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        ChaCha20Rng::from_seed([2; 32]),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    assert_eq!(compressed.len(), transcript.finalize().len());
+    let saved = uncompressed.len() - compressed.len();
+    assert!(saved > 0 && saved % 32 == 0);
+    assert_eq!(
+        PointEncoding::Uncompressed.len::<G1Affine>() - PointEncoding::Compressed.len::<G1Affine>(),
+        32
+    );
+
+    // A proof read with the other encoding is rejected without panicking.
+    assert!(verify(&compressed, PointEncoding::Uncompressed).is_err());
+    assert!(verify(&uncompressed, PointEncoding::Compressed).is_err());
+}
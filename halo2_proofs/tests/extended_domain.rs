@@ -0,0 +1,174 @@
+//! Checks that a circuit whose degree was raised with
+//! `ConstraintSystem::set_minimum_degree` can be proven on the smaller
+//! extended domain its constraints need, and that a domain too small for
+//! them is rejected.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_domain, create_domain_with_extended_k, create_proof, keygen_pk, keygen_vk, verify_proof,
+    Advice, Circuit, Column, ConstraintSystem, DegreeSource, Error, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct SquareConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks that `b` is the square of `a` with a gate of degree 3, in a
+/// circuit whose degree is raised to 9. Unless `EXTENDED_K` is zero, the
+/// extended domain has `2^EXTENDED_K` rows.
+#[derive(Clone, Copy)]
+struct Square<const EXTENDED_K: u32> {
+    a: u64,
+}
+
+impl<const EXTENDED_K: u32> Circuit<Fr> for Square<EXTENDED_K> {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (a.clone() * a - b)]
+        });
+        meta.set_minimum_degree(9);
+        if EXTENDED_K > 0 {
+            meta.set_extended_k(EXTENDED_K);
+        }
+
+        SquareConfig { q, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "square",
+            |mut region| {
+                let a = Fr::from(self.a);
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || Value::known(a))?;
+                region.assign_advice(|| "b", config.b, 0, || Value::known(a * a))?;
+                Ok(())
+            },
+        )
+    }
+}
+
+fn domain<const EXTENDED_K: u32>() -> (u32, ConstraintSystem<Fr>) {
+    let (domain, cs, _) = create_domain::<G1Affine, Square<EXTENDED_K>>(
+        K,
+        #[cfg(feature = "circuit-params")]
+        (),
+    );
+    (domain.extended_k(), cs)
+}
+
+#[test]
+fn smaller_extended_domain() {
+    // The degree of the circuit asks for 8 times as many rows, but the gate
+    // only needs twice as many.
+    let (extended_k, cs) = domain::<0>();
+    assert_eq!(extended_k, K + 3);
+    assert_eq!(
+        cs.highest_degree_constraint(),
+        Some((
+            3,
+            DegreeSource::Gate {
+                gate: 0,
+                name: "square".to_string()
+            }
+        ))
+    );
+    assert_eq!(cs.minimum_extended_k(K), K + 1);
+    assert_eq!(domain::<{ K + 1 }>().0, K + 1);
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Square::<{ K + 1 }> { a: 0 }).expect("keygen_vk should not fail");
+    assert_eq!(vk.get_domain().extended_k(), K + 1);
+    let pk =
+        keygen_pk(&params, vk, &Square::<{ K + 1 }> { a: 0 }).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Square::<{ K + 1 }> { a: 3 }],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+#[test]
+fn extended_domain_too_small() {
+    let error = match create_domain_with_extended_k::<G1Affine, Square<0>>(
+        K,
+        K,
+        #[cfg(feature = "circuit-params")]
+        (),
+    ) {
+        Err(error) => error,
+        Ok(_) => panic!("an extended domain of 2^K rows should be too small"),
+    };
+    assert!(
+        error.to_string().contains("gate 'square' has degree 3"),
+        "{}",
+        error
+    );
+    assert!(matches!(
+        error,
+        Error::ExtendedDomainTooSmall {
+            extended_k: K,
+            minimum,
+            degree: 3,
+            constraint: DegreeSource::Gate { gate: 0, .. },
+        } if minimum == K + 1
+    ));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    assert!(matches!(
+        keygen_vk(&params, &Square::<K> { a: 0 }),
+        Err(Error::ExtendedDomainTooSmall { .. })
+    ));
+}
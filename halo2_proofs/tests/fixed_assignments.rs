@@ -0,0 +1,186 @@
+//! Checks that `keygen_vk_from_params` generates the verifying key of a
+//! parameterized circuit from its parameters alone.
+#![cfg(feature = "circuit-params")]
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    keygen_pk, keygen_vk, keygen_vk_from_params, Advice, Circuit, Column, ConstraintSystem, Error,
+    Fixed, FixedAssignments, Selector, TableColumn,
+};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::SerdeFormat;
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct StepsConfig {
+    q: Selector,
+    value: Column<Advice>,
+    step: Column<Fixed>,
+    table: TableColumn,
+}
+
+/// Checks, on as many rows as its parameter, that each value equals the
+/// fixed step of its row, to which it is also copied, and is below 16.
+#[derive(Clone, Copy, Default)]
+struct Steps {
+    rows: usize,
+}
+
+impl Circuit<Fr> for Steps {
+    type Config = StepsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = usize;
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn params(&self) -> Self::Params {
+        self.rows
+    }
+
+    fn configure_with_params(meta: &mut ConstraintSystem<Fr>, _rows: usize) -> Self::Config {
+        let q = meta.complex_selector();
+        let value = meta.advice_column();
+        let step = meta.fixed_column();
+        let table = meta.lookup_table_column();
+        meta.enable_equality(value);
+        meta.enable_equality(step);
+
+        meta.create_gate("step", |meta| {
+            let q = meta.query_selector(q);
+            let value = meta.query_advice(value, Rotation::cur());
+            let step = meta.query_fixed(step, Rotation::cur());
+            vec![q * (value - step)]
+        });
+        meta.lookup("small", |meta| {
+            let q = meta.query_selector(q);
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![(q * value, table)]
+        });
+
+        StepsConfig {
+            q,
+            value,
+            step,
+            table,
+        }
+    }
+
+    fn configure(_meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        unreachable!("configured with its parameters")
+    }
+
+    fn fixed_assignments(config: &Self::Config, rows: &usize) -> Option<FixedAssignments<Fr>> {
+        let mut assignments = FixedAssignments::new();
+        assignments.assign_table(config.table, (0..16).map(Fr::from).collect());
+        for row in 0..*rows {
+            assignments.enable_selector(config.q, row);
+            assignments.assign_fixed(config.step, row, Fr::from(row as u64 + 1));
+            assignments.copy(config.value, row, config.step, row);
+        }
+        Some(assignments)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "small",
+            |mut table| {
+                for value in 0..16 {
+                    table.assign_cell(
+                        || "value",
+                        config.table,
+                        value as usize,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "steps",
+            |mut region| {
+                for row in 0..self.rows {
+                    config.q.enable(&mut region, row)?;
+                    let step = Fr::from(row as u64 + 1);
+                    let step =
+                        region.assign_fixed(|| "step", config.step, row, || Value::known(step))?;
+                    let value = region.assign_advice(
+                        || "value",
+                        config.value,
+                        row,
+                        || Value::known(Fr::from(row as u64 + 1)),
+                    )?;
+                    region.constrain_equal(value.cell(), step.cell())?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// [`Steps`] without [`Circuit::fixed_assignments`].
+#[derive(Clone, Copy, Default)]
+struct SynthesizedSteps(Steps);
+
+impl Circuit<Fr> for SynthesizedSteps {
+    type Config = StepsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = usize;
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn params(&self) -> Self::Params {
+        self.0.rows
+    }
+
+    fn configure_with_params(meta: &mut ConstraintSystem<Fr>, rows: usize) -> Self::Config {
+        Steps::configure_with_params(meta, rows)
+    }
+
+    fn configure(_meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        unreachable!("configured with its parameters")
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        self.0.synthesize(config, layouter)
+    }
+}
+
+#[test]
+fn keygen_from_params() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    for rows in [1, 3, 10] {
+        let circuit = Steps { rows };
+        let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+        let from_params = keygen_vk_from_params::<G1Affine, _, Steps>(&params, rows)
+            .expect("keygen_vk_from_params should not fail");
+        assert_eq!(
+            from_params.to_bytes(SerdeFormat::RawBytes),
+            vk.to_bytes(SerdeFormat::RawBytes)
+        );
+        assert_eq!(from_params.transcript_repr(), vk.transcript_repr());
+
+        // The key is usable as if it came from the circuit.
+        keygen_pk(&params, from_params, &circuit).expect("keygen_pk should not fail");
+    }
+}
+
+#[test]
+fn keygen_from_params_without_fixed_assignments() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    assert!(matches!(
+        keygen_vk_from_params::<G1Affine, _, SynthesizedSteps>(&params, 3),
+        Err(Error::FixedAssignmentsUnavailable)
+    ));
+}
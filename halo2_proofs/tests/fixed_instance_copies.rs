@@ -0,0 +1,161 @@
+//! Checks copy constraints between fixed and instance cells, which check that
+//! public inputs equal constants of the circuit, and the errors for columns
+//! without equality enabled.
+
+use halo2_proofs::circuit::{Cell, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Any, Circuit, Column, ConstraintSystem,
+    Error, Fixed, Instance,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+const CONSTANT: u64 = 42;
+
+#[derive(Clone, Copy)]
+struct ConstantsConfig {
+    constant: Column<Fixed>,
+    public: Column<Instance>,
+}
+
+/// Copies a fixed cell holding `CONSTANT`, and the fixed cell below it, which
+/// is never assigned, to the first two instances. Equality is enabled on the
+/// fixed column with `FIXED_EQUALITY`, and on the instance column with
+/// `INSTANCE_EQUALITY`.
+#[derive(Clone, Copy, Default)]
+struct Constants<const FIXED_EQUALITY: bool, const INSTANCE_EQUALITY: bool>;
+
+impl<const FIXED_EQUALITY: bool, const INSTANCE_EQUALITY: bool> Circuit<Fr>
+    for Constants<FIXED_EQUALITY, INSTANCE_EQUALITY>
+{
+    type Config = ConstantsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let constant = meta.fixed_column();
+        let public = meta.instance_column();
+        if FIXED_EQUALITY {
+            meta.enable_equality(constant);
+        }
+        if INSTANCE_EQUALITY {
+            meta.enable_equality(public);
+        }
+        ConstantsConfig { constant, public }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let cells = layouter.assign_region(
+            || "constants",
+            |mut region| {
+                let constant = region.assign_fixed(
+                    || "constant",
+                    config.constant,
+                    0,
+                    || Value::known(Fr::from(CONSTANT)),
+                )?;
+                let unassigned = Cell {
+                    row_offset: 1,
+                    ..constant.cell()
+                };
+                Ok([constant.cell(), unassigned])
+            },
+        )?;
+        for (row, cell) in cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.public, row)?;
+        }
+        Ok(())
+    }
+}
+
+fn instances(constant: u64) -> Vec<Fr> {
+    vec![Fr::from(constant), Fr::from(0)]
+}
+
+#[test]
+fn fixed_to_instance() {
+    let prover = MockProver::run(K, &Constants::<true, true>, vec![instances(CONSTANT)]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let prover =
+        MockProver::run(K, &Constants::<true, true>, vec![instances(CONSTANT + 1)]).unwrap();
+    let failures = prover.verify().unwrap_err();
+    assert!(!failures.is_empty());
+    assert!(failures
+        .iter()
+        .all(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Constants::<true, true>).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &Constants::<true, true>).expect("keygen_pk should not fail");
+
+    let public = instances(CONSTANT);
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Constants::<true, true>],
+        &[&[&public]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |instances: &[Fr]| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[instances]],
+            &mut transcript,
+        )
+    };
+    assert!(verify(&public).is_ok());
+    assert!(verify(&instances(CONSTANT + 1)).is_err());
+}
+
+/// Checks that `C` fails to be synthesized, by the mock prover and keygen,
+/// because the column of type `missing` does not have equality enabled.
+fn missing_equality<C: Circuit<Fr> + Default>(missing: Any) {
+    let check = |error: Error| match &error {
+        Error::ColumnNotInPermutation(column) => {
+            assert_eq!(*column.column_type(), missing);
+            assert!(error.to_string().contains("meta.enable_equality"));
+        }
+        error => panic!("unexpected error {:?}", error),
+    };
+
+    check(MockProver::run(K, &C::default(), vec![instances(CONSTANT)]).unwrap_err());
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    check(keygen_vk(&params, &C::default()).unwrap_err());
+}
+
+#[test]
+fn fixed_column_without_equality() {
+    missing_equality::<Constants<false, true>>(Any::Fixed);
+}
+
+#[test]
+fn instance_column_without_equality() {
+    missing_equality::<Constants<true, false>>(Any::Instance);
+}
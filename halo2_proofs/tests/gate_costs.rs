@@ -0,0 +1,169 @@
+//! Checks that the prover evaluates a costly gate on its own, that its
+//! proofs do not depend on how the rows of the gates are chunked, and that
+//! the time spent on each group of gates is reported.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof_timed, create_proof_with_options, keygen_pk, keygen_vk, verify_proof, Advice,
+    ChunkSize, Circuit, Column, ConstraintSystem, Error, EvaluationStrategy, ProverOptions,
+    ProvingKey, Selector, VirtualCells,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, SeedableRng};
+
+const K: u32 = 6;
+const ROWS: usize = 40;
+
+#[derive(Clone, Copy)]
+struct SkewedConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks on each row that `b` is twice `a` and that `a` is one more than on
+/// the previous row, the latter with a gate hinted to be costly if `HINTED`.
+#[derive(Clone, Copy)]
+struct Skewed<const HINTED: bool>;
+
+impl<const HINTED: bool> Circuit<Fr> for Skewed<HINTED> {
+    type Config = SkewedConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        meta.create_gate("double", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (a.clone() + a - b)]
+        });
+        let increment = |meta: &mut VirtualCells<'_, Fr>| {
+            let q = meta.query_selector(q);
+            let cur = meta.query_advice(a, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            vec![q * (cur + Fr::from(1) - next)]
+        };
+        if HINTED {
+            meta.create_gate_with_cost("increment", 1000, increment);
+        } else {
+            meta.create_gate("increment", increment);
+        }
+
+        SkewedConfig { q, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..=ROWS {
+                    let a = Fr::from(row as u64);
+                    if row < ROWS {
+                        config.q.enable(&mut region, row)?;
+                    }
+                    region.assign_advice(|| "a", config.a, row, || Value::known(a))?;
+                    region.assign_advice(|| "b", config.b, row, || Value::known(a + a))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove(params: &ParamsKZG<Bn256>, pk: &ProvingKey<G1Affine>, options: &ProverOptions) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_options::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        params,
+        pk,
+        &[Skewed::<true>],
+        &[&[]],
+        ChaCha20Rng::from_seed([3u8; 32]),
+        &mut transcript,
+        options,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+#[test]
+fn costly_gate() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Skewed::<true>).expect("keygen_vk should not fail");
+    let unhinted = keygen_vk(&params, &Skewed::<false>).expect("keygen_vk should not fail");
+    // The hint does not change the keys.
+    assert_eq!(vk.transcript_repr(), unhinted.transcript_repr());
+    let costs: Vec<_> = vk.cs().gates().map(|gate| gate.cost()).collect();
+    assert_eq!(costs[1], 1000);
+    assert!(costs[0] < 1000);
+    let pk = keygen_pk(&params, vk, &Skewed::<true>).expect("keygen_pk should not fail");
+
+    // The proof is the same however the rows are split.
+    let proof = prove(&params, &pk, &ProverOptions::new());
+    for gate_chunks in [ChunkSize::Rows(7), ChunkSize::Auto] {
+        for strategy in [
+            EvaluationStrategy::Compiled,
+            EvaluationStrategy::Interpreted,
+        ] {
+            let options = ProverOptions::new()
+                .gate_chunk_size(gate_chunks)
+                .evaluation_strategy(strategy);
+            assert_eq!(prove(&params, &pk, &options), proof);
+        }
+    }
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+
+    // The costly gate is timed on its own.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let timings = create_proof_timed::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Skewed::<true>],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let groups: Vec<_> = timings
+        .gates
+        .iter()
+        .map(|group| (group.gates.clone(), group.cost))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            (vec!["double".to_string()], costs[0]),
+            (vec!["increment".to_string()], 1000),
+        ]
+    );
+}
@@ -0,0 +1,219 @@
+//! Checks lookups whose input expressions query instance columns, with both
+//! lookup backends, and with commitment schemes that evaluate the instance
+//! columns (KZG) or query them (IPA).
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, LookupBackend, Selector, TableColumn,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::ipa::commitment::{IPACommitmentScheme, ParamsIPA};
+use halo2_proofs::poly::ipa::multiopen::{ProverIPA, VerifierIPA};
+use halo2_proofs::poly::ipa::strategy::SingleStrategy as IPASingleStrategy;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct SuccessorConfig {
+    q: Selector,
+    a: Column<Advice>,
+    x: TableColumn,
+    successor: TableColumn,
+}
+
+/// Looks up each witness and the instance on the next row in a table of
+/// successors, so that the instances are one more than the witnesses.
+#[derive(Clone, Copy)]
+struct Successor<F, const LOGUP: bool> {
+    witnesses: [u64; 2],
+    _marker: PhantomData<F>,
+}
+
+impl<F, const LOGUP: bool> Successor<F, LOGUP> {
+    fn new(witnesses: [u64; 2]) -> Self {
+        Successor {
+            witnesses,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, const LOGUP: bool> Circuit<F> for Successor<F, LOGUP> {
+    type Config = SuccessorConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        if LOGUP {
+            meta.set_lookup_backend(LookupBackend::LogUp);
+        }
+        let q = meta.complex_selector();
+        let a = meta.advice_column();
+        let public = meta.instance_column();
+        let x = meta.lookup_table_column();
+        let successor = meta.lookup_table_column();
+
+        meta.lookup("successor", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let public = meta.query_instance(public, Rotation::next());
+            vec![(q.clone() * a, x), (q * public, successor)]
+        });
+
+        SuccessorConfig { q, a, x, successor }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "successors",
+            |mut table| {
+                // The inputs of the rows the lookup is disabled on are zeros.
+                let rows = [(0, 0)].into_iter().chain((1..8).map(|x| (x, x + 1)));
+                for (offset, (x, successor)) in rows.enumerate() {
+                    table.assign_cell(|| "x", config.x, offset, || Value::known(F::from(x)))?;
+                    table.assign_cell(
+                        || "successor",
+                        config.successor,
+                        offset,
+                        || Value::known(F::from(successor)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_region(
+            || "witnesses",
+            |mut region| {
+                for (offset, &witness) in self.witnesses.iter().enumerate() {
+                    config.q.enable(&mut region, offset)?;
+                    region.assign_advice(
+                        || "a",
+                        config.a,
+                        offset,
+                        || Value::known(F::from(witness)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn mock<const LOGUP: bool>(
+    witnesses: [u64; 2],
+    instances: [u64; 3],
+) -> Result<(), Vec<VerifyFailure>> {
+    let instances = instances.into_iter().map(Fr::from).collect();
+    MockProver::run(K, &Successor::<Fr, LOGUP>::new(witnesses), vec![instances])
+        .unwrap()
+        .verify()
+}
+
+#[test]
+fn mock_prover() {
+    assert_eq!(mock::<false>([2, 4], [0, 3, 5]), Ok(()));
+    assert_eq!(mock::<true>([2, 4], [0, 3, 5]), Ok(()));
+
+    // The lookup on the second row reads the wrong instance.
+    for result in [
+        mock::<false>([2, 4], [0, 3, 6]),
+        mock::<true>([2, 4], [0, 3, 6]),
+    ] {
+        match &result.unwrap_err()[..] {
+            [VerifyFailure::Lookup { name, .. }] => assert_eq!(name, "successor"),
+            failures => panic!("unexpected failures {:?}", failures),
+        }
+    }
+}
+
+fn prove_kzg<const LOGUP: bool>() {
+    let circuit = Successor::<Fr, LOGUP>::new([2, 4]);
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let instances = [0u64, 3, 5].map(Fr::from);
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&instances]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[&instances]],
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+fn prove_ipa<const LOGUP: bool>() {
+    let circuit = Successor::<Fp, LOGUP>::new([2, 4]);
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let instances = [0u64, 3, 5].map(Fp::from);
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&instances]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        &params,
+        pk.get_vk(),
+        IPASingleStrategy::new(&params),
+        &[&[&instances]],
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+#[test]
+fn prove_and_verify() {
+    prove_kzg::<false>();
+    prove_kzg::<true>();
+    prove_ipa::<false>();
+    prove_ipa::<true>();
+}
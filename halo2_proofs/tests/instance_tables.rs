@@ -0,0 +1,226 @@
+//! Checks lookups whose table is read from an instance column, with both
+//! lookup backends, and with commitment schemes that evaluate the instance
+//! columns (KZG) or query them (IPA).
+
+use ff::PrimeField;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Instance, LookupBackend, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::ipa::commitment::{IPACommitmentScheme, ParamsIPA};
+use halo2_proofs::poly::ipa::multiopen::{ProverIPA, VerifierIPA};
+use halo2_proofs::poly::ipa::strategy::SingleStrategy as IPASingleStrategy;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use halo2curves::pasta::{EqAffine, Fp};
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+const K: u32 = 8;
+
+/// The public table of allowed recipients.
+fn allowed<F: PrimeField>() -> Vec<F> {
+    (0..100).map(|i| F::from(1000 + 7 * i)).collect()
+}
+
+#[derive(Clone, Copy)]
+struct RecipientsConfig {
+    q: Selector,
+    recipient: Column<Advice>,
+    allowed: Column<Instance>,
+}
+
+/// Looks up each recipient in the table of allowed recipients, given as the
+/// instances of a column. The rows of the column past the table are zeros,
+/// which the rows the lookup is disabled on look up.
+#[derive(Clone, Copy)]
+struct Recipients<F, const LOGUP: bool> {
+    recipients: [u64; 3],
+    _marker: PhantomData<F>,
+}
+
+impl<F, const LOGUP: bool> Recipients<F, LOGUP> {
+    fn new(recipients: [u64; 3]) -> Self {
+        Recipients {
+            recipients,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, const LOGUP: bool> Circuit<F> for Recipients<F, LOGUP> {
+    type Config = RecipientsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        if LOGUP {
+            meta.set_lookup_backend(LookupBackend::LogUp);
+        }
+        let q = meta.complex_selector();
+        let recipient = meta.advice_column();
+        let allowed = meta.instance_column();
+
+        meta.lookup_any("allowed recipient", |meta| {
+            let q = meta.query_selector(q);
+            let recipient = meta.query_advice(recipient, Rotation::cur());
+            let allowed = meta.query_instance(allowed, Rotation::cur());
+            vec![(q * recipient, allowed)]
+        });
+
+        RecipientsConfig {
+            q,
+            recipient,
+            allowed,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "recipients",
+            |mut region| {
+                for (offset, &recipient) in self.recipients.iter().enumerate() {
+                    config.q.enable(&mut region, offset)?;
+                    region.assign_advice(
+                        || "recipient",
+                        config.recipient,
+                        offset,
+                        || Value::known(F::from(recipient)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+const MEMBERS: [u64; 3] = [1000, 1350, 1693];
+const NON_MEMBER: [u64; 3] = [1000, 1351, 1693];
+
+fn mock<const LOGUP: bool>(recipients: [u64; 3]) -> Result<(), Vec<VerifyFailure>> {
+    let circuit = Recipients::<Fr, LOGUP>::new(recipients);
+    MockProver::run(K, &circuit, vec![allowed()])
+        .unwrap()
+        .verify()
+}
+
+#[test]
+fn mock_prover() {
+    assert_eq!(mock::<false>(MEMBERS), Ok(()));
+    assert_eq!(mock::<true>(MEMBERS), Ok(()));
+
+    for result in [mock::<false>(NON_MEMBER), mock::<true>(NON_MEMBER)] {
+        match &result.unwrap_err()[..] {
+            [VerifyFailure::Lookup { name, .. }] => assert_eq!(name, "allowed recipient"),
+            failures => panic!("unexpected failures {:?}", failures),
+        }
+    }
+}
+
+fn prove_kzg<const LOGUP: bool>() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Recipients::<Fr, LOGUP>::new(MEMBERS))
+        .expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &Recipients::<Fr, LOGUP>::new(MEMBERS))
+        .expect("keygen_pk should not fail");
+    let instances = allowed::<Fr>();
+
+    let prove = |recipients| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[Recipients::<Fr, LOGUP>::new(recipients)],
+            &[&[&instances]],
+            OsRng,
+            &mut transcript,
+        )
+        .map(|()| transcript.finalize())
+    };
+
+    let proof = prove(MEMBERS).expect("proof generation should not fail");
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[&instances]],
+        &mut transcript,
+    )
+    .is_ok());
+
+    // The prover cannot find the non-member in the table.
+    assert!(matches!(
+        prove(NON_MEMBER),
+        Err(Error::ConstraintSystemFailure)
+    ));
+}
+
+fn prove_ipa<const LOGUP: bool>() {
+    let circuit = Recipients::<Fp, LOGUP>::new(MEMBERS);
+    let params: ParamsIPA<EqAffine> = ParamsIPA::new(K);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let instances = allowed::<Fp>();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&instances]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        &params,
+        pk.get_vk(),
+        IPASingleStrategy::new(&params),
+        &[&[&instances]],
+        &mut transcript,
+    )
+    .is_ok());
+
+    // The table is public: the proof does not verify against another one.
+    let mut other = instances.clone();
+    other[50] = Fp::from(1);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierIPA<_>, _, _, _>(
+        &params,
+        pk.get_vk(),
+        IPASingleStrategy::new(&params),
+        &[&[&other]],
+        &mut transcript,
+    )
+    .is_err());
+}
+
+#[test]
+fn prove_and_verify() {
+    prove_kzg::<false>();
+    prove_kzg::<true>();
+    prove_ipa::<false>();
+    prove_ipa::<true>();
+}
@@ -0,0 +1,132 @@
+//! Checks that the name of a lookup shows up in the tools describing a
+//! circuit, and that renaming it does not change the verifying key.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{CircuitGates, MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Selector, TableColumn,
+};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::Rotation;
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct RangeConfig {
+    q: Selector,
+    value: Column<Advice>,
+    table: TableColumn,
+}
+
+/// Looks up `value` in a table of the values below 8, with a lookup named
+/// "range", or "small value" if `RENAMED`.
+#[derive(Clone, Copy)]
+struct Range<const RENAMED: bool> {
+    value: u64,
+}
+
+impl<const RENAMED: bool> Circuit<Fr> for Range<RENAMED> {
+    type Config = RangeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.complex_selector();
+        let value = meta.advice_column();
+        let table = meta.lookup_table_column();
+
+        let name = if RENAMED { "small value" } else { "range" };
+        meta.lookup(name, |meta| {
+            let q = meta.query_selector(q);
+            let value = meta.query_advice(value, Rotation::cur());
+            vec![(q * value, table)]
+        });
+
+        RangeConfig { q, value, table }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range",
+            |mut table| {
+                for value in 0..8 {
+                    table.assign_cell(
+                        || "value",
+                        config.table,
+                        value as usize,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "value",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(
+                    || "value",
+                    config.value,
+                    0,
+                    || Value::known(Fr::from(self.value)),
+                )?;
+                Ok(())
+            },
+        )
+    }
+}
+
+fn failing_lookup<const RENAMED: bool>() -> String {
+    let prover = MockProver::run(K, &Range::<RENAMED> { value: 12 }, vec![]).unwrap();
+    match &prover.verify().unwrap_err()[..] {
+        [VerifyFailure::Lookup { name, .. }] => name.clone(),
+        failures => panic!("unexpected failures {:?}", failures),
+    }
+}
+
+fn gates<const RENAMED: bool>() -> String {
+    #[cfg(feature = "circuit-params")]
+    let gates = CircuitGates::collect::<Fr, Range<RENAMED>>(());
+    #[cfg(not(feature = "circuit-params"))]
+    let gates = CircuitGates::collect::<Fr, Range<RENAMED>>();
+    gates.to_string()
+}
+
+#[test]
+fn renamed_lookup() {
+    assert_eq!(failing_lookup::<false>(), "range");
+    assert_eq!(failing_lookup::<true>(), "small value");
+
+    assert_eq!(
+        gates::<false>(),
+        "lookup 'range':\n\
+         - S0 * A0@0 -> F0@0\n\
+         Total gates: 0\n\
+         Total custom constraint polynomials: 0\n\
+         Total lookups: 1\n\
+         Total negations: 0\n\
+         Total additions: 0\n\
+         Total multiplications: 0\n"
+    );
+    assert!(gates::<true>().starts_with("lookup 'small value':\n"));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Range::<false> { value: 5 }).expect("keygen_vk should not fail");
+    let renamed =
+        keygen_vk(&params, &Range::<true> { value: 5 }).expect("keygen_vk should not fail");
+    assert_eq!(vk.export().lookups[0].name, "range");
+    assert_eq!(renamed.export().lookups[0].name, "small value");
+    assert_eq!(renamed.transcript_repr(), vk.transcript_repr());
+    assert_eq!(renamed.content_hash(), vk.content_hash());
+}
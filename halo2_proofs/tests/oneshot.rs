@@ -0,0 +1,138 @@
+//! Checks that `prove` and `verify` interoperate with proofs created and
+//! verified with the lower-level API.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, prove, verify, verify_proof, Advice, Circuit, Column,
+    ConstraintSystem, Error, Instance, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Exposes the sum of two values.
+#[derive(Clone, Copy)]
+struct MyCircuit {
+    a: u64,
+    b: u64,
+}
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(b);
+        meta.enable_equality(instance);
+
+        meta.create_gate("b_next = a + b", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let sum = meta.query_advice(b, Rotation::next());
+            vec![q * (sum - a - b)]
+        });
+
+        MyConfig { q, a, b, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let sum = layouter.assign_region(
+            || "sum",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(self.a)))?;
+                region.assign_advice(|| "b", config.b, 0, || Value::known(Fr::from(self.b)))?;
+                region.assign_advice(
+                    || "sum",
+                    config.b,
+                    1,
+                    || Value::known(Fr::from(self.a + self.b)),
+                )
+            },
+        )?;
+        layouter.constrain_instance(sum.cell(), config.instance, 0)
+    }
+}
+
+#[test]
+fn oneshot() {
+    let circuits = [MyCircuit { a: 2, b: 3 }, MyCircuit { a: 4, b: 5 }];
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, OsRng);
+    let vk = keygen_vk(&params, &circuits[0]).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuits[0]).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+    let (first, second) = ([Fr::from(5)], [Fr::from(9)]);
+    let instances: &[&[&[Fr]]] = &[&[&first[..]], &[&second[..]]];
+
+    // A proof from the wrappers verifies with the lower-level API.
+    let proof = prove(&params, &pk, &circuits, instances, OsRng).expect("proof should be created");
+    assert!(verify(&params, vk, instances, &proof).is_ok());
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        vk,
+        SingleStrategy::new(&params),
+        instances,
+        &mut transcript,
+    )
+    .is_ok());
+
+    // A proof from the lower-level API verifies with the wrappers.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &circuits,
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let verbose_proof = transcript.finalize();
+    assert_eq!(verbose_proof.len(), proof.len());
+    assert!(verify(&params, vk, instances, &verbose_proof).is_ok());
+
+    // Other instances, a truncated proof and trailing bytes are rejected.
+    let swapped: &[&[&[Fr]]] = &[&[&second[..]], &[&first[..]]];
+    assert!(matches!(
+        verify(&params, vk, swapped, &proof),
+        Err(Error::ConstraintSystemFailure)
+    ));
+    assert!(verify(&params, vk, instances, &proof[..proof.len() - 1]).is_err());
+    let mut extended = proof.clone();
+    extended.push(0);
+    assert!(matches!(
+        verify(&params, vk, instances, &extended),
+        Err(Error::TrailingBytes(1))
+    ));
+}
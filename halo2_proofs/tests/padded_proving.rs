@@ -0,0 +1,215 @@
+//! Proves circuits at a larger `k` than they need, and checks that they
+//! behave the same as at the smallest `k` they fit in.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Instance, Selector, TableColumn,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const TABLE_SIZE: u64 = 16;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+    table: TableColumn,
+    instance: Column<Instance>,
+}
+
+/// Adds two values in range on each of `rows` rows, and exposes the last
+/// sum. If `bad`, the first value of the last row is out of range.
+#[derive(Clone, Copy)]
+struct MyCircuit {
+    rows: usize,
+    bad: bool,
+}
+
+impl MyCircuit {
+    fn values(&self, row: usize) -> (u64, u64) {
+        let a = if self.bad && row + 1 == self.rows {
+            TABLE_SIZE
+        } else {
+            row as u64 % TABLE_SIZE
+        };
+        (a, (3 * row as u64) % TABLE_SIZE)
+    }
+
+    fn instance(&self) -> Vec<Vec<Fr>> {
+        let (a, b) = self.values(self.rows - 1);
+        vec![vec![Fr::from(a + b)]]
+    }
+}
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let c = meta.advice_column();
+        let table = meta.lookup_table_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(c);
+        meta.enable_equality(instance);
+
+        meta.create_gate("sum", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            vec![q * (a + b - c)]
+        });
+        for (name, column) in [("a in range", a), ("b in range", b)] {
+            meta.lookup(name, |meta| {
+                let q = meta.query_selector(q);
+                vec![(q * meta.query_advice(column, Rotation::cur()), table)]
+            });
+        }
+
+        MyConfig {
+            q,
+            a,
+            b,
+            c,
+            table,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range",
+            |mut table| {
+                for i in 0..TABLE_SIZE {
+                    let value = Value::known(Fr::from(i));
+                    table.assign_cell(|| "value", config.table, i as usize, || value)?;
+                }
+                Ok(())
+            },
+        )?;
+        let sum = layouter.assign_region(
+            || "sums",
+            |mut region| {
+                let mut sum = None;
+                for row in 0..self.rows {
+                    config.q.enable(&mut region, row)?;
+                    let (a, b) = self.values(row);
+                    region.assign_advice(|| "a", config.a, row, || Value::known(Fr::from(a)))?;
+                    region.assign_advice(|| "b", config.b, row, || Value::known(Fr::from(b)))?;
+                    sum = Some(region.assign_advice(
+                        || "c",
+                        config.c,
+                        row,
+                        || Value::known(Fr::from(a + b)),
+                    )?);
+                }
+                Ok(sum.unwrap())
+            },
+        )?;
+        layouter.constrain_instance(sum.cell(), config.instance, 0)
+    }
+}
+
+/// Checks that a circuit of `rows` rows, which fits in `2^min_k` rows but
+/// not in half as many, behaves the same at `k`, and proves it at `k`.
+fn check_padded(rows: usize, min_k: u32, k: u32) {
+    let circuit = MyCircuit { rows, bad: false };
+    let bad = MyCircuit { rows, bad: true };
+
+    let mut cs = ConstraintSystem::<Fr>::default();
+    MyCircuit::configure(&mut cs);
+    assert!(cs.usable_rows(min_k - 1).end < rows);
+    assert!(cs.usable_rows(min_k).end >= rows);
+
+    let run = |circuit: &MyCircuit, k| {
+        MockProver::run(k, circuit, circuit.instance())
+            .unwrap()
+            .verify()
+    };
+    assert_eq!(run(&circuit, min_k), Ok(()));
+    assert_eq!(run(&circuit, k), Ok(()));
+    let failures = run(&bad, min_k).unwrap_err();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(run(&bad, k), Err(failures));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(k, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+
+    let instance = circuit.instance();
+    let instance: Vec<&[Fr]> = instance.iter().map(|column| &column[..]).collect();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&instance],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&instance],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    // The lookup fails in the prover as it does in the `MockProver`.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let bad_instance = bad.instance();
+    let bad_instance: Vec<&[Fr]> = bad_instance.iter().map(|column| &column[..]).collect();
+    assert!(
+        create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[bad],
+            &[&bad_instance],
+            OsRng,
+            &mut transcript,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn padded_proving() {
+    check_padded(20, 5, 8);
+}
+
+#[test]
+#[ignore]
+fn padded_proving_k17_at_k20() {
+    check_padded((1 << 16) + 1, 17, 20);
+}
@@ -0,0 +1,155 @@
+//! Checks the accessors of the permutation argument against the commitments
+//! of the verifying key and of the prover.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::plonk::{
+    create_proof_listened, keygen_pk, keygen_vk, Advice, Any, Circuit, Column, CommitmentKind,
+    ConstraintSystem, Error, Fixed, Instance, Selector, TranscriptEvent, TranscriptRecorder,
+};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone)]
+struct CopiesConfig {
+    q: Selector,
+    advice: [Column<Advice>; 3],
+    constant: Column<Fixed>,
+    instance: Column<Instance>,
+}
+
+/// Copies the instance into the first of three advice columns, and checks
+/// that their product, of degree 4, is the instance cubed. Five columns take
+/// part in the permutation argument, in chunks of two.
+#[derive(Clone, Copy, Default)]
+struct Copies;
+
+impl Circuit<Fr> for Copies {
+    type Config = CopiesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let advice = [(); 3].map(|_| meta.advice_column());
+        let constant = meta.fixed_column();
+        let instance = meta.instance_column();
+
+        meta.enable_equality(instance);
+        for column in advice.iter().rev() {
+            meta.enable_equality(*column);
+        }
+        meta.enable_constant(constant);
+        // Enabling equality again keeps the column where it was.
+        meta.enable_equality(instance);
+
+        meta.create_gate("cube", |meta| {
+            let q = meta.query_selector(q);
+            let [a, b, c] = advice.map(|column| meta.query_advice(column, Rotation::cur()));
+            let cube = meta.query_advice(advice[2], Rotation::next());
+            vec![q * (a * b * c - cube)]
+        });
+
+        CopiesConfig {
+            q,
+            advice,
+            constant,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "cube",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let x = region.assign_advice_from_instance(
+                    || "x",
+                    config.instance,
+                    0,
+                    config.advice[0],
+                    0,
+                )?;
+                x.copy_advice(|| "x", &mut region, config.advice[1], 0)?;
+                x.copy_advice(|| "x", &mut region, config.advice[2], 0)?;
+                region.assign_advice_from_constant(|| "x^3", config.advice[2], 1, Fr::from(8))?;
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn permutation_accessors() {
+    let mut cs = ConstraintSystem::<Fr>::default();
+    let config = Copies::configure(&mut cs);
+    let columns: Vec<Column<Any>> = vec![
+        config.instance.into(),
+        config.advice[2].into(),
+        config.advice[1].into(),
+        config.advice[0].into(),
+        config.constant.into(),
+    ];
+    assert_eq!(cs.permutation_columns(), &columns[..]);
+    assert_eq!(cs.permutation_chunk_size(), 2);
+    let chunks: Vec<_> = cs.permutation_chunks().collect();
+    assert_eq!(chunks, [&columns[..2], &columns[2..4], &columns[4..]]);
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Copies).expect("keygen_vk should not fail");
+    assert_eq!(vk.cs().permutation_columns(), &columns[..]);
+    assert_eq!(vk.permutation_chunk_size(), cs.permutation_chunk_size());
+    let committed: Vec<_> = vk
+        .permutation_commitments()
+        .map(|(column, commitment)| (column, *commitment))
+        .collect();
+    let expected: Vec<_> = columns
+        .iter()
+        .copied()
+        .zip(vk.permutation().commitments().iter().copied())
+        .collect();
+    assert_eq!(committed, expected);
+
+    // The prover commits to one grand product for each chunk.
+    let pk = keygen_pk(&params, vk, &Copies).expect("keygen_pk should not fail");
+    let mut recorder = TranscriptRecorder::new();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_listened::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _, _>(
+        &params,
+        &pk,
+        &[Copies],
+        &[&[&[Fr::from(2)]]],
+        OsRng,
+        &mut transcript,
+        &mut recorder,
+    )
+    .expect("proof generation should not fail");
+    let products = recorder
+        .events
+        .iter()
+        .filter(|event| {
+            matches!(
+                event,
+                TranscriptEvent::Commitment {
+                    kind: CommitmentKind::PermutationProduct,
+                    ..
+                }
+            )
+        })
+        .count();
+    assert_eq!(products, pk.get_vk().cs().permutation_chunks().len());
+}
@@ -0,0 +1,180 @@
+//! Checks that assigning a value computed from a challenge to an advice column
+//! committed to before the challenge is squeezed is caught, with advice
+//! columns typed by their phase and by `MockProver`.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, AdviceColumn, Challenge, Circuit,
+    Column, ConstraintSystem, Error, FirstPhase, SecondPhase, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct ScaleConfig {
+    q: Selector,
+    a: AdviceColumn<FirstPhase>,
+    b: AdviceColumn<SecondPhase>,
+    scratch: Column<Advice>,
+    theta: Challenge,
+}
+
+/// Where the product of `a` and the challenge is assigned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mistake {
+    /// Only to `b`, in the second phase.
+    None,
+    /// Also to `scratch`, a first-phase column, with the untyped API.
+    Untyped,
+    /// Also to `a`, with the typed API.
+    Typed,
+}
+
+/// Checks that `b = a * theta`, with `b` in the second phase.
+#[derive(Clone, Copy)]
+struct Scale(Mistake);
+
+impl Circuit<Fr> for Scale {
+    type Config = ScaleConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.phased_advice_column::<FirstPhase>();
+        let scratch = meta.advice_column();
+        let theta = meta.challenge_usable_after(FirstPhase);
+        let b = meta.phased_advice_column::<SecondPhase>();
+
+        meta.create_gate("scale", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a.into(), Rotation::cur());
+            let b = meta.query_advice(b.into(), Rotation::cur());
+            let theta = meta.query_challenge(theta);
+            vec![q * (a * theta - b)]
+        });
+
+        ScaleConfig {
+            q,
+            a,
+            b,
+            scratch,
+            theta,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let theta = layouter.get_challenge(config.theta);
+        assert_eq!(theta.challenge_phase(), Some(0));
+
+        layouter.assign_region(
+            || "scale",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let a = Value::known(Fr::from(3));
+                assert_eq!(a.challenge_phase(), None);
+                let b = a * theta;
+                assert_eq!(b.challenge_phase(), Some(0));
+
+                region.assign_advice_in_phase(|| "a", config.a, 0, || a)?;
+                region.assign_advice_in_phase(|| "b", config.b, 0, || b)?;
+                match self.0 {
+                    Mistake::None => (),
+                    Mistake::Untyped => {
+                        region.assign_advice(|| "scratch", config.scratch, 0, || b)?;
+                    }
+                    Mistake::Typed => {
+                        region.assign_advice_in_phase(|| "a", config.a, 1, || b)?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn mock_prover() {
+    let prover = MockProver::run(K, &Scale(Mistake::None), vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let config = Scale::configure(&mut ConstraintSystem::default());
+    for (mistake, column) in [
+        (Mistake::Untyped, config.scratch),
+        (Mistake::Typed, config.a.column()),
+    ] {
+        match MockProver::run(K, &Scale(mistake), vec![]) {
+            Err(Error::ChallengeFromLaterPhase {
+                region,
+                column: assigned,
+                challenge_phase,
+                ..
+            }) => {
+                assert_eq!(region.as_deref(), Some("scale"));
+                assert_eq!(assigned, column.into());
+                assert_eq!(challenge_phase, 0);
+            }
+            result => panic!("unexpected result for {:?}: {:?}", mistake, result.err()),
+        }
+    }
+}
+
+#[test]
+fn prove_and_verify() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Scale(Mistake::None)).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &Scale(Mistake::None)).expect("keygen_pk should not fail");
+
+    let prove = |circuit: Scale| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[circuit],
+            &[&[]],
+            OsRng,
+            &mut transcript,
+        )
+        .map(|()| transcript.finalize())
+    };
+
+    // The typed API catches the mistake with any backend.
+    assert!(matches!(
+        prove(Scale(Mistake::Typed)),
+        Err(Error::ChallengeFromLaterPhase {
+            challenge_phase: 0,
+            ..
+        })
+    ));
+
+    let proof = prove(Scale(Mistake::None)).expect("proof generation should not fail");
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
@@ -0,0 +1,146 @@
+//! Checks a circuit with four phases, each with a challenge squeezed after
+//! it but the last.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Challenge, Circuit, Column,
+    ConstraintSystem, Error, FirstPhase, NthPhase, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+const PHASES: u8 = 4;
+
+#[derive(Clone)]
+struct PhasesConfig {
+    q: Selector,
+    advice: Vec<Column<Advice>>,
+    challenges: Vec<Challenge>,
+}
+
+/// Multiplies a witness by the challenge squeezed after each phase, in an
+/// advice column of the next phase. With `BAD`, the last product is off by
+/// one.
+#[derive(Clone, Copy)]
+struct Phases<const BAD: bool>(u64);
+
+impl<const BAD: bool> Circuit<Fr> for Phases<BAD> {
+    type Config = PhasesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let mut advice = vec![meta.advice_column_in(FirstPhase)];
+        let mut challenges = vec![];
+        for phase in 1..PHASES {
+            challenges.push(meta.challenge_usable_after(NthPhase(phase - 1)));
+            advice.push(meta.advice_column_in(NthPhase(phase)));
+        }
+
+        meta.create_gate("products", |meta| {
+            let q = meta.query_selector(q);
+            advice
+                .windows(2)
+                .zip(&challenges)
+                .map(|(columns, challenge)| {
+                    let cur = meta.query_advice(columns[0], Rotation::cur());
+                    let next = meta.query_advice(columns[1], Rotation::cur());
+                    let challenge = meta.query_challenge(*challenge);
+                    q.clone() * (cur * challenge - next)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        PhasesConfig {
+            q,
+            advice,
+            challenges,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let challenges: Vec<_> = config
+            .challenges
+            .iter()
+            .map(|challenge| layouter.get_challenge(*challenge))
+            .collect();
+        layouter.assign_region(
+            || "products",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                let mut value = Value::known(Fr::from(self.0));
+                region.assign_advice(|| "witness", config.advice[0], 0, || value)?;
+                for (column, challenge) in config.advice[1..].iter().zip(&challenges) {
+                    value = value * challenge;
+                    if BAD && *column == config.advice[PHASES as usize - 1] {
+                        value = value + Value::known(Fr::from(1));
+                    }
+                    region.assign_advice(|| "product", *column, 0, || value)?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn four_phases() {
+    let cs = {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        Phases::<false>::configure(&mut cs);
+        cs
+    };
+    assert_eq!(cs.advice_column_phase(), [0, 1, 2, 3]);
+    assert_eq!(cs.challenge_phases(), [0, 1, 2]);
+
+    let prover = MockProver::run(K, &Phases::<false>(3), vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &Phases::<true>(3), vec![]).unwrap();
+    assert!(prover.verify().is_err());
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Phases::<false>(0)).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &Phases::<false>(0)).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Phases::<false>(3)],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
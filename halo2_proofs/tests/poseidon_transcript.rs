@@ -0,0 +1,175 @@
+//! Proves and verifies with the Poseidon transcript.
+
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof_listened, keygen_pk, keygen_vk, verify_proof_listened, Advice, Circuit, Column,
+    ConstraintSystem, Error, Instance, ProverListener, Selector, VerifierListener,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{Challenge255, PoseidonRead, PoseidonSpec, PoseidonWrite};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Doubles a few values, exposing the last double.
+#[derive(Clone, Copy)]
+struct MyCircuit;
+
+const ROWS: u64 = 4;
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(b);
+        meta.enable_equality(instance);
+
+        meta.create_gate("b = 2a", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (b - a.clone() - a)]
+        });
+
+        MyConfig { q, a, b, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let last = layouter.assign_region(
+            || "doubles",
+            |mut region| {
+                let mut last = None;
+                for row in 0..ROWS {
+                    let offset = row as usize;
+                    let a = Fr::from(row + 1);
+                    config.q.enable(&mut region, offset)?;
+                    region.assign_advice(|| "a", config.a, offset, || Value::known(a))?;
+                    last = Some(region.assign_advice(
+                        || "b",
+                        config.b,
+                        offset,
+                        || Value::known(a.double()),
+                    )?);
+                }
+                Ok(last.unwrap())
+            },
+        )?;
+        layouter.constrain_instance(last.cell(), config.instance, 0)
+    }
+}
+
+/// Records the challenges squeezed from a transcript.
+#[derive(Default)]
+struct Challenges(Vec<Fr>);
+
+impl ProverListener<G1Affine> for Challenges {
+    fn challenge(&mut self, challenge: &Fr) {
+        self.0.push(*challenge);
+    }
+}
+
+impl VerifierListener<G1Affine> for Challenges {
+    fn challenge(&mut self, challenge: &Fr) {
+        self.0.push(*challenge);
+    }
+}
+
+/// A toy specification of width 3 and rate 2. Its constants are not secure.
+fn spec() -> PoseidonSpec<Fr> {
+    let width = 3;
+    let round_constants = (0..8 + 57)
+        .map(|round| {
+            (0..width)
+                .map(|i| Fr::from((round * width + i + 1) as u64))
+                .collect()
+        })
+        .collect();
+    let mds = (0..width)
+        .map(|i| {
+            (0..width)
+                .map(|j| Fr::from((i + width + j) as u64).invert().unwrap())
+                .collect()
+        })
+        .collect();
+    PoseidonSpec::new(2, 8, 57, 5, round_constants, mds)
+}
+
+#[test]
+fn poseidon_transcript() {
+    let spec = spec();
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, ChaCha20Rng::from_seed([1; 32]));
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let instance = [Fr::from(2 * ROWS)];
+    let instances: &[&[&[Fr]]] = &[&[&instance[..]]];
+
+    let mut prover_challenges = Challenges::default();
+    let mut transcript = PoseidonWrite::<_, _, Challenge255<_>>::init(&spec, vec![]);
+    create_proof_listened::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        ChaCha20Rng::from_seed([2; 32]),
+        &mut transcript,
+        &mut prover_challenges,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    // The verifier squeezes the same challenges from the proof.
+    let mut verifier_challenges = Challenges::default();
+    let mut transcript = PoseidonRead::<_, _, Challenge255<_>>::init(&spec, &proof[..]);
+    verify_proof_listened::<_, VerifierSHPLONK<_>, _, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        instances,
+        &mut transcript,
+        &mut verifier_challenges,
+    )
+    .expect("proof should verify");
+    assert!(!prover_challenges.0.is_empty());
+    assert_eq!(prover_challenges.0, verifier_challenges.0);
+
+    // Another instance changes the challenges, and fails verification.
+    let other = [Fr::from(2 * ROWS + 1)];
+    let mut transcript = PoseidonRead::<_, _, Challenge255<_>>::init(&spec, &proof[..]);
+    assert!(verify_proof_listened::<_, VerifierSHPLONK<_>, _, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[&other[..]]],
+        &mut transcript,
+        &mut Challenges::default(),
+    )
+    .is_err());
+}
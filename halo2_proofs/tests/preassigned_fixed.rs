@@ -0,0 +1,233 @@
+//! Checks that round constants given to
+//! `ConstraintSystem::fixed_column_with_values` produce the same keys as
+//! round constants assigned in a region.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Fixed, Selector,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+const ROUNDS: usize = 8;
+
+fn round_constants() -> Vec<Fr> {
+    (0..ROUNDS as u64).map(|i| Fr::from(7 * i + 3)).collect()
+}
+
+/// Applies the rounds `x -> (x + c)^5` to `state`.
+fn permute(mut state: Fr) -> Vec<Fr> {
+    let mut states = vec![state];
+    for c in round_constants() {
+        let x = state + c;
+        state = x * x * x * x * x;
+        states.push(state);
+    }
+    states
+}
+
+#[derive(Clone, Copy)]
+struct RoundsConfig {
+    q_round: Selector,
+    state: Column<Advice>,
+    round_constant: Column<Fixed>,
+}
+
+/// Applies `ROUNDS` rounds of a Poseidon-like S-box to `state`, with round
+/// constants assigned in the region, or given at configure time if
+/// `PREASSIGNED`. With `TOO_MANY`, more round constants are given than the
+/// circuit has rows.
+#[derive(Clone, Copy)]
+struct Rounds<const PREASSIGNED: bool, const TOO_MANY: bool = false> {
+    state: u64,
+}
+
+impl<const PREASSIGNED: bool, const TOO_MANY: bool> Circuit<Fr> for Rounds<PREASSIGNED, TOO_MANY> {
+    type Config = RoundsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q_round = meta.selector();
+        let state = meta.advice_column();
+        let round_constant = if PREASSIGNED {
+            let mut values = round_constants();
+            if TOO_MANY {
+                values.resize(1 << K, Fr::from(1));
+            }
+            meta.fixed_column_with_values(values)
+        } else {
+            meta.fixed_column()
+        };
+
+        meta.create_gate("round", |meta| {
+            let q_round = meta.query_selector(q_round);
+            let cur = meta.query_advice(state, Rotation::cur());
+            let next = meta.query_advice(state, Rotation::next());
+            let c = meta.query_fixed(round_constant, Rotation::cur());
+            let x = cur + c;
+            vec![q_round * (x.clone() * x.clone() * x.clone() * x.clone() * x - next)]
+        });
+
+        RoundsConfig {
+            q_round,
+            state,
+            round_constant,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rounds",
+            |mut region| {
+                for (round, c) in round_constants().into_iter().enumerate() {
+                    config.q_round.enable(&mut region, round)?;
+                    if !PREASSIGNED {
+                        region.assign_fixed(
+                            || "round constant",
+                            config.round_constant,
+                            round,
+                            || Value::known(c),
+                        )?;
+                    }
+                }
+                for (row, state) in permute(Fr::from(self.state)).into_iter().enumerate() {
+                    region.assign_advice(|| "state", config.state, row, || Value::known(state))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Assigns into the column of the round constants although they were given
+/// at configure time.
+#[derive(Clone, Copy)]
+struct Overwrite(Rounds<true>);
+
+impl Circuit<Fr> for Overwrite {
+    type Config = RoundsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Rounds::<true>::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "overwrite",
+            |mut region| {
+                region.assign_fixed(
+                    || "round constant",
+                    config.round_constant,
+                    0,
+                    || Value::known(Fr::from(1)),
+                )?;
+                Ok(())
+            },
+        )?;
+        self.0.synthesize(config, layouter)
+    }
+}
+
+#[test]
+fn preassigned_round_constants() {
+    let prover = MockProver::run(K, &Rounds::<true> { state: 2 }, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &Rounds::<false> { state: 2 }, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let assigned = keygen_vk(&params, &Rounds::<false> { state: 0 }).unwrap();
+    let vk = keygen_vk(&params, &Rounds::<true> { state: 0 }).unwrap();
+    let (round_constant, _) = vk.cs().fixed_queries()[0];
+    assert_eq!(
+        vk.cs().fixed_column_values(round_constant),
+        Some(&round_constants()[..])
+    );
+    assert_eq!(vk.fixed_commitments(), assigned.fixed_commitments());
+    // Only the hash of the round constants is pinned in addition.
+    assert_ne!(vk.transcript_repr(), assigned.transcript_repr());
+
+    let assigned_pk = keygen_pk(&params, assigned, &Rounds::<false> { state: 0 }).unwrap();
+    let pk = keygen_pk(&params, vk, &Rounds::<true> { state: 0 }).unwrap();
+    for (poly, assigned_poly) in pk.fixed_values().iter().zip(assigned_pk.fixed_values()) {
+        assert_eq!(&poly[..], &assigned_poly[..]);
+    }
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Rounds::<true> { state: 2 }],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+#[test]
+fn assign_into_preassigned_column() {
+    let circuit = Overwrite(Rounds { state: 2 });
+    assert!(matches!(
+        MockProver::run(K, &circuit, vec![]),
+        Err(Error::PreassignedFixedColumn { row: 0, .. })
+    ));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    assert!(matches!(
+        keygen_vk(&params, &circuit),
+        Err(Error::PreassignedFixedColumn { row: 0, .. })
+    ));
+}
+
+#[test]
+fn too_many_preassigned_values() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    assert!(matches!(
+        keygen_vk(&params, &Rounds::<true, true> { state: 0 }),
+        Err(Error::NotEnoughRowsAvailable { .. })
+    ));
+}
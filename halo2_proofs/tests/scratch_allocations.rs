@@ -0,0 +1,182 @@
+//! Counts the allocations of the size of the extended domain made while
+//! proving, with and without reusing buffers across the quotient evaluation.
+//!
+//! This is the only test of this binary, so that no other test allocates
+//! while the allocations are counted.
+
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof_with_options, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+    ConstraintSystem, Error, Fixed, ProverOptions,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, SeedableRng};
+
+#[path = "../benches/common/alloc.rs"]
+mod alloc;
+use alloc::{count_large_allocations, PeakAlloc};
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+const ROWS: u64 = 16;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Column<Fixed>,
+    table: Column<Fixed>,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Looks up a few expressions of two advice columns in a range table, and
+/// checks that one column is a shuffle of the other.
+#[derive(Clone, Copy, Default)]
+struct MyCircuit;
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.fixed_column();
+        let table = meta.fixed_column();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        for name in ["a", "b", "a + b", "2a"] {
+            meta.lookup_any(name, |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let table = meta.query_fixed(table, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                let input = match name {
+                    "a" => a,
+                    "b" => b,
+                    "a + b" => a + b,
+                    _ => a.clone() + a,
+                };
+                vec![(q * input, table)]
+            });
+        }
+        for name in ["b is a shuffle of a", "a is a shuffle of b"] {
+            meta.shuffle(name, |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                if name.starts_with('b') {
+                    vec![(q.clone() * a, q * b)]
+                } else {
+                    vec![(q.clone() * b, q * a)]
+                }
+            });
+        }
+
+        MyConfig { q, table, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..2 * ROWS {
+                    let offset = row as usize;
+                    region.assign_fixed(
+                        || "table",
+                        config.table,
+                        offset,
+                        || Value::known(Fr::from(row)),
+                    )?;
+                }
+                for row in 0..ROWS {
+                    let offset = row as usize;
+                    region.assign_fixed(|| "q", config.q, offset, || Value::known(Fr::ONE))?;
+                    region.assign_advice(
+                        || "a",
+                        config.a,
+                        offset,
+                        || Value::known(Fr::from(row)),
+                    )?;
+                    region.assign_advice(
+                        || "b",
+                        config.b,
+                        offset,
+                        || Value::known(Fr::from(ROWS - 1 - row)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn reused_buffers_reduce_allocations() {
+    let k = 6;
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(k, OsRng);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+
+    let extended_len = pk.get_vk().get_domain().extended_len();
+    let threshold = extended_len * std::mem::size_of::<Fr>();
+    let prove = |options: ProverOptions| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        let (_, allocations) = count_large_allocations(threshold, || {
+            create_proof_with_options::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+                &params,
+                &pk,
+                &[MyCircuit, MyCircuit],
+                &[&[], &[]],
+                ChaCha20Rng::from_seed([7u8; 32]),
+                &mut transcript,
+                &options,
+            )
+            .expect("proof generation should not fail")
+        });
+        (transcript.finalize(), allocations)
+    };
+
+    let (fresh_proof, fresh) = prove(ProverOptions::new().reuse_buffers(false));
+    let (proof, reused) = prove(ProverOptions::new());
+
+    // Each circuit needs the cosets of its two advice columns, three for each
+    // lookup and one for each shuffle. Without reuse, each is a new
+    // allocation; with it, only those used at once by the first circuit are.
+    assert!(
+        reused + 10 <= fresh,
+        "{} large allocations with reuse, {} without",
+        reused,
+        fresh
+    );
+    assert_eq!(proof, fresh_proof);
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[], &[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
@@ -0,0 +1,183 @@
+//! Checks a gate activated by a boolean combination of selectors, from
+//! `VirtualCells::selector_expr`, against the same gate activated by the
+//! product of their expressions.
+
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Expression, Selector, VerifyingKey,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct CombinationConfig {
+    s1: Selector,
+    s2: Selector,
+    s3: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks that `a = b` where `s1` is enabled but neither `s2` nor `s3` is,
+/// `b = 2a` where `s2` is and `b = a^2` where `s3` is. With `NAIVE`, the first
+/// gate multiplies the expressions of complex selectors instead. With `BAD`,
+/// `a != b` on the first row.
+#[derive(Clone, Copy)]
+struct Combination<const NAIVE: bool, const BAD: bool>;
+
+impl<const NAIVE: bool, const BAD: bool> Circuit<Fr> for Combination<NAIVE, BAD> {
+    type Config = CombinationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let [s1, s2, s3] = [(); 3].map(|_| {
+            if NAIVE {
+                meta.complex_selector()
+            } else {
+                meta.selector()
+            }
+        });
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        meta.create_gate("equal", |meta| {
+            let s = if NAIVE {
+                let one = Expression::Constant(Fr::ONE);
+                meta.query_selector(s1)
+                    * (one.clone() - meta.query_selector(s2))
+                    * (one - meta.query_selector(s3))
+            } else {
+                meta.selector_expr(s1.and(!s2).and(!s3))
+            };
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![s * (a - b)]
+        });
+        meta.create_gate("double", |meta| {
+            let s2 = meta.query_selector(s2);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![s2 * (a.clone() + a - b)]
+        });
+        meta.create_gate("square", |meta| {
+            let s3 = meta.query_selector(s3);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![s3 * (a.clone() * a - b)]
+        });
+
+        CombinationConfig { s1, s2, s3, a, b }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rows",
+            |mut region| {
+                let rows = [
+                    (&[config.s1][..], [3, if BAD { 4 } else { 3 }]),
+                    (&[config.s1, config.s2], [3, 6]),
+                    (&[config.s1, config.s3], [3, 9]),
+                    (&[config.s2], [1, 2]),
+                ];
+                for (offset, (selectors, values)) in rows.into_iter().enumerate() {
+                    for selector in selectors {
+                        selector.enable(&mut region, offset)?;
+                    }
+                    for (column, value) in [config.a, config.b].into_iter().zip(values) {
+                        region.assign_advice(
+                            || "value",
+                            column,
+                            offset,
+                            || Value::known(Fr::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Returns the degree of the gate `name` of the verifying key.
+fn gate_degree(vk: &VerifyingKey<G1Affine>, name: &str) -> usize {
+    let gate = vk.cs().gates().find(|gate| gate.name() == name).unwrap();
+    gate.polynomials()[0].degree()
+}
+
+#[test]
+fn selector_combinations() {
+    // The combination is a selector of its own, of degree 1.
+    let mut cs = ConstraintSystem::<Fr>::default();
+    Combination::<false, false>::configure(&mut cs);
+    assert_eq!(cs.num_selectors(), 4);
+    assert_eq!(cs.gates().next().unwrap().polynomials()[0].degree(), 2);
+    let mut naive = ConstraintSystem::<Fr>::default();
+    Combination::<true, false>::configure(&mut naive);
+    assert_eq!(naive.gates().next().unwrap().polynomials()[0].degree(), 4);
+    assert_eq!(naive.degree(), 4);
+
+    let prover = MockProver::run(K, &Combination::<false, false>, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &Combination::<false, true>, vec![]).unwrap();
+    match &prover.verify().unwrap_err()[..] {
+        [VerifyFailure::ConstraintNotSatisfied { .. }] => (),
+        failures => panic!("unexpected failures {:?}", failures),
+    }
+
+    // The product of the three selectors would raise the degree of the
+    // circuit, so the combination gets a fixed column.
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &Combination::<false, false>).expect("keygen_vk should not fail");
+    let naive = keygen_vk(&params, &Combination::<true, false>).expect("keygen_vk should not fail");
+    assert_eq!(gate_degree(&vk, "equal"), 2);
+    assert_eq!(gate_degree(&naive, "equal"), 4);
+    assert_eq!(vk.cs().degree(), 3);
+    assert_eq!(naive.cs().degree(), 4);
+
+    let pk =
+        keygen_pk(&params, vk, &Combination::<false, false>).expect("keygen_pk should not fail");
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Combination::<false, false>],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
@@ -0,0 +1,145 @@
+//! Checks that keys generated with `keygen_vk_custom` without selector
+//! compression give each selector a fixed column, survive serialization and
+//! prove the circuit.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, keygen_vk_custom, verify_proof, Advice, Circuit, Column,
+    ConstraintSystem, Error, Selector, VerifyingKey,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2_proofs::SerdeFormat;
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct DoubleOrTripleConfig {
+    double: Selector,
+    triple: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Checks that `b = 2a` on the first row and `b = 3a` on the second one.
+#[derive(Clone, Copy, Default)]
+struct DoubleOrTriple;
+
+impl Circuit<Fr> for DoubleOrTriple {
+    type Config = DoubleOrTripleConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let double = meta.selector();
+        let triple = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+
+        meta.create_gate("double", |meta| {
+            let double = meta.query_selector(double);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![double * (a.clone() + a - b)]
+        });
+        meta.create_gate("triple", |meta| {
+            let triple = meta.query_selector(triple);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![triple * (a.clone() + a.clone() + a - b)]
+        });
+
+        DoubleOrTripleConfig {
+            double,
+            triple,
+            a,
+            b,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rows",
+            |mut region| {
+                config.double.enable(&mut region, 0)?;
+                config.triple.enable(&mut region, 1)?;
+                for (offset, [a, b]) in [[3, 6], [3, 9]].into_iter().enumerate() {
+                    region.assign_advice(|| "a", config.a, offset, || Value::known(Fr::from(a)))?;
+                    region.assign_advice(|| "b", config.b, offset, || Value::known(Fr::from(b)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn keygen_without_compression() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let compressed = keygen_vk(&params, &DoubleOrTriple).expect("keygen_vk should not fail");
+    let vk = keygen_vk_custom(&params, &DoubleOrTriple, false).expect("keygen_vk should not fail");
+
+    // The selectors are never enabled on the same row, so compression puts
+    // them in the same fixed column, which raises the degree of their gates.
+    assert!(compressed.compress_selectors());
+    assert!(!vk.compress_selectors());
+    assert_eq!(compressed.fixed_commitments().len(), 1);
+    assert_eq!(vk.fixed_commitments().len(), 2);
+    let gate_degree =
+        |vk: &VerifyingKey<G1Affine>| vk.cs().gates().next().unwrap().polynomials()[0].degree();
+    assert_eq!(gate_degree(&compressed), 3);
+    assert_eq!(gate_degree(&vk), 2);
+    assert_ne!(vk.transcript_repr(), compressed.transcript_repr());
+
+    for format in [SerdeFormat::Processed, SerdeFormat::RawBytes] {
+        let read = VerifyingKey::<G1Affine>::from_bytes::<DoubleOrTriple>(
+            &vk.to_bytes(format),
+            format,
+            #[cfg(feature = "circuit-params")]
+            (),
+        )
+        .expect("reading vk should not fail");
+        assert!(!read.compress_selectors());
+        assert_eq!(read.transcript_repr(), vk.transcript_repr());
+    }
+
+    let pk = keygen_pk(&params, vk, &DoubleOrTriple).expect("keygen_pk should not fail");
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[DoubleOrTriple],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
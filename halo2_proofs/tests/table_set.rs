@@ -0,0 +1,213 @@
+//! Checks lookups into the logical tables of a table set.
+
+use halo2_proofs::circuit::{Layouter, LogicalTable, SimpleFloorPlanner, TableSet, Value};
+use halo2_proofs::dev::{MockProver, VerifyFailure};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Selector, TableError,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 7;
+
+#[derive(Clone)]
+struct TablesConfig {
+    set: TableSet,
+    range: LogicalTable,
+    xor: LogicalTable,
+    q_range: Selector,
+    q_xor: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+}
+
+/// Checks that a value has 2 bits, and the XOR of two values of 3 bits, in
+/// two logical tables of the same set. With `BAD_ROW`, a row of the XOR
+/// table misses a value.
+#[derive(Clone, Copy)]
+struct Tables<const BAD_ROW: bool> {
+    range: u64,
+    xor: (u64, u64, u64),
+}
+
+impl<const BAD_ROW: bool> Circuit<Fr> for Tables<BAD_ROW> {
+    type Config = TablesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let mut set = TableSet::new(meta, "bits", 3);
+        let columns = set.columns().to_vec();
+        let range = set.add_table("range", 1, &columns[..1]);
+        let xor = set.add_table("xor", 2, &columns);
+
+        let q_range = meta.complex_selector();
+        let q_xor = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let c = meta.advice_column();
+
+        meta.lookup_in_set("a has 2 bits", &range, |meta| {
+            let q_range = meta.query_selector(q_range);
+            let a = meta.query_advice(a, Rotation::cur());
+            (q_range, vec![a])
+        });
+        meta.lookup_in_set("c is a xor b", &xor, |meta| {
+            let q_xor = meta.query_selector(q_xor);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            (q_xor, vec![a, b, c])
+        });
+
+        TablesConfig {
+            set,
+            range,
+            xor,
+            q_range,
+            q_xor,
+            a,
+            b,
+            c,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table_set(
+            || "bits",
+            &config.set,
+            |table| match table.name() {
+                "range" => (0..4).map(|i| vec![Fr::from(i)]).collect(),
+                _ => (0..8)
+                    .flat_map(|a| (0..8).map(move |b| (a, b)))
+                    .map(|(a, b)| {
+                        let mut row = vec![Fr::from(a), Fr::from(b), Fr::from(a ^ b)];
+                        if BAD_ROW && a == 7 {
+                            row.pop();
+                        }
+                        row
+                    })
+                    .collect(),
+            },
+        )?;
+
+        layouter.assign_region(
+            || "checks",
+            |mut region| {
+                let value = |v: u64| Value::known(Fr::from(v));
+                config.q_range.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || value(self.range))?;
+
+                let (a, b, c) = self.xor;
+                config.q_xor.enable(&mut region, 1)?;
+                region.assign_advice(|| "a", config.a, 1, || value(a))?;
+                region.assign_advice(|| "b", config.b, 1, || value(b))?;
+                region.assign_advice(|| "c", config.c, 1, || value(c))?;
+                Ok(())
+            },
+        )
+    }
+}
+
+fn failed_tables(circuit: Tables<false>) -> Vec<Option<String>> {
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    prover
+        .verify()
+        .unwrap_err()
+        .into_iter()
+        .map(|failure| match failure {
+            VerifyFailure::Lookup { .. } => failure.lookup_table(&prover).map(String::from),
+            _ => panic!("unexpected failure {:?}", failure),
+        })
+        .collect()
+}
+
+#[test]
+fn lookups_in_set() {
+    let circuit = Tables::<false> {
+        range: 3,
+        xor: (5, 6, 3),
+    };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let lookups: Vec<_> = pk.get_vk().cs().lookups().map(|l| l.table()).collect();
+    assert_eq!(lookups, [Some("range"), Some("xor")]);
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+#[test]
+fn failures_name_logical_tables() {
+    // 5 is in the first column of the set, but only in rows of the XOR table.
+    let failed = failed_tables(Tables {
+        range: 5,
+        xor: (5, 6, 3),
+    });
+    assert_eq!(failed, [Some("range".to_string())]);
+
+    let failed = failed_tables(Tables {
+        range: 3,
+        xor: (5, 6, 4),
+    });
+    assert_eq!(failed, [Some("xor".to_string())]);
+}
+
+#[test]
+fn rows_of_wrong_width() {
+    let circuit = Tables::<true> {
+        range: 3,
+        xor: (5, 6, 3),
+    };
+    match MockProver::run(K, &circuit, vec![]) {
+        Err(Error::TableError(TableError::RowWidth {
+            table,
+            row: 56,
+            width: 2,
+            columns: 3,
+        })) => assert_eq!(table, "xor"),
+        _ => panic!("the table set should fail to be assigned"),
+    }
+}
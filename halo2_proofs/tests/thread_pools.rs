@@ -0,0 +1,187 @@
+#![cfg(feature = "multicore")]
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use halo2_proofs::arithmetic::{CpuEngine, Engine, Field, PoolEngine};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof_with_engine, create_proof_with_options, keygen_pk, keygen_vk, Advice, Circuit,
+    Column, ConstraintSystem, Error, Expression, Fixed, ProverOptions,
+};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer};
+use halo2curves::bn256::{Bn256, Fr, G1Affine, G1};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, SeedableRng};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// The names of the threads some work ran on.
+type Threads = Arc<Mutex<Vec<String>>>;
+
+fn record(threads: &Threads) {
+    let name = thread::current().name().unwrap_or("unnamed").to_string();
+    threads.lock().unwrap().push(name);
+}
+
+fn pool(name: &'static str, threads: usize) -> Arc<ThreadPool> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |i| format!("{}-{}", name, i))
+        .build()
+        .unwrap();
+    Arc::new(pool)
+}
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Column<Fixed>,
+    a: Column<Advice>,
+}
+
+/// Checks that a value is the sum of the previous one and one, while
+/// recording where the parallel work of its synthesis runs.
+#[derive(Clone)]
+struct MyCircuit {
+    synthesis_threads: Threads,
+}
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.fixed_column();
+        let a = meta.advice_column();
+        meta.create_gate("increment", |meta| {
+            let q = meta.query_fixed(q, Rotation::cur());
+            let a = meta.query_advice(a, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            vec![q * (next - a - Expression::Constant(Fr::ONE))]
+        });
+        MyConfig { q, a }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let values: Vec<Fr> = (0..8u64)
+            .into_par_iter()
+            .map(|i| {
+                record(&self.synthesis_threads);
+                Fr::from(i)
+            })
+            .collect();
+        layouter.assign_region(
+            || "increments",
+            |mut region| {
+                for (row, value) in values.iter().enumerate() {
+                    if row + 1 < values.len() {
+                        region.assign_fixed(|| "q", config.q, row, || Value::known(Fr::ONE))?;
+                    }
+                    region.assign_advice(|| "a", config.a, row, || Value::known(*value))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// An [`Engine`] recording the threads its operations run on.
+struct RecordingEngine {
+    threads: Threads,
+}
+
+impl Engine<G1Affine> for RecordingEngine {
+    fn best_multiexp(&self, coeffs: &[Fr], bases: &[G1Affine]) -> G1 {
+        record(&self.threads);
+        CpuEngine.best_multiexp(coeffs, bases)
+    }
+
+    fn fft(&self, values: &mut [Fr], omega: Fr, log_n: u32) {
+        record(&self.threads);
+        Engine::<G1Affine>::fft(&CpuEngine, values, omega, log_n)
+    }
+}
+
+#[test]
+fn synthesis_and_compute_pools() {
+    let synthesis_pool = pool("synthesis", 2);
+    let compute_pool = pool("compute", 3);
+
+    let circuit = MyCircuit {
+        synthesis_threads: Threads::default(),
+    };
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    circuit.synthesis_threads.lock().unwrap().clear();
+
+    // Synthesis runs in the pool the prover is called from, while the FFTs
+    // and multiexponentiations run in the engine's pool.
+    let compute_threads = Threads::default();
+    let engine = PoolEngine::new(
+        compute_pool.clone(),
+        RecordingEngine {
+            threads: compute_threads.clone(),
+        },
+    );
+    synthesis_pool.install(|| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof_with_engine::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[circuit.clone()],
+            &[&[]],
+            OsRng,
+            &mut transcript,
+            &engine,
+        )
+        .expect("proof generation should not fail");
+    });
+
+    let synthesis_threads = circuit.synthesis_threads.lock().unwrap().clone();
+    assert!(!synthesis_threads.is_empty());
+    for name in synthesis_threads {
+        assert!(name.starts_with("synthesis-"), "synthesis ran on {}", name);
+    }
+    let compute_threads = compute_threads.lock().unwrap().clone();
+    assert!(!compute_threads.is_empty());
+    for name in compute_threads {
+        assert!(
+            name.starts_with("compute-"),
+            "an FFT or MSM ran on {}",
+            name
+        );
+    }
+
+    // Moving the work to another pool doesn't change the proof.
+    let prove = |options: ProverOptions| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof_with_options::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[circuit.clone()],
+            &[&[]],
+            ChaCha20Rng::from_seed([5u8; 32]),
+            &mut transcript,
+            &options,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+    let expected = prove(ProverOptions::new());
+    let proof = synthesis_pool.install(|| prove(ProverOptions::new().compute_pool(compute_pool)));
+    assert_eq!(proof, expected);
+}
@@ -0,0 +1,195 @@
+//! Checks that a selector allocated with
+//! `ConstraintSystem::uncompressed_selector` keeps a fixed column of its own,
+//! as the compression map of the verifying key shows.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Selector, VerifyingKey,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct SelectorsConfig {
+    q_hot: Selector,
+    q_sum: Selector,
+    q_cube: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+}
+
+/// Checks that `a = b` with `q_hot`, `a + b = c` with `q_sum` and `a^3 = c`
+/// with `q_cube`, on different rows. The cube makes the circuit of degree 4,
+/// which lets `q_hot` and `q_sum` be combined into one fixed column, unless
+/// `q_hot` is `UNCOMPRESSED`.
+#[derive(Clone, Copy, Default)]
+struct Selectors<const UNCOMPRESSED: bool>;
+
+impl<const UNCOMPRESSED: bool> Circuit<Fr> for Selectors<UNCOMPRESSED> {
+    type Config = SelectorsConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q_hot = if UNCOMPRESSED {
+            meta.uncompressed_selector()
+        } else {
+            meta.selector()
+        };
+        let q_sum = meta.selector();
+        let q_cube = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let c = meta.advice_column();
+
+        meta.create_gate("hot", |meta| {
+            let q_hot = meta.query_selector(q_hot);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q_hot * (a - b)]
+        });
+        meta.create_gate("sum", |meta| {
+            let q_sum = meta.query_selector(q_sum);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            vec![q_sum * (a + b - c)]
+        });
+        meta.create_gate("cube", |meta| {
+            let q_cube = meta.query_selector(q_cube);
+            let a = meta.query_advice(a, Rotation::cur());
+            let c = meta.query_advice(c, Rotation::cur());
+            vec![q_cube * (a.clone() * a.clone() * a - c)]
+        });
+
+        SelectorsConfig {
+            q_hot,
+            q_sum,
+            q_cube,
+            a,
+            b,
+            c,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rows",
+            |mut region| {
+                let rows = [
+                    (config.q_hot, [2, 2, 0]),
+                    (config.q_sum, [2, 3, 5]),
+                    (config.q_cube, [2, 0, 8]),
+                ];
+                for (offset, (selector, values)) in rows.into_iter().enumerate() {
+                    selector.enable(&mut region, offset)?;
+                    for (column, value) in [config.a, config.b, config.c].into_iter().zip(values) {
+                        region.assign_advice(
+                            || "value",
+                            column,
+                            offset,
+                            || Value::known(Fr::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Returns `q_hot`, as configured by `Selectors<UNCOMPRESSED>`.
+fn q_hot<const UNCOMPRESSED: bool>() -> Selector {
+    let mut meta = ConstraintSystem::<Fr>::default();
+    Selectors::<UNCOMPRESSED>::configure(&mut meta).q_hot
+}
+
+/// Returns the degree of the gate `name` of the verifying key.
+fn gate_degree(vk: &VerifyingKey<G1Affine>, name: &str) -> usize {
+    let gate = vk.cs().gates().find(|gate| gate.name() == name).unwrap();
+    gate.polynomials()[0].degree()
+}
+
+#[test]
+fn uncompressed_selector() {
+    let prover = MockProver::run(K, &Selectors::<false>, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &Selectors::<true>, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let compressed = keygen_vk(&params, &Selectors::<false>).expect("keygen_vk should not fail");
+    let vk = keygen_vk(&params, &Selectors::<true>).expect("keygen_vk should not fail");
+
+    // Combined with `q_sum`, `q_hot` is replaced with an expression of a higher
+    // degree than its own fixed column.
+    assert!(gate_degree(&compressed, "hot") > 2);
+    assert_eq!(gate_degree(&vk, "hot"), 2);
+    assert_eq!(
+        vk.cs().num_fixed_columns(),
+        compressed.cs().num_fixed_columns() + 1
+    );
+    assert_ne!(vk.transcript_repr(), compressed.transcript_repr());
+
+    // `q_hot` is the first selector, and `q_sum` the second.
+    let map = compressed.selector_compression_map();
+    let hot = map.iter().next().unwrap();
+    assert_eq!(hot.shared_with, vec![1]);
+    assert!(!compressed.cs().is_uncompressed_selector(q_hot::<false>()));
+    let map = vk.selector_compression_map();
+    let hot = map.iter().next().unwrap();
+    assert!(hot.shared_with.is_empty());
+    assert_eq!(hot.expression.degree(), 1);
+    assert_eq!(hot.activations, vec![0]);
+    assert_eq!(hot.multiplier, Some(Fr::from(1)));
+    assert!(map
+        .iter()
+        .skip(1)
+        .all(|selector| selector.column != hot.column));
+    assert!(vk.cs().is_uncompressed_selector(q_hot::<true>()));
+
+    let pk = keygen_pk(&params, vk, &Selectors::<true>).expect("keygen_pk should not fail");
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[Selectors::<true>],
+        &[&[]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        pk.get_vk(),
+        SingleStrategy::new(&params),
+        &[&[]],
+        &mut transcript,
+    )
+    .is_ok());
+}
@@ -0,0 +1,179 @@
+//! Compares the verifier cost counted by `verifier_cost` with the proofs and
+//! final MSMs of actual verifications.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::cost_model::{verifier_cost, EvmGasEstimate};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Instance, Selector, TableColumn,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverGWC, ProverSHPLONK, VerifierGWC, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::DeferredStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, PointEncoding, TranscriptReadBuffer,
+    TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    table: TableColumn,
+    instance: Column<Instance>,
+}
+
+/// Squares a few small values and exposes the squares.
+#[derive(Clone, Copy)]
+struct MyCircuit;
+
+const ROWS: usize = 3;
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.complex_selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let table = meta.lookup_table_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(b);
+        meta.enable_equality(instance);
+
+        meta.create_gate("b = a^2", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (b - a.clone() * a)]
+        });
+        meta.lookup("a is small", |meta| {
+            let q = meta.query_selector(q);
+            vec![(q * meta.query_advice(a, Rotation::cur()), table)]
+        });
+
+        MyConfig {
+            q,
+            a,
+            b,
+            table,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "small",
+            |mut table| {
+                for i in 0..8 {
+                    table.assign_cell(
+                        || "value",
+                        config.table,
+                        i,
+                        || Value::known(Fr::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        let squares = layouter.assign_region(
+            || "squares",
+            |mut region| {
+                (0..ROWS)
+                    .map(|row| {
+                        let a = Fr::from(row as u64 + 1);
+                        config.q.enable(&mut region, row)?;
+                        region.assign_advice(|| "a", config.a, row, || Value::known(a))?;
+                        region.assign_advice(|| "b", config.b, row, || Value::known(a * a))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        for (row, square) in squares.iter().enumerate() {
+            layouter.constrain_instance(square.cell(), config.instance, row)?;
+        }
+        Ok(())
+    }
+}
+
+fn instance() -> Vec<Fr> {
+    (0..ROWS)
+        .map(|row| Fr::from(((row + 1) * (row + 1)) as u64))
+        .collect()
+}
+
+macro_rules! check {
+    ($prover:ident, $verifier:ident) => {{
+        let params: ParamsKZG<Bn256> = ParamsKZG::setup(5, OsRng);
+        let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+        let vk = pk.get_vk();
+
+        let instance = instance();
+        let instances: &[&[&[Fr]]] = &[&[&instance[..]], &[&instance[..]]];
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<_>, $prover<_>, _, _, _, _>(
+            &params,
+            &pk,
+            &[MyCircuit, MyCircuit],
+            instances,
+            OsRng,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        let msm = verify_proof::<_, $verifier<_>, _, _, _>(
+            params.verifier_params(),
+            vk,
+            DeferredStrategy::new(&params),
+            instances,
+            &mut transcript,
+        )
+        .expect("everything but the pairing should be checked");
+        assert!(msm.check(&params));
+
+        let cost = verifier_cost::<_, $verifier<_>>(&params, vk, 2, &[ROWS]);
+        assert_eq!(cost.proofs, 2);
+        assert_eq!(cost.instance_values, 2 * ROWS);
+        assert_eq!(cost.msm_terms, msm.left().len() + msm.right().len() + 1);
+        assert_eq!(
+            cost.proof_len(PointEncoding::Compressed.len::<G1Affine>()),
+            proof.len()
+        );
+        assert_eq!(
+            cost.calldata_len(PointEncoding::Uncompressed.len::<G1Affine>()),
+            proof.len() + 32 * cost.proof_points + 32 * 2 * ROWS
+        );
+        let gas = EvmGasEstimate::new(&cost);
+        assert!(gas.total() > gas.pairing + gas.msm);
+        cost
+    }};
+}
+
+#[test]
+fn verifier_cost_matches_verification() {
+    let shplonk = check!(ProverSHPLONK, VerifierSHPLONK);
+    let gwc = check!(ProverGWC, VerifierGWC);
+
+    // The proofs only differ in their multiopen arguments.
+    assert_eq!(shplonk.proof_scalars, gwc.proof_scalars);
+    assert_eq!(shplonk.pairings, gwc.pairings);
+}
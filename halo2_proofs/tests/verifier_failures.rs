@@ -0,0 +1,257 @@
+//! Corrupts parts of a proof and checks that `verify_proof_detailed` reports
+//! the component that was corrupted.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, verify_proof_detailed, verify_proof_exact,
+    Advice, Circuit, Column, ConstraintSystem, Error, Instance, ProofComponentKind, ProofLayout,
+    Selector, VerificationFailure,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptRead, TranscriptReadBuffer,
+    TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Squares a value and exposes its square.
+#[derive(Clone, Copy)]
+struct MyCircuit;
+
+impl Circuit<Fr> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(b);
+        meta.enable_equality(instance);
+
+        meta.create_gate("b = a^2", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (b - a.clone() * a)]
+        });
+
+        MyConfig { q, a, b, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let b = layouter.assign_region(
+            || "square",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || Value::known(Fr::from(3)))?;
+                region.assign_advice(|| "b", config.b, 0, || Value::known(Fr::from(9)))
+            },
+        )?;
+        layouter.constrain_instance(b.cell(), config.instance, 0)
+    }
+}
+
+#[test]
+fn verifier_failures() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let instance = [Fr::from(9)];
+    let instances: &[&[&[Fr]]] = &[&[&instance[..]]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |proof: &[u8], instances: &[&[&[Fr]]]| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verify_proof_detailed::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            vk,
+            SingleStrategy::new(&params),
+            instances,
+            &mut transcript,
+        )
+    };
+    let verify_bool = |proof: &[u8]| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+            params.verifier_params(),
+            vk,
+            SingleStrategy::new(&params),
+            instances,
+            &mut transcript,
+        )
+        .is_ok()
+    };
+    let assert_read = |result: Result<_, VerificationFailure>, kind, index, at| match result {
+        Err(VerificationFailure::Read {
+            component,
+            index: read_index,
+            position,
+            ..
+        }) => assert_eq!((component, read_index, position), (kind, index, at)),
+        other => panic!("expected a read failure, got {:?}", other.err()),
+    };
+    assert!(verify(&proof, instances).is_ok());
+
+    let layout = ProofLayout::parse::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<_>>(vk, 1);
+    let component = |kind| {
+        *layout
+            .components()
+            .iter()
+            .find(|component| component.kind == kind)
+            .unwrap()
+    };
+    let advice = component(ProofComponentKind::Advice { phase: 0 });
+    let evaluations = component(ProofComponentKind::Evaluations);
+    let scalar_len = evaluations.len / evaluations.count;
+
+    // An invalid encoding of the commitment to the second advice column
+    let mut bad = proof.clone();
+    let point_len = advice.len / advice.count;
+    let offset = advice.offset + point_len;
+    bad[offset..offset + point_len].fill(0xff);
+    assert_read(
+        verify(&bad, instances),
+        ProofComponentKind::Advice { phase: 0 },
+        0,
+        1,
+    );
+    assert!(!verify_bool(&bad));
+
+    // A non-canonical encoding of the third evaluation
+    let mut bad = proof.clone();
+    let offset = evaluations.offset + 2 * scalar_len;
+    bad[offset..offset + scalar_len].fill(0xff);
+    assert_read(
+        verify(&bad, instances),
+        ProofComponentKind::Evaluations,
+        0,
+        2,
+    );
+    assert!(!verify_bool(&bad));
+
+    // A proof that ends before the multiopen argument
+    assert_read(
+        verify(&proof[..layout.end()], instances),
+        ProofComponentKind::Multiopen,
+        0,
+        0,
+    );
+
+    // A valid encoding of another evaluation, which the multiopen argument
+    // rejects
+    let mut bad = proof.clone();
+    bad[evaluations.offset + 2 * scalar_len] ^= 1;
+    assert!(matches!(
+        verify(&bad, instances),
+        Err(VerificationFailure::Opening)
+    ));
+    assert!(!verify_bool(&bad));
+
+    // Another value for the instance
+    let other = [Fr::from(10)];
+    assert!(matches!(
+        verify(&proof, &[&[&other[..]]]),
+        Err(VerificationFailure::Opening)
+    ));
+
+    // Too many instance columns
+    assert!(matches!(
+        verify(&proof, &[&[&instance[..], &instance[..]]]),
+        Err(VerificationFailure::Input(Error::InvalidInstances))
+    ));
+}
+
+#[test]
+fn trailing_bytes() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let vk = keygen_vk(&params, &MyCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit).expect("keygen_pk should not fail");
+    let vk = pk.get_vk();
+
+    let instance = [Fr::from(9)];
+    let instances: &[&[&[Fr]]] = &[&[&instance[..]]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[MyCircuit],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |proof: &[u8]| {
+        verify_proof_exact::<_, VerifierSHPLONK<_>, _, Blake2bRead<_, _, Challenge255<_>>, _>(
+            params.verifier_params(),
+            vk,
+            SingleStrategy::new(&params),
+            instances,
+            proof,
+        )
+    };
+    assert!(verify(&proof).is_ok());
+
+    // The verifier reads the whole proof, and nothing more.
+    let mut extended = proof.clone();
+    extended.push(0);
+    let mut remaining = &extended[..];
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&mut remaining);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        vk,
+        SingleStrategy::new(&params),
+        instances,
+        &mut transcript,
+    )
+    .is_ok());
+    drop(transcript);
+    assert_eq!(remaining, &[0]);
+
+    assert!(matches!(verify(&extended), Err(Error::TrailingBytes(1))));
+    let twice = [&proof[..], &proof[..]].concat();
+    assert!(matches!(
+        verify(&twice),
+        Err(Error::TrailingBytes(len)) if len == proof.len()
+    ));
+}
@@ -0,0 +1,281 @@
+//! Checks that the pinned representation of verifying keys of circuits that
+//! opt into `PinnedVersion::V1`, and thus their transcript representation,
+//! only depends on what the proof system does.
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::plonk::{
+    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column, ConstraintSystem,
+    Error, Fixed, Instance, PinnedVersion, Selector, TableColumn, VerifyingKey,
+};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Copy)]
+struct SquareConfig {
+    q: Column<Fixed>,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Exposes the square of a witness. The `REFACTORED` variant names its gate,
+/// constraint and region differently, queries the columns in another order
+/// and enables equality after creating the gate.
+#[derive(Clone, Copy)]
+struct SquareCircuit<const REFACTORED: bool>(Value<Fr>);
+
+impl<const REFACTORED: bool> Circuit<Fr> for SquareCircuit<REFACTORED> {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self(Value::unknown())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        meta.set_pinned_version(PinnedVersion::V1);
+        let q = meta.fixed_column();
+        let a = meta.advice_column();
+        let b = meta.advice_column();
+        let instance = meta.instance_column();
+
+        if REFACTORED {
+            meta.create_gate("b is the square of a", |meta| {
+                let b = meta.query_advice(b, Rotation::next());
+                let a = meta.query_advice(a, Rotation::cur());
+                let q = meta.query_fixed(q, Rotation::cur());
+                vec![("square", q * (a.clone() * a - b))]
+            });
+            meta.enable_equality(b);
+            meta.enable_equality(instance);
+        } else {
+            meta.enable_equality(b);
+            meta.enable_equality(instance);
+            meta.create_gate("square", |meta| {
+                let q = meta.query_fixed(q, Rotation::cur());
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::next());
+                vec![q * (a.clone() * a - b)]
+            });
+        }
+
+        SquareConfig { q, a, b, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let square = layouter.assign_region(
+            || if REFACTORED { "witness" } else { "square" },
+            |mut region| {
+                region.assign_fixed(|| "q", config.q, 0, || Value::known(Fr::from(1)))?;
+                region.assign_advice(|| "a", config.a, 0, || self.0)?;
+                region.assign_advice(|| "b", config.b, 1, || self.0.map(|a| a * a))
+            },
+        )?;
+        layouter.constrain_instance(square.cell(), config.instance, 0)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RangeConfig {
+    s: Selector,
+    a: Column<Advice>,
+    table: TableColumn,
+}
+
+/// Looks up the difference of consecutive values of a column in a table.
+#[derive(Clone, Copy)]
+struct RangeCircuit;
+
+impl Circuit<Fr> for RangeCircuit {
+    type Config = RangeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    #[cfg(feature = "circuit-params")]
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        meta.set_pinned_version(PinnedVersion::V1);
+        let s = meta.complex_selector();
+        let a = meta.advice_column();
+        let table = meta.lookup_table_column();
+
+        meta.lookup("difference in range", |meta| {
+            let s = meta.query_selector(s);
+            let cur = meta.query_advice(a, Rotation::cur());
+            let prev = meta.query_advice(a, Rotation::prev());
+            vec![(s * (cur - prev), table)]
+        });
+
+        RangeConfig { s, a, table }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range",
+            |mut table| {
+                for i in 0..8u64 {
+                    let value = Value::known(Fr::from(i));
+                    table.assign_cell(|| "value", config.table, i as usize, || value)?;
+                }
+                Ok(())
+            },
+        )?;
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                config.s.enable(&mut region, 1)?;
+                for (row, value) in [2u64, 5].into_iter().enumerate() {
+                    region.assign_advice(
+                        || "a",
+                        config.a,
+                        row,
+                        || Value::known(Fr::from(value)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Checks the version of the pinned representation of `vk`, and that its
+/// constraint system is pinned as `expected`.
+fn check_pinned(vk: &VerifyingKey<G1Affine>, expected: &str) {
+    assert_eq!(vk.cs().pinned_version(), PinnedVersion::V1);
+    assert!(format!("{:?}", vk.pinned()).starts_with("PinnedVerificationKey { version: 1, "));
+    assert_eq!(format!("{:?}", vk.cs().pinned()), expected);
+}
+
+#[test]
+fn pinned_constraint_systems() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+
+    let vk = keygen_vk(&params, &SquareCircuit::<false>(Value::unknown()))
+        .expect("keygen_vk should not fail");
+    check_pinned(
+        &vk,
+        concat!(
+            "PinnedConstraintSystem { num_fixed_columns: 1, num_advice_columns: 2, ",
+            "num_instance_columns: 1, gates: [Product(Fixed { query_index: 0, ",
+            "column_index: 0, rotation: Rotation(0) }, Sum(Product(Advice { query_index: 0, ",
+            "column_index: 0, rotation: Rotation(0) }, Advice { query_index: 0, ",
+            "column_index: 0, rotation: Rotation(0) }), Negated(Advice { query_index: 2, ",
+            "column_index: 1, rotation: Rotation(1) })))], advice_queries: [(Column { ",
+            "index: 0, column_type: Advice }, Rotation(0)), (Column { index: 1, ",
+            "column_type: Advice }, Rotation(0)), (Column { index: 1, column_type: Advice }, ",
+            "Rotation(1))], instance_queries: [(Column { index: 0, column_type: Instance }, ",
+            "Rotation(0))], fixed_queries: [(Column { index: 0, column_type: Fixed }, ",
+            "Rotation(0))], permutation: Argument { columns: [Column { index: 1, ",
+            "column_type: Advice }, Column { index: 0, column_type: Instance }] }, ",
+            "lookups: [], minimum_degree: None }",
+        ),
+    );
+
+    let vk = keygen_vk(&params, &RangeCircuit).expect("keygen_vk should not fail");
+    check_pinned(
+        &vk,
+        concat!(
+            "PinnedConstraintSystem { num_fixed_columns: 2, num_advice_columns: 1, ",
+            "num_instance_columns: 0, gates: [], advice_queries: [(Column { index: 0, ",
+            "column_type: Advice }, Rotation(-1)), (Column { index: 0, column_type: Advice }, ",
+            "Rotation(0))], instance_queries: [], fixed_queries: [(Column { index: 0, ",
+            "column_type: Fixed }, Rotation(0)), (Column { index: 1, column_type: Fixed }, ",
+            "Rotation(0))], permutation: Argument { columns: [] }, lookups: [Argument { ",
+            "input_expressions: [Product(Fixed { query_index: 1, column_index: 1, ",
+            "rotation: Rotation(0) }, Sum(Advice { query_index: 1, column_index: 0, ",
+            "rotation: Rotation(0) }, Negated(Advice { query_index: 0, column_index: 0, ",
+            "rotation: Rotation(-1) })))], table_expressions: [Fixed { query_index: 0, ",
+            "column_index: 0, rotation: Rotation(0) }] }], minimum_degree: None }",
+        ),
+    );
+}
+
+#[test]
+fn legacy_pinned_version() {
+    let pinned = |version| {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        SquareCircuit::<false>::configure(&mut meta);
+        meta.set_pinned_version(version);
+        let (meta, _) = meta.compress_selectors(vec![]);
+        format!("{:?}", meta.pinned())
+    };
+
+    // The queries stay in the order `configure` made them in, and the number
+    // of selectors and the constants are pinned.
+    let legacy = pinned(PinnedVersion::V0);
+    assert!(legacy.contains(concat!(
+        "num_instance_columns: 1, num_selectors: 0, gates: [Product(Fixed { query_index: 0, ",
+        "column_index: 0, rotation: Rotation(0) }, Sum(Product(Advice { query_index: 1, "
+    )));
+    assert!(legacy.contains(concat!(
+        "advice_queries: [(Column { index: 1, column_type: Advice }, Rotation(0)), ",
+        "(Column { index: 0, column_type: Advice }, Rotation(0)), "
+    )));
+    assert!(legacy.contains("constants: [], minimum_degree: None"));
+    assert!(!pinned(PinnedVersion::V1).contains("num_selectors"));
+}
+
+#[test]
+fn refactored_configure_keeps_transcript_repr() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(K, OsRng);
+    let circuit = SquareCircuit::<false>(Value::known(Fr::from(3)));
+    let refactored = SquareCircuit::<true>(Value::known(Fr::from(3)));
+
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let refactored_vk = keygen_vk(&params, &refactored).expect("keygen_vk should not fail");
+    assert_eq!(
+        format!("{:?}", vk.pinned()),
+        format!("{:?}", refactored_vk.pinned())
+    );
+    assert_eq!(vk.transcript_repr(), refactored_vk.transcript_repr());
+    assert_eq!(vk.fingerprint(), refactored_vk.fingerprint());
+
+    // The keys describe the same proof system, so a proof made with one
+    // verifies with the other.
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let instance = [Fr::from(9)];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverSHPLONK<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&instance[..]]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof::<_, VerifierSHPLONK<_>, _, _, _>(
+        params.verifier_params(),
+        &refactored_vk,
+        SingleStrategy::new(&params),
+        &[&[&instance[..]]],
+        &mut transcript,
+    )
+    .is_ok());
+}